@@ -0,0 +1,84 @@
+# German messages for the shabka CLI and TUI. Keep in step with
+# locales/en/cli.ftl.
+
+## Shared
+
+dry-run = Probelauf — nichts wurde geändert.
+no-memories = Keine Erinnerungen gefunden.
+no-results = Keine Ergebnisse gefunden.
+memory-count =
+    { $count ->
+        [one] { $count } Erinnerung
+       *[other] { $count } Erinnerungen
+    }
+
+## Table columns
+
+column-id = ID
+column-kind = Art
+column-score = Wert
+column-created = Erstellt
+column-title = Titel
+column-importance = Gew.
+column-status = Status
+
+## Review, quarantine and the inbox
+
+approved-memory = Erinnerung { $id } freigegeben
+approved-pending = { $count } ausstehende Erinnerungen freigegeben.
+rejected-memory = Erinnerung { $id } abgelehnt (archiviert)
+released-memory = Erinnerung { $id } aus der Quarantäne entlassen
+deleted-quarantined = Erinnerung { $id } aus der Quarantäne gelöscht
+no-pending = Keine Erinnerungen warten auf Prüfung.
+no-quarantined = Keine Erinnerungen in Quarantäne.
+inbox-empty = Posteingang leer — nichts braucht deine Aufmerksamkeit.
+
+## Other commands
+
+chain-from = Kette ab: { $title } ({ $id })
+digest-sent = Zusammenfassung an { $target } gesendet
+pushed-to = Nach { $branch } übertragen
+revoked-token = Token { $name } widerrufen
+deleted-snapshot = Snapshot { $name } gelöscht
+
+## TUI
+
+tui-loading = Wird geladen...
+tui-empty = Keine Erinnerungen gefunden. / zum Suchen, r zum Aktualisieren.
+tui-results = Ergebnisse für "{ $query }" ({ $count })
+tui-archived = Archivierte Erinnerungen ({ $count }) — u zum Wiederherstellen, a zurück
+tui-memories = Erinnerungen ({ $count })
+tui-status = Systemstatus
+tui-kind-breakdown = Nach Art
+tui-no-memories-yet = Noch keine Erinnerungen.
+tui-storage = Speicher
+tui-provider = Anbieter
+tui-memories-label = Einträge
+tui-kinds = Arten
+tui-inbox = Eingang
+
+## TUI key hints
+
+key-navigate = bewegen
+key-open = öffnen
+key-search = suchen
+key-similar = ähnliche
+key-filter = filtern
+key-new = neu
+key-archived = Archiv
+key-status = Status
+key-refresh = aktualisieren
+key-quit = beenden
+key-cancel = abbrechen
+key-cycle-kind = Art wechseln
+key-confirm = bestätigen
+key-scroll = blättern
+key-page = Seite
+key-edit = bearbeiten
+key-sources = Quellen
+key-unarchive = wiederherstellen
+key-back = zurück
+key-next-field = nächstes Feld
+key-prev-field = voriges Feld
+key-save = speichern
+key-back-to-list = zurück zur Liste
@@ -0,0 +1,85 @@
+# Messages shown by the shabka CLI and TUI. Every message here must also
+# appear in each translation under locales/<language>/cli.ftl; a message a
+# translation lacks is shown in English.
+
+## Shared
+
+dry-run = Dry run — no changes made.
+no-memories = No memories found.
+no-results = No results found.
+memory-count =
+    { $count ->
+        [one] { $count } memory
+       *[other] { $count } memories
+    }
+
+## Table columns
+
+column-id = ID
+column-kind = Kind
+column-score = Score
+column-created = Created
+column-title = Title
+column-importance = Imp
+column-status = Status
+
+## Review, quarantine and the inbox
+
+approved-memory = Approved memory { $id }
+approved-pending = Approved { $count } pending memories.
+rejected-memory = Rejected (archived) memory { $id }
+released-memory = Released memory { $id }
+deleted-quarantined = Deleted quarantined memory { $id }
+no-pending = No pending memories to review.
+no-quarantined = No quarantined memories.
+inbox-empty = Inbox empty — nothing needs your attention.
+
+## Other commands
+
+chain-from = Chain from: { $title } ({ $id })
+digest-sent = digest sent to { $target }
+pushed-to = Pushed to { $branch }
+revoked-token = Revoked token { $name }
+deleted-snapshot = Deleted snapshot { $name }
+
+## TUI
+
+tui-loading = Loading...
+tui-empty = No memories found. Press / to search or r to refresh.
+tui-results = Results for "{ $query }" ({ $count })
+tui-archived = Archived memories ({ $count }) — u to restore, a to go back
+tui-memories = Memories ({ $count })
+tui-status = System Status
+tui-kind-breakdown = Kind Breakdown
+tui-no-memories-yet = No memories yet.
+tui-storage = Storage
+tui-provider = Provider
+tui-memories-label = Memories
+tui-kinds = Kinds
+tui-inbox = Inbox
+
+## TUI key hints
+
+key-navigate = navigate
+key-open = open
+key-search = search
+key-similar = similar
+key-filter = filter
+key-new = new
+key-archived = archived
+key-status = status
+key-refresh = refresh
+key-quit = quit
+key-cancel = cancel
+key-cycle-kind = cycle kind
+key-confirm = confirm
+key-scroll = scroll
+key-page = page
+key-edit = edit
+key-sources = sources
+key-unarchive = unarchive
+key-back = back
+key-next-field = next field
+key-prev-field = prev field
+key-save = save
+key-back-to-list = back to list
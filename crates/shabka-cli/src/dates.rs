@@ -0,0 +1,102 @@
+//! How the CLI and TUI show timestamps.
+//!
+//! Times are stored in UTC and shown in the local timezone, or in UTC with
+//! `--utc` or `[display] utc`. Lists, search results and the TUI show when a
+//! memory was created as "3d ago" unless `[display] relative_times = false`
+//! or `--stable-output` asks for run-to-run identical text.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::{DateTime, Local, Utc};
+
+use crate::output;
+
+static UTC: AtomicBool = AtomicBool::new(false);
+static RELATIVE: AtomicBool = AtomicBool::new(true);
+
+/// Set the timezone and relative-time preferences for the whole process.
+pub fn configure(utc: bool, relative: bool) {
+    UTC.store(utc, Ordering::Relaxed);
+    RELATIVE.store(relative, Ordering::Relaxed);
+}
+
+fn format(at: DateTime<Utc>, fmt: &str) -> String {
+    if UTC.load(Ordering::Relaxed) {
+        at.format(fmt).to_string()
+    } else {
+        at.with_timezone(&Local).format(fmt).to_string()
+    }
+}
+
+/// `2026-10-16 09:12:03`
+pub fn datetime(at: DateTime<Utc>) -> String {
+    format(at, "%Y-%m-%d %H:%M:%S")
+}
+
+/// `2026-10-16 09:12`
+pub fn minutes(at: DateTime<Utc>) -> String {
+    format(at, "%Y-%m-%d %H:%M")
+}
+
+/// `2026-10-16`
+pub fn date(at: DateTime<Utc>) -> String {
+    format(at, "%Y-%m-%d")
+}
+
+/// `3d ago` when relative times are on, otherwise the date.
+pub fn when(at: DateTime<Utc>) -> String {
+    if RELATIVE.load(Ordering::Relaxed) && !output::stable() {
+        ago(at, Utc::now())
+    } else {
+        date(at)
+    }
+}
+
+/// The full time followed by ` (3d ago)` when relative times are on.
+pub fn datetime_ago(at: DateTime<Utc>) -> String {
+    if RELATIVE.load(Ordering::Relaxed) && !output::stable() {
+        format!("{} ({})", datetime(at), ago(at, Utc::now()))
+    } else {
+        datetime(at)
+    }
+}
+
+/// How long before `now` `at` was, in its largest whole unit: `just now`,
+/// `5m ago`, `3h ago`, `3d ago`, `4mo ago` or `2y ago`. Times in the future
+/// (clock skew between devices) count as just now.
+pub fn ago(at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let elapsed = now - at;
+    let minutes = elapsed.num_minutes();
+    let days = elapsed.num_days();
+    if minutes < 1 {
+        "just now".to_string()
+    } else if minutes < 60 {
+        format!("{minutes}m ago")
+    } else if days < 1 {
+        format!("{}h ago", elapsed.num_hours())
+    } else if days < 30 {
+        format!("{days}d ago")
+    } else if days < 365 {
+        format!("{}mo ago", days / 30)
+    } else {
+        format!("{}y ago", days / 365)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_ago() {
+        let now = Utc::now();
+        assert_eq!(ago(now, now), "just now");
+        assert_eq!(ago(now + Duration::hours(2), now), "just now");
+        assert_eq!(ago(now - Duration::minutes(5), now), "5m ago");
+        assert_eq!(ago(now - Duration::hours(3), now), "3h ago");
+        assert_eq!(ago(now - Duration::days(3), now), "3d ago");
+        assert_eq!(ago(now - Duration::days(125), now), "4mo ago");
+        assert_eq!(ago(now - Duration::days(800), now), "2y ago");
+    }
+}
@@ -0,0 +1,158 @@
+//! Translated CLI and TUI messages.
+//!
+//! Messages are Fluent files under `locales/<language>/cli.ftl`, built into
+//! the binary. The language is `[output] language`, or else the first of
+//! `LC_ALL`, `LC_MESSAGES` and `LANG` that is set; a language without a
+//! translation, or a message a translation lacks, is shown in English.
+//! Look messages up with [`t!`]: `t!("inbox-empty")` or
+//! `t!("approved-memory", id = short_id)`.
+
+use std::sync::OnceLock;
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::FluentResource;
+use unic_langid::LanguageIdentifier;
+
+pub use fluent_bundle::FluentArgs;
+
+/// Translations by primary language subtag, English first.
+const LOCALES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en/cli.ftl")),
+    ("de", include_str!("../locales/de/cli.ftl")),
+];
+
+type Bundle = FluentBundle<FluentResource>;
+
+static ENGLISH: OnceLock<Bundle> = OnceLock::new();
+static SELECTED: OnceLock<Option<Bundle>> = OnceLock::new();
+
+/// Pick the message language for the whole process: `language` is
+/// `[output] language`, falling back to the locale environment variables.
+pub fn configure(language: Option<&str>) {
+    let tag = language.map(str::to_string).or_else(env_language);
+    let selected = tag
+        .as_deref()
+        .and_then(locale)
+        .filter(|(code, _)| *code != LOCALES[0].0)
+        .map(|(code, source)| bundle(code, source));
+    let _ = SELECTED.set(selected);
+}
+
+/// Message `id` in the configured language, with `args` filled in and
+/// glyphs such as dashes in ASCII under `--plain`. An unknown id comes back
+/// as is, so a typo shows up rather than vanishing.
+pub fn message(id: &str, args: Option<&FluentArgs>) -> String {
+    let english = ENGLISH.get_or_init(|| bundle(LOCALES[0].0, LOCALES[0].1));
+    let text = SELECTED
+        .get()
+        .and_then(Option::as_ref)
+        .and_then(|selected| format(selected, id, args))
+        .or_else(|| format(english, id, args))
+        .unwrap_or_else(|| id.to_string());
+    crate::style::plain_text(text)
+}
+
+/// Look up a translated message, with optional `name = value` arguments.
+macro_rules! t {
+    ($id:literal) => {
+        $crate::i18n::message($id, None)
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = $crate::i18n::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $crate::i18n::message($id, Some(&args))
+    }};
+}
+pub(crate) use t;
+
+fn env_language() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// The translation for a language tag or POSIX locale such as `de`,
+/// `de-AT` or `de_DE.UTF-8`.
+fn locale(tag: &str) -> Option<(&'static str, &'static str)> {
+    let primary = tag.split(['-', '_', '.', '@']).next()?.to_ascii_lowercase();
+    LOCALES.iter().copied().find(|(code, _)| *code == primary)
+}
+
+fn bundle(code: &str, source: &str) -> Bundle {
+    let language: LanguageIdentifier = code.parse().unwrap_or_default();
+    let mut bundle = FluentBundle::new_concurrent(vec![language]);
+    // Terminals print the Unicode isolation marks around arguments.
+    bundle.set_use_isolating(false);
+    let resource =
+        FluentResource::try_new(source.to_string()).unwrap_or_else(|(resource, _errors)| resource);
+    if let Err(errors) = bundle.add_resource(resource) {
+        tracing::debug!("i18n: {code} messages: {errors:?}");
+    }
+    bundle
+}
+
+fn format(bundle: &Bundle, id: &str, args: Option<&FluentArgs>) -> Option<String> {
+    let pattern = bundle.get_message(id)?.value()?;
+    let mut errors = Vec::new();
+    let text = bundle.format_pattern(pattern, args, &mut errors);
+    if !errors.is_empty() {
+        tracing::debug!("i18n: {id}: {errors:?}");
+    }
+    Some(text.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    fn ids(source: &str) -> BTreeSet<&str> {
+        source
+            .lines()
+            .filter(|line| line.starts_with(|c: char| c.is_ascii_alphabetic()))
+            .filter_map(|line| line.split_once(" =").map(|(id, _)| id))
+            .collect()
+    }
+
+    #[test]
+    fn test_translations_parse_and_match_english() {
+        let english = ids(LOCALES[0].1);
+        for (code, source) in LOCALES {
+            assert!(
+                FluentResource::try_new(source.to_string()).is_ok(),
+                "{code} messages don't parse"
+            );
+            assert_eq!(ids(source), english, "{code} messages differ from English");
+        }
+    }
+
+    #[test]
+    fn test_format_with_arguments() {
+        let (code, source) = locale("de_DE.UTF-8").unwrap();
+        let german = bundle(code, source);
+        let mut args = FluentArgs::new();
+        args.set("count", 1);
+        assert_eq!(
+            format(&german, "memory-count", Some(&args)).unwrap(),
+            "1 Erinnerung"
+        );
+        args.set("count", 3);
+        assert_eq!(
+            format(&german, "memory-count", Some(&args)).unwrap(),
+            "3 Erinnerungen"
+        );
+        assert!(locale("C").is_none());
+        assert!(locale("fr_FR").is_none());
+    }
+
+    #[test]
+    fn test_english_fallback() {
+        assert_eq!(
+            t!("approved-memory", id = "0190abcd"),
+            "Approved memory 0190abcd"
+        );
+        assert_eq!(message("no-such-message", None), "no-such-message");
+    }
+}
@@ -1,41 +1,134 @@
+mod dates;
+mod i18n;
+mod output;
+mod progress;
+mod style;
+mod telemetry;
 mod tui;
 
 use std::collections::HashMap;
-use std::path::Path;
+use std::sync::Arc;
 
 use anyhow::{Context, Result};
-use clap::Parser;
-use owo_colors::OwoColorize;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use shabka_core::api_tokens::{self, TokenScope};
 use shabka_core::assess::{self, AssessConfig, AssessmentResult, IssueCounts};
+use shabka_core::attachments::{self, AttachmentConfig, BlobStore, ExportedBlob};
+use shabka_core::audit::{AuditEntry, AuditFilter, AuditLog};
+use shabka_core::centrality::Centrality;
+use shabka_core::citations;
+use shabka_core::codebase::{Codebase, MissingReferents};
 use shabka_core::config::{
-    self, EmbeddingState, GraphConfig, ShabkaConfig, UpdateCheckState, VALID_PROVIDERS,
+    self, EmbeddingConfig, EmbeddingState, GraphConfig, ShabkaConfig, UpdateCheckState,
+    VALID_PROVIDERS,
 };
+use shabka_core::context_pack::PackFilter;
 use shabka_core::decay::{self, PruneConfig, PruneResult};
+use shabka_core::devices::{self, DeviceRegistry};
+use shabka_core::digest;
+use shabka_core::embedding::batch::{OpenAiBatchClient, PendingBatch};
 use shabka_core::embedding::EmbeddingService;
+use shabka_core::error::ShabkaError;
+use shabka_core::gaps;
 use shabka_core::graph;
-use shabka_core::history::{EventAction, HistoryLogger, MemoryEvent};
+use shabka_core::history::{self, EventAction, HistoryFilter, HistoryLogger, MemoryEvent};
+use shabka_core::inbox;
+use shabka_core::journal::{self, CaptureJournal};
+use shabka_core::kaizen;
+use shabka_core::labels::{Boundary, LabelConfig};
+use shabka_core::llm_budget::{LlmFeature, ParseStats, TokenLedger};
+use shabka_core::maintenance::{MaintenanceOp, MaintenanceRun};
 use shabka_core::model::*;
-use shabka_core::ranking::{self, RankCandidate, RankingWeights};
+use shabka_core::query::SearchQuery;
+use shabka_core::questions::{OpenQuestion, Question, QuestionLog};
+use shabka_core::ranking;
+use shabka_core::retrieval::{self, SearchRequest, SearchResults, DEFAULT_SEARCH_LIMIT};
+use shabka_core::screen;
+use shabka_core::services::Services;
 use shabka_core::sharing;
-use shabka_core::storage::{create_backend, Storage, StorageBackend};
+use shabka_core::snapshot::{self, SnapshotStore};
+use shabka_core::storage::{create_backend, sqlite_path, HelixStorage, Storage, StorageBackend};
+use shabka_core::sync::{self, SyncRepo};
+use shabka_core::telemetry::{TelemetryEvent, TelemetryLog};
+use shabka_core::tenants;
 use uuid::Uuid;
 
+use i18n::t;
+use progress::{Checkpoint, Interrupt};
+use style::Paint;
+
 #[derive(Parser)]
 #[command(name = "shabka", about = "Shabka: Shared LLM Memory System", version)]
-enum Cli {
+struct Cli {
+    /// Project namespace (default: the project pinned with `shabka project use`)
+    #[arg(short, long, global = true)]
+    project: Option<String>,
+    /// Don't draw progress bars (for scripts and logs)
+    #[arg(long, global = true)]
+    no_progress: bool,
+    /// Leave out dates, versions and other run-to-run noise, and order
+    /// records deterministically, so output can be diffed or snapshotted
+    #[arg(long, global = true)]
+    stable_output: bool,
+    /// Show times in UTC instead of the local timezone
+    #[arg(long, global = true)]
+    utc: bool,
+    /// ASCII-only output without colors or progress bars, for screen
+    /// readers and scripts (also `SHABKA_PLAIN=1`; `NO_COLOR` drops colors only)
+    #[arg(long, global = true)]
+    plain: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
     /// Initialize Shabka in the current project
     Init {
-        /// Embedding provider to configure (hash, ollama, openai, gemini)
+        /// Embedding provider to configure (hash, ollama, openai, gemini, exec)
         #[arg(long, default_value = "hash")]
         provider: String,
         /// Only run prerequisite checks, don't create config
         #[arg(long)]
         check: bool,
     },
+    /// Add a memory (content from an argument or `-` for stdin)
+    Add {
+        /// Memory title
+        #[arg(required_unless_present = "schema")]
+        title: Option<String>,
+        /// Memory content (omit or use `-` to read from stdin)
+        content: Option<String>,
+        /// Memory kind (observation, decision, pattern, error, fix, preference, fact, lesson, todo, procedure)
+        #[arg(short, long, default_value = "observation")]
+        kind: String,
+        /// Tags (can be repeated)
+        #[arg(short, long)]
+        tag: Option<Vec<String>>,
+        /// Importance between 0.0 and 1.0
+        #[arg(long, default_value = "0.5")]
+        importance: f32,
+        /// Privacy level: public, team, private (default from config)
+        #[arg(long)]
+        privacy: Option<String>,
+        /// Classification label, e.g. internal or confidential (default from [labels])
+        #[arg(long)]
+        label: Option<String>,
+        /// Output raw JSON
+        #[arg(long)]
+        json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
+    },
     /// Search memories
     Search {
-        /// Search query
-        query: String,
+        /// Search query; supports field operators (kind:error tag:auth after:2025-01-01 "exact words")
+        #[arg(required_unless_present_any = ["schema", "symbol"])]
+        query: Option<String>,
+        /// Boost memories referencing this code symbol (e.g. parse_config)
+        #[arg(long)]
+        symbol: Option<String>,
         /// Filter by memory kind (observation, decision, pattern, error, fix, preference, fact, lesson, todo, procedure)
         #[arg(short, long)]
         kind: Option<String>,
@@ -45,31 +138,56 @@ enum Cli {
         /// Filter by tags (can be repeated)
         #[arg(short, long)]
         tag: Option<Vec<String>>,
-        /// Filter by project name (derived from cwd)
-        #[arg(short, long)]
-        project: Option<String>,
         /// Output raw JSON instead of table
         #[arg(long)]
         json: bool,
+        /// Stream results as JSON Lines (one object per line)
+        #[arg(long, conflicts_with = "json")]
+        jsonl: bool,
+        /// Print nothing; exit 0 if results were found, 1 if none, 2 on error
+        #[arg(short, long)]
+        quiet: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
         /// Cap results to fit within a token budget (estimated)
         #[arg(long)]
         token_budget: Option<usize>,
+        #[command(flatten)]
+        exclude: ExcludeArgs,
     },
     /// Get a memory's full details by ID
     Get {
-        /// Memory ID (full UUID or short 8-char prefix)
-        id: String,
+        /// Memory ID (full UUID, short prefix, or title words)
+        #[arg(required_unless_present = "schema")]
+        id: Option<String>,
+        /// Also print the memories a consolidated summary was built from
+        #[arg(long)]
+        expand_sources: bool,
         /// Output raw JSON
         #[arg(long)]
         json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
     },
     /// Show system status
-    Status,
+    Status {
+        /// Show the history of prune, consolidate, reembed, backfill, check and centrality runs instead
+        #[arg(long)]
+        maintenance: bool,
+    },
     /// Export memories to JSON
     Export {
-        /// Output file path
+        /// Output file path (`-` for stdout)
         #[arg(short, long, default_value = "shabka-export.json")]
         output: String,
+        /// Write JSON Lines (one memory or relation record per line)
+        #[arg(long)]
+        jsonl: bool,
+        /// Print the JSON Schema of the export format and exit
+        #[arg(long)]
+        schema: bool,
         /// Privacy threshold: only export memories at this level or more open (public, team, private)
         #[arg(long, default_value = "private")]
         privacy: String,
@@ -79,25 +197,89 @@ enum Cli {
         /// Dry run: show what PII would be found without exporting
         #[arg(long)]
         scrub_report: bool,
+        /// Embed attachment contents (base64) in the export
+        #[arg(long)]
+        attachments: bool,
     },
-    /// Import memories from JSON
+    /// Import memories from JSON or JSON Lines
     Import {
-        /// Input file path
+        /// Input file path (`-` for stdin)
         path: String,
     },
+    /// Copy memories, relations and history from a Kaizen store
+    MigrateFromKaizen {
+        /// Kaizen SQLite database (omit to read a Kaizen HelixDB via --helix-url)
+        #[arg(required_unless_present = "helix_url")]
+        path: Option<std::path::PathBuf>,
+        /// Kaizen HelixDB URL, e.g. http://localhost
+        #[arg(long, conflicts_with = "path")]
+        helix_url: Option<String>,
+        /// Kaizen HelixDB port
+        #[arg(long, default_value = "6969")]
+        helix_port: u16,
+        /// Kaizen history file (history.jsonl) to append to Shabka's
+        #[arg(long)]
+        history: Option<std::path::PathBuf>,
+        /// Report what would be copied without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Output raw JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Follow a chain of relations from a memory (debugging narratives, version history)
     Chain {
-        /// Starting memory ID
-        id: String,
+        /// Starting memory (full UUID, short prefix, or title words)
+        #[arg(required_unless_present = "schema")]
+        id: Option<String>,
         /// Relation types to follow (caused_by, fixes, supersedes, related, contradicts)
         #[arg(short, long)]
         relation: Option<Vec<String>>,
         /// Maximum traversal depth (default from config, fallback 5)
         #[arg(long)]
         depth: Option<usize>,
+        /// Most memories to show (default from config, fallback 50)
+        #[arg(long)]
+        max_nodes: Option<usize>,
+        /// Output raw JSON
+        #[arg(long)]
+        json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
+    },
+    /// List the memories most similar to a memory (near-duplicates, related prior work)
+    Similar {
+        /// Memory to compare against (full UUID, short prefix, or title words)
+        #[arg(required_unless_present = "schema")]
+        id: Option<String>,
+        /// Maximum number of neighbours
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+        /// Output raw JSON
+        #[arg(long)]
+        json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
+    },
+    /// Resurface a random forgotten memory, favouring important ones not read in a long time
+    Random {
+        /// Only pick memories of this kind
+        #[arg(short, long)]
+        kind: Option<String>,
+        /// Print a single compact line and never fail (for shell startup files)
+        #[arg(long)]
+        motd: bool,
+        /// Show at most one memory per day (for login shells and cron jobs)
+        #[arg(long)]
+        daily: bool,
         /// Output raw JSON
         #[arg(long)]
         json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
     },
     /// Prune stale memories (archive those not accessed in N days)
     Prune {
@@ -110,34 +292,126 @@ enum Cli {
         /// Also decay importance of stale memories
         #[arg(long)]
         decay_importance: bool,
+        /// Delete memories archived more than N days ago (default from config; unset keeps them)
+        #[arg(long)]
+        delete_after: Option<u64>,
+        /// Append memories to this JSONL file before deleting them (restore with `shabka import`)
+        #[arg(long, value_name = "FILE")]
+        cold_storage: Option<String>,
     },
     /// Show audit history for a memory or recent events
     History {
-        /// Memory ID to show history for (omit for recent events)
+        /// Memory ID, prefix or title to show history for (omit for recent events)
         id: Option<String>,
         /// Maximum number of events to show
         #[arg(short, long, default_value = "20")]
         limit: usize,
+        /// Only events by this actor (user or agent)
+        #[arg(long)]
+        actor: Option<String>,
+        /// Only these actions (comma-separated: created, updated, deleted,
+        /// archived, imported, superseded)
+        #[arg(long, value_delimiter = ',')]
+        action: Vec<EventAction>,
+        /// Only events from this time on: YYYY-MM-DD, RFC 3339, or a span
+        /// back from now like 12h, 7d, 2w
+        #[arg(long)]
+        since: Option<String>,
+        /// Only events before this time (a bare date includes that day)
+        #[arg(long)]
+        until: Option<String>,
         /// Output raw JSON
         #[arg(long)]
         json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
+    },
+    /// Summarize recent activity per project (captures, merges, contradictions, prunes)
+    Digest {
+        /// Start of the period: YYYY-MM-DD, RFC 3339, or a span back from
+        /// now like 12h, 1d, 1w
+        #[arg(long, default_value = "1d")]
+        since: String,
+        /// End of the period (default: now; a bare date includes that day)
+        #[arg(long)]
+        until: Option<String>,
+        /// Deliver to the webhook and/or file configured under [digest]
+        #[arg(long)]
+        send: bool,
+        /// Output raw JSON
+        #[arg(long, conflicts_with = "send")]
+        json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
     },
     /// Assess memory quality and find issues
     Assess {
         /// Check for duplicates (slower — requires embedding comparison)
         #[arg(long)]
         duplicates: bool,
+        /// Flag memories referencing files or symbols missing from this
+        /// working tree (default: current directory)
+        #[arg(long, value_name = "DIR", num_args = 0..=1, default_missing_value = ".")]
+        codebase: Option<std::path::PathBuf>,
+        /// Ask the LLM to check the kind of auto-captured memories (uses tokens)
+        #[arg(long)]
+        classify: bool,
+        /// Change the kind of memories with a suggested kind
+        #[arg(long)]
+        fix: bool,
         /// Maximum memories to analyze (default: all)
         #[arg(short, long)]
         limit: Option<usize>,
+        /// Show the quality score month by month from earlier runs instead
+        #[arg(long, conflicts_with_all = ["duplicates", "codebase", "classify", "fix", "limit", "quiet"])]
+        trend: bool,
+        /// Output raw JSON
+        #[arg(long)]
+        json: bool,
+        /// Print nothing; exit 0 if issues were found, 1 if none, 2 on error
+        #[arg(short, long)]
+        quiet: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
+    },
+    /// Analyze similarity between memories' stored embeddings
+    Similarity {
+        #[command(subcommand)]
+        action: SimilarityAction,
+    },
+    /// Analyze the relation graph (centrality of memories)
+    Graph {
+        #[command(subcommand)]
+        action: GraphAction,
+    },
+    /// Report areas of a project with heavy editing but no captured
+    /// decisions or lessons
+    Gaps {
+        /// Project to report on (default: the current project)
+        #[arg(value_name = "PROJECT")]
+        name: Option<String>,
+        /// Edits an undocumented area needs to be reported
+        #[arg(long, default_value_t = gaps::DEFAULT_MIN_EDITS)]
+        min_edits: usize,
+        /// List searches that found nothing or only weak matches instead
+        #[arg(long)]
+        questions: bool,
         /// Output raw JSON
         #[arg(long)]
         json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
     },
     /// Run diagnostic checks on the Shabka pipeline
     Doctor,
     /// Consolidate clusters of similar memories into comprehensive summaries (requires LLM)
     Consolidate {
+        #[command(subcommand)]
+        action: Option<ConsolidateAction>,
         /// Show what would be done without making changes
         #[arg(long)]
         dry_run: bool,
@@ -150,6 +424,9 @@ enum Cli {
         /// Output raw JSON
         #[arg(long)]
         json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
     },
     /// Re-embed all memories with the current embedding provider
     Reembed {
@@ -162,14 +439,104 @@ enum Cli {
         /// Force re-embed all memories, ignoring incremental skip logic
         #[arg(long)]
         force: bool,
+        /// Submit one asynchronous job to the OpenAI Batch API (cheaper, can
+        /// take hours) and wait for it; re-run to resume an interrupted wait
+        #[arg(long, conflicts_with = "batch_size")]
+        batch_api: bool,
+    },
+    /// Auto-tag and re-classify memories captured before LLM features were on
+    Backfill {
+        /// Suggest tags and importance for memories with only capture tags
+        #[arg(long)]
+        auto_tag: bool,
+        /// Re-classify the kind of auto-captured memories
+        #[arg(long)]
+        classify_kind: bool,
+        /// Number of memories sent to the LLM per call
+        #[arg(long, default_value = "50")]
+        batch: usize,
+        /// Process at most this many memories, newest first
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Show the suggested changes without saving them
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Set verification status on a memory (verified, disputed, outdated)
     Verify {
-        /// Memory ID (full UUID or short 8-char prefix)
+        /// Memory ID (full UUID, short prefix, or title words)
         id: String,
         /// Verification status: verified, disputed, outdated, unverified
         #[arg(long)]
         status: String,
+        /// Why the memory is disputed (with --status disputed)
+        #[arg(long)]
+        reason: Option<String>,
+        /// Memory holding counter-evidence; linked with a contradicts relation
+        /// (with --status disputed)
+        #[arg(long, value_name = "ID")]
+        evidence: Option<String>,
+    },
+    /// Endorse a teammate's memory; endorsements raise its trust and ranking
+    Endorse {
+        /// Memory ID (full UUID, short prefix, or title words)
+        id: String,
+        /// Withdraw your endorsement instead
+        #[arg(long)]
+        withdraw: bool,
+    },
+    /// Make someone responsible for verifying a memory; while it is
+    /// unverified, disputed or due for re-verification it shows up in their
+    /// inbox
+    Assign {
+        /// Memory ID (full UUID, short prefix, or title words)
+        id: String,
+        /// User to assign it to
+        #[arg(required_unless_present = "clear")]
+        user: Option<String>,
+        /// Remove the current owner instead
+        #[arg(long, conflicts_with = "user")]
+        clear: bool,
+    },
+    /// Check the memory citations in an answer: each cited ID must exist, be
+    /// active and support the sentence citing it
+    CiteCheck {
+        /// File holding the answer (`-` for stdin)
+        #[arg(default_value = output::STDIO)]
+        file: String,
+        /// Only compare words; skip the embedding similarity check
+        #[arg(long)]
+        lexical: bool,
+        /// Print nothing; exit 0 if citations were flagged, 1 if none, 2 on error
+        #[arg(short, long)]
+        quiet: bool,
+        /// Output raw JSON
+        #[arg(long)]
+        json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
+    },
+    /// Set or clear a memory's classification label
+    Label {
+        /// Memory ID (full UUID, short prefix, or title words)
+        id: String,
+        /// New label (e.g. internal, confidential, public-ok); omit to show the current one
+        #[arg(conflicts_with = "clear")]
+        label: Option<String>,
+        /// Remove the label
+        #[arg(long)]
+        clear: bool,
+    },
+    /// Attach a file (stack trace, config snippet, screenshot) to a memory
+    Attach {
+        /// Memory ID (full UUID, short prefix, or title words)
+        id: String,
+        /// File to attach (`-` for stdin)
+        file: String,
+        /// Name to record (default: the file name)
+        #[arg(long)]
+        name: Option<String>,
     },
     /// Generate a paste-ready context pack from project memories
     ContextPack {
@@ -179,9 +546,6 @@ enum Cli {
         /// Token budget for the pack (default 2000)
         #[arg(long, default_value = "2000")]
         tokens: usize,
-        /// Filter by project
-        #[arg(short, long)]
-        project: Option<String>,
         /// Filter by memory kind
         #[arg(short, long)]
         kind: Option<String>,
@@ -191,21 +555,29 @@ enum Cli {
         /// Output raw JSON instead of markdown
         #[arg(long)]
         json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
         /// Write output to file instead of stdout
         #[arg(short, long)]
         output: Option<String>,
+        /// Only include verified memories
+        #[arg(long)]
+        verified_only: bool,
+        /// Leave out memories flagged by capture screening
+        #[arg(long)]
+        exclude_suspicious: bool,
+        #[command(flatten)]
+        exclude: ExcludeArgs,
     },
     /// Delete one or more memories
     Delete {
-        /// Memory ID to delete (full UUID or short 8-char prefix)
+        /// Memory ID to delete (full UUID, short prefix, or title words)
         id: Option<String>,
         /// Filter by memory kind (observation, decision, pattern, error, fix, preference, fact, lesson, todo, procedure)
         #[arg(short, long)]
         kind: Option<String>,
-        /// Filter by project
-        #[arg(short, long)]
-        project: Option<String>,
-        /// Filter by status (active, archived, superseded, pending)
+        /// Filter by status (active, archived, superseded, pending, quarantined)
         #[arg(short, long)]
         status: Option<String>,
         /// Required for bulk deletion (when using filters instead of a single ID)
@@ -214,30 +586,70 @@ enum Cli {
         /// Output raw JSON instead of formatted text
         #[arg(long)]
         json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
+    },
+    /// Restore archived memories to active
+    Unarchive {
+        /// Memory to restore (full UUID, short prefix, or title words of an archived memory)
+        id: Option<String>,
+        /// Only restore archived memories of this kind (with --all)
+        #[arg(short, long)]
+        kind: Option<String>,
+        /// Restore every archived memory matching the filters (required without an ID)
+        #[arg(long, conflicts_with = "id")]
+        all: bool,
+        /// Raise importance to at least this value (0.0-1.0); never lowers it
+        #[arg(long)]
+        boost: Option<f32>,
+        /// Show what would be restored without making changes
+        #[arg(long)]
+        dry_run: bool,
+        /// Output raw JSON instead of formatted text
+        #[arg(long)]
+        json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
     },
     /// List memories with optional filters
     List {
         /// Filter by memory kind (observation, decision, pattern, error, fix, preference, fact, lesson, todo, procedure)
         #[arg(short, long)]
         kind: Option<String>,
-        /// Filter by status (active, archived, superseded, pending)
+        /// Filter by status (active, archived, superseded, pending, quarantined)
         #[arg(short, long)]
         status: Option<String>,
-        /// Filter by project
-        #[arg(short, long)]
-        project: Option<String>,
+        /// Only archived memories (shortcut for --status archived)
+        #[arg(short, long, conflicts_with = "status")]
+        archived: bool,
         /// Maximum number of results
         #[arg(short, long, default_value = "20")]
         limit: usize,
         /// Output raw JSON instead of table
         #[arg(long)]
         json: bool,
+        /// Stream results as JSON Lines (one object per line)
+        #[arg(long, conflicts_with = "json")]
+        jsonl: bool,
+        /// Print nothing; exit 0 if results were found, 1 if none, 2 on error
+        #[arg(short, long)]
+        quiet: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
+        #[command(flatten)]
+        exclude: ExcludeArgs,
     },
     /// Check database integrity
     Check {
         /// Auto-repair: remove orphaned embeddings and broken relations
         #[arg(long)]
         repair: bool,
+        /// Print nothing; exit 0 if issues were found, 1 if none, 2 on error
+        #[arg(short, long)]
+        quiet: bool,
     },
     /// Launch interactive TUI for browsing memories
     Tui,
@@ -246,171 +658,952 @@ enum Cli {
         /// Remove demo memories instead of creating them
         #[arg(long)]
         clean: bool,
+        /// Walk through search, get, chain, verify, consolidate and
+        /// context-pack on the demo memories, checking each step
+        #[arg(long, conflicts_with = "clean")]
+        tutorial: bool,
+    },
+    /// Show or pin the default project for this directory
+    Project {
+        #[command(subcommand)]
+        action: ProjectAction,
     },
-    /// Review pending memories (approve or reject auto-captured memories)
+    /// Manage the HelixDB schema
+    Helix {
+        #[command(subcommand)]
+        action: HelixAction,
+    },
+    /// Review pending memories (approve or reject auto-captured memories) and
+    /// list verified memories whose verification has expired
     Review {
         /// List pending memories without taking action
         #[arg(long)]
         list: bool,
-        /// Approve a specific memory by ID (full UUID or short prefix)
+        /// Approve a specific memory by ID (full UUID, short prefix, or title words)
         #[arg(long)]
         approve: Option<String>,
-        /// Reject (archive) a specific memory by ID (full UUID or short prefix)
+        /// Reject (archive) a specific memory by ID (full UUID, short prefix, or title words)
         #[arg(long)]
         reject: Option<String>,
         /// Approve all pending memories at once
         #[arg(long)]
         approve_all: bool,
     },
+    /// List memories quarantined by capture screening, or release or delete one
+    Quarantine {
+        /// Release a memory into active use (full UUID, short prefix, or title words)
+        #[arg(long, conflicts_with = "delete")]
+        release: Option<String>,
+        /// Delete a quarantined memory (full UUID, short prefix, or title words)
+        #[arg(long)]
+        delete: Option<String>,
+    },
+    /// List everything waiting on you: memories to review, duplicates,
+    /// contradictions, lapsing verifications, parked captures, sync conflicts
+    Inbox {
+        /// Only these kinds (comma-separated, e.g. review,duplicate)
+        #[arg(long, value_delimiter = ',')]
+        kind: Vec<inbox::InboxKind>,
+        /// Step through each item, accepting, rejecting or skipping it
+        #[arg(long, conflicts_with = "json")]
+        triage: bool,
+        /// Output raw JSON
+        #[arg(long)]
+        json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
+    },
+    /// List the machines memories were captured on, or rename this one
+    Devices {
+        /// Rename this machine
+        #[arg(long)]
+        rename: Option<String>,
+        /// Output raw JSON
+        #[arg(long)]
+        json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
+    },
+    /// Sync memories with teammates through a Git repository
+    Sync {
+        #[command(subcommand)]
+        action: SyncAction,
+    },
+    /// Show each tenant's memories, tokens and requests (multi-tenant mode)
+    Tenants {
+        /// Output raw JSON
+        #[arg(long)]
+        json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
+    },
+    /// Manage API tokens for shabka-web and its MCP endpoint
+    Token {
+        #[command(subcommand)]
+        action: TokenAction,
+    },
+    /// Inspect the request audit log of shabka-web and the MCP server
+    Audit {
+        #[command(subcommand)]
+        action: AuditAction,
+    },
+    /// Save or roll back the database, config and embedding state
+    Snapshot {
+        #[command(subcommand)]
+        action: SnapshotAction,
+    },
+    /// Summarize the opt-in local usage telemetry
+    Telemetry {
+        #[command(subcommand)]
+        action: TelemetryAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConsolidateAction {
+    /// List the clusters consolidation would merge, without calling the LLM
+    Preview {
+        /// Minimum cluster size to consolidate
+        #[arg(long)]
+        min_cluster: Option<usize>,
+        /// Minimum age in days before a memory is eligible
+        #[arg(long)]
+        min_age: Option<u64>,
+        /// Output raw JSON
+        #[arg(long)]
+        json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
+    },
+    /// Reverse a consolidation run: restore its sources and archive its summaries
+    Undo {
+        /// Run ID or prefix (default: the latest run not yet undone)
+        run: Option<String>,
+        /// Delete the summaries instead of archiving them
+        #[arg(long)]
+        delete: bool,
+        /// List recorded runs instead of undoing one
+        #[arg(long, conflicts_with_all = ["run", "delete"])]
+        list: bool,
+        /// Output raw JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProjectAction {
+    /// Pin a default project in .shabka/config.local.toml
+    Use {
+        /// Project name
+        name: String,
+    },
+    /// Remove the pinned default project
+    Clear,
+    /// Show the effective project and where it comes from
+    Show,
+}
+
+#[derive(Subcommand)]
+enum SimilarityAction {
+    /// Write memory pairs scoring above a threshold as CSV
+    Export {
+        /// Minimum similarity for a pair to be written
+        #[arg(long, default_value = "0.8")]
+        threshold: f32,
+        /// Output file path (`-` for stdout)
+        #[arg(short, long, default_value = "-")]
+        output: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum GraphAction {
+    /// List the most central memories per project, the knowledge worth
+    /// verifying first
+    Top {
+        /// Memories to list per project
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+        /// Recompute centrality first instead of using the stored scores
+        #[arg(long)]
+        refresh: bool,
+        /// Output raw JSON
+        #[arg(long)]
+        json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
+    },
+    /// Recompute every memory's centrality from its relations
+    Centrality,
+}
+
+#[derive(Subcommand, Debug)]
+enum SyncAction {
+    /// Create the sync repository and register its merge driver
+    Init {
+        /// Git remote to push to and pull from
+        #[arg(long)]
+        remote: Option<String>,
+    },
+    /// Write local memories to the repository, merge the remote's, and push
+    Push,
+    /// Pull the remote's memories and embed them locally
+    Pull,
+    /// Git merge driver for memory files (run by git, not by hand)
+    #[command(hide = true)]
+    MergeDriver {
+        base: std::path::PathBuf,
+        ours: std::path::PathBuf,
+        theirs: std::path::PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TokenAction {
+    /// Create a token and print its secret (shown only once)
+    Create {
+        /// Name recorded in history for changes made with the token
+        name: String,
+        /// What the token may do: read, or write (also required for /mcp)
+        #[arg(long, default_value = "read")]
+        scope: TokenScope,
+        /// Expire after a span like 12h, 30d, 2w, or on a date (default: never)
+        #[arg(long)]
+        expires: Option<String>,
+        /// Serve the token from this tenant's store ([tenants] enabled)
+        #[arg(long)]
+        tenant: Option<String>,
+        /// Output raw JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// List tokens, including revoked and expired ones
+    List {
+        /// Output raw JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Revoke a token by name or ID prefix
+    Revoke { name_or_id: String },
+}
+
+#[derive(Subcommand, Debug)]
+enum AuditAction {
+    /// Show the latest API requests and tool calls
+    Tail {
+        /// Number of entries to show
+        #[arg(short, long, default_value = "20")]
+        limit: usize,
+        /// Keep printing new entries as they arrive
+        #[arg(short, long)]
+        follow: bool,
+        /// Only this surface: web or mcp
+        #[arg(long)]
+        surface: Option<String>,
+        /// Only calls made with this API token
+        #[arg(long)]
+        token: Option<String>,
+        /// Only calls made for this tenant
+        #[arg(long)]
+        tenant: Option<String>,
+        /// Only failed calls
+        #[arg(long)]
+        failures: bool,
+        /// Output JSON lines
+        #[arg(long)]
+        json: bool,
+        /// Print the JSON Schema of an entry and exit
+        #[arg(long)]
+        schema: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TelemetryAction {
+    /// Runs, failures, latencies and result counts per command
+    Report {
+        /// Only runs from the last N days
+        #[arg(long)]
+        days: Option<i64>,
+        /// Output raw JSON
+        #[arg(long)]
+        json: bool,
+        /// Print the JSON Schema of the --json output and exit
+        #[arg(long)]
+        schema: bool,
+    },
+    /// Delete the telemetry log
+    Clear,
+}
+
+#[derive(Subcommand, Debug)]
+enum SnapshotAction {
+    /// Save the current state under a name
+    Create {
+        /// Snapshot name (letters, digits, '-', '_' and '.')
+        name: String,
+    },
+    /// Put a snapshot's database, config and embedding state back
+    Restore {
+        /// Snapshot name
+        name: String,
+    },
+    /// List snapshots
+    List {
+        /// Output raw JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Delete a snapshot
+    Delete {
+        /// Snapshot name
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum HelixAction {
+    /// Push Shabka's schema and queries to HelixDB and verify them
+    Init {
+        /// Directory for the Helix project (default: ~/.config/shabka/helix)
+        #[arg(long)]
+        dir: Option<std::path::PathBuf>,
+        /// Only write the project files and verify; don't run `helix push`
+        #[arg(long)]
+        no_push: bool,
+    },
+}
+
+/// Exclusion flags shared by `search`, `list` and `context-pack`.
+#[derive(Args, Debug, Default)]
+struct ExcludeArgs {
+    /// Exclude memories with this tag (can be repeated)
+    #[arg(long)]
+    not_tag: Vec<String>,
+    /// Exclude memories of this kind (can be repeated)
+    #[arg(long)]
+    exclude_kind: Vec<String>,
+    /// Exclude memories from this project (can be repeated)
+    #[arg(long)]
+    exclude_project: Vec<String>,
+    /// Exclude memories captured on this device, by ID prefix (can be repeated)
+    #[arg(long)]
+    exclude_device: Vec<String>,
+    /// Exclude these memories, by ID prefix (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    exclude_ids: Vec<String>,
+    /// Exclude memories whose content SHA-256 starts with one of these (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    exclude_hashes: Vec<String>,
+}
+
+impl ExcludeArgs {
+    /// Add the exclusions to a parsed query.
+    fn apply(&self, query: SearchQuery) -> Result<SearchQuery> {
+        let kinds = self
+            .exclude_kind
+            .iter()
+            .map(|k| k.parse::<MemoryKind>().map_err(|e| anyhow::anyhow!("{e}")))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(query
+            .without_kinds(kinds)
+            .without_tags(self.not_tag.clone())
+            .without_projects(self.exclude_project.clone())
+            .without_devices(self.exclude_device.clone())
+            .without_ids(self.exclude_ids.clone())
+            .without_hashes(self.exclude_hashes.clone()))
+    }
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> std::process::ExitCode {
     tracing_subscriber::fmt()
         .with_writer(std::io::stderr)
         .compact()
         .init();
 
-    let cli = Cli::parse();
-    let config = ShabkaConfig::load(Some(&std::env::current_dir()?))
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let project_dir = project_dir();
+    let config = ShabkaConfig::load(project_dir.as_deref())
         .unwrap_or_else(|_| ShabkaConfig::default_config());
     let user_id = config::resolve_user_id(&config.sharing);
 
+    let started = std::time::Instant::now();
     let result = run(cli, &config, &user_id).await;
-    if let Err(ref err) = result {
-        let friendly = format_helix_error(err, &config);
-        if friendly != format!("{}", err) {
-            eprintln!("{}", friendly);
-            std::process::exit(1);
+    // Reading the telemetry log isn't usage worth recording.
+    if let Some(command) = telemetry::command_name(&matches)
+        .filter(|c| config.telemetry.enabled && !c.starts_with("telemetry"))
+    {
+        TelemetryLog::new(&config.telemetry).record(&TelemetryEvent::new(
+            command,
+            result.is_ok(),
+            started.elapsed(),
+            telemetry::take_counts(),
+        ));
+    }
+
+    match result {
+        Ok(outcome) => outcome.exit_code(),
+        Err(err) => {
+            eprintln!("{}", format_error(&err));
+            std::process::ExitCode::from(output::EXIT_ERROR)
         }
     }
-    result
 }
 
-async fn run(cli: Cli, config: &ShabkaConfig, user_id: &str) -> Result<()> {
-    match cli {
-        Cli::Init { provider, check } => cmd_init(&provider, check).await,
-        Cli::Search {
-            query,
+async fn run(cli: Cli, config: &ShabkaConfig, user_id: &str) -> Result<output::Outcome> {
+    // Query-style commands return early with their own outcome; everything
+    // else succeeds with exit status 0.
+    style::configure(cli.plain);
+    progress::set_enabled(!cli.no_progress && !style::plain());
+    output::set_stable(cli.stable_output);
+    dates::configure(cli.utc || config.display.utc, config.display.relative_times);
+    i18n::configure(config.output.language.as_deref());
+    let explicit_project = cli.project;
+    let project = config.resolve_project(explicit_project.clone());
+    let services = Services::new(config.clone());
+    let result: Result<()> = match cli.command {
+        Command::Init { provider, check } => cmd_init(&provider, check).await,
+        Command::Add { schema: true, .. } => output::print_schema::<output::AddOutput>(),
+        Command::Add {
+            title,
+            content,
             kind,
-            limit,
             tag,
-            project,
+            importance,
+            privacy,
+            label,
             json,
-            token_budget,
+            schema: _,
         } => {
-            let storage = make_storage(config)?;
-            let embedder = EmbeddingService::from_config(&config.embedding)
-                .context("failed to create embedding service")?;
-            cmd_search(
+            let storage = make_storage(&services)?;
+            let embedder = make_embedder(&services)?;
+            let history = services.history();
+            let content = output::read_text_arg(content.as_deref())?;
+            let privacy = match privacy {
+                Some(p) => p.parse().map_err(|e: String| anyhow::anyhow!("{}", e))?,
+                None => sharing::parse_default_privacy(&config.privacy),
+            };
+            let label = config.labels.resolve(label.as_deref());
+            let privacy = config.labels.clamp_privacy(label.as_deref(), privacy);
+            cmd_add(
                 &storage,
                 &embedder,
+                &history,
                 user_id,
-                &query,
-                kind,
-                limit,
-                tag,
+                &title.unwrap_or_default(),
+                &content,
+                &kind,
+                tag.unwrap_or_default(),
+                importance,
                 project,
+                privacy,
+                label,
                 json,
-                token_budget,
             )
             .await
         }
-        Cli::Get { id, json } => {
-            let storage = make_storage(config)?;
-            cmd_get(&storage, &id, json).await
+        Command::Search { schema: true, .. } => output::print_schema::<Vec<MemoryIndex>>(),
+        Command::Search {
+            query,
+            symbol,
+            kind,
+            limit,
+            tag,
+            json,
+            jsonl,
+            quiet,
+            schema: _,
+            token_budget,
+            exclude,
+        } => {
+            let storage = make_storage(&services)?;
+            let embedder = make_embedder(&services)?;
+            let request = search_request(&query.unwrap_or_default(), kind, tag, project, &exclude)?
+                .map_query(|q| q.with_symbol(symbol))
+                .limit(limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+                .token_budget(token_budget)
+                .demote_contradicted(config.retrieval.demote_contradicted)
+                .ranker(services.wasm_ranker());
+            let out = SearchOutput { json, jsonl, quiet };
+            return cmd_search(
+                &storage,
+                &embedder,
+                &services.questions(),
+                user_id,
+                request,
+                out,
+            )
+            .await
+            .map(output::Outcome::found);
+        }
+        Command::Get { schema: true, .. } => output::print_schema::<Memory>(),
+        Command::Get {
+            id,
+            expand_sources,
+            json,
+            ..
+        } => {
+            let storage = make_storage(&services)?;
+            cmd_get(&storage, &id.unwrap_or_default(), expand_sources, json).await
+        }
+        Command::Status { maintenance: true } => {
+            let storage = make_storage(&services)?;
+            cmd_status_maintenance(&storage).await
         }
-        Cli::Status => {
-            let storage = make_storage(config)?;
+        Command::Status { maintenance: false } => {
+            let storage = make_storage(&services)?;
             cmd_status(&storage, config, user_id).await
         }
-        Cli::Export {
+        Command::Export {
+            schema: true,
+            jsonl,
+            ..
+        } => {
+            if jsonl {
+                output::print_schema::<output::ExportRecord>()
+            } else {
+                output::print_schema::<ExportData>()
+            }
+        }
+        Command::Export {
             output,
+            jsonl,
+            schema: _,
             privacy,
             scrub,
             scrub_report,
+            attachments,
         } => {
-            let storage = make_storage(config)?;
+            let storage = make_storage(&services)?;
             let scrub_config = if scrub || scrub_report {
                 Some(config.scrub.clone())
             } else {
                 None
             };
+            let blobs = attachments.then(|| services.blobs());
             cmd_export(
                 &storage,
                 &output,
+                jsonl,
                 &privacy,
+                &config.labels,
                 scrub_config.as_ref(),
                 scrub_report,
+                blobs.as_deref(),
+            )
+            .await
+        }
+        Command::Import { path } => {
+            let storage = make_storage(&services)?;
+            let embedder = make_embedder(&services)?;
+            let history = services.history();
+            let blobs = services.blobs();
+            cmd_import(&storage, &embedder, user_id, &path, &history, &blobs).await
+        }
+        Command::MigrateFromKaizen {
+            path,
+            helix_url,
+            helix_port,
+            history,
+            dry_run,
+            json,
+        } => {
+            let storage = make_storage(&services)?;
+            let embedder = make_embedder(&services)?;
+            let source = match (path, helix_url) {
+                (Some(path), _) => KaizenSource::Sqlite(path),
+                (None, Some(url)) => KaizenSource::Helix(url, helix_port),
+                (None, None) => anyhow::bail!("give a Kaizen database path or --helix-url"),
+            };
+            cmd_migrate_from_kaizen(
+                &storage,
+                &embedder,
+                &services.history(),
+                source,
+                history.as_deref(),
+                dry_run,
+                json,
             )
             .await
         }
-        Cli::Import { path } => {
-            let storage = make_storage(config)?;
-            let embedder = EmbeddingService::from_config(&config.embedding)
-                .context("failed to create embedding service")?;
-            let history = HistoryLogger::new(config.history.enabled);
-            cmd_import(&storage, &embedder, user_id, &path, &history).await
+        Command::Chain { schema: true, .. } => {
+            output::print_schema::<Vec<output::ChainLinkOutput>>()
         }
-        Cli::Chain {
+        Command::Chain {
             id,
             relation,
             depth,
+            max_nodes,
             json,
+            schema: _,
         } => {
-            let storage = make_storage(config)?;
+            let storage = make_storage(&services)?;
             let depth = depth.unwrap_or(config.graph.max_chain_depth);
-            cmd_chain(&storage, &id, relation, depth, json).await
+            let max_nodes = max_nodes.unwrap_or(config.graph.max_chain_nodes);
+            cmd_chain(
+                &storage,
+                &id.unwrap_or_default(),
+                relation,
+                depth,
+                max_nodes,
+                json,
+            )
+            .await
+        }
+        Command::Similar { schema: true, .. } => {
+            output::print_schema::<Vec<output::SimilarOutput>>()
+        }
+        Command::Similar {
+            id, limit, json, ..
+        } => {
+            let storage = make_storage(&services)?;
+            let embedder = make_embedder(&services)?;
+            cmd_similar(
+                &storage,
+                &embedder,
+                user_id,
+                &id.unwrap_or_default(),
+                limit,
+                json,
+            )
+            .await
+        }
+        Command::Random { schema: true, .. } => output::print_schema::<Memory>(),
+        Command::Random {
+            kind,
+            motd,
+            daily,
+            json,
+            ..
+        } => {
+            let picked = async {
+                let storage = make_storage(&services)?;
+                cmd_random(&storage, user_id, kind, project, motd, daily, json).await
+            };
+            if motd {
+                // A shell startup file must never be broken by a missing or
+                // unreachable store, so errors are swallowed here.
+                let _ = picked.await;
+                return Ok(output::Outcome::Success);
+            }
+            return picked.await.map(output::Outcome::found);
         }
-        Cli::Prune {
+        Command::Prune {
             days,
             dry_run,
             decay_importance,
+            delete_after,
+            cold_storage,
+        } => {
+            let storage = make_storage(&services)?;
+            let prune = PruneConfig {
+                inactive_days: days.unwrap_or(config.graph.stale_days),
+                decay_importance,
+                delete_after_days: delete_after.or(config.graph.delete_after_days),
+                retention: config.retention.clone(),
+                ..Default::default()
+            };
+            let history = services.history();
+            let mut run = MaintenanceRun::start(
+                MaintenanceOp::Prune,
+                serde_json::json!({
+                    "inactive_days": prune.inactive_days,
+                    "decay_importance": prune.decay_importance,
+                    "delete_after_days": prune.delete_after_days,
+                    "cold_storage": cold_storage,
+                }),
+            );
+            let result = cmd_prune(
+                &storage,
+                &history,
+                user_id,
+                &prune,
+                dry_run,
+                cold_storage.as_deref(),
+                &mut run,
+            )
+            .await;
+            if !dry_run {
+                record_maintenance(&storage, run, &result).await;
+            }
+            result
+        }
+        Command::History { schema: true, .. } => output::print_schema::<Vec<MemoryEvent>>(),
+        Command::History {
+            id,
+            limit,
+            actor,
+            action,
+            since,
+            until,
+            json,
+            ..
+        } => {
+            let history = services.history();
+            let now = chrono::Utc::now();
+            let mut filter = HistoryFilter {
+                actor,
+                actions: action,
+                since: since
+                    .map(|s| history::parse_time(&s, false, now))
+                    .transpose()?,
+                until: until
+                    .map(|s| history::parse_time(&s, true, now))
+                    .transpose()?,
+                project: explicit_project,
+                ..Default::default()
+            };
+            // Full UUIDs skip storage so deleted memories stay reachable.
+            filter.memory_id = match id {
+                Some(id) => match Uuid::parse_str(id.trim()) {
+                    Ok(uuid) => Some(uuid),
+                    Err(_) => Some(resolve_memory_id(&*make_storage(&services)?, &id).await?),
+                },
+                None => None,
+            };
+            if filter.project.is_some() {
+                filter
+                    .resolve_project(&*make_storage(&services)?)
+                    .await
+                    .context("failed to look up project memories")?;
+            }
+            cmd_history(&history, &filter, limit, json)
+        }
+        Command::Digest { schema: true, .. } => output::print_schema::<digest::Digest>(),
+        Command::Digest {
+            since,
+            until,
+            send,
+            json,
+            ..
         } => {
-            let storage = make_storage(config)?;
-            let days = days.unwrap_or(config.graph.stale_days);
-            let history = HistoryLogger::new(config.history.enabled);
-            cmd_prune(&storage, &history, user_id, days, dry_run, decay_importance).await
+            let now = chrono::Utc::now();
+            let since = history::parse_time(&since, false, now)?;
+            let until = until
+                .map(|s| history::parse_time(&s, true, now))
+                .transpose()?
+                .unwrap_or(now);
+            let storage = make_storage(&services)?;
+            let mut digest = digest::build(&*storage, &services.history(), since, until).await?;
+            if let Some(project) = &explicit_project {
+                digest
+                    .projects
+                    .retain(|p| p.project.as_deref() == Some(project.as_str()));
+            }
+            cmd_digest(&digest, &config.digest, send, json).await
         }
-        Cli::History { id, limit, json } => {
-            let history = HistoryLogger::new(config.history.enabled);
-            cmd_history(&history, id, limit, json)
+        Command::Assess {
+            schema: true,
+            trend: true,
+            ..
+        } => output::print_schema::<Vec<assess::HealthPoint>>(),
+        Command::Assess { schema: true, .. } => output::print_schema::<output::AssessOutput>(),
+        Command::Assess {
+            trend: true, json, ..
+        } => {
+            let storage = make_storage(&services)?;
+            return cmd_assess_trend(&storage, json)
+                .await
+                .map(output::Outcome::found);
         }
-        Cli::Assess {
+        Command::Assess {
             duplicates,
+            codebase,
+            classify,
+            fix,
             limit,
+            trend: _,
             json,
+            quiet,
+            schema: _,
         } => {
-            let storage = make_storage(config)?;
+            let storage = make_storage(&services)?;
             let embedder = if duplicates {
+                Some(make_embedder(&services)?)
+            } else {
+                None
+            };
+            let llm = if classify {
+                if !config.llm.enabled {
+                    anyhow::bail!("--classify requires LLM. Enable it in config.toml under [llm].");
+                }
                 Some(
-                    EmbeddingService::from_config(&config.embedding)
-                        .context("failed to create embedding service")?,
+                    shabka_core::llm::LlmService::from_config(&config.llm)
+                        .context("failed to create LLM service")?,
                 )
             } else {
                 None
             };
-            cmd_assess(
+            let history = services.history();
+            let project = config.resolve_project(None);
+            let mut run = MaintenanceRun::start(
+                MaintenanceOp::Assess,
+                serde_json::json!({
+                    "duplicates": duplicates,
+                    "codebase": codebase.is_some(),
+                    "classify": classify,
+                    "stale_days": config.graph.stale_days,
+                }),
+            );
+            let result = cmd_assess(
                 &storage,
-                embedder.as_ref(),
+                embedder.as_deref(),
+                llm.as_ref(),
                 &config.graph,
+                &config.retention,
+                config.capture.min_confidence,
                 limit,
                 duplicates,
+                codebase.as_deref().map(|root| (root, project.as_deref())),
+                fix.then_some((history.as_ref(), user_id)),
                 json,
+                quiet,
+                &mut run,
             )
-            .await
+            .await;
+            // A partial assessment would skew the trend.
+            if limit.is_none() {
+                record_maintenance(&storage, run, &result).await;
+            }
+            return result.map(output::Outcome::found);
+        }
+        Command::Similarity {
+            action: SimilarityAction::Export { threshold, output },
+        } => {
+            let storage = make_storage(&services)?;
+            cmd_similarity_export(&storage, &config.labels, threshold, &output).await
         }
-        Cli::Consolidate {
+        Command::Graph {
+            action: GraphAction::Top { schema: true, .. },
+        } => output::print_schema::<Vec<output::CentralMemoryOutput>>(),
+        Command::Graph {
+            action:
+                GraphAction::Top {
+                    limit,
+                    refresh,
+                    json,
+                    schema: _,
+                },
+        } => {
+            let storage = make_storage(&services)?;
+            let never_scored = storage
+                .last_successful_run(MaintenanceOp::Centrality)
+                .await?
+                .is_none();
+            if refresh || (never_scored && storage.capabilities().centrality) {
+                cmd_graph_centrality(&storage, json).await?;
+            }
+            return cmd_graph_top(&storage, user_id, explicit_project.as_deref(), limit, json)
+                .await
+                .map(output::Outcome::found);
+        }
+        Command::Graph {
+            action: GraphAction::Centrality,
+        } => {
+            let storage = make_storage(&services)?;
+            cmd_graph_centrality(&storage, false).await
+        }
+        Command::Gaps {
+            schema: true,
+            questions: true,
+            ..
+        } => output::print_schema::<Vec<OpenQuestion>>(),
+        Command::Gaps { schema: true, .. } => output::print_schema::<output::GapsOutput>(),
+        Command::Gaps {
+            name,
+            questions: true,
+            json,
+            ..
+        } => {
+            let project = name.or(project);
+            return cmd_questions(&services.questions(), project.as_deref(), json)
+                .map(output::Outcome::found);
+        }
+        Command::Gaps {
+            name,
+            min_edits,
+            json,
+            ..
+        } => {
+            let Some(project) = name.or(project) else {
+                anyhow::bail!("no project given; pass one or pin it with `shabka project use`");
+            };
+            let storage = make_storage(&services)?;
+            return cmd_gaps(&storage, &project, min_edits, json)
+                .await
+                .map(output::Outcome::found);
+        }
+        Command::Consolidate {
+            action: Some(ConsolidateAction::Preview { schema: true, .. }),
+            ..
+        } => output::print_schema::<Vec<shabka_core::consolidate::ClusterPreview>>(),
+        Command::Consolidate {
+            action:
+                Some(ConsolidateAction::Preview {
+                    min_cluster,
+                    min_age,
+                    json,
+                    schema: _,
+                }),
+            ..
+        } => {
+            let storage = make_storage(&services)?;
+            let embedder = make_embedder(&services)?;
+            cmd_consolidate_preview(&storage, &embedder, config, min_cluster, min_age, json).await
+        }
+        Command::Consolidate {
+            action:
+                Some(ConsolidateAction::Undo {
+                    run,
+                    delete,
+                    list,
+                    json,
+                }),
+            ..
+        } => {
+            let log = shabka_core::consolidate::ConsolidationLog::new();
+            if list {
+                cmd_consolidate_runs(&log, json)
+            } else {
+                let storage = make_storage(&services)?;
+                let history = services.history();
+                cmd_consolidate_undo(
+                    &storage,
+                    &log,
+                    &history,
+                    user_id,
+                    run.as_deref(),
+                    delete,
+                    json,
+                )
+                .await
+            }
+        }
+        Command::Consolidate { schema: true, .. } => {
+            output::print_schema::<shabka_core::consolidate::ConsolidateResult>()
+        }
+        Command::Consolidate {
+            action: None,
             dry_run,
             min_cluster,
             min_age,
             json,
+            schema: _,
         } => {
-            let storage = make_storage(config)?;
-            let embedder = EmbeddingService::from_config(&config.embedding)
-                .context("failed to create embedding service")?;
-            let history = HistoryLogger::new(config.history.enabled);
-            cmd_consolidate(
+            let storage = make_storage(&services)?;
+            let embedder = make_embedder(&services)?;
+            let history = services.history();
+            let mut run =
+                MaintenanceRun::start(MaintenanceOp::Consolidate, serde_json::Value::Null);
+            let result = cmd_consolidate(
                 &storage,
                 &embedder,
                 config,
@@ -420,114 +1613,406 @@ async fn run(cli: Cli, config: &ShabkaConfig, user_id: &str) -> Result<()> {
                 min_cluster,
                 min_age,
                 json,
+                &mut run,
             )
-            .await
+            .await;
+            if !dry_run {
+                record_maintenance(&storage, run, &result).await;
+            }
+            result
         }
-        Cli::Doctor => cmd_doctor(config).await,
-        Cli::Reembed {
+        Command::Doctor => cmd_doctor(config).await,
+        Command::Reembed {
             batch_size,
             dry_run,
             force,
+            batch_api,
+        } => {
+            let storage = make_storage(&services)?;
+            let embedder = make_embedder(&services)?;
+            let mut run = MaintenanceRun::start(
+                MaintenanceOp::Reembed,
+                serde_json::json!({
+                    "provider": embedder.provider_name(),
+                    "model": embedder.model_id(),
+                    "dimensions": embedder.dimensions(),
+                    "batch_size": batch_size,
+                    "force": force,
+                    "batch_api": batch_api,
+                }),
+            );
+            let batch_api = batch_api.then_some(&config.embedding);
+            let result = cmd_reembed(
+                &storage, &embedder, batch_size, dry_run, force, batch_api, &mut run,
+            )
+            .await;
+            if !dry_run {
+                record_maintenance(&storage, run, &result).await;
+            }
+            result
+        }
+        Command::Backfill {
+            auto_tag,
+            classify_kind,
+            batch,
+            limit,
+            dry_run,
+        } => {
+            let storage = make_storage(&services)?;
+            let embedder = make_embedder(&services)?;
+            let history = services.history();
+            let options = shabka_core::backfill::BackfillOptions {
+                auto_tag,
+                classify_kind,
+            };
+            let mut run = MaintenanceRun::start(
+                MaintenanceOp::Backfill,
+                serde_json::json!({
+                    "auto_tag": auto_tag,
+                    "classify_kind": classify_kind,
+                    "batch": batch,
+                    "limit": limit,
+                    "model": config.llm.model,
+                }),
+            );
+            let result = cmd_backfill(
+                &storage, &embedder, config, &history, user_id, options, batch, limit, dry_run,
+                &mut run,
+            )
+            .await;
+            if !dry_run {
+                record_maintenance(&storage, run, &result).await;
+            }
+            result
+        }
+        Command::Verify {
+            id,
+            status,
+            reason,
+            evidence,
+        } => {
+            let storage = make_storage(&services)?;
+            let history = services.history();
+            cmd_verify(
+                &storage,
+                &history,
+                &config.retention,
+                user_id,
+                &id,
+                &status,
+                reason,
+                evidence.as_deref(),
+            )
+            .await
+        }
+        Command::Endorse { id, withdraw } => {
+            let storage = make_storage(&services)?;
+            cmd_endorse(&storage, user_id, &id, withdraw).await
+        }
+        Command::Assign { id, user, .. } => {
+            let storage = make_storage(&services)?;
+            let history = services.history();
+            cmd_assign(&storage, &history, user_id, &id, user).await
+        }
+        Command::CiteCheck { schema: true, .. } => {
+            output::print_schema::<Vec<citations::CitationCheck>>()
+        }
+        Command::CiteCheck {
+            file,
+            lexical,
+            quiet,
+            json,
+            ..
         } => {
-            let storage = make_storage(config)?;
-            let embedder = EmbeddingService::from_config(&config.embedding)
-                .context("failed to create embedding service")?;
-            cmd_reembed(&storage, &embedder, batch_size, dry_run, force).await
+            let storage = make_storage(&services)?;
+            let embedder = if lexical {
+                None
+            } else {
+                Some(make_embedder(&services)?)
+            };
+            let answer = output::read_source(&file)?;
+            return cmd_cite_check(&storage, embedder.as_deref(), user_id, &answer, quiet, json)
+                .await
+                .map(output::Outcome::found);
+        }
+        Command::Label { id, label, clear } => {
+            let storage = make_storage(&services)?;
+            let history = services.history();
+            let label = if clear { Some(String::new()) } else { label };
+            cmd_label(&storage, &history, &config.labels, user_id, &id, label).await
+        }
+        Command::Attach { id, file, name } => {
+            let storage = make_storage(&services)?;
+            let history = services.history();
+            let blobs = services.blobs();
+            cmd_attach(
+                &storage,
+                &blobs,
+                &config.attachments,
+                &history,
+                user_id,
+                &id,
+                &file,
+                name,
+            )
+            .await
         }
-        Cli::Verify { id, status } => {
-            let storage = make_storage(config)?;
-            let history = HistoryLogger::new(config.history.enabled);
-            cmd_verify(&storage, &history, user_id, &id, &status).await
+        Command::ContextPack { schema: true, .. } => {
+            output::print_schema::<shabka_core::context_pack::ContextPack>()
         }
-        Cli::ContextPack {
+        Command::ContextPack {
             query,
             tokens,
-            project,
             kind,
             tag,
             json,
+            schema: _,
             output,
+            verified_only,
+            exclude_suspicious,
+            exclude,
         } => {
-            let storage = make_storage(config)?;
-            let embedder = EmbeddingService::from_config(&config.embedding)
-                .context("failed to create embedding service")?;
-            cmd_context_pack(
-                &storage, &embedder, user_id, &query, tokens, project, kind, tag, json, output,
-            )
-            .await
+            let storage = make_storage(&services)?;
+            let embedder = make_embedder(&services)?;
+            let filter = PackFilter {
+                verified_only,
+                exclude_suspicious,
+                min_confidence: config.capture.min_confidence,
+            };
+            let request = search_request(&query, kind, tag, project, &exclude)?
+                .visible_to(user_id)
+                .filter(filter)
+                .demote_contradicted(config.retrieval.demote_contradicted)
+                .ranker(services.wasm_ranker());
+            cmd_context_pack(&storage, &embedder, request, tokens, json, output).await
         }
-        Cli::Delete {
+        Command::Delete { schema: true, .. } => output::print_schema::<output::DeleteOutput>(),
+        Command::Delete {
             id,
             kind,
-            project,
             status,
             confirm,
             json,
+            schema: _,
         } => {
-            let storage = make_storage(config)?;
-            let history = HistoryLogger::new(config.history.enabled);
+            let storage = make_storage(&services)?;
+            let history = services.history();
+            // A pinned project narrows a bulk delete but never starts one on its own.
+            let project = if kind.is_some() || status.is_some() || explicit_project.is_some() {
+                project
+            } else {
+                None
+            };
             cmd_delete(
                 &storage, &history, user_id, id, kind, project, status, confirm, json,
             )
             .await
         }
-        Cli::List {
+        Command::Unarchive { schema: true, .. } => {
+            output::print_schema::<output::UnarchiveOutput>()
+        }
+        Command::Unarchive {
+            id,
+            kind,
+            all,
+            boost,
+            dry_run,
+            json,
+            schema: _,
+        } => {
+            let storage = make_storage(&services)?;
+            let history = services.history();
+            cmd_unarchive(
+                &storage, &history, user_id, id, kind, project, all, boost, dry_run, json,
+            )
+            .await
+        }
+        Command::List { schema: true, .. } => output::print_schema::<Vec<TimelineEntry>>(),
+        Command::List {
             kind,
             status,
-            project,
+            archived,
             limit,
             json,
+            jsonl,
+            quiet,
+            schema: _,
+            exclude,
         } => {
-            let storage = make_storage(config)?;
-            cmd_list(&storage, kind, status, project, limit, json).await
-        }
-        Cli::Check { repair } => {
-            let storage = make_storage(config)?;
-            cmd_check(&storage, repair).await
-        }
-        Cli::Tui => tui::run_tui(config).await,
-        Cli::Demo { clean } => {
-            let storage = make_storage(config)?;
-            let embedder = EmbeddingService::from_config(&config.embedding)
-                .context("failed to create embedding service")?;
-            let history = HistoryLogger::new(config.history.enabled);
-            cmd_demo(&storage, &embedder, user_id, &history, clean).await
-        }
-        Cli::Review {
+            let storage = make_storage(&services)?;
+            let status = if archived {
+                Some("archived".to_string())
+            } else {
+                status
+            };
+            return cmd_list(
+                &storage, kind, status, project, limit, json, jsonl, quiet, &exclude,
+            )
+            .await
+            .map(output::Outcome::found);
+        }
+        Command::Check { repair, quiet } => {
+            let storage = make_storage(&services)?;
+            let mut run = MaintenanceRun::start(
+                MaintenanceOp::Check,
+                serde_json::json!({ "repair": repair }),
+            );
+            let result = cmd_check(&storage, repair, quiet, &mut run).await;
+            if storage.capabilities().integrity_check {
+                record_maintenance(&storage, run, &result).await;
+            }
+            return result.map(output::Outcome::found);
+        }
+        Command::Tui => tui::run_tui(&services).await,
+        Command::Demo { clean, tutorial } => {
+            let storage = make_storage(&services)?;
+            let embedder = make_embedder(&services)?;
+            let history = services.history();
+            if tutorial {
+                use std::io::IsTerminal;
+                let interactive = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+                cmd_demo_tutorial(
+                    &storage,
+                    &embedder,
+                    config,
+                    &services.questions(),
+                    user_id,
+                    &history,
+                    interactive,
+                )
+                .await
+            } else {
+                cmd_demo(&storage, &embedder, user_id, &history, clean).await
+            }
+        }
+        Command::Project { action } => {
+            let dir = project_dir().context("failed to determine current directory")?;
+            cmd_project(action, config, explicit_project, &dir)
+        }
+        Command::Helix { action } => cmd_helix(action, config).await,
+        Command::Review {
             list,
             approve,
             reject,
             approve_all,
         } => {
-            let storage = make_storage(config)?;
+            let storage = make_storage(&services)?;
             cmd_review(&storage, list, approve, reject, approve_all).await
         }
-    }
+        Command::Quarantine { release, delete } => {
+            let storage = make_storage(&services)?;
+            let history = services.history();
+            cmd_quarantine(&storage, &history, user_id, release, delete).await
+        }
+        Command::Inbox { schema: true, .. } => output::print_schema::<inbox::Inbox>(),
+        Command::Inbox {
+            kind, triage, json, ..
+        } => {
+            let storage = make_storage(&services)?;
+            if triage {
+                let history = services.history();
+                cmd_inbox_triage(&storage, &history, config, user_id, &kind).await
+            } else {
+                cmd_inbox(&storage, config, user_id, &kind, json).await
+            }
+        }
+        Command::Devices { schema: true, .. } => {
+            output::print_schema::<Vec<devices::DeviceStats>>()
+        }
+        Command::Devices { rename, json, .. } => {
+            let storage = make_storage(&services)?;
+            cmd_devices(&storage, rename, json).await
+        }
+        Command::Sync { action } => cmd_sync(action, &services, config, user_id).await,
+        Command::Tenants { schema: true, .. } => {
+            output::print_schema::<Vec<tenants::TenantUsage>>()
+        }
+        Command::Tenants { json, .. } => {
+            let storage = make_storage(&services)?;
+            let usage = tenants::usage(
+                &config.tenants.root(),
+                &api_tokens::list(&storage).await?,
+                &services.audit(),
+            )
+            .await
+            .context("failed to read tenant usage")?;
+            cmd_tenants(&usage, json)
+        }
+        Command::Token { action } => cmd_token(action, &*make_storage(&services)?).await,
+        Command::Audit {
+            action: AuditAction::Tail { schema: true, .. },
+        } => output::print_schema::<AuditEntry>(),
+        Command::Audit {
+            action:
+                AuditAction::Tail {
+                    limit,
+                    follow,
+                    surface,
+                    token,
+                    tenant,
+                    failures,
+                    json,
+                    ..
+                },
+        } => {
+            let filter = AuditFilter {
+                surface,
+                token,
+                tenant,
+                failures_only: failures,
+            };
+            cmd_audit_tail(&services.audit(), &filter, limit, follow, json).await
+        }
+        Command::Snapshot { action } => cmd_snapshot(action, config),
+        Command::Telemetry {
+            action: TelemetryAction::Report { schema: true, .. },
+        } => output::print_schema::<Vec<shabka_core::telemetry::CommandUsage>>(),
+        Command::Telemetry {
+            action: TelemetryAction::Report { days, json, .. },
+        } => {
+            let since = days.map(|d| chrono::Utc::now() - chrono::Duration::days(d));
+            return cmd_telemetry_report(&TelemetryLog::new(&config.telemetry), since, json)
+                .map(output::Outcome::found);
+        }
+        Command::Telemetry {
+            action: TelemetryAction::Clear,
+        } => {
+            let log = TelemetryLog::new(&config.telemetry);
+            log.clear()
+                .with_context(|| format!("failed to delete {}", log.path().display()))?;
+            println!("Cleared {}", log.path().display());
+            Ok(())
+        }
+    };
+    result.map(|()| output::Outcome::Success)
 }
 
-fn make_storage(config: &ShabkaConfig) -> Result<Storage> {
-    create_backend(config).context("failed to create storage backend")
+/// Directory whose `.shabka/` config layers apply: the nearest ancestor of
+/// the cwd that has one, else the cwd itself.
+fn project_dir() -> Option<std::path::PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    Some(config::find_project_root(&cwd).unwrap_or(cwd))
 }
 
-/// Format HelixDB connection errors with a user-friendly message.
-fn format_helix_error(err: &anyhow::Error, config: &ShabkaConfig) -> String {
-    let msg = format!("{:#}", err);
-    let is_connection = msg.contains("connection refused")
-        || msg.contains("Connection refused")
-        || msg.contains("timed out")
-        || msg.contains("connect error")
-        || msg.contains("dns error")
-        || msg.contains("No connection");
-    if is_connection {
-        format!(
-            "{}\n\n  Cannot connect to HelixDB at {}:{}.\n  Make sure HelixDB is running: {}\n",
-            "Error: HelixDB unavailable".red(),
-            config.helix.url,
-            config.helix.port,
-            "just db".cyan()
-        )
-    } else {
-        format!("{}", err)
+fn make_storage(services: &Services) -> Result<Arc<Storage>> {
+    services
+        .storage()
+        .context("failed to create storage backend")
+}
+
+fn make_embedder(services: &Services) -> Result<Arc<EmbeddingService>> {
+    services
+        .embedding()
+        .context("failed to create embedding service")
+}
+
+/// Render a command error for the terminal: the error chain, plus a hint
+/// when a [`ShabkaError`] in the chain has a known fix.
+fn format_error(err: &anyhow::Error) -> String {
+    let shabka = err.chain().find_map(|e| e.downcast_ref::<ShabkaError>());
+    match shabka.and_then(ShabkaError::hint) {
+        Some(hint) => format!("{} {err:#}\n  {} {hint}", "Error:".red(), "hint:".dimmed()),
+        None => format!("Error: {err:?}"),
     }
 }
 
@@ -588,6 +2073,12 @@ async fn check_provider_prereqs(provider: &str) {
                 println!("  {} GEMINI_API_KEY is set", "OK:".green());
             }
         }
+        "exec" => {
+            println!(
+                "  {} set embedding.command and embedding.dimensions in .shabka/config.toml",
+                "NOTE:".yellow()
+            );
+        }
         _ => {}
     }
 }
@@ -638,6 +2129,10 @@ async fn cmd_init(provider: &str, check_only: bool) -> Result<()> {
             "text-embedding-004".to_string(),
             "# Set GEMINI_API_KEY env var\n",
         ),
+        "exec" => (
+            String::new(),
+            "# Set embedding.command (text on stdin, JSON vector on stdout) and embedding.dimensions\n",
+        ),
         _ => (
             "hash-128d".to_string(),
             "# Deterministic hashing, no semantic search (for testing)\n",
@@ -680,7 +2175,11 @@ async fn cmd_init(provider: &str, check_only: bool) -> Result<()> {
     println!(
         "  {} {}",
         "Note:".dimmed(),
-        "SQLite is the default storage — no HelixDB needed.".dimmed()
+        format!(
+            "SQLite is the default storage {} no HelixDB needed.",
+            style::DASH
+        )
+        .dimmed()
     );
     println!("  1. Run MCP server:  {}", "just mcp".cyan());
     println!(
@@ -696,120 +2195,111 @@ async fn cmd_init(provider: &str, check_only: bool) -> Result<()> {
 // search
 // ---------------------------------------------------------------------------
 
-#[allow(clippy::too_many_arguments)]
-async fn cmd_search(
-    storage: &Storage,
-    embedder: &EmbeddingService,
-    user_id: &str,
+/// How `search` reports its results.
+#[derive(Debug, Clone, Copy, Default)]
+struct SearchOutput {
+    json: bool,
+    jsonl: bool,
+    /// Print nothing; only the exit code says whether anything matched.
+    quiet: bool,
+}
+
+/// The search a `search` or `context-pack` invocation describes, before
+/// limits and visibility.
+fn search_request(
     query: &str,
     kind: Option<String>,
-    limit: Option<usize>,
     tags: Option<Vec<String>>,
     project: Option<String>,
-    json: bool,
-    token_budget: Option<usize>,
-) -> Result<()> {
-    let limit = limit.unwrap_or(10);
-    let kind_filter: Option<MemoryKind> = match &kind {
+    exclude: &ExcludeArgs,
+) -> Result<SearchRequest> {
+    let kind: Option<MemoryKind> = match &kind {
         Some(k) => Some(k.parse().map_err(|e: String| anyhow::anyhow!("{}", e))?),
         None => None,
     };
-    let tag_filter: Vec<String> = tags.unwrap_or_default();
+    let query = exclude.apply(
+        SearchQuery::parse(query)?
+            .with_kind(kind)
+            .with_tags(tags.unwrap_or_default())
+            .with_project(project),
+    )?;
+    Ok(SearchRequest::new(query))
+}
 
-    // Embed query
-    let embedding = embedder
-        .embed(query)
+async fn cmd_search(
+    storage: &Storage,
+    embedder: &EmbeddingService,
+    questions: &QuestionLog,
+    user_id: &str,
+    request: SearchRequest,
+    out: SearchOutput,
+) -> Result<bool> {
+    let request = request.visible_to(user_id);
+    let SearchResults {
+        ranked, conflicts, ..
+    } = retrieval::search(storage, embedder, &request)
         .await
-        .context("failed to embed query")?;
+        .context("search failed")?;
 
-    // Fetch candidates (over-fetch to allow post-filtering)
-    let mut candidates = storage
-        .vector_search(&embedding, limit * 3)
-        .await
-        .context("vector search failed")?;
+    // An error, its fix and its lesson share one slot of the limit.
+    let ranked_ids: Vec<Uuid> = ranked.iter().map(|r| r.memory.id).collect();
+    let mut groups = graph::chain_groups(storage, &ranked_ids).await;
+    groups.truncate(request.limit);
+    let results: Vec<MemoryIndex> = groups
+        .iter()
+        .flat_map(|g| std::iter::once(g.primary).chain(g.members.iter().copied()))
+        .map(|i| MemoryIndex::from((&ranked[i].memory, ranked[i].score)))
+        .collect();
+    questions.record(
+        &Question::new(&request.query.text, "cli", user_id)
+            .with_project(request.query.project.clone())
+            .with_scores(results.iter().map(|r| r.score)),
+    );
 
-    // Filter by privacy
-    sharing::filter_search_results(&mut candidates, user_id);
+    // Apply token budget if set
+    let mut results = match request.token_budget {
+        Some(budget) => ranking::budget_truncate(results, budget),
+        None => results,
+    };
+    retrieval::mark_conflicts(&mut results, &conflicts);
+    telemetry::count(shabka_core::telemetry::RESULTS, results.len());
 
-    // Get relation counts for ranking
-    let memory_ids: Vec<Uuid> = candidates.iter().map(|(m, _)| m.id).collect();
-    let counts = storage
-        .count_relations(&memory_ids)
-        .await
-        .unwrap_or_default();
-    let count_map: HashMap<Uuid, usize> = counts.into_iter().collect();
-
-    let contradiction_counts = storage
-        .count_contradictions(&memory_ids)
-        .await
-        .unwrap_or_default();
-    let contradiction_map: HashMap<Uuid, usize> = contradiction_counts.into_iter().collect();
-
-    // Build rank candidates, applying kind/tag/project filters
-    let rank_candidates: Vec<RankCandidate> = candidates
-        .into_iter()
-        .filter(|(m, _)| {
-            if let Some(ref kf) = kind_filter {
-                if m.kind != *kf {
-                    return false;
-                }
-            }
-            if !tag_filter.is_empty() && !tag_filter.iter().any(|t| m.tags.contains(t)) {
-                return false;
-            }
-            if let Some(ref p) = project {
-                if m.project_id.as_deref() != Some(p.as_str()) {
-                    return false;
-                }
-            }
-            true
-        })
-        .map(|(memory, vector_score)| {
-            let kw_score = ranking::keyword_score(query, &memory);
-            RankCandidate {
-                relation_count: count_map.get(&memory.id).copied().unwrap_or(0),
-                keyword_score: kw_score,
-                contradiction_count: contradiction_map.get(&memory.id).copied().unwrap_or(0),
-                memory,
-                vector_score,
-            }
-        })
-        .collect();
-
-    let ranked = ranking::rank(rank_candidates, &RankingWeights::default());
-    let results: Vec<MemoryIndex> = ranked
-        .into_iter()
-        .take(limit)
-        .map(|r| MemoryIndex::from((&r.memory, r.score)))
-        .collect();
-
-    // Apply token budget if set
-    let results = match token_budget {
-        Some(budget) => ranking::budget_truncate(results, budget),
-        None => results,
-    };
+    let found = !results.is_empty();
+    if out.quiet {
+        return Ok(found);
+    }
+    if out.jsonl {
+        return output::print_jsonl(&results).map(|()| found);
+    }
 
     if results.is_empty() {
-        if json {
+        if out.json {
             println!("[]");
         } else {
-            println!("{}", "No results found.".dimmed());
+            println!("{}", t!("no-results").dimmed());
         }
-        return Ok(());
+        return Ok(false);
     }
 
-    if json {
+    if out.json {
         println!("{}", serde_json::to_string_pretty(&results)?);
     } else {
         // Table output
         println!(
-            "{:<12} {:<12} {:<6} {}",
-            "ID".dimmed(),
-            "Kind".dimmed(),
-            "Score".dimmed(),
-            "Title".dimmed()
+            "{:<12} {:<12} {:<6} {:<9} {}",
+            t!("column-id").dimmed(),
+            t!("column-kind").dimmed(),
+            t!("column-score").dimmed(),
+            t!("column-created").dimmed(),
+            t!("column-title").dimmed()
         );
-        for r in &results {
+        // Chain members go under their group's leader; the token budget may
+        // have cut some of them.
+        let shown: HashMap<Uuid, &MemoryIndex> = results.iter().map(|r| (r.id, r)).collect();
+        for group in &groups {
+            let Some(r) = shown.get(&ranked[group.primary].memory.id) else {
+                continue;
+            };
             let short_id = &r.id.to_string()[..8];
             let score_color = if r.score >= 0.7 {
                 format!("{:<6.2}", r.score).green().to_string()
@@ -819,108 +2309,156 @@ async fn cmd_search(
                 format!("{:<6.2}", r.score).red().to_string()
             };
             println!(
-                "{:<12} {:<12} {} {}",
+                "{:<12} {:<12} {} {} {}{}",
                 short_id.cyan(),
                 r.kind.to_string().magenta(),
                 score_color,
-                r.title
+                format!("{:<9}", dates::when(r.created_at)).dimmed(),
+                r.title,
+                contradiction_marker(r)
             );
+            for member in &group.members {
+                let Some(m) = shown.get(&ranked[*member].memory.id) else {
+                    continue;
+                };
+                println!(
+                    "{:<12} {:<12} {:<6} {} {} {}{}",
+                    "",
+                    m.kind.to_string().magenta(),
+                    "",
+                    format!("{:<9}", dates::when(m.created_at)).dimmed(),
+                    format!("{} {}", style::NESTED, &m.id.to_string()[..8]).dimmed(),
+                    m.title,
+                    contradiction_marker(m)
+                );
+            }
         }
     }
 
-    Ok(())
+    Ok(true)
+}
+
+/// `  ⚠ contradicts 1a2b3c4d` after a result that conflicts with others shown.
+fn contradiction_marker(entry: &MemoryIndex) -> String {
+    if entry.contradicts.is_empty() {
+        return String::new();
+    }
+    let ids: Vec<String> = entry
+        .contradicts
+        .iter()
+        .map(|id| id.to_string()[..8].to_string())
+        .collect();
+    format!(
+        "  {}",
+        format!("{} contradicts {}", style::WARN, ids.join(", ")).red()
+    )
 }
 
 // ---------------------------------------------------------------------------
-// context-pack
+// add
 // ---------------------------------------------------------------------------
 
 #[allow(clippy::too_many_arguments)]
-async fn cmd_context_pack(
+async fn cmd_add(
     storage: &Storage,
     embedder: &EmbeddingService,
+    history: &HistoryLogger,
     user_id: &str,
-    query: &str,
-    token_budget: usize,
+    title: &str,
+    content: &str,
+    kind: &str,
+    tags: Vec<String>,
+    importance: f32,
     project: Option<String>,
-    kind: Option<String>,
-    tags: Option<Vec<String>>,
+    privacy: MemoryPrivacy,
+    label: Option<String>,
     json: bool,
-    output: Option<String>,
 ) -> Result<()> {
-    use shabka_core::context_pack::{build_context_pack, format_context_pack};
+    let kind: MemoryKind = kind.parse().map_err(|e: String| anyhow::anyhow!("{}", e))?;
+    let content = content.trim_end();
+    if content.trim().is_empty() {
+        anyhow::bail!("content cannot be empty (pass it as an argument or pipe it on stdin)");
+    }
+    shabka_core::model::validate_create_input(title, content, importance)?;
 
-    let kind_filter: Option<MemoryKind> = match &kind {
-        Some(k) => Some(k.parse().map_err(|e: String| anyhow::anyhow!("{}", e))?),
-        None => None,
-    };
-    let tag_filter: Vec<String> = tags.unwrap_or_default();
+    let mut memory = Memory::new(
+        title.to_string(),
+        content.to_string(),
+        kind,
+        user_id.to_string(),
+    )
+    .with_tags(tags)
+    .with_importance(importance)
+    .with_privacy(privacy)
+    .with_label(label);
+    if let Some(p) = project {
+        memory = memory.with_project(p);
+    }
+    devices::stamp(&mut memory);
 
-    // Wide search for candidates
-    let search_query = if query.is_empty() { "*" } else { query };
     let embedding = embedder
-        .embed(search_query)
+        .embed(&memory.embedding_text())
         .await
-        .context("failed to embed query")?;
-
-    let mut candidates = storage
-        .vector_search(&embedding, 50)
+        .context("failed to embed memory")?;
+    storage
+        .save_memory(&memory, Some(&embedding))
         .await
-        .context("vector search failed")?;
+        .context("failed to save memory")?;
+
+    history.log(
+        &MemoryEvent::new(memory.id, EventAction::Created, user_id.to_string())
+            .with_title(&memory.title),
+    );
 
-    // Filter by privacy
-    sharing::filter_search_results(&mut candidates, user_id);
+    if json {
+        let out = output::AddOutput {
+            id: memory.id,
+            title: memory.title,
+            kind: memory.kind,
+        };
+        println!("{}", serde_json::to_string_pretty(&out)?);
+    } else {
+        println!(
+            "{} {} ({}) [{}]",
+            "Added:".green(),
+            memory.title,
+            memory.id.to_string()[..8].to_string().cyan(),
+            memory.kind.to_string().magenta()
+        );
+    }
+    Ok(())
+}
 
-    // Get relation counts for ranking
-    let memory_ids: Vec<Uuid> = candidates.iter().map(|(m, _)| m.id).collect();
-    let counts = storage
-        .count_relations(&memory_ids)
-        .await
-        .unwrap_or_default();
-    let count_map: HashMap<Uuid, usize> = counts.into_iter().collect();
+// ---------------------------------------------------------------------------
+// context-pack
+// ---------------------------------------------------------------------------
 
-    let contradiction_counts = storage
-        .count_contradictions(&memory_ids)
-        .await
-        .unwrap_or_default();
-    let contradiction_map: HashMap<Uuid, usize> = contradiction_counts.into_iter().collect();
+/// Candidates ranked before packing, whatever the budget.
+const CONTEXT_PACK_CANDIDATES: usize = 50;
 
-    // Build rank candidates, applying filters
-    let rank_candidates: Vec<RankCandidate> = candidates
-        .into_iter()
-        .filter(|(m, _)| {
-            if let Some(ref kf) = kind_filter {
-                if m.kind != *kf {
-                    return false;
-                }
-            }
-            if !tag_filter.is_empty() && !tag_filter.iter().any(|t| m.tags.contains(t)) {
-                return false;
-            }
-            if let Some(ref p) = project {
-                if m.project_id.as_deref() != Some(p.as_str()) {
-                    return false;
-                }
-            }
-            true
-        })
-        .map(|(memory, vector_score)| {
-            let kw_score = ranking::keyword_score(search_query, &memory);
-            RankCandidate {
-                relation_count: count_map.get(&memory.id).copied().unwrap_or(0),
-                keyword_score: kw_score,
-                contradiction_count: contradiction_map.get(&memory.id).copied().unwrap_or(0),
-                memory,
-                vector_score,
-            }
-        })
-        .collect();
+async fn cmd_context_pack(
+    storage: &Storage,
+    embedder: &EmbeddingService,
+    request: SearchRequest,
+    token_budget: usize,
+    json: bool,
+    output: Option<String>,
+) -> Result<()> {
+    use shabka_core::context_pack::{
+        build_context_pack, format_context_pack, format_context_pack_stable,
+    };
 
-    let ranked = ranking::rank(rank_candidates, &RankingWeights::default());
-    let memories: Vec<Memory> = ranked.into_iter().map(|r| r.memory).collect();
+    // Wide search for candidates
+    let request = request.fetch_limit(CONTEXT_PACK_CANDIDATES);
+    let results = retrieval::search(storage, embedder, &request)
+        .await
+        .context("search failed")?;
+    let memories: Vec<Memory> = results.ranked.into_iter().map(|r| r.memory).collect();
 
     // Build context pack
-    let pack = build_context_pack(memories, token_budget, project.clone());
+    let pack = build_context_pack(memories, token_budget, request.query.project)
+        .with_trust(&results.contradiction_counts);
+    telemetry::count(shabka_core::telemetry::RESULTS, pack.memories.len());
 
     if pack.memories.is_empty() {
         eprintln!("{}", "No memories fit within the token budget.".dimmed());
@@ -930,6 +2468,8 @@ async fn cmd_context_pack(
     // Format output
     let text = if json {
         serde_json::to_string_pretty(&pack)?
+    } else if output::stable() {
+        format_context_pack_stable(&pack)
     } else {
         format_context_pack(&pack)
     };
@@ -959,36 +2499,137 @@ async fn cmd_context_pack(
 // ---------------------------------------------------------------------------
 
 /// Resolve a memory ID from a full UUID or short prefix.
+/// Most candidates a reference lookup returns (and a picker shows).
+const MAX_ID_CANDIDATES: usize = 10;
+
+/// Resolve a memory reference: a full UUID, a short ID prefix, or words from
+/// the title (`shabka get 'pool exhaustion'`).
 async fn resolve_memory_id(storage: &Storage, id: &str) -> Result<Uuid> {
-    if id.len() < 32 {
-        let entries = storage
-            .timeline(&TimelineQuery {
-                limit: 10000,
-                ..Default::default()
-            })
+    resolve_reference(storage, id, None).await
+}
+
+/// Like [`resolve_memory_id`], but only considers memories with `status`.
+async fn resolve_reference(
+    storage: &Storage,
+    reference: &str,
+    status: Option<MemoryStatus>,
+) -> Result<Uuid> {
+    let reference = reference.trim();
+    if let Ok(id) = Uuid::parse_str(reference) {
+        return Ok(id);
+    }
+    if reference.is_empty() {
+        anyhow::bail!("memory ID cannot be empty");
+    }
+    let keep = |entries: Vec<TimelineEntry>| -> Vec<TimelineEntry> {
+        entries
+            .into_iter()
+            .filter(|e| !matches!(status, Some(s) if e.status != s))
+            .collect()
+    };
+
+    if reference.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+        let matches = keep(
+            storage
+                .find_by_id_prefix(reference, MAX_ID_CANDIDATES)
+                .await
+                .context("failed to look up memory ID")?,
+        );
+        if !matches.is_empty() {
+            return pick_memory(reference, &matches);
+        }
+    }
+
+    let matches = keep(
+        storage
+            .find_by_title(reference, MAX_ID_CANDIDATES)
             .await
-            .context("failed to fetch timeline")?;
-        let matches: Vec<_> = entries
-            .iter()
-            .filter(|e| e.id.to_string().starts_with(id))
-            .collect();
-        match matches.len() {
-            0 => anyhow::bail!("no memory found matching prefix '{id}'"),
-            1 => Ok(matches[0].id),
-            n => {
-                anyhow::bail!("ambiguous prefix '{id}' matches {n} memories. Use a longer prefix.")
-            }
+            .context("failed to look up memory title")?,
+    );
+    match matches.as_slice() {
+        [] => anyhow::bail!("no memory found matching '{reference}'"),
+        // An exact title match wins over looser ones
+        [first, second, ..]
+            if first.title.eq_ignore_ascii_case(reference)
+                && !second.title.eq_ignore_ascii_case(reference) =>
+        {
+            Ok(first.id)
         }
-    } else {
-        Uuid::parse_str(id).context("invalid memory ID")
+        _ => pick_memory(reference, &matches),
     }
 }
 
+/// Pick one of several matches: a numbered prompt on a terminal, an error
+/// listing the candidates otherwise (scripts must not block on input).
+fn pick_memory(reference: &str, matches: &[TimelineEntry]) -> Result<Uuid> {
+    use std::io::IsTerminal;
+
+    if let [only] = matches {
+        return Ok(only.id);
+    }
+    if !(std::io::stdin().is_terminal() && std::io::stderr().is_terminal()) {
+        let list: Vec<String> = matches.iter().map(format_candidate).collect();
+        anyhow::bail!(
+            "'{reference}' matches {} memories:\n{}\nUse a longer ID prefix or the full ID.",
+            matches.len(),
+            list.join("\n")
+        );
+    }
+    eprintln!(
+        "{} matches {} memories:",
+        format!("'{reference}'").bold(),
+        matches.len()
+    );
+    prompt_choice(matches, std::io::stdin().lock(), &mut std::io::stderr())
+}
+
+/// Print numbered candidates to `out` and read a 1-based choice from `input`.
+fn prompt_choice(
+    matches: &[TimelineEntry],
+    mut input: impl std::io::BufRead,
+    out: &mut impl std::io::Write,
+) -> Result<Uuid> {
+    for (i, entry) in matches.iter().enumerate() {
+        writeln!(out, "  {:>2}) {}", i + 1, format_candidate(entry))?;
+    }
+    write!(out, "Select [1-{}]: ", matches.len())?;
+    out.flush()?;
+
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    let choice: usize = line
+        .trim()
+        .parse()
+        .map_err(|_| anyhow::anyhow!("no memory selected"))?;
+    matches
+        .get(choice.wrapping_sub(1))
+        .map(|e| e.id)
+        .ok_or_else(|| anyhow::anyhow!("selection out of range: {choice}"))
+}
+
+fn format_candidate(entry: &TimelineEntry) -> String {
+    format!(
+        "{}  {:<11}  {:<10}  {}",
+        &entry.id.to_string()[..8],
+        entry.kind.to_string(),
+        dates::when(entry.created_at),
+        entry.title
+    )
+}
+
 // ---------------------------------------------------------------------------
 // get
 // ---------------------------------------------------------------------------
 
-async fn cmd_get(storage: &Storage, id: &str, json: bool) -> Result<()> {
+/// `get --expand-sources --json` output: the memory plus its sources.
+#[derive(serde::Serialize)]
+struct MemoryWithSources {
+    #[serde(flatten)]
+    memory: Memory,
+    sources: Vec<Memory>,
+}
+
+async fn cmd_get(storage: &Storage, id: &str, expand_sources: bool, json: bool) -> Result<()> {
     let memory_id = resolve_memory_id(storage, id).await?;
 
     let memory = storage
@@ -997,7 +2638,13 @@ async fn cmd_get(storage: &Storage, id: &str, json: bool) -> Result<()> {
         .context("memory not found")?;
 
     if json {
-        println!("{}", serde_json::to_string_pretty(&memory)?);
+        if expand_sources {
+            let sources = shabka_core::consolidate::sources(storage, &memory).await?;
+            let expanded = MemoryWithSources { memory, sources };
+            println!("{}", serde_json::to_string_pretty(&expanded)?);
+        } else {
+            println!("{}", serde_json::to_string_pretty(&memory)?);
+        }
         return Ok(());
     }
 
@@ -1021,23 +2668,35 @@ async fn cmd_get(storage: &Storage, id: &str, json: bool) -> Result<()> {
     println!(
         "  {}  {}",
         "Created:".dimmed(),
-        memory.created_at.format("%Y-%m-%d %H:%M:%S")
+        dates::datetime_ago(memory.created_at)
     );
     println!(
         "  {}  {}",
         "Updated:".dimmed(),
-        memory.updated_at.format("%Y-%m-%d %H:%M:%S")
+        dates::datetime_ago(memory.updated_at)
     );
     println!(
         "  {}  {}",
         "Accessed:".dimmed(),
-        memory.accessed_at.format("%Y-%m-%d %H:%M:%S")
+        dates::datetime_ago(memory.accessed_at)
     );
     println!("  {}  {}", "Privacy:".dimmed(), memory.privacy);
+    if let Some(label) = &memory.label {
+        println!("  {}  {}", "Label:".dimmed(), label.yellow());
+    }
     println!("  {}  {}", "Created by:".dimmed(), memory.created_by);
     if !memory.tags.is_empty() {
         println!("  {}  {}", "Tags:".dimmed(), memory.tags.join(", ").cyan());
     }
+    for a in &memory.attachments {
+        println!(
+            "  {}  {} ({} bytes, {})",
+            "Attachment:".dimmed(),
+            a.name.cyan(),
+            a.size,
+            &a.sha256[..12]
+        );
+    }
 
     // Compute trust score
     let relations = storage.get_relations(memory_id).await.unwrap_or_default();
@@ -1048,7 +2707,74 @@ async fn cmd_get(storage: &Storage, id: &str, json: bool) -> Result<()> {
     let trust = shabka_core::trust::trust_score(&memory, contradiction_count);
 
     println!("  {}  {}", "Verification:".dimmed(), memory.verification);
+    if let Some(ref dispute) = memory.dispute {
+        println!(
+            "  {}  by {} on {}",
+            "Disputed:".dimmed(),
+            dispute.disputed_by,
+            dates::date(dispute.disputed_at)
+        );
+        if let Some(ref reason) = dispute.reason {
+            println!("  {}  {}", "Reason:".dimmed(), reason.yellow());
+        }
+        if let Some(evidence_id) = dispute.evidence_id {
+            let title = storage
+                .get_memory(evidence_id)
+                .await
+                .map(|m| m.title)
+                .unwrap_or_else(|_| "(deleted)".to_string());
+            println!(
+                "  {}  {} {}",
+                "Evidence:".dimmed(),
+                evidence_id.to_string()[..8].to_string().cyan(),
+                title
+            );
+        }
+    }
+    if let Some(owner) = &memory.owner {
+        println!("  {}  {}", "Owner:".dimmed(), owner.cyan());
+    }
     println!("  {}  {:.0}%", "Trust:".dimmed(), trust * 100.0);
+    for a in &memory.assertions {
+        println!(
+            "  {}  {} via {} on {}",
+            "Asserted:".dimmed(),
+            a.asserted_by.cyan(),
+            a.channel,
+            dates::date(a.asserted_at)
+        );
+    }
+    if !memory.endorsements.is_empty() {
+        let endorsers: Vec<&str> = memory
+            .endorsements
+            .iter()
+            .map(|e| e.endorsed_by.as_str())
+            .collect();
+        println!(
+            "  {}  {} ({})",
+            "Endorsed:".dimmed(),
+            endorsers.len(),
+            endorsers.join(", ").cyan()
+        );
+    }
+
+    if let Some(ref code) = memory.code {
+        let location = [code.file.as_deref(), code.symbol.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(&format!(" {} ", style::CHEVRON));
+        println!();
+        println!(
+            "{} {} {}",
+            "--- Code ---".dimmed(),
+            code.language.as_deref().unwrap_or("text").cyan(),
+            location.dimmed()
+        );
+        for line in code.snippet.lines() {
+            println!("  {line}");
+        }
+    }
 
     // Relations
     if !relations.is_empty() {
@@ -1081,6 +2807,43 @@ async fn cmd_get(storage: &Storage, id: &str, json: bool) -> Result<()> {
         }
     }
 
+    // Sources of a consolidated summary
+    let source_ids = shabka_core::consolidate::source_ids(&memory, &relations);
+    if !source_ids.is_empty() && !expand_sources {
+        println!();
+        println!(
+            "{} {} memories; show them with {}",
+            "Consolidated from".dimmed(),
+            source_ids.len().to_string().cyan(),
+            format!(
+                "shabka get {} --expand-sources",
+                &memory_id.to_string()[..8]
+            )
+            .cyan()
+        );
+    } else if !source_ids.is_empty() {
+        let sources = storage.get_memories(&source_ids).await?;
+        println!();
+        println!(
+            "{} ({})",
+            "--- Sources ---".dimmed(),
+            sources.len().to_string().cyan()
+        );
+        for source in &sources {
+            println!();
+            println!(
+                "  {} {} {} {}",
+                source.id.to_string()[..8].to_string().cyan(),
+                source.title.bold(),
+                source.kind.to_string().magenta(),
+                dates::date(source.created_at).dimmed()
+            );
+            for line in source.content.lines() {
+                println!("    {line}");
+            }
+        }
+    }
+
     Ok(())
 }
 
@@ -1088,85 +2851,406 @@ async fn cmd_get(storage: &Storage, id: &str, json: bool) -> Result<()> {
 // verify
 // ---------------------------------------------------------------------------
 
+#[allow(clippy::too_many_arguments)]
 async fn cmd_verify(
     storage: &Storage,
     history: &HistoryLogger,
+    retention: &decay::RetentionConfig,
     user_id: &str,
     id_str: &str,
     status_str: &str,
+    reason: Option<String>,
+    evidence: Option<&str>,
 ) -> Result<()> {
     let id = resolve_memory_id(storage, id_str).await?;
     let verification: VerificationStatus =
         status_str.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+    if verification != VerificationStatus::Disputed && (reason.is_some() || evidence.is_some()) {
+        anyhow::bail!("--reason and --evidence only apply with --status disputed");
+    }
+    let evidence_id = match evidence {
+        Some(evidence) => {
+            let evidence_id = resolve_memory_id(storage, evidence).await?;
+            if evidence_id == id {
+                anyhow::bail!("a memory can't be its own counter-evidence");
+            }
+            Some(evidence_id)
+        }
+        None => None,
+    };
 
     let old_memory = storage.get_memory(id).await.context("memory not found")?;
+    let dispute = (verification == VerificationStatus::Disputed).then(|| {
+        Dispute::new(user_id.to_string())
+            .with_reason(reason)
+            .with_evidence(evidence_id)
+    });
 
     let input = UpdateMemoryInput {
         verification: Some(verification),
+        verification_expires_at: shabka_core::trust::verification_expiry(
+            verification,
+            old_memory.kind,
+            retention,
+            chrono::Utc::now(),
+        ),
+        dispute,
         ..Default::default()
     };
 
     let memory = storage.update_memory(id, &input).await?;
 
+    if let Some(evidence_id) = evidence_id {
+        storage
+            .add_relation(&MemoryRelation {
+                source_id: evidence_id,
+                target_id: id,
+                relation_type: RelationType::Contradicts,
+                strength: 1.0,
+            })
+            .await
+            .context("failed to link counter-evidence")?;
+    }
+
+    let mut changes = vec![shabka_core::history::FieldChange {
+        field: "verification".to_string(),
+        old_value: old_memory.verification.to_string(),
+        new_value: verification.to_string(),
+    }];
+    if let Some(reason) = memory.dispute.as_ref().and_then(|d| d.reason.clone()) {
+        changes.push(shabka_core::history::FieldChange {
+            field: "dispute_reason".to_string(),
+            old_value: old_memory
+                .dispute
+                .and_then(|d| d.reason)
+                .unwrap_or_default(),
+            new_value: reason,
+        });
+    }
     history.log(
         &MemoryEvent::new(id, EventAction::Updated, user_id.to_string())
             .with_title(&memory.title)
-            .with_changes(vec![shabka_core::history::FieldChange {
-                field: "verification".to_string(),
-                old_value: old_memory.verification.to_string(),
-                new_value: verification.to_string(),
-            }]),
+            .with_changes(changes),
     );
 
     println!(
         "{} Memory '{}' marked as {}",
-        "✓".green(),
+        style::OK.green(),
         memory.title.bold(),
         verification.to_string().cyan()
     );
+    if let Some(evidence_id) = evidence_id {
+        println!(
+            "  Linked counter-evidence {} ({})",
+            evidence_id.to_string()[..8].to_string().cyan(),
+            RelationType::Contradicts
+        );
+    }
 
     Ok(())
 }
 
 // ---------------------------------------------------------------------------
-// update check
+// endorse
 // ---------------------------------------------------------------------------
 
-/// Check for a newer version on GitHub. Returns `Some(latest)` if an update
-/// is available, `None` otherwise. Never errors — all failures are silent.
-async fn check_for_update() -> Option<String> {
-    let current = env!("CARGO_PKG_VERSION");
-    let mut state = UpdateCheckState::load();
-
-    // If cache is fresh, use it
-    if !state.is_stale() && !state.latest_version.is_empty() {
-        let current_ver = semver::Version::parse(current).ok()?;
-        let latest_ver = semver::Version::parse(&state.latest_version).ok()?;
-        return if latest_ver > current_ver {
-            Some(state.latest_version)
+async fn cmd_endorse(storage: &Storage, user_id: &str, id_str: &str, withdraw: bool) -> Result<()> {
+    let id = resolve_memory_id(storage, id_str).await?;
+    let memory = if withdraw {
+        shabka_core::trust::withdraw_endorsement(storage, id, user_id).await?
+    } else {
+        shabka_core::trust::endorse(storage, id, user_id).await?
+    };
+    let count = memory.endorsements.len();
+    println!(
+        "{} {} '{}' ({} endorsement{})",
+        style::OK.green(),
+        if withdraw {
+            "Withdrew endorsement of"
         } else {
-            None
-        };
-    }
-
-    // Fetch from GitHub
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(2))
-        .user_agent(format!("shabka/{current}"))
-        .build()
-        .ok()?;
+            "Endorsed"
+        },
+        memory.title.bold(),
+        count,
+        if count == 1 { "" } else { "s" }
+    );
+    Ok(())
+}
 
-    let resp = client
-        .get("https://api.github.com/repos/mehdig-dev/shabka/releases/latest")
-        .header("Accept", "application/vnd.github+json")
-        .send()
-        .await
-        .ok()?;
+// ---------------------------------------------------------------------------
+// assign
+// ---------------------------------------------------------------------------
 
-    if !resp.status().is_success() {
-        return None;
+/// Set the owner of a memory, or clear it when `owner` is `None`.
+async fn cmd_assign(
+    storage: &Storage,
+    history: &HistoryLogger,
+    user_id: &str,
+    id_str: &str,
+    owner: Option<String>,
+) -> Result<()> {
+    if !storage.capabilities().owners {
+        anyhow::bail!("this storage backend doesn't store memory owners");
     }
-
+    let owner = owner.map(|o| o.trim().to_string()).unwrap_or_default();
+    let id = resolve_memory_id(storage, id_str).await?;
+    let old_memory = storage.get_memory(id).await.context("memory not found")?;
+    let input = UpdateMemoryInput {
+        owner: Some(owner.clone()),
+        ..Default::default()
+    };
+    let memory = storage.update_memory(id, &input).await?;
+    history.log(
+        &MemoryEvent::new(id, EventAction::Updated, user_id.to_string())
+            .with_title(&memory.title)
+            .with_changes(shabka_core::history::diff_update(&old_memory, &input)),
+    );
+
+    if owner.is_empty() {
+        println!("{} Unassigned '{}'", style::OK.green(), memory.title.bold());
+    } else {
+        println!(
+            "{} Assigned '{}' to {}",
+            style::OK.green(),
+            memory.title.bold(),
+            owner.cyan()
+        );
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// cite-check
+// ---------------------------------------------------------------------------
+
+/// Check the citations in `answer` and report them. Returns whether any
+/// citation was flagged.
+async fn cmd_cite_check(
+    storage: &Storage,
+    embedder: Option<&EmbeddingService>,
+    user_id: &str,
+    answer: &str,
+    quiet: bool,
+    json: bool,
+) -> Result<bool> {
+    let checks = citations::verify_citations(storage, embedder, user_id, answer)
+        .await
+        .context("failed to check citations")?;
+    let flagged = checks.iter().filter(|c| c.is_flagged()).count();
+    if quiet {
+        return Ok(flagged > 0);
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&checks)?);
+        return Ok(flagged > 0);
+    }
+    if checks.is_empty() {
+        println!("{}", "No memory citations found.".dimmed());
+        return Ok(false);
+    }
+
+    println!(
+        "{} {} checked, {} flagged",
+        "Citations:".bold(),
+        checks.len(),
+        flagged
+    );
+    for check in &checks {
+        let verdict = format!("{:<11}", check.verdict.as_str());
+        let verdict = match check.verdict {
+            citations::CitationVerdict::Supported => verdict.green().to_string(),
+            citations::CitationVerdict::Unsupported | citations::CitationVerdict::Inactive => {
+                verdict.yellow().to_string()
+            }
+            citations::CitationVerdict::Missing | citations::CitationVerdict::Ambiguous => {
+                verdict.red().to_string()
+            }
+        };
+        let reference: String = check.reference.chars().take(8).collect();
+        println!(
+            "  {} {:<8}  {}",
+            verdict,
+            reference.cyan(),
+            check.title.as_deref().unwrap_or("-")
+        );
+        if !check.claim.is_empty() {
+            println!("  {:<11} {:<8}  {}", "", "", check.claim.dimmed());
+        }
+    }
+
+    Ok(flagged > 0)
+}
+
+// ---------------------------------------------------------------------------
+// label
+// ---------------------------------------------------------------------------
+
+/// Show a memory's label, or set it (an empty label clears it).
+async fn cmd_label(
+    storage: &Storage,
+    history: &HistoryLogger,
+    labels: &LabelConfig,
+    user_id: &str,
+    id: &str,
+    label: Option<String>,
+) -> Result<()> {
+    let id = resolve_memory_id(storage, id).await?;
+    let old_memory = storage.get_memory(id).await.context("memory not found")?;
+
+    let Some(label) = label else {
+        match &old_memory.label {
+            Some(label) => println!("{label}"),
+            None => println!("{}", "(no label)".dimmed()),
+        }
+        return Ok(());
+    };
+
+    let mut input = UpdateMemoryInput {
+        label: Some(label),
+        ..Default::default()
+    };
+    labels.constrain_update(&old_memory, &mut input);
+    let memory = storage
+        .update_memory(id, &input)
+        .await
+        .context("failed to update label")?;
+
+    history.log(
+        &MemoryEvent::new(id, EventAction::Updated, user_id.to_string())
+            .with_title(&memory.title)
+            .with_changes(shabka_core::history::diff_update(&old_memory, &input)),
+    );
+
+    match &memory.label {
+        Some(label) => println!(
+            "{} Memory '{}' labelled {}",
+            style::OK.green(),
+            memory.title.bold(),
+            label.cyan()
+        ),
+        None => println!(
+            "{} Removed the label from '{}'",
+            style::OK.green(),
+            memory.title.bold()
+        ),
+    }
+    if memory.privacy != old_memory.privacy {
+        println!(
+            "  Privacy lowered to {} (the label may not be shared)",
+            memory.privacy.to_string().yellow()
+        );
+    }
+    for boundary in [Boundary::Export, Boundary::Share, Boundary::Sync] {
+        if !labels.permits(&memory, boundary) {
+            println!("  {} {boundary} blocked by [labels.policies]", "-".dimmed());
+        }
+    }
+
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// attach
+// ---------------------------------------------------------------------------
+
+#[allow(clippy::too_many_arguments)]
+async fn cmd_attach(
+    storage: &Storage,
+    blobs: &BlobStore,
+    attachment_config: &AttachmentConfig,
+    history: &HistoryLogger,
+    user_id: &str,
+    id_str: &str,
+    file: &str,
+    name: Option<String>,
+) -> Result<()> {
+    let id = resolve_memory_id(storage, id_str).await?;
+    let bytes = if file == output::STDIO {
+        let mut buf = Vec::new();
+        std::io::Read::read_to_end(&mut std::io::stdin(), &mut buf)?;
+        buf
+    } else {
+        std::fs::read(file).with_context(|| format!("failed to read {file}"))?
+    };
+    let name = name.unwrap_or_else(|| {
+        std::path::Path::new(file)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "stdin".to_string())
+    });
+
+    let old_memory = storage.get_memory(id).await.context("memory not found")?;
+    let memory = attachments::attach(storage, blobs, attachment_config, id, &name, &bytes).await?;
+    let changes = shabka_core::history::diff_update(
+        &old_memory,
+        &UpdateMemoryInput {
+            attachments: Some(memory.attachments.clone()),
+            ..Default::default()
+        },
+    );
+    if changes.is_empty() {
+        println!(
+            "'{}' is already attached to '{}'",
+            name,
+            memory.title.bold()
+        );
+        return Ok(());
+    }
+    history.log(
+        &MemoryEvent::new(id, EventAction::Updated, user_id.to_string())
+            .with_title(&memory.title)
+            .with_changes(changes),
+    );
+
+    println!(
+        "{} Attached {} ({} bytes) to '{}'",
+        style::OK.green(),
+        name.cyan(),
+        bytes.len(),
+        memory.title.bold()
+    );
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// update check
+// ---------------------------------------------------------------------------
+
+/// Check for a newer version on GitHub. Returns `Some(latest)` if an update
+/// is available, `None` otherwise. Never errors — all failures are silent.
+async fn check_for_update() -> Option<String> {
+    let current = env!("CARGO_PKG_VERSION");
+    let mut state = UpdateCheckState::load();
+
+    // If cache is fresh, use it
+    if !state.is_stale() && !state.latest_version.is_empty() {
+        let current_ver = semver::Version::parse(current).ok()?;
+        let latest_ver = semver::Version::parse(&state.latest_version).ok()?;
+        return if latest_ver > current_ver {
+            Some(state.latest_version)
+        } else {
+            None
+        };
+    }
+
+    // Fetch from GitHub
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(2))
+        .user_agent(format!("shabka/{current}"))
+        .build()
+        .ok()?;
+
+    let resp = client
+        .get("https://api.github.com/repos/mehdig-dev/shabka/releases/latest")
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+        .ok()?;
+
+    if !resp.status().is_success() {
+        return None;
+    }
+
     let body: serde_json::Value = resp.json().await.ok()?;
     let tag = body["tag_name"].as_str()?;
     let tag_version = tag.strip_prefix('v').unwrap_or(tag);
@@ -1191,19 +3275,103 @@ async fn check_for_update() -> Option<String> {
 // status
 // ---------------------------------------------------------------------------
 
+/// How many runs `shabka status --maintenance` lists.
+const MAINTENANCE_HISTORY_LIMIT: usize = 20;
+
+async fn cmd_status_maintenance(storage: &Storage) -> Result<()> {
+    if !storage.capabilities().maintenance_log {
+        println!(
+            "Maintenance history is not kept by the {} backend.",
+            storage.backend_name()
+        );
+        return Ok(());
+    }
+    let runs = storage
+        .maintenance_runs(None, MAINTENANCE_HISTORY_LIMIT)
+        .await
+        .context("failed to read maintenance runs")?;
+
+    println!("{}", "Maintenance".bold());
+    for op in MaintenanceOp::ALL {
+        let last = storage.last_successful_run(op).await.ok().flatten();
+        let when = match last.and_then(|run| run.finished_at) {
+            Some(at) => format!(
+                "{} ({})",
+                dates::minutes(at),
+                dates::ago(at, chrono::Utc::now())
+            ),
+            None => "never".dimmed().to_string(),
+        };
+        println!("  {:<12} last success {}", format!("{op}:").dimmed(), when);
+    }
+
+    if runs.is_empty() {
+        println!("\n{}", "No maintenance runs recorded yet.".dimmed());
+        return Ok(());
+    }
+    println!("\n{}", "Recent runs".bold());
+    for run in &runs {
+        let outcome = if run.finished_at.is_none() {
+            "unfinished".yellow().to_string()
+        } else if run.errors.is_empty() {
+            "ok".green().to_string()
+        } else {
+            format!("{} errors", run.errors.len()).red().to_string()
+        };
+        let duration = run
+            .duration()
+            .map(|d| format!("{:.1}s", d.num_milliseconds() as f64 / 1000.0))
+            .unwrap_or_else(|| "-".to_string());
+        let counts = run
+            .counts
+            .iter()
+            .filter(|(_, n)| **n > 0)
+            .map(|(name, n)| format!("{n} {}", name.replace('_', " ")))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!(
+            "  {}  {:<12} {:>7}  {}  {}",
+            dates::minutes(run.started_at).dimmed(),
+            run.operation,
+            duration,
+            outcome,
+            counts.dimmed()
+        );
+        for error in run.errors.iter().take(3) {
+            println!("      {}", error.red());
+        }
+    }
+    Ok(())
+}
+
 async fn cmd_status(storage: &Storage, config: &ShabkaConfig, user_id: &str) -> Result<()> {
     let version = env!("CARGO_PKG_VERSION");
-    println!("{}", format!("Shabka Status v{version}").bold());
-    println!("  {}    {}", "Version:".dimmed(), version);
+    let stable = output::stable();
+    if stable {
+        println!("{}", "Shabka Status".bold());
+    } else {
+        println!("{}", format!("Shabka Status v{version}").bold());
+        println!("  {}    {}", "Version:".dimmed(), version);
+    }
     println!("  {}       {}", "User:".dimmed(), user_id);
 
     // Schema info (SQLite only)
     if let Some((schema_ver, writer_ver)) = storage.schema_info() {
         let writer = writer_ver
+            .filter(|_| !stable)
             .map(|v| format!(", last written by {v}"))
             .unwrap_or_default();
         println!("  {}   schema v{schema_ver}{writer}", "Database:".dimmed(),);
     }
+    let unavailable = storage.capabilities().unavailable();
+    if !unavailable.is_empty() {
+        println!(
+            "  {}    {} {}",
+            "Backend:".dimmed(),
+            storage.backend_name(),
+            format!("(unavailable: {})", unavailable.join(", ")).yellow()
+        );
+    }
 
     // Check HelixDB connectivity
     let timeline_result = storage
@@ -1296,6 +3464,48 @@ async fn cmd_status(storage: &Storage, config: &ShabkaConfig, user_id: &str) ->
         capture_status,
         config.capture.min_importance
     );
+    if config.capture.has_quota() {
+        let daily = config
+            .capture
+            .max_memories_per_day
+            .map(|n| format!("{n}/day"))
+            .unwrap_or_else(|| "unlimited".to_string());
+        println!(
+            "  {}     {} ({} project quota{})",
+            "Quotas:".dimmed(),
+            daily,
+            config.capture.project_quotas.len(),
+            if config.capture.project_quotas.len() == 1 {
+                ""
+            } else {
+                "s"
+            }
+        );
+    }
+    if config.llm.enabled {
+        let usage = TokenLedger::new().today();
+        let budget = config.llm.daily_token_budget;
+        let line = token_usage_line(usage.total, budget);
+        println!(
+            "  {} {}",
+            "LLM tokens:".dimmed(),
+            if usage.is_exhausted(budget) {
+                format!("{line}, exhausted (using heuristics)")
+                    .yellow()
+                    .to_string()
+            } else {
+                line
+            }
+        );
+    }
+    let backlog = shabka_core::quota::CaptureBacklog::new().len();
+    if backlog > 0 {
+        println!(
+            "  {}    {} held by capture quotas",
+            "Backlog:".dimmed(),
+            backlog.to_string().yellow()
+        );
+    }
     println!(
         "  {}    {} (default)",
         "Privacy:".dimmed(),
@@ -1309,7 +3519,7 @@ async fn cmd_status(storage: &Storage, config: &ShabkaConfig, user_id: &str) ->
     println!("  {}     {}", "Config:".dimmed(), config_path);
 
     // Check for updates (non-blocking, silent on failure)
-    if config.updates.check_for_updates {
+    if config.updates.check_for_updates && !stable {
         if let Some(latest) = check_for_update().await {
             println!();
             println!(
@@ -1328,23 +3538,40 @@ async fn cmd_status(storage: &Storage, config: &ShabkaConfig, user_id: &str) ->
 // export
 // ---------------------------------------------------------------------------
 
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 struct ExportData {
     memories: Vec<Memory>,
     relations: Vec<MemoryRelation>,
+    /// Attachment contents, present with `--attachments`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    blobs: Vec<ExportedBlob>,
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn cmd_export(
     storage: &Storage,
     output: &str,
+    jsonl: bool,
     privacy: &str,
+    labels: &LabelConfig,
     scrub_config: Option<&shabka_core::scrub::ScrubConfig>,
     scrub_report_only: bool,
+    blobs: Option<&BlobStore>,
 ) -> Result<()> {
     let threshold: MemoryPrivacy = privacy
         .parse()
         .map_err(|e: String| anyhow::anyhow!("{}", e))?;
 
+    // When the export itself goes to stdout, status lines go to stderr.
+    let to_stdout = output == output::STDIO;
+    let note = |msg: String| {
+        if to_stdout {
+            eprintln!("{msg}");
+        } else {
+            println!("{msg}");
+        }
+    };
+
     // Fetch all memories via timeline
     let entries = storage
         .timeline(&TimelineQuery {
@@ -1355,7 +3582,7 @@ async fn cmd_export(
         .context("failed to fetch timeline")?;
 
     if entries.is_empty() {
-        println!("No memories to export.");
+        note("No memories to export.".to_string());
         return Ok(());
     }
 
@@ -1370,8 +3597,18 @@ async fn cmd_export(
     // Filter by privacy threshold
     memories.retain(|m| sharing::should_export(m.privacy, threshold));
 
+    // Withhold labels whose policy forbids export
+    let before = memories.len();
+    memories.retain(|m| labels.permits(m, Boundary::Export));
+    let withheld = before - memories.len();
+    if withheld > 0 {
+        note(format!(
+            "Withheld {withheld} memories whose label forbids export."
+        ));
+    }
+
     if memories.is_empty() {
-        println!("No memories match privacy threshold '{}'.", privacy);
+        note(format!("No memories match privacy threshold '{privacy}'."));
         return Ok(());
     }
 
@@ -1394,8 +3631,9 @@ async fn cmd_export(
                 if found > 0 {
                     flagged += 1;
                     println!(
-                        "  {} — emails:{} keys:{} ips:{} paths:{}",
+                        "  {} {} emails:{} keys:{} ips:{} paths:{}",
                         &m.id.to_string()[..8],
+                        style::DASH,
                         report.emails_found,
                         report.api_keys_found,
                         report.ips_found,
@@ -1435,14 +3673,21 @@ async fn cmd_export(
             }
         }
         if scrubbed_count > 0 {
-            println!("PII scrubbed from {} memories.", scrubbed_count);
+            note(format!("PII scrubbed from {scrubbed_count} memories."));
         }
     }
 
     // Fetch all relations (only for exported memories)
     let exported_ids: std::collections::HashSet<Uuid> = memories.iter().map(|m| m.id).collect();
     let mut all_relations = Vec::new();
+    let interrupt = Interrupt::install();
+    let bar = progress::bar(memories.len(), "Collecting relations");
     for memory in &memories {
+        if interrupt.requested() {
+            bar.finish_and_clear();
+            anyhow::bail!("export interrupted; nothing was written");
+        }
+        bar.inc(1);
         if let Ok(rels) = storage.get_relations(memory.id).await {
             // Only include relations where both ends are in the export
             for r in rels {
@@ -1452,48 +3697,156 @@ async fn cmd_export(
             }
         }
     }
+    bar.finish_and_clear();
+
+    let exported_blobs = blobs
+        .map(|b| attachments::export_blobs(b, &memories))
+        .unwrap_or_default();
 
+    let memory_count = memories.len();
+    let relation_count = all_relations.len();
+    let blob_count = exported_blobs.len();
     let export = ExportData {
         memories,
         relations: all_relations,
+        blobs: exported_blobs,
     };
+    let body = render_export(export, jsonl, output::stable())?;
 
-    let json = serde_json::to_string_pretty(&export)?;
-    std::fs::write(output, json)?;
+    if to_stdout {
+        println!("{}", body.trim_end());
+    } else {
+        std::fs::write(output, body)?;
+    }
 
-    println!(
-        "Exported {} memories and {} relations to {} (privacy: {})",
-        export.memories.len(),
-        export.relations.len(),
-        output,
-        privacy
-    );
+    let blob_note = if blob_count > 0 {
+        format!(", {blob_count} attachments")
+    } else {
+        String::new()
+    };
+    note(format!(
+        "Exported {memory_count} memories and {relation_count} relations{blob_note} to {} (privacy: {privacy})",
+        if to_stdout { "stdout" } else { output }
+    ));
     Ok(())
 }
 
+/// Serialize an export as one JSON document or as JSON Lines. `stable`
+/// orders memories by creation time and relations by endpoints, rather than
+/// in whatever order storage returned them.
+fn render_export(mut export: ExportData, jsonl: bool, stable: bool) -> Result<String> {
+    if stable {
+        export.memories.sort_by_key(|m| (m.created_at, m.id));
+        export
+            .relations
+            .sort_by_key(|r| (r.source_id, r.target_id, r.relation_type.to_string()));
+        export.blobs.sort_by(|a, b| a.sha256.cmp(&b.sha256));
+    }
+    if !jsonl {
+        return Ok(serde_json::to_string_pretty(&export)?);
+    }
+    let records: Vec<output::ExportRecord> = export
+        .memories
+        .into_iter()
+        .map(|m| output::ExportRecord::Memory(Box::new(m)))
+        .chain(
+            export
+                .relations
+                .into_iter()
+                .map(output::ExportRecord::Relation),
+        )
+        .chain(export.blobs.into_iter().map(output::ExportRecord::Blob))
+        .collect();
+    let mut buf = Vec::new();
+    output::write_jsonl(&mut buf, &records)?;
+    Ok(String::from_utf8(buf)?)
+}
+
 // ---------------------------------------------------------------------------
 // import
 // ---------------------------------------------------------------------------
 
+/// Parse an export in either format: a single `ExportData` JSON document, or
+/// JSON Lines of `ExportRecord`s as written by `shabka export --jsonl`.
+fn parse_export(text: &str) -> Result<ExportData> {
+    if let Ok(data) = serde_json::from_str::<ExportData>(text) {
+        return Ok(data);
+    }
+
+    let mut data = ExportData {
+        memories: Vec::new(),
+        relations: Vec::new(),
+        blobs: Vec::new(),
+    };
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let record: output::ExportRecord = serde_json::from_str(line)
+            .with_context(|| format!("failed to parse export record on line {}", i + 1))?;
+        match record {
+            output::ExportRecord::Memory(m) => data.memories.push(*m),
+            output::ExportRecord::Relation(r) => data.relations.push(r),
+            output::ExportRecord::Blob(b) => data.blobs.push(b),
+        }
+    }
+    if data.memories.is_empty() && data.relations.is_empty() {
+        anyhow::bail!("failed to parse export file: no records found");
+    }
+    Ok(data)
+}
+
+/// Checkpoint name for interrupted `shabka import` runs.
+const IMPORT_CHECKPOINT: &str = "import";
+
 async fn cmd_import(
     storage: &Storage,
     embedder: &EmbeddingService,
     user_id: &str,
     path: &str,
     history: &HistoryLogger,
+    blobs: &BlobStore,
 ) -> Result<()> {
-    if !Path::new(path).exists() {
-        anyhow::bail!("file not found: {}", path);
-    }
+    let text = output::read_source(path)?;
+    let data = parse_export(&text)?;
 
-    let json = std::fs::read_to_string(path)?;
-    let data: ExportData = serde_json::from_str(&json).context("failed to parse export file")?;
+    for blob in &data.blobs {
+        if let Err(e) = attachments::import_blob(blobs, blob) {
+            eprintln!("{} skipping attachment: {e}", "warning:".yellow());
+        }
+    }
 
     let mut imported_memories = 0;
     let mut imported_relations = 0;
     let mut skipped_test = 0;
 
+    // Memories an interrupted import of the same file already saved. Stdin
+    // can't be re-read, so it never resumes.
+    let checkpoint_key = format!("{path}:{}", text.len());
+    let mut checkpoint = if path == output::STDIO {
+        Checkpoint::default()
+    } else {
+        Checkpoint::load(IMPORT_CHECKPOINT, &checkpoint_key)
+    };
+    if !checkpoint.done.is_empty() {
+        println!(
+            "Resuming: {} memories were imported before the last interruption",
+            checkpoint.done.len()
+        );
+    }
+
+    let interrupt = Interrupt::install();
+    let bar = progress::bar(data.memories.len(), "Importing");
     for memory in &data.memories {
+        if interrupt.requested() {
+            break;
+        }
+        bar.inc(1);
+        if checkpoint.done.contains(&memory.id) {
+            imported_memories += 1;
+            continue;
+        }
         // Skip test data (integration tests tag titles with [test-...])
         if memory.title.contains("[test-")
             || memory.created_by == "integration-test"
@@ -1520,8 +3873,30 @@ async fn cmd_import(
             &MemoryEvent::new(m.id, EventAction::Imported, user_id.to_string())
                 .with_title(&m.title),
         );
+        checkpoint.done.insert(m.id);
         imported_memories += 1;
     }
+    bar.finish_and_clear();
+
+    if interrupt.requested() {
+        if path != output::STDIO {
+            checkpoint.key = checkpoint_key;
+            checkpoint
+                .save(IMPORT_CHECKPOINT)
+                .context("failed to save import checkpoint")?;
+        }
+        println!(
+            "Interrupted after importing {imported_memories} of {} memories; relations were not imported.",
+            data.memories.len()
+        );
+        if path != output::STDIO {
+            println!("Run the same import again to continue.");
+        }
+        anyhow::bail!("import interrupted");
+    }
+    if path != output::STDIO {
+        Checkpoint::clear(IMPORT_CHECKPOINT);
+    }
 
     for relation in &data.relations {
         storage
@@ -1536,28 +3911,107 @@ async fn cmd_import(
     }
     println!(
         "Imported {} memories and {} relations from {}",
-        imported_memories, imported_relations, path
+        imported_memories,
+        imported_relations,
+        if path == output::STDIO { "stdin" } else { path }
     );
     Ok(())
 }
 
 // ---------------------------------------------------------------------------
-// chain
+// migrate-from-kaizen
 // ---------------------------------------------------------------------------
 
-async fn cmd_chain(
+enum KaizenSource {
+    Sqlite(std::path::PathBuf),
+    Helix(String, u16),
+}
+
+async fn cmd_migrate_from_kaizen(
     storage: &Storage,
-    id: &str,
-    relations: Option<Vec<String>>,
-    depth: usize,
+    embedder: &EmbeddingService,
+    history: &HistoryLogger,
+    source: KaizenSource,
+    history_path: Option<&std::path::Path>,
+    dry_run: bool,
     json: bool,
 ) -> Result<()> {
-    let start_id =
-        Uuid::parse_str(id).context("invalid memory ID (use full UUID or copy from search)")?;
-
-    // Parse relation types (default: all)
-    let relation_types: Vec<RelationType> = match relations {
-        Some(rels) => rels
+    let mut report = match source {
+        KaizenSource::Sqlite(path) => {
+            let store = kaizen::open_sqlite(&path)?;
+            let mut report = kaizen::migrate(&store.storage, storage, embedder, dry_run)
+                .await
+                .context("failed to migrate Kaizen memories")?;
+            report.unmapped = store.unmapped.clone();
+            report
+        }
+        // Kaizen's HelixDB schema is Shabka's, so every field maps.
+        KaizenSource::Helix(url, port) => {
+            let store = HelixStorage::new(Some(&url), Some(port), None);
+            kaizen::migrate(&store, storage, embedder, dry_run)
+                .await
+                .context("failed to migrate Kaizen memories")?
+        }
+    };
+    if let Some(path) = history_path {
+        (report.history_events, report.history_skipped) =
+            kaizen::migrate_history(path, history, dry_run)?;
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    let verb = if dry_run { "Would copy" } else { "Copied" };
+    println!(
+        "{verb} {} memories and {} relations from Kaizen",
+        report.memories, report.relations
+    );
+    if report.already_present > 0 {
+        println!(
+            "Skipped {} memories already in Shabka",
+            report.already_present
+        );
+    }
+    if history_path.is_some() {
+        println!("{verb} {} history events", report.history_events);
+        if report.history_skipped > 0 {
+            println!(
+                "{} skipped {} unreadable history lines",
+                "warning:".yellow(),
+                report.history_skipped
+            );
+        }
+    }
+    if !report.unmapped.is_empty() {
+        println!(
+            "{} these Kaizen fields have no Shabka equivalent and were not copied:",
+            "warning:".yellow()
+        );
+        for field in &report.unmapped {
+            println!("  {}.{}", field.table, field.column);
+        }
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// chain
+// ---------------------------------------------------------------------------
+
+async fn cmd_chain(
+    storage: &Storage,
+    id: &str,
+    relations: Option<Vec<String>>,
+    depth: usize,
+    max_nodes: usize,
+    json: bool,
+) -> Result<()> {
+    let start_id = resolve_memory_id(storage, id).await?;
+
+    // Parse relation types (default: all)
+    let relation_types: Vec<RelationType> = match relations {
+        Some(rels) => rels
             .iter()
             .map(|s| {
                 s.parse::<RelationType>()
@@ -1579,18 +4033,32 @@ async fn cmd_chain(
         .await
         .context("starting memory not found")?;
 
-    let chain = graph::follow_chain(storage, start_id, &relation_types, Some(depth)).await;
+    let chain = graph::follow_chain(
+        storage,
+        start_id,
+        &relation_types,
+        Some(depth),
+        Some(max_nodes),
+    )
+    .await;
+    telemetry::count(shabka_core::telemetry::RESULTS, chain.node_count());
+    let truncated_note =
+        format!("Stopped after {max_nodes} memories; raise --max-nodes to see the rest.");
 
     if chain.is_empty() {
-        println!(
-            "{}",
-            format!("No connected memories found from: {}", start_memory.title).dimmed()
-        );
+        if json {
+            println!("[]");
+        } else {
+            println!(
+                "{}",
+                format!("No connected memories found from: {}", start_memory.title).dimmed()
+            );
+        }
         return Ok(());
     }
 
     // Fetch full details for chain memories
-    let chain_ids: Vec<Uuid> = chain.iter().map(|l| l.memory_id).collect();
+    let chain_ids: Vec<Uuid> = chain.links.iter().map(|l| l.memory_id).collect();
     let memories = storage
         .get_memories(&chain_ids)
         .await
@@ -1598,60 +4066,238 @@ async fn cmd_chain(
     let memory_map: HashMap<Uuid, &Memory> = memories.iter().map(|m| (m.id, m)).collect();
 
     if json {
-        let results: Vec<serde_json::Value> = chain
+        let results: Vec<output::ChainLinkOutput> = chain
+            .links
             .iter()
             .filter_map(|link| {
-                memory_map.get(&link.memory_id).map(|memory| {
-                    serde_json::json!({
-                        "id": memory.id.to_string(),
-                        "title": memory.title,
-                        "kind": memory.kind.to_string(),
-                        "relation_type": link.relation_type.to_string(),
-                        "from_id": link.from_id.to_string(),
-                        "strength": link.strength,
-                        "depth": link.depth,
+                memory_map
+                    .get(&link.memory_id)
+                    .map(|memory| output::ChainLinkOutput {
+                        id: memory.id,
+                        title: memory.title.clone(),
+                        kind: memory.kind,
+                        relation_type: link.relation_type,
+                        from_id: link.from_id,
+                        strength: link.strength,
+                        depth: link.depth,
+                        cycle: link.cycle,
                     })
-                })
             })
             .collect();
         println!("{}", serde_json::to_string_pretty(&results)?);
-    } else {
-        println!(
-            "Chain from: {} ({})",
-            start_memory.title.bold(),
-            id[..8.min(id.len())].to_string().cyan()
-        );
-        println!();
-        for link in &chain {
-            if let Some(memory) = memory_map.get(&link.memory_id) {
-                let indent = "  ".repeat(link.depth);
-                let short_id = &memory.id.to_string()[..8];
-                let rel_color = match link.relation_type {
-                    RelationType::Fixes => link.relation_type.to_string().green().to_string(),
-                    RelationType::CausedBy => link.relation_type.to_string().red().to_string(),
-                    RelationType::Supersedes => link.relation_type.to_string().yellow().to_string(),
-                    RelationType::Contradicts => {
-                        link.relation_type.to_string().magenta().to_string()
-                    }
-                    _ => link.relation_type.to_string().blue().to_string(),
-                };
-                println!(
-                    "{}{} --[{:.2}]--> {} {} ({})",
-                    indent,
-                    rel_color,
-                    link.strength,
-                    short_id.cyan(),
-                    memory.title,
-                    memory.kind.to_string().dimmed()
-                );
-            }
+        if chain.truncated {
+            eprintln!("{truncated_note}");
         }
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        t!(
+            "chain-from",
+            title = start_memory.title.bold().to_string(),
+            id = start_id.to_string()[..8].cyan().to_string()
+        )
+    );
+    for line in chain_tree(start_id, &chain.links, &memory_map) {
+        println!("{line}");
     }
 
     println!(
         "\n{} connected memories found.",
-        chain.len().to_string().cyan()
+        chain.node_count().to_string().cyan()
+    );
+    if chain.truncated {
+        println!("{}", truncated_note.yellow());
+    }
+    Ok(())
+}
+
+/// Render `links` as a tree under `start_id`. Each memory's links are
+/// grouped into one lane per relation type, in the order first reached; a
+/// link back to a memory already shown is marked "(cycle)" and not expanded.
+fn chain_tree(
+    start_id: Uuid,
+    links: &[graph::ChainLink],
+    memory_map: &HashMap<Uuid, &Memory>,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    chain_subtree(start_id, "", links, memory_map, &mut lines);
+    lines
+}
+
+fn chain_subtree(
+    node: Uuid,
+    prefix: &str,
+    links: &[graph::ChainLink],
+    memory_map: &HashMap<Uuid, &Memory>,
+    lines: &mut Vec<String>,
+) {
+    let children: Vec<&graph::ChainLink> = links
+        .iter()
+        .filter(|l| l.from_id == node && memory_map.contains_key(&l.memory_id))
+        .collect();
+    let mut lanes: Vec<RelationType> = Vec::new();
+    for link in &children {
+        if !lanes.contains(&link.relation_type) {
+            lanes.push(link.relation_type);
+        }
+    }
+
+    for (lane_index, relation) in lanes.iter().enumerate() {
+        let last_lane = lane_index + 1 == lanes.len();
+        let (branch, lane_prefix) = if last_lane {
+            (style::TREE_LAST, format!("{prefix}   "))
+        } else {
+            (style::TREE_BRANCH, format!("{prefix}{}", style::TREE_PIPE))
+        };
+        lines.push(format!("{prefix}{branch}{}", relation_label(*relation)));
+
+        let lane: Vec<&&graph::ChainLink> = children
+            .iter()
+            .filter(|l| l.relation_type == *relation)
+            .collect();
+        for (i, link) in lane.iter().enumerate() {
+            let memory = memory_map[&link.memory_id];
+            let last = i + 1 == lane.len();
+            let (branch, child_prefix) = if last {
+                (style::TREE_LAST, format!("{lane_prefix}   "))
+            } else {
+                (
+                    style::TREE_BRANCH,
+                    format!("{lane_prefix}{}", style::TREE_PIPE),
+                )
+            };
+            let cycle = if link.cycle {
+                format!(" {}", "(cycle)".yellow())
+            } else {
+                String::new()
+            };
+            lines.push(format!(
+                "{lane_prefix}{branch}[{:.2}] {} {} ({}){cycle}",
+                link.strength,
+                memory.id.to_string()[..8].to_string().cyan(),
+                memory.title,
+                memory.kind.to_string().dimmed()
+            ));
+            if !link.cycle {
+                chain_subtree(link.memory_id, &child_prefix, links, memory_map, lines);
+            }
+        }
+    }
+}
+
+/// A relation type in the colour `shabka chain` gives its lane.
+fn relation_label(relation: RelationType) -> String {
+    let name = relation.to_string();
+    match relation {
+        RelationType::Fixes => name.green().to_string(),
+        RelationType::CausedBy => name.red().to_string(),
+        RelationType::Supersedes => name.yellow().to_string(),
+        RelationType::Contradicts => name.magenta().to_string(),
+        _ => name.blue().to_string(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// similar
+// ---------------------------------------------------------------------------
+
+async fn cmd_similar(
+    storage: &Storage,
+    embedder: &EmbeddingService,
+    user_id: &str,
+    id: &str,
+    limit: usize,
+    json: bool,
+) -> Result<()> {
+    let memory_id = resolve_memory_id(storage, id).await?;
+    let memory = storage
+        .get_memory(memory_id)
+        .await
+        .context("memory not found")?;
+    let embedding = embedder
+        .embed(&memory.embedding_text())
+        .await
+        .context("failed to embed memory")?;
+
+    let mut similar = graph::find_similar(storage, memory_id, &embedding, limit)
+        .await
+        .context("similarity search failed")?;
+    similar.retain(|s| sharing::is_visible(s.memory.privacy, &s.memory.created_by, user_id));
+    telemetry::count(shabka_core::telemetry::RESULTS, similar.len());
+
+    if json {
+        let results: Vec<output::SimilarOutput> = similar
+            .iter()
+            .map(|s| output::SimilarOutput {
+                id: s.memory.id,
+                title: s.memory.title.clone(),
+                kind: s.memory.kind,
+                score: s.score,
+                relation: s.relation,
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&results)?);
+        return Ok(());
+    }
+
+    if similar.is_empty() {
+        println!(
+            "{}",
+            format!("No similar memories found for: {}", memory.title).dimmed()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "Similar to: {} ({})",
+        memory.title.bold(),
+        memory_id.to_string()[..8].to_string().cyan()
+    );
+    println!();
+    println!(
+        "{:<12} {:<6} {:<12} {}",
+        "ID".dimmed(),
+        "Score".dimmed(),
+        "Kind".dimmed(),
+        "Title".dimmed()
     );
+    for s in &similar {
+        let short_id = &s.memory.id.to_string()[..8];
+        let score = format!("{:<6.2}", s.score);
+        let score = if s.score >= 0.9 {
+            score.red().to_string()
+        } else if s.score >= 0.7 {
+            score.yellow().to_string()
+        } else {
+            score.dimmed().to_string()
+        };
+        let marker = s
+            .relation
+            .map(|r| format!(" [{r}]").green().to_string())
+            .unwrap_or_default();
+        println!(
+            "{:<12} {} {:<12} {}{}",
+            short_id.cyan(),
+            score,
+            s.memory.kind.to_string().magenta(),
+            s.memory.title,
+            marker
+        );
+    }
+
+    let unlinked = similar
+        .iter()
+        .filter(|s| s.relation.is_none() && s.score >= 0.9)
+        .count();
+    if unlinked > 0 {
+        println!(
+            "\n{} unlinked near-duplicate{} (score >= 0.90).",
+            unlinked.to_string().yellow(),
+            if unlinked == 1 { "" } else { "s" }
+        );
+    }
     Ok(())
 }
 
@@ -1659,19 +4305,146 @@ async fn cmd_chain(
 // prune
 // ---------------------------------------------------------------------------
 
+// ---------------------------------------------------------------------------
+// random
+// ---------------------------------------------------------------------------
+
+/// Stamp file recording the last day `shabka random --daily` showed a memory.
+fn random_stamp_path() -> Option<std::path::PathBuf> {
+    dirs::config_dir().map(|p| p.join("shabka").join("random_daily"))
+}
+
+fn shown_today(stamp: &std::path::Path, today: &str) -> bool {
+    std::fs::read_to_string(stamp).is_ok_and(|s| s.trim() == today)
+}
+
+/// A uniform sample in `[0, 1)`. `RandomState` is seeded from the OS for
+/// every instance, which is plenty for picking a memory.
+fn random_unit() -> f64 {
+    use std::hash::BuildHasher;
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let bits = std::collections::hash_map::RandomState::new().hash_one(nanos);
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn cmd_random(
+    storage: &Storage,
+    user_id: &str,
+    kind: Option<String>,
+    project: Option<String>,
+    motd: bool,
+    daily: bool,
+    json: bool,
+) -> Result<bool> {
+    let now = chrono::Utc::now();
+    let today = now.format("%Y-%m-%d").to_string();
+    let stamp = random_stamp_path();
+    if daily && stamp.as_deref().is_some_and(|p| shown_today(p, &today)) {
+        return Ok(false);
+    }
+
+    let kind = kind
+        .as_deref()
+        .map(|s| {
+            s.parse::<MemoryKind>()
+                .map_err(|_| anyhow::anyhow!("unknown memory kind: {s}"))
+        })
+        .transpose()?;
+    let entries = storage
+        .timeline(&TimelineQuery {
+            limit: 10000,
+            project_id: project,
+            kind,
+            status: Some(MemoryStatus::Active),
+            ..Default::default()
+        })
+        .await
+        .context("failed to fetch timeline")?;
+    let ids: Vec<Uuid> = entries.iter().map(|e| e.id).collect();
+    let mut memories = storage
+        .get_memories(&ids)
+        .await
+        .context("failed to fetch memories")?;
+    memories.retain(|m| sharing::is_visible(m.privacy, &m.created_by, user_id));
+
+    let Some(memory) = decay::pick_resurface(&memories, now, random_unit()) else {
+        if !json && !motd {
+            println!("{}", "No memories to resurface.".dimmed());
+        }
+        return Ok(false);
+    };
+    let days_unread = (now - memory.accessed_at).num_days().max(0);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(memory)?);
+    } else if motd {
+        // Plain text: the line often ends up in notifications, not a terminal.
+        println!(
+            "shabka: {} ({}, unread for {days_unread} days) {} shabka get {}",
+            memory.title,
+            memory.kind,
+            style::DASH,
+            &memory.id.to_string()[..8]
+        );
+    } else {
+        println!("{}", format!("Not read in {days_unread} days:").dimmed());
+        println!();
+        println!("{}", memory.title.bold());
+        println!(
+            "{} {}",
+            memory.kind.to_string().magenta(),
+            format!("importance: {:.0}%", memory.importance * 100.0).dimmed()
+        );
+        println!();
+        println!("{}", memory.content);
+        println!();
+        println!(
+            "{} {}",
+            "ID:".dimmed(),
+            memory.id.to_string()[..8].to_string().cyan()
+        );
+    }
+
+    // Surfacing counts as reading it: reset the staleness clock so the same
+    // memory doesn't keep coming back, and prune leaves it alone.
+    storage
+        .update_memory(
+            memory.id,
+            &UpdateMemoryInput {
+                accessed_at: Some(now),
+                ..Default::default()
+            },
+        )
+        .await
+        .context("failed to mark memory as accessed")?;
+
+    if daily {
+        if let Some(stamp) = stamp {
+            if let Some(parent) = stamp.parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+            let _ = std::fs::write(stamp, &today);
+        }
+    }
+    Ok(true)
+}
+
 async fn cmd_prune(
     storage: &Storage,
     history: &HistoryLogger,
     user_id: &str,
-    days: u64,
+    config: &PruneConfig,
     dry_run: bool,
-    decay_importance: bool,
+    cold_storage: Option<&str>,
+    run: &mut MaintenanceRun,
 ) -> Result<()> {
-    let config = PruneConfig {
-        inactive_days: days,
-        decay_importance,
-        ..Default::default()
-    };
+    if config.delete_after_days == Some(0) {
+        anyhow::bail!("--delete-after must be at least 1 day");
+    }
 
     // Fetch all memories via timeline
     let entries = storage
@@ -1683,7 +4456,7 @@ async fn cmd_prune(
         .context("failed to fetch timeline")?;
 
     if entries.is_empty() {
-        println!("No memories found.");
+        println!("{}", t!("no-memories"));
         return Ok(());
     }
 
@@ -1694,9 +4467,12 @@ async fn cmd_prune(
         .context("failed to fetch memories")?;
 
     let now = chrono::Utc::now();
-    let actions = decay::analyze(&memories, &config, now);
+    let days = config.inactive_days;
+    let (deletions, actions): (Vec<_>, Vec<_>) = decay::analyze(&memories, config, now)
+        .into_iter()
+        .partition(|a| a.should_delete);
 
-    if actions.is_empty() {
+    if actions.is_empty() && deletions.is_empty() {
         println!(
             "{}",
             format!("No stale memories found (threshold: {} days).", days).dimmed()
@@ -1704,32 +4480,62 @@ async fn cmd_prune(
         return Ok(());
     }
 
-    println!(
-        "Found {} stale memories (inactive > {} days):",
-        actions.len().to_string().yellow(),
-        days
-    );
+    if !actions.is_empty() {
+        let retention = if config.retention.kinds.is_empty() {
+            ""
+        } else {
+            " or over retention"
+        };
+        println!(
+            "Found {} stale memories (inactive > {} days{}):",
+            actions.len().to_string().yellow(),
+            days,
+            retention
+        );
+    }
     for action in &actions {
         let imp_info = if let Some(decayed) = action.decayed_importance {
             format!(
-                " importance: {} → {}",
+                " importance: {} {} {}",
                 format!("{:.2}", action.current_importance).dimmed(),
+                style::ARROW,
                 format!("{:.2}", decayed).yellow()
             )
         } else {
             String::new()
         };
+        let reason = match action.retention {
+            Some(limit) => format!("retention: {limit}").red().to_string(),
+            None => format!("{}d inactive", action.days_inactive.to_string().red()),
+        };
         println!(
-            "  {} ({}d inactive){} — {}",
+            "  {} ({}){} {} {}",
             action.memory_id.to_string()[..8].to_string().cyan(),
-            action.days_inactive.to_string().red(),
+            reason,
             imp_info,
+            style::DASH,
             action.title
         );
     }
+    if let (false, Some(grace)) = (deletions.is_empty(), config.delete_after_days) {
+        println!(
+            "Found {} archived memories past the grace period (archived > {} days):",
+            deletions.len().to_string().red(),
+            grace
+        );
+        for action in &deletions {
+            println!(
+                "  {} ({}d archived) {} {}",
+                action.memory_id.to_string()[..8].to_string().cyan(),
+                action.days_inactive.to_string().red(),
+                style::DASH,
+                action.title
+            );
+        }
+    }
 
     if dry_run {
-        println!("\n{}", "Dry run — no changes made.".yellow());
+        println!("\n{}", t!("dry-run").yellow());
         return Ok(());
     }
 
@@ -1760,40 +4566,143 @@ async fn cmd_prune(
                     &action.memory_id.to_string()[..8],
                     e
                 );
+                run.error(format!("archive {}: {e}", action.memory_id));
                 result.errors += 1;
             }
         }
     }
 
-    println!(
-        "\nDone: {} archived, {} importance-decayed, {} errors",
-        result.archived.to_string().green(),
-        result.importance_decayed.to_string().yellow(),
-        if result.errors > 0 {
-            result.errors.to_string().red().to_string()
-        } else {
-            result.errors.to_string()
+    if !deletions.is_empty() {
+        // Write the cold-storage copy first: if that fails, nothing is deleted.
+        if let Some(path) = cold_storage {
+            let doomed: std::collections::HashSet<Uuid> =
+                deletions.iter().map(|a| a.memory_id).collect();
+            let archived: Vec<&Memory> =
+                memories.iter().filter(|m| doomed.contains(&m.id)).collect();
+            append_cold_storage(storage, path, &archived).await?;
+            println!(
+                "Moved {} memories to cold storage: {}",
+                archived.len(),
+                path
+            );
         }
-    );
-    Ok(())
-}
 
-// ---------------------------------------------------------------------------
-// history
-// ---------------------------------------------------------------------------
+        for action in &deletions {
+            let snapshot = if history.snapshots_enabled() {
+                storage.get_memory(action.memory_id).await.ok()
+            } else {
+                None
+            };
+            match storage.delete_memory(action.memory_id).await {
+                Ok(()) => {
+                    result.deleted += 1;
+                    let event = MemoryEvent::new(
+                        action.memory_id,
+                        EventAction::Deleted,
+                        user_id.to_string(),
+                    );
+                    history.log(&match &snapshot {
+                        Some(memory) => event.with_snapshot(memory),
+                        None => event.with_title(&action.title),
+                    });
+                }
+                Err(e) => {
+                    eprintln!(
+                        "  Error deleting {}: {}",
+                        &action.memory_id.to_string()[..8],
+                        e
+                    );
+                    run.error(format!("delete {}: {e}", action.memory_id));
+                    result.errors += 1;
+                }
+            }
+        }
+    }
+
+    run.count("archived", result.archived);
+    run.count("deleted", result.deleted);
+    run.count("importance_decayed", result.importance_decayed);
+    println!(
+        "\nDone: {} archived, {} deleted, {} importance-decayed, {} errors",
+        result.archived.to_string().green(),
+        result.deleted.to_string().red(),
+        result.importance_decayed.to_string().yellow(),
+        if result.errors > 0 {
+            result.errors.to_string().red().to_string()
+        } else {
+            result.errors.to_string()
+        }
+    );
+    Ok(())
+}
+
+/// Append memories (and the relations among them) to a JSONL export file in
+/// the `shabka export --jsonl` format, so `shabka import` can bring them back.
+async fn append_cold_storage(storage: &Storage, path: &str, memories: &[&Memory]) -> Result<()> {
+    let ids: std::collections::HashSet<Uuid> = memories.iter().map(|m| m.id).collect();
+    let mut relations = Vec::new();
+    for memory in memories {
+        for r in storage.get_relations(memory.id).await.unwrap_or_default() {
+            // Each relation is listed under both ends; keep it once, from its source.
+            if r.source_id == memory.id && ids.contains(&r.target_id) {
+                relations.push(r);
+            }
+        }
+    }
+
+    let records: Vec<output::ExportRecord> = memories
+        .iter()
+        .map(|m| output::ExportRecord::Memory(Box::new((*m).clone())))
+        .chain(relations.into_iter().map(output::ExportRecord::Relation))
+        .collect();
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open cold storage file {path}"))?;
+    output::write_jsonl(&mut file, &records)
+        .with_context(|| format!("failed to write cold storage file {path}"))
+}
+
+// ---------------------------------------------------------------------------
+// history
+// ---------------------------------------------------------------------------
+
+async fn cmd_digest(
+    digest: &digest::Digest,
+    config: &digest::DigestConfig,
+    send: bool,
+    json: bool,
+) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(digest)?);
+        return Ok(());
+    }
+    if !send {
+        print!("{}", digest::render_markdown(digest));
+        return Ok(());
+    }
+    if config.webhook.is_none() && config.file.is_none() {
+        anyhow::bail!("nothing to send to: set digest.webhook or digest.file in config.toml");
+    }
+    for target in digest::deliver(digest, config).await? {
+        println!(
+            "{} {}",
+            style::OK.green(),
+            t!("digest-sent", target = target)
+        );
+    }
+    Ok(())
+}
 
 fn cmd_history(
     history: &HistoryLogger,
-    id: Option<String>,
+    filter: &HistoryFilter,
     limit: usize,
     json: bool,
 ) -> Result<()> {
-    let events = if let Some(ref id_str) = id {
-        let memory_id = Uuid::parse_str(id_str).context("invalid memory ID")?;
-        history.history_for(memory_id)
-    } else {
-        history.recent(limit)
-    };
+    let events = history.query(filter, limit);
 
     if events.is_empty() {
         println!("{}", "No history events found.".dimmed());
@@ -1810,7 +4719,7 @@ fn cmd_history(
             "ID".dimmed(),
             "Title".dimmed()
         );
-        for event in events.iter().take(limit) {
+        for event in &events {
             let short_id = &event.memory_id.to_string()[..8];
             let title = event.memory_title.as_deref().unwrap_or("-");
             let action_str = event.action.to_string();
@@ -1824,15 +4733,14 @@ fn cmd_history(
             };
             print!(
                 "{:<20} {:<21} {:<8} {}",
-                event
-                    .timestamp
-                    .format("%Y-%m-%d %H:%M:%S")
-                    .to_string()
-                    .dimmed(),
+                dates::datetime(event.timestamp).dimmed(),
                 action_colored,
                 short_id.cyan(),
                 title
             );
+            if let Some(token) = &event.token {
+                print!(" {}", format!("(token {token})").dimmed());
+            }
             if !event.changes.is_empty() {
                 let changes: Vec<String> = event
                     .changes
@@ -1859,13 +4767,250 @@ fn cmd_history(
 // reembed
 // ---------------------------------------------------------------------------
 
+#[allow(clippy::too_many_arguments)]
+async fn cmd_backfill(
+    storage: &Storage,
+    embedder: &EmbeddingService,
+    config: &ShabkaConfig,
+    history: &HistoryLogger,
+    user_id: &str,
+    options: shabka_core::backfill::BackfillOptions,
+    batch: usize,
+    limit: Option<usize>,
+    dry_run: bool,
+    run: &mut MaintenanceRun,
+) -> Result<()> {
+    use shabka_core::backfill;
+
+    if !options.auto_tag && !options.classify_kind {
+        anyhow::bail!("nothing to backfill: pass --auto-tag, --classify-kind or both");
+    }
+    if batch == 0 {
+        anyhow::bail!("--batch must be at least 1");
+    }
+    if !config.llm.enabled {
+        anyhow::bail!("Backfill requires LLM. Enable it in config.toml under [llm].");
+    }
+    let llm = shabka_core::llm::LlmService::from_config(&config.llm)
+        .context("failed to create LLM service")?;
+
+    let entries = storage
+        .timeline(&TimelineQuery {
+            limit: 10000,
+            ..Default::default()
+        })
+        .await
+        .context("failed to fetch timeline")?;
+    let ids: Vec<Uuid> = entries.iter().map(|e| e.id).collect();
+    let all_memories = storage
+        .get_memories(&ids)
+        .await
+        .context("failed to fetch memories")?;
+    let mut memories = backfill::candidates(all_memories, &options);
+    if let Some(limit) = limit {
+        memories.truncate(limit);
+    }
+
+    let count = memories.len();
+    if count == 0 {
+        println!(
+            "Nothing to backfill {} every memory already has what was asked for.",
+            style::DASH
+        );
+        return Ok(());
+    }
+    let estimate = backfill::estimate_tokens(&memories, batch);
+    println!(
+        "Backfill {} memories in {} LLM calls (~{} tokens, {} / {})",
+        count,
+        count.div_ceil(batch),
+        estimate,
+        config.llm.provider,
+        config.llm.model
+    );
+    let reply_tokens = batch.min(count) * backfill::REPLY_TOKENS_PER_MEMORY;
+    if reply_tokens > config.llm.max_tokens {
+        println!(
+            "  {}",
+            format!(
+                "Replies for {} memories may not fit llm.max_tokens ({}); lower --batch if calls fail.",
+                batch.min(count),
+                config.llm.max_tokens
+            )
+            .yellow()
+        );
+    }
+    let budget = config.llm.daily_token_budget;
+    let used = TokenLedger::new().today().total;
+    println!("  Budget:     {}", token_usage_line(used, budget));
+    if let Some(budget) = budget {
+        if used + estimate > budget {
+            println!(
+                "  {}",
+                "The estimate exceeds today's remaining budget; the run stops when it is used up."
+                    .yellow()
+            );
+        }
+    }
+
+    let interrupt = Interrupt::install();
+    let bar = progress::bar(count, "Backfilling");
+    let mut updated = 0usize;
+    let mut unchanged = 0usize;
+    let mut errors = 0usize;
+    let mut quota_hit = false;
+
+    for chunk in memories.chunks(batch) {
+        if interrupt.requested() {
+            break;
+        }
+        let suggestions = match backfill::suggest(chunk, &llm, &options).await {
+            Ok(suggestions) => suggestions,
+            Err(e @ ShabkaError::QuotaExceeded { .. }) => {
+                bar.println(format!("  {e}"));
+                quota_hit = true;
+                break;
+            }
+            Err(e) => {
+                bar.println(format!("  Batch error: {e}"));
+                run.error(format!("suggest: {e}"));
+                errors += chunk.len();
+                bar.inc(chunk.len() as u64);
+                continue;
+            }
+        };
+
+        for memory in chunk {
+            let Some(input) = suggestions
+                .iter()
+                .find(|s| s.memory_id == memory.id)
+                .and_then(|s| backfill::update_for(memory, s))
+            else {
+                unchanged += 1;
+                continue;
+            };
+            if dry_run {
+                bar.println(format!(
+                    "  {} {}{}{}",
+                    memory.id.to_string()[..8].to_string().dimmed(),
+                    memory.title,
+                    input
+                        .kind
+                        .map(|k| format!("  kind: {} {} {}", memory.kind, style::ARROW, k))
+                        .unwrap_or_default(),
+                    input
+                        .tags
+                        .as_ref()
+                        .map(|t| format!("  tags: {}", t.join(", ")))
+                        .unwrap_or_default(),
+                ));
+                updated += 1;
+                continue;
+            }
+
+            let changes = history::diff_update(memory, &input);
+            let edited = match storage.update_memory(memory.id, &input).await {
+                Ok(edited) => edited,
+                Err(e) => {
+                    bar.println(format!(
+                        "  Error updating {}: {e}",
+                        &memory.id.to_string()[..8]
+                    ));
+                    run.error(format!("update {}: {e}", memory.id));
+                    errors += 1;
+                    continue;
+                }
+            };
+            history.log(
+                &MemoryEvent::new(memory.id, EventAction::Updated, user_id.to_string())
+                    .with_title(&memory.title)
+                    .with_changes(changes),
+            );
+            if let Err(e) = shabka_core::embedding::refresh_after_edit(
+                storage,
+                embedder,
+                &config.embedding,
+                Some(memory),
+                &edited,
+            )
+            .await
+            {
+                bar.println(format!(
+                    "  Error re-embedding {}: {e}",
+                    &memory.id.to_string()[..8]
+                ));
+                run.error(format!("embed {}: {e}", memory.id));
+            }
+            updated += 1;
+        }
+        bar.inc(chunk.len() as u64);
+    }
+    bar.finish_and_clear();
+
+    run.count("updated", updated);
+    run.count("unchanged", unchanged);
+    if dry_run {
+        println!(
+            "Would update {} memories ({} unchanged, {} errors)",
+            updated, unchanged, errors
+        );
+        println!("  {}", t!("dry-run"));
+        return Ok(());
+    }
+    let remaining = count - updated - unchanged - errors;
+    if interrupt.requested() || quota_hit {
+        println!(
+            "Stopped: {} updated, {} unchanged, {} errors, {} left. Run {} again to continue.",
+            updated,
+            unchanged,
+            errors,
+            remaining,
+            "shabka backfill".cyan()
+        );
+        if interrupt.requested() {
+            anyhow::bail!("backfill interrupted");
+        }
+        return Ok(());
+    }
+    println!(
+        "Done: {} updated, {} unchanged, {} errors",
+        updated, unchanged, errors
+    );
+    Ok(())
+}
+
 async fn cmd_reembed(
     storage: &Storage,
     embedder: &EmbeddingService,
     batch_size: usize,
     dry_run: bool,
     force: bool,
+    batch_api: Option<&EmbeddingConfig>,
+    run: &mut MaintenanceRun,
 ) -> Result<()> {
+    let batch_client = batch_api.map(OpenAiBatchClient::from_config).transpose()?;
+    if let Some(client) = &batch_client {
+        if let Some(pending) = PendingBatch::load().filter(|p| p.model == embedder.model_id()) {
+            if dry_run {
+                println!(
+                    "Batch {} ({} requests) is still pending; dry run, not waiting.",
+                    pending.id, pending.requests
+                );
+                return Ok(());
+            }
+            println!(
+                "Resuming batch {} ({} requests, submitted {})",
+                pending.id.cyan(),
+                pending.requests,
+                pending.submitted_at
+            );
+            let stored = apply_embedding_batch(storage, client, &pending.id).await?;
+            run.count("reembedded", stored);
+            record_reembed(embedder);
+            return Ok(());
+        }
+    }
+
     let saved_state = EmbeddingState::load();
     let provider_changed = !saved_state.provider.is_empty()
         && !saved_state.matches(
@@ -1949,19 +5094,69 @@ async fn cmd_reembed(
     }
 
     if count == 0 {
-        println!("Nothing to do — all memories are up to date.");
+        println!("Nothing to do {} all memories are up to date.", style::DASH);
         return Ok(());
     }
 
     if dry_run {
-        println!("  Dry run — no changes made.");
+        println!("  {}", t!("dry-run"));
+        return Ok(());
+    }
+
+    if let Some(client) = &batch_client {
+        let items: Vec<(String, String)> = memories
+            .iter()
+            .map(|m| (m.id.to_string(), m.embedding_text()))
+            .collect();
+        let batch_id = client
+            .submit(&items)
+            .await
+            .context("failed to submit embedding batch")?;
+        let pending = PendingBatch {
+            id: batch_id.clone(),
+            submitted_at: chrono::Utc::now().to_rfc3339(),
+            model: embedder.model_id().to_string(),
+            requests: items.len(),
+        };
+        if let Err(e) = pending.save() {
+            eprintln!("Warning: {e}; an interrupted wait can't be resumed");
+        }
+        println!(
+            "Submitted batch {} ({} requests). Waiting for it to finish; \
+             interrupt and re-run `shabka reembed --batch-api` to resume later.",
+            batch_id.cyan(),
+            items.len()
+        );
+        let stored = apply_embedding_batch(storage, client, &batch_id).await?;
+        run.count("reembedded", stored);
+        record_reembed(embedder);
         return Ok(());
     }
 
+    // Memories an interrupted run already re-embedded with this model.
+    let mut checkpoint = Checkpoint::load(REEMBED_CHECKPOINT, embedder.model_id());
+    let memories: Vec<Memory> = if checkpoint.done.is_empty() {
+        memories
+    } else {
+        println!(
+            "Resuming: {} memories were re-embedded before the last interruption",
+            checkpoint.done.len()
+        );
+        memories
+            .into_iter()
+            .filter(|m| !checkpoint.done.contains(&m.id))
+            .collect()
+    };
+
+    let interrupt = Interrupt::install();
+    let bar = progress::bar(memories.len(), "Re-embedding");
     let mut processed = 0usize;
     let mut errors = 0usize;
 
     for chunk in memories.chunks(batch_size) {
+        if interrupt.requested() {
+            break;
+        }
         let texts: Vec<String> = chunk.iter().map(|m| m.embedding_text()).collect();
         let text_refs: Vec<&str> = texts.iter().map(|s| s.as_str()).collect();
 
@@ -1969,13 +5164,14 @@ async fn cmd_reembed(
             Ok(embs) => embs,
             Err(e) => {
                 // Fallback: try one at a time
-                eprintln!("  Batch error ({}), falling back to single-item", e);
+                bar.println(format!("  Batch error ({e}), falling back to single-item"));
                 let mut single_embs = Vec::with_capacity(chunk.len());
                 for text in &text_refs {
                     match embedder.embed(text).await {
                         Ok(emb) => single_embs.push(emb),
                         Err(e2) => {
-                            eprintln!("  Error embedding: {}", e2);
+                            bar.println(format!("  Error embedding: {e2}"));
+                            run.error(format!("embed: {e2}"));
                             errors += 1;
                             single_embs.push(Vec::new());
                         }
@@ -1990,21 +5186,51 @@ async fn cmd_reembed(
                 continue;
             }
             match storage.save_memory(memory, Some(embedding)).await {
-                Ok(()) => processed += 1,
+                Ok(()) => {
+                    processed += 1;
+                    checkpoint.done.insert(memory.id);
+                }
                 Err(e) => {
-                    eprintln!("  Error saving {}: {}", &memory.id.to_string()[..8], e);
+                    bar.println(format!(
+                        "  Error saving {}: {e}",
+                        &memory.id.to_string()[..8]
+                    ));
+                    run.error(format!("save {}: {e}", memory.id));
                     errors += 1;
                 }
             }
         }
 
-        eprint!("\r  Progress: {}/{}", processed + errors, count);
+        bar.inc(chunk.len() as u64);
     }
-
-    eprintln!();
+    bar.finish_and_clear();
+
+    run.count("reembedded", processed);
+    run.count("skipped", skipped);
+    if interrupt.requested() {
+        checkpoint
+            .save(REEMBED_CHECKPOINT)
+            .context("failed to save reembed checkpoint")?;
+        println!(
+            "Interrupted: {} re-embedded, {} errors. Run {} again to continue.",
+            processed,
+            errors,
+            "shabka reembed".cyan()
+        );
+        anyhow::bail!("reembed interrupted");
+    }
+    Checkpoint::clear(REEMBED_CHECKPOINT);
     println!("Done: {} re-embedded, {} errors", processed, errors);
 
-    // Update embedding state so future runs know what provider was used
+    record_reembed(embedder);
+    Ok(())
+}
+
+/// Checkpoint name for interrupted `shabka reembed` runs.
+const REEMBED_CHECKPOINT: &str = "reembed";
+
+/// Update embedding state so future runs know what provider was used.
+fn record_reembed(embedder: &EmbeddingService) {
     let mut state = EmbeddingState::from_provider(
         embedder.provider_name(),
         embedder.model_id(),
@@ -2014,22 +5240,132 @@ async fn cmd_reembed(
     if let Err(e) = state.save() {
         eprintln!("Warning: failed to save embedding state: {}", e);
     }
+}
 
-    Ok(())
+/// How often to check on a submitted embedding batch.
+const BATCH_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Wait for an embedding batch to finish and store its embeddings.
+/// Returns how many were stored.
+async fn apply_embedding_batch(
+    storage: &Storage,
+    client: &OpenAiBatchClient,
+    batch_id: &str,
+) -> Result<usize> {
+    let status = loop {
+        let status = client
+            .status(batch_id)
+            .await
+            .context("failed to check batch status")?;
+        if status.is_finished() {
+            break status;
+        }
+        eprint!(
+            "\r  Batch {}: {}/{} done",
+            status.status, status.request_counts.completed, status.request_counts.total
+        );
+        tokio::time::sleep(BATCH_POLL_INTERVAL).await;
+    };
+    eprintln!();
+
+    let results = client
+        .results(&status)
+        .await
+        .context("failed to download batch results")?;
+    PendingBatch::clear();
+    if status.status != "completed" && results.embeddings.is_empty() {
+        anyhow::bail!(
+            "batch {batch_id} {} without results; run `shabka reembed` again",
+            status.status
+        );
+    }
+
+    let mut processed = 0usize;
+    let mut errors = results.errors.len();
+    for (custom_id, message) in &results.errors {
+        eprintln!(
+            "  Error embedding {}: {message}",
+            &custom_id[..8.min(custom_id.len())]
+        );
+    }
+    for (custom_id, embedding) in &results.embeddings {
+        let saved = match custom_id.parse::<Uuid>() {
+            Ok(id) => match storage.get_memory(id).await {
+                Ok(memory) => storage.save_memory(&memory, Some(embedding)).await,
+                Err(e) => Err(e),
+            },
+            Err(_) => continue,
+        };
+        match saved {
+            Ok(()) => processed += 1,
+            Err(e) => {
+                eprintln!(
+                    "  Error saving {}: {e}",
+                    &custom_id[..8.min(custom_id.len())]
+                );
+                errors += 1;
+            }
+        }
+    }
+    println!("Done: {} re-embedded, {} errors", processed, errors);
+    Ok(processed)
 }
 
 // ---------------------------------------------------------------------------
 // assess
 // ---------------------------------------------------------------------------
 
+/// Closest matches reported per memory by `assess --duplicates`.
+const DUPLICATES_PER_MEMORY: usize = 4;
+
+/// Memories embedded per request when `assess --duplicates` finds some
+/// without a stored vector.
+const DUPLICATE_EMBED_BATCH: usize = 32;
+
+/// Memories sent to the LLM per call by `assess --classify`.
+const CLASSIFY_BATCH: usize = 20;
+
+#[allow(clippy::too_many_arguments)]
+/// The `shabka assess --json` scorecard for `results`, worst first.
+fn assess_output(total: usize, results: &[AssessmentResult]) -> output::AssessOutput {
+    output::AssessOutput {
+        total_memories: total,
+        memories_with_issues: results.len(),
+        score: assess::quality_score(results, total),
+        counts: IssueCounts::from_results(results),
+        issues: results
+            .iter()
+            .map(|r| {
+                let missing = missing_referents(r);
+                output::AssessIssueOutput {
+                    id: r.memory_id,
+                    title: r.title.clone(),
+                    issues: r.issues.iter().map(|i| i.label().to_string()).collect(),
+                    missing_paths: missing.map(|m| m.paths.clone()).unwrap_or_default(),
+                    missing_symbols: missing.map(|m| m.symbols.clone()).unwrap_or_default(),
+                    suggested_kind: assess::suggested_kind(r),
+                }
+            })
+            .collect(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn cmd_assess(
     storage: &Storage,
     embedder: Option<&EmbeddingService>,
+    llm: Option<&shabka_core::llm::LlmService>,
     graph_config: &GraphConfig,
+    retention: &decay::RetentionConfig,
+    min_confidence: f32,
     limit: Option<usize>,
     check_duplicates: bool,
+    codebase: Option<(&std::path::Path, Option<&str>)>,
+    fix: Option<(&HistoryLogger, &str)>,
     json: bool,
-) -> Result<()> {
+    quiet: bool,
+    run: &mut MaintenanceRun,
+) -> Result<bool> {
     // Fetch all memories via timeline
     let entries = storage
         .timeline(&TimelineQuery {
@@ -2040,8 +5376,10 @@ async fn cmd_assess(
         .context("failed to fetch timeline")?;
 
     if entries.is_empty() {
-        println!("No memories to assess.");
-        return Ok(());
+        if !quiet {
+            println!("No memories to assess.");
+        }
+        return Ok(false);
     }
 
     let ids: Vec<Uuid> = entries.iter().map(|e| e.id).collect();
@@ -2059,6 +5397,7 @@ async fn cmd_assess(
 
     let assess_config = AssessConfig {
         stale_days: graph_config.stale_days,
+        min_confidence,
         ..AssessConfig::default()
     };
 
@@ -2079,87 +5418,172 @@ async fn cmd_assess(
             }
         })
         .collect();
+    assess::flag_retention(&mut results, &memories, retention);
+
+    if let Some((root, project)) = codebase {
+        let codebase = Codebase::scan(root)
+            .with_context(|| format!("failed to scan codebase at {}", root.display()))?;
+        // Hooks tag captured memories with the directory basename.
+        let basename = codebase
+            .root()
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(str::to_string);
+        let project = project.map(str::to_string).or(basename);
+        if !quiet {
+            eprintln!(
+                "Checked {} files in {}.",
+                codebase.file_count(),
+                codebase.root().display()
+            );
+        }
+        assess::flag_codebase(&mut results, &memories, &codebase, project.as_deref());
+    }
 
-    // Optional duplicate check
-    if check_duplicates {
-        if let Some(embedder) = embedder {
-            eprint!("Checking for duplicates...");
-            let mut dup_count = 0usize;
-            for mem in &memories {
-                let embedding = match embedder.embed(&mem.embedding_text()).await {
-                    Ok(e) => e,
-                    Err(_) => continue,
-                };
-                // Search for similar memories
-                let similar = storage
-                    .vector_search(&embedding, 5)
-                    .await
-                    .unwrap_or_default();
-                for (other, score) in &similar {
-                    if other.id != mem.id && *score > graph_config.similarity_threshold {
-                        // Check if we already have this result
-                        let existing = results.iter_mut().find(|r| r.memory_id == mem.id);
-                        let dup_issue = assess::QualityIssue::PossibleDuplicate {
-                            other_id: other.id,
-                            other_title: other.title.clone(),
-                            similarity: *score,
-                        };
-                        if let Some(r) = existing {
-                            if !r.issues.iter().any(|iss| matches!(iss, assess::QualityIssue::PossibleDuplicate { other_id, .. } if *other_id == other.id)) {
-                                r.issues.push(dup_issue);
-                                dup_count += 1;
-                            }
-                        } else {
-                            results.push(AssessmentResult {
-                                memory_id: mem.id,
-                                title: mem.title.clone(),
-                                issues: vec![dup_issue],
-                            });
-                            dup_count += 1;
-                        }
-                    }
+    // Optional LLM kind check; its answers replace the keyword suggestions.
+    if let Some(llm) = llm {
+        let options = shabka_core::backfill::BackfillOptions {
+            classify_kind: true,
+            ..Default::default()
+        };
+        let candidates: Vec<Memory> = memories
+            .iter()
+            .filter(|m| assess::kind_checkable(m))
+            .cloned()
+            .collect();
+        let interrupt = Interrupt::install();
+        let bar = if quiet {
+            indicatif::ProgressBar::hidden()
+        } else {
+            progress::bar(candidates.len(), "Checking kinds")
+        };
+        let mut kinds: HashMap<Uuid, MemoryKind> = HashMap::new();
+        for chunk in candidates.chunks(CLASSIFY_BATCH) {
+            if interrupt.requested() {
+                break;
+            }
+            match shabka_core::backfill::suggest(chunk, llm, &options).await {
+                Ok(suggestions) => kinds.extend(
+                    suggestions
+                        .into_iter()
+                        .filter_map(|s| s.kind.map(|kind| (s.memory_id, kind))),
+                ),
+                Err(e) => {
+                    bar.println(format!("LLM kind check stopped: {e}"));
+                    break;
                 }
             }
-            eprintln!(" found {} potential duplicates.", dup_count);
+            bar.inc(chunk.len() as u64);
+        }
+        bar.finish_and_clear();
+        assess::flag_kinds(&mut results, &memories, &kinds);
+        if !quiet {
+            eprintln!(
+                "Checked the kind of {} of {} auto-captured memories with the LLM.",
+                kinds.len(),
+                candidates.len()
+            );
         }
     }
 
-    // Sort by number of issues (worst first)
-    results.sort_by(|a, b| b.issues.len().cmp(&a.issues.len()));
-
-    let score = assess::quality_score(&results, total);
-    let counts = IssueCounts::from_results(&results);
-
-    if json {
-        let json_results: Vec<serde_json::Value> = results
+    // Optional duplicate check: compare stored vectors locally, embedding
+    // only the memories that have none stored.
+    if check_duplicates {
+        let mut embeddings = storage
+            .stored_embeddings()
+            .await
+            .context("failed to read stored embeddings")?;
+        let unembedded: Vec<&Memory> = memories
             .iter()
-            .map(|r| {
-                let issues: Vec<String> = r.issues.iter().map(|i| i.label().to_string()).collect();
-                serde_json::json!({
-                    "id": r.memory_id.to_string(),
-                    "title": r.title,
-                    "issues": issues,
-                })
-            })
+            .filter(|m| !embeddings.contains_key(&m.id))
             .collect();
-        let output = serde_json::json!({
-            "total_memories": total,
-            "memories_with_issues": results.len(),
-            "score": score,
-            "counts": {
-                "generic_titles": counts.generic_titles,
-                "short_content": counts.short_content,
-                "no_tags": counts.no_tags,
-                "low_importance": counts.low_importance,
-                "stale": counts.stale,
-                "orphaned": counts.orphaned,
-                "duplicates": counts.duplicates,
-                "low_trust": counts.low_trust,
-            },
-            "issues": json_results,
-        });
-        println!("{}", serde_json::to_string_pretty(&output)?);
-        return Ok(());
+        let mut interrupted = false;
+        if let Some(embedder) = embedder.filter(|_| !unembedded.is_empty()) {
+            let interrupt = Interrupt::install();
+            let bar = if quiet {
+                indicatif::ProgressBar::hidden()
+            } else {
+                progress::bar(unembedded.len(), "Embedding for duplicate check")
+            };
+            for chunk in unembedded.chunks(DUPLICATE_EMBED_BATCH) {
+                if interrupt.requested() {
+                    interrupted = true;
+                    break;
+                }
+                let texts: Vec<String> = chunk.iter().map(|m| m.embedding_text()).collect();
+                let text_refs: Vec<&str> = texts.iter().map(String::as_str).collect();
+                if let Ok(vectors) = embedder.embed_batch(&text_refs).await {
+                    embeddings.extend(chunk.iter().map(|m| m.id).zip(vectors));
+                }
+                bar.inc(chunk.len() as u64);
+            }
+            bar.finish_and_clear();
+        }
+
+        let dup_count = assess::flag_duplicates(
+            &mut results,
+            &memories,
+            &embeddings,
+            graph_config.similarity_threshold,
+            DUPLICATES_PER_MEMORY,
+        );
+        if !quiet {
+            if interrupted {
+                eprintln!("Duplicate check interrupted; memories not yet embedded were skipped.");
+            }
+            eprintln!("Found {} potential duplicates.", dup_count);
+        }
+    }
+
+    // Sort by number of issues (worst first)
+    if output::stable() {
+        results.sort_by_key(|r| (std::cmp::Reverse(r.issues.len()), r.memory_id));
+    } else {
+        results.sort_by_key(|r| std::cmp::Reverse(r.issues.len()));
+    }
+
+    let score = assess::quality_score(&results, total);
+    let counts = IssueCounts::from_results(&results);
+    assess::record_scorecard(run, score, total, &counts);
+    let found = !results.is_empty();
+
+    let mut fixed = 0usize;
+    if let Some((history, user_id)) = fix {
+        for result in &results {
+            let Some(kind) = assess::suggested_kind(result) else {
+                continue;
+            };
+            let Some(memory) = memories.iter().find(|m| m.id == result.memory_id) else {
+                continue;
+            };
+            let input = UpdateMemoryInput {
+                kind: Some(kind),
+                ..Default::default()
+            };
+            if let Err(e) = storage.update_memory(memory.id, &input).await {
+                eprintln!("  Error updating {}: {e}", &memory.id.to_string()[..8]);
+                continue;
+            }
+            history.log(
+                &MemoryEvent::new(memory.id, EventAction::Updated, user_id.to_string())
+                    .with_title(&memory.title)
+                    .with_changes(shabka_core::history::diff_update(memory, &input)),
+            );
+            fixed += 1;
+        }
+    }
+
+    if quiet {
+        return Ok(found);
+    }
+
+    if json {
+        let out = assess_output(total, &results);
+        println!("{}", serde_json::to_string_pretty(&out)?);
+        if fix.is_some() {
+            eprintln!("Changed the kind of {fixed} memories.");
+        }
+        return Ok(found);
     }
 
     // Pretty print scorecard
@@ -2226,6 +5650,34 @@ async fn cmd_assess(
         counts.low_trust,
         pct(counts.low_trust, total)
     );
+    println!(
+        "  {:<20} {:>4}  ({})",
+        "Low confidence:",
+        counts.low_confidence,
+        pct(counts.low_confidence, total)
+    );
+    if !retention.kinds.is_empty() {
+        println!(
+            "  {:<20} {:>4}  ({})",
+            "Over retention:",
+            counts.over_retention,
+            pct(counts.over_retention, total)
+        );
+    }
+    if codebase.is_some() {
+        println!(
+            "  {:<20} {:>4}  ({})",
+            "Missing referents:",
+            counts.missing_referents,
+            pct(counts.missing_referents, total)
+        );
+    }
+    println!(
+        "  {:<20} {:>4}  ({})",
+        "Wrong kind:",
+        counts.wrong_kind,
+        pct(counts.wrong_kind, total)
+    );
 
     // Top issues (up to 10)
     if !results.is_empty() {
@@ -2235,11 +5687,34 @@ async fn cmd_assess(
             let short_id = &r.memory_id.to_string()[..8];
             let labels: Vec<&str> = r.issues.iter().map(|i| i.label()).collect();
             println!(
-                "  {} {} — {}",
+                "  {} {} {} {}",
                 format!("[{}]", short_id).cyan(),
                 format!("\"{}\"", r.title).dimmed(),
+                style::DASH,
                 labels.join(", ").yellow()
             );
+            if let Some(missing) = missing_referents(r) {
+                let mut gone: Vec<String> = missing
+                    .paths
+                    .iter()
+                    .map(|p| {
+                        if missing.deleted.contains(p) {
+                            format!("{p} (deleted)")
+                        } else {
+                            p.clone()
+                        }
+                    })
+                    .collect();
+                gone.extend(missing.symbols.iter().cloned());
+                println!("      {} {}", "missing:".dimmed(), gone.join(", ").dimmed());
+            }
+            if let Some(kind) = assess::suggested_kind(r) {
+                println!(
+                    "      {} {}",
+                    "suggested kind:".dimmed(),
+                    kind.to_string().dimmed()
+                );
+            }
         }
     }
 
@@ -2252,6 +5727,12 @@ async fn cmd_assess(
         format!("{}/100", score).red().to_string()
     };
     println!("Overall score: {}", score_colored);
+    if fix.is_some() {
+        println!(
+            "Changed the kind of {} memories.",
+            fixed.to_string().green()
+        );
+    }
 
     // Actionable suggestions
     let mut suggestions: Vec<&str> = Vec::new();
@@ -2277,6 +5758,21 @@ async fn cmd_assess(
         suggestions
             .push("Low trust: use `shabka verify <id> --status verified` to confirm or update");
     }
+    if counts.low_confidence > 0 {
+        suggestions.push(
+            "Low confidence: check these captures and `shabka verify <id> --status verified` them",
+        );
+    }
+    if counts.missing_referents > 0 {
+        suggestions.push(
+            "Missing referents: update them, or mark with `shabka verify <id> --status outdated`",
+        );
+    }
+    if counts.wrong_kind > 0 && fix.is_none() {
+        suggestions.push(
+            "Wrong kind: `shabka assess --fix` applies the suggested kinds, or review them in `shabka inbox --triage`",
+        );
+    }
     if !suggestions.is_empty() {
         println!();
         println!("{}:", "Suggestions".bold());
@@ -2285,1335 +5781,5501 @@ async fn cmd_assess(
         }
     }
 
-    Ok(())
+    Ok(found)
 }
 
-// ---------------------------------------------------------------------------
-// doctor
-// ---------------------------------------------------------------------------
+/// Assess runs recorded for the trend; enough for years of daily runs.
+const ASSESS_TREND_RUNS: usize = 2000;
 
-async fn cmd_doctor(config: &ShabkaConfig) -> Result<()> {
-    println!("{}", "Shabka Doctor".bold());
-    println!("{}", "=============".dimmed());
+/// Print the quality score at the end of each month with an assess run.
+async fn cmd_assess_trend(storage: &Storage, json: bool) -> Result<bool> {
+    let runs = storage
+        .maintenance_runs(Some(MaintenanceOp::Assess), ASSESS_TREND_RUNS)
+        .await
+        .context("failed to read assess runs")?;
+    let trend = assess::health_trend(&runs);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&trend)?);
+        return Ok(!trend.is_empty());
+    }
+    if trend.is_empty() {
+        println!(
+            "{}",
+            "No assessments recorded yet. Each full `shabka assess` run adds to the trend."
+                .dimmed()
+        );
+        return Ok(false);
+    }
+
+    let scores: Vec<u32> = trend.iter().map(|p| p.score).collect();
+    println!("{}", "Memory Quality Trend".bold());
+    println!("{}", "====================".dimmed());
+    println!("{}", assess::sparkline(&scores).cyan());
     println!();
+    println!(
+        "{:<8}  {:>5}  {:>8}  {:>6}  {:>6}",
+        "Month".dimmed(),
+        "Score".dimmed(),
+        "Memories".dimmed(),
+        "Issues".dimmed(),
+        "Change".dimmed()
+    );
+    let mut previous: Option<u32> = None;
+    for point in &trend {
+        let score = format!("{:>5}", point.score);
+        let score = if point.score >= 80 {
+            score.green().to_string()
+        } else if point.score >= 50 {
+            score.yellow().to_string()
+        } else {
+            score.red().to_string()
+        };
+        let change = match previous {
+            Some(prev) => {
+                let delta = i64::from(point.score) - i64::from(prev);
+                let text = format!("{delta:>+6}");
+                match delta.cmp(&0) {
+                    std::cmp::Ordering::Greater => text.green().to_string(),
+                    std::cmp::Ordering::Less => text.red().to_string(),
+                    std::cmp::Ordering::Equal => text.dimmed().to_string(),
+                }
+            }
+            None => format!("{:>6}", ""),
+        };
+        println!(
+            "{:<8}  {}  {:>8}  {:>6}  {}",
+            point.month,
+            score,
+            point.memories,
+            point.counts.total(),
+            change
+        );
+        previous = Some(point.score);
+    }
+    Ok(true)
+}
 
-    let mut critical_fail = false;
+/// The missing paths and symbols behind a result's `MissingReferents` issue.
+fn missing_referents(result: &AssessmentResult) -> Option<&MissingReferents> {
+    result.issues.iter().find_map(|issue| match issue {
+        assess::QualityIssue::MissingReferents { missing } => Some(missing),
+        _ => None,
+    })
+}
 
-    // 1. HelixDB connectivity
-    let storage = make_storage(config)?;
-    let helix_ok = match storage
-        .timeline(&TimelineQuery {
-            limit: 1,
-            ..Default::default()
-        })
+// ---------------------------------------------------------------------------
+// graph
+// ---------------------------------------------------------------------------
+
+/// Recompute and store centrality, recording the run. The summary goes to
+/// stderr when `to_stderr`, so it stays out of JSON on stdout.
+async fn cmd_graph_centrality(storage: &Storage, to_stderr: bool) -> Result<()> {
+    let mut run = MaintenanceRun::start(MaintenanceOp::Centrality, serde_json::Value::Null);
+    let result = graph::refresh_centrality(storage)
         .await
-    {
-        Ok(_) => {
-            println!(
-                "  {} HelixDB        {}",
-                "OK".green(),
-                format!("{}:{}", config.helix.url, config.helix.port).dimmed()
-            );
-            true
-        }
-        Err(e) => {
-            println!(
-                "  {} HelixDB        {} ({})",
-                "FAIL".red(),
-                format!("{}:{}", config.helix.url, config.helix.port).dimmed(),
-                format!("{e:#}").red()
-            );
-            println!(
-                "       {} Start HelixDB with: {}",
-                "hint:".dimmed(),
-                "just db".cyan()
-            );
-            critical_fail = true;
-            false
-        }
-    };
+        .context("failed to compute centrality");
+    if let Ok(scores) = &result {
+        run.count("memories_scored", scores.len());
+    }
+    record_maintenance(storage, run, &result).await;
+    let scored = result?.len();
 
-    // 2. Embedding provider
-    match EmbeddingService::from_config(&config.embedding) {
-        Ok(service) => {
-            // Try a test embed
-            match service.embed("shabka doctor test").await {
-                Ok(vec) => {
-                    println!(
-                        "  {} Embedding      {} / {} ({}d, vec_len={})",
-                        "OK".green(),
-                        service.provider_name().cyan(),
-                        service.model_id(),
-                        service.dimensions(),
-                        vec.len(),
-                    );
-                }
-                Err(e) => {
-                    println!(
-                        "  {} Embedding      {} / {} — {}",
-                        "FAIL".red(),
-                        service.provider_name(),
-                        service.model_id(),
-                        format!("{e:#}").red()
-                    );
-                    critical_fail = true;
-                }
-            }
-        }
-        Err(e) => {
-            println!(
-                "  {} Embedding      {} — {}",
-                "FAIL".red(),
-                config.embedding.provider,
-                format!("{e}").red()
-            );
-            critical_fail = true;
-        }
+    let summary = format!("Scored the centrality of {scored} connected memories.");
+    if to_stderr {
+        eprintln!("{summary}");
+    } else {
+        println!("{summary}");
     }
+    Ok(())
+}
 
-    // 3. Dimension compatibility
-    match config::check_dimensions(&config.embedding) {
-        Ok(()) => {
-            let state = EmbeddingState::load();
-            if state.provider.is_empty() {
-                println!(
-                    "  {} Dimensions     {}",
-                    "OK".green(),
-                    "no prior state (first run)".dimmed()
-                );
-            } else {
-                println!(
-                    "  {} Dimensions     {}d matches stored state",
-                    "OK".green(),
-                    state.dimensions
-                );
-            }
+/// A project and its most central memories, highest score first.
+type CentralGroup<'a> = (Option<String>, Vec<(&'a Memory, &'a Centrality)>);
+
+/// The stored centrality of active memories `user_id` can see, grouped by
+/// project in order of each project's most central memory, at most `limit`
+/// per project.
+fn central_memories_by_project<'a>(
+    ranking: &'a [Centrality],
+    memories: &'a [Memory],
+    user_id: &str,
+    project: Option<&str>,
+    limit: usize,
+) -> Vec<CentralGroup<'a>> {
+    let memory_map: HashMap<Uuid, &Memory> = memories.iter().map(|m| (m.id, m)).collect();
+    let mut groups: Vec<CentralGroup> = Vec::new();
+    for score in ranking {
+        let Some(&memory) = memory_map.get(&score.memory_id) else {
+            continue;
+        };
+        let shown = memory.status == MemoryStatus::Active
+            && sharing::is_visible(memory.privacy, &memory.created_by, user_id)
+            && (project.is_none() || memory.project_id.as_deref() == project);
+        if !shown {
+            continue;
         }
-        Err(msg) => {
-            println!("  {} Dimensions     {}", "WARN".yellow(), msg.yellow());
-            // Not a critical failure — just a warning
+        match groups.iter_mut().find(|(p, _)| *p == memory.project_id) {
+            Some((_, members)) => members.push((memory, score)),
+            None => groups.push((memory.project_id.clone(), vec![(memory, score)])),
         }
     }
+    for (_, members) in &mut groups {
+        members.truncate(limit);
+    }
+    groups.retain(|(_, members)| !members.is_empty());
+    groups
+}
 
-    // 4. Hooks binary
-    match which::which("shabka-hooks") {
-        Ok(path) => {
-            println!(
-                "  {} Hooks binary   {}",
-                "OK".green(),
-                path.display().to_string().dimmed()
-            );
+async fn cmd_graph_top(
+    storage: &Storage,
+    user_id: &str,
+    project: Option<&str>,
+    limit: usize,
+    json: bool,
+) -> Result<bool> {
+    let ranking = storage
+        .centrality_ranking()
+        .await
+        .context("failed to read centrality")?;
+    let ids: Vec<Uuid> = ranking.iter().map(|c| c.memory_id).collect();
+    let memories = storage
+        .get_memories(&ids)
+        .await
+        .context("failed to fetch central memories")?;
+    let groups = central_memories_by_project(&ranking, &memories, user_id, project, limit);
+    telemetry::count(
+        shabka_core::telemetry::RESULTS,
+        groups.iter().map(|(_, members)| members.len()).sum(),
+    );
+
+    if json {
+        let results: Vec<output::CentralMemoryOutput> = groups
+            .iter()
+            .flat_map(|(_, members)| members)
+            .map(|(memory, score)| output::CentralMemoryOutput {
+                id: memory.id,
+                title: memory.title.clone(),
+                kind: memory.kind,
+                project_id: memory.project_id.clone(),
+                verification: memory.verification,
+                degree: score.degree,
+                pagerank: score.pagerank,
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&results)?);
+        return Ok(!results.is_empty());
+    }
+
+    if groups.is_empty() {
+        println!(
+            "{}",
+            "No related memories to rank yet; centrality needs relations between memories."
+                .dimmed()
+        );
+        return Ok(false);
+    }
+
+    let mut unverified = 0;
+    for (i, (project_id, members)) in groups.iter().enumerate() {
+        if i > 0 {
+            println!();
         }
-        Err(_) => {
-            println!(
-                "  {} Hooks binary   {}",
-                "FAIL".red(),
-                "shabka-hooks not found in PATH".red()
-            );
+        println!("{}", project_id.as_deref().unwrap_or("(no project)").bold());
+        println!(
+            "{:<12} {:<6} {:<6} {:<12} {}",
+            "ID".dimmed(),
+            "Score".dimmed(),
+            "Links".dimmed(),
+            "Kind".dimmed(),
+            "Title".dimmed()
+        );
+        for (memory, score) in members {
+            let marker = if memory.verification == VerificationStatus::Verified {
+                String::new()
+            } else {
+                unverified += 1;
+                format!(" [{}]", memory.verification).yellow().to_string()
+            };
             println!(
-                "       {} Install with: {}",
-                "hint:".dimmed(),
-                "just cli-install".cyan()
+                "{:<12} {:<6.2} {:<6} {:<12} {}{}",
+                memory.id.to_string()[..8].to_string().cyan(),
+                score.pagerank,
+                score.degree,
+                memory.kind.to_string(),
+                memory.title,
+                marker
             );
-            critical_fail = true;
         }
     }
+    if unverified > 0 {
+        println!(
+            "\n{} central memories are not verified; check them with `shabka verify <id>`.",
+            unverified.to_string().yellow()
+        );
+    }
+    Ok(true)
+}
 
-    // 5. Session buffers
-    let sessions_dir = dirs::config_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("/tmp"))
-        .join("shabka")
-        .join("sessions");
-    let buffer_count = if sessions_dir.exists() {
-        std::fs::read_dir(&sessions_dir)
-            .map(|entries| {
-                entries
-                    .flatten()
-                    .filter(|e| e.path().extension().and_then(|ext| ext.to_str()) == Some("jsonl"))
-                    .count()
-            })
-            .unwrap_or(0)
-    } else {
-        0
+// ---------------------------------------------------------------------------
+// similarity
+// ---------------------------------------------------------------------------
+
+/// Write every pair of memories whose stored embeddings score above
+/// `threshold` as CSV, most similar first.
+async fn cmd_similarity_export(
+    storage: &Storage,
+    labels: &LabelConfig,
+    threshold: f32,
+    output: &str,
+) -> Result<()> {
+    let to_stdout = output == output::STDIO;
+    let note = |msg: String| {
+        if to_stdout {
+            eprintln!("{msg}");
+        } else {
+            println!("{msg}");
+        }
     };
 
-    if buffer_count == 0 {
-        println!(
-            "  {} Buffers        {}",
-            "OK".green(),
-            "no active session buffers".dimmed()
+    let entries = storage
+        .timeline(&TimelineQuery {
+            limit: 10000,
+            ..Default::default()
+        })
+        .await
+        .context("failed to fetch timeline")?;
+    let ids: Vec<Uuid> = entries.iter().map(|e| e.id).collect();
+    let mut memories = storage
+        .get_memories(&ids)
+        .await
+        .context("failed to fetch memories")?;
+    // Titles end up in the file, so honour label export policies.
+    memories.retain(|m| labels.permits(m, Boundary::Export));
+
+    let embeddings = storage
+        .stored_embeddings()
+        .await
+        .context("failed to read stored embeddings")?;
+    if embeddings.is_empty() {
+        anyhow::bail!(
+            "no stored embeddings to compare (the SQLite backend keeps them; run `shabka reembed` if it has none)"
         );
+    }
+    let vectors: Vec<(Uuid, &[f32])> = memories
+        .iter()
+        .filter_map(|m| embeddings.get(&m.id).map(|v| (m.id, v.as_slice())))
+        .collect();
+    let titles: HashMap<Uuid, &str> = memories.iter().map(|m| (m.id, m.title.as_str())).collect();
+
+    let pairs = shabka_core::similarity::similar_pairs(&vectors, threshold);
+    let mut body = String::from("source_id,target_id,similarity,source_title,target_title\n");
+    for pair in &pairs {
+        body.push_str(&format!(
+            "{},{},{:.4},{},{}\n",
+            pair.source_id,
+            pair.target_id,
+            pair.similarity,
+            csv_field(titles[&pair.source_id]),
+            csv_field(titles[&pair.target_id]),
+        ));
+    }
+
+    if to_stdout {
+        print!("{body}");
+    } else {
+        std::fs::write(output, body)?;
+    }
+    note(format!(
+        "Wrote {} pairs above {threshold} from {} embedded memories to {}",
+        pairs.len(),
+        vectors.len(),
+        if to_stdout { "stdout" } else { output }
+    ));
+    Ok(())
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break.
+fn csv_field(value: &str) -> std::borrow::Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\"")).into()
     } else {
+        value.into()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// gaps
+// ---------------------------------------------------------------------------
+
+async fn cmd_gaps(storage: &Storage, project: &str, min_edits: usize, json: bool) -> Result<bool> {
+    let entries = storage
+        .timeline(&TimelineQuery {
+            project_id: Some(project.to_string()),
+            limit: 10000,
+            ..Default::default()
+        })
+        .await
+        .context("failed to fetch timeline")?;
+    let ids: Vec<Uuid> = entries.iter().map(|e| e.id).collect();
+    let memories = storage
+        .get_memories(&ids)
+        .await
+        .context("failed to fetch memories")?;
+
+    let report = gaps::analyze(project, &memories);
+    let gaps = report.gaps(min_edits);
+    let found = !gaps.is_empty();
+
+    if json {
+        let out = output::GapsOutput {
+            gaps: gaps.into_iter().cloned().collect(),
+            report,
+        };
+        println!("{}", serde_json::to_string_pretty(&out)?);
+        return Ok(found);
+    }
+
+    println!("{} {}", "Knowledge gaps for".bold(), project.cyan().bold());
+    println!(
+        "{} memories ({} edit logs) across {} sessions",
+        report.memories.to_string().cyan(),
+        report.edit_logs,
+        report.sessions
+    );
+
+    if !report.kinds.is_empty() {
+        println!();
+        println!("{}:", "Captured knowledge".bold());
+        for (kind, count) in &report.kinds {
+            println!("  {:<14} {:>4}", format!("{kind}:"), count);
+        }
+    }
+    if !report.tags.is_empty() {
+        let tags: Vec<String> = report
+            .tags
+            .iter()
+            .map(|(tag, count)| format!("{tag} ({count})"))
+            .collect();
+        println!();
+        println!("{}: {}", "Top tags".bold(), tags.join(", "));
+    }
+
+    if report.areas.is_empty() {
+        println!();
+        println!("No recorded edits for this project.");
+        return Ok(false);
+    }
+
+    println!();
+    println!("{}:", "Activity by area".bold());
+    for area in report.areas.iter().take(15) {
+        let documented = if area.is_documented() {
+            format!("{} decisions, {} lessons", area.decisions, area.lessons)
+                .green()
+                .to_string()
+        } else {
+            "undocumented".yellow().to_string()
+        };
         println!(
-            "  {} Buffers        {} active session buffer{}",
-            "WARN".yellow(),
-            buffer_count.to_string().yellow(),
-            if buffer_count == 1 { "" } else { "s" }
+            "  {:<40} {:>4} edits  {:>3} sessions  {}",
+            area.area, area.edits, area.sessions, documented
+        );
+    }
+    if report.areas.len() > 15 {
+        println!(
+            "  {}",
+            format!("... and {} more", report.areas.len() - 15).dimmed()
         );
     }
 
-    // Summary
     println!();
-    if critical_fail {
+    if gaps.is_empty() {
         println!(
-            "{}",
-            "Some checks failed. Fix the issues above and re-run `shabka doctor`.".red()
+            "{} No undocumented area has {} or more edits.",
+            style::OK.green(),
+            min_edits
         );
-        std::process::exit(1);
-    } else if !helix_ok {
+    } else {
+        println!("{}:", "Gaps".bold());
+        for area in &gaps {
+            println!(
+                "  {} {} {} {} edits, no decisions or lessons",
+                "!".yellow(),
+                area.area.bold(),
+                style::DASH,
+                area.edits
+            );
+        }
+        println!();
         println!(
-            "{}",
-            "All critical checks passed, but some warnings exist.".yellow()
+            "  {} Record what was decided or learned there with `shabka add --kind decision` or `--kind lesson`",
+            "-".dimmed()
         );
-    } else {
-        println!("{}", "All checks passed!".green());
     }
 
-    Ok(())
+    Ok(found)
 }
 
-#[allow(clippy::too_many_arguments)]
-async fn cmd_consolidate(
-    storage: &Storage,
-    embedder: &EmbeddingService,
-    config: &ShabkaConfig,
-    user_id: &str,
-    history: &HistoryLogger,
-    dry_run: bool,
-    min_cluster: Option<usize>,
-    min_age: Option<u64>,
-    json: bool,
-) -> Result<()> {
-    if !config.llm.enabled {
-        anyhow::bail!("Consolidation requires LLM. Enable it in config.toml under [llm].");
-    }
-
-    let llm = shabka_core::llm::LlmService::from_config(&config.llm)
-        .context("failed to create LLM service")?;
+fn cmd_questions(questions: &QuestionLog, project: Option<&str>, json: bool) -> Result<bool> {
+    let open = questions.open_questions(project);
+    let found = !open.is_empty();
 
-    let mut consolidate_config = config.consolidate.clone();
-    if let Some(min) = min_cluster {
-        consolidate_config.min_cluster_size = min;
-    }
-    if let Some(age) = min_age {
-        consolidate_config.min_age_days = age;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&open)?);
+        return Ok(found);
     }
-
-    if dry_run && !json {
-        println!("{}", "Dry run — no changes will be made".yellow());
+    if open.is_empty() {
+        println!("{}", "No unanswered questions logged.".dimmed());
+        return Ok(false);
     }
 
-    let result = shabka_core::consolidate::consolidate(
-        storage,
-        embedder,
-        &llm,
-        &consolidate_config,
-        user_id,
-        history,
-        dry_run,
-    )
-    .await?;
-
-    if json {
-        println!("{}", serde_json::to_string_pretty(&result)?);
-    } else {
+    println!("{}", "Questions without answers".bold());
+    println!(
+        "{:<6} {:<11} {:<6} {:<10} {}",
+        "Asked".dimmed(),
+        "Last".dimmed(),
+        "Best".dimmed(),
+        "Via".dimmed(),
+        "Query".dimmed()
+    );
+    for q in &open {
+        let best = q
+            .best_score
+            .map(|s| format!("{s:.2}"))
+            .unwrap_or_else(|| "-".to_string());
         println!(
-            "\n{}\n  Clusters found: {}\n  Clusters consolidated: {}\n  Memories superseded: {}\n  Memories created: {}",
-            "Consolidation complete".green().bold(),
-            result.clusters_found,
-            result.clusters_consolidated,
-            result.memories_superseded,
-            result.memories_created,
+            "{:<6} {:<11} {:<6} {:<10} {}",
+            q.times_asked.to_string().cyan(),
+            dates::date(q.last_asked),
+            best.red(),
+            q.channels.join(","),
+            q.query
         );
     }
+    println!();
+    println!(
+        "  {} Capture the answers with `shabka add` once you find them",
+        "-".dimmed()
+    );
 
-    Ok(())
+    Ok(found)
 }
 
 // ---------------------------------------------------------------------------
-// delete
+// doctor
 // ---------------------------------------------------------------------------
 
-#[allow(clippy::too_many_arguments)]
-async fn cmd_delete(
-    storage: &Storage,
-    history: &HistoryLogger,
-    user_id: &str,
-    id: Option<String>,
-    kind: Option<String>,
-    project: Option<String>,
-    status: Option<String>,
-    confirm: bool,
-    json: bool,
-) -> Result<()> {
-    if let Some(ref id_str) = id {
-        // Single delete
-        let memory_id = resolve_memory_id(storage, id_str).await?;
-        let memory = storage
-            .get_memory(memory_id)
-            .await
-            .context("memory not found")?;
-        let title = memory.title.clone();
-        let kind_str = memory.kind.to_string();
+/// "N tokens today", with the budget when one is set.
+fn token_usage_line(total: u64, budget: Option<u64>) -> String {
+    match budget {
+        Some(budget) => format!("{total} / {budget} tokens today"),
+        None => format!("{total} tokens today"),
+    }
+}
 
-        storage
-            .delete_memory(memory_id)
-            .await
-            .context("failed to delete memory")?;
+async fn cmd_doctor(config: &ShabkaConfig) -> Result<()> {
+    println!("{}", "Shabka Doctor".bold());
+    println!("{}", "=============".dimmed());
+    println!();
 
-        history.log(
-            &MemoryEvent::new(memory_id, EventAction::Deleted, user_id.to_string())
-                .with_title(&title),
-        );
+    let mut critical_fail = false;
 
-        if json {
+    // 1. HelixDB connectivity
+    let storage = create_backend(config).context("failed to create storage backend")?;
+    let helix_ok = match storage
+        .timeline(&TimelineQuery {
+            limit: 1,
+            ..Default::default()
+        })
+        .await
+    {
+        Ok(_) => {
             println!(
-                "{}",
-                serde_json::json!({
-                    "deleted": memory_id.to_string(),
-                    "title": title,
-                    "kind": kind_str,
-                })
+                "  {} HelixDB        {}",
+                "OK".green(),
+                format!("{}:{}", config.helix.url, config.helix.port).dimmed()
             );
-        } else {
+            true
+        }
+        Err(e) => {
             println!(
-                "{} {} ({}) [{}]",
-                "Deleted:".red(),
-                title,
-                memory_id.to_string()[..8].to_string().cyan(),
-                kind_str.magenta()
+                "  {} HelixDB        {} ({})",
+                "FAIL".red(),
+                format!("{}:{}", config.helix.url, config.helix.port).dimmed(),
+                format!("{e:#}").red()
             );
-        }
-    } else if kind.is_some() || project.is_some() || status.is_some() {
-        // Bulk delete with filters
-        if !confirm {
-            anyhow::bail!(
-                "bulk delete requires --confirm flag. Use filters (--kind, --project, --status) to select memories."
+            println!(
+                "       {} Start HelixDB with: {}",
+                "hint:".dimmed(),
+                "just db".cyan()
             );
+            critical_fail = true;
+            false
         }
+    };
 
-        let query = TimelineQuery {
-            limit: 10000,
-            project_id: project,
-            ..Default::default()
-        };
-        let mut entries = storage
-            .timeline(&query)
-            .await
-            .context("failed to fetch timeline")?;
-
-        // Apply kind filter
-        if let Some(ref kind_str) = kind {
-            if let Ok(k) = kind_str.parse::<MemoryKind>() {
-                entries.retain(|e| e.kind == k);
-            } else {
-                anyhow::bail!("unknown memory kind: {kind_str}");
+    // 2. Embedding provider
+    match EmbeddingService::from_config(&config.embedding) {
+        Ok(service) => {
+            // Try a test embed
+            match service.embed("shabka doctor test").await {
+                Ok(vec) => {
+                    println!(
+                        "  {} Embedding      {} / {} ({}d, vec_len={})",
+                        "OK".green(),
+                        service.provider_name().cyan(),
+                        service.model_id(),
+                        service.dimensions(),
+                        vec.len(),
+                    );
+                }
+                Err(e) => {
+                    println!(
+                        "  {} Embedding      {} / {} {} {}",
+                        "FAIL".red(),
+                        service.provider_name(),
+                        service.model_id(),
+                        style::DASH,
+                        format!("{e:#}").red()
+                    );
+                    critical_fail = true;
+                }
             }
         }
-
-        // Apply status filter
-        if let Some(ref status_str) = status {
-            let st: MemoryStatus = serde_json::from_str(&format!("\"{status_str}\""))
-                .map_err(|_| anyhow::anyhow!("unknown status: {status_str}"))?;
-            entries.retain(|e| e.status == st);
+        Err(e) => {
+            println!(
+                "  {} Embedding      {} {} {}",
+                "FAIL".red(),
+                config.embedding.provider,
+                style::DASH,
+                format!("{e}").red()
+            );
+            critical_fail = true;
         }
+    }
 
-        if entries.is_empty() {
-            if json {
-                println!("{}", serde_json::json!({ "deleted": 0 }));
+    // 3. LLM provider and token budget (only when enabled)
+    let llm_usage = TokenLedger::new().today();
+    let llm_budget = config.llm.daily_token_budget;
+    if config.llm.enabled && llm_usage.is_exhausted(llm_budget) {
+        println!(
+            "  {} LLM            {}",
+            "WARN".yellow(),
+            format!(
+                "{}, features use heuristics until tomorrow (UTC)",
+                token_usage_line(llm_usage.total, llm_budget)
+            )
+            .yellow()
+        );
+    } else if config.llm.enabled {
+        if llm_usage.total > 0 {
+            println!(
+                "  {} LLM tokens     {}",
+                "OK".green(),
+                token_usage_line(llm_usage.total, llm_budget).dimmed()
+            );
+        }
+        let parsing = llm_usage
+            .structured
+            .values()
+            .fold(ParseStats::default(), |acc, s| ParseStats {
+                calls: acc.calls + s.calls,
+                repaired: acc.repaired + s.repaired,
+                failed: acc.failed + s.failed,
+            });
+        if parsing.calls > 0 {
+            let line = format!(
+                "{} structured replies today, {} repaired, {} unparseable ({:.0}% malformed)",
+                parsing.calls,
+                parsing.repaired,
+                parsing.failed,
+                parsing.failure_rate() * 100.0
+            );
+            if parsing.failed > 0 {
+                println!("  {} LLM output     {}", "WARN".yellow(), line.yellow());
             } else {
-                println!("No matching memories found.");
+                println!("  {} LLM output     {}", "OK".green(), line.dimmed());
+            }
+        }
+        match shabka_core::llm::LlmService::from_config(&config.llm) {
+            Ok(llm) => match llm
+                .generate(LlmFeature::Check, "Reply with the single word OK.", None)
+                .await
+            {
+                Ok(_) => println!(
+                    "  {} LLM            {} / {}",
+                    "OK".green(),
+                    config.llm.provider.cyan(),
+                    config.llm.model
+                ),
+                Err(e) => {
+                    println!(
+                        "  {} LLM            {} / {} {} {}",
+                        "FAIL".red(),
+                        config.llm.provider,
+                        config.llm.model,
+                        style::DASH,
+                        format!("{e:#}").red()
+                    );
+                    critical_fail = true;
+                }
+            },
+            Err(e) => {
+                println!(
+                    "  {} LLM            {} {} {}",
+                    "FAIL".red(),
+                    config.llm.provider,
+                    style::DASH,
+                    format!("{e}").red()
+                );
+                critical_fail = true;
             }
-            return Ok(());
         }
+    }
 
-        let mut deleted = 0usize;
-        for entry in &entries {
-            if storage.delete_memory(entry.id).await.is_ok() {
-                history.log(
-                    &MemoryEvent::new(entry.id, EventAction::Deleted, user_id.to_string())
-                        .with_title(&entry.title),
+    // 4. Dimension compatibility
+    match config::check_dimensions(&config.embedding) {
+        Ok(()) => {
+            let state = EmbeddingState::load();
+            if state.provider.is_empty() {
+                println!(
+                    "  {} Dimensions     {}",
+                    "OK".green(),
+                    "no prior state (first run)".dimmed()
+                );
+            } else {
+                println!(
+                    "  {} Dimensions     {}d matches stored state",
+                    "OK".green(),
+                    state.dimensions
                 );
-                deleted += 1;
             }
         }
+        Err(e) => {
+            println!(
+                "  {} Dimensions     {}",
+                "WARN".yellow(),
+                e.to_string().yellow()
+            );
+            if let Some(hint) = e.hint() {
+                println!("       {} {hint}", "hint:".dimmed());
+            }
+            // Not a critical failure — just a warning
+        }
+    }
 
-        if json {
-            println!("{}", serde_json::json!({ "deleted": deleted }));
-        } else {
+    // 5. Hooks binary
+    match which::which("shabka-hooks") {
+        Ok(path) => {
             println!(
-                "{} {} memor{}",
-                "Deleted".red(),
-                deleted,
-                if deleted == 1 { "y" } else { "ies" }
+                "  {} Hooks binary   {}",
+                "OK".green(),
+                path.display().to_string().dimmed()
+            );
+        }
+        Err(_) => {
+            println!(
+                "  {} Hooks binary   {}",
+                "FAIL".red(),
+                "shabka-hooks not found in PATH".red()
             );
+            println!(
+                "       {} Install with: {}",
+                "hint:".dimmed(),
+                "just cli-install".cyan()
+            );
+            critical_fail = true;
         }
+    }
+
+    // 6. Session buffers
+    let sessions_dir = dirs::config_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from("/tmp"))
+        .join("shabka")
+        .join("sessions");
+    let buffer_count = if sessions_dir.exists() {
+        std::fs::read_dir(&sessions_dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|e| e.path().extension().and_then(|ext| ext.to_str()) == Some("jsonl"))
+                    .count()
+            })
+            .unwrap_or(0)
     } else {
-        anyhow::bail!(
-            "usage: shabka delete <ID> or shabka delete --kind <kind> --confirm\n\
-             Provide a memory ID for single delete, or use filters (--kind, --project, --status) with --confirm for bulk delete."
+        0
+    };
+
+    if buffer_count == 0 {
+        println!(
+            "  {} Buffers        {}",
+            "OK".green(),
+            "no active session buffers".dimmed()
+        );
+    } else {
+        println!(
+            "  {} Buffers        {} active session buffer{}",
+            "WARN".yellow(),
+            buffer_count.to_string().yellow(),
+            if buffer_count == 1 { "" } else { "s" }
+        );
+    }
+
+    // 7. Capture backlog
+    let backlog = shabka_core::quota::CaptureBacklog::new();
+    let backlog_count = backlog.len();
+    if backlog_count == 0 {
+        println!(
+            "  {} Backlog        {}",
+            "OK".green(),
+            "no captures held by quotas".dimmed()
+        );
+    } else {
+        println!(
+            "  {} Backlog        {} capture{} held by quotas",
+            "WARN".yellow(),
+            backlog_count.to_string().yellow(),
+            if backlog_count == 1 { "" } else { "s" }
+        );
+        println!(
+            "       {} Saved as quota frees up; inspect or delete {}",
+            "hint:".dimmed(),
+            backlog.path().display().to_string().cyan()
+        );
+    }
+
+    // 8. Helix schema (only meaningful for the helix backend)
+    if config.storage.backend == "helix" {
+        use shabka_core::storage::helix_schema::{HelixSchemaState, SchemaStatus};
+        match HelixSchemaState::load().status() {
+            SchemaStatus::Current => println!(
+                "  {} Helix schema   {}",
+                "OK".green(),
+                "matches this version of shabka".dimmed()
+            ),
+            SchemaStatus::Unknown => {
+                println!(
+                    "  {} Helix schema   {}",
+                    "WARN".yellow(),
+                    "never pushed from this machine".yellow()
+                );
+                println!(
+                    "       {} Push it with: {}",
+                    "hint:".dimmed(),
+                    "shabka helix init".cyan()
+                );
+            }
+            SchemaStatus::Drifted { version } => {
+                println!(
+                    "  {} Helix schema   pushed by shabka {}, bundled schema has changed",
+                    "WARN".yellow(),
+                    if version.is_empty() { "?" } else { &version }.yellow()
+                );
+                println!(
+                    "       {} Update it with: {}",
+                    "hint:".dimmed(),
+                    "shabka helix init".cyan()
+                );
+            }
+        }
+    }
+
+    // Summary
+    println!();
+    if critical_fail {
+        println!(
+            "{}",
+            "Some checks failed. Fix the issues above and re-run `shabka doctor`.".red()
+        );
+        std::process::exit(1);
+    } else if !helix_ok {
+        println!(
+            "{}",
+            "All critical checks passed, but some warnings exist.".yellow()
         );
+    } else {
+        println!("{}", "All checks passed!".green());
     }
 
     Ok(())
 }
 
-// ---------------------------------------------------------------------------
-// list
-// ---------------------------------------------------------------------------
-
-async fn cmd_list(
+async fn cmd_consolidate_preview(
     storage: &Storage,
-    kind: Option<String>,
-    status: Option<String>,
-    project: Option<String>,
-    limit: usize,
+    embedder: &EmbeddingService,
+    config: &ShabkaConfig,
+    min_cluster: Option<usize>,
+    min_age: Option<u64>,
     json: bool,
 ) -> Result<()> {
-    let kind_filter = kind
-        .as_deref()
-        .map(|s| {
-            s.parse::<MemoryKind>()
-                .map_err(|_| anyhow::anyhow!("unknown memory kind: {s}"))
-        })
-        .transpose()?;
-
-    let status_filter = status
-        .as_deref()
-        .map(|s| {
-            serde_json::from_str::<MemoryStatus>(&format!("\"{s}\""))
-                .map_err(|_| anyhow::anyhow!("unknown status: {s}"))
-        })
-        .transpose()?;
-
-    let query = TimelineQuery {
-        limit,
-        project_id: project,
-        kind: kind_filter,
-        status: status_filter,
-        ..Default::default()
-    };
-
-    let entries = storage
-        .timeline(&query)
-        .await
-        .context("failed to fetch timeline")?;
+    let mut consolidate_config = config.consolidate.clone();
+    if let Some(min) = min_cluster {
+        consolidate_config.min_cluster_size = min;
+    }
+    if let Some(age) = min_age {
+        consolidate_config.min_age_days = age;
+    }
 
+    let clusters = shabka_core::consolidate::preview(storage, embedder, &consolidate_config).await;
     if json {
-        println!("{}", serde_json::to_string_pretty(&entries)?);
+        println!("{}", serde_json::to_string_pretty(&clusters)?);
         return Ok(());
     }
-
-    if entries.is_empty() {
-        println!("No memories found.");
+    if clusters.is_empty() {
+        println!("{}", "No clusters to consolidate.".dimmed());
         return Ok(());
     }
 
-    // Table header
+    let now = chrono::Utc::now();
+    for (i, cluster) in clusters.iter().enumerate() {
+        let kinds = cluster
+            .kinds
+            .iter()
+            .map(|(kind, n)| format!("{n} {kind}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!(
+            "{} {} memories, similarity {:.2}–{:.2} (mean {:.2}), {}–{} days old, {}",
+            format!("Cluster {}:", i + 1).bold(),
+            cluster.members.len(),
+            cluster.similarity.min,
+            cluster.similarity.max,
+            cluster.similarity.mean,
+            (now - cluster.newest).num_days(),
+            (now - cluster.oldest).num_days(),
+            kinds
+        );
+        for member in &cluster.members {
+            println!(
+                "  {} {} {} {}",
+                member.id.to_string()[..8].dimmed(),
+                format!("{:.2}", member.similarity).cyan(),
+                format!("[{}]", member.kind).dimmed(),
+                member.title
+            );
+        }
+        println!();
+    }
     println!(
-        "  {}  {}  {}  {}  {}",
-        format!("{:<8}", "ID").dimmed(),
-        format!("{:<12}", "Kind").dimmed(),
-        format!("{:<5}", "Imp").dimmed(),
-        format!("{:<10}", "Date").dimmed(),
-        "Title".dimmed(),
+        "{} clusters, {} memories would be merged. Run {} to consolidate them.",
+        clusters.len(),
+        clusters.iter().map(|c| c.members.len()).sum::<usize>(),
+        "shabka consolidate".cyan()
     );
-    println!("{}", "─".repeat(78).dimmed());
-
-    for entry in &entries {
-        let short_id = &entry.id.to_string()[..8];
-        let date = entry.created_at.format("%Y-%m-%d");
-        let imp = format!("{:.0}%", entry.importance * 100.0);
-        println!(
-            "  {}  {:<12}  {:<5}  {}  {}",
-            short_id.cyan(),
-            entry.kind.to_string().magenta(),
-            imp.dimmed(),
-            date,
-            entry.title,
-        );
-    }
-
-    println!("{}", "─".repeat(78).dimmed());
-    println!(
-        "  {} memor{}",
-        entries.len(),
-        if entries.len() == 1 { "y" } else { "ies" }
-    );
-
     Ok(())
 }
 
-const DEMO_PREFIX: &str = "[demo] ";
-
-async fn cmd_demo(
+#[allow(clippy::too_many_arguments)]
+async fn cmd_consolidate(
     storage: &Storage,
     embedder: &EmbeddingService,
+    config: &ShabkaConfig,
     user_id: &str,
     history: &HistoryLogger,
-    clean: bool,
+    dry_run: bool,
+    min_cluster: Option<usize>,
+    min_age: Option<u64>,
+    json: bool,
+    run: &mut MaintenanceRun,
 ) -> Result<()> {
-    if clean {
-        return demo_clean(storage, history, user_id).await;
+    if !config.llm.enabled {
+        anyhow::bail!("Consolidation requires LLM. Enable it in config.toml under [llm].");
     }
 
-    // Check if demo data already exists
-    let timeline = storage
-        .timeline(&TimelineQuery {
-            limit: 500,
-            ..Default::default()
-        })
-        .await?;
-    if timeline.iter().any(|e| e.title.starts_with(DEMO_PREFIX)) {
-        println!(
-            "{} Demo data already exists. Use {} to remove it first.",
-            "Skipped.".yellow(),
-            "shabka demo --clean".cyan()
-        );
-        return Ok(());
-    }
+    let llm = shabka_core::llm::LlmService::from_config(&config.llm)
+        .context("failed to create LLM service")?
+        .with_language(config.output.language.as_deref());
 
-    println!("{}", "Seeding demo memories...".cyan());
+    let mut consolidate_config = config.consolidate.clone();
+    if let Some(min) = min_cluster {
+        consolidate_config.min_cluster_size = min;
+    }
+    if let Some(age) = min_age {
+        consolidate_config.min_age_days = age;
+    }
 
-    // 12 sample memories across all 9 kinds
-    let demos: Vec<(MemoryKind, &str, &str, f32, Vec<&str>)> = vec![
-        (
-            MemoryKind::Decision,
-            "[demo] Use JWT with short-lived access tokens for API auth",
-            "Chose JWT over session cookies for the REST API. Access tokens expire in 15 minutes, \
-             refresh tokens in 7 days. Stateless validation reduces database load. \
-             Trade-off: token revocation requires a deny-list check.",
-            0.9,
-            vec!["auth", "api", "security"],
-        ),
-        (
-            MemoryKind::Error,
-            "[demo] Connection pool exhaustion under load",
-            "The API started returning 503s during peak traffic. Root cause: default pool size \
-             of 10 connections was too low for 200 concurrent requests. Each request held a \
-             connection for ~50ms, creating a bottleneck at the pool checkout.",
-            0.8,
-            vec!["database", "performance", "production"],
-        ),
-        (
-            MemoryKind::Fix,
-            "[demo] Increase pool size and add connection timeout",
-            "Fixed the pool exhaustion by increasing max connections to 50 and adding a 5s \
-             checkout timeout with a retry. Also added connection pool metrics to the /health \
-             endpoint for early warning.",
-            0.8,
-            vec!["database", "performance"],
-        ),
-        (
-            MemoryKind::Pattern,
-            "[demo] Repository pattern for database access",
-            "All database access goes through repository structs that own a connection pool \
-             reference. Each entity has its own repository (UserRepo, OrderRepo). Repositories \
-             expose domain-specific methods, not raw SQL. This keeps SQL contained and testable \
-             with mock repositories.",
-            0.7,
-            vec!["architecture", "database", "testing"],
-        ),
-        (
-            MemoryKind::Observation,
-            "[demo] Users abandon onboarding at the email verification step",
-            "Analytics show 40% drop-off at email verification. Users sign up, receive the \
-             confirmation email (delivery confirmed via SendGrid), but never click the link. \
-             Hypothesis: the email lands in spam, or users expect magic-link login instead.",
-            0.6,
-            vec!["ux", "onboarding", "analytics"],
-        ),
-        (
-            MemoryKind::Lesson,
-            "[demo] Always add database indexes before load testing",
-            "Spent two days debugging slow queries that turned out to need a composite index \
-             on (user_id, created_at). The query planner was doing full table scans on 2M rows. \
-             Lesson: check EXPLAIN output for any query that filters or sorts, especially in \
-             join conditions.",
-            0.85,
-            vec!["database", "performance", "testing"],
-        ),
-        (
-            MemoryKind::Preference,
-            "[demo] Prefer Result<T> over panicking in library code",
-            "Library crates should propagate errors with Result, never panic. Use anyhow::Result \
-             in applications, thiserror for library error types. Reserve unwrap() for cases with \
-             proof of correctness (e.g., static regexes, known-valid parses).",
-            0.7,
-            vec!["rust", "error-handling", "conventions"],
-        ),
-        (
-            MemoryKind::Fact,
-            "[demo] PostgreSQL JSONB supports GIN indexes for containment queries",
-            "JSONB columns with a GIN index support fast @> (contains) queries. This means \
-             you can query JSON arrays and nested objects efficiently without extracting them \
-             into separate tables. GIN indexes are slower to update but fast for reads.",
-            0.5,
-            vec!["database", "postgresql"],
-        ),
-        (
-            MemoryKind::Todo,
-            "[demo] Migrate from bcrypt to argon2id for password hashing",
-            "bcrypt truncates at 72 bytes and has weaker GPU resistance than argon2id. Plan: \
-             add argon2id as the default hasher, re-hash on next login, keep bcrypt as fallback \
-             for un-migrated passwords. Target: next security sprint.",
-            0.6,
-            vec!["security", "auth", "migration"],
-        ),
-        (
-            MemoryKind::Decision,
-            "[demo] Use SQLite for local development, PostgreSQL for production",
-            "SQLite for dev gives instant setup with no Docker dependency. Feature flags gate \
-             PostgreSQL-specific features (LISTEN/NOTIFY, advisory locks). CI runs tests against \
-             both databases. The ORM layer abstracts differences.",
-            0.7,
-            vec!["database", "architecture", "dx"],
-        ),
-        (
-            MemoryKind::Pattern,
-            "[demo] Structured logging with correlation IDs across services",
-            "Every incoming request gets a correlation ID (X-Request-ID header or generated UUID). \
-             This ID propagates through all internal service calls and appears in every log line. \
-             Makes distributed tracing possible without a full tracing backend.",
-            0.7,
-            vec!["observability", "architecture", "microservices"],
-        ),
-        (
-            MemoryKind::Lesson,
-            "[demo] Feature flags should default to off in production",
-            "Shipped a half-built feature to production because the flag defaulted to true. \
-             Now every flag is off by default, requires explicit opt-in per environment, and \
-             has an owner + expiration date. Stale flags get cleaned up quarterly.",
-            0.8,
-            vec!["devops", "conventions", "production"],
-        ),
-    ];
+    run.config = serde_json::to_value(&consolidate_config)?;
 
-    let mut ids = Vec::new();
-    for (i, (kind, title, content, importance, tags)) in demos.iter().enumerate() {
-        let mut memory = Memory::new(
-            title.to_string(),
-            content.to_string(),
-            *kind,
-            user_id.to_string(),
+    if dry_run && !json {
+        println!(
+            "{}",
+            format!("Dry run {} no changes will be made", style::DASH).yellow()
         );
-        memory.importance = *importance;
-        memory.tags = tags.iter().map(|t| t.to_string()).collect();
+    }
 
-        let embed_text = format!("{} {}", title, content);
-        let embedding = embedder.embed(&embed_text).await?;
-        storage
-            .save_memory(&memory, Some(&embedding))
-            .await
-            .with_context(|| format!("failed to save demo memory {}", i + 1))?;
+    let result = shabka_core::consolidate::consolidate(
+        storage,
+        embedder,
+        &llm,
+        &consolidate_config,
+        user_id,
+        history,
+        dry_run,
+    )
+    .await?;
+    result.record_counts(run);
 
-        history.log(
-            &MemoryEvent::new(memory.id, EventAction::Created, user_id.to_string())
-                .with_title(*title),
+    if json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    } else {
+        println!(
+            "\n{}\n  Clusters found: {}\n  Clusters consolidated: {}\n  Memories superseded: {}\n  Memories created: {}",
+            "Consolidation complete".green().bold(),
+            result.clusters_found,
+            result.clusters_consolidated,
+            result.memories_superseded,
+            result.memories_created,
         );
-
-        println!("  {} {}", format!("[{}/12]", i + 1).dimmed(), title.cyan());
-        ids.push(memory.id);
+        if let Some(run_id) = result.run_id {
+            println!(
+                "  Run: {} (undo with {})",
+                &run_id.simple().to_string()[..8],
+                "shabka consolidate undo".cyan()
+            );
+        }
     }
 
-    // 5 relations between demo memories
-    let relations: Vec<(usize, usize, RelationType, f32)> = vec![
-        (2, 1, RelationType::Fixes, 0.95),      // Fix fixes Error
-        (1, 3, RelationType::CausedBy, 0.8), // Error caused_by Pattern (pool issue from repo pattern)
-        (0, 9, RelationType::Related, 0.7),  // JWT decision related to SQLite/PG decision
-        (9, 0, RelationType::Contradicts, 0.5), // SQLite decision contradicts JWT (stateless vs local)
-        (11, 4, RelationType::Supersedes, 0.6), // Feature flag lesson supersedes onboarding observation
-    ];
+    Ok(())
+}
 
-    println!("\n{}", "Creating relations...".cyan());
-    for (src_idx, tgt_idx, rel_type, strength) in &relations {
-        let relation = MemoryRelation {
-            source_id: ids[*src_idx],
-            target_id: ids[*tgt_idx],
-            relation_type: *rel_type,
-            strength: *strength,
+fn cmd_consolidate_runs(
+    log: &shabka_core::consolidate::ConsolidationLog,
+    json: bool,
+) -> Result<()> {
+    let mut runs = log.all();
+    runs.reverse();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&runs)?);
+        return Ok(());
+    }
+    if runs.is_empty() {
+        println!("{}", "No consolidation runs recorded.".dimmed());
+        return Ok(());
+    }
+    for run in &runs {
+        let state = match run.undone_at {
+            Some(at) => format!("undone {}", dates::minutes(at))
+                .dimmed()
+                .to_string(),
+            None => "active".green().to_string(),
         };
-        storage.add_relation(&relation).await?;
         println!(
-            "  {} {} → {}",
-            format!("{}", rel_type).magenta(),
-            demos[*src_idx].1.replace(DEMO_PREFIX, "").dimmed(),
-            demos[*tgt_idx].1.replace(DEMO_PREFIX, "").dimmed(),
+            "{} {} {} summaries, {} sources  {}",
+            run.id.simple().to_string()[..8].bold(),
+            dates::minutes(run.ran_at),
+            run.summaries.len(),
+            run.summaries.iter().map(|s| s.sources.len()).sum::<usize>(),
+            state
         );
+        for summary in &run.summaries {
+            println!(
+                "  {} {}",
+                summary.summary_id.to_string()[..8].dimmed(),
+                summary.title
+            );
+        }
     }
-
-    println!(
-        "\n{} Created {} demo memories and {} relations.\n\nTry:\n  {} Browse interactively\n  {} Search from CLI",
-        "✓".green().bold(),
-        ids.len(),
-        relations.len(),
-        "shabka tui".cyan(),
-        "shabka search \"authentication\"".cyan(),
-    );
-
     Ok(())
 }
 
-async fn demo_clean(storage: &Storage, history: &HistoryLogger, user_id: &str) -> Result<()> {
-    let timeline = storage
-        .timeline(&TimelineQuery {
-            limit: 500,
-            ..Default::default()
-        })
-        .await?;
-
-    let demo_entries: Vec<_> = timeline
-        .iter()
-        .filter(|e| e.title.starts_with(DEMO_PREFIX))
-        .collect();
+async fn cmd_consolidate_undo(
+    storage: &Storage,
+    log: &shabka_core::consolidate::ConsolidationLog,
+    history: &HistoryLogger,
+    user_id: &str,
+    run: Option<&str>,
+    delete: bool,
+    json: bool,
+) -> Result<()> {
+    let run = log.find(run)?;
+    let result =
+        shabka_core::consolidate::undo(storage, log, &run, delete, user_id, history).await?;
 
-    if demo_entries.is_empty() {
-        println!("{}", "No demo memories found.".yellow());
+    if json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
         return Ok(());
     }
-
     println!(
-        "{}",
-        format!("Removing {} demo memories...", demo_entries.len()).cyan()
+        "{} run {} from {}",
+        "Undid consolidation".green().bold(),
+        &run.id.simple().to_string()[..8],
+        dates::minutes(run.ran_at)
     );
-
-    for entry in &demo_entries {
-        storage.delete_memory(entry.id).await?;
-        history.log(
-            &MemoryEvent::new(entry.id, EventAction::Deleted, user_id.to_string())
-                .with_title(&entry.title),
+    println!("  Memories restored: {}", result.memories_restored);
+    if delete {
+        println!("  Summaries deleted: {}", result.summaries_deleted);
+    } else {
+        println!("  Summaries archived: {}", result.summaries_archived);
+        if !storage.capabilities().relation_removal {
+            println!(
+                "  {}",
+                "This backend can't remove relations; archived summaries keep their supersedes links."
+                    .yellow()
+            );
+        }
+    }
+    if result.summaries_missing > 0 {
+        println!(
+            "  {}",
+            format!(
+                "{} summaries were already deleted.",
+                result.summaries_missing
+            )
+            .dimmed()
         );
-        println!("  {} {}", "×".red(), entry.title.dimmed());
     }
-
-    println!(
-        "\n{} Removed {} demo memories.",
-        "✓".green().bold(),
-        demo_entries.len()
-    );
-
     Ok(())
 }
 
 // ---------------------------------------------------------------------------
-// check
+// delete
 // ---------------------------------------------------------------------------
 
-async fn cmd_check(storage: &Storage, repair: bool) -> Result<()> {
-    println!("Database Integrity Check");
-    println!("========================\n");
+#[allow(clippy::too_many_arguments)]
+async fn cmd_delete(
+    storage: &Storage,
+    history: &HistoryLogger,
+    user_id: &str,
+    id: Option<String>,
+    kind: Option<String>,
+    project: Option<String>,
+    status: Option<String>,
+    confirm: bool,
+    json: bool,
+) -> Result<()> {
+    if let Some(ref id_str) = id {
+        // Single delete
+        let memory_id = resolve_memory_id(storage, id_str).await?;
+        let memory = storage
+            .get_memory(memory_id)
+            .await
+            .context("memory not found")?;
+        let title = memory.title.clone();
+        let kind = memory.kind;
+        let kind_str = kind.to_string();
 
-    let report = match storage.integrity_check() {
-        Some(r) => r,
-        None => {
-            println!("  Integrity check is only available for SQLite storage.");
-            return Ok(());
-        }
-    };
+        storage
+            .delete_memory(memory_id)
+            .await
+            .context("failed to delete memory")?;
 
-    let missing_note = if report.missing_embeddings > 0 {
-        format!(" ({} missing)", report.missing_embeddings)
-    } else {
-        String::new()
-    };
+        history.log(
+            &MemoryEvent::new(memory_id, EventAction::Deleted, user_id.to_string())
+                .with_snapshot(&memory),
+        );
 
-    println!("  Memories:    {}", report.total_memories);
-    println!("  Embeddings:  {}{}", report.total_embeddings, missing_note);
-    println!("  Relations:   {}", report.total_relations);
-    println!("  Sessions:    {}", report.total_sessions);
-    println!(
-        "  SQLite:      {}",
-        if report.sqlite_integrity_ok {
-            "ok"
+        if json {
+            let out = output::DeleteOutput {
+                deleted: 1,
+                memories: vec![output::DeletedMemory {
+                    id: memory_id,
+                    title,
+                    kind,
+                }],
+            };
+            println!("{}", serde_json::to_string_pretty(&out)?);
         } else {
-            "FAILED"
+            println!(
+                "{} {} ({}) [{}]",
+                "Deleted:".red(),
+                title,
+                memory_id.to_string()[..8].to_string().cyan(),
+                kind_str.magenta()
+            );
+        }
+    } else if kind.is_some() || project.is_some() || status.is_some() {
+        // Bulk delete with filters
+        if !confirm {
+            anyhow::bail!(
+                "bulk delete requires --confirm flag. Use filters (--kind, --project, --status) to select memories."
+            );
         }
-    );
 
-    let has_issues = !report.orphaned_embeddings.is_empty()
-        || !report.broken_relations.is_empty()
-        || report.missing_embeddings > 0;
+        let query = TimelineQuery {
+            limit: 10000,
+            project_id: project,
+            ..Default::default()
+        };
+        let mut entries = storage
+            .timeline(&query)
+            .await
+            .context("failed to fetch timeline")?;
 
-    if has_issues {
-        println!("\n  Issues:");
-        if !report.orphaned_embeddings.is_empty() {
+        // Apply kind filter
+        if let Some(ref kind_str) = kind {
+            if let Ok(k) = kind_str.parse::<MemoryKind>() {
+                entries.retain(|e| e.kind == k);
+            } else {
+                anyhow::bail!("unknown memory kind: {kind_str}");
+            }
+        }
+
+        // Apply status filter
+        if let Some(ref status_str) = status {
+            let st: MemoryStatus = serde_json::from_str(&format!("\"{status_str}\""))
+                .map_err(|_| anyhow::anyhow!("unknown status: {status_str}"))?;
+            entries.retain(|e| e.status == st);
+        }
+
+        if entries.is_empty() {
+            if json {
+                let out = output::DeleteOutput {
+                    deleted: 0,
+                    memories: Vec::new(),
+                };
+                println!("{}", serde_json::to_string_pretty(&out)?);
+            } else {
+                println!("No matching memories found.");
+            }
+            return Ok(());
+        }
+
+        let snapshots: HashMap<Uuid, Memory> = if history.snapshots_enabled() {
+            let ids: Vec<Uuid> = entries.iter().map(|e| e.id).collect();
+            storage
+                .get_memories(&ids)
+                .await
+                .context("failed to fetch memories")?
+                .into_iter()
+                .map(|m| (m.id, m))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        let mut removed = Vec::new();
+        for entry in &entries {
+            if storage.delete_memory(entry.id).await.is_ok() {
+                let event = MemoryEvent::new(entry.id, EventAction::Deleted, user_id.to_string());
+                history.log(&match snapshots.get(&entry.id) {
+                    Some(memory) => event.with_snapshot(memory),
+                    None => event.with_title(&entry.title),
+                });
+                removed.push(output::DeletedMemory {
+                    id: entry.id,
+                    title: entry.title.clone(),
+                    kind: entry.kind,
+                });
+            }
+        }
+        let deleted = removed.len();
+
+        if json {
+            let out = output::DeleteOutput {
+                deleted,
+                memories: removed,
+            };
+            println!("{}", serde_json::to_string_pretty(&out)?);
+        } else {
             println!(
-                "    {} orphaned embeddings",
-                report.orphaned_embeddings.len()
+                "{} {} memor{}",
+                "Deleted".red(),
+                deleted,
+                if deleted == 1 { "y" } else { "ies" }
             );
         }
-        if !report.broken_relations.is_empty() {
-            println!("    {} broken relations", report.broken_relations.len());
+    } else {
+        anyhow::bail!(
+            "usage: shabka delete <ID> or shabka delete --kind <kind> --confirm\n\
+             Provide a memory ID for single delete, or use filters (--kind, --project, --status) with --confirm for bulk delete."
+        );
+    }
+
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// unarchive
+// ---------------------------------------------------------------------------
+
+#[allow(clippy::too_many_arguments)]
+async fn cmd_unarchive(
+    storage: &Storage,
+    history: &HistoryLogger,
+    user_id: &str,
+    id: Option<String>,
+    kind: Option<String>,
+    project: Option<String>,
+    all: bool,
+    boost: Option<f32>,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    if let Some(b) = boost {
+        if !(0.0..=1.0).contains(&b) {
+            anyhow::bail!("--boost must be between 0.0 and 1.0");
         }
-        if report.missing_embeddings > 0 {
-            println!(
-                "    {} memories without embeddings (run `shabka reembed`)",
-                report.missing_embeddings
+    }
+
+    let ids = if let Some(ref reference) = id {
+        vec![resolve_reference(storage, reference, Some(MemoryStatus::Archived)).await?]
+    } else if all {
+        let kind = kind
+            .as_deref()
+            .map(|s| {
+                s.parse::<MemoryKind>()
+                    .map_err(|_| anyhow::anyhow!("unknown memory kind: {s}"))
+            })
+            .transpose()?;
+        storage
+            .timeline(&TimelineQuery {
+                limit: 10000,
+                project_id: project,
+                kind,
+                status: Some(MemoryStatus::Archived),
+                ..Default::default()
+            })
+            .await
+            .context("failed to fetch timeline")?
+            .into_iter()
+            .map(|e| e.id)
+            .collect()
+    } else {
+        anyhow::bail!(
+            "usage: shabka unarchive <ID> or shabka unarchive --all [--kind <kind>]\n\
+             Browse archived memories with: shabka list --archived"
+        );
+    };
+
+    let memories = storage
+        .get_memories(&ids)
+        .await
+        .context("failed to fetch memories")?;
+    let now = chrono::Utc::now();
+    let mut restored = Vec::new();
+    for memory in memories
+        .iter()
+        .filter(|m| m.status == MemoryStatus::Archived)
+    {
+        if !dry_run {
+            let input = decay::restore_input(memory, boost, now);
+            let changes = shabka_core::history::diff_update(memory, &input);
+            if let Err(e) = storage.update_memory(memory.id, &input).await {
+                eprintln!("  Error restoring {}: {}", &memory.id.to_string()[..8], e);
+                continue;
+            }
+            history.log(
+                &MemoryEvent::new(memory.id, EventAction::Updated, user_id.to_string())
+                    .with_title(&memory.title)
+                    .with_changes(changes),
             );
         }
+        restored.push(output::DeletedMemory {
+            id: memory.id,
+            title: memory.title.clone(),
+            kind: memory.kind,
+        });
+    }
+
+    if json {
+        let out = output::UnarchiveOutput {
+            restored: if dry_run { 0 } else { restored.len() },
+            memories: restored,
+        };
+        println!("{}", serde_json::to_string_pretty(&out)?);
+        return Ok(());
+    }
+
+    if restored.is_empty() {
+        println!("No archived memories found.");
+        return Ok(());
+    }
+    for memory in &restored {
+        println!(
+            "  {} {} [{}]",
+            memory.id.to_string()[..8].to_string().cyan(),
+            memory.title,
+            memory.kind.to_string().magenta()
+        );
     }
+    let count = restored.len();
+    let noun = if count == 1 { "memory" } else { "memories" };
+    if dry_run {
+        println!(
+            "\n{}",
+            format!("Dry run {} would restore {count} {noun}.", style::DASH).yellow()
+        );
+    } else {
+        println!("{} {count} {noun}", "Restored".green());
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// list
+// ---------------------------------------------------------------------------
+
+#[allow(clippy::too_many_arguments)]
+async fn cmd_list(
+    storage: &Storage,
+    kind: Option<String>,
+    status: Option<String>,
+    project: Option<String>,
+    limit: usize,
+    json: bool,
+    jsonl: bool,
+    quiet: bool,
+    exclude: &ExcludeArgs,
+) -> Result<bool> {
+    let exclusions = exclude.apply(SearchQuery::default())?;
+    let kind_filter = kind
+        .as_deref()
+        .map(|s| {
+            s.parse::<MemoryKind>()
+                .map_err(|_| anyhow::anyhow!("unknown memory kind: {s}"))
+        })
+        .transpose()?;
+
+    let status_filter = status
+        .as_deref()
+        .map(|s| {
+            serde_json::from_str::<MemoryStatus>(&format!("\"{s}\""))
+                .map_err(|_| anyhow::anyhow!("unknown status: {s}"))
+        })
+        .transpose()?;
+
+    let query = TimelineQuery {
+        limit,
+        project_id: project,
+        kind: kind_filter,
+        status: status_filter,
+        ..Default::default()
+    };
+
+    let entries = if exclusions.has_exclusions() {
+        list_excluding(storage, query, &exclusions).await?
+    } else {
+        storage
+            .timeline(&query)
+            .await
+            .context("failed to fetch timeline")?
+    };
+    telemetry::count(shabka_core::telemetry::RESULTS, entries.len());
+
+    let found = !entries.is_empty();
+    if quiet {
+        return Ok(found);
+    }
+    if jsonl {
+        return output::print_jsonl(&entries).map(|()| found);
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(found);
+    }
+
+    if entries.is_empty() {
+        println!("{}", t!("no-memories"));
+        return Ok(false);
+    }
+
+    // Table header
+    println!(
+        "  {}  {}  {}  {}  {}",
+        format!("{:<8}", t!("column-id")).dimmed(),
+        format!("{:<12}", t!("column-kind")).dimmed(),
+        format!("{:<5}", t!("column-importance")).dimmed(),
+        format!("{:<10}", t!("column-created")).dimmed(),
+        t!("column-title").dimmed(),
+    );
+    println!("{}", style::rule(78).dimmed());
+
+    for entry in &entries {
+        let short_id = &entry.id.to_string()[..8];
+        let date = dates::when(entry.created_at);
+        let imp = format!("{:.0}%", entry.importance * 100.0);
+        println!(
+            "  {}  {:<12}  {:<5}  {:<10}  {}",
+            short_id.cyan(),
+            entry.kind.to_string().magenta(),
+            imp.dimmed(),
+            date,
+            entry.title,
+        );
+    }
+
+    println!("{}", style::rule(78).dimmed());
+    println!("  {}", t!("memory-count", count = entries.len()));
+    if status_filter == Some(MemoryStatus::Archived) {
+        println!(
+            "  {}",
+            "Restore with: shabka unarchive <id> (or --all)".dimmed()
+        );
+    }
+
+    Ok(true)
+}
+
+/// Page through the timeline until `query.limit` entries survive the
+/// exclusions (tags and content aren't on timeline entries, so each page's
+/// memories are loaded and checked).
+async fn list_excluding(
+    storage: &Storage,
+    mut query: TimelineQuery,
+    exclusions: &SearchQuery,
+) -> Result<Vec<TimelineEntry>> {
+    let limit = query.limit;
+    let mut kept = Vec::new();
+    while kept.len() < limit {
+        let page = storage
+            .timeline(&query)
+            .await
+            .context("failed to fetch timeline")?;
+        let page_len = page.len();
+        let ids: Vec<Uuid> = page.iter().map(|e| e.id).collect();
+        let allowed: std::collections::HashSet<Uuid> = storage
+            .get_memories(&ids)
+            .await?
+            .iter()
+            .filter(|m| exclusions.matches(m))
+            .map(|m| m.id)
+            .collect();
+        kept.extend(page.into_iter().filter(|e| allowed.contains(&e.id)));
+        if page_len < query.limit {
+            break;
+        }
+        query.offset += page_len;
+    }
+    kept.truncate(limit);
+    Ok(kept)
+}
+
+const DEMO_PREFIX: &str = "[demo] ";
+
+async fn cmd_demo(
+    storage: &Storage,
+    embedder: &EmbeddingService,
+    user_id: &str,
+    history: &HistoryLogger,
+    clean: bool,
+) -> Result<()> {
+    if clean {
+        return demo_clean(storage, history, user_id).await;
+    }
+
+    if !demo_entries(storage).await?.is_empty() {
+        println!(
+            "{} Demo data already exists. Use {} to remove it first.",
+            "Skipped.".yellow(),
+            "shabka demo --clean".cyan()
+        );
+        return Ok(());
+    }
+
+    let (memories, relations) = demo_seed(storage, embedder, user_id, history).await?;
+    println!(
+        "\n{} Created {} demo memories and {} relations.\n\nTry:\n  {} Browse interactively\n  {} Search from CLI\n  {} Take the guided tour",
+        style::OK.green().bold(),
+        memories,
+        relations,
+        "shabka tui".cyan(),
+        "shabka search \"authentication\"".cyan(),
+        "shabka demo --tutorial".cyan(),
+    );
+
+    Ok(())
+}
+
+/// Demo memories currently stored, newest first.
+async fn demo_entries(storage: &Storage) -> Result<Vec<TimelineEntry>> {
+    let timeline = storage
+        .timeline(&TimelineQuery {
+            limit: 500,
+            ..Default::default()
+        })
+        .await?;
+    Ok(timeline
+        .into_iter()
+        .filter(|e| e.title.starts_with(DEMO_PREFIX))
+        .collect())
+}
+
+/// Save the demo memories and their relations, returning how many of each.
+async fn demo_seed(
+    storage: &Storage,
+    embedder: &EmbeddingService,
+    user_id: &str,
+    history: &HistoryLogger,
+) -> Result<(usize, usize)> {
+    println!("{}", "Seeding demo memories...".cyan());
+
+    // 12 sample memories across all 9 kinds
+    let demos: Vec<(MemoryKind, &str, &str, f32, Vec<&str>)> = vec![
+        (
+            MemoryKind::Decision,
+            "[demo] Use JWT with short-lived access tokens for API auth",
+            "Chose JWT over session cookies for the REST API. Access tokens expire in 15 minutes, \
+             refresh tokens in 7 days. Stateless validation reduces database load. \
+             Trade-off: token revocation requires a deny-list check.",
+            0.9,
+            vec!["auth", "api", "security"],
+        ),
+        (
+            MemoryKind::Error,
+            DEMO_ERROR,
+            "The API started returning 503s during peak traffic. Root cause: default pool size \
+             of 10 connections was too low for 200 concurrent requests. Each request held a \
+             connection for ~50ms, creating a bottleneck at the pool checkout.",
+            0.8,
+            vec!["database", "performance", "production"],
+        ),
+        (
+            MemoryKind::Fix,
+            DEMO_FIX,
+            "Fixed the pool exhaustion by increasing max connections to 50 and adding a 5s \
+             checkout timeout with a retry. Also added connection pool metrics to the /health \
+             endpoint for early warning.",
+            0.8,
+            vec!["database", "performance"],
+        ),
+        (
+            MemoryKind::Pattern,
+            "[demo] Repository pattern for database access",
+            "All database access goes through repository structs that own a connection pool \
+             reference. Each entity has its own repository (UserRepo, OrderRepo). Repositories \
+             expose domain-specific methods, not raw SQL. This keeps SQL contained and testable \
+             with mock repositories.",
+            0.7,
+            vec!["architecture", "database", "testing"],
+        ),
+        (
+            MemoryKind::Observation,
+            "[demo] Users abandon onboarding at the email verification step",
+            "Analytics show 40% drop-off at email verification. Users sign up, receive the \
+             confirmation email (delivery confirmed via SendGrid), but never click the link. \
+             Hypothesis: the email lands in spam, or users expect magic-link login instead.",
+            0.6,
+            vec!["ux", "onboarding", "analytics"],
+        ),
+        (
+            MemoryKind::Lesson,
+            "[demo] Always add database indexes before load testing",
+            "Spent two days debugging slow queries that turned out to need a composite index \
+             on (user_id, created_at). The query planner was doing full table scans on 2M rows. \
+             Lesson: check EXPLAIN output for any query that filters or sorts, especially in \
+             join conditions.",
+            0.85,
+            vec!["database", "performance", "testing"],
+        ),
+        (
+            MemoryKind::Preference,
+            "[demo] Prefer Result<T> over panicking in library code",
+            "Library crates should propagate errors with Result, never panic. Use anyhow::Result \
+             in applications, thiserror for library error types. Reserve unwrap() for cases with \
+             proof of correctness (e.g., static regexes, known-valid parses).",
+            0.7,
+            vec!["rust", "error-handling", "conventions"],
+        ),
+        (
+            MemoryKind::Fact,
+            "[demo] PostgreSQL JSONB supports GIN indexes for containment queries",
+            "JSONB columns with a GIN index support fast @> (contains) queries. This means \
+             you can query JSON arrays and nested objects efficiently without extracting them \
+             into separate tables. GIN indexes are slower to update but fast for reads.",
+            0.5,
+            vec!["database", "postgresql"],
+        ),
+        (
+            MemoryKind::Todo,
+            "[demo] Migrate from bcrypt to argon2id for password hashing",
+            "bcrypt truncates at 72 bytes and has weaker GPU resistance than argon2id. Plan: \
+             add argon2id as the default hasher, re-hash on next login, keep bcrypt as fallback \
+             for un-migrated passwords. Target: next security sprint.",
+            0.6,
+            vec!["security", "auth", "migration"],
+        ),
+        (
+            MemoryKind::Decision,
+            "[demo] Use SQLite for local development, PostgreSQL for production",
+            "SQLite for dev gives instant setup with no Docker dependency. Feature flags gate \
+             PostgreSQL-specific features (LISTEN/NOTIFY, advisory locks). CI runs tests against \
+             both databases. The ORM layer abstracts differences.",
+            0.7,
+            vec!["database", "architecture", "dx"],
+        ),
+        (
+            MemoryKind::Pattern,
+            "[demo] Structured logging with correlation IDs across services",
+            "Every incoming request gets a correlation ID (X-Request-ID header or generated UUID). \
+             This ID propagates through all internal service calls and appears in every log line. \
+             Makes distributed tracing possible without a full tracing backend.",
+            0.7,
+            vec!["observability", "architecture", "microservices"],
+        ),
+        (
+            MemoryKind::Lesson,
+            "[demo] Feature flags should default to off in production",
+            "Shipped a half-built feature to production because the flag defaulted to true. \
+             Now every flag is off by default, requires explicit opt-in per environment, and \
+             has an owner + expiration date. Stale flags get cleaned up quarterly.",
+            0.8,
+            vec!["devops", "conventions", "production"],
+        ),
+    ];
+
+    let mut ids = Vec::new();
+    for (i, (kind, title, content, importance, tags)) in demos.iter().enumerate() {
+        let mut memory = Memory::new(
+            title.to_string(),
+            content.to_string(),
+            *kind,
+            user_id.to_string(),
+        );
+        memory.importance = *importance;
+        memory.tags = tags.iter().map(|t| t.to_string()).collect();
+
+        let embed_text = format!("{} {}", title, content);
+        let embedding = embedder.embed(&embed_text).await?;
+        storage
+            .save_memory(&memory, Some(&embedding))
+            .await
+            .with_context(|| format!("failed to save demo memory {}", i + 1))?;
+
+        history.log(
+            &MemoryEvent::new(memory.id, EventAction::Created, user_id.to_string())
+                .with_title(*title),
+        );
+
+        println!("  {} {}", format!("[{}/12]", i + 1).dimmed(), title.cyan());
+        ids.push(memory.id);
+    }
+
+    // 5 relations between demo memories
+    let relations: Vec<(usize, usize, RelationType, f32)> = vec![
+        (2, 1, RelationType::Fixes, 0.95),      // Fix fixes Error
+        (1, 3, RelationType::CausedBy, 0.8), // Error caused_by Pattern (pool issue from repo pattern)
+        (0, 9, RelationType::Related, 0.7),  // JWT decision related to SQLite/PG decision
+        (9, 0, RelationType::Contradicts, 0.5), // SQLite decision contradicts JWT (stateless vs local)
+        (11, 4, RelationType::Supersedes, 0.6), // Feature flag lesson supersedes onboarding observation
+    ];
+
+    println!("\n{}", "Creating relations...".cyan());
+    for (src_idx, tgt_idx, rel_type, strength) in &relations {
+        let relation = MemoryRelation {
+            source_id: ids[*src_idx],
+            target_id: ids[*tgt_idx],
+            relation_type: *rel_type,
+            strength: *strength,
+        };
+        storage.add_relation(&relation).await?;
+        println!(
+            "  {} {} {} {}",
+            format!("{}", rel_type).magenta(),
+            demos[*src_idx].1.replace(DEMO_PREFIX, "").dimmed(),
+            style::ARROW,
+            demos[*tgt_idx].1.replace(DEMO_PREFIX, "").dimmed(),
+        );
+    }
+
+    Ok((ids.len(), relations.len()))
+}
+
+async fn demo_clean(storage: &Storage, history: &HistoryLogger, user_id: &str) -> Result<()> {
+    let demo_entries = demo_entries(storage).await?;
+
+    if demo_entries.is_empty() {
+        println!("{}", "No demo memories found.".yellow());
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        format!("Removing {} demo memories...", demo_entries.len()).cyan()
+    );
+
+    for entry in &demo_entries {
+        storage.delete_memory(entry.id).await?;
+        history.log(
+            &MemoryEvent::new(entry.id, EventAction::Deleted, user_id.to_string())
+                .with_title(&entry.title),
+        );
+        println!("  {} {}", style::REMOVED.red(), entry.title.dimmed());
+    }
+
+    println!(
+        "\n{} Removed {} demo memories.",
+        style::OK.green().bold(),
+        demo_entries.len()
+    );
+
+    Ok(())
+}
+
+/// ID prefix lengths the tutorial tries, shortest first. UUIDv7 prefixes
+/// are timestamps, and the demo memories are all saved within a moment.
+const SHORT_REF_LENS: [usize; 3] = [8, 13, 18];
+
+/// The demo memories the tutorial walks through.
+const DEMO_ERROR: &str = "[demo] Connection pool exhaustion under load";
+const DEMO_FIX: &str = "[demo] Increase pool size and add connection timeout";
+
+/// Progress through `shabka demo --tutorial`.
+struct Tutorial {
+    steps: usize,
+    step: usize,
+    passed: usize,
+    failed: usize,
+    stopped: bool,
+    /// Wait for Enter before each step.
+    interactive: bool,
+}
+
+impl Tutorial {
+    /// Introduce the next step and the command it runs. Returns `false`
+    /// when the user chose to stop.
+    fn step(&mut self, title: &str, about: &str, command: &str) -> Result<bool> {
+        self.step += 1;
+        println!(
+            "\n{} {}\n{about}\n\n  $ {}",
+            format!("Step {}/{}:", self.step, self.steps).bold(),
+            title.bold(),
+            command.cyan()
+        );
+        let go = !self.interactive
+            || tutorial_continue(
+                std::io::stdin().lock(),
+                &mut std::io::stdout(),
+                "Press Enter to run it, or q to stop: ",
+            )?;
+        if go {
+            println!();
+        } else {
+            self.stopped = true;
+        }
+        Ok(go)
+    }
+
+    /// Report whether the step did what `expected` says.
+    fn check(&mut self, outcome: Result<bool>, expected: &str) {
+        match outcome {
+            Ok(true) => {
+                self.passed += 1;
+                println!("\n{} {expected}", style::OK.green());
+            }
+            Ok(false) => {
+                self.failed += 1;
+                println!("\n{} expected: {expected}", style::FAIL.red());
+            }
+            Err(e) => {
+                self.failed += 1;
+                println!("\n{} {e:#}", style::FAIL.red());
+            }
+        }
+    }
+
+    fn finish(self) -> Result<()> {
+        println!();
+        if self.stopped {
+            println!(
+                "Stopped after {} of {} steps. The demo memories stay; run {} to pick up again.",
+                self.passed + self.failed,
+                self.steps,
+                "shabka demo --tutorial".cyan()
+            );
+        } else if self.failed == 0 {
+            println!(
+                "{} All {} steps worked. Remove the demo memories with {}, then run {} in a project of your own.",
+                style::OK.green().bold(),
+                self.steps,
+                "shabka demo --clean".cyan(),
+                "shabka init".cyan()
+            );
+        }
+        if self.failed > 0 {
+            anyhow::bail!(
+                "{} of {} tutorial steps failed; `shabka doctor` checks the setup",
+                self.failed,
+                self.passed + self.failed
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Print `prompt` and read a line: `false` for `q` or end of input.
+fn tutorial_continue(
+    mut input: impl std::io::BufRead,
+    out: &mut impl std::io::Write,
+    prompt: &str,
+) -> Result<bool> {
+    write!(out, "{}", prompt.dimmed())?;
+    out.flush()?;
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Ok(false);
+    }
+    Ok(!line.trim().eq_ignore_ascii_case("q"))
+}
+
+/// Shortest prefix of `id` from [`SHORT_REF_LENS`] that names only that
+/// memory, or the full ID.
+async fn short_ref(storage: &Storage, id: Uuid) -> Result<String> {
+    let full = id.to_string();
+    for len in SHORT_REF_LENS {
+        let prefix = &full[..len];
+        if storage.find_by_id_prefix(prefix, 2).await?.len() == 1 {
+            return Ok(prefix.to_string());
+        }
+    }
+    Ok(full)
+}
+
+/// `shabka demo --tutorial`: seed the demo memories if needed, then run
+/// the everyday commands on them one at a time, checking each worked.
+/// `interactive` waits for Enter before each step.
+async fn cmd_demo_tutorial(
+    storage: &Storage,
+    embedder: &EmbeddingService,
+    config: &ShabkaConfig,
+    questions: &QuestionLog,
+    user_id: &str,
+    history: &HistoryLogger,
+    interactive: bool,
+) -> Result<()> {
+    let mut entries = demo_entries(storage).await?;
+    if entries.is_empty() {
+        demo_seed(storage, embedder, user_id, history).await?;
+        entries = demo_entries(storage).await?;
+    }
+    let find = |title: &str| {
+        entries
+            .iter()
+            .find(|e| e.title == title)
+            .map(|e| e.id)
+            .with_context(|| {
+                format!("demo memory '{title}' is missing; run `shabka demo --clean` and try again")
+            })
+    };
+    let error_id = find(DEMO_ERROR)?;
+    let fix_id = find(DEMO_FIX)?;
+    let error_ref = short_ref(storage, error_id).await?;
+    let fix_ref = short_ref(storage, fix_id).await?;
+
+    println!(
+        "\n{}\nEach step shows a command, runs it on the demo memories and checks the result.",
+        "Shabka tutorial".bold()
+    );
+    let mut tour = Tutorial {
+        steps: 6,
+        step: 0,
+        passed: 0,
+        failed: 0,
+        stopped: false,
+        interactive,
+    };
+
+    if !tour.step(
+        "Search",
+        "Search matches meaning as well as keywords, so describe what you need in your own words.",
+        "shabka search \"connection pool\"",
+    )? {
+        return tour.finish();
+    }
+    let request = SearchRequest::new(SearchQuery::parse("connection pool")?).limit(5);
+    let out = SearchOutput::default();
+    let found = cmd_search(storage, embedder, questions, user_id, request, out).await;
+    tour.check(found, "search found memories about the connection pool");
+
+    if !tour.step(
+        "Get",
+        "Every command takes an ID prefix (or title words) to name a memory; get shows all of it.",
+        &format!("shabka get {error_ref}"),
+    )? {
+        return tour.finish();
+    }
+    let got = cmd_get(storage, &error_ref, false, false).await;
+    tour.check(got.map(|()| true), "the memory was found by its ID prefix");
+
+    if !tour.step(
+        "Chain",
+        "Relations connect memories. chain follows them, here from an error to the fix for it.",
+        &format!("shabka chain {error_ref}"),
+    )? {
+        return tour.finish();
+    }
+    let chained = async {
+        cmd_chain(
+            storage,
+            &error_ref,
+            None,
+            config.graph.max_chain_depth,
+            config.graph.max_chain_nodes,
+            false,
+        )
+        .await?;
+        let relations = storage.get_relations(error_id).await?;
+        Ok::<_, anyhow::Error>(
+            relations
+                .iter()
+                .any(|r| r.relation_type == RelationType::Fixes && r.source_id == fix_id),
+        )
+    }
+    .await;
+    tour.check(chained, "the error links to its fix");
+
+    if !tour.step(
+        "Verify",
+        "Marking a memory verified raises its trust, so search and context packs favour it.",
+        &format!("shabka verify {fix_ref} --status verified"),
+    )? {
+        return tour.finish();
+    }
+    let verified = async {
+        cmd_verify(
+            storage,
+            history,
+            &config.retention,
+            user_id,
+            &fix_ref,
+            "verified",
+            None,
+            None,
+        )
+        .await?;
+        let memory = storage.get_memory(fix_id).await?;
+        Ok::<_, anyhow::Error>(memory.verification == VerificationStatus::Verified)
+    }
+    .await;
+    tour.check(verified, "the fix is now verified");
+
+    if !tour.step(
+        "Consolidate (dry run)",
+        "Consolidation merges clusters of similar memories into one. The preview lists the \
+         clusters without changing anything or calling the LLM; `shabka consolidate --dry-run` \
+         then drafts the merges with the LLM, still without saving them.",
+        "shabka consolidate preview --min-age 0",
+    )? {
+        return tour.finish();
+    }
+    let previewed = cmd_consolidate_preview(storage, embedder, config, None, Some(0), false).await;
+    tour.check(
+        previewed.map(|()| true),
+        "the preview ran and left the memories as they were",
+    );
+
+    let pack_path = std::env::temp_dir().join("shabka-tutorial-context.md");
+    let pack_path = pack_path.to_string_lossy().into_owned();
+    if !tour.step(
+        "Context pack",
+        "A context pack fits the most useful memories for a topic into a token budget, \
+         ready to paste into a prompt or hand to an agent.",
+        &format!("shabka context-pack \"database performance\" --tokens 1000 --output {pack_path}"),
+    )? {
+        return tour.finish();
+    }
+    let packed = async {
+        let request =
+            SearchRequest::new(SearchQuery::parse("database performance")?).visible_to(user_id);
+        cmd_context_pack(
+            storage,
+            embedder,
+            request,
+            1000,
+            false,
+            Some(pack_path.clone()),
+        )
+        .await?;
+        let pack = std::fs::read_to_string(&pack_path)?;
+        Ok::<_, anyhow::Error>(pack.contains(DEMO_PREFIX.trim()))
+    }
+    .await;
+    tour.check(packed, "the pack holds demo memories");
+
+    tour.finish()
+}
+
+// ---------------------------------------------------------------------------
+// check
+// ---------------------------------------------------------------------------
+
+async fn cmd_check(
+    storage: &Storage,
+    repair: bool,
+    quiet: bool,
+    run: &mut MaintenanceRun,
+) -> Result<bool> {
+    if !storage.capabilities().integrity_check {
+        if !quiet {
+            println!(
+                "Integrity check is not supported by the {} backend.",
+                storage.backend_name()
+            );
+        }
+        return Ok(false);
+    }
+    let Some(report) = storage.integrity_check() else {
+        anyhow::bail!("integrity check failed to run");
+    };
+
+    let journal = CaptureJournal::new();
+    let unfinished = journal.unfinished().len();
+
+    let has_issues = !report.orphaned_embeddings.is_empty()
+        || !report.broken_relations.is_empty()
+        || report.missing_embeddings > 0
+        || unfinished > 0;
+    let pass = report.sqlite_integrity_ok
+        && report.orphaned_embeddings.is_empty()
+        && report.broken_relations.is_empty();
+    let repairable = !report.orphaned_embeddings.is_empty() || !report.broken_relations.is_empty();
+    run.count("orphaned_embeddings", report.orphaned_embeddings.len());
+    run.count("broken_relations", report.broken_relations.len());
+    run.count("missing_embeddings", report.missing_embeddings);
+    run.count("unfinished_journal_entries", unfinished);
+    if !report.sqlite_integrity_ok {
+        run.error("SQLite integrity check failed");
+    }
+
+    if quiet {
+        if repair && repairable {
+            if let Some((orphans, relations)) = storage.repair(&report) {
+                run.count("orphans_removed", orphans);
+                run.count("relations_removed", relations);
+            }
+        }
+        if repair && unfinished > 0 {
+            recover_journal(storage, &journal).await;
+        }
+        return Ok(has_issues || !pass);
+    }
+
+    println!("Database Integrity Check");
+    println!("========================\n");
+
+    let missing_note = if report.missing_embeddings > 0 {
+        format!(" ({} missing)", report.missing_embeddings)
+    } else {
+        String::new()
+    };
+
+    println!("  Memories:    {}", report.total_memories);
+    println!("  Embeddings:  {}{}", report.total_embeddings, missing_note);
+    println!("  Relations:   {}", report.total_relations);
+    println!("  Sessions:    {}", report.total_sessions);
+    println!(
+        "  SQLite:      {}",
+        if report.sqlite_integrity_ok {
+            "ok"
+        } else {
+            "FAILED"
+        }
+    );
+
+    if has_issues {
+        println!("\n  Issues:");
+        if !report.orphaned_embeddings.is_empty() {
+            println!(
+                "    {} orphaned embeddings",
+                report.orphaned_embeddings.len()
+            );
+        }
+        if !report.broken_relations.is_empty() {
+            println!("    {} broken relations", report.broken_relations.len());
+        }
+        if report.missing_embeddings > 0 {
+            println!(
+                "    {} memories without embeddings (run `shabka reembed`)",
+                report.missing_embeddings
+            );
+        }
+        if unfinished > 0 {
+            println!(
+                "    {} unfinished capture journal entries ({})",
+                unfinished,
+                journal.path().display()
+            );
+        }
+    }
+
+    if repair && repairable {
+        println!("\n  Repairing...");
+        if let Some((orphans, relations)) = storage.repair(&report) {
+            run.count("orphans_removed", orphans);
+            run.count("relations_removed", relations);
+            println!("    Removed {} orphaned embeddings", orphans);
+            println!("    Removed {} broken relations", relations);
+        }
+    }
+    if repair && unfinished > 0 {
+        if !repairable {
+            println!("\n  Repairing...");
+        }
+        let resolved = recover_journal(storage, &journal).await;
+        println!(
+            "    Resolved {} of {} journal entries",
+            resolved.len(),
+            unfinished
+        );
+        for (entry, outcome) in &resolved {
+            println!("      {}  {}", &entry.id.to_string()[..8], outcome);
+        }
+    }
+
+    println!("\n  Result: {}", if pass { "PASS" } else { "ISSUES FOUND" });
+
+    Ok(has_issues || !pass)
+}
+
+/// Resolve journal entries old enough that no hook can still be running them.
+async fn recover_journal(
+    storage: &Storage,
+    journal: &CaptureJournal,
+) -> Vec<(journal::JournalEntry, journal::JournalOutcome)> {
+    let cutoff = chrono::Utc::now() - chrono::Duration::minutes(journal::RECOVERY_GRACE_MINUTES);
+    journal::recover(storage, journal, cutoff).await
+}
+
+/// Finish `run`, noting `result`'s error if the command failed, and record it.
+async fn record_maintenance<T>(storage: &Storage, mut run: MaintenanceRun, result: &Result<T>) {
+    if let Err(e) = result {
+        run.error(format!("{e:#}"));
+    }
+    run.finish();
+    for (name, value) in &run.counts {
+        telemetry::count(name, *value as usize);
+    }
+    if let Err(e) = storage.record_maintenance_run(&run).await {
+        eprintln!("Warning: failed to record maintenance run: {e}");
+    }
+}
+
+// ---------------------------------------------------------------------------
+// project
+// ---------------------------------------------------------------------------
+
+fn cmd_project(
+    action: ProjectAction,
+    config: &ShabkaConfig,
+    explicit: Option<String>,
+    dir: &std::path::Path,
+) -> Result<()> {
+    match action {
+        ProjectAction::Use { name } => {
+            let name = name.trim();
+            if name.is_empty() {
+                anyhow::bail!("project name cannot be empty");
+            }
+            let path = config::pin_project(dir, Some(name))?;
+            println!(
+                "{} default project {} in {}",
+                "Pinned".green(),
+                name.cyan(),
+                path.display()
+            );
+        }
+        ProjectAction::Clear => {
+            let path = config::pin_project(dir, None)?;
+            println!("Cleared pinned project in {}", path.display());
+        }
+        ProjectAction::Show => match (explicit, &config.project.default) {
+            (Some(p), _) if !p.trim().is_empty() => {
+                println!("{} {}", p.cyan(), "(from --project)".dimmed());
+            }
+            (_, Some(p)) if !p.trim().is_empty() => {
+                println!("{} {}", p.cyan(), "(pinned in config)".dimmed());
+            }
+            _ => println!(
+                "{}",
+                "No project set (commands span all projects).".dimmed()
+            ),
+        },
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// helix
+// ---------------------------------------------------------------------------
+
+async fn cmd_helix(action: HelixAction, config: &ShabkaConfig) -> Result<()> {
+    use shabka_core::storage::helix_schema::{self, HelixSchemaState};
+
+    let HelixAction::Init { dir, no_push } = action;
+    let dir = match dir {
+        Some(dir) => dir,
+        None => dirs::config_dir()
+            .context("cannot determine config directory")?
+            .join("shabka")
+            .join("helix"),
+    };
+    helix_schema::write_project(&dir, config.helix.port)?;
+    println!(
+        "Wrote Helix project to {}",
+        dir.display().to_string().cyan()
+    );
+
+    if !no_push {
+        let helix = which::which("helix").map_err(|_| {
+            anyhow::anyhow!(
+                "helix CLI not found in PATH; install it from https://github.com/HelixDB/helix-db \
+                 or re-run with --no-push after pushing {} yourself",
+                dir.display()
+            )
+        })?;
+        println!("Pushing schema with {}...", "helix push dev".cyan());
+        let status = std::process::Command::new(helix)
+            .args(["push", "dev"])
+            .current_dir(&dir)
+            .status()
+            .context("failed to run helix push")?;
+        if !status.success() {
+            anyhow::bail!("helix push dev failed ({status})");
+        }
+    }
+
+    let storage = HelixStorage::new(
+        Some(&config.helix.url),
+        Some(config.helix.port),
+        config.helix.api_key.as_deref(),
+    );
+    // A freshly pushed container can take a few seconds to accept queries.
+    let mut attempt = 0;
+    loop {
+        match helix_schema::verify(&storage).await {
+            Ok(()) => break,
+            Err(e) if attempt < 10 => {
+                tracing::debug!("helix not ready yet: {e}");
+                attempt += 1;
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+            Err(e) => {
+                return Err(anyhow::anyhow!(e).context(format!(
+                    "HelixDB at {}:{} did not answer Shabka's queries",
+                    config.helix.url, config.helix.port
+                )))
+            }
+        }
+    }
+
+    let state = HelixSchemaState::current();
+    state.save()?;
+    println!(
+        "{} schema {} verified at {}",
+        "OK".green(),
+        state.fingerprint.dimmed(),
+        format!("{}:{}", config.helix.url, config.helix.port).cyan()
+    );
+    if config.storage.backend != "helix" {
+        println!(
+            "{} set {} in config.toml to use it",
+            "hint:".dimmed(),
+            "[storage] backend = \"helix\"".cyan()
+        );
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// review
+// ---------------------------------------------------------------------------
+
+async fn cmd_review(
+    storage: &Storage,
+    _list: bool,
+    approve: Option<String>,
+    reject: Option<String>,
+    approve_all: bool,
+) -> Result<()> {
+    let pending_query = TimelineQuery {
+        status: Some(MemoryStatus::Pending),
+        limit: 10000,
+        ..Default::default()
+    };
+
+    if let Some(id_str) = approve {
+        let id = resolve_pending_id(storage, &id_str).await?;
+        storage
+            .update_memory(
+                id,
+                &UpdateMemoryInput {
+                    status: Some(MemoryStatus::Active),
+                    ..Default::default()
+                },
+            )
+            .await
+            .context("failed to approve memory")?;
+        println!(
+            "{} {}",
+            style::OK.green(),
+            t!("approved-memory", id = id.to_string()[..8].to_string())
+        );
+        return Ok(());
+    }
+
+    if let Some(id_str) = reject {
+        let id = resolve_pending_id(storage, &id_str).await?;
+        storage
+            .update_memory(
+                id,
+                &UpdateMemoryInput {
+                    status: Some(MemoryStatus::Archived),
+                    ..Default::default()
+                },
+            )
+            .await
+            .context("failed to reject memory")?;
+        println!(
+            "{} {}",
+            style::FAIL.red(),
+            t!("rejected-memory", id = id.to_string()[..8].to_string())
+        );
+        return Ok(());
+    }
+
+    let entries = storage
+        .timeline(&pending_query)
+        .await
+        .context("failed to fetch pending memories")?;
+
+    if approve_all {
+        if entries.is_empty() {
+            println!("{}", t!("no-pending"));
+            return Ok(());
+        }
+        let mut approved = 0usize;
+        for entry in &entries {
+            if storage
+                .update_memory(
+                    entry.id,
+                    &UpdateMemoryInput {
+                        status: Some(MemoryStatus::Active),
+                        ..Default::default()
+                    },
+                )
+                .await
+                .is_ok()
+            {
+                approved += 1;
+            }
+        }
+        println!(
+            "{} {}",
+            style::OK.green(),
+            t!("approved-pending", count = approved)
+        );
+        return Ok(());
+    }
+
+    // Default or --list: show pending memories, then lapsed verifications
+    let due = due_for_reverification(storage).await?;
+    if entries.is_empty() && due.is_empty() {
+        println!("{}", t!("no-pending"));
+        return Ok(());
+    }
+
+    if !entries.is_empty() {
+        println!(
+            "{} pending memories:\n",
+            entries.len().to_string().yellow().bold()
+        );
+        println!(
+            "{:<12} {:<12} {:<6} {}",
+            "ID".dimmed(),
+            "Kind".dimmed(),
+            "Imp".dimmed(),
+            "Title".dimmed()
+        );
+        for entry in &entries {
+            let short_id = &entry.id.to_string()[..8];
+            println!(
+                "{:<12} {:<12} {:<6.2} {}",
+                short_id.cyan(),
+                entry.kind.to_string().magenta(),
+                entry.importance,
+                entry.title
+            );
+        }
+        println!(
+            "\nUse {} or {} to act on individual memories.",
+            "--approve <id>".green(),
+            "--reject <id>".red()
+        );
+    }
+
+    if !due.is_empty() {
+        if !entries.is_empty() {
+            println!();
+        }
+        println!(
+            "{} memories due for re-verification:\n",
+            due.len().to_string().yellow().bold()
+        );
+        println!(
+            "{:<12} {:<12} {:<12} {}",
+            "ID".dimmed(),
+            "Kind".dimmed(),
+            "Expired".dimmed(),
+            "Title".dimmed()
+        );
+        for memory in &due {
+            let short_id = &memory.id.to_string()[..8];
+            let expired = memory
+                .verification_expires_at
+                .map(dates::date)
+                .unwrap_or_default();
+            println!(
+                "{:<12} {:<12} {:<12} {}",
+                short_id.cyan(),
+                memory.kind.to_string().magenta(),
+                expired,
+                memory.title
+            );
+        }
+        println!(
+            "\nRenew with {} or retire with {}.",
+            "shabka verify <id> --status verified".green(),
+            "--status outdated".red()
+        );
+    }
+
+    Ok(())
+}
+
+/// Active memories whose verification has expired, oldest expiry first.
+async fn due_for_reverification(storage: &Storage) -> Result<Vec<Memory>> {
+    let entries = storage
+        .timeline(&TimelineQuery {
+            status: Some(MemoryStatus::Active),
+            limit: 10000,
+            ..Default::default()
+        })
+        .await
+        .context("failed to fetch memories")?;
+    let ids: Vec<Uuid> = entries.iter().map(|e| e.id).collect();
+    let now = chrono::Utc::now();
+    let mut due: Vec<Memory> = storage
+        .get_memories(&ids)
+        .await
+        .context("failed to fetch memories")?
+        .into_iter()
+        .filter(|m| shabka_core::trust::verification_due(m, now))
+        .collect();
+    due.sort_by_key(|m| m.verification_expires_at);
+    Ok(due)
+}
+
+/// Resolve a pending memory by full ID, short prefix or title.
+async fn resolve_pending_id(storage: &Storage, id: &str) -> Result<Uuid> {
+    resolve_reference(storage, id, Some(MemoryStatus::Pending)).await
+}
+
+// ---------------------------------------------------------------------------
+// quarantine
+// ---------------------------------------------------------------------------
+
+async fn cmd_quarantine(
+    storage: &Storage,
+    history: &HistoryLogger,
+    user_id: &str,
+    release: Option<String>,
+    delete: Option<String>,
+) -> Result<()> {
+    if let Some(id_str) = release {
+        let id = resolve_reference(storage, &id_str, Some(MemoryStatus::Quarantined)).await?;
+        let memory = storage.get_memory(id).await.context("memory not found")?;
+        let tags: Vec<String> = memory
+            .tags
+            .into_iter()
+            .filter(|t| t != screen::SUSPICIOUS_TAG)
+            .collect();
+        storage
+            .update_memory(
+                id,
+                &UpdateMemoryInput {
+                    status: Some(MemoryStatus::Active),
+                    tags: Some(tags),
+                    ..Default::default()
+                },
+            )
+            .await
+            .context("failed to release memory")?;
+        println!(
+            "{} {}",
+            style::OK.green(),
+            t!("released-memory", id = id.to_string()[..8].to_string())
+        );
+        return Ok(());
+    }
+
+    if let Some(id_str) = delete {
+        let id = resolve_reference(storage, &id_str, Some(MemoryStatus::Quarantined)).await?;
+        let memory = storage.get_memory(id).await.context("memory not found")?;
+        storage
+            .delete_memory(id)
+            .await
+            .context("failed to delete memory")?;
+        history.log(
+            &MemoryEvent::new(id, EventAction::Deleted, user_id.to_string()).with_snapshot(&memory),
+        );
+        println!(
+            "{} {}",
+            style::FAIL.red(),
+            t!("deleted-quarantined", id = id.to_string()[..8].to_string())
+        );
+        return Ok(());
+    }
+
+    let entries = storage
+        .timeline(&TimelineQuery {
+            status: Some(MemoryStatus::Quarantined),
+            limit: 10000,
+            ..Default::default()
+        })
+        .await
+        .context("failed to fetch quarantined memories")?;
+    if entries.is_empty() {
+        println!("{}", t!("no-quarantined"));
+        return Ok(());
+    }
+    let ids: Vec<Uuid> = entries.iter().map(|e| e.id).collect();
+    let memories = storage
+        .get_memories(&ids)
+        .await
+        .context("failed to fetch memories")?;
+
+    println!(
+        "{} quarantined memories:\n",
+        memories.len().to_string().yellow().bold()
+    );
+    for memory in &memories {
+        println!(
+            "{} {} {}",
+            memory.id.to_string()[..8].cyan(),
+            memory.kind.to_string().magenta(),
+            memory.title
+        );
+        let findings = screen::screen_memory(memory);
+        if findings.is_empty() {
+            println!("  {}", "flagged by LLM screening".dimmed());
+        }
+        for finding in findings {
+            println!(
+                "  {} {}",
+                format!("{}:", finding.kind.label()).red(),
+                finding.excerpt.dimmed()
+            );
+        }
+    }
+    println!(
+        "\nUse {} or {} after reading the content with {}.",
+        "--release <id>".green(),
+        "--delete <id>".red(),
+        "shabka get <id>".cyan()
+    );
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// inbox
+// ---------------------------------------------------------------------------
+
+async fn cmd_inbox(
+    storage: &Storage,
+    config: &ShabkaConfig,
+    user_id: &str,
+    kinds: &[inbox::InboxKind],
+    json: bool,
+) -> Result<()> {
+    let options = inbox::InboxOptions::from_config(config);
+    let mut items = inbox::collect(storage, user_id, &options)
+        .await
+        .context("failed to collect inbox")?;
+    items.retain_kinds(kinds);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&items)?);
+        return Ok(());
+    }
+    if items.total == 0 {
+        println!("{} {}", style::OK.green(), t!("inbox-empty"));
+        return Ok(());
+    }
+
+    println!(
+        "{} items need attention\n",
+        items.total.to_string().yellow().bold()
+    );
+    for kind in inbox::InboxKind::ALL {
+        let group: Vec<&inbox::InboxItem> = items.of_kind(kind).collect();
+        if group.is_empty() {
+            continue;
+        }
+        println!("{} ({})", kind.label().bold(), group.len());
+        for item in group {
+            let ids: Vec<String> = item
+                .memory_ids
+                .iter()
+                .map(|id| id.to_string()[..8].to_string())
+                .collect();
+            println!("  {} {}", ids.join(" ").cyan(), item.title);
+            println!(
+                "    {} {} {}",
+                item.detail.dimmed(),
+                style::DOT,
+                dates::date(item.since).dimmed()
+            );
+        }
+        println!("  {} {}\n", style::ARROW.dimmed(), kind.hint().green());
+    }
+    Ok(())
+}
+
+/// What accepting and rejecting an item of `kind` does, for kinds triage
+/// can act on.
+fn triage_choices(kind: inbox::InboxKind) -> Option<(&'static str, &'static str)> {
+    use inbox::InboxKind::*;
+    match kind {
+        Review => Some(("approve", "archive")),
+        Quarantine => Some(("release", "delete")),
+        Duplicate => Some(("newer supersedes older", "not duplicates")),
+        Contradiction => Some(("older is outdated", "no contradiction")),
+        VerificationExpired | VerificationExpiring => Some(("re-verify", "mark outdated")),
+        Assigned => Some(("verify", "mark outdated")),
+        Reclassify => Some(("change kind", "keep kind")),
+        Backlog | SyncConflict | Consolidation => None,
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct TriageSummary {
+    accepted: usize,
+    rejected: usize,
+    skipped: usize,
+}
+
+async fn cmd_inbox_triage(
+    storage: &Storage,
+    history: &HistoryLogger,
+    config: &ShabkaConfig,
+    user_id: &str,
+    kinds: &[inbox::InboxKind],
+) -> Result<()> {
+    let options = inbox::InboxOptions::from_config(config);
+    let mut items = inbox::collect(storage, user_id, &options)
+        .await
+        .context("failed to collect inbox")?;
+    items.retain_kinds(kinds);
+    if items.total == 0 {
+        println!("{} {}", style::OK.green(), t!("inbox-empty"));
+        return Ok(());
+    }
+
+    let summary = triage(
+        storage,
+        history,
+        &config.retention,
+        user_id,
+        &items.items,
+        std::io::stdin().lock(),
+        &mut std::io::stdout(),
+    )
+    .await?;
+    println!(
+        "\n{} accepted, {} rejected, {} skipped",
+        summary.accepted.to_string().green(),
+        summary.rejected.to_string().red(),
+        summary.skipped.to_string().dimmed()
+    );
+    Ok(())
+}
+
+/// Prompt for each of `items` on `out`, reading one answer per line from
+/// `input`: `a`ccept, `r`eject, `s`kip (the default) or `q`uit. Stops at
+/// end of input.
+async fn triage(
+    storage: &Storage,
+    history: &HistoryLogger,
+    retention: &decay::RetentionConfig,
+    user_id: &str,
+    items: &[inbox::InboxItem],
+    mut input: impl std::io::BufRead,
+    out: &mut impl std::io::Write,
+) -> Result<TriageSummary> {
+    let mut summary = TriageSummary::default();
+    for (i, item) in items.iter().enumerate() {
+        let ids: Vec<String> = item
+            .memory_ids
+            .iter()
+            .map(|id| id.to_string()[..8].to_string())
+            .collect();
+        writeln!(
+            out,
+            "\n[{}/{}] {} {}",
+            i + 1,
+            items.len(),
+            item.kind.label().bold(),
+            ids.join(" ").cyan()
+        )?;
+        writeln!(out, "  {}", item.title)?;
+        writeln!(out, "  {}", item.detail.dimmed())?;
+
+        let Some((accept, reject)) = triage_choices(item.kind) else {
+            writeln!(
+                out,
+                "  {} {}",
+                style::ARROW.dimmed(),
+                item.kind.hint().green()
+            )?;
+            summary.skipped += 1;
+            continue;
+        };
+        write!(out, "  [a] {accept}  [r] {reject}  [s] skip  [q] quit: ")?;
+        out.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            break;
+        }
+        let accepted = match line.trim().to_ascii_lowercase().as_str() {
+            "a" | "accept" => true,
+            "r" | "reject" => false,
+            "q" | "quit" => break,
+            _ => {
+                summary.skipped += 1;
+                continue;
+            }
+        };
+        match resolve_inbox_item(storage, history, retention, user_id, item, accepted).await {
+            Ok(done) => {
+                writeln!(out, "  {} {done}", style::OK.green())?;
+                if accepted {
+                    summary.accepted += 1;
+                } else {
+                    summary.rejected += 1;
+                }
+            }
+            Err(e) => {
+                writeln!(out, "  {} {e:#}", style::FAIL.red())?;
+                summary.skipped += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Carry out an accept or reject decision on `item`; returns what was done.
+async fn resolve_inbox_item(
+    storage: &Storage,
+    history: &HistoryLogger,
+    retention: &decay::RetentionConfig,
+    user_id: &str,
+    item: &inbox::InboxItem,
+    accept: bool,
+) -> Result<String> {
+    use inbox::InboxKind::*;
+
+    let [first, rest @ ..] = item.memory_ids.as_slice() else {
+        anyhow::bail!("nothing to act on");
+    };
+    let first = *first;
+    let log = |id: Uuid, action: EventAction, title: &str| {
+        history.log(&MemoryEvent::new(id, action, user_id.to_string()).with_title(title));
+    };
+    let set_status = |id: Uuid, status: MemoryStatus| async move {
+        storage
+            .update_memory(
+                id,
+                &UpdateMemoryInput {
+                    status: Some(status),
+                    ..Default::default()
+                },
+            )
+            .await
+            .context("failed to update memory")
+    };
+    let set_verification = |memory: Memory, verification: VerificationStatus| async move {
+        storage
+            .update_memory(
+                memory.id,
+                &UpdateMemoryInput {
+                    verification: Some(verification),
+                    verification_expires_at: shabka_core::trust::verification_expiry(
+                        verification,
+                        memory.kind,
+                        retention,
+                        chrono::Utc::now(),
+                    ),
+                    ..Default::default()
+                },
+            )
+            .await
+            .context("failed to update verification")
+    };
+
+    match (item.kind, accept) {
+        (Review, true) => {
+            let memory = set_status(first, MemoryStatus::Active).await?;
+            log(first, EventAction::Updated, &memory.title);
+            Ok("approved".to_string())
+        }
+        (Review, false) => {
+            let memory = set_status(first, MemoryStatus::Archived).await?;
+            log(first, EventAction::Updated, &memory.title);
+            Ok("archived".to_string())
+        }
+        (Quarantine, true) => {
+            let memory = storage
+                .get_memory(first)
+                .await
+                .context("memory not found")?;
+            let tags: Vec<String> = memory
+                .tags
+                .into_iter()
+                .filter(|t| t != screen::SUSPICIOUS_TAG)
+                .collect();
+            let memory = storage
+                .update_memory(
+                    first,
+                    &UpdateMemoryInput {
+                        status: Some(MemoryStatus::Active),
+                        tags: Some(tags),
+                        ..Default::default()
+                    },
+                )
+                .await
+                .context("failed to release memory")?;
+            log(first, EventAction::Updated, &memory.title);
+            Ok("released".to_string())
+        }
+        (Quarantine, false) => {
+            let memory = storage
+                .get_memory(first)
+                .await
+                .context("memory not found")?;
+            storage
+                .delete_memory(first)
+                .await
+                .context("failed to delete memory")?;
+            history.log(
+                &MemoryEvent::new(first, EventAction::Deleted, user_id.to_string())
+                    .with_snapshot(&memory),
+            );
+            Ok("deleted".to_string())
+        }
+        (Duplicate | Contradiction, _) => {
+            let [second] = rest else {
+                anyhow::bail!("expected a pair of memories");
+            };
+            let mut pair = storage
+                .get_memories(&[first, *second])
+                .await
+                .context("failed to fetch memories")?;
+            if pair.len() != 2 {
+                anyhow::bail!("memory not found");
+            }
+            pair.sort_by_key(|m| m.created_at);
+            let [older, newer]: [Memory; 2] = pair.try_into().expect("two memories");
+            match (item.kind, accept) {
+                (Duplicate, true) => {
+                    storage
+                        .add_relation(&MemoryRelation {
+                            source_id: newer.id,
+                            target_id: older.id,
+                            relation_type: RelationType::Supersedes,
+                            strength: 1.0,
+                        })
+                        .await
+                        .context("failed to link memories")?;
+                    set_status(older.id, MemoryStatus::Superseded).await?;
+                    log(older.id, EventAction::Updated, &older.title);
+                    Ok(format!("'{}' superseded by the newer memory", older.title))
+                }
+                (Duplicate, false) => {
+                    storage
+                        .add_relation(&MemoryRelation {
+                            source_id: newer.id,
+                            target_id: older.id,
+                            relation_type: RelationType::Related,
+                            strength: 0.5,
+                        })
+                        .await
+                        .context("failed to link memories")?;
+                    Ok("linked as related, won't be flagged again".to_string())
+                }
+                (_, true) => {
+                    let (id, title) = (older.id, older.title.clone());
+                    set_verification(older, VerificationStatus::Outdated).await?;
+                    log(id, EventAction::Updated, &title);
+                    Ok(format!("'{title}' marked outdated"))
+                }
+                (_, false) => {
+                    let mut removed = false;
+                    for (source, target) in [(older.id, newer.id), (newer.id, older.id)] {
+                        removed |= storage
+                            .remove_relation(source, target, RelationType::Contradicts)
+                            .await
+                            .context("failed to remove relation")?;
+                    }
+                    if !removed {
+                        anyhow::bail!("this storage backend can't remove relations");
+                    }
+                    Ok("contradiction link removed".to_string())
+                }
+            }
+        }
+        (VerificationExpired | VerificationExpiring | Assigned, _) => {
+            let memory = storage
+                .get_memory(first)
+                .await
+                .context("memory not found")?;
+            let title = memory.title.clone();
+            let verification = if accept {
+                VerificationStatus::Verified
+            } else {
+                VerificationStatus::Outdated
+            };
+            let memory = set_verification(memory, verification).await?;
+            log(first, EventAction::Updated, &title);
+            Ok(match memory.verification_expires_at {
+                Some(at) => format!("re-verified until {}", dates::date(at)),
+                None => format!("marked {verification}"),
+            })
+        }
+        (Reclassify, _) => {
+            let memory = storage
+                .get_memory(first)
+                .await
+                .context("memory not found")?;
+            let Some(suggested) = assess::suggest_kind(&memory) else {
+                anyhow::bail!("no kind change is suggested any more");
+            };
+            let input = if accept {
+                UpdateMemoryInput {
+                    kind: Some(suggested),
+                    ..Default::default()
+                }
+            } else {
+                let mut tags = memory.tags.clone();
+                tags.push(assess::KIND_KEPT_TAG.to_string());
+                UpdateMemoryInput {
+                    tags: Some(tags),
+                    ..Default::default()
+                }
+            };
+            storage
+                .update_memory(first, &input)
+                .await
+                .context("failed to update memory")?;
+            history.log(
+                &MemoryEvent::new(first, EventAction::Updated, user_id.to_string())
+                    .with_title(&memory.title)
+                    .with_changes(shabka_core::history::diff_update(&memory, &input)),
+            );
+            Ok(if accept {
+                format!("kind changed to {suggested}")
+            } else {
+                format!("kept as {}", memory.kind)
+            })
+        }
+        (Backlog | SyncConflict | Consolidation, _) => {
+            anyhow::bail!("{}", item.kind.hint())
+        }
+    }
+}
+
+// ===========================================================================
+// Unit tests
+// ===========================================================================
+
+async fn cmd_devices(storage: &Storage, rename: Option<String>, json: bool) -> Result<()> {
+    if let Some(name) = rename {
+        if name.trim().is_empty() {
+            anyhow::bail!("device name cannot be empty");
+        }
+        let device = devices::rename_local(&name).context("failed to rename device")?;
+        println!(
+            "{} This device ({}) is now {}",
+            style::OK.green(),
+            devices::short_id(&device.id),
+            device.name.cyan()
+        );
+        return Ok(());
+    }
+
+    let entries = storage
+        .timeline(&TimelineQuery {
+            limit: 10000,
+            ..Default::default()
+        })
+        .await
+        .context("failed to fetch timeline")?;
+    let ids: Vec<Uuid> = entries.iter().map(|e| e.id).collect();
+    let memories = storage
+        .get_memories(&ids)
+        .await
+        .context("failed to fetch memories")?;
+    let stats = devices::stats(
+        &memories,
+        &DeviceRegistry::new().all(),
+        &devices::local().id,
+    );
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+    if stats.is_empty() {
+        println!("No memories record a device yet.");
+        return Ok(());
+    }
+    for device in &stats {
+        let marker = if device.current { " (this device)" } else { "" };
+        println!(
+            "{} {}{}",
+            devices::short_id(&device.id).cyan(),
+            device.name.as_deref().unwrap_or("unknown").bold(),
+            marker.dimmed()
+        );
+        println!(
+            "  {} memories, {} to {}",
+            device.memories,
+            dates::date(device.first_capture),
+            dates::date(device.last_capture)
+        );
+    }
+    let untracked = memories.iter().filter(|m| m.device_id.is_none()).count();
+    if untracked > 0 {
+        println!(
+            "\n{} memories predate device tracking.",
+            untracked.to_string().dimmed()
+        );
+    }
+    println!(
+        "\nExclude a device with {} or {} in search.",
+        "--exclude-device <id>".cyan(),
+        "-device:<id>".cyan()
+    );
+    Ok(())
+}
+
+async fn cmd_sync(
+    action: SyncAction,
+    services: &Services,
+    config: &ShabkaConfig,
+    user_id: &str,
+) -> Result<()> {
+    if let SyncAction::MergeDriver { base, ours, theirs } = action {
+        return sync::merge_files(&base, &ours, &theirs).context("failed to merge memory");
+    }
+
+    let settings = &config.sync;
+    let repo = SyncRepo::new(settings)?;
+    if let SyncAction::Init { remote } = action {
+        let remote = remote.or_else(|| settings.remote.clone());
+        repo.init(remote.as_deref(), &settings.branch)?;
+        println!(
+            "{} Sync repository ready at {}",
+            style::OK.green(),
+            repo.dir().display().to_string().cyan()
+        );
+        if remote.is_none() && !repo.has_remote() {
+            println!(
+                "No remote configured. Add one with {}.",
+                "shabka sync init --remote <url>".cyan()
+            );
+        }
+        return Ok(());
+    }
+    if !repo.is_initialized() {
+        anyhow::bail!(
+            "no sync repository at {}; run `shabka sync init --remote <url>` first",
+            repo.dir().display()
+        );
+    }
+
+    let storage = make_storage(services)?;
+    let embedder = make_embedder(services)?;
+    match action {
+        SyncAction::Push => {
+            let threshold: MemoryPrivacy = settings
+                .privacy
+                .parse()
+                .map_err(|e: String| anyhow::anyhow!("{}", e))?;
+            let written = sync::write_local(
+                storage.as_ref(),
+                &repo,
+                threshold,
+                &config.labels,
+                &devices::local().id,
+            )
+            .await?;
+            repo.write_device(devices::local())?;
+            repo.commit(&format!("Sync {} memories from {user_id}", written.written))?;
+            println!(
+                "Wrote {} memories ({} unchanged, {} withheld by privacy or label, {} removed)",
+                written.written.to_string().green(),
+                written.unchanged,
+                written.withheld,
+                written.removed
+            );
+            if !repo.has_remote() {
+                println!("No remote configured; committed locally only.");
+                return Ok(());
+            }
+            // Merge whatever teammates pushed first; the merge driver
+            // reconciles memories both sides edited.
+            repo.pull(&settings.branch)?;
+            let pulled = pull_remote(storage.as_ref(), &embedder, &repo, config).await?;
+            print_pull_stats(&pulled);
+            repo.push(&settings.branch)?;
+            println!(
+                "{} {}",
+                style::OK.green(),
+                t!("pushed-to", branch = settings.branch.cyan().to_string())
+            );
+        }
+        SyncAction::Pull => {
+            if !repo.has_remote() {
+                anyhow::bail!(
+                    "sync repository has no remote; run `shabka sync init --remote <url>`"
+                );
+            }
+            repo.pull(&settings.branch)?;
+            let pulled = pull_remote(storage.as_ref(), &embedder, &repo, config).await?;
+            print_pull_stats(&pulled);
+        }
+        SyncAction::Init { .. } | SyncAction::MergeDriver { .. } => unreachable!(),
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// token
+// ---------------------------------------------------------------------------
+
+async fn cmd_token(action: TokenAction, storage: &Storage) -> Result<()> {
+    match action {
+        TokenAction::Create {
+            name,
+            scope,
+            expires,
+            tenant,
+            json,
+        } => {
+            let expires_at = expires
+                .map(|e| api_tokens::parse_expiry(&e, chrono::Utc::now()))
+                .transpose()?;
+            let created = api_tokens::create(storage, &name, scope, expires_at, tenant.as_deref())
+                .await
+                .context("failed to create token")?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&created)?);
+                return Ok(());
+            }
+            let expiry = created
+                .token
+                .expires_at
+                .map(|at| format!(", expires {}", dates::minutes(at)))
+                .unwrap_or_default();
+            let tenant = created
+                .token
+                .tenant
+                .as_deref()
+                .map(|t| format!(" for tenant {t}"))
+                .unwrap_or_default();
+            println!(
+                "{} Created {} token {}{}{}",
+                style::OK.green(),
+                scope,
+                created.token.name.cyan(),
+                tenant,
+                expiry
+            );
+            println!("\n  {}\n", created.secret.bold());
+            println!(
+                "{}",
+                "Copy it now; it won't be shown again. Send it as `Authorization: Bearer <token>`."
+                    .dimmed()
+            );
+        }
+        TokenAction::List { json } => {
+            let tokens = api_tokens::list(storage)
+                .await
+                .context("failed to list tokens")?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&tokens)?);
+                return Ok(());
+            }
+            if tokens.is_empty() {
+                println!(
+                    "{}",
+                    "No API tokens. Create one with `shabka token create`.".dimmed()
+                );
+                return Ok(());
+            }
+            let now = chrono::Utc::now();
+            let date = |at: Option<chrono::DateTime<chrono::Utc>>| {
+                at.map(dates::minutes).unwrap_or_else(|| "-".to_string())
+            };
+            println!(
+                "{:<8} {:<20} {:<6} {:<12} {:<17} {:<17} {:<17} {}",
+                "ID".dimmed(),
+                "Name".dimmed(),
+                "Scope".dimmed(),
+                "Tenant".dimmed(),
+                "Created".dimmed(),
+                "Expires".dimmed(),
+                "Last used".dimmed(),
+                "Status".dimmed()
+            );
+            for token in &tokens {
+                let status = if token.revoked_at.is_some() {
+                    "revoked".red().to_string()
+                } else if !token.is_active(now) {
+                    "expired".yellow().to_string()
+                } else {
+                    "active".green().to_string()
+                };
+                println!(
+                    "{:<8} {:<20} {:<6} {:<12} {:<17} {:<17} {:<17} {}",
+                    token.id.to_string()[..8].cyan(),
+                    token.name,
+                    token.scope,
+                    token.tenant.as_deref().unwrap_or("-"),
+                    date(Some(token.created_at)),
+                    date(token.expires_at),
+                    date(token.last_used_at),
+                    status
+                );
+            }
+        }
+        TokenAction::Revoke { name_or_id } => {
+            let token = api_tokens::revoke(storage, &name_or_id).await?;
+            println!(
+                "{} {}",
+                style::OK.green(),
+                t!("revoked-token", name = token.name.cyan().to_string())
+            );
+        }
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// tenants
+// ---------------------------------------------------------------------------
+
+fn cmd_tenants(usage: &[tenants::TenantUsage], json: bool) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(usage)?);
+        return Ok(());
+    }
+    if usage.is_empty() {
+        println!(
+            "{}",
+            "No tenants. Create a tenant's token with `shabka token create <name> --tenant <tenant>`."
+                .dimmed()
+        );
+        return Ok(());
+    }
+    for tenant in usage {
+        let last_used = tenant
+            .last_used_at
+            .map(dates::minutes)
+            .unwrap_or_else(|| "never".to_string());
+        println!("{}", tenant.tenant.cyan().bold());
+        println!(
+            "  {} memories, {:.1} MiB, {} active tokens, last used {}",
+            tenant.memories,
+            tenant.database_bytes as f64 / (1024.0 * 1024.0),
+            tenant.tokens,
+            last_used
+        );
+        println!(
+            "  {} requests in the audit log, {} failed",
+            tenant.requests, tenant.failed_requests
+        );
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// snapshot
+// ---------------------------------------------------------------------------
+
+fn cmd_snapshot(action: SnapshotAction, config: &ShabkaConfig) -> Result<()> {
+    let store = SnapshotStore::open()?;
+    match action {
+        SnapshotAction::Create { name } => {
+            if config.storage.backend != "sqlite" {
+                anyhow::bail!(
+                    "snapshots need the sqlite backend (this config uses {})",
+                    config.storage.backend
+                );
+            }
+            let database = sqlite_path(config)?;
+            let files = snapshot::state_files(project_dir().as_deref());
+            let manifest = store
+                .create(&name, &database, &files)
+                .context("failed to create snapshot")?;
+            let saved = manifest.files.iter().filter(|f| f.stored.is_some()).count();
+            println!(
+                "{} Saved snapshot {} ({} and {saved} state files)",
+                style::OK.green(),
+                name.cyan(),
+                database.display()
+            );
+        }
+        SnapshotAction::Restore { name } => {
+            let manifest = store.restore(&name).context("failed to restore snapshot")?;
+            println!(
+                "{} Restored snapshot {} from {}",
+                style::OK.green(),
+                name.cyan(),
+                dates::minutes(manifest.created_at)
+            );
+            for file in manifest.files.iter().filter(|f| f.stored.is_none()) {
+                println!("  removed {}", file.path.display().to_string().dimmed());
+            }
+        }
+        SnapshotAction::List { json } => {
+            let snapshots = store.list()?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&snapshots)?);
+            } else if snapshots.is_empty() {
+                println!("No snapshots. Create one with `shabka snapshot create <name>`.");
+            } else {
+                for s in &snapshots {
+                    println!(
+                        "{}  {}  {}",
+                        s.name.cyan(),
+                        dates::minutes(s.created_at).dimmed(),
+                        s.database.display()
+                    );
+                }
+            }
+        }
+        SnapshotAction::Delete { name } => {
+            store.delete(&name).context("failed to delete snapshot")?;
+            println!(
+                "{} {}",
+                style::OK.green(),
+                t!("deleted-snapshot", name = name.cyan().to_string())
+            );
+        }
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// audit
+// ---------------------------------------------------------------------------
+
+async fn cmd_audit_tail(
+    audit: &AuditLog,
+    filter: &AuditFilter,
+    limit: usize,
+    follow: bool,
+    json: bool,
+) -> Result<()> {
+    if !audit.enabled() && !audit.path().exists() {
+        println!(
+            "{}",
+            "Audit logging is off. Set [audit] enabled = true and restart shabka-web.".dimmed()
+        );
+        return Ok(());
+    }
+    let entries = audit.tail(filter, limit);
+    if entries.is_empty() && !follow {
+        println!("{}", "No audit entries found.".dimmed());
+        return Ok(());
+    }
+    for entry in &entries {
+        print_audit_entry(entry, json)?;
+    }
+    if !follow {
+        return Ok(());
+    }
+
+    let (_, mut offset) = audit.read_from(0, &AuditFilter::default());
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+            _ = tokio::time::sleep(std::time::Duration::from_secs(1)) => {}
+        }
+        let (entries, next) = audit.read_from(offset, filter);
+        offset = next;
+        for entry in &entries {
+            print_audit_entry(entry, json)?;
+        }
+    }
+}
+
+fn print_audit_entry(entry: &AuditEntry, json: bool) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string(entry)?);
+        return Ok(());
+    }
+    let outcome = if entry.success {
+        entry.outcome.green().to_string()
+    } else {
+        entry.outcome.red().to_string()
+    };
+    let token = entry
+        .token
+        .as_deref()
+        .map(|t| format!(" (token {t})"))
+        .unwrap_or_default();
+    print!(
+        "{} {:<3} {} {} {}{}",
+        dates::datetime(entry.timestamp).dimmed(),
+        entry.surface,
+        entry.endpoint.bold(),
+        outcome,
+        format!("{}ms", entry.latency_ms).dimmed(),
+        token.dimmed()
+    );
+    if !entry.params.is_empty() {
+        print!("  {}", entry.params.dimmed());
+    }
+    println!();
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// telemetry
+// ---------------------------------------------------------------------------
+
+fn cmd_telemetry_report(
+    log: &TelemetryLog,
+    since: Option<chrono::DateTime<chrono::Utc>>,
+    json: bool,
+) -> Result<bool> {
+    let usage = shabka_core::telemetry::report(&log.events(since));
+    if json {
+        println!("{}", serde_json::to_string_pretty(&usage)?);
+        return Ok(!usage.is_empty());
+    }
+    if usage.is_empty() {
+        let hint = if log.enabled() {
+            "No commands recorded yet."
+        } else {
+            "Telemetry is off. Set [telemetry] enabled = true to record command usage locally."
+        };
+        println!("{}", hint.dimmed());
+        return Ok(false);
+    }
+    println!(
+        "{:<20} {:>6} {:>6} {:>8} {:>8} {:>8} {:>6}",
+        "Command".dimmed(),
+        "Runs".dimmed(),
+        "Failed".dimmed(),
+        "p50 ms".dimmed(),
+        "p95 ms".dimmed(),
+        "Results".dimmed(),
+        "Empty".dimmed()
+    );
+    for u in &usage {
+        let results = u.mean_counts.get(shabka_core::telemetry::RESULTS);
+        let empty = match results {
+            Some(_) => format!("{:.0}%", 100.0 * u.empty_results as f64 / u.runs as f64),
+            None => "-".to_string(),
+        };
+        let failures = format!("{:>6}", u.failures);
+        let failures = if u.failures > 0 {
+            failures.red().to_string()
+        } else {
+            failures
+        };
+        println!(
+            "{:<20} {:>6} {} {:>8} {:>8} {:>8} {:>6}",
+            u.command.cyan(),
+            u.runs,
+            failures,
+            u.median_latency_ms,
+            u.p95_latency_ms,
+            results.map_or("-".to_string(), |r| format!("{r:.1}")),
+            empty
+        );
+        // Counts other than results, e.g. memories archived by prune.
+        let other: Vec<String> = u
+            .mean_counts
+            .iter()
+            .filter(|(name, _)| name.as_str() != shabka_core::telemetry::RESULTS)
+            .map(|(name, mean)| format!("{name} {mean:.1}"))
+            .collect();
+        if !other.is_empty() {
+            println!(
+                "{:<20} {}",
+                "",
+                format!("mean {}", other.join(", ")).dimmed()
+            );
+        }
+    }
+    Ok(true)
+}
+
+/// Import the sync repository's memories and learn its device names.
+async fn pull_remote(
+    storage: &Storage,
+    embedder: &EmbeddingService,
+    repo: &SyncRepo,
+    config: &ShabkaConfig,
+) -> Result<sync::PullStats> {
+    if let Err(e) = DeviceRegistry::new().remember(&repo.read_devices()) {
+        tracing::warn!("failed to record synced devices: {e}");
+    }
+    Ok(sync::read_remote(storage, embedder, repo, &config.devices).await?)
+}
+
+fn print_pull_stats(stats: &sync::PullStats) {
+    println!(
+        "Pulled {} new and {} updated memories ({} unchanged, {} relations)",
+        stats.added.to_string().green(),
+        stats.updated.to_string().yellow(),
+        stats.unchanged,
+        stats.relations
+    );
+    if stats.excluded > 0 {
+        println!(
+            "Skipped {} memories from excluded devices",
+            stats.excluded.to_string().yellow()
+        );
+    }
+    if stats.quarantined > 0 {
+        println!(
+            "Quarantined {} memories flagged as possible prompt injection (see `shabka quarantine`)",
+            stats.quarantined.to_string().red()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shabka_core::storage::SqliteStorage;
+
+    fn test_storage() -> Storage {
+        Storage::Sqlite(SqliteStorage::open_in_memory().unwrap())
+    }
+
+    fn test_config() -> ShabkaConfig {
+        ShabkaConfig::default_config()
+    }
+
+    fn test_embedder(config: &ShabkaConfig) -> EmbeddingService {
+        EmbeddingService::from_config(&config.embedding).unwrap()
+    }
+
+    fn test_history() -> HistoryLogger {
+        HistoryLogger::new(true)
+    }
+
+    fn test_questions() -> QuestionLog {
+        let path = std::env::temp_dir().join(format!(
+            "shabka-test-questions-{}.jsonl",
+            uuid::Uuid::now_v7()
+        ));
+        QuestionLog::with_path(path, &Default::default())
+    }
+
+    fn test_blobs() -> BlobStore {
+        let root = std::env::temp_dir().join(format!("shabka-test-blobs-{}", uuid::Uuid::now_v7()));
+        BlobStore::with_path(root, &AttachmentConfig::default())
+    }
+
+    /// Save a test memory and return its ID as a string.
+    async fn seed_memory(storage: &Storage, title: &str, content: &str, kind: &str) -> String {
+        let mem = Memory::new(
+            title.to_string(),
+            content.to_string(),
+            kind.parse().unwrap_or(MemoryKind::Observation),
+            "test-user".to_string(),
+        );
+        let id = mem.id;
+        let config = test_config();
+        let embedder = test_embedder(&config);
+        let embedding = embedder.embed(&mem.embedding_text()).await.ok();
+        storage
+            .save_memory(&mem, embedding.as_deref())
+            .await
+            .unwrap();
+        id.to_string()
+    }
+
+    // -----------------------------------------------------------------------
+    // search
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn test_cmd_search_no_results() {
+        let storage = test_storage();
+        let config = test_config();
+        let embedder = test_embedder(&config);
+        let result = cmd_search(
+            &storage,
+            &embedder,
+            &test_questions(),
+            "test-user",
+            search_request(
+                "nonexistent query",
+                None,
+                None,
+                None,
+                &ExcludeArgs::default(),
+            )
+            .unwrap(),
+            SearchOutput {
+                json: true,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(
+            matches!(result, Ok(false)),
+            "empty search reports no results"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cmd_search_quiet_reports_found() {
+        let storage = test_storage();
+        let config = test_config();
+        let embedder = test_embedder(&config);
+        seed_memory(
+            &storage,
+            "Quiet search target",
+            "Scripts only care whether this memory exists.",
+            "fact",
+        )
+        .await;
+
+        let result = cmd_search(
+            &storage,
+            &embedder,
+            &test_questions(),
+            "test-user",
+            search_request(
+                "quiet search target",
+                None,
+                None,
+                None,
+                &ExcludeArgs::default(),
+            )
+            .unwrap(),
+            SearchOutput {
+                quiet: true,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(matches!(result, Ok(true)));
+    }
+
+    #[tokio::test]
+    async fn test_cmd_search_with_results() {
+        let storage = test_storage();
+        let config = test_config();
+        let embedder = test_embedder(&config);
+        seed_memory(
+            &storage,
+            "Rust borrow checker rules",
+            "The borrow checker enforces ownership and lifetime rules at compile time.",
+            "lesson",
+        )
+        .await;
+
+        let result = cmd_search(
+            &storage,
+            &embedder,
+            &test_questions(),
+            "test-user",
+            search_request("borrow checker", None, None, None, &ExcludeArgs::default())
+                .unwrap()
+                .limit(5),
+            SearchOutput::default(),
+        )
+        .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cmd_search_json() {
+        let storage = test_storage();
+        let config = test_config();
+        let embedder = test_embedder(&config);
+        seed_memory(
+            &storage,
+            "JSON output test alpha",
+            "This memory tests the JSON output mode for search results.",
+            "observation",
+        )
+        .await;
+
+        let result = cmd_search(
+            &storage,
+            &embedder,
+            &test_questions(),
+            "test-user",
+            search_request("json output", None, None, None, &ExcludeArgs::default())
+                .unwrap()
+                .limit(5),
+            SearchOutput {
+                json: true,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(result.is_ok());
+    }
+
+    // -----------------------------------------------------------------------
+    // get
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn test_cmd_get_found() {
+        let storage = test_storage();
+        let id = seed_memory(
+            &storage,
+            "Get test memory bravo",
+            "A memory used to test the get command with JSON output.",
+            "fact",
+        )
+        .await;
+        let result = cmd_get(&storage, &id, false, true).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cmd_get_expand_sources() {
+        let storage = test_storage();
+        let source = seed_memory(
+            &storage,
+            "Original memory charlie",
+            "One of the memories a summary was consolidated from.",
+            "fact",
+        )
+        .await;
+        let source = Uuid::parse_str(&source).unwrap();
+        let summary = Memory::new(
+            "Consolidated summary".into(),
+            "Merged content.".into(),
+            MemoryKind::Fact,
+            "tester".into(),
+        )
+        .with_source(MemorySource::Consolidated {
+            sources: vec![source],
+        });
+        storage.save_memory(&summary, None).await.unwrap();
+
+        let id = summary.id.to_string();
+        assert!(cmd_get(&storage, &id, true, false).await.is_ok());
+        assert!(cmd_get(&storage, &id, true, true).await.is_ok());
+        let sources = shabka_core::consolidate::sources(&storage, &summary)
+            .await
+            .unwrap();
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].id, source);
+    }
+
+    #[tokio::test]
+    async fn test_cmd_get_not_found() {
+        let storage = test_storage();
+        let fake_id = uuid::Uuid::now_v7().to_string();
+        let result = cmd_get(&storage, &fake_id, false, true).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_memory_id_by_prefix_and_title() {
+        let storage = test_storage();
+        let id = seed_memory(
+            &storage,
+            "Pool exhaustion",
+            "Ran out of connections.",
+            "error",
+        )
+        .await;
+        seed_memory(
+            &storage,
+            "Pool exhaustion under load",
+            "Same story, bigger load.",
+            "error",
+        )
+        .await;
+        let expected = Uuid::parse_str(&id).unwrap();
+
+        assert_eq!(resolve_memory_id(&storage, &id).await.unwrap(), expected);
+        assert_eq!(
+            resolve_memory_id(&storage, &id[..30]).await.unwrap(),
+            expected
+        );
+        // Exact title wins over the looser match
+        assert_eq!(
+            resolve_memory_id(&storage, "pool exhaustion")
+                .await
+                .unwrap(),
+            expected
+        );
+        // Tests aren't on a terminal, so ambiguity is an error listing candidates
+        let err = resolve_memory_id(&storage, "exhaustion")
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("matches 2 memories"), "{err}");
+        assert!(resolve_memory_id(&storage, "nothing like this")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_prompt_choice() {
+        let storage = test_storage();
+        seed_memory(&storage, "First choice", "a", "fact").await;
+        seed_memory(&storage, "Second choice", "b", "fact").await;
+        let entries = storage.find_by_title("choice", 10).await.unwrap();
+
+        let mut out = Vec::new();
+        let picked = prompt_choice(&entries, "2\n".as_bytes(), &mut out).unwrap();
+        assert_eq!(picked, entries[1].id);
+        let shown = String::from_utf8(out).unwrap();
+        assert!(shown.contains(" 1) ") && shown.contains("Select [1-2]"));
+
+        assert!(prompt_choice(&entries, "3\n".as_bytes(), &mut Vec::new()).is_err());
+        assert!(prompt_choice(&entries, "\n".as_bytes(), &mut Vec::new()).is_err());
+    }
+
+    // -----------------------------------------------------------------------
+    // similar
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn test_cmd_similar() {
+        let storage = test_storage();
+        let config = test_config();
+        let embedder = test_embedder(&config);
+        let id = seed_memory(
+            &storage,
+            "Connection pool exhaustion",
+            "The pool ran out of connections.",
+            "error",
+        )
+        .await;
+        seed_memory(
+            &storage,
+            "Connection pool sizing",
+            "Size the pool to the worker count.",
+            "fix",
+        )
+        .await;
+
+        let result = cmd_similar(&storage, &embedder, "test-user", &id, 5, true).await;
+        assert!(result.is_ok(), "{result:?}");
+        let result = cmd_similar(&storage, &embedder, "test-user", &id, 5, false).await;
+        assert!(result.is_ok());
+        assert!(
+            cmd_similar(&storage, &embedder, "test-user", "no such memory", 5, true)
+                .await
+                .is_err()
+        );
+    }
+
+    // -----------------------------------------------------------------------
+    // list
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn test_cmd_list_empty() {
+        let storage = test_storage();
+        let result = cmd_list(
+            &storage,
+            None,
+            None,
+            None,
+            20,
+            true,
+            false,
+            false,
+            &ExcludeArgs::default(),
+        )
+        .await;
+        assert!(matches!(result, Ok(false)), "empty list reports no results");
+    }
+
+    #[tokio::test]
+    async fn test_cmd_list_with_filter() {
+        let storage = test_storage();
+        seed_memory(
+            &storage,
+            "List filter decision charlie",
+            "A decision memory for testing list filters.",
+            "decision",
+        )
+        .await;
+        seed_memory(
+            &storage,
+            "List filter error delta",
+            "An error memory for testing list filters.",
+            "error",
+        )
+        .await;
+
+        // Filter to only decision kind
+        let result = cmd_list(
+            &storage,
+            Some("decision".to_string()),
+            None,
+            None,
+            20,
+            true,
+            false,
+            false,
+            &ExcludeArgs::default(),
+        )
+        .await;
+        assert!(matches!(result, Ok(true)));
+
+        // Quiet mode prints nothing but still reports the outcome
+        let result = cmd_list(
+            &storage,
+            Some("lesson".to_string()),
+            None,
+            None,
+            20,
+            false,
+            false,
+            true,
+            &ExcludeArgs::default(),
+        )
+        .await;
+        assert!(matches!(result, Ok(false)));
+    }
+
+    #[tokio::test]
+    async fn test_list_excluding_pages_past_excluded() {
+        let storage = test_storage();
+        seed_memory(&storage, "Kept decision", "Oldest entry.", "decision").await;
+        for i in 0..3 {
+            seed_memory(
+                &storage,
+                &format!("Noisy observation {i}"),
+                "Auto-captured.",
+                "observation",
+            )
+            .await;
+        }
+
+        let exclude = ExcludeArgs {
+            exclude_kind: vec!["observation".to_string()],
+            ..Default::default()
+        };
+        let exclusions = exclude.apply(SearchQuery::default()).unwrap();
+        let query = TimelineQuery {
+            limit: 1,
+            ..Default::default()
+        };
+        let entries = list_excluding(&storage, query, &exclusions).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].title, "Kept decision");
+
+        let bogus = ExcludeArgs {
+            exclude_kind: vec!["bogus".to_string()],
+            ..Default::default()
+        };
+        assert!(bogus.apply(SearchQuery::default()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cmd_search_not_tag() {
+        let storage = test_storage();
+        let config = test_config();
+        let embedder = test_embedder(&config);
+        let mem = Memory::new(
+            "Auto captured pool note".to_string(),
+            "Connection pool note from a hook.".to_string(),
+            MemoryKind::Observation,
+            "test-user".to_string(),
+        )
+        .with_tags(vec!["auto-capture".to_string()]);
+        let embedding = embedder.embed(&mem.embedding_text()).await.ok();
+        storage
+            .save_memory(&mem, embedding.as_deref())
+            .await
+            .unwrap();
+
+        let exclude = ExcludeArgs {
+            not_tag: vec!["auto-capture".to_string()],
+            ..Default::default()
+        };
+        let result = cmd_search(
+            &storage,
+            &embedder,
+            &test_questions(),
+            "test-user",
+            search_request("connection pool", None, None, None, &exclude).unwrap(),
+            SearchOutput {
+                quiet: true,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(matches!(result, Ok(false)), "tagged memory is excluded");
+    }
+
+    #[tokio::test]
+    async fn test_cmd_search_symbol_only() {
+        let storage = test_storage();
+        let config = test_config();
+        let embedder = test_embedder(&config);
+        let mem = Memory::new(
+            "Pool fix".to_string(),
+            "Raised the limit in open_pool".to_string(),
+            MemoryKind::Fix,
+            "test-user".to_string(),
+        );
+        storage.save_memory(&mem, None).await.unwrap();
+
+        let result = cmd_search(
+            &storage,
+            &embedder,
+            &test_questions(),
+            "test-user",
+            search_request("", None, None, None, &ExcludeArgs::default())
+                .unwrap()
+                .map_query(|q| q.with_symbol(Some("openPool".to_string()))),
+            SearchOutput {
+                quiet: true,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(matches!(result, Ok(true)), "found through the symbol index");
+    }
+
+    // -----------------------------------------------------------------------
+    // status
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn test_cmd_status() {
+        let storage = test_storage();
+        let config = test_config();
+        seed_memory(
+            &storage,
+            "Status test memory echo",
+            "This memory exists so that status has something to count.",
+            "observation",
+        )
+        .await;
+        let result = cmd_status(&storage, &config, "test-user").await;
+        assert!(result.is_ok());
+    }
+
+    // -----------------------------------------------------------------------
+    // unarchive
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn test_cmd_unarchive_restores_and_boosts() {
+        let storage = test_storage();
+        let history = test_history();
+        let mut ids = Vec::new();
+        for title in ["Archived papa", "Archived quebec"] {
+            let id = seed_memory(&storage, title, "Stale but still useful.", "decision").await;
+            let id = Uuid::parse_str(&id).unwrap();
+            storage
+                .update_memory(
+                    id,
+                    &UpdateMemoryInput {
+                        status: Some(MemoryStatus::Archived),
+                        importance: Some(0.1),
+                        ..Default::default()
+                    },
+                )
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+
+        // Neither an ID nor --all is an error.
+        let result = cmd_unarchive(
+            &storage,
+            &history,
+            "test-user",
+            None,
+            None,
+            None,
+            false,
+            None,
+            false,
+            true,
+        )
+        .await;
+        assert!(result.is_err());
+
+        // Single restore by title, with a boost.
+        cmd_unarchive(
+            &storage,
+            &history,
+            "test-user",
+            Some("papa".to_string()),
+            None,
+            None,
+            false,
+            Some(0.6),
+            false,
+            true,
+        )
+        .await
+        .unwrap();
+        let restored = storage.get_memory(ids[0]).await.unwrap();
+        assert_eq!(restored.status, MemoryStatus::Active);
+        assert!((restored.importance - 0.6).abs() < f32::EPSILON);
+
+        // Dry run leaves the rest archived; --all then restores them.
+        cmd_unarchive(
+            &storage,
+            &history,
+            "test-user",
+            None,
+            None,
+            None,
+            true,
+            None,
+            true,
+            true,
+        )
+        .await
+        .unwrap();
+        let still = storage.get_memory(ids[1]).await.unwrap();
+        assert_eq!(still.status, MemoryStatus::Archived);
+
+        cmd_unarchive(
+            &storage,
+            &history,
+            "test-user",
+            None,
+            None,
+            None,
+            true,
+            None,
+            false,
+            true,
+        )
+        .await
+        .unwrap();
+        let restored = storage.get_memory(ids[1]).await.unwrap();
+        assert_eq!(restored.status, MemoryStatus::Active);
+        assert!((restored.importance - 0.1).abs() < f32::EPSILON);
+    }
+
+    // -----------------------------------------------------------------------
+    // delete
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn test_cmd_delete_single() {
+        let storage = test_storage();
+        let history = test_history();
+        let id = seed_memory(
+            &storage,
+            "Delete me foxtrot",
+            "A memory that will be deleted in this test.",
+            "observation",
+        )
+        .await;
+
+        let result = cmd_delete(
+            &storage,
+            &history,
+            "test-user",
+            Some(id),
+            None,
+            None,
+            None,
+            false,
+            true,
+        )
+        .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cmd_delete_bulk_no_confirm() {
+        let storage = test_storage();
+        let history = test_history();
+        seed_memory(
+            &storage,
+            "Bulk delete golf",
+            "This memory should not actually be deleted since confirm is false.",
+            "error",
+        )
+        .await;
+
+        let result = cmd_delete(
+            &storage,
+            &history,
+            "test-user",
+            None,
+            Some("error".to_string()),
+            None,
+            None,
+            false, // no --confirm
+            true,
+        )
+        .await;
+        assert!(result.is_err(), "bulk delete without --confirm should fail");
+    }
+
+    // -----------------------------------------------------------------------
+    // verify
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn test_cmd_verify() {
+        let storage = test_storage();
+        let history = test_history();
+        let config = test_config();
+        let id = seed_memory(
+            &storage,
+            "Verify me hotel",
+            "A memory whose verification status will be changed.",
+            "fact",
+        )
+        .await;
+        let result = cmd_verify(
+            &storage,
+            &history,
+            &config.retention,
+            "test-user",
+            &id,
+            "verified",
+            None,
+            None,
+        )
+        .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cmd_endorse() {
+        let storage = test_storage();
+        let id = seed_memory(
+            &storage,
+            "Endorse me india",
+            "A memory a teammate agrees with.",
+            "pattern",
+        )
+        .await;
+        assert!(cmd_endorse(&storage, "test-user", &id, false)
+            .await
+            .is_err());
+        cmd_endorse(&storage, "alice", &id, false).await.unwrap();
+        let uuid = Uuid::parse_str(&id).unwrap();
+        assert_eq!(
+            storage.get_memory(uuid).await.unwrap().endorsements.len(),
+            1
+        );
+        cmd_endorse(&storage, "alice", &id, true).await.unwrap();
+        assert!(storage
+            .get_memory(uuid)
+            .await
+            .unwrap()
+            .endorsements
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cmd_assign() {
+        let storage = test_storage();
+        let history = test_history();
+        let id = seed_memory(
+            &storage,
+            "Assign me juliet",
+            "A memory someone should check.",
+            "fact",
+        )
+        .await;
+        let uuid = Uuid::parse_str(&id).unwrap();
+        cmd_assign(&storage, &history, "test-user", &id, Some("bob".into()))
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.get_memory(uuid).await.unwrap().owner.as_deref(),
+            Some("bob")
+        );
+        cmd_assign(&storage, &history, "test-user", &id, None)
+            .await
+            .unwrap();
+        assert!(storage.get_memory(uuid).await.unwrap().owner.is_none());
+        assert!(Cli::try_parse_from(["shabka", "assign", "abc"]).is_err());
+        assert!(Cli::try_parse_from(["shabka", "assign", "abc", "--clear"]).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cmd_audit_tail() {
+        let path = std::env::temp_dir().join(format!("shabka-cli-audit-{}.jsonl", Uuid::now_v7()));
+        let audit = AuditLog::with_path(
+            path.clone(),
+            &shabka_core::audit::AuditConfig {
+                enabled: true,
+                ..Default::default()
+            },
+        );
+        audit.log(&AuditEntry::new("mcp", "search").with_params(r#"{"query":"pool"}"#));
+        cmd_audit_tail(&audit, &AuditFilter::default(), 20, false, false)
+            .await
+            .unwrap();
+        cmd_audit_tail(&audit, &AuditFilter::default(), 20, false, true)
+            .await
+            .unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(Cli::try_parse_from(["shabka", "audit", "tail", "-l", "5", "-f"]).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cmd_token() {
+        let storage = test_storage();
+        let cli = Cli::try_parse_from([
+            "shabka",
+            "token",
+            "create",
+            "ci",
+            "--scope",
+            "write",
+            "--expires",
+            "30d",
+            "--tenant",
+            "acme",
+        ])
+        .unwrap();
+        let Command::Token { action } = cli.command else {
+            panic!("expected token command");
+        };
+        cmd_token(action, &storage).await.unwrap();
+        let tokens = api_tokens::list(&storage).await.unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].scope, TokenScope::Write);
+        assert!(tokens[0].expires_at.is_some());
+        assert_eq!(tokens[0].tenant.as_deref(), Some("acme"));
+        assert!(
+            Cli::try_parse_from(["shabka", "token", "create", "x", "--scope", "admin"]).is_err()
+        );
+
+        cmd_token(
+            TokenAction::Revoke {
+                name_or_id: "ci".into(),
+            },
+            &storage,
+        )
+        .await
+        .unwrap();
+        assert!(api_tokens::list(&storage).await.unwrap()[0]
+            .revoked_at
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_cmd_verify_dispute_links_evidence() {
+        let storage = test_storage();
+        let history = test_history();
+        let config = test_config();
+        let id = seed_memory(
+            &storage,
+            "Disputed claim oscar",
+            "Connection pooling makes the importer slower.",
+            "fact",
+        )
+        .await;
+        let evidence = seed_memory(
+            &storage,
+            "Pool benchmark papa",
+            "Benchmarks show pooling makes the importer faster.",
+            "observation",
+        )
+        .await;
+
+        cmd_verify(
+            &storage,
+            &history,
+            &config.retention,
+            "test-user",
+            &id,
+            "disputed",
+            Some("Contradicted by the benchmark".to_string()),
+            Some(&evidence),
+        )
+        .await
+        .unwrap();
+
+        let id = Uuid::parse_str(&id).unwrap();
+        let memory = storage.get_memory(id).await.unwrap();
+        let dispute = memory.dispute.unwrap();
+        assert_eq!(
+            dispute.reason.as_deref(),
+            Some("Contradicted by the benchmark")
+        );
+        assert_eq!(dispute.evidence_id.unwrap().to_string(), evidence);
+        let relations = storage.get_relations(id).await.unwrap();
+        assert_eq!(relations.len(), 1);
+        assert_eq!(relations[0].relation_type, RelationType::Contradicts);
+        assert!(cmd_get(&storage, &id.to_string(), false, false)
+            .await
+            .is_ok());
 
-    if repair && (!report.orphaned_embeddings.is_empty() || !report.broken_relations.is_empty()) {
-        println!("\n  Repairing...");
-        if let Some((orphans, relations)) = storage.repair(&report) {
-            println!("    Removed {} orphaned embeddings", orphans);
-            println!("    Removed {} broken relations", relations);
-        }
+        // Re-verifying clears the dispute; a reason needs --status disputed.
+        let id = id.to_string();
+        cmd_verify(
+            &storage,
+            &history,
+            &config.retention,
+            "test-user",
+            &id,
+            "verified",
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let memory = storage.get_memory(id.parse().unwrap()).await.unwrap();
+        assert!(memory.dispute.is_none());
+        let result = cmd_verify(
+            &storage,
+            &history,
+            &config.retention,
+            "test-user",
+            &id,
+            "outdated",
+            Some("stale".to_string()),
+            None,
+        )
+        .await;
+        assert!(result.is_err());
     }
 
-    let pass = report.sqlite_integrity_ok
-        && report.orphaned_embeddings.is_empty()
-        && report.broken_relations.is_empty();
+    #[tokio::test]
+    async fn test_verification_expiry_resurfaces_in_review() {
+        let storage = test_storage();
+        let history = test_history();
+        let mut config = test_config();
+        config.retention.kinds.insert(
+            MemoryKind::Fact,
+            decay::RetentionPolicy {
+                verification_expires_days: Some(0),
+                ..Default::default()
+            },
+        );
+        let id = seed_memory(
+            &storage,
+            "Verify me november",
+            "A fact whose verification lapses immediately.",
+            "fact",
+        )
+        .await;
+        cmd_verify(
+            &storage,
+            &history,
+            &config.retention,
+            "test-user",
+            &id,
+            "verified",
+            None,
+            None,
+        )
+        .await
+        .unwrap();
 
-    println!("\n  Result: {}", if pass { "PASS" } else { "ISSUES FOUND" });
+        let due = due_for_reverification(&storage).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id.to_string(), id);
+        assert!(cmd_review(&storage, true, None, None, false).await.is_ok());
 
-    Ok(())
-}
+        // Marking it outdated clears the expiry.
+        cmd_verify(
+            &storage,
+            &history,
+            &config.retention,
+            "test-user",
+            &id,
+            "outdated",
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(due_for_reverification(&storage).await.unwrap().is_empty());
+    }
 
-// ---------------------------------------------------------------------------
-// review
-// ---------------------------------------------------------------------------
+    // -----------------------------------------------------------------------
+    // history
+    // -----------------------------------------------------------------------
 
-async fn cmd_review(
-    storage: &Storage,
-    _list: bool,
-    approve: Option<String>,
-    reject: Option<String>,
-    approve_all: bool,
-) -> Result<()> {
-    let pending_query = TimelineQuery {
-        status: Some(MemoryStatus::Pending),
-        limit: 10000,
-        ..Default::default()
-    };
+    #[test]
+    fn test_cmd_history() {
+        let history = test_history();
+        // cmd_history is sync; with no prior events it should print "no events"
+        let result = cmd_history(&history, &HistoryFilter::default(), 20, true);
+        assert!(result.is_ok());
+    }
 
-    if let Some(id_str) = approve {
-        let id = resolve_pending_id(storage, &id_str).await?;
-        storage
-            .update_memory(
-                id,
-                &UpdateMemoryInput {
-                    status: Some(MemoryStatus::Active),
-                    ..Default::default()
-                },
-            )
-            .await
-            .context("failed to approve memory")?;
-        println!("{} Approved memory {}", "✓".green(), &id.to_string()[..8]);
-        return Ok(());
+    #[test]
+    fn test_history_filters_parse() {
+        let cli = Cli::try_parse_from([
+            "shabka",
+            "history",
+            "--actor",
+            "ci-bot",
+            "--action",
+            "updated,deleted",
+            "--since",
+            "7d",
+            "-p",
+            "api",
+        ])
+        .unwrap();
+        assert_eq!(cli.project.as_deref(), Some("api"));
+        let Command::History {
+            actor,
+            action,
+            since,
+            ..
+        } = cli.command
+        else {
+            panic!("expected history command");
+        };
+        assert_eq!(actor.as_deref(), Some("ci-bot"));
+        assert_eq!(action, vec![EventAction::Updated, EventAction::Deleted]);
+        assert_eq!(since.as_deref(), Some("7d"));
+        assert!(Cli::try_parse_from(["shabka", "history", "--action", "nope"]).is_err());
     }
 
-    if let Some(id_str) = reject {
-        let id = resolve_pending_id(storage, &id_str).await?;
-        storage
-            .update_memory(
-                id,
-                &UpdateMemoryInput {
-                    status: Some(MemoryStatus::Archived),
-                    ..Default::default()
-                },
-            )
-            .await
-            .context("failed to reject memory")?;
-        println!(
-            "{} Rejected (archived) memory {}",
-            "✗".red(),
-            &id.to_string()[..8]
-        );
-        return Ok(());
+    #[test]
+    fn test_digest_defaults_to_last_day() {
+        let cli = Cli::try_parse_from(["shabka", "digest", "--send"]).unwrap();
+        let Command::Digest {
+            since, until, send, ..
+        } = cli.command
+        else {
+            panic!("expected digest command");
+        };
+        assert_eq!(since, "1d");
+        assert!(until.is_none());
+        assert!(send);
+        assert!(Cli::try_parse_from(["shabka", "digest", "--send", "--json"]).is_err());
     }
 
-    let entries = storage
-        .timeline(&pending_query)
-        .await
-        .context("failed to fetch pending memories")?;
+    // -----------------------------------------------------------------------
+    // prune
+    // -----------------------------------------------------------------------
 
-    if entries.is_empty() {
-        println!("No pending memories to review.");
-        return Ok(());
-    }
+    #[tokio::test]
+    async fn test_cmd_prune_dry_run() {
+        let storage = test_storage();
+        let history = test_history();
+        seed_memory(
+            &storage,
+            "Prune candidate india",
+            "A memory that might be pruned during a dry run test.",
+            "observation",
+        )
+        .await;
 
-    if approve_all {
-        let mut approved = 0usize;
-        for entry in &entries {
-            if storage
-                .update_memory(
-                    entry.id,
-                    &UpdateMemoryInput {
-                        status: Some(MemoryStatus::Active),
-                        ..Default::default()
-                    },
-                )
-                .await
-                .is_ok()
-            {
-                approved += 1;
-            }
-        }
-        println!("{} Approved {} pending memories.", "✓".green(), approved);
-        return Ok(());
+        let config = PruneConfig::default();
+        let mut run = MaintenanceRun::start(MaintenanceOp::Prune, serde_json::Value::Null);
+        let result = cmd_prune(
+            &storage,
+            &history,
+            "test-user",
+            &config,
+            true,
+            None,
+            &mut run,
+        )
+        .await;
+        assert!(result.is_ok());
     }
 
-    // Default or --list: show pending memories
-    println!(
-        "{} pending memories:\n",
-        entries.len().to_string().yellow().bold()
-    );
-    println!(
-        "{:<12} {:<12} {:<6} {}",
-        "ID".dimmed(),
-        "Kind".dimmed(),
-        "Imp".dimmed(),
-        "Title".dimmed()
-    );
-    for entry in &entries {
-        let short_id = &entry.id.to_string()[..8];
-        println!(
-            "{:<12} {:<12} {:<6.2} {}",
-            short_id.cyan(),
-            entry.kind.to_string().magenta(),
-            entry.importance,
-            entry.title
+    #[tokio::test]
+    async fn test_cmd_prune_deletes_expired_archives_to_cold_storage() {
+        let storage = test_storage();
+        let history = test_history();
+        let mut expired = Memory::new(
+            "Long archived romeo".to_string(),
+            "Archived two months ago and never restored.".to_string(),
+            MemoryKind::Fact,
+            "test-user".to_string(),
         );
-    }
-    println!(
-        "\nUse {} or {} to act on individual memories.",
-        "--approve <id>".green(),
-        "--reject <id>".red()
-    );
+        expired.status = MemoryStatus::Archived;
+        expired.updated_at = chrono::Utc::now() - chrono::Duration::days(60);
+        storage.save_memory(&expired, None).await.unwrap();
+        let keep = seed_memory(&storage, "Fresh sierra", "Recently used.", "fact").await;
+
+        let cold = std::env::temp_dir().join(format!("shabka-test-cold-{}.jsonl", Uuid::now_v7()));
+        let cold_path = cold.to_str().unwrap();
+        let config = PruneConfig {
+            delete_after_days: Some(30),
+            ..Default::default()
+        };
 
-    Ok(())
-}
+        let mut run = MaintenanceRun::start(MaintenanceOp::Prune, serde_json::Value::Null);
+        cmd_prune(
+            &storage,
+            &history,
+            "test-user",
+            &config,
+            true,
+            Some(cold_path),
+            &mut run,
+        )
+        .await
+        .unwrap();
+        assert!(storage.get_memory(expired.id).await.is_ok(), "dry run");
+        assert!(!cold.exists());
 
-/// Resolve a memory ID (full or short prefix) from pending memories.
-async fn resolve_pending_id(storage: &Storage, id: &str) -> Result<Uuid> {
-    if id.len() >= 32 {
-        return Uuid::parse_str(id).context("invalid memory ID");
-    }
-    let entries = storage
-        .timeline(&TimelineQuery {
-            status: Some(MemoryStatus::Pending),
-            limit: 10000,
-            ..Default::default()
-        })
+        cmd_prune(
+            &storage,
+            &history,
+            "test-user",
+            &config,
+            false,
+            Some(cold_path),
+            &mut run,
+        )
         .await
-        .context("failed to fetch pending memories")?;
+        .unwrap();
+        assert_eq!(run.counts["deleted"], 1);
+        assert!(storage.get_memory(expired.id).await.is_err());
+        assert!(storage
+            .get_memory(Uuid::parse_str(&keep).unwrap())
+            .await
+            .is_ok());
 
-    let matches: Vec<_> = entries
-        .iter()
-        .filter(|e| e.id.to_string().starts_with(id))
-        .collect();
+        let data = parse_export(&std::fs::read_to_string(&cold).unwrap()).unwrap();
+        assert_eq!(data.memories.len(), 1);
+        assert_eq!(data.memories[0].id, expired.id);
+        let _ = std::fs::remove_file(&cold);
+    }
 
-    match matches.len() {
-        0 => anyhow::bail!("no pending memory found matching prefix '{id}'"),
-        1 => Ok(matches[0].id),
-        n => anyhow::bail!(
-            "ambiguous prefix '{id}' matches {n} pending memories. Use a longer prefix."
-        ),
+    // -----------------------------------------------------------------------
+    // random
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn test_cmd_random_touches_picked_memory() {
+        let storage = test_storage();
+        let id = seed_memory(
+            &storage,
+            "Forgotten lesson oscar",
+            "An important lesson nobody has looked at in a while.",
+            "lesson",
+        )
+        .await;
+        let id = Uuid::parse_str(&id).unwrap();
+        let before = storage.get_memory(id).await.unwrap();
+
+        let found = cmd_random(&storage, "test-user", None, None, false, false, true)
+            .await
+            .unwrap();
+        assert!(found);
+        let after = storage.get_memory(id).await.unwrap();
+        assert!(after.accessed_at >= before.accessed_at);
+        assert_eq!(after.updated_at, before.updated_at);
+
+        // No memories of the requested kind.
+        let found = cmd_random(
+            &storage,
+            "test-user",
+            Some("error".to_string()),
+            None,
+            false,
+            false,
+            true,
+        )
+        .await
+        .unwrap();
+        assert!(!found);
     }
-}
 
-// ===========================================================================
-// Unit tests
-// ===========================================================================
+    // -----------------------------------------------------------------------
+    // chain
+    // -----------------------------------------------------------------------
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use shabka_core::storage::SqliteStorage;
+    #[tokio::test]
+    async fn test_cmd_chain_no_relations() {
+        let storage = test_storage();
+        let id = seed_memory(
+            &storage,
+            "Chain start juliet",
+            "An isolated memory with no relations for chain traversal.",
+            "pattern",
+        )
+        .await;
 
-    fn test_storage() -> Storage {
-        Storage::Sqlite(SqliteStorage::open_in_memory().unwrap())
+        let result = cmd_chain(&storage, &id, None, 5, 50, true).await;
+        assert!(result.is_ok());
     }
 
-    fn test_config() -> ShabkaConfig {
-        ShabkaConfig::default_config()
+    #[test]
+    fn test_chain_tree_lanes_and_cycles() {
+        let memory =
+            |title: &str| Memory::new(title.into(), "c".into(), MemoryKind::Fact, "u".into());
+        let (start, fix, cause, other) = (
+            memory("Start"),
+            memory("Fix"),
+            memory("Cause"),
+            memory("Other"),
+        );
+        let link = |from: &Memory, to: &Memory, relation_type, depth, cycle| graph::ChainLink {
+            memory_id: to.id,
+            from_id: from.id,
+            relation_type,
+            strength: 0.5,
+            depth,
+            cycle,
+        };
+        let links = vec![
+            link(&start, &fix, RelationType::Fixes, 1, false),
+            link(&start, &other, RelationType::Related, 1, false),
+            link(&fix, &cause, RelationType::CausedBy, 2, false),
+            link(&cause, &start, RelationType::Related, 3, true),
+        ];
+        let memory_map: HashMap<Uuid, &Memory> = [&start, &fix, &cause, &other]
+            .into_iter()
+            .map(|m| (m.id, m))
+            .collect();
+
+        let lines = chain_tree(start.id, &links, &memory_map);
+        assert_eq!(lines.len(), 8);
+        assert!(lines[0].starts_with("├─ ") && lines[0].contains("fixes"));
+        assert!(lines[1].starts_with("│  └─ ") && lines[1].contains("Fix"));
+        assert!(lines[2].contains("caused_by"));
+        assert!(lines[5].contains("Start") && lines[5].contains("(cycle)"));
+        assert!(lines[6].starts_with("└─ ") && lines[6].contains("related"));
+        assert!(lines[7].starts_with("   └─ ") && lines[7].contains("Other"));
     }
 
-    fn test_embedder(config: &ShabkaConfig) -> EmbeddingService {
-        EmbeddingService::from_config(&config.embedding).unwrap()
-    }
+    // -----------------------------------------------------------------------
+    // graph
+    // -----------------------------------------------------------------------
 
-    fn test_history() -> HistoryLogger {
-        HistoryLogger::new(true)
-    }
+    #[tokio::test]
+    async fn test_cmd_graph_top_groups_by_project() {
+        let storage = test_storage();
+        let mut ids = Vec::new();
+        for (title, project) in [
+            ("Hub decision", Some("api")),
+            ("Spoke one", Some("api")),
+            ("Spoke two", Some("api")),
+            ("Web note", Some("web")),
+            ("Loner", None),
+        ] {
+            let mut memory = Memory::new(title.into(), "c".into(), MemoryKind::Fact, "u".into());
+            memory.project_id = project.map(str::to_string);
+            storage.save_memory(&memory, None).await.unwrap();
+            ids.push(memory.id);
+        }
+        for (source_id, target_id) in [(ids[1], ids[0]), (ids[2], ids[0]), (ids[3], ids[0])] {
+            storage
+                .add_relation(&MemoryRelation {
+                    source_id,
+                    target_id,
+                    relation_type: RelationType::Related,
+                    strength: 0.8,
+                })
+                .await
+                .unwrap();
+        }
 
-    /// Save a test memory and return its ID as a string.
-    async fn seed_memory(storage: &Storage, title: &str, content: &str, kind: &str) -> String {
-        let mem = Memory::new(
-            title.to_string(),
-            content.to_string(),
-            kind.parse().unwrap_or(MemoryKind::Observation),
-            "test-user".to_string(),
-        );
-        let id = mem.id;
-        let config = test_config();
-        let embedder = test_embedder(&config);
-        let embedding = embedder.embed(&mem.embedding_text()).await.ok();
-        storage
-            .save_memory(&mem, embedding.as_deref())
+        assert!(!cmd_graph_top(&storage, "u", None, 10, true).await.unwrap());
+        cmd_graph_centrality(&storage, true).await.unwrap();
+        let runs = storage
+            .maintenance_runs(Some(MaintenanceOp::Centrality), 10)
             .await
             .unwrap();
-        id.to_string()
+        assert_eq!(runs[0].counts["memories_scored"], 4);
+
+        let ranking = storage.centrality_ranking().await.unwrap();
+        let memories = storage.get_memories(&ids).await.unwrap();
+        let groups = central_memories_by_project(&ranking, &memories, "u", None, 2);
+        let projects: Vec<Option<&str>> = groups.iter().map(|(p, _)| p.as_deref()).collect();
+        assert_eq!(projects, vec![Some("api"), Some("web")]);
+        assert_eq!(groups[0].1.len(), 2);
+        assert_eq!(groups[0].1[0].0.id, ids[0]);
+
+        let web = central_memories_by_project(&ranking, &memories, "u", Some("web"), 10);
+        assert_eq!(web.len(), 1);
+        assert!(cmd_graph_top(&storage, "u", Some("api"), 10, true)
+            .await
+            .unwrap());
     }
 
     // -----------------------------------------------------------------------
-    // search
+    // export / import roundtrip
     // -----------------------------------------------------------------------
 
     #[tokio::test]
-    async fn test_cmd_search_no_results() {
+    async fn test_cmd_export_import_roundtrip() {
         let storage = test_storage();
         let config = test_config();
         let embedder = test_embedder(&config);
-        let result = cmd_search(
+        let history = test_history();
+
+        seed_memory(
             &storage,
-            &embedder,
-            "test-user",
-            "nonexistent query",
-            None,
-            None,
-            None,
+            "Export roundtrip kilo",
+            "This memory will be exported and then imported back.",
+            "lesson",
+        )
+        .await;
+
+        // Export to a temp file
+        let tmp_path =
+            std::env::temp_dir().join(format!("shabka-test-export-{}.json", uuid::Uuid::now_v7()));
+        let tmp_str = tmp_path.to_str().unwrap();
+
+        let export_result = cmd_export(
+            &storage,
+            tmp_str,
+            false,
+            "private",
+            &LabelConfig::default(),
             None,
-            true,
+            false,
             None,
         )
         .await;
-        assert!(result.is_ok());
+        assert!(export_result.is_ok(), "export failed: {:?}", export_result);
+
+        // Import into a fresh storage
+        let storage2 = test_storage();
+        let import_result = cmd_import(
+            &storage2,
+            &embedder,
+            "test-user",
+            tmp_str,
+            &history,
+            &test_blobs(),
+        )
+        .await;
+        assert!(import_result.is_ok(), "import failed: {:?}", import_result);
+
+        // Verify the imported memory exists
+        let entries = storage2
+            .timeline(&TimelineQuery {
+                limit: 100,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(
+            !entries.is_empty(),
+            "imported storage should have at least one memory"
+        );
+
+        // Clean up temp file
+        let _ = std::fs::remove_file(&tmp_path);
     }
 
     #[tokio::test]
-    async fn test_cmd_search_with_results() {
+    async fn test_cmd_export_withholds_labels() {
+        let storage = test_storage();
+        let id = seed_memory(&storage, "Export visible", "Anyone may read this.", "fact").await;
+        let secret = seed_memory(&storage, "Export secret", "Stays on this box.", "fact").await;
+        storage
+            .update_memory(
+                secret.parse().unwrap(),
+                &UpdateMemoryInput {
+                    label: Some("confidential".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        let labels = LabelConfig {
+            policies: [(
+                "confidential".to_string(),
+                shabka_core::labels::LabelPolicy {
+                    export: false,
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+
+        let tmp_path =
+            std::env::temp_dir().join(format!("shabka-test-export-{}.jsonl", uuid::Uuid::now_v7()));
+        let tmp_str = tmp_path.to_str().unwrap();
+        cmd_export(
+            &storage, tmp_str, true, "private", &labels, None, false, None,
+        )
+        .await
+        .unwrap();
+        let text = std::fs::read_to_string(&tmp_path).unwrap();
+        assert!(text.contains(&id));
+        assert!(!text.contains(&secret));
+
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+
+    #[tokio::test]
+    async fn test_cmd_export_import_jsonl_roundtrip() {
         let storage = test_storage();
         let config = test_config();
         let embedder = test_embedder(&config);
+        let history = test_history();
+
         seed_memory(
             &storage,
-            "Rust borrow checker rules",
-            "The borrow checker enforces ownership and lifetime rules at compile time.",
-            "lesson",
+            "JSONL export one",
+            "First streamed memory.",
+            "fact",
+        )
+        .await;
+        seed_memory(
+            &storage,
+            "JSONL export two",
+            "Second streamed memory.",
+            "fact",
         )
         .await;
 
-        let result = cmd_search(
+        let tmp_path =
+            std::env::temp_dir().join(format!("shabka-test-export-{}.jsonl", uuid::Uuid::now_v7()));
+        let tmp_str = tmp_path.to_str().unwrap();
+
+        cmd_export(
             &storage,
-            &embedder,
-            "test-user",
-            "borrow checker",
-            None,
-            Some(5),
-            None,
+            tmp_str,
+            true,
+            "private",
+            &LabelConfig::default(),
             None,
             false,
             None,
         )
-        .await;
-        assert!(result.is_ok());
+        .await
+        .unwrap();
+        let text = std::fs::read_to_string(&tmp_path).unwrap();
+        assert_eq!(text.lines().count(), 2);
+        assert!(text.lines().all(|l| l.starts_with(r#"{"record":"memory""#)));
+
+        let storage2 = test_storage();
+        cmd_import(
+            &storage2,
+            &embedder,
+            "test-user",
+            tmp_str,
+            &history,
+            &test_blobs(),
+        )
+        .await
+        .unwrap();
+        let entries = storage2
+            .timeline(&TimelineQuery {
+                limit: 100,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let _ = std::fs::remove_file(&tmp_path);
     }
 
     #[tokio::test]
-    async fn test_cmd_search_json() {
+    async fn test_cmd_attach_export_import_blobs() {
         let storage = test_storage();
         let config = test_config();
         let embedder = test_embedder(&config);
-        seed_memory(
-            &storage,
-            "JSON output test alpha",
-            "This memory tests the JSON output mode for search results.",
-            "observation",
-        )
-        .await;
+        let history = test_history();
+        let blobs = test_blobs();
 
-        let result = cmd_search(
+        let id = seed_memory(&storage, "Crash with trace", "See attached.", "error").await;
+        let trace_path =
+            std::env::temp_dir().join(format!("shabka-test-trace-{}.log", uuid::Uuid::now_v7()));
+        std::fs::write(&trace_path, "thread 'main' panicked").unwrap();
+        cmd_attach(
             &storage,
-            &embedder,
+            &blobs,
+            &config.attachments,
+            &history,
             "test-user",
-            "json output",
-            None,
-            Some(5),
-            None,
+            &id,
+            trace_path.to_str().unwrap(),
             None,
+        )
+        .await
+        .unwrap();
+        let memory = storage.get_memory(id.parse().unwrap()).await.unwrap();
+        assert_eq!(memory.attachments.len(), 1);
+        let sha = memory.attachments[0].sha256.clone();
+
+        let tmp_path =
+            std::env::temp_dir().join(format!("shabka-test-export-{}.jsonl", uuid::Uuid::now_v7()));
+        let tmp_str = tmp_path.to_str().unwrap();
+        cmd_export(
+            &storage,
+            tmp_str,
             true,
+            "private",
+            &LabelConfig::default(),
             None,
+            false,
+            Some(&blobs),
         )
-        .await;
-        assert!(result.is_ok());
-    }
-
-    // -----------------------------------------------------------------------
-    // get
-    // -----------------------------------------------------------------------
+        .await
+        .unwrap();
+        let text = std::fs::read_to_string(&tmp_path).unwrap();
+        assert!(text.lines().any(|l| l.starts_with(r#"{"record":"blob""#)));
 
-    #[tokio::test]
-    async fn test_cmd_get_found() {
-        let storage = test_storage();
-        let id = seed_memory(
-            &storage,
-            "Get test memory bravo",
-            "A memory used to test the get command with JSON output.",
-            "fact",
+        let storage2 = test_storage();
+        let blobs2 = test_blobs();
+        cmd_import(
+            &storage2,
+            &embedder,
+            "test-user",
+            tmp_str,
+            &history,
+            &blobs2,
         )
-        .await;
-        let result = cmd_get(&storage, &id, true).await;
-        assert!(result.is_ok());
+        .await
+        .unwrap();
+        assert_eq!(blobs2.get(&sha).unwrap(), b"thread 'main' panicked");
+
+        let _ = std::fs::remove_file(&tmp_path);
+        let _ = std::fs::remove_file(&trace_path);
+        let _ = std::fs::remove_dir_all(blobs.path());
+        let _ = std::fs::remove_dir_all(blobs2.path());
     }
 
-    #[tokio::test]
-    async fn test_cmd_get_not_found() {
-        let storage = test_storage();
-        let fake_id = uuid::Uuid::now_v7().to_string();
-        let result = cmd_get(&storage, &fake_id, true).await;
-        assert!(result.is_err());
+    #[test]
+    fn test_parse_export_rejects_garbage() {
+        assert!(parse_export("not json at all").is_err());
+        assert!(parse_export("").is_err());
     }
 
     // -----------------------------------------------------------------------
-    // list
+    // add
     // -----------------------------------------------------------------------
 
     #[tokio::test]
-    async fn test_cmd_list_empty() {
+    async fn test_cmd_add() {
         let storage = test_storage();
-        let result = cmd_list(&storage, None, None, None, 20, true).await;
-        assert!(result.is_ok());
-    }
+        let config = test_config();
+        let embedder = test_embedder(&config);
+        let history = test_history();
 
-    #[tokio::test]
-    async fn test_cmd_list_with_filter() {
-        let storage = test_storage();
-        seed_memory(
+        cmd_add(
             &storage,
-            "List filter decision charlie",
-            "A decision memory for testing list filters.",
+            &embedder,
+            &history,
+            "test-user",
+            "Piped memory",
+            "Content that arrived on stdin.\n",
             "decision",
+            vec!["cli".to_string()],
+            0.7,
+            Some("proj".to_string()),
+            MemoryPrivacy::Team,
+            Some("internal".to_string()),
+            true,
         )
-        .await;
-        seed_memory(
-            &storage,
-            "List filter error delta",
-            "An error memory for testing list filters.",
-            "error",
-        )
-        .await;
+        .await
+        .unwrap();
 
-        // Filter to only decision kind
-        let result = cmd_list(&storage, Some("decision".to_string()), None, None, 20, true).await;
-        assert!(result.is_ok());
+        let entries = storage
+            .timeline(&TimelineQuery {
+                limit: 10,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        let memory = storage.get_memory(entries[0].id).await.unwrap();
+        assert_eq!(memory.title, "Piped memory");
+        assert_eq!(memory.content, "Content that arrived on stdin.");
+        assert_eq!(memory.kind, MemoryKind::Decision);
+        assert_eq!(memory.privacy, MemoryPrivacy::Team);
+        assert_eq!(memory.label.as_deref(), Some("internal"));
+        assert_eq!(memory.project_id.as_deref(), Some("proj"));
     }
 
-    // -----------------------------------------------------------------------
-    // status
-    // -----------------------------------------------------------------------
-
     #[tokio::test]
-    async fn test_cmd_status() {
+    async fn test_cmd_add_rejects_empty_content() {
         let storage = test_storage();
         let config = test_config();
-        seed_memory(
+        let embedder = test_embedder(&config);
+        let history = test_history();
+
+        let result = cmd_add(
             &storage,
-            "Status test memory echo",
-            "This memory exists so that status has something to count.",
+            &embedder,
+            &history,
+            "test-user",
+            "Empty",
+            "",
             "observation",
+            vec![],
+            0.5,
+            None,
+            MemoryPrivacy::Private,
+            None,
+            false,
         )
         .await;
-        let result = cmd_status(&storage, &config, "test-user").await;
-        assert!(result.is_ok());
+        assert!(result.is_err());
     }
 
     // -----------------------------------------------------------------------
-    // delete
+    // assess
     // -----------------------------------------------------------------------
 
     #[tokio::test]
-    async fn test_cmd_delete_single() {
+    async fn test_cmd_assess() {
         let storage = test_storage();
-        let history = test_history();
-        let id = seed_memory(
+        let config = test_config();
+        seed_memory(
             &storage,
-            "Delete me foxtrot",
-            "A memory that will be deleted in this test.",
+            "Assess target lima",
+            "A memory to be assessed for quality issues.",
             "observation",
         )
         .await;
 
-        let result = cmd_delete(
+        let mut run = MaintenanceRun::start(MaintenanceOp::Assess, serde_json::Value::Null);
+        let result = cmd_assess(
             &storage,
-            &history,
-            "test-user",
-            Some(id),
             None,
             None,
+            &config.graph,
+            &config.retention,
+            config.capture.min_confidence,
             None,
             false,
+            None,
+            None,
             true,
+            false,
+            &mut run,
         )
         .await;
         assert!(result.is_ok());
+        assert_eq!(run.counts[assess::MEMORIES_COUNT], 1);
+        assert!(run.counts.contains_key(assess::SCORE_COUNT));
+
+        record_maintenance(&storage, run, &result).await;
+        assert!(cmd_assess_trend(&storage, true).await.unwrap());
     }
 
     #[tokio::test]
-    async fn test_cmd_delete_bulk_no_confirm() {
+    async fn test_cmd_assess_trend_without_runs() {
         let storage = test_storage();
-        let history = test_history();
-        seed_memory(
+        assert!(!cmd_assess_trend(&storage, false).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_cmd_assess_quiet_empty() {
+        let storage = test_storage();
+        let config = test_config();
+        let result = cmd_assess(
             &storage,
-            "Bulk delete golf",
-            "This memory should not actually be deleted since confirm is false.",
-            "error",
+            None,
+            None,
+            &config.graph,
+            &config.retention,
+            config.capture.min_confidence,
+            None,
+            false,
+            None,
+            None,
+            false,
+            true,
+            &mut MaintenanceRun::start(MaintenanceOp::Assess, serde_json::Value::Null),
         )
         .await;
+        assert!(matches!(result, Ok(false)));
+    }
 
-        let result = cmd_delete(
+    #[tokio::test]
+    async fn test_cmd_assess_fix_changes_suggested_kind() {
+        let storage = test_storage();
+        let history = test_history();
+        let config = test_config();
+        let memory = Memory::new(
+            "Switched to sqlite-vec".to_string(),
+            "Decided on sqlite-vec instead of a separate vector server.".to_string(),
+            MemoryKind::Observation,
+            "test-user".to_string(),
+        )
+        .with_source(MemorySource::AutoCapture {
+            hook: "Stop".to_string(),
+        });
+        storage.save_memory(&memory, None).await.unwrap();
+
+        let result = cmd_assess(
             &storage,
-            &history,
-            "test-user",
             None,
-            Some("error".to_string()),
             None,
+            &config.graph,
+            &config.retention,
+            config.capture.min_confidence,
             None,
-            false, // no --confirm
+            false,
+            None,
+            Some((&history, "test-user")),
+            false,
             true,
+            &mut MaintenanceRun::start(MaintenanceOp::Assess, serde_json::Value::Null),
         )
         .await;
-        assert!(result.is_err(), "bulk delete without --confirm should fail");
+        assert!(matches!(result, Ok(true)));
+        assert_eq!(
+            storage.get_memory(memory.id).await.unwrap().kind,
+            MemoryKind::Decision
+        );
     }
 
-    // -----------------------------------------------------------------------
-    // verify
-    // -----------------------------------------------------------------------
-
     #[tokio::test]
-    async fn test_cmd_verify() {
+    async fn test_cmd_assess_codebase() {
         let storage = test_storage();
-        let history = test_history();
-        let id = seed_memory(
+        let config = test_config();
+        seed_memory(
             &storage,
-            "Verify me hotel",
-            "A memory whose verification status will be changed.",
+            "Legacy loader mike",
+            "The loader used to live in src/legacy_loader.rs before the rewrite.",
             "fact",
         )
         .await;
-        let result = cmd_verify(&storage, &history, "test-user", &id, "verified").await;
-        assert!(result.is_ok());
-    }
 
-    // -----------------------------------------------------------------------
-    // history
-    // -----------------------------------------------------------------------
+        let dir = std::env::temp_dir().join(format!("shabka-assess-{}", uuid::Uuid::now_v7()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
 
-    #[test]
-    fn test_cmd_history() {
-        let history = test_history();
-        // cmd_history is sync; with no prior events it should print "no events"
-        let result = cmd_history(&history, None, 20, true);
-        assert!(result.is_ok());
+        let result = cmd_assess(
+            &storage,
+            None,
+            None,
+            &config.graph,
+            &config.retention,
+            config.capture.min_confidence,
+            None,
+            false,
+            Some((&dir, None)),
+            None,
+            false,
+            false,
+            &mut MaintenanceRun::start(MaintenanceOp::Assess, serde_json::Value::Null),
+        )
+        .await;
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(result, Ok(true)));
     }
 
-    // -----------------------------------------------------------------------
-    // prune
-    // -----------------------------------------------------------------------
-
     #[tokio::test]
-    async fn test_cmd_prune_dry_run() {
+    async fn test_cmd_similarity_export() {
         let storage = test_storage();
-        let history = test_history();
+        let config = test_config();
+        let first = seed_memory(&storage, "Retry policy, v2", "Retry three times.", "fact").await;
+        let second = seed_memory(&storage, "Retry policy, v2", "Retry three times.", "fact").await;
         seed_memory(
             &storage,
-            "Prune candidate india",
-            "A memory that might be pruned during a dry run test.",
-            "observation",
+            "Deploy window",
+            "Deploys happen on Tuesdays.",
+            "fact",
         )
         .await;
 
-        let result = cmd_prune(&storage, &history, "test-user", 90, true, false).await;
-        assert!(result.is_ok());
-    }
+        let path = std::env::temp_dir().join(format!("shabka-similarity-{}.csv", Uuid::now_v7()));
+        cmd_similarity_export(&storage, &config.labels, 0.99, path.to_str().unwrap())
+            .await
+            .unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
 
-    // -----------------------------------------------------------------------
-    // chain
-    // -----------------------------------------------------------------------
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2, "header and the one identical pair: {csv}");
+        assert!(lines[1].contains(&first) && lines[1].contains(&second));
+        assert!(lines[1].ends_with("\"Retry policy, v2\",\"Retry policy, v2\""));
+    }
 
     #[tokio::test]
-    async fn test_cmd_chain_no_relations() {
+    async fn test_cmd_cite_check() {
         let storage = test_storage();
         let id = seed_memory(
             &storage,
-            "Chain start juliet",
-            "An isolated memory with no relations for chain traversal.",
-            "pattern",
+            "Deploy window",
+            "Deploys happen on Tuesdays after standup.",
+            "decision",
         )
         .await;
 
-        let result = cmd_chain(&storage, &id, None, 5, true).await;
-        assert!(result.is_ok());
+        let supported = format!("Deploys happen on Tuesdays [{id}].");
+        assert!(
+            !cmd_cite_check(&storage, None, "test-user", &supported, true, false)
+                .await
+                .unwrap(),
+            "a supported citation is not flagged"
+        );
+        let invented = format!("Deploys happen on Tuesdays [{}].", Uuid::now_v7());
+        assert!(
+            cmd_cite_check(&storage, None, "test-user", &invented, true, false)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a, \"b\""), "\"a, \"\"b\"\"\"");
     }
 
     // -----------------------------------------------------------------------
-    // export / import roundtrip
+    // project
     // -----------------------------------------------------------------------
 
-    #[tokio::test]
-    async fn test_cmd_export_import_roundtrip() {
-        let storage = test_storage();
+    #[test]
+    fn test_cmd_project_use_and_clear() {
+        let dir = std::env::temp_dir().join(format!("shabka-project-{}", uuid::Uuid::now_v7()));
+        std::fs::create_dir_all(&dir).unwrap();
         let config = test_config();
-        let embedder = test_embedder(&config);
-        let history = test_history();
 
-        seed_memory(
-            &storage,
-            "Export roundtrip kilo",
-            "This memory will be exported and then imported back.",
-            "lesson",
+        cmd_project(
+            ProjectAction::Use {
+                name: "thesis".to_string(),
+            },
+            &config,
+            None,
+            &dir,
         )
-        .await;
+        .unwrap();
+        let loaded = ShabkaConfig::load(Some(&dir)).unwrap();
+        assert_eq!(loaded.resolve_project(None).as_deref(), Some("thesis"));
 
-        // Export to a temp file
-        let tmp_path =
-            std::env::temp_dir().join(format!("shabka-test-export-{}.json", uuid::Uuid::now_v7()));
-        let tmp_str = tmp_path.to_str().unwrap();
+        cmd_project(ProjectAction::Clear, &config, None, &dir).unwrap();
+        let loaded = ShabkaConfig::load(Some(&dir)).unwrap();
+        assert_eq!(loaded.resolve_project(None), None);
 
-        let export_result = cmd_export(&storage, tmp_str, "private", None, false).await;
-        assert!(export_result.is_ok(), "export failed: {:?}", export_result);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 
-        // Import into a fresh storage
-        let storage2 = test_storage();
-        let import_result = cmd_import(&storage2, &embedder, "test-user", tmp_str, &history).await;
-        assert!(import_result.is_ok(), "import failed: {:?}", import_result);
+    #[test]
+    fn test_cmd_project_use_rejects_empty() {
+        let dir = std::env::temp_dir();
+        let result = cmd_project(
+            ProjectAction::Use {
+                name: "  ".to_string(),
+            },
+            &test_config(),
+            None,
+            &dir,
+        );
+        assert!(result.is_err());
+    }
 
-        // Verify the imported memory exists
-        let entries = storage2
-            .timeline(&TimelineQuery {
-                limit: 100,
-                ..Default::default()
-            })
-            .await
-            .unwrap();
-        assert!(
-            !entries.is_empty(),
-            "imported storage should have at least one memory"
+    #[test]
+    fn test_global_project_flag_parses_after_subcommand() {
+        let cli = Cli::try_parse_from(["shabka", "list", "--project", "thesis"]).unwrap();
+        assert_eq!(cli.project.as_deref(), Some("thesis"));
+        let cli = Cli::try_parse_from(["shabka", "-p", "thesis", "search", "pool"]).unwrap();
+        assert_eq!(cli.project.as_deref(), Some("thesis"));
+    }
+
+    #[tokio::test]
+    async fn test_inbox_triage_applies_answers() {
+        let storage = test_storage();
+        let history = test_history();
+        let retention = decay::RetentionConfig::default();
+        let save = |title: &str, status: MemoryStatus| {
+            let mut m = Memory::new(
+                title.to_string(),
+                "Content".to_string(),
+                MemoryKind::Fact,
+                "test-user".to_string(),
+            );
+            m.status = status;
+            m
+        };
+        let pending = save("Captured", MemoryStatus::Pending);
+        let (c, d) = (
+            save("Pool size", MemoryStatus::Active),
+            save("Pool size again", MemoryStatus::Active),
         );
+        let lapsed = save("Old fact", MemoryStatus::Active);
+        for m in [&pending, &c, &d, &lapsed] {
+            storage.save_memory(m, None).await.unwrap();
+        }
+        let item = |kind, memory_ids| inbox::InboxItem {
+            kind,
+            memory_ids,
+            title: "item".to_string(),
+            detail: String::new(),
+            since: chrono::Utc::now(),
+        };
+        let items = vec![
+            item(inbox::InboxKind::Review, vec![pending.id]),
+            item(inbox::InboxKind::Backlog, vec![]),
+            item(inbox::InboxKind::Duplicate, vec![c.id, d.id]),
+            item(inbox::InboxKind::VerificationExpired, vec![lapsed.id]),
+        ];
+
+        let mut out = Vec::new();
+        let summary = triage(
+            &storage,
+            &history,
+            &retention,
+            "test-user",
+            &items,
+            "a\nr\n\n".as_bytes(),
+            &mut out,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            summary,
+            TriageSummary {
+                accepted: 1,
+                rejected: 1,
+                skipped: 2,
+            }
+        );
+        assert_eq!(
+            storage.get_memory(pending.id).await.unwrap().status,
+            MemoryStatus::Active
+        );
+        let relations = storage.get_relations(c.id).await.unwrap();
+        assert!(relations
+            .iter()
+            .any(|r| r.relation_type == RelationType::Related));
+        assert_eq!(
+            storage.get_memory(lapsed.id).await.unwrap().verification,
+            VerificationStatus::default()
+        );
+        assert!(String::from_utf8(out).unwrap().contains("[1/4]"));
+    }
 
-        // Clean up temp file
-        let _ = std::fs::remove_file(&tmp_path);
+    #[test]
+    fn test_inbox_kind_filter_parses() {
+        let cli =
+            Cli::try_parse_from(["shabka", "inbox", "--kind", "review,sync-conflict"]).unwrap();
+        let Command::Inbox { kind, .. } = cli.command else {
+            panic!("expected inbox command");
+        };
+        assert_eq!(
+            kind,
+            vec![inbox::InboxKind::Review, inbox::InboxKind::SyncConflict]
+        );
+        assert!(Cli::try_parse_from(["shabka", "inbox", "--kind", "nope"]).is_err());
     }
 
     // -----------------------------------------------------------------------
-    // assess
+    // check
     // -----------------------------------------------------------------------
 
     #[tokio::test]
-    async fn test_cmd_assess() {
+    async fn test_cmd_check_clean_database() {
         let storage = test_storage();
-        let config = test_config();
         seed_memory(
             &storage,
-            "Assess target lima",
-            "A memory to be assessed for quality issues.",
-            "observation",
+            "Check target mike",
+            "A healthy memory with an embedding.",
+            "fact",
         )
         .await;
-
-        let result = cmd_assess(&storage, None, &config.graph, None, false, true).await;
-        assert!(result.is_ok());
+        let mut run = MaintenanceRun::start(MaintenanceOp::Check, serde_json::Value::Null);
+        let result = cmd_check(&storage, false, true, &mut run).await;
+        assert!(matches!(result, Ok(false)), "clean database has no issues");
     }
 
     // -----------------------------------------------------------------------
@@ -3633,25 +11295,49 @@ mod tests {
         )
         .await;
 
-        let result = cmd_context_pack(
+        let request = search_request("context", None, None, None, &ExcludeArgs::default())
+            .unwrap()
+            .visible_to("test-user");
+        let result = cmd_context_pack(&storage, &embedder, request, 2000, true, None).await;
+        assert!(result.is_ok());
+    }
+
+    // -----------------------------------------------------------------------
+    // demo
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn test_cmd_demo_tutorial() {
+        let storage = test_storage();
+        let config = test_config();
+        let embedder = test_embedder(&config);
+        let history = test_history();
+
+        let result = cmd_demo_tutorial(
             &storage,
             &embedder,
+            &config,
+            &test_questions(),
             "test-user",
-            "context",
-            2000,
-            None,
-            None,
-            None,
-            true,
-            None,
+            &history,
+            false,
         )
         .await;
-        assert!(result.is_ok());
+        assert!(result.is_ok(), "tutorial failed: {result:?}");
+
+        let entries = demo_entries(&storage).await.unwrap();
+        let fix = entries.iter().find(|e| e.title == DEMO_FIX).unwrap();
+        let fix = storage.get_memory(fix.id).await.unwrap();
+        assert_eq!(fix.verification, VerificationStatus::Verified);
     }
 
-    // -----------------------------------------------------------------------
-    // demo
-    // -----------------------------------------------------------------------
+    #[test]
+    fn test_tutorial_continue() {
+        let mut out = Vec::new();
+        assert!(tutorial_continue(&b"\n"[..], &mut out, "> ").unwrap());
+        assert!(!tutorial_continue(&b"q\n"[..], &mut out, "> ").unwrap());
+        assert!(!tutorial_continue(&b""[..], &mut out, "> ").unwrap());
+    }
 
     #[tokio::test]
     async fn test_cmd_demo_and_clean() {
@@ -3704,4 +11390,93 @@ mod tests {
             "should have no demo memories after demo --clean"
         );
     }
+
+    /// A memory with a fixed ID and timestamps, for snapshots.
+    fn fixture(n: u128, title: &str, content: &str, kind: MemoryKind, day: u32) -> Memory {
+        use chrono::TimeZone;
+
+        let mut m = Memory::new(title.to_string(), content.to_string(), kind, "alice".into())
+            .with_project("api".to_string());
+        m.id = Uuid::from_u128(n);
+        m.created_at = chrono::Utc
+            .with_ymd_and_hms(2026, 1, day, 9, 30, 0)
+            .unwrap();
+        m.updated_at = m.created_at;
+        m.accessed_at = m.created_at;
+        m
+    }
+
+    /// Two memories and a relation, listed newest first as storage might.
+    fn fixture_export() -> ExportData {
+        let pool = fixture(
+            1,
+            "Pool sizing",
+            "Keep the pool at 20 connections; more starves the database.",
+            MemoryKind::Decision,
+            15,
+        )
+        .with_tags(vec!["db".to_string(), "pool".to_string()])
+        .with_importance(0.8);
+        let retry = fixture(
+            2,
+            "Retry budget",
+            "Retry idempotent calls twice with jitter.",
+            MemoryKind::Pattern,
+            16,
+        )
+        .with_tags(vec!["http".to_string()])
+        .with_importance(0.6);
+        ExportData {
+            relations: vec![MemoryRelation {
+                source_id: retry.id,
+                target_id: pool.id,
+                relation_type: RelationType::Related,
+                strength: 0.5,
+            }],
+            memories: vec![retry, pool],
+            blobs: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_export_snapshots() {
+        insta::assert_snapshot!(
+            "export_json",
+            render_export(fixture_export(), false, true).unwrap()
+        );
+        insta::assert_snapshot!(
+            "export_jsonl",
+            render_export(fixture_export(), true, true).unwrap()
+        );
+
+        // Without --stable-output, storage order is kept.
+        let unsorted = render_export(fixture_export(), false, false).unwrap();
+        let data: ExportData = serde_json::from_str(&unsorted).unwrap();
+        assert_eq!(data.memories[0].title, "Retry budget");
+    }
+
+    #[test]
+    fn test_assess_snapshot() {
+        let results = vec![
+            AssessmentResult {
+                memory_id: Uuid::from_u128(1),
+                title: "Modified main.rs".to_string(),
+                issues: vec![
+                    assess::QualityIssue::GenericTitle {
+                        title: "Modified main.rs".to_string(),
+                    },
+                    assess::QualityIssue::NoTags,
+                ],
+            },
+            AssessmentResult {
+                memory_id: Uuid::from_u128(2),
+                title: "Pool sizing".to_string(),
+                issues: vec![assess::QualityIssue::ShortContent { length: 12 }],
+            },
+        ];
+        insta::assert_snapshot!(
+            "assess_json",
+            serde_json::to_string_pretty(&assess_output(4, &results)).unwrap()
+        );
+    }
 }
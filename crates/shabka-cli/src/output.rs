@@ -0,0 +1,308 @@
+//! Machine-readable output helpers shared by CLI commands.
+//!
+//! Every `--json` output has a stable, typed shape that can be printed as a
+//! JSON Schema with `--schema`. List-like commands also support `--jsonl`,
+//! which streams one JSON object per line for shell pipelines.
+//! `--stable-output` strips dates and versions from text output and sorts
+//! exports, so any of these formats can be snapshotted and diffed.
+
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use shabka_core::attachments::ExportedBlob;
+use shabka_core::model::{Memory, MemoryKind, MemoryRelation, RelationType, VerificationStatus};
+use uuid::Uuid;
+
+/// Conventional argument meaning "read from stdin" / "write to stdout".
+pub const STDIO: &str = "-";
+
+/// Process exit status when a query-style command (search, list, assess,
+/// check) ran successfully but found nothing.
+pub const EXIT_NO_RESULTS: u8 = 1;
+
+/// Process exit status for any error.
+pub const EXIT_ERROR: u8 = 2;
+
+static STABLE: AtomicBool = AtomicBool::new(false);
+
+/// Turn stable output on or off for the whole process (`--stable-output`).
+pub fn set_stable(stable: bool) {
+    STABLE.store(stable, Ordering::Relaxed);
+}
+
+/// Whether output should leave out dates and versions and sort records
+/// deterministically, so it can be treated as a contract.
+pub fn stable() -> bool {
+    STABLE.load(Ordering::Relaxed)
+}
+
+/// Result of a successful command, mapped onto the exit-code contract:
+/// `0` when results were found (grep-style), `1` when nothing matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    NoResults,
+}
+
+impl Outcome {
+    /// `found` is whether a query-style command produced any results.
+    pub fn found(found: bool) -> Self {
+        if found {
+            Self::Success
+        } else {
+            Self::NoResults
+        }
+    }
+
+    pub fn exit_code(self) -> std::process::ExitCode {
+        match self {
+            Self::Success => std::process::ExitCode::SUCCESS,
+            Self::NoResults => std::process::ExitCode::from(EXIT_NO_RESULTS),
+        }
+    }
+}
+
+/// Read `source` as text: `-` reads all of stdin, anything else is a file path.
+pub fn read_source(source: &str) -> Result<String> {
+    if source == STDIO {
+        let mut buf = String::new();
+        std::io::stdin()
+            .read_to_string(&mut buf)
+            .context("failed to read from stdin")?;
+        Ok(buf)
+    } else {
+        if !std::path::Path::new(source).exists() {
+            anyhow::bail!("file not found: {}", source);
+        }
+        std::fs::read_to_string(source).with_context(|| format!("failed to read {source}"))
+    }
+}
+
+/// Resolve an inline text argument: `-` (or no value) reads stdin, anything
+/// else is used verbatim.
+pub fn read_text_arg(value: Option<&str>) -> Result<String> {
+    match value {
+        None | Some(STDIO) => read_source(STDIO),
+        Some(text) => Ok(text.to_string()),
+    }
+}
+
+/// Print each item as a single compact JSON line. A closed pipe (e.g.
+/// `| head`) ends the stream quietly instead of failing.
+pub fn print_jsonl<T: Serialize>(items: &[T]) -> Result<()> {
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    ignore_broken_pipe(write_jsonl(&mut out, items))
+}
+
+/// Write each item as a single compact JSON line to `out`.
+pub fn write_jsonl<T: Serialize, W: Write>(out: &mut W, items: &[T]) -> Result<()> {
+    for item in items {
+        serde_json::to_writer(&mut *out, item)?;
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Print the JSON Schema describing `T` (the `--json` output of a command).
+pub fn print_schema<T: JsonSchema>() -> Result<()> {
+    let schema = schemars::schema_for!(T);
+    let text = serde_json::to_string_pretty(&schema)?;
+    ignore_broken_pipe(writeln!(std::io::stdout().lock(), "{text}").map_err(Into::into))
+}
+
+fn ignore_broken_pipe(result: Result<()>) -> Result<()> {
+    match result {
+        Err(e)
+            if e.chain().any(|c| {
+                c.downcast_ref::<std::io::Error>()
+                    .is_some_and(|io| io.kind() == std::io::ErrorKind::BrokenPipe)
+                    || c.downcast_ref::<serde_json::Error>()
+                        .and_then(|j| j.io_error_kind())
+                        == Some(std::io::ErrorKind::BrokenPipe)
+            }) =>
+        {
+            Ok(())
+        }
+        other => other,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Typed output shapes for commands that previously emitted ad-hoc JSON
+// ---------------------------------------------------------------------------
+
+/// One hop in `shabka chain --json`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ChainLinkOutput {
+    pub id: Uuid,
+    pub title: String,
+    pub kind: MemoryKind,
+    pub relation_type: RelationType,
+    pub from_id: Uuid,
+    pub strength: f32,
+    pub depth: usize,
+    /// The relation leads back to a memory already in the chain.
+    pub cycle: bool,
+}
+
+/// A memory in `shabka graph top --json`, grouped by project, most central
+/// first within each.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CentralMemoryOutput {
+    pub id: Uuid,
+    pub title: String,
+    pub kind: MemoryKind,
+    pub project_id: Option<String>,
+    pub verification: VerificationStatus,
+    /// Distinct memories related to this one.
+    pub degree: usize,
+    /// PageRank over relations; the most central memory scores 1.0.
+    pub pagerank: f32,
+}
+
+/// A neighbour in `shabka similar --json`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SimilarOutput {
+    pub id: Uuid,
+    pub title: String,
+    pub kind: MemoryKind,
+    /// Vector similarity to the source memory (0.0–1.0).
+    pub score: f32,
+    /// Relation already linking the two memories, if any.
+    pub relation: Option<RelationType>,
+}
+
+/// A memory removed by `shabka delete --json`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct DeletedMemory {
+    pub id: Uuid,
+    pub title: String,
+    pub kind: MemoryKind,
+}
+
+/// Output of `shabka delete --json` (single and bulk share one shape).
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct DeleteOutput {
+    pub deleted: usize,
+    pub memories: Vec<DeletedMemory>,
+}
+
+/// Output of `shabka unarchive --json` (single and bulk share one shape).
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct UnarchiveOutput {
+    pub restored: usize,
+    /// Restored memories, or those that would be restored with `--dry-run`.
+    pub memories: Vec<DeletedMemory>,
+}
+
+/// A memory flagged by `shabka assess --json`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct AssessIssueOutput {
+    pub id: Uuid,
+    pub title: String,
+    pub issues: Vec<String>,
+    /// Referenced paths missing from the tree (`--codebase`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing_paths: Vec<String>,
+    /// Referenced symbols missing from the tree (`--codebase`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing_symbols: Vec<String>,
+    /// Kind the memory's content suggests, for `wrong kind` issues.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggested_kind: Option<MemoryKind>,
+}
+
+/// Output of `shabka assess --json`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct AssessOutput {
+    pub total_memories: usize,
+    pub memories_with_issues: usize,
+    pub score: u32,
+    pub counts: shabka_core::assess::IssueCounts,
+    pub issues: Vec<AssessIssueOutput>,
+}
+
+/// Output of `shabka gaps --json`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct GapsOutput {
+    #[serde(flatten)]
+    pub report: shabka_core::gaps::GapReport,
+    /// Undocumented areas with at least `--min-edits` edits.
+    pub gaps: Vec<shabka_core::gaps::AreaActivity>,
+}
+
+/// Output of `shabka add --json`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct AddOutput {
+    pub id: Uuid,
+    pub title: String,
+    pub kind: MemoryKind,
+}
+
+/// A single line of `shabka export --jsonl`. Memories come first, followed
+/// by the relations between them, so a streaming importer can insert in order.
+/// With `--attachments`, attachment contents follow as `blob` records.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum ExportRecord {
+    Memory(Box<Memory>),
+    Relation(MemoryRelation),
+    Blob(ExportedBlob),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_jsonl_one_object_per_line() {
+        let items = vec![
+            serde_json::json!({"a": 1}),
+            serde_json::json!({"b": [1, 2]}),
+        ];
+        let mut buf = Vec::new();
+        write_jsonl(&mut buf, &items).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines, vec![r#"{"a":1}"#, r#"{"b":[1,2]}"#]);
+    }
+
+    #[test]
+    fn test_export_record_roundtrip() {
+        let memory = Memory::new(
+            "Record".to_string(),
+            "content".to_string(),
+            MemoryKind::Fact,
+            "tester".to_string(),
+        );
+        let line = serde_json::to_string(&ExportRecord::Memory(Box::new(memory.clone()))).unwrap();
+        assert!(line.starts_with(r#"{"record":"memory""#));
+        match serde_json::from_str::<ExportRecord>(&line).unwrap() {
+            ExportRecord::Memory(m) => assert_eq!(m.id, memory.id),
+            ExportRecord::Relation(_) | ExportRecord::Blob(_) => panic!("expected memory record"),
+        }
+    }
+
+    #[test]
+    fn test_outcome_found() {
+        assert_eq!(Outcome::found(true), Outcome::Success);
+        assert_eq!(Outcome::found(false), Outcome::NoResults);
+    }
+
+    #[test]
+    fn test_read_text_arg_inline() {
+        assert_eq!(read_text_arg(Some("hello")).unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_schema_has_properties() {
+        let schema = schemars::schema_for!(DeleteOutput);
+        let value = serde_json::to_value(&schema).unwrap();
+        assert!(value["properties"]["deleted"].is_object());
+        assert!(value["properties"]["memories"].is_object());
+    }
+}
@@ -0,0 +1,120 @@
+//! Progress bars, Ctrl-C handling and resumable checkpoints for
+//! long-running commands (reembed, assess --duplicates, export, import).
+//!
+//! Bars draw on stderr and only when it is a terminal, so piped output stays
+//! clean; `--no-progress` turns them off everywhere. The first Ctrl-C asks
+//! the running command to stop after the current item and save a
+//! [`Checkpoint`]; a second one exits immediately.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Exit status after a second Ctrl-C (128 + SIGINT).
+const EXIT_INTERRUPTED: i32 = 130;
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turn progress bars on or off for the whole process (`--no-progress`).
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// A progress bar over `len` items, labelled `message`. Hidden when
+/// progress is disabled or stderr isn't a terminal.
+pub fn bar(len: usize, message: &str) -> ProgressBar {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return ProgressBar::hidden();
+    }
+    let bar = ProgressBar::with_draw_target(Some(len as u64), ProgressDrawTarget::stderr());
+    bar.set_style(
+        ProgressStyle::with_template("  {msg} [{bar:30.cyan/blue}] {pos}/{len} ({eta} left)")
+            .unwrap_or_else(|_| ProgressStyle::default_bar())
+            .progress_chars("=> "),
+    );
+    bar.set_message(message.to_string());
+    bar.enable_steady_tick(Duration::from_millis(200));
+    bar
+}
+
+/// Set when the user pressed Ctrl-C during a command that handles it.
+pub struct Interrupt {
+    flag: Arc<AtomicBool>,
+}
+
+impl Interrupt {
+    /// Take over Ctrl-C for the rest of the process: the first press sets
+    /// the flag, the second exits.
+    pub fn install() -> Self {
+        let flag = Arc::new(AtomicBool::new(false));
+        let handler = flag.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+            handler.store(true, Ordering::Relaxed);
+            eprintln!("\nStopping after the current item (Ctrl-C again to quit now)...");
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(EXIT_INTERRUPTED);
+            }
+        });
+        Self { flag }
+    }
+
+    pub fn requested(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+}
+
+/// Memories an interrupted command already handled, so running it again
+/// continues where it stopped. Stored in
+/// `~/.config/shabka/checkpoints/<operation>.json`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// What the work depends on (embedding model, import file); a
+    /// checkpoint for a different key is ignored.
+    pub key: String,
+    pub done: HashSet<Uuid>,
+}
+
+impl Checkpoint {
+    fn path(operation: &str) -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("shabka")
+            .join("checkpoints")
+            .join(format!("{operation}.json"))
+    }
+
+    /// The saved checkpoint for `operation` if it matches `key`, otherwise
+    /// an empty one.
+    pub fn load(operation: &str, key: &str) -> Self {
+        std::fs::read_to_string(Self::path(operation))
+            .ok()
+            .and_then(|text| serde_json::from_str::<Self>(&text).ok())
+            .filter(|checkpoint| checkpoint.key == key)
+            .unwrap_or_else(|| Self {
+                key: key.to_string(),
+                done: HashSet::new(),
+            })
+    }
+
+    pub fn save(&self, operation: &str) -> std::io::Result<()> {
+        let path = Self::path(operation);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)? + "\n")
+    }
+
+    /// Remove the checkpoint once the work is complete.
+    pub fn clear(operation: &str) {
+        let _ = std::fs::remove_file(Self::path(operation));
+    }
+}
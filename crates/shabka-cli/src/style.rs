@@ -0,0 +1,231 @@
+//! Colors and symbols in CLI output.
+//!
+//! Text output is colored and uses a few Unicode symbols (`✓`, `→`, tree
+//! lines). `--plain` or `SHABKA_PLAIN=1` swaps every symbol for ASCII, turns
+//! off colors and progress bars, and is what screen readers and scripts
+//! should use. `NO_COLOR` (<https://no-color.org>) turns off colors only.
+//!
+//! Main imports [`Paint`] in place of `owo_colors::OwoColorize`, so existing
+//! `.green()` and `.dimmed()` calls honour these settings.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use owo_colors::Style;
+
+static COLOR: AtomicBool = AtomicBool::new(true);
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Set plain output for the whole process: `plain` is `--plain`, and the
+/// `SHABKA_PLAIN` and `NO_COLOR` environment variables are read here.
+pub fn configure(plain: bool) {
+    let plain = plain || env_flag("SHABKA_PLAIN");
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    PLAIN.store(plain, Ordering::Relaxed);
+    COLOR.store(!plain && !no_color, Ordering::Relaxed);
+}
+
+/// Whether output should be ASCII-only, uncolored and free of progress bars.
+pub fn plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+fn color() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+fn env_flag(name: &str) -> bool {
+    std::env::var(name).is_ok_and(|v| !matches!(v.as_str(), "" | "0" | "false"))
+}
+
+/// A symbol with an ASCII stand-in for plain output.
+#[derive(Debug, Clone, Copy)]
+pub struct Glyph {
+    fancy: &'static str,
+    ascii: &'static str,
+}
+
+impl Glyph {
+    const fn new(fancy: &'static str, ascii: &'static str) -> Self {
+        Self { fancy, ascii }
+    }
+
+    fn as_str(self) -> &'static str {
+        self.pick(plain())
+    }
+
+    fn pick(self, plain: bool) -> &'static str {
+        if plain {
+            self.ascii
+        } else {
+            self.fancy
+        }
+    }
+}
+
+impl fmt::Display for Glyph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// Something succeeded.
+pub const OK: Glyph = Glyph::new("✓", "ok");
+/// Something was rejected or failed.
+pub const FAIL: Glyph = Glyph::new("✗", "x");
+/// Something was removed.
+pub const REMOVED: Glyph = Glyph::new("×", "x");
+/// Needs attention.
+pub const WARN: Glyph = Glyph::new("⚠", "!");
+/// Before and after, or what to do next.
+pub const ARROW: Glyph = Glyph::new("→", "->");
+/// Between the parts of a path, such as file and symbol.
+pub const CHEVRON: Glyph = Glyph::new("›", ">");
+/// Between two details on one line.
+pub const DOT: Glyph = Glyph::new("·", "-");
+/// Between a subject and what is said about it.
+pub const DASH: Glyph = Glyph::new("—", "-");
+/// A tree child with more siblings after it.
+pub const TREE_BRANCH: Glyph = Glyph::new("├─ ", "|- ");
+/// The last child in a tree.
+pub const TREE_LAST: Glyph = Glyph::new("└─ ", "`- ");
+/// Indent under a tree child with more siblings after it.
+pub const TREE_PIPE: Glyph = Glyph::new("│  ", "|  ");
+/// A short marker for an item nested under the line above.
+pub const NESTED: Glyph = Glyph::new("└", "`");
+
+/// Every glyph, for swapping them in text that isn't formatted here.
+const GLYPHS: &[Glyph] = &[
+    OK,
+    FAIL,
+    REMOVED,
+    WARN,
+    ARROW,
+    CHEVRON,
+    DOT,
+    DASH,
+    TREE_BRANCH,
+    TREE_LAST,
+    TREE_PIPE,
+    NESTED,
+];
+
+/// `text` with its glyphs swapped for ASCII when output is plain, for prose
+/// such as translated messages.
+pub fn plain_text(text: String) -> String {
+    if plain() {
+        to_ascii(&text)
+    } else {
+        text
+    }
+}
+
+fn to_ascii(text: &str) -> String {
+    GLYPHS.iter().fold(text.to_string(), |text, glyph| {
+        text.replace(glyph.fancy.trim_end(), glyph.ascii.trim_end())
+    })
+}
+
+/// A horizontal rule `width` characters wide.
+pub fn rule(width: usize) -> String {
+    (if plain() { "-" } else { "─" }).repeat(width)
+}
+
+/// Colors for anything printable, dropped when colors are off. Padding
+/// such as `{:<12}` applies to the text, not the escape codes.
+pub trait Paint {
+    fn paint(&self, style: Style) -> Painted<'_, Self> {
+        Painted { inner: self, style }
+    }
+
+    fn blue(&self) -> Painted<'_, Self> {
+        self.paint(Style::new().blue())
+    }
+
+    fn bold(&self) -> Painted<'_, Self> {
+        self.paint(Style::new().bold())
+    }
+
+    fn cyan(&self) -> Painted<'_, Self> {
+        self.paint(Style::new().cyan())
+    }
+
+    fn dimmed(&self) -> Painted<'_, Self> {
+        self.paint(Style::new().dimmed())
+    }
+
+    fn green(&self) -> Painted<'_, Self> {
+        self.paint(Style::new().green())
+    }
+
+    fn magenta(&self) -> Painted<'_, Self> {
+        self.paint(Style::new().magenta())
+    }
+
+    fn red(&self) -> Painted<'_, Self> {
+        self.paint(Style::new().red())
+    }
+
+    fn yellow(&self) -> Painted<'_, Self> {
+        self.paint(Style::new().yellow())
+    }
+}
+
+impl<T: ?Sized> Paint for T {}
+
+/// Text from [`Paint`], colored if colors are on.
+pub struct Painted<'a, T: ?Sized> {
+    inner: &'a T,
+    style: Style,
+}
+
+impl<T: fmt::Display + ?Sized> fmt::Display for Painted<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if color() {
+            fmt::Display::fmt(&self.style.style(self.inner), f)
+        } else {
+            fmt::Display::fmt(self.inner, f)
+        }
+    }
+}
+
+impl<T: fmt::Debug + ?Sized> fmt::Debug for Painted<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if color() {
+            fmt::Debug::fmt(&self.style.style(self.inner), f)
+        } else {
+            fmt::Debug::fmt(self.inner, f)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glyphs_are_ascii_when_plain() {
+        for glyph in GLYPHS {
+            assert!(glyph.pick(true).is_ascii(), "{}", glyph.fancy);
+            assert_eq!(glyph.pick(false), glyph.fancy);
+        }
+        // Tree lines keep their width so nested lines still line up.
+        for glyph in [TREE_BRANCH, TREE_LAST, TREE_PIPE, NESTED] {
+            assert_eq!(glyph.pick(true).len(), glyph.fancy.chars().count());
+        }
+    }
+
+    #[test]
+    fn test_english_messages_are_ascii_when_plain() {
+        let english = include_str!("../locales/en/cli.ftl");
+        assert!(!english.is_ascii(), "messages use glyphs such as a dash");
+        assert!(to_ascii(english).is_ascii());
+    }
+
+    #[test]
+    fn test_padding_applies_to_text() {
+        let padded = format!("[{:<4}]", "ab".cyan());
+        assert!(padded.contains("ab  "), "{padded:?}");
+        assert!(padded.starts_with('[') && padded.ends_with(']'));
+    }
+}
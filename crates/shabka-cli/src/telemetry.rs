@@ -0,0 +1,60 @@
+//! Result counts for the opt-in usage telemetry.
+//!
+//! Commands report what they returned with [`count`] while they run; `main`
+//! takes the counts once the command finishes and, with `[telemetry]
+//! enabled`, records them with its name and latency in
+//! [`shabka_core::telemetry::TelemetryLog`]. Only numbers are kept, never
+//! arguments or memory content.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+static COUNTS: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+
+/// Record `value` under `name` for the running command, replacing any
+/// earlier value.
+pub fn count(name: &str, value: usize) {
+    if let Ok(mut counts) = COUNTS.lock() {
+        counts.insert(name.to_string(), value as u64);
+    }
+}
+
+/// The counts recorded so far, leaving none behind.
+pub fn take_counts() -> BTreeMap<String, u64> {
+    COUNTS
+        .lock()
+        .map(|mut counts| std::mem::take(&mut *counts))
+        .unwrap_or_default()
+}
+
+/// The command and subcommand names the user ran, e.g. `graph top`.
+pub fn command_name(matches: &clap::ArgMatches) -> Option<String> {
+    let mut names = Vec::new();
+    let mut current = matches;
+    while let Some((name, sub)) = current.subcommand() {
+        names.push(name);
+        current = sub;
+    }
+    (!names.is_empty()).then(|| names.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{Arg, Command};
+
+    #[test]
+    fn test_command_name_joins_subcommands() {
+        let cli = Command::new("shabka")
+            .subcommand(Command::new("search").arg(Arg::new("query")))
+            .subcommand(Command::new("graph").subcommand(Command::new("top")));
+        let matches = cli.clone().get_matches_from(["shabka", "graph", "top"]);
+        assert_eq!(command_name(&matches).as_deref(), Some("graph top"));
+        let matches = cli
+            .clone()
+            .get_matches_from(["shabka", "search", "secret query"]);
+        assert_eq!(command_name(&matches).as_deref(), Some("search"));
+        let matches = cli.get_matches_from(["shabka"]);
+        assert_eq!(command_name(&matches), None);
+    }
+}
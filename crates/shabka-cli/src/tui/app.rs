@@ -1,4 +1,5 @@
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use shabka_core::inbox::Inbox;
 use shabka_core::model::*;
 
 use super::event::{AsyncAction, AsyncResult, SearchResultEntry};
@@ -66,6 +67,7 @@ pub struct App {
     pub active_query: Option<String>,
     pub search_results: Vec<SearchResultEntry>,
     pub filter_kind_index: usize, // index into ALL_KINDS
+    pub show_archived: bool,
 
     // -- Detail state --
     pub detail_memory: Option<Memory>,
@@ -73,9 +75,14 @@ pub struct App {
     pub detail_trust: f32,
     pub detail_history: Vec<String>,
     pub detail_scroll: u16,
+    /// Sources of a consolidated summary, listed by title until unfolded.
+    pub detail_sources: Vec<Memory>,
+    pub detail_sources_expanded: bool,
 
     // -- Status state --
     pub kind_counts: Vec<(String, usize)>,
+    /// Loaded when the status screen opens.
+    pub inbox: Option<Inbox>,
 
     // -- Splash --
     pub splash_until: std::time::Instant,
@@ -109,14 +116,18 @@ impl App {
             active_query: None,
             search_results: Vec::new(),
             filter_kind_index: 0,
+            show_archived: false,
 
             detail_memory: None,
             detail_relations: Vec::new(),
             detail_trust: 0.0,
             detail_history: Vec::new(),
             detail_scroll: 0,
+            detail_sources: Vec::new(),
+            detail_sources_expanded: false,
 
             kind_counts: Vec::new(),
+            inbox: None,
 
             splash_until: std::time::Instant::now() + std::time::Duration::from_secs(3),
 
@@ -151,12 +162,15 @@ impl App {
                 relations,
                 trust,
                 history,
+                sources,
             } => {
                 self.detail_memory = Some(*memory);
                 self.detail_relations = relations;
                 self.detail_trust = trust;
                 self.detail_history = history;
                 self.detail_scroll = 0;
+                self.detail_sources = sources;
+                self.detail_sources_expanded = false;
                 self.screen = Screen::Detail;
                 self.loading = false;
             }
@@ -168,6 +182,9 @@ impl App {
                 // We set a flag via `loading` so the next handle cycle picks it up.
                 self.needs_refresh = true;
             }
+            AsyncResult::Inbox(inbox) => {
+                self.inbox = Some(inbox);
+            }
             AsyncResult::Error(msg) => {
                 self.error_message = Some(msg);
                 self.error_timer = 100; // ~5s at 50ms tick
@@ -232,6 +249,10 @@ impl App {
                 None
             }
             KeyCode::Enter => self.open_detail(),
+            KeyCode::Char('s') => self.selected_id().map(|id| {
+                self.loading = true;
+                AsyncAction::Similar { id }
+            }),
             KeyCode::Char('/') => {
                 self.input_mode = InputMode::Search;
                 self.search_input.clear();
@@ -245,12 +266,29 @@ impl App {
             KeyCode::Tab => {
                 self.screen = Screen::Status;
                 self.compute_kind_counts();
-                None
+                Some(AsyncAction::LoadInbox)
             }
             KeyCode::Char('r') => {
                 // Refresh
                 self.loading = true;
-                Some(AsyncAction::LoadTimeline { limit: 500 })
+                Some(self.timeline_action())
+            }
+            KeyCode::Char('a') => {
+                // Toggle between the live timeline and archived memories
+                self.show_archived = !self.show_archived;
+                self.active_query = None;
+                self.search_results.clear();
+                self.selected = 0;
+                self.loading = true;
+                Some(self.timeline_action())
+            }
+            KeyCode::Char('u') => {
+                let id = self.selected_id()?;
+                if self.selected_status()? != MemoryStatus::Archived {
+                    return None;
+                }
+                self.loading = true;
+                Some(AsyncAction::Unarchive { id })
             }
             KeyCode::Char('n') => {
                 // Open create screen with blank form
@@ -372,11 +410,32 @@ impl App {
                 }
                 None
             }
+            KeyCode::Char('u') => {
+                let memory = self.detail_memory.as_ref()?;
+                if memory.status != MemoryStatus::Archived {
+                    return None;
+                }
+                self.loading = true;
+                Some(AsyncAction::Unarchive { id: memory.id })
+            }
+            KeyCode::Char('o') => {
+                if !self.detail_sources.is_empty() {
+                    self.detail_sources_expanded = !self.detail_sources_expanded;
+                }
+                None
+            }
+            KeyCode::Char('s') => {
+                let id = self.detail_memory.as_ref()?.id;
+                self.screen = Screen::List;
+                self.loading = true;
+                Some(AsyncAction::Similar { id })
+            }
             KeyCode::Esc | KeyCode::Backspace => {
                 self.screen = Screen::List;
                 self.detail_memory = None;
                 self.detail_relations.clear();
                 self.detail_history.clear();
+                self.detail_sources.clear();
                 self.detail_scroll = 0;
                 None
             }
@@ -495,7 +554,14 @@ impl App {
     }
 
     fn open_detail(&mut self) -> Option<AsyncAction> {
-        let id = if self.active_query.is_some() {
+        let id = self.selected_id()?;
+        self.loading = true;
+        Some(AsyncAction::LoadDetail { id })
+    }
+
+    /// ID of the highlighted row (search result or timeline entry).
+    fn selected_id(&self) -> Option<uuid::Uuid> {
+        if self.active_query.is_some() {
             // Browsing search results
             self.search_results.get(self.selected).map(|r| r.memory.id)
         } else {
@@ -504,13 +570,28 @@ impl App {
                 .get(self.selected)
                 .and_then(|&idx| self.entries.get(idx))
                 .map(|e| e.id)
-        };
+        }
+    }
 
-        if let Some(id) = id {
-            self.loading = true;
-            Some(AsyncAction::LoadDetail { id })
+    /// Status of the highlighted row.
+    fn selected_status(&self) -> Option<MemoryStatus> {
+        if self.active_query.is_some() {
+            self.search_results
+                .get(self.selected)
+                .map(|r| r.memory.status)
         } else {
-            None
+            self.filtered_entries
+                .get(self.selected)
+                .and_then(|&idx| self.entries.get(idx))
+                .map(|e| e.status)
+        }
+    }
+
+    /// Timeline reload for the current view (live or archived memories).
+    pub fn timeline_action(&self) -> AsyncAction {
+        AsyncAction::LoadTimeline {
+            limit: 500,
+            status: self.show_archived.then_some(MemoryStatus::Archived),
         }
     }
 
@@ -560,7 +641,7 @@ impl App {
             *counts.entry(entry.kind.to_string()).or_default() += 1;
         }
         let mut sorted: Vec<_> = counts.into_iter().collect();
-        sorted.sort_by(|a, b| b.1.cmp(&a.1));
+        sorted.sort_by_key(|e| std::cmp::Reverse(e.1));
         self.kind_counts = sorted;
     }
 
@@ -708,8 +789,12 @@ mod tests {
     fn test_tab_to_status() {
         let mut app = App::new();
         app.loading = false;
-        app.handle_key(key(KeyCode::Tab));
+        let action = app.handle_key(key(KeyCode::Tab));
         assert_eq!(app.screen, Screen::Status);
+        assert!(matches!(action, Some(AsyncAction::LoadInbox)));
+
+        app.handle_result(super::super::event::AsyncResult::Inbox(Default::default()));
+        assert_eq!(app.inbox.as_ref().map(|i| i.total), Some(0));
 
         app.handle_key(key(KeyCode::Tab));
         assert_eq!(app.screen, Screen::List);
@@ -737,6 +822,25 @@ mod tests {
         assert_eq!(app.screen, Screen::List);
     }
 
+    #[test]
+    fn test_detail_toggle_sources() {
+        let mut app = App::new();
+        app.screen = Screen::Detail;
+        app.handle_key(key(KeyCode::Char('o')));
+        assert!(!app.detail_sources_expanded, "nothing to unfold");
+
+        app.detail_sources = vec![Memory::new(
+            "Source".into(),
+            "Original content".into(),
+            MemoryKind::Fact,
+            "tester".into(),
+        )];
+        app.handle_key(key(KeyCode::Char('o')));
+        assert!(app.detail_sources_expanded);
+        app.handle_key(key(KeyCode::Char('o')));
+        assert!(!app.detail_sources_expanded);
+    }
+
     #[test]
     fn test_ctrl_c_quits() {
         let mut app = App::new();
@@ -772,6 +876,73 @@ mod tests {
         assert_eq!(app.filtered_entries.len(), 1);
     }
 
+    #[test]
+    fn test_similar_key() {
+        let mut app = App::new();
+        app.loading = false;
+        assert!(
+            app.handle_key(key(KeyCode::Char('s'))).is_none(),
+            "no selection"
+        );
+
+        let memory = Memory::new(
+            "Pool exhaustion".into(),
+            "content".into(),
+            MemoryKind::Error,
+            "test".into(),
+        );
+        let id = memory.id;
+        app.detail_memory = Some(memory);
+        app.screen = Screen::Detail;
+        let action = app.handle_key(key(KeyCode::Char('s')));
+        assert!(matches!(action, Some(AsyncAction::Similar { id: got }) if got == id));
+        assert_eq!(app.screen, Screen::List);
+        assert!(app.loading);
+    }
+
+    #[test]
+    fn test_archived_view_and_unarchive() {
+        let mut app = App::new();
+        app.loading = false;
+        let action = app.handle_key(key(KeyCode::Char('a')));
+        assert!(app.show_archived);
+        assert!(matches!(
+            action,
+            Some(AsyncAction::LoadTimeline {
+                status: Some(MemoryStatus::Archived),
+                ..
+            })
+        ));
+
+        let mut memory = Memory::new(
+            "Old decision".into(),
+            "content".into(),
+            MemoryKind::Decision,
+            "test".into(),
+        );
+        app.screen = Screen::Detail;
+        app.detail_memory = Some(memory.clone());
+        assert!(
+            app.handle_key(key(KeyCode::Char('u'))).is_none(),
+            "only archived memories can be restored"
+        );
+
+        memory.status = MemoryStatus::Archived;
+        let id = memory.id;
+        app.detail_memory = Some(memory);
+        let action = app.handle_key(key(KeyCode::Char('u')));
+        assert!(matches!(action, Some(AsyncAction::Unarchive { id: got }) if got == id));
+
+        app.handle_result(super::super::event::AsyncResult::MemoryUpdated);
+        assert!(matches!(
+            app.timeline_action(),
+            AsyncAction::LoadTimeline {
+                status: Some(MemoryStatus::Archived),
+                ..
+            }
+        ));
+    }
+
     #[test]
     fn test_error_toast_timer() {
         let mut app = App::new();
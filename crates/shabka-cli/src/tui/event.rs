@@ -1,15 +1,23 @@
+use shabka_core::inbox::Inbox;
 use shabka_core::model::*;
 use uuid::Uuid;
 
 /// Actions the UI sends to the async worker task.
 #[derive(Debug)]
 pub enum AsyncAction {
-    /// Load the timeline (initial data fetch).
-    LoadTimeline { limit: usize },
+    /// Load the timeline (initial data fetch), optionally limited to one status.
+    LoadTimeline {
+        limit: usize,
+        status: Option<MemoryStatus>,
+    },
     /// Perform a search: embed query → vector_search → rank.
     Search { query: String },
     /// Fetch full detail for a memory (memory + relations + trust).
     LoadDetail { id: Uuid },
+    /// List a memory's nearest neighbours (shown as search results).
+    Similar { id: Uuid },
+    /// Restore an archived memory to active.
+    Unarchive { id: Uuid },
     /// Save a new memory.
     SaveMemory {
         title: String,
@@ -23,6 +31,8 @@ pub enum AsyncAction {
         content: String,
         kind: MemoryKind,
     },
+    /// Collect the inbox (see [`shabka_core::inbox`]) for the status screen.
+    LoadInbox,
 }
 
 /// Results the async worker sends back to the UI.
@@ -41,11 +51,15 @@ pub enum AsyncResult {
         relations: Vec<MemoryRelation>,
         trust: f32,
         history: Vec<String>,
+        /// Memories a consolidated summary was built from.
+        sources: Vec<Memory>,
     },
     /// A new memory was saved successfully.
     MemorySaved,
     /// An existing memory was updated successfully.
     MemoryUpdated,
+    /// Items waiting on the user.
+    Inbox(Inbox),
     /// An error occurred during an async operation.
     Error(String),
 }
@@ -55,4 +69,11 @@ pub enum AsyncResult {
 pub struct SearchResultEntry {
     pub memory: Memory,
     pub score: f32,
+    /// Existing relation to the source memory (similar-memory results only).
+    pub relation: Option<RelationType>,
+    /// Other results on screen this one contradicts.
+    pub contradicts: usize,
+    /// Lower-ranked results in the same error/fix/lesson chain, collapsed
+    /// under this one (see [`shabka_core::graph::group_by_chain`]).
+    pub chain: Vec<Memory>,
 }
@@ -3,28 +3,38 @@ pub mod event;
 mod views;
 mod widgets;
 
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::{Context, Result};
 use crossterm::event::{self as ct_event, Event};
 use ratatui::{DefaultTerminal, Frame};
 use shabka_core::config::ShabkaConfig;
-use shabka_core::embedding::EmbeddingService;
-use shabka_core::history::HistoryLogger;
+use shabka_core::decay;
+use shabka_core::embedding::{self, EmbeddingService};
+use shabka_core::error::ShabkaError;
+use shabka_core::history::{diff_update, EventAction, HistoryLogger, MemoryEvent};
+use shabka_core::inbox::{self, InboxOptions};
 use shabka_core::model::*;
-use shabka_core::ranking::{self, RankCandidate, RankingWeights};
+use shabka_core::retrieval::{self, SearchRequest, SearchResults};
+use shabka_core::services::Services;
 use shabka_core::storage::{Storage, StorageBackend};
 use shabka_core::trust;
 use tokio::sync::mpsc;
 
+use crate::dates;
+
 use self::app::{App, Screen};
 use self::event::{AsyncAction, AsyncResult, SearchResultEntry};
 
 /// Entry point for the interactive TUI mode.
-pub async fn run_tui(config: &ShabkaConfig) -> Result<()> {
-    let storage =
-        shabka_core::storage::create_backend(config).context("failed to create storage backend")?;
-    let embedder = EmbeddingService::from_config(&config.embedding)
+pub async fn run_tui(services: &Services) -> Result<()> {
+    let config = services.config();
+    let storage = services
+        .storage()
+        .context("failed to create storage backend")?;
+    let embedder = services
+        .embedding()
         .context("failed to create embedding service")?;
 
     // Channels for async communication
@@ -32,17 +42,30 @@ pub async fn run_tui(config: &ShabkaConfig) -> Result<()> {
     let (result_tx, mut result_rx) = mpsc::unbounded_channel::<AsyncResult>();
 
     // Storage info for status view
-    let storage_info = config.storage.backend.clone();
+    let unavailable = storage.capabilities().unavailable();
+    let storage_info = if unavailable.is_empty() {
+        config.storage.backend.clone()
+    } else {
+        format!(
+            "{} (unavailable: {})",
+            config.storage.backend,
+            unavailable.join(", ")
+        )
+    };
     let provider_info = config.embedding.provider.clone();
 
     // Spawn async worker
     let worker_result_tx = result_tx.clone();
-    let history_enabled = config.history.enabled;
+    let history = services.history();
+    let worker_config = Arc::clone(config);
+    let user_id = services.user_id().to_string();
     tokio::spawn(async move {
         worker_loop(
             storage,
             embedder,
-            history_enabled,
+            history,
+            worker_config,
+            user_id,
             &mut action_rx,
             &worker_result_tx,
         )
@@ -50,7 +73,10 @@ pub async fn run_tui(config: &ShabkaConfig) -> Result<()> {
     });
 
     // Fire initial timeline load
-    action_tx.send(AsyncAction::LoadTimeline { limit: 500 })?;
+    action_tx.send(AsyncAction::LoadTimeline {
+        limit: 500,
+        status: None,
+    })?;
 
     // Initialize terminal
     let mut terminal = ratatui::init();
@@ -91,7 +117,7 @@ fn run_loop(
         // If a save/update completed, trigger a timeline refresh
         if app.needs_refresh {
             app.needs_refresh = false;
-            let _ = action_tx.send(AsyncAction::LoadTimeline { limit: 500 });
+            let _ = action_tx.send(app.timeline_action());
         }
 
         // Poll for keyboard events (50ms timeout for responsive UI)
@@ -148,19 +174,22 @@ fn render_error_toast(frame: &mut Frame, msg: &str) {
     use ratatui::{
         layout::{Constraint, Flex, Layout},
         style::{Color, Style},
-        widgets::{Block, Borders, Clear, Paragraph},
+        widgets::{Block, Borders, Clear, Paragraph, Wrap},
     };
 
     let area = frame.area();
     let [toast_area] = Layout::horizontal([Constraint::Percentage(60)])
         .flex(Flex::Center)
         .areas(area);
-    let [toast_area] = Layout::vertical([Constraint::Length(3)])
+    // Borders plus one line per line of message (error, then any hint).
+    let height = msg.lines().count().max(1) as u16 + 2;
+    let [toast_area] = Layout::vertical([Constraint::Length(height)])
         .flex(Flex::End)
         .areas(toast_area);
 
     frame.render_widget(Clear, toast_area);
     let toast = Paragraph::new(format!(" ✗ {msg}"))
+        .wrap(Wrap { trim: false })
         .style(Style::default().fg(Color::White).bg(Color::Red))
         .block(
             Block::default()
@@ -171,40 +200,66 @@ fn render_error_toast(frame: &mut Frame, msg: &str) {
     frame.render_widget(toast, toast_area);
 }
 
+/// Toast for a failed action, with the fix on a second line when the
+/// error has a known one.
+fn failed(what: &str, err: impl Into<anyhow::Error>) -> AsyncResult {
+    let err = err.into();
+    let hint = err
+        .chain()
+        .find_map(|e| e.downcast_ref::<ShabkaError>())
+        .and_then(ShabkaError::hint);
+    AsyncResult::Error(match hint {
+        Some(hint) => format!("{what}: {err:#}\n   hint: {hint}"),
+        None => format!("{what}: {err:#}"),
+    })
+}
+
 /// Async worker loop: processes actions using the storage + embedder.
 async fn worker_loop(
-    storage: Storage,
-    embedder: EmbeddingService,
-    history_enabled: bool,
+    storage: Arc<Storage>,
+    embedder: Arc<EmbeddingService>,
+    history: Arc<HistoryLogger>,
+    config: Arc<ShabkaConfig>,
+    user_id: String,
     action_rx: &mut mpsc::UnboundedReceiver<AsyncAction>,
     result_tx: &mpsc::UnboundedSender<AsyncResult>,
 ) {
-    let history = HistoryLogger::new(history_enabled);
-
     while let Some(action) = action_rx.recv().await {
         let result = match action {
-            AsyncAction::LoadTimeline { limit } => {
+            AsyncAction::LoadTimeline { limit, status } => {
                 let query = TimelineQuery {
                     limit,
+                    status,
                     ..Default::default()
                 };
                 match storage.timeline(&query).await {
                     Ok(entries) => AsyncResult::Timeline(entries),
-                    Err(e) => AsyncResult::Error(format!("Failed to load timeline: {e}")),
+                    Err(e) => failed("Failed to load timeline", e),
                 }
             }
-            AsyncAction::Search { query } => match do_search(&storage, &embedder, &query).await {
-                Ok(results) => AsyncResult::SearchResults { query, results },
-                Err(e) => AsyncResult::Error(format!("Search failed: {e}")),
+            AsyncAction::Search { query } => {
+                match do_search(&storage, &embedder, &config, &query).await {
+                    Ok(results) => AsyncResult::SearchResults { query, results },
+                    Err(e) => failed("Search failed", e),
+                }
+            }
+            AsyncAction::Similar { id } => match do_similar(&storage, &embedder, id).await {
+                Ok((query, results)) => AsyncResult::SearchResults { query, results },
+                Err(e) => failed("Similar search failed", e),
             },
             AsyncAction::LoadDetail { id } => match do_load_detail(&storage, &history, id).await {
-                Ok((memory, relations, trust_val, hist)) => AsyncResult::Detail {
+                Ok((memory, relations, trust_val, hist, sources)) => AsyncResult::Detail {
                     memory: Box::new(memory),
                     relations,
                     trust: trust_val,
                     history: hist,
+                    sources,
                 },
-                Err(e) => AsyncResult::Error(format!("Failed to load detail: {e}")),
+                Err(e) => failed("Failed to load detail", e),
+            },
+            AsyncAction::Unarchive { id } => match do_unarchive(&storage, &history, id).await {
+                Ok(()) => AsyncResult::MemoryUpdated,
+                Err(e) => failed("Failed to restore memory", e),
             },
             AsyncAction::SaveMemory {
                 title,
@@ -214,7 +269,7 @@ async fn worker_loop(
                 let memory = Memory::new(title, content, kind, "tui".to_string());
                 match storage.save_memory(&memory, None).await {
                     Ok(()) => AsyncResult::MemorySaved,
-                    Err(e) => AsyncResult::Error(format!("Failed to save memory: {e}")),
+                    Err(e) => failed("Failed to save memory", e),
                 }
             }
             AsyncAction::UpdateMemory {
@@ -229,9 +284,16 @@ async fn worker_loop(
                     kind: Some(kind),
                     ..Default::default()
                 };
-                match storage.update_memory(id, &input).await {
-                    Ok(_) => AsyncResult::MemoryUpdated,
-                    Err(e) => AsyncResult::Error(format!("Failed to update memory: {e}")),
+                match do_update(&storage, &embedder, &config, id, &input).await {
+                    Ok(()) => AsyncResult::MemoryUpdated,
+                    Err(e) => failed("Failed to update memory", e),
+                }
+            }
+            AsyncAction::LoadInbox => {
+                let options = InboxOptions::from_config(&config);
+                match inbox::collect(&storage, &user_id, &options).await {
+                    Ok(inbox) => AsyncResult::Inbox(inbox),
+                    Err(e) => failed("Failed to load inbox", e),
                 }
             }
         };
@@ -244,66 +306,124 @@ async fn worker_loop(
 async fn do_search(
     storage: &Storage,
     embedder: &EmbeddingService,
+    config: &ShabkaConfig,
     query: &str,
 ) -> Result<Vec<SearchResultEntry>> {
-    let embedding = embedder
-        .embed(query)
-        .await
-        .context("failed to embed search query")?;
-
-    let results = storage
-        .vector_search(&embedding, 50)
+    let request = SearchRequest::parse(query)?
+        .fetch_limit(50)
+        .demote_contradicted(config.retrieval.demote_contradicted);
+    let SearchResults {
+        ranked, conflicts, ..
+    } = retrieval::search(storage, embedder, &request)
         .await
-        .context("vector search failed")?;
-
-    if results.is_empty() {
+        .context("search failed")?;
+    if ranked.is_empty() {
         return Ok(Vec::new());
     }
 
-    let memory_ids: Vec<_> = results.iter().map(|(m, _)| m.id).collect();
-    let relation_counts = storage
-        .count_relations(&memory_ids)
+    let ids: Vec<_> = ranked.iter().map(|r| r.memory.id).collect();
+    let groups = shabka_core::graph::chain_groups(storage, &ids).await;
+    let groups: Vec<_> = groups.into_iter().take(20).collect();
+    // Only count contradictions against results that are on screen.
+    let shown: Vec<uuid::Uuid> = groups
+        .iter()
+        .flat_map(|g| std::iter::once(g.primary).chain(g.members.iter().copied()))
+        .map(|i| ranked[i].memory.id)
+        .collect();
+    let contradicts = |id: &uuid::Uuid| {
+        conflicts.get(id).map_or(0, |others| {
+            others.iter().filter(|o| shown.contains(o)).count()
+        })
+    };
+    Ok(groups
+        .into_iter()
+        .map(|group| SearchResultEntry {
+            score: ranked[group.primary].score,
+            memory: ranked[group.primary].memory.clone(),
+            relation: None,
+            contradicts: contradicts(&ranked[group.primary].memory.id),
+            chain: group
+                .members
+                .iter()
+                .map(|&i| ranked[i].memory.clone())
+                .collect(),
+        })
+        .collect())
+}
+
+/// Nearest neighbours of a memory, labelled for the results header.
+async fn do_similar(
+    storage: &Storage,
+    embedder: &EmbeddingService,
+    id: uuid::Uuid,
+) -> Result<(String, Vec<SearchResultEntry>)> {
+    let memory = storage
+        .get_memory(id)
         .await
-        .unwrap_or_default();
-    let contradiction_counts = storage
-        .count_contradictions(&memory_ids)
+        .context("failed to load memory")?;
+    let embedding = embedder
+        .embed(&memory.embedding_text())
         .await
-        .unwrap_or_default();
-
-    let rel_map: std::collections::HashMap<_, _> = relation_counts.into_iter().collect();
-    let contra_map: std::collections::HashMap<_, _> = contradiction_counts.into_iter().collect();
+        .context("failed to embed memory")?;
+    let similar = shabka_core::graph::find_similar(storage, id, &embedding, 20).await?;
 
-    let candidates: Vec<RankCandidate> = results
+    let results = similar
         .into_iter()
-        .map(|(memory, score)| {
-            let keyword_score = ranking::keyword_score(query, &memory);
-            RankCandidate {
-                vector_score: score,
-                keyword_score,
-                relation_count: rel_map.get(&memory.id).copied().unwrap_or(0),
-                contradiction_count: contra_map.get(&memory.id).copied().unwrap_or(0),
-                memory,
-            }
+        .map(|s| SearchResultEntry {
+            memory: s.memory,
+            score: s.score,
+            relation: s.relation,
+            contradicts: 0,
+            chain: Vec::new(),
         })
         .collect();
+    Ok((format!("similar to: {}", memory.title), results))
+}
 
-    let ranked = ranking::rank(candidates, &RankingWeights::default());
+/// Save an edit, re-embedding the memory when its title or content changed.
+async fn do_update(
+    storage: &Storage,
+    embedder: &EmbeddingService,
+    config: &ShabkaConfig,
+    id: uuid::Uuid,
+    input: &UpdateMemoryInput,
+) -> Result<()> {
+    let previous = storage.get_memory(id).await?;
+    let memory = storage.update_memory(id, input).await?;
+    embedding::refresh_after_edit(
+        storage,
+        embedder,
+        &config.embedding,
+        Some(&previous),
+        &memory,
+    )
+    .await
+    .context("saved, but re-embedding failed")?;
+    Ok(())
+}
 
-    Ok(ranked
-        .into_iter()
-        .take(20)
-        .map(|r| SearchResultEntry {
-            score: r.score,
-            memory: r.memory,
-        })
-        .collect())
+/// Restore an archived memory and record the change in history.
+async fn do_unarchive(storage: &Storage, history: &HistoryLogger, id: uuid::Uuid) -> Result<()> {
+    let memory = storage
+        .get_memory(id)
+        .await
+        .context("failed to load memory")?;
+    let input = decay::restore_input(&memory, None, chrono::Utc::now());
+    let changes = diff_update(&memory, &input);
+    storage.update_memory(id, &input).await?;
+    history.log(
+        &MemoryEvent::new(id, EventAction::Updated, "tui".to_string())
+            .with_title(&memory.title)
+            .with_changes(changes),
+    );
+    Ok(())
 }
 
 async fn do_load_detail(
     storage: &Storage,
     history: &HistoryLogger,
     id: uuid::Uuid,
-) -> Result<(Memory, Vec<MemoryRelation>, f32, Vec<String>)> {
+) -> Result<(Memory, Vec<MemoryRelation>, f32, Vec<String>, Vec<Memory>)> {
     let memory = storage
         .get_memory(id)
         .await
@@ -322,18 +442,25 @@ async fn do_load_detail(
 
     let trust_val = trust::trust_score(&memory, contradiction_count);
 
+    let source_ids = shabka_core::consolidate::source_ids(&memory, &relations);
+    let sources = if source_ids.is_empty() {
+        Vec::new()
+    } else {
+        storage.get_memories(&source_ids).await.unwrap_or_default()
+    };
+
     let hist_events = history.history_for(id);
     let hist_strings: Vec<String> = hist_events
         .iter()
         .map(|e| {
             format!(
                 "{} — {} by {}",
-                e.timestamp.format("%Y-%m-%d %H:%M"),
+                dates::minutes(e.timestamp),
                 e.action,
                 e.actor,
             )
         })
         .collect();
 
-    Ok((memory, relations, trust_val, hist_strings))
+    Ok((memory, relations, trust_val, hist_strings, sources))
 }
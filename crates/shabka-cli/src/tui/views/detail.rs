@@ -5,8 +5,10 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph, Wrap},
     Frame,
 };
-use shabka_core::model::{RelationType, VerificationStatus};
+use shabka_core::code::{highlight_line, TokenKind};
+use shabka_core::model::{CodeSnippet, RelationType, VerificationStatus};
 
+use crate::dates;
 use crate::tui::{app::App, widgets::help_bar::HelpBar};
 
 pub fn render(frame: &mut Frame, app: &App, area: Rect) {
@@ -64,7 +66,7 @@ pub fn render(frame: &mut Frame, app: &App, area: Rect) {
         ),
         Span::styled("│ ", Style::default().fg(Color::DarkGray)),
         Span::styled(
-            memory.created_at.format("%Y-%m-%d %H:%M").to_string(),
+            dates::minutes(memory.created_at),
             Style::default().fg(Color::DarkGray),
         ),
         Span::styled(" │ imp: ", Style::default().fg(Color::DarkGray)),
@@ -128,6 +130,44 @@ fn render_content(frame: &mut Frame, app: &App, memory: &shabka_core::model::Mem
         lines.push(Line::from(line.to_string()));
     }
 
+    // Dispute section
+    if let Some(ref dispute) = memory.dispute {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            "─── Dispute ───",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )));
+        lines.push(Line::from(Span::styled(
+            format!(
+                "  by {} on {}",
+                dispute.disputed_by,
+                dates::date(dispute.disputed_at)
+            ),
+            Style::default().fg(Color::DarkGray),
+        )));
+        if let Some(ref reason) = dispute.reason {
+            lines.push(Line::from(format!("  {reason}")));
+        }
+        if let Some(evidence_id) = dispute.evidence_id {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    "  counter-evidence → ",
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(
+                    evidence_id.to_string()[..8].to_string(),
+                    Style::default().fg(Color::Cyan),
+                ),
+            ]));
+        }
+    }
+
+    // Code section
+    if let Some(ref code) = memory.code {
+        lines.push(Line::from(""));
+        lines.extend(code_lines(code));
+    }
+
     // Relations section
     if !app.detail_relations.is_empty() {
         lines.push(Line::from(""));
@@ -176,6 +216,96 @@ fn render_content(frame: &mut Frame, app: &App, memory: &shabka_core::model::Mem
         )));
     }
 
+    // Sources section (consolidated summaries)
+    if !app.detail_sources.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!(
+                "─── Consolidated from ({}) ─── {}",
+                app.detail_sources.len(),
+                if app.detail_sources_expanded {
+                    "o: fold"
+                } else {
+                    "o: unfold"
+                }
+            ),
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )));
+        lines.push(Line::from(""));
+        for source in &app.detail_sources {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  {} ", &source.id.to_string()[..8]),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::styled(
+                    source.title.clone(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    format!(" {} · {}", source.kind, dates::date(source.created_at)),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]));
+            if app.detail_sources_expanded {
+                for line in source.content.lines() {
+                    lines.push(Line::from(format!("    {line}")));
+                }
+                lines.push(Line::from(""));
+            }
+        }
+    }
+
+    // Corroboration section
+    if !memory.assertions.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!("─── Asserted by ({}) ───", memory.assertions.len()),
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )));
+        lines.push(Line::from(""));
+        for a in &memory.assertions {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  {}", a.asserted_by),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::styled(
+                    format!(" via {} · {}", a.channel, dates::date(a.asserted_at)),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]));
+        }
+    }
+
+    // Endorsements section
+    if !memory.endorsements.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!("─── Endorsed by ({}) ───", memory.endorsements.len()),
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )));
+        lines.push(Line::from(""));
+        for e in &memory.endorsements {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  {}", e.endorsed_by),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::styled(
+                    format!(" · {}", dates::date(e.endorsed_at)),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]));
+        }
+    }
+
     // History section
     if !app.detail_history.is_empty() {
         lines.push(Line::from(""));
@@ -210,13 +340,16 @@ fn render_content(frame: &mut Frame, app: &App, memory: &shabka_core::model::Mem
     if let Some(ref proj) = memory.project_id {
         lines.push(Line::from(format!("  Project: {proj}")));
     }
+    if let Some(ref owner) = memory.owner {
+        lines.push(Line::from(format!("  Owner: {owner}")));
+    }
     lines.push(Line::from(format!(
         "  Updated: {}",
-        memory.updated_at.format("%Y-%m-%d %H:%M")
+        dates::minutes(memory.updated_at)
     )));
     lines.push(Line::from(format!(
         "  Accessed: {}",
-        memory.accessed_at.format("%Y-%m-%d %H:%M")
+        dates::minutes(memory.accessed_at)
     )));
 
     let paragraph = Paragraph::new(lines)
@@ -232,6 +365,58 @@ fn render_content(frame: &mut Frame, app: &App, memory: &shabka_core::model::Mem
     frame.render_widget(paragraph, area);
 }
 
+fn code_lines(code: &CodeSnippet) -> Vec<Line<'_>> {
+    let mut header = String::from("─── Code");
+    if let Some(ref language) = code.language {
+        header.push_str(&format!(" ({language})"));
+    }
+    header.push_str(" ───");
+    let mut lines = vec![Line::from(Span::styled(
+        header,
+        Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD),
+    ))];
+    let location = match (&code.file, &code.symbol) {
+        (Some(file), Some(symbol)) => Some(format!("  {file} › {symbol}")),
+        (Some(file), None) => Some(format!("  {file}")),
+        (None, Some(symbol)) => Some(format!("  {symbol}")),
+        (None, None) => None,
+    };
+    if let Some(location) = location {
+        lines.push(Line::from(Span::styled(
+            location,
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    lines.push(Line::from(""));
+
+    for line in code.snippet.lines() {
+        let mut spans = vec![Span::raw("  ")];
+        spans.extend(
+            highlight_line(line, code.language.as_deref())
+                .into_iter()
+                .map(|(kind, text)| Span::styled(text, token_style(kind))),
+        );
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+fn token_style(kind: TokenKind) -> Style {
+    match kind {
+        TokenKind::Plain => Style::default(),
+        TokenKind::Keyword => Style::default()
+            .fg(Color::Magenta)
+            .add_modifier(Modifier::BOLD),
+        TokenKind::String => Style::default().fg(Color::Green),
+        TokenKind::Comment => Style::default()
+            .fg(Color::DarkGray)
+            .add_modifier(Modifier::ITALIC),
+        TokenKind::Number => Style::default().fg(Color::Yellow),
+    }
+}
+
 fn relation_style(rel_type: &RelationType) -> (&str, Color) {
     match rel_type {
         RelationType::Fixes => ("🔧", Color::Green),
@@ -7,6 +7,8 @@ use ratatui::{
 };
 use shabka_core::model::VerificationStatus;
 
+use crate::dates;
+use crate::i18n::t;
 use crate::tui::{
     app::{App, InputMode},
     widgets::{filter_bar::FilterBar, help_bar::HelpBar, search_input::SearchInput},
@@ -56,7 +58,7 @@ pub fn render(frame: &mut Frame, app: &App, area: Rect) {
 fn render_table(frame: &mut Frame, app: &App, area: Rect) {
     if app.loading {
         let loading = Line::from(vec![Span::styled(
-            "  Loading...",
+            format!("  {}", t!("tui-loading")),
             Style::default()
                 .fg(Color::Yellow)
                 .add_modifier(Modifier::BOLD),
@@ -66,12 +68,12 @@ fn render_table(frame: &mut Frame, app: &App, area: Rect) {
     }
 
     let header = Row::new(vec![
-        Cell::from("ID"),
-        Cell::from("Kind"),
-        Cell::from("Imp%"),
-        Cell::from("Status"),
-        Cell::from("Title"),
-        Cell::from("Created"),
+        Cell::from(t!("column-id")),
+        Cell::from(t!("column-kind")),
+        Cell::from(format!("{}%", t!("column-importance"))),
+        Cell::from(t!("column-status")),
+        Cell::from(t!("column-title")),
+        Cell::from(t!("column-created")),
     ])
     .style(
         Style::default()
@@ -86,13 +88,29 @@ fn render_table(frame: &mut Frame, app: &App, area: Rect) {
             .iter()
             .map(|result| {
                 let m = &result.memory;
+                let mut title = match result.relation {
+                    Some(relation) => format!("{} [{relation}]", m.title),
+                    None => m.title.clone(),
+                };
+                if result.contradicts > 0 {
+                    title = format!("⚠ {title} [contradicts {}]", result.contradicts);
+                }
+                if !result.chain.is_empty() {
+                    let kinds: Vec<String> =
+                        result.chain.iter().map(|c| c.kind.to_string()).collect();
+                    title.push_str(&format!(
+                        "  (+{} in chain: {})",
+                        result.chain.len(),
+                        kinds.join(", ")
+                    ));
+                }
                 make_memory_row(
                     m.id.to_string()[..8].to_string(),
                     m.kind.to_string(),
                     m.importance,
                     &m.verification,
-                    &m.title,
-                    m.created_at.format("%Y-%m-%d").to_string(),
+                    &title,
+                    dates::when(m.created_at),
                     Some(result.score),
                 )
             })
@@ -100,7 +118,7 @@ fn render_table(frame: &mut Frame, app: &App, area: Rect) {
     } else if app.filtered_entries.is_empty() {
         // Empty state
         vec![Row::new(vec![Cell::from(Span::styled(
-            "  No memories found. Press / to search or r to refresh.",
+            format!("  {}", t!("tui-empty")),
             Style::default().fg(Color::DarkGray),
         ))])]
     } else {
@@ -114,7 +132,7 @@ fn render_table(frame: &mut Frame, app: &App, area: Rect) {
                     entry.importance,
                     &entry.verification,
                     &entry.title,
-                    entry.created_at.format("%Y-%m-%d").to_string(),
+                    dates::when(entry.created_at),
                     None,
                 )
             })
@@ -131,9 +149,24 @@ fn render_table(frame: &mut Frame, app: &App, area: Rect) {
     ];
 
     let title = if let Some(ref q) = app.active_query {
-        format!(" Results for \"{}\" ({}) ", q, app.search_results.len())
+        format!(
+            " {} ",
+            t!(
+                "tui-results",
+                query = q.as_str(),
+                count = app.search_results.len()
+            )
+        )
+    } else if app.show_archived {
+        format!(
+            " {} ",
+            t!("tui-archived", count = app.filtered_entries.len())
+        )
     } else {
-        format!(" Memories ({}) ", app.filtered_entries.len())
+        format!(
+            " {} ",
+            t!("tui-memories", count = app.filtered_entries.len())
+        )
     };
 
     let table = Table::new(rows, widths)
@@ -6,6 +6,7 @@ use ratatui::{
     Frame,
 };
 
+use crate::i18n::t;
 use crate::tui::{app::App, widgets::help_bar::HelpBar};
 
 pub fn render(frame: &mut Frame, app: &App, area: Rect, storage_info: &str, provider_info: &str) {
@@ -20,15 +21,15 @@ pub fn render(frame: &mut Frame, app: &App, area: Rect, storage_info: &str, prov
     let total: usize = app.kind_counts.iter().map(|(_, c)| c).sum();
     let info_lines = vec![
         Line::from(vec![
-            Span::styled("  Storage:  ", Style::default().fg(Color::DarkGray)),
+            label(t!("tui-storage")),
             Span::styled(storage_info.to_string(), Style::default().fg(Color::Cyan)),
         ]),
         Line::from(vec![
-            Span::styled("  Provider: ", Style::default().fg(Color::DarkGray)),
+            label(t!("tui-provider")),
             Span::styled(provider_info.to_string(), Style::default().fg(Color::Cyan)),
         ]),
         Line::from(vec![
-            Span::styled("  Memories: ", Style::default().fg(Color::DarkGray)),
+            label(t!("tui-memories-label")),
             Span::styled(
                 total.to_string(),
                 Style::default()
@@ -37,19 +38,20 @@ pub fn render(frame: &mut Frame, app: &App, area: Rect, storage_info: &str, prov
             ),
         ]),
         Line::from(vec![
-            Span::styled("  Kinds:    ", Style::default().fg(Color::DarkGray)),
+            label(t!("tui-kinds")),
             Span::styled(
                 app.kind_counts.len().to_string(),
                 Style::default().fg(Color::Green),
             ),
         ]),
+        Line::from(vec![label(t!("tui-inbox")), inbox_span(app)]),
     ];
 
     let info = Paragraph::new(info_lines).block(
         Block::default()
             .borders(Borders::ALL)
             .border_style(Style::default().fg(Color::DarkGray))
-            .title(" System Status "),
+            .title(format!(" {} ", t!("tui-status"))),
     );
     frame.render_widget(info, layout[0]);
 
@@ -60,7 +62,7 @@ pub fn render(frame: &mut Frame, app: &App, area: Rect, storage_info: &str, prov
 
     let bar_lines: Vec<Line> = if app.kind_counts.is_empty() {
         vec![Line::from(Span::styled(
-            "  No memories yet.",
+            format!("  {}", t!("tui-no-memories-yet")),
             Style::default().fg(Color::DarkGray),
         ))]
     } else {
@@ -96,7 +98,7 @@ pub fn render(frame: &mut Frame, app: &App, area: Rect, storage_info: &str, prov
         Block::default()
             .borders(Borders::ALL)
             .border_style(Style::default().fg(Color::DarkGray))
-            .title(" Kind Breakdown "),
+            .title(format!(" {} ", t!("tui-kind-breakdown"))),
     );
     frame.render_widget(bars, layout[1]);
 
@@ -109,3 +111,34 @@ pub fn render(frame: &mut Frame, app: &App, area: Rect, storage_info: &str, prov
         layout[2],
     );
 }
+
+/// A label in the info block, padded so the values line up.
+fn label(text: String) -> Span<'static> {
+    Span::styled(
+        format!("  {:<10}", format!("{text}:")),
+        Style::default().fg(Color::DarkGray),
+    )
+}
+
+/// Inbox total with a per-kind breakdown, e.g. `3 (review 2, duplicate 1)`.
+fn inbox_span(app: &App) -> Span<'static> {
+    match &app.inbox {
+        None => Span::styled("loading…", Style::default().fg(Color::DarkGray)),
+        Some(inbox) if inbox.total == 0 => {
+            Span::styled("nothing waiting", Style::default().fg(Color::Green))
+        }
+        Some(inbox) => {
+            let kinds: Vec<String> = inbox
+                .counts
+                .iter()
+                .map(|(kind, count)| format!("{kind} {count}"))
+                .collect();
+            Span::styled(
+                format!("{} ({}) — run shabka inbox", inbox.total, kinds.join(", ")),
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )
+        }
+    }
+}
@@ -6,6 +6,7 @@ use ratatui::{
     widgets::Widget,
 };
 
+use crate::i18n::t;
 use crate::tui::app::{InputMode, Screen};
 
 /// Bottom help bar showing context-sensitive key bindings.
@@ -19,67 +20,58 @@ impl Widget for HelpBar<'_> {
         let style = Style::default().fg(Color::DarkGray);
         let key_style = Style::default().fg(Color::Cyan);
 
-        let spans: Vec<Span> = match (self.screen, self.input_mode) {
+        let hints: Vec<(&str, String)> = match (self.screen, self.input_mode) {
             (Screen::List, InputMode::Normal) => vec![
-                Span::styled("j/k", key_style),
-                Span::styled(" navigate  ", style),
-                Span::styled("Enter", key_style),
-                Span::styled(" open  ", style),
-                Span::styled("/", key_style),
-                Span::styled(" search  ", style),
-                Span::styled("f", key_style),
-                Span::styled(" filter  ", style),
-                Span::styled("n", key_style),
-                Span::styled(" new  ", style),
-                Span::styled("Tab", key_style),
-                Span::styled(" status  ", style),
-                Span::styled("r", key_style),
-                Span::styled(" refresh  ", style),
-                Span::styled("q", key_style),
-                Span::styled(" quit", style),
-            ],
-            (Screen::List, InputMode::Search) => vec![
-                Span::styled("Enter", key_style),
-                Span::styled(" search  ", style),
-                Span::styled("Esc", key_style),
-                Span::styled(" cancel", style),
+                ("j/k", t!("key-navigate")),
+                ("Enter", t!("key-open")),
+                ("/", t!("key-search")),
+                ("s", t!("key-similar")),
+                ("f", t!("key-filter")),
+                ("n", t!("key-new")),
+                ("a", t!("key-archived")),
+                ("Tab", t!("key-status")),
+                ("r", t!("key-refresh")),
+                ("q", t!("key-quit")),
             ],
+            (Screen::List, InputMode::Search) => {
+                vec![("Enter", t!("key-search")), ("Esc", t!("key-cancel"))]
+            }
             (Screen::List, InputMode::Filter) => vec![
-                Span::styled("←/→", key_style),
-                Span::styled(" cycle kind  ", style),
-                Span::styled("Enter/Esc", key_style),
-                Span::styled(" confirm", style),
+                ("←/→", t!("key-cycle-kind")),
+                ("Enter/Esc", t!("key-confirm")),
             ],
             (Screen::Detail, _) => vec![
-                Span::styled("j/k", key_style),
-                Span::styled(" scroll  ", style),
-                Span::styled("PgUp/PgDn", key_style),
-                Span::styled(" page  ", style),
-                Span::styled("e", key_style),
-                Span::styled(" edit  ", style),
-                Span::styled("Esc", key_style),
-                Span::styled(" back  ", style),
-                Span::styled("q", key_style),
-                Span::styled(" quit", style),
+                ("j/k", t!("key-scroll")),
+                ("PgUp/PgDn", t!("key-page")),
+                ("e", t!("key-edit")),
+                ("s", t!("key-similar")),
+                ("o", t!("key-sources")),
+                ("u", t!("key-unarchive")),
+                ("Esc", t!("key-back")),
+                ("q", t!("key-quit")),
             ],
             (Screen::Create, _) => vec![
-                Span::styled("Tab", key_style),
-                Span::styled(" next field  ", style),
-                Span::styled("Shift+Tab", key_style),
-                Span::styled(" prev field  ", style),
-                Span::styled("Ctrl+S", key_style),
-                Span::styled(" save  ", style),
-                Span::styled("Esc", key_style),
-                Span::styled(" cancel", style),
-            ],
-            (Screen::Status, _) => vec![
-                Span::styled("Tab/Esc", key_style),
-                Span::styled(" back to list  ", style),
-                Span::styled("q", key_style),
-                Span::styled(" quit", style),
+                ("Tab", t!("key-next-field")),
+                ("Shift+Tab", t!("key-prev-field")),
+                ("Ctrl+S", t!("key-save")),
+                ("Esc", t!("key-cancel")),
             ],
+            (Screen::Status, _) => vec![("Tab/Esc", t!("key-back-to-list")), ("q", t!("key-quit"))],
         };
 
+        let last = hints.len().saturating_sub(1);
+        let spans: Vec<Span> = hints
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, (key, action))| {
+                let gap = if i == last { "" } else { "  " };
+                [
+                    Span::styled(key, key_style),
+                    Span::styled(format!(" {action}{gap}"), style),
+                ]
+            })
+            .collect();
+
         let line = Line::from(spans);
         buf.set_line(area.x, area.y, &line, area.width);
     }
@@ -27,7 +27,7 @@ impl Widget for SearchInput<'_> {
             .title(if self.focused {
                 " Search (Enter to submit, Esc to cancel) "
             } else {
-                " Search (press /) "
+                " Search (press /) — kind:error tag:auth after:2025-01-01 "
             });
 
         let inner = block.inner(area);
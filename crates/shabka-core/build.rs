@@ -1,4 +1,8 @@
 fn main() {
+    // The SQLite extensions are only linked into the storage layer.
+    if std::env::var_os("CARGO_FEATURE_RUNTIME").is_none() {
+        return;
+    }
     let sqlean = "vendor/sqlean/src";
 
     // libsqlite3-sys exports its include path via cargo:include metadata.
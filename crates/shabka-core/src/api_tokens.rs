@@ -0,0 +1,314 @@
+//! Scoped API tokens for the web dashboard and its MCP endpoint.
+//!
+//! `shabka token create` mints a secret, prints it once and keeps only its
+//! SHA-256 hash in the SQLite `api_tokens` table. With `[web] require_token`
+//! set, every request to `shabka-web` must present an active token; a
+//! [`TokenScope::Read`] token may only read, a [`TokenScope::Write`] token
+//! may also change memories and use `/mcp`. History events logged while
+//! serving a request carry the token's name (see
+//! [`crate::history::with_token`]).
+//!
+//! In multi-tenant mode (see [`crate::tenants`]) a token also names the
+//! tenant whose store it opens.
+
+use chrono::{DateTime, Duration, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::error::{Result, ShabkaError};
+use crate::storage::Storage;
+
+/// Prefix of every token secret, so leaked ones are easy to grep for.
+pub const SECRET_PREFIX: &str = "shabka_";
+
+/// `last_used_at` is only rewritten when it is older than this, so a busy
+/// client doesn't turn every read into a database write.
+const TOUCH_INTERVAL_SECS: i64 = 60;
+
+/// What a token may do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenScope {
+    Read,
+    Write,
+}
+
+impl TokenScope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+        }
+    }
+}
+
+impl std::fmt::Display for TokenScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for TokenScope {
+    type Err = ShabkaError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "read" => Ok(Self::Read),
+            "write" => Ok(Self::Write),
+            _ => Err(ShabkaError::InvalidInput(format!(
+                "unknown token scope '{s}' (read, write)"
+            ))),
+        }
+    }
+}
+
+/// A stored API token. The secret itself is never stored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub name: String,
+    pub scope: TokenScope,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// Tenant whose store the token opens; `None` for the default store.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// SHA-256 of the secret, hex encoded.
+    #[serde(skip)]
+    pub hash: String,
+}
+
+impl ApiToken {
+    /// Neither revoked nor expired at `now`.
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && !matches!(self.expires_at, Some(at) if at <= now)
+    }
+
+    /// Whether the token may make a request that changes data.
+    pub fn can_write(&self) -> bool {
+        self.scope == TokenScope::Write
+    }
+}
+
+/// A freshly created token together with its secret, which is shown once.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct NewToken {
+    #[serde(flatten)]
+    pub token: ApiToken,
+    pub secret: String,
+}
+
+/// SHA-256 of `secret`, hex encoded.
+pub fn hash_secret(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
+
+/// Parse an expiry: a span from `now` such as `12h`, `30d` or `2w`, or an
+/// absolute `YYYY-MM-DD` / RFC 3339 date.
+pub fn parse_expiry(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+    let at = match crate::history::parse_span(value) {
+        Some(span) => now + span?,
+        None => crate::query::parse_date("expires", value)?,
+    };
+    if at <= now {
+        return Err(ShabkaError::InvalidInput(format!(
+            "expiry '{value}' is not in the future"
+        )));
+    }
+    Ok(at)
+}
+
+/// Create a token called `name`, for `tenant` if given. Names must be
+/// unique among active tokens, since history records the name rather than
+/// the ID.
+pub async fn create(
+    storage: &Storage,
+    name: &str,
+    scope: TokenScope,
+    expires_at: Option<DateTime<Utc>>,
+    tenant: Option<&str>,
+) -> Result<NewToken> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ShabkaError::InvalidInput(
+            "token name must not be empty".into(),
+        ));
+    }
+    if let Some(tenant) = tenant {
+        crate::tenants::validate_name(tenant)?;
+    }
+    let now = Utc::now();
+    if list(storage)
+        .await?
+        .iter()
+        .any(|t| t.is_active(now) && t.name == name)
+    {
+        return Err(ShabkaError::InvalidInput(format!(
+            "an active token named '{name}' already exists"
+        )));
+    }
+
+    let secret = format!(
+        "{SECRET_PREFIX}{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    );
+    let token = ApiToken {
+        id: Uuid::now_v7(),
+        name: name.to_string(),
+        scope,
+        created_at: now,
+        expires_at,
+        last_used_at: None,
+        revoked_at: None,
+        tenant: tenant.map(str::to_string),
+        hash: hash_secret(&secret),
+    };
+    storage.save_api_token(&token).await?;
+    Ok(NewToken { token, secret })
+}
+
+/// Every token, newest first, including revoked and expired ones.
+pub async fn list(storage: &Storage) -> Result<Vec<ApiToken>> {
+    storage.api_tokens().await
+}
+
+/// Revoke the active token whose name or ID (or ID prefix) is `name_or_id`.
+pub async fn revoke(storage: &Storage, name_or_id: &str) -> Result<ApiToken> {
+    let now = Utc::now();
+    let needle = name_or_id.trim();
+    let mut matches: Vec<ApiToken> = list(storage)
+        .await?
+        .into_iter()
+        .filter(|t| t.is_active(now))
+        .filter(|t| t.name == needle || t.id.to_string().starts_with(needle))
+        .collect();
+    let mut token = match matches.len() {
+        0 => {
+            return Err(ShabkaError::NotFound(format!(
+                "no active token matches '{needle}'"
+            )))
+        }
+        1 => matches.remove(0),
+        n => {
+            return Err(ShabkaError::InvalidInput(format!(
+                "'{needle}' matches {n} tokens; use a longer ID"
+            )))
+        }
+    };
+    token.revoked_at = Some(now);
+    storage.save_api_token(&token).await?;
+    Ok(token)
+}
+
+/// The active token whose secret is `secret`, if any. Records the use.
+pub async fn authenticate(
+    storage: &Storage,
+    secret: &str,
+    now: DateTime<Utc>,
+) -> Result<Option<ApiToken>> {
+    let hash = hash_secret(secret.trim());
+    let Some(mut token) = list(storage)
+        .await?
+        .into_iter()
+        .find(|t| t.hash == hash && t.is_active(now))
+    else {
+        return Ok(None);
+    };
+    let stale = match token.last_used_at {
+        Some(at) => now - at >= Duration::seconds(TOUCH_INTERVAL_SECS),
+        None => true,
+    };
+    if stale {
+        token.last_used_at = Some(now);
+        storage.save_api_token(&token).await?;
+    }
+    Ok(Some(token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::SqliteStorage;
+
+    fn test_storage() -> Storage {
+        Storage::Sqlite(SqliteStorage::open_in_memory().unwrap())
+    }
+
+    #[test]
+    fn test_parse_expiry() {
+        let now = Utc::now();
+        assert_eq!(parse_expiry("30d", now).unwrap(), now + Duration::days(30));
+        assert_eq!(parse_expiry("12h", now).unwrap(), now + Duration::hours(12));
+        assert!(parse_expiry("2999-01-01", now).is_ok());
+        assert!(parse_expiry("2000-01-01", now).is_err());
+        assert!(parse_expiry("3y", now).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_create_authenticate_revoke() {
+        let storage = test_storage();
+        let created = create(&storage, "ci", TokenScope::Read, None, None)
+            .await
+            .unwrap();
+        assert!(created.secret.starts_with(SECRET_PREFIX));
+        assert_ne!(created.token.hash, created.secret);
+        assert!(create(&storage, "ci", TokenScope::Write, None, None)
+            .await
+            .is_err());
+
+        let now = Utc::now();
+        let found = authenticate(&storage, &created.secret, now)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.name, "ci");
+        assert!(!found.can_write());
+        assert!(list(&storage).await.unwrap()[0].last_used_at.is_some());
+        assert!(authenticate(&storage, "shabka_wrong", now)
+            .await
+            .unwrap()
+            .is_none());
+
+        revoke(&storage, "ci").await.unwrap();
+        assert!(authenticate(&storage, &created.secret, now)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(revoke(&storage, "ci").await.is_err());
+        // The name is free again once the old token is revoked.
+        create(&storage, "ci", TokenScope::Write, None, None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_rejected() {
+        let storage = test_storage();
+        let now = Utc::now();
+        let created = create(
+            &storage,
+            "short",
+            TokenScope::Write,
+            Some(now + Duration::hours(1)),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(authenticate(&storage, &created.secret, now)
+            .await
+            .unwrap()
+            .is_some());
+        assert!(
+            authenticate(&storage, &created.secret, now + Duration::hours(2))
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
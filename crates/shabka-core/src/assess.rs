@@ -1,7 +1,60 @@
-use chrono::Utc;
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Datelike, Utc};
 use uuid::Uuid;
 
-use crate::model::Memory;
+use crate::codebase::{Codebase, MissingReferents};
+use crate::decay::{self, RetentionConfig, RetentionLimit};
+use crate::maintenance::MaintenanceRun;
+use crate::model::{Memory, MemoryKind, VerificationStatus};
+use crate::similarity;
+
+/// Tag marking a memory whose kind was kept after a suggestion to change
+/// it, so the suggestion isn't made again.
+pub const KIND_KEPT_TAG: &str = "kind-kept";
+
+/// Phrases suggesting what an auto-captured observation really records.
+const KIND_KEYWORDS: &[(MemoryKind, &[&str])] = &[
+    (
+        MemoryKind::Decision,
+        &[
+            "decided",
+            "decision",
+            "chose ",
+            "going with",
+            "opted for",
+            "settled on",
+            "switched to",
+            "instead of",
+        ],
+    ),
+    (
+        MemoryKind::Fix,
+        &["fixed", "the fix", "resolved by", "workaround", "fixes "],
+    ),
+    (
+        MemoryKind::Error,
+        &[
+            "error:",
+            "panicked",
+            "exception",
+            "stack trace",
+            "failed with",
+        ],
+    ),
+    (
+        MemoryKind::Lesson,
+        &["lesson", "learned", "turns out", "gotcha", "next time"],
+    ),
+    (
+        MemoryKind::Todo,
+        &["todo", "need to", "follow up", "remember to"],
+    ),
+    (
+        MemoryKind::Preference,
+        &["prefer", "always use", "never use"],
+    ),
+];
 
 /// A quality issue found in a memory.
 #[derive(Debug, Clone)]
@@ -28,6 +81,22 @@ pub enum QualityIssue {
     LowTrust {
         trust_score: f32,
     },
+    /// Auto-captured with low confidence and not verified since.
+    LowConfidence {
+        confidence: f32,
+    },
+    /// Exceeds its kind's `[retention]` policy; the next prune archives it.
+    OverRetention {
+        limit: RetentionLimit,
+    },
+    /// References files or symbols that no longer exist in the codebase.
+    MissingReferents {
+        missing: MissingReferents,
+    },
+    /// Auto-captured with a kind its content doesn't match.
+    WrongKind {
+        suggested: MemoryKind,
+    },
 }
 
 impl QualityIssue {
@@ -42,6 +111,10 @@ impl QualityIssue {
             QualityIssue::Orphaned => 5.0,
             QualityIssue::PossibleDuplicate { .. } => 15.0,
             QualityIssue::LowTrust { .. } => 10.0,
+            QualityIssue::LowConfidence { .. } => 10.0,
+            QualityIssue::OverRetention { .. } => 5.0,
+            QualityIssue::MissingReferents { .. } => 15.0,
+            QualityIssue::WrongKind { .. } => 5.0,
         }
     }
 
@@ -56,6 +129,10 @@ impl QualityIssue {
             QualityIssue::Orphaned => "orphaned",
             QualityIssue::PossibleDuplicate { .. } => "possible duplicate",
             QualityIssue::LowTrust { .. } => "low trust",
+            QualityIssue::LowConfidence { .. } => "low confidence",
+            QualityIssue::OverRetention { .. } => "over retention",
+            QualityIssue::MissingReferents { .. } => "missing referents",
+            QualityIssue::WrongKind { .. } => "wrong kind",
         }
     }
 }
@@ -74,6 +151,7 @@ pub struct AssessConfig {
     pub min_content_length: usize,
     pub stale_days: u64,
     pub min_importance: f32,
+    pub min_confidence: f32,
 }
 
 impl Default for AssessConfig {
@@ -89,6 +167,7 @@ impl Default for AssessConfig {
             min_content_length: 50,
             stale_days: 90,
             min_importance: 0.3,
+            min_confidence: 0.5,
         }
     }
 }
@@ -150,6 +229,14 @@ pub fn analyze_memory(
         issues.push(QualityIssue::LowTrust { trust_score: trust });
     }
 
+    if let Some(issue) = low_confidence(memory, config) {
+        issues.push(issue);
+    }
+
+    if let Some(suggested) = suggest_kind(memory) {
+        issues.push(QualityIssue::WrongKind { suggested });
+    }
+
     issues
 }
 
@@ -190,11 +277,210 @@ pub fn check_new_memory(memory: &Memory, config: &AssessConfig) -> Vec<QualityIs
         });
     }
 
+    if let Some(issue) = low_confidence(memory, config) {
+        issues.push(issue);
+    }
+
     issues
 }
 
+/// A `LowConfidence` issue when the memory needs verification before it
+/// is related or packed (see [`Memory::needs_confirmation`]).
+fn low_confidence(memory: &Memory, config: &AssessConfig) -> Option<QualityIssue> {
+    memory
+        .needs_confirmation(config.min_confidence)
+        .then(|| QualityIssue::LowConfidence {
+            confidence: memory.confidence.unwrap_or_default(),
+        })
+}
+
+/// Whether `memory`'s kind is still a hook's guess that nobody has
+/// confirmed: auto-captured, not classified by the LLM, not kept after an
+/// earlier suggestion and not verified.
+pub fn kind_checkable(memory: &Memory) -> bool {
+    crate::backfill::needs_kind(memory) && memory.verification != VerificationStatus::Verified
+}
+
+/// The kind an auto-captured observation's wording suggests it really is,
+/// from keywords in its title (counted twice) and content. `None` unless
+/// one kind clearly leads with at least two hits.
+pub fn suggest_kind(memory: &Memory) -> Option<MemoryKind> {
+    if memory.kind != MemoryKind::Observation || !kind_checkable(memory) {
+        return None;
+    }
+    let title = memory.title.to_lowercase();
+    let content = memory.content.to_lowercase();
+    let mut scores: Vec<(MemoryKind, usize)> = KIND_KEYWORDS
+        .iter()
+        .map(|(kind, phrases)| {
+            let hits = |text: &str| phrases.iter().filter(|p| text.contains(**p)).count();
+            (*kind, 2 * hits(&title) + hits(&content))
+        })
+        .collect();
+    scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    match scores.as_slice() {
+        [(kind, best), (_, next), ..] if *best >= 2 && best > next => Some(*kind),
+        _ => None,
+    }
+}
+
+/// The kind a result's `WrongKind` issue suggests.
+pub fn suggested_kind(result: &AssessmentResult) -> Option<MemoryKind> {
+    result.issues.iter().find_map(|issue| match issue {
+        QualityIssue::WrongKind { suggested } => Some(*suggested),
+        _ => None,
+    })
+}
+
+/// Replace the keyword-based kind suggestions for the memories in `kinds`
+/// with the kinds given there, e.g. by the LLM: a memory whose given kind
+/// is its current one loses its `WrongKind` issue. Merges into `results`
+/// like `flag_retention()`.
+pub fn flag_kinds(
+    results: &mut Vec<AssessmentResult>,
+    memories: &[Memory],
+    kinds: &HashMap<Uuid, MemoryKind>,
+) {
+    for memory in memories {
+        let Some(&kind) = kinds.get(&memory.id) else {
+            continue;
+        };
+        if let Some(result) = results.iter_mut().find(|r| r.memory_id == memory.id) {
+            result
+                .issues
+                .retain(|i| !matches!(i, QualityIssue::WrongKind { .. }));
+        }
+        if kind == memory.kind {
+            continue;
+        }
+        let issue = QualityIssue::WrongKind { suggested: kind };
+        match results.iter_mut().find(|r| r.memory_id == memory.id) {
+            Some(result) => result.issues.push(issue),
+            None => results.push(AssessmentResult {
+                memory_id: memory.id,
+                title: memory.title.clone(),
+                issues: vec![issue],
+            }),
+        }
+    }
+    results.retain(|r| !r.issues.is_empty());
+}
+
+/// Flag memories that exceed their kind's retention policy.
+///
+/// Count limits depend on the whole set, so this runs over all memories after
+/// the per-memory `analyze_memory()` pass and merges into `results`.
+pub fn flag_retention(
+    results: &mut Vec<AssessmentResult>,
+    memories: &[Memory],
+    retention: &RetentionConfig,
+) {
+    let violations = decay::retention_violations(memories, retention, Utc::now());
+    for memory in memories {
+        let Some(&limit) = violations.get(&memory.id) else {
+            continue;
+        };
+        let issue = QualityIssue::OverRetention { limit };
+        match results.iter_mut().find(|r| r.memory_id == memory.id) {
+            Some(result) => result.issues.push(issue),
+            None => results.push(AssessmentResult {
+                memory_id: memory.id,
+                title: memory.title.clone(),
+                issues: vec![issue],
+            }),
+        }
+    }
+}
+
+/// Flag memories that reference paths or symbols missing from `codebase`.
+///
+/// Memories tagged with a different project are skipped: their referents
+/// live in some other tree. Merges into `results` like `flag_retention()`.
+pub fn flag_codebase(
+    results: &mut Vec<AssessmentResult>,
+    memories: &[Memory],
+    codebase: &Codebase,
+    project: Option<&str>,
+) {
+    for memory in memories {
+        let other_project = match (memory.project_id.as_deref(), project) {
+            (Some(own), Some(project)) => own != project,
+            _ => false,
+        };
+        if other_project {
+            continue;
+        }
+        let missing = codebase.missing_referents(memory);
+        if missing.is_empty() {
+            continue;
+        }
+        let issue = QualityIssue::MissingReferents { missing };
+        match results.iter_mut().find(|r| r.memory_id == memory.id) {
+            Some(result) => result.issues.push(issue),
+            None => results.push(AssessmentResult {
+                memory_id: memory.id,
+                title: memory.title.clone(),
+                issues: vec![issue],
+            }),
+        }
+    }
+}
+
+/// Flag memories whose embeddings are at least `threshold` similar (see
+/// [`similarity`](crate::similarity) for how pairs are scored). Each memory
+/// keeps its `per_memory` closest matches; memories without a vector in
+/// `embeddings` are skipped. Merges into `results` like `flag_retention()`
+/// and returns the number of issues added.
+pub fn flag_duplicates(
+    results: &mut Vec<AssessmentResult>,
+    memories: &[Memory],
+    embeddings: &HashMap<Uuid, Vec<f32>>,
+    threshold: f32,
+    per_memory: usize,
+) -> usize {
+    let (embedded, vectors): (Vec<&Memory>, Vec<&[f32]>) = memories
+        .iter()
+        .filter_map(|m| embeddings.get(&m.id).map(|v| (m, v.as_slice())))
+        .unzip();
+
+    let mut added = 0;
+    for (memory, neighbours) in embedded.iter().zip(similarity::nearest_neighbours(
+        &vectors, threshold, per_memory,
+    )) {
+        for (j, similarity) in neighbours {
+            let other = embedded[j];
+            let issue = QualityIssue::PossibleDuplicate {
+                other_id: other.id,
+                other_title: other.title.clone(),
+                similarity,
+            };
+            match results.iter_mut().find(|r| r.memory_id == memory.id) {
+                Some(result) => {
+                    let known = result.issues.iter().any(|issue| {
+                        matches!(issue, QualityIssue::PossibleDuplicate { other_id, .. } if *other_id == other.id)
+                    });
+                    if known {
+                        continue;
+                    }
+                    result.issues.push(issue);
+                }
+                None => results.push(AssessmentResult {
+                    memory_id: memory.id,
+                    title: memory.title.clone(),
+                    issues: vec![issue],
+                }),
+            }
+            added += 1;
+        }
+    }
+    added
+}
+
 /// Issue category counts for the scorecard.
-#[derive(Debug, Default, serde::Serialize)]
+#[derive(
+    Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
+#[serde(default)]
 pub struct IssueCounts {
     pub generic_titles: usize,
     pub short_content: usize,
@@ -204,6 +490,10 @@ pub struct IssueCounts {
     pub orphaned: usize,
     pub duplicates: usize,
     pub low_trust: usize,
+    pub low_confidence: usize,
+    pub over_retention: usize,
+    pub missing_referents: usize,
+    pub wrong_kind: usize,
 }
 
 impl IssueCounts {
@@ -221,11 +511,97 @@ impl IssueCounts {
                     QualityIssue::Orphaned => counts.orphaned += 1,
                     QualityIssue::PossibleDuplicate { .. } => counts.duplicates += 1,
                     QualityIssue::LowTrust { .. } => counts.low_trust += 1,
+                    QualityIssue::LowConfidence { .. } => counts.low_confidence += 1,
+                    QualityIssue::OverRetention { .. } => counts.over_retention += 1,
+                    QualityIssue::MissingReferents { .. } => counts.missing_referents += 1,
+                    QualityIssue::WrongKind { .. } => counts.wrong_kind += 1,
                 }
             }
         }
         counts
     }
+
+    /// Issues of every category together.
+    pub fn total(&self) -> usize {
+        self.by_name().values().sum()
+    }
+
+    /// Each category's count keyed by its field name, e.g. `no_tags`.
+    fn by_name(&self) -> BTreeMap<String, usize> {
+        serde_json::to_value(self)
+            .and_then(serde_json::from_value)
+            .unwrap_or_default()
+    }
+}
+
+/// Count holding the quality score in an assess [`MaintenanceRun`].
+pub const SCORE_COUNT: &str = "quality_score";
+
+/// Count holding the number of memories assessed in an assess
+/// [`MaintenanceRun`].
+pub const MEMORIES_COUNT: &str = "memories";
+
+/// Record an assessment's score, size and issue counts on its run, so
+/// [`health_trend`] can chart them later.
+pub fn record_scorecard(run: &mut MaintenanceRun, score: u32, total: usize, counts: &IssueCounts) {
+    run.count(SCORE_COUNT, score as usize);
+    run.count(MEMORIES_COUNT, total);
+    for (name, count) in counts.by_name() {
+        run.count(&name, count);
+    }
+}
+
+/// The memory store's health at the end of one month.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, schemars::JsonSchema)]
+pub struct HealthPoint {
+    /// `YYYY-MM`.
+    pub month: String,
+    /// When the month's last assessment ran.
+    pub assessed_at: DateTime<Utc>,
+    pub score: u32,
+    pub memories: usize,
+    pub counts: IssueCounts,
+}
+
+/// One point per calendar month with a successful assess run, taken from
+/// that month's latest run, oldest month first.
+pub fn health_trend(runs: &[MaintenanceRun]) -> Vec<HealthPoint> {
+    let mut by_month: BTreeMap<(i32, u32), &MaintenanceRun> = BTreeMap::new();
+    for run in runs.iter().filter(|r| r.succeeded()) {
+        if !run.counts.contains_key(SCORE_COUNT) {
+            continue;
+        }
+        let month = (run.started_at.year(), run.started_at.month());
+        let latest = by_month.entry(month).or_insert(run);
+        if run.started_at > latest.started_at {
+            *latest = run;
+        }
+    }
+    by_month
+        .into_iter()
+        .map(|((year, month), run)| {
+            let count = |name: &str| run.counts.get(name).copied().unwrap_or(0);
+            let counts = serde_json::to_value(&run.counts)
+                .and_then(serde_json::from_value)
+                .unwrap_or_default();
+            HealthPoint {
+                month: format!("{year:04}-{month:02}"),
+                assessed_at: run.started_at,
+                score: count(SCORE_COUNT) as u32,
+                memories: count(MEMORIES_COUNT) as usize,
+                counts,
+            }
+        })
+        .collect()
+}
+
+/// Scores (0–100) as a row of block characters, e.g. `▃▄▆█`.
+pub fn sparkline(scores: &[u32]) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    scores
+        .iter()
+        .map(|&s| BLOCKS[(s.min(100) as usize * (BLOCKS.len() - 1) + 50) / 100])
+        .collect()
 }
 
 /// Compute an overall quality score (0–100) from assessment results.
@@ -463,6 +839,79 @@ mod tests {
             .any(|i| matches!(i, QualityIssue::LowTrust { .. })));
     }
 
+    #[test]
+    fn test_low_confidence_flagged_until_verified() {
+        let low = |m: &Memory| {
+            analyze_memory(m, &AssessConfig::default(), 1)
+                .iter()
+                .any(|i| matches!(i, QualityIssue::LowConfidence { .. }))
+        };
+        let m = make_memory("Good title", "content", 0.5, vec![]).with_confidence(0.3);
+        assert!(low(&m));
+        assert!(!low(&m.clone().with_confidence(0.8)));
+        assert!(!low(
+            &m.with_verification(crate::model::VerificationStatus::Verified)
+        ));
+    }
+
+    #[test]
+    fn test_suggest_kind_from_keywords() {
+        use crate::model::MemorySource;
+        let captured = |title: &str, content: &str| {
+            make_memory(title, content, 0.5, vec![]).with_source(MemorySource::AutoCapture {
+                hook: "Stop".to_string(),
+            })
+        };
+        let decision = captured(
+            "Switched to sqlite-vec",
+            "We decided on sqlite-vec instead of a separate vector server.",
+        );
+        assert_eq!(suggest_kind(&decision), Some(MemoryKind::Decision));
+        assert!(analyze_memory(&decision, &AssessConfig::default(), 1)
+            .iter()
+            .any(|i| matches!(i, QualityIssue::WrongKind { suggested } if *suggested == MemoryKind::Decision)));
+
+        // One hit isn't enough, and neither is a tie.
+        assert_eq!(
+            suggest_kind(&captured("Edit main.rs", "Need to rerun")),
+            None
+        );
+        assert_eq!(
+            suggest_kind(&captured(
+                "Notes",
+                "Decided on retries instead of backoff. Turns out a lesson."
+            )),
+            None
+        );
+        // Only unconfirmed auto-captured observations.
+        let manual = make_memory("Switched to sqlite-vec", "decided", 0.5, vec![]);
+        assert_eq!(suggest_kind(&manual), None);
+        let kept = decision.clone().with_tags(vec![KIND_KEPT_TAG.to_string()]);
+        assert_eq!(suggest_kind(&kept), None);
+        let verified = decision.with_verification(VerificationStatus::Verified);
+        assert_eq!(suggest_kind(&verified), None);
+    }
+
+    #[test]
+    fn test_flag_kinds_overrides_keywords() {
+        let a = make_memory("a", "content", 0.5, vec![]);
+        let b = make_memory("b", "content", 0.5, vec![]);
+        let mut results = vec![AssessmentResult {
+            memory_id: a.id,
+            title: a.title.clone(),
+            issues: vec![QualityIssue::WrongKind {
+                suggested: MemoryKind::Decision,
+            }],
+        }];
+        let kinds = HashMap::from([(a.id, MemoryKind::Observation), (b.id, MemoryKind::Lesson)]);
+        flag_kinds(&mut results, &[a, b.clone()], &kinds);
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].memory_id, b.id);
+        assert_eq!(suggested_kind(&results[0]), Some(MemoryKind::Lesson));
+        assert_eq!(IssueCounts::from_results(&results).wrong_kind, 1);
+    }
+
     #[test]
     fn test_quality_score_perfect() {
         let score = quality_score(&[], 10);
@@ -535,4 +984,146 @@ mod tests {
         assert_eq!(counts.orphaned, 1);
         assert_eq!(counts.generic_titles, 1);
     }
+
+    #[test]
+    fn test_flag_retention_merges_into_results() {
+        let first = make_memory("first", "content", 0.5, vec![]);
+        let second = make_memory("second", "content", 0.5, vec!["tag".into()]);
+        let mut retention = RetentionConfig::default();
+        retention.kinds.insert(
+            MemoryKind::Observation,
+            crate::decay::RetentionPolicy {
+                max_count: Some(0),
+                ..Default::default()
+            },
+        );
+
+        let mut results = vec![AssessmentResult {
+            memory_id: first.id,
+            title: first.title.clone(),
+            issues: vec![QualityIssue::NoTags],
+        }];
+        flag_retention(&mut results, &[first, second], &retention);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].issues.len(), 2);
+        let counts = IssueCounts::from_results(&results);
+        assert_eq!(counts.over_retention, 2);
+    }
+
+    #[test]
+    fn test_flag_codebase_skips_other_projects() {
+        let codebase = Codebase::from_parts(
+            "/work/app".into(),
+            ["src/main.rs".to_string()].into(),
+            Default::default(),
+            Default::default(),
+        );
+        let ours = make_memory("ours", "Logic moved out of src/old.rs", 0.5, vec![])
+            .with_project("app".into());
+        let untagged = make_memory("untagged", "See src/gone.rs", 0.5, vec![]);
+        let theirs =
+            make_memory("theirs", "See src/other.rs", 0.5, vec![]).with_project("other".into());
+        let current = make_memory("current", "Entry point is src/main.rs", 0.5, vec![]);
+
+        let mut results = Vec::new();
+        flag_codebase(
+            &mut results,
+            &[ours.clone(), untagged.clone(), theirs, current],
+            &codebase,
+            Some("app"),
+        );
+
+        let flagged: Vec<Uuid> = results.iter().map(|r| r.memory_id).collect();
+        assert_eq!(flagged, vec![ours.id, untagged.id]);
+        assert!(matches!(
+            &results[0].issues[0],
+            QualityIssue::MissingReferents { missing } if missing.paths == ["src/old.rs"]
+        ));
+        assert_eq!(IssueCounts::from_results(&results).missing_referents, 2);
+    }
+
+    #[test]
+    fn test_flag_duplicates() {
+        let a = make_memory(
+            "Use sqlite-vec",
+            "Vector search runs in SQLite",
+            0.5,
+            vec![],
+        );
+        let b = make_memory(
+            "sqlite-vec for search",
+            "Vector search uses SQLite",
+            0.5,
+            vec![],
+        );
+        let c = make_memory("Deploy on Fridays", "Never deploy on Fridays", 0.5, vec![]);
+        let unembedded = make_memory("No vector", "Never embedded", 0.5, vec![]);
+        let embeddings = HashMap::from([
+            (a.id, vec![1.0, 0.0, 0.0]),
+            (b.id, vec![1.0, 0.1, 0.0]),
+            (c.id, vec![0.0, 0.0, 5.0]),
+        ]);
+        let memories = [a.clone(), b.clone(), c, unembedded];
+
+        let mut results = Vec::new();
+        let added = flag_duplicates(&mut results, &memories, &embeddings, 0.6, 4);
+        assert_eq!(added, 2, "a and b flag each other, c matches nothing");
+        let flagged: Vec<Uuid> = results.iter().map(|r| r.memory_id).collect();
+        assert_eq!(flagged, vec![a.id, b.id]);
+        assert!(matches!(
+            &results[0].issues[0],
+            QualityIssue::PossibleDuplicate { other_id, similarity, .. }
+                if *other_id == b.id && *similarity > 0.9
+        ));
+
+        // Running again adds nothing new.
+        assert_eq!(
+            flag_duplicates(&mut results, &memories, &embeddings, 0.6, 4),
+            0
+        );
+    }
+
+    #[test]
+    fn test_health_trend_keeps_last_run_per_month() {
+        use crate::maintenance::MaintenanceOp;
+        use chrono::TimeZone;
+
+        let run = |day: u32, month: u32, score: u32, no_tags: usize| {
+            let mut run = MaintenanceRun::start(MaintenanceOp::Assess, serde_json::Value::Null);
+            run.started_at = Utc.with_ymd_and_hms(2026, month, day, 12, 0, 0).unwrap();
+            let counts = IssueCounts {
+                no_tags,
+                stale: 2,
+                ..Default::default()
+            };
+            record_scorecard(&mut run, score, 40, &counts);
+            run.finish();
+            run
+        };
+        let mut failed = run(28, 3, 10, 30);
+        failed.error("interrupted");
+        // Newest first, as storage returns them.
+        let runs = vec![
+            run(2, 4, 81, 3),
+            failed,
+            run(20, 3, 74, 6),
+            run(5, 3, 60, 12),
+        ];
+
+        let trend = health_trend(&runs);
+        assert_eq!(trend.len(), 2);
+        assert_eq!(trend[0].month, "2026-03");
+        assert_eq!((trend[0].score, trend[0].memories), (74, 40));
+        assert_eq!(trend[0].counts.no_tags, 6);
+        assert_eq!(trend[0].counts.total(), 8);
+        assert_eq!((trend[1].month.as_str(), trend[1].score), ("2026-04", 81));
+        assert!(health_trend(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[0, 50, 100, 120]), "▁▅██");
+        assert_eq!(sparkline(&[]), "");
+    }
 }
@@ -0,0 +1,364 @@
+//! Content-addressable store for small artifacts attached to memories.
+//!
+//! Blobs live under `~/.config/shabka/blobs/<first two hex chars>/<sha256>`;
+//! memories reference them by hash through [`Memory::attachments`], so the
+//! same stack trace attached twice is stored once.
+
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::error::{Result, ShabkaError};
+use crate::model::{Attachment, Memory, UpdateMemoryInput};
+use crate::storage::StorageBackend;
+
+/// Limits for attachments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentConfig {
+    /// Largest accepted attachment, in bytes (default 1 MiB).
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: u64,
+    /// Most attachments a single memory may carry (default 10).
+    #[serde(default = "default_max_per_memory")]
+    pub max_per_memory: usize,
+}
+
+fn default_max_bytes() -> u64 {
+    1024 * 1024
+}
+
+fn default_max_per_memory() -> usize {
+    10
+}
+
+impl Default for AttachmentConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: default_max_bytes(),
+            max_per_memory: default_max_per_memory(),
+        }
+    }
+}
+
+/// Hex SHA-256 of `bytes`.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn is_sha256_hex(s: &str) -> bool {
+    s.len() == 64 && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Best-effort MIME type from a file name's extension.
+pub fn guess_content_type(name: &str) -> &'static str {
+    let ext = Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "json" => "application/json",
+        "toml" => "application/toml",
+        "yaml" | "yml" => "application/yaml",
+        "txt" | "log" | "md" | "rs" | "py" | "js" | "ts" | "sh" | "diff" | "patch" => {
+            "text/plain; charset=utf-8"
+        }
+        _ => "application/octet-stream",
+    }
+}
+
+/// Blobs on disk, keyed by SHA-256.
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    root: PathBuf,
+    max_bytes: u64,
+}
+
+impl BlobStore {
+    /// The store at `~/.config/shabka/blobs/`.
+    pub fn new(config: &AttachmentConfig) -> Self {
+        let root = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("/tmp"))
+            .join("shabka")
+            .join("blobs");
+        Self::with_path(root, config)
+    }
+
+    pub fn with_path(root: PathBuf, config: &AttachmentConfig) -> Self {
+        Self {
+            root,
+            max_bytes: config.max_bytes,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    fn blob_path(&self, sha256: &str) -> Result<PathBuf> {
+        if !is_sha256_hex(sha256) {
+            return Err(ShabkaError::InvalidInput(format!(
+                "not a sha256 digest: {sha256}"
+            )));
+        }
+        Ok(self.root.join(&sha256[..2]).join(sha256))
+    }
+
+    /// Store `bytes` and return the reference to put on a memory.
+    pub fn put(&self, name: &str, bytes: &[u8]) -> Result<Attachment> {
+        if bytes.len() as u64 > self.max_bytes {
+            return Err(ShabkaError::InvalidInput(format!(
+                "attachment '{name}' is {} bytes, limit is {}",
+                bytes.len(),
+                self.max_bytes
+            )));
+        }
+        let sha256 = sha256_hex(bytes);
+        let path = self.blob_path(&sha256)?;
+        if !path.exists() {
+            let dir = path.parent().expect("blob path has a parent");
+            std::fs::create_dir_all(dir)
+                .map_err(|e| ShabkaError::Storage(format!("failed to create blob dir: {e}")))?;
+            let tmp = dir.join(format!(".{sha256}.{}", Uuid::now_v7()));
+            std::fs::write(&tmp, bytes)
+                .and_then(|()| std::fs::rename(&tmp, &path))
+                .map_err(|e| {
+                    let _ = std::fs::remove_file(&tmp);
+                    ShabkaError::Storage(format!("failed to write blob {sha256}: {e}"))
+                })?;
+        }
+        Ok(Attachment {
+            sha256,
+            name: name.to_string(),
+            size: bytes.len() as u64,
+            content_type: guess_content_type(name).to_string(),
+        })
+    }
+
+    /// Read a blob back, checking it still matches its hash.
+    pub fn get(&self, sha256: &str) -> Result<Vec<u8>> {
+        let path = self.blob_path(sha256)?;
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(ShabkaError::NotFound(format!("blob {sha256}")));
+            }
+            Err(e) => {
+                return Err(ShabkaError::Storage(format!(
+                    "failed to read blob {sha256}: {e}"
+                )))
+            }
+        };
+        if sha256_hex(&bytes) != sha256 {
+            return Err(ShabkaError::Storage(format!(
+                "blob {sha256} is corrupt (hash mismatch)"
+            )));
+        }
+        Ok(bytes)
+    }
+
+    pub fn contains(&self, sha256: &str) -> bool {
+        self.blob_path(sha256).is_ok_and(|p| p.exists())
+    }
+}
+
+/// Store `bytes` and attach them to a memory. Attaching the same content
+/// twice is a no-op.
+pub async fn attach(
+    storage: &impl StorageBackend,
+    blobs: &BlobStore,
+    config: &AttachmentConfig,
+    memory_id: Uuid,
+    name: &str,
+    bytes: &[u8],
+) -> Result<Memory> {
+    if !storage.capabilities().attachments {
+        return Err(ShabkaError::InvalidInput(
+            "attachments are not supported by this storage backend".into(),
+        ));
+    }
+    let memory = storage.get_memory(memory_id).await?;
+    let attachment = blobs.put(name, bytes)?;
+    if memory
+        .attachments
+        .iter()
+        .any(|a| a.sha256 == attachment.sha256)
+    {
+        return Ok(memory);
+    }
+    if memory.attachments.len() >= config.max_per_memory {
+        return Err(ShabkaError::InvalidInput(format!(
+            "memory already has {} attachments (limit {})",
+            memory.attachments.len(),
+            config.max_per_memory
+        )));
+    }
+    let mut attachments = memory.attachments;
+    attachments.push(attachment);
+    storage
+        .update_memory(
+            memory_id,
+            &UpdateMemoryInput {
+                attachments: Some(attachments),
+                ..Default::default()
+            },
+        )
+        .await
+}
+
+/// A blob carried inside an export, base64-encoded.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExportedBlob {
+    pub sha256: String,
+    pub data: String,
+}
+
+/// Blobs referenced by `memories`, for inclusion in an export. Blobs missing
+/// from the store are skipped.
+pub fn export_blobs(blobs: &BlobStore, memories: &[Memory]) -> Vec<ExportedBlob> {
+    let mut seen = std::collections::HashSet::new();
+    memories
+        .iter()
+        .flat_map(|m| &m.attachments)
+        .filter(|a| seen.insert(a.sha256.clone()))
+        .filter_map(|a| match blobs.get(&a.sha256) {
+            Ok(bytes) => Some(ExportedBlob {
+                sha256: a.sha256.clone(),
+                data: base64::engine::general_purpose::STANDARD.encode(bytes),
+            }),
+            Err(e) => {
+                tracing::warn!("skipping attachment {} in export: {e}", a.name);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Restore an exported blob, rejecting it if the content doesn't match its hash.
+pub fn import_blob(blobs: &BlobStore, blob: &ExportedBlob) -> Result<()> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(&blob.data)
+        .map_err(|e| ShabkaError::InvalidInput(format!("blob {}: {e}", blob.sha256)))?;
+    if sha256_hex(&bytes) != blob.sha256 {
+        return Err(ShabkaError::InvalidInput(format!(
+            "blob {} does not match its content",
+            blob.sha256
+        )));
+    }
+    blobs.put(&blob.sha256, &bytes).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::MemoryKind;
+    use crate::storage::SqliteStorage;
+
+    fn temp_store(max_bytes: u64) -> BlobStore {
+        let root = std::env::temp_dir().join(format!("shabka-blobs-{}", Uuid::now_v7()));
+        BlobStore::with_path(
+            root,
+            &AttachmentConfig {
+                max_bytes,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_put_get_roundtrip() {
+        let store = temp_store(1024);
+        let a = store.put("trace.log", b"panicked at main.rs:1").unwrap();
+        assert_eq!(a.size, 21);
+        assert_eq!(a.content_type, "text/plain; charset=utf-8");
+        assert!(store.contains(&a.sha256));
+        assert_eq!(store.get(&a.sha256).unwrap(), b"panicked at main.rs:1");
+        // Same content, same blob.
+        assert_eq!(
+            store
+                .put("copy.txt", b"panicked at main.rs:1")
+                .unwrap()
+                .sha256,
+            a.sha256
+        );
+        let _ = std::fs::remove_dir_all(store.path());
+    }
+
+    #[test]
+    fn test_size_limit_and_bad_digest() {
+        let store = temp_store(4);
+        assert!(matches!(
+            store.put("big.bin", b"12345"),
+            Err(ShabkaError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            store.get("../../etc/passwd"),
+            Err(ShabkaError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            store.get(&sha256_hex(b"absent")),
+            Err(ShabkaError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_export_import_blob() {
+        let store = temp_store(1024);
+        let mut memory = Memory::new("t".into(), "c".into(), MemoryKind::Error, "u".into());
+        memory
+            .attachments
+            .push(store.put("a.txt", b"hello").unwrap());
+        let exported = export_blobs(&store, &[memory.clone(), memory]);
+        assert_eq!(exported.len(), 1);
+
+        let other = temp_store(1024);
+        import_blob(&other, &exported[0]).unwrap();
+        assert_eq!(other.get(&exported[0].sha256).unwrap(), b"hello");
+
+        let tampered = ExportedBlob {
+            data: base64::engine::general_purpose::STANDARD.encode(b"bye"),
+            ..exported[0].clone()
+        };
+        assert!(import_blob(&other, &tampered).is_err());
+        let _ = std::fs::remove_dir_all(store.path());
+        let _ = std::fs::remove_dir_all(other.path());
+    }
+
+    #[tokio::test]
+    async fn test_attach_to_memory() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let store = temp_store(1024);
+        let config = AttachmentConfig {
+            max_per_memory: 1,
+            ..Default::default()
+        };
+        let memory = Memory::new("t".into(), "c".into(), MemoryKind::Error, "u".into());
+        storage.save_memory(&memory, None).await.unwrap();
+
+        let updated = attach(&storage, &store, &config, memory.id, "a.txt", b"one")
+            .await
+            .unwrap();
+        assert_eq!(updated.attachments.len(), 1);
+        // Re-attaching identical content is a no-op, even at the limit.
+        attach(&storage, &store, &config, memory.id, "b.txt", b"one")
+            .await
+            .unwrap();
+        assert!(
+            attach(&storage, &store, &config, memory.id, "c.txt", b"two")
+                .await
+                .is_err()
+        );
+        let stored = storage.get_memory(memory.id).await.unwrap();
+        assert_eq!(stored.attachments, updated.attachments);
+        let _ = std::fs::remove_dir_all(store.path());
+    }
+}
@@ -0,0 +1,394 @@
+//! Request audit log for `shabka-web` and the MCP server.
+//!
+//! With `[audit] enabled`, every REST/dashboard request and MCP tool call is
+//! appended as one [`AuditEntry`] to `~/.config/shabka/audit.jsonl`: what
+//! was called, with which API token, a short summary of the parameters, the
+//! outcome and how long it took. Unlike the history log, which records what
+//! changed, this answers what a client *touched*, reads included. The file
+//! is rotated to `audit.jsonl.1`, `.2`, ... once it reaches `max_bytes`.
+//! `shabka audit tail` shows the latest entries.
+
+use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Longest parameter summary kept, in characters.
+pub const MAX_PARAMS_CHARS: usize = 200;
+
+/// Where audit entries go.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Rotate the log once it reaches this size (default 10 MiB).
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: u64,
+    /// Rotated files kept besides the live one (default 5).
+    #[serde(default = "default_keep")]
+    pub keep: usize,
+}
+
+fn default_max_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_keep() -> usize {
+    5
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_bytes: default_max_bytes(),
+            keep: default_keep(),
+        }
+    }
+}
+
+/// One API request or MCP tool call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    /// `web` or `mcp`.
+    pub surface: String,
+    /// `GET /api/v1/memories` for web requests, the tool name for MCP.
+    pub endpoint: String,
+    /// Name of the API token used, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Tenant the API token belongs to, in multi-tenant mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// Query string or tool arguments, cut to [`MAX_PARAMS_CHARS`].
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub params: String,
+    /// HTTP status code, or `ok` / `error: ...` for tool calls.
+    pub outcome: String,
+    pub success: bool,
+    pub latency_ms: u64,
+}
+
+impl AuditEntry {
+    pub fn new(surface: impl Into<String>, endpoint: impl Into<String>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            surface: surface.into(),
+            endpoint: endpoint.into(),
+            token: None,
+            tenant: None,
+            params: String::new(),
+            outcome: String::new(),
+            success: true,
+            latency_ms: 0,
+        }
+    }
+
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    pub fn with_tenant(mut self, tenant: Option<String>) -> Self {
+        self.tenant = tenant;
+        self
+    }
+
+    /// Record a parameter summary, cut to [`MAX_PARAMS_CHARS`].
+    pub fn with_params(mut self, params: &str) -> Self {
+        self.params = match params.char_indices().nth(MAX_PARAMS_CHARS) {
+            Some((end, _)) => format!("{}…", &params[..end]),
+            None => params.to_string(),
+        };
+        self
+    }
+
+    /// Record how the call ended and how long it took.
+    pub fn finish(
+        mut self,
+        outcome: impl Into<String>,
+        success: bool,
+        latency: std::time::Duration,
+    ) -> Self {
+        self.outcome = outcome.into();
+        self.success = success;
+        self.latency_ms = latency.as_millis() as u64;
+        self
+    }
+}
+
+/// Which entries [`AuditLog::tail`] returns.
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    /// `web` or `mcp`.
+    pub surface: Option<String>,
+    /// Token name, compared case-insensitively.
+    pub token: Option<String>,
+    /// Tenant name, compared exactly.
+    pub tenant: Option<String>,
+    /// Only failed calls.
+    pub failures_only: bool,
+}
+
+impl AuditFilter {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        if self
+            .surface
+            .as_deref()
+            .is_some_and(|s| !entry.surface.eq_ignore_ascii_case(s))
+        {
+            return false;
+        }
+        if let Some(token) = &self.token {
+            if !entry
+                .token
+                .as_deref()
+                .is_some_and(|t| t.eq_ignore_ascii_case(token))
+            {
+                return false;
+            }
+        }
+        if self.tenant.is_some() && entry.tenant != self.tenant {
+            return false;
+        }
+        !(self.failures_only && entry.success)
+    }
+}
+
+/// Append-only, size-rotated JSONL audit log.
+pub struct AuditLog {
+    path: PathBuf,
+    config: AuditConfig,
+}
+
+impl AuditLog {
+    pub fn new(config: &AuditConfig) -> Self {
+        let path = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("shabka")
+            .join("audit.jsonl");
+        Self::with_path(path, config)
+    }
+
+    /// A log stored at `path` instead of the config directory.
+    pub fn with_path(path: PathBuf, config: &AuditConfig) -> Self {
+        Self {
+            path,
+            config: config.clone(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append `entry`, rotating first if the log is full. Failures are
+    /// logged and otherwise ignored; auditing never fails a request.
+    pub fn log(&self, entry: &AuditEntry) {
+        if !self.config.enabled {
+            return;
+        }
+        if let Some(parent) = self.path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let full = std::fs::metadata(&self.path)
+            .map(|m| m.len() >= self.config.max_bytes)
+            .unwrap_or(false);
+        if full {
+            self.rotate();
+        }
+        let line = match serde_json::to_string(entry) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::debug!("audit: failed to serialize entry: {e}");
+                return;
+            }
+        };
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path);
+        match file {
+            Ok(mut f) => {
+                let _ = writeln!(f, "{line}");
+            }
+            Err(e) => tracing::debug!("audit: failed to open log: {e}"),
+        }
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{n}"));
+        PathBuf::from(name)
+    }
+
+    /// Shift `audit.jsonl.N` to `.N+1`, dropping the oldest, and move the
+    /// live log to `.1`.
+    fn rotate(&self) {
+        if self.config.keep == 0 {
+            let _ = std::fs::remove_file(&self.path);
+            return;
+        }
+        let _ = std::fs::remove_file(self.rotated(self.config.keep));
+        for n in (1..self.config.keep).rev() {
+            let _ = std::fs::rename(self.rotated(n), self.rotated(n + 1));
+        }
+        if let Err(e) = std::fs::rename(&self.path, self.rotated(1)) {
+            tracing::debug!("audit: failed to rotate log: {e}");
+        }
+    }
+
+    /// The last `limit` entries matching `filter`, oldest first. Reads the
+    /// most recently rotated file too when the live one has too few.
+    pub fn tail(&self, filter: &AuditFilter, limit: usize) -> Vec<AuditEntry> {
+        let mut entries = read_entries(&self.path, filter);
+        if entries.len() < limit {
+            let mut older = read_entries(&self.rotated(1), filter);
+            older.append(&mut entries);
+            entries = older;
+        }
+        let skip = entries.len().saturating_sub(limit);
+        entries.split_off(skip)
+    }
+
+    /// Every entry matching `filter`, in the live log and all rotated
+    /// files, oldest first.
+    pub fn entries(&self, filter: &AuditFilter) -> Vec<AuditEntry> {
+        let mut entries = Vec::new();
+        for n in (1..=self.config.keep).rev() {
+            entries.extend(read_entries(&self.rotated(n), filter));
+        }
+        entries.extend(read_entries(&self.path, filter));
+        entries
+    }
+
+    /// Entries appended after byte `offset` of the live log, and the offset
+    /// to resume from. Starts over from the beginning after a rotation.
+    pub fn read_from(&self, offset: u64, filter: &AuditFilter) -> (Vec<AuditEntry>, u64) {
+        let Ok(mut file) = std::fs::File::open(&self.path) else {
+            return (Vec::new(), 0);
+        };
+        let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+        let offset = if len < offset { 0 } else { offset };
+        if file.seek(SeekFrom::Start(offset)).is_err() {
+            return (Vec::new(), offset);
+        }
+        let mut text = String::new();
+        if file.read_to_string(&mut text).is_err() {
+            return (Vec::new(), offset);
+        }
+        // Leave a line still being written for the next read.
+        let complete = text.rfind('\n').map_or(0, |i| i + 1);
+        let entries = text[..complete]
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .filter(|e| filter.matches(e))
+            .collect();
+        (entries, offset + complete as u64)
+    }
+}
+
+fn read_entries(path: &Path, filter: &AuditFilter) -> Vec<AuditEntry> {
+    let Ok(file) = std::fs::File::open(path) else {
+        return Vec::new();
+    };
+    std::io::BufReader::new(file)
+        .lines()
+        .map_while(|line| line.ok())
+        .filter_map(|line| serde_json::from_str::<AuditEntry>(&line).ok())
+        .filter(|e| filter.matches(e))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_log(config: &AuditConfig) -> AuditLog {
+        let dir = std::env::temp_dir().join(format!("shabka-audit-{}", uuid::Uuid::now_v7()));
+        AuditLog::with_path(dir.join("audit.jsonl"), config)
+    }
+
+    fn entry(surface: &str, endpoint: &str, success: bool) -> AuditEntry {
+        AuditEntry::new(surface, endpoint).finish(
+            if success { "ok" } else { "error: boom" },
+            success,
+            Duration::from_millis(3),
+        )
+    }
+
+    #[test]
+    fn test_disabled_log_writes_nothing() {
+        let log = temp_log(&AuditConfig::default());
+        log.log(&entry("web", "GET /api/v1/memories", true));
+        assert!(!log.path().exists());
+    }
+
+    #[test]
+    fn test_tail_filters_and_limits() {
+        let log = temp_log(&AuditConfig {
+            enabled: true,
+            ..Default::default()
+        });
+        log.log(&entry("web", "GET /api/v1/memories", true).with_token(Some("ci".into())));
+        log.log(&entry("mcp", "search", true));
+        log.log(&entry("mcp", "save_memory", false));
+
+        let all = log.tail(&AuditFilter::default(), 2);
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].endpoint, "search");
+        assert_eq!(all[1].latency_ms, 3);
+        let failed = AuditFilter {
+            failures_only: true,
+            ..Default::default()
+        };
+        assert_eq!(log.tail(&failed, 10)[0].endpoint, "save_memory");
+        let by_token = AuditFilter {
+            token: Some("CI".into()),
+            ..Default::default()
+        };
+        assert_eq!(log.tail(&by_token, 10).len(), 1);
+
+        let (entries, offset) = log.read_from(0, &AuditFilter::default());
+        assert_eq!(entries.len(), 3);
+        log.log(&entry("mcp", "history", true));
+        let (entries, _) = log.read_from(offset, &AuditFilter::default());
+        assert_eq!(entries.len(), 1);
+        let _ = std::fs::remove_dir_all(log.path().parent().unwrap());
+    }
+
+    #[test]
+    fn test_rotation_keeps_configured_files() {
+        let log = temp_log(&AuditConfig {
+            enabled: true,
+            max_bytes: 1,
+            keep: 2,
+        });
+        for tool in ["a", "b", "c", "d"] {
+            log.log(&entry("mcp", tool, true));
+        }
+        // Every write after the first rotates: d is live, c and b rotated.
+        assert_eq!(log.tail(&AuditFilter::default(), 1)[0].endpoint, "d");
+        assert!(log.rotated(2).exists());
+        assert!(!log.rotated(3).exists());
+        let tail = log.tail(&AuditFilter::default(), 5);
+        let endpoints: Vec<_> = tail.iter().map(|e| e.endpoint.as_str()).collect();
+        assert_eq!(endpoints, ["c", "d"]);
+        let _ = std::fs::remove_dir_all(log.path().parent().unwrap());
+    }
+
+    #[test]
+    fn test_params_are_truncated() {
+        let long = "x".repeat(MAX_PARAMS_CHARS + 50);
+        let entry = AuditEntry::new("mcp", "search").with_params(&long);
+        assert_eq!(entry.params.chars().count(), MAX_PARAMS_CHARS + 1);
+    }
+}
@@ -3,9 +3,11 @@
 //! When enabled (`capture.auto_tag = true` and `llm.enabled = true`),
 //! newly captured memories are sent to the LLM for tag and importance suggestions.
 
+use schemars::JsonSchema;
 use serde::Deserialize;
 
 use crate::llm::LlmService;
+use crate::llm_budget::LlmFeature;
 use crate::model::Memory;
 
 /// Result of auto-tagging a memory.
@@ -16,7 +18,7 @@ pub struct AutoTagResult {
 }
 
 /// Raw JSON response from the LLM for auto-tagging.
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug)]
 struct AutoTagLlmResponse {
     #[serde(default)]
     tags: Vec<String>,
@@ -52,7 +54,7 @@ pub async fn auto_tag(memory: &Memory, llm: &LlmService) -> Option<AutoTagResult
     );
 
     let response: AutoTagLlmResponse = llm
-        .generate_structured(&prompt, Some(AUTO_TAG_SYSTEM_PROMPT))
+        .generate_structured(LlmFeature::AutoTag, &prompt, Some(AUTO_TAG_SYSTEM_PROMPT))
         .await
         .ok()?;
 
@@ -0,0 +1,330 @@
+//! Backfill LLM tagging and kind classification onto existing memories.
+//!
+//! Memories captured before `[llm]` was enabled carry only the tags hooks
+//! add on their own (`auto-capture`, `file-change`, ...) and the kind the
+//! hook guessed from the event. `shabka backfill` sends them to the LLM a
+//! batch at a time: [`candidates`] picks the memories still missing what
+//! was asked for, [`estimate_tokens`] prices the run up front, [`suggest`]
+//! asks the LLM about one batch and [`update_for`] turns its answer into an
+//! update.
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::assess::KIND_KEPT_TAG;
+use crate::error::Result;
+use crate::llm::LlmService;
+use crate::llm_budget::LlmFeature;
+use crate::model::{Memory, MemoryKind, MemorySource, UpdateMemoryInput};
+use crate::tokens;
+
+/// Tags hooks add on their own. They say how a memory was captured, not
+/// what it is about, so a memory with only these still needs tagging.
+pub const SYSTEM_TAGS: &[&str] = &[
+    "auto-capture",
+    "session-compressed",
+    "llm-summarized",
+    "file-change",
+    "bash-error",
+    "tool-failure",
+];
+
+/// Tag marking a memory whose kind the LLM has already classified.
+pub const CLASSIFIED_TAG: &str = "llm-classified";
+
+/// Characters of each memory's content sent to the LLM.
+const CONTENT_CHARS: usize = 800;
+
+/// Estimated reply tokens per memory in a batch.
+pub const REPLY_TOKENS_PER_MEMORY: usize = 40;
+
+/// What to backfill.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BackfillOptions {
+    /// Suggest tags and importance for memories with only capture tags.
+    pub auto_tag: bool,
+    /// Re-classify the kind of auto-captured memories.
+    pub classify_kind: bool,
+}
+
+impl BackfillOptions {
+    /// Whether `memory` still lacks something these options backfill.
+    pub fn applies_to(&self, memory: &Memory) -> bool {
+        (self.auto_tag && needs_tags(memory)) || (self.classify_kind && needs_kind(memory))
+    }
+}
+
+/// Whether `memory` has no tags beyond [`SYSTEM_TAGS`].
+pub fn needs_tags(memory: &Memory) -> bool {
+    memory
+        .tags
+        .iter()
+        .all(|t| SYSTEM_TAGS.contains(&t.as_str()))
+}
+
+/// Whether `memory`'s kind is a hook's guess: auto-captured, neither
+/// summarized by the LLM at capture nor classified by an earlier backfill,
+/// and not kept by someone who reviewed it (see [`crate::assess`]).
+pub fn needs_kind(memory: &Memory) -> bool {
+    matches!(memory.source, MemorySource::AutoCapture { .. })
+        && !memory
+            .tags
+            .iter()
+            .any(|t| t == "llm-summarized" || t == CLASSIFIED_TAG || t == KIND_KEPT_TAG)
+}
+
+/// The memories among `memories` that `options` would change.
+pub fn candidates(memories: Vec<Memory>, options: &BackfillOptions) -> Vec<Memory> {
+    memories
+        .into_iter()
+        .filter(|m| options.applies_to(m))
+        .collect()
+}
+
+/// Estimated LLM tokens to backfill `memories`, `batch` per call: the
+/// system prompt for every call, each memory's prompt text and a short
+/// reply per memory. Like the ledger, this is an estimate, not a bill.
+pub fn estimate_tokens(memories: &[Memory], batch: usize) -> u64 {
+    let calls = memories.len().div_ceil(batch.max(1));
+    let system = tokens::estimate_tokens(SYSTEM_PROMPT) + SCHEMA_TOKENS;
+    let prompts: usize = memories
+        .iter()
+        .map(|m| tokens::estimate_tokens(&memory_prompt(0, m)) + REPLY_TOKENS_PER_MEMORY)
+        .sum();
+    (calls * system + prompts) as u64
+}
+
+/// Approximate size of the reply schema appended to the system prompt.
+const SCHEMA_TOKENS: usize = 150;
+
+/// The LLM's suggestion for one memory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub memory_id: Uuid,
+    pub tags: Vec<String>,
+    pub importance: Option<f32>,
+    pub kind: Option<MemoryKind>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+struct BackfillReply {
+    memories: Vec<BackfillItem>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+struct BackfillItem {
+    /// The memory's number in the prompt.
+    index: usize,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    importance: Option<f64>,
+    #[serde(default)]
+    kind: Option<String>,
+}
+
+const SYSTEM_PROMPT: &str = r#"You are a developer knowledge-base curator. You are given numbered memories from a coding assistant's memory store. For each memory, reply with an object holding its number as "index" and the fields you are asked for.
+
+- "tags": 3-8 specific, lowercase tags (technology, library, concept, file or pattern, e.g. "rust", "sqlite", "config", "bug-fix"). Never generic tags like "auto-capture", "memory" or "note".
+- "importance": 0.0-1.0; 0.1-0.3 routine, 0.4-0.6 useful, 0.7-0.8 important decisions or critical bugs, 0.9-1.0 security or data loss.
+- "kind": exactly one of observation, decision, pattern, error, fix, preference, fact, lesson, todo, procedure; what the memory actually records, whatever its current kind says.
+
+Reply with {"memories": [...]}, one object per memory."#;
+
+fn memory_prompt(index: usize, memory: &Memory) -> String {
+    let content: String = memory.content.chars().take(CONTENT_CHARS).collect();
+    format!(
+        "## {index}\nTitle: {}\nKind: {}\nContent: {content}\n",
+        memory.title, memory.kind
+    )
+}
+
+/// Ask the LLM about one batch. Memories the reply leaves out, or answers
+/// with nothing usable, get no suggestion.
+pub async fn suggest(
+    memories: &[Memory],
+    llm: &LlmService,
+    options: &BackfillOptions,
+) -> Result<Vec<Suggestion>> {
+    let mut fields = Vec::new();
+    if options.auto_tag {
+        fields.push("tags, importance");
+    }
+    if options.classify_kind {
+        fields.push("kind");
+    }
+    let mut prompt = format!("Fields: {}\n\n", fields.join(", "));
+    for (i, memory) in memories.iter().enumerate() {
+        prompt.push_str(&memory_prompt(i, memory));
+    }
+
+    let reply: BackfillReply = llm
+        .generate_structured(LlmFeature::Backfill, &prompt, Some(SYSTEM_PROMPT))
+        .await?;
+    Ok(parse_reply(reply, memories, options))
+}
+
+fn parse_reply(
+    reply: BackfillReply,
+    memories: &[Memory],
+    options: &BackfillOptions,
+) -> Vec<Suggestion> {
+    let mut suggestions: Vec<Suggestion> = Vec::new();
+    for item in reply.memories {
+        let Some(memory) = memories.get(item.index) else {
+            continue;
+        };
+        if suggestions.iter().any(|s| s.memory_id == memory.id) {
+            continue;
+        }
+        let mut tags: Vec<String> = Vec::new();
+        if options.auto_tag {
+            for tag in item.tags {
+                let tag = tag.trim().to_lowercase();
+                if !tag.is_empty() && !SYSTEM_TAGS.contains(&tag.as_str()) && !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+        }
+        let importance = item
+            .importance
+            .filter(|_| options.auto_tag && !tags.is_empty())
+            .map(|i| (i as f32).clamp(0.0, 1.0));
+        let kind = item
+            .kind
+            .filter(|_| options.classify_kind)
+            .and_then(|k| k.parse::<MemoryKind>().ok());
+        if tags.is_empty() && kind.is_none() {
+            continue;
+        }
+        suggestions.push(Suggestion {
+            memory_id: memory.id,
+            tags,
+            importance,
+            kind,
+        });
+    }
+    suggestions
+}
+
+/// The update applying `suggestion` to `memory`: suggested tags join the
+/// existing ones, and a classified memory is tagged [`CLASSIFIED_TAG`] so
+/// later runs skip it. `None` when nothing would change.
+pub fn update_for(memory: &Memory, suggestion: &Suggestion) -> Option<UpdateMemoryInput> {
+    let mut tags = memory.tags.clone();
+    for tag in &suggestion.tags {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    if suggestion.kind.is_some() && !tags.iter().any(|t| t == CLASSIFIED_TAG) {
+        tags.push(CLASSIFIED_TAG.to_string());
+    }
+
+    let input = UpdateMemoryInput {
+        tags: (tags != memory.tags).then_some(tags),
+        importance: suggestion
+            .importance
+            .filter(|i| (i - memory.importance).abs() > f32::EPSILON),
+        kind: suggestion.kind.filter(|k| *k != memory.kind),
+        ..Default::default()
+    };
+    let changes = input.tags.is_some() || input.importance.is_some() || input.kind.is_some();
+    changes.then_some(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn captured(title: &str, tags: &[&str]) -> Memory {
+        Memory::new(
+            title.into(),
+            "content".into(),
+            MemoryKind::Decision,
+            "u".into(),
+        )
+        .with_source(MemorySource::AutoCapture {
+            hook: "PostToolUse".into(),
+        })
+        .with_tags(tags.iter().map(|t| t.to_string()).collect())
+    }
+
+    #[test]
+    fn test_candidates() {
+        let untagged = captured("Edit main.rs", &["auto-capture", "file-change"]);
+        let tagged = captured("Pool sizing", &["auto-capture", "sqlite"]);
+        let summarized = captured("Summary", &["llm-summarized", "rust"]);
+        let manual = Memory::new("Manual".into(), "c".into(), MemoryKind::Fact, "u".into());
+
+        let tag_only = BackfillOptions {
+            auto_tag: true,
+            ..Default::default()
+        };
+        let ids = |options: &BackfillOptions| -> Vec<Uuid> {
+            let all = vec![
+                untagged.clone(),
+                tagged.clone(),
+                summarized.clone(),
+                manual.clone(),
+            ];
+            candidates(all, options).iter().map(|m| m.id).collect()
+        };
+        assert_eq!(ids(&tag_only), vec![untagged.id, manual.id]);
+
+        let kind_only = BackfillOptions {
+            classify_kind: true,
+            ..Default::default()
+        };
+        assert_eq!(ids(&kind_only), vec![untagged.id, tagged.id]);
+    }
+
+    #[test]
+    fn test_estimate_tokens_counts_system_prompt_per_call() {
+        let memories: Vec<Memory> = (0..4).map(|i| captured(&format!("M{i}"), &[])).collect();
+        let one_call = estimate_tokens(&memories, 4);
+        let four_calls = estimate_tokens(&memories, 1);
+        let system = (tokens::estimate_tokens(SYSTEM_PROMPT) + SCHEMA_TOKENS) as u64;
+        assert_eq!(four_calls - one_call, 3 * system);
+    }
+
+    #[test]
+    fn test_parse_reply_and_update() {
+        let memories = vec![
+            captured("Edit db.rs", &["auto-capture"]),
+            captured("Bash error", &["auto-capture"]),
+        ];
+        let options = BackfillOptions {
+            auto_tag: true,
+            classify_kind: true,
+        };
+        let reply: BackfillReply = serde_json::from_str(
+            r#"{"memories": [
+                {"index": 0, "tags": ["SQLite", "auto-capture", "pool"], "importance": 0.7, "kind": "decision"},
+                {"index": 1, "kind": "error"},
+                {"index": 7, "tags": ["ghost"]}
+            ]}"#,
+        )
+        .unwrap();
+        let suggestions = parse_reply(reply, &memories, &options);
+        assert_eq!(suggestions.len(), 2);
+        assert_eq!(suggestions[0].tags, vec!["sqlite", "pool"]);
+        assert_eq!(suggestions[1].importance, None);
+
+        let update = update_for(&memories[0], &suggestions[0]).unwrap();
+        assert_eq!(
+            update.tags.unwrap(),
+            vec!["auto-capture", "sqlite", "pool", CLASSIFIED_TAG]
+        );
+        assert_eq!(update.kind, None); // already a decision
+        let update = update_for(&memories[1], &suggestions[1]).unwrap();
+        assert_eq!(update.kind, Some(MemoryKind::Error));
+
+        let mut done = memories[1].clone();
+        done.tags.push(CLASSIFIED_TAG.to_string());
+        done.kind = MemoryKind::Error;
+        assert!(update_for(&done, &suggestions[1]).is_none());
+        assert!(!needs_kind(&done));
+    }
+}
@@ -0,0 +1,171 @@
+//! Centrality of memories in the relation graph.
+//!
+//! [`compute`] scores each connected memory by its degree (distinct
+//! neighbours) and a PageRank over its relations, weighted by strength.
+//! A memory many others fix, extend or were caused by ends up central, and
+//! those are usually the knowledge worth verifying first.
+//! [`crate::graph::refresh_centrality`] stores the scores, search reads them
+//! back as a ranking signal and `shabka graph top` lists the most central
+//! memories per project.
+
+use std::collections::{HashMap, HashSet};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::model::MemoryRelation;
+
+/// Share of rank passed along edges on each iteration.
+const DAMPING: f64 = 0.85;
+
+/// Iterations before giving up on convergence.
+const MAX_ITERATIONS: usize = 100;
+
+/// Total change in rank below which the iteration has converged.
+const TOLERANCE: f64 = 1e-9;
+
+/// Weight of an edge whose strength is zero, so it still counts.
+const MIN_EDGE_WEIGHT: f64 = 0.01;
+
+/// How central one memory is.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Centrality {
+    pub memory_id: Uuid,
+    /// Distinct memories related to this one, in either direction.
+    pub degree: usize,
+    /// PageRank over relations, scaled so the most central memory scores 1.0.
+    pub pagerank: f32,
+}
+
+/// Centrality of every memory among `memory_ids` with at least one relation
+/// to another of them, most central first. Relations are followed in both
+/// directions: a fix points at its error as much as the error at its fix.
+pub fn compute(memory_ids: &[Uuid], relations: &[MemoryRelation]) -> Vec<Centrality> {
+    let index: HashMap<Uuid, usize> = memory_ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| (*id, i))
+        .collect();
+    let n = memory_ids.len();
+
+    let mut edges: Vec<Vec<(usize, f64)>> = vec![Vec::new(); n];
+    let mut neighbours: Vec<HashSet<usize>> = vec![HashSet::new(); n];
+    for relation in relations {
+        let (Some(&a), Some(&b)) = (
+            index.get(&relation.source_id),
+            index.get(&relation.target_id),
+        ) else {
+            continue;
+        };
+        if a == b {
+            continue;
+        }
+        let weight = (relation.strength as f64).max(MIN_EDGE_WEIGHT);
+        edges[a].push((b, weight));
+        edges[b].push((a, weight));
+        neighbours[a].insert(b);
+        neighbours[b].insert(a);
+    }
+    let connected: Vec<usize> = (0..n).filter(|&i| !neighbours[i].is_empty()).collect();
+    if connected.is_empty() {
+        return Vec::new();
+    }
+
+    let out_weight: Vec<f64> = edges
+        .iter()
+        .map(|e| e.iter().map(|(_, w)| w).sum())
+        .collect();
+    let base = (1.0 - DAMPING) / n as f64;
+    let mut rank = vec![1.0 / n as f64; n];
+    for _ in 0..MAX_ITERATIONS {
+        // Isolated memories spread their rank evenly, like a random jump.
+        let dangling: f64 = (0..n)
+            .filter(|&i| out_weight[i] == 0.0)
+            .map(|i| rank[i])
+            .sum();
+        let mut next = vec![base + DAMPING * dangling / n as f64; n];
+        for (from, targets) in edges.iter().enumerate() {
+            for &(to, weight) in targets {
+                next[to] += DAMPING * rank[from] * weight / out_weight[from];
+            }
+        }
+        let change: f64 = rank.iter().zip(&next).map(|(a, b)| (a - b).abs()).sum();
+        rank = next;
+        if change < TOLERANCE {
+            break;
+        }
+    }
+
+    let max = connected
+        .iter()
+        .map(|&i| rank[i])
+        .fold(f64::MIN_POSITIVE, f64::max);
+    let mut scores: Vec<Centrality> = connected
+        .into_iter()
+        .map(|i| Centrality {
+            memory_id: memory_ids[i],
+            degree: neighbours[i].len(),
+            pagerank: (rank[i] / max) as f32,
+        })
+        .collect();
+    scores.sort_by(|a, b| {
+        b.pagerank
+            .partial_cmp(&a.pagerank)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.degree.cmp(&a.degree))
+    });
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::RelationType;
+
+    fn relation(source_id: Uuid, target_id: Uuid, strength: f32) -> MemoryRelation {
+        MemoryRelation {
+            source_id,
+            target_id,
+            relation_type: RelationType::Related,
+            strength,
+        }
+    }
+
+    #[test]
+    fn test_hub_is_most_central() {
+        let ids: Vec<Uuid> = (0..6).map(|_| Uuid::now_v7()).collect();
+        let (hub, spokes, isolated) = (ids[0], &ids[1..5], ids[5]);
+        let mut relations: Vec<MemoryRelation> =
+            spokes.iter().map(|&s| relation(s, hub, 0.8)).collect();
+        relations.push(relation(spokes[0], spokes[1], 0.5));
+        // Edges to unknown memories and self-loops are ignored.
+        relations.push(relation(hub, Uuid::now_v7(), 1.0));
+        relations.push(relation(hub, hub, 1.0));
+
+        let scores = compute(&ids, &relations);
+        assert_eq!(scores.len(), 5);
+        assert_eq!(scores[0].memory_id, hub);
+        assert_eq!(scores[0].degree, 4);
+        assert!((scores[0].pagerank - 1.0).abs() < f32::EPSILON);
+        assert!(scores[1..].iter().all(|s| s.pagerank < 1.0));
+        assert!(scores.iter().all(|s| s.memory_id != isolated));
+    }
+
+    #[test]
+    fn test_duplicate_edges_count_one_neighbour() {
+        let (a, b) = (Uuid::now_v7(), Uuid::now_v7());
+        let mut fixes = relation(a, b, 0.9);
+        fixes.relation_type = RelationType::Fixes;
+        let scores = compute(&[a, b], &[relation(a, b, 0.5), relation(b, a, 0.5), fixes]);
+        assert_eq!(scores.len(), 2);
+        assert!(scores.iter().all(|s| s.degree == 1));
+        assert!((scores[0].pagerank - scores[1].pagerank).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_no_relations() {
+        assert!(compute(&[Uuid::now_v7()], &[]).is_empty());
+        assert!(compute(&[], &[]).is_empty());
+    }
+}
@@ -0,0 +1,360 @@
+//! Citation checks for answers built from memories.
+//!
+//! An answer cites a memory by its full UUID or by a bracketed short ID
+//! (`[0192a4b7]`, `[^0192a4b7]`, `[id: 0192a4b7]`). Each citation is checked
+//! against the sentence it appears in: the memory must exist and be visible,
+//! still be active, and support the sentence, either by sharing its words or,
+//! when an embedder is available, by being semantically close. Anything else
+//! is flagged, so a hallucinated or stale citation doesn't pass for evidence.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::embedding::EmbeddingService;
+use crate::error::{Result, ShabkaError};
+use crate::model::{Memory, MemoryStatus};
+use crate::ranking;
+use crate::sharing;
+use crate::similarity;
+use crate::storage::{Storage, StorageBackend};
+
+/// Share of a claim's significant words the memory must contain.
+pub const MIN_OVERLAP: f32 = 0.5;
+
+/// Embedding similarity that counts as support on its own.
+pub const MIN_SIMILARITY: f32 = 0.75;
+
+/// Words too common to show support.
+const STOPWORDS: &[&str] = &[
+    "the", "and", "for", "with", "from", "into", "that", "this", "use", "using", "was", "are",
+    "has", "have", "not", "but", "its", "also", "which", "should", "because",
+];
+
+static CITATION_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\[?\^?(?:id:\s*)?\b([0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12})\b\]?|\[\^?(?:id:\s*)?([0-9a-f]{8}[0-9a-f-]{0,28})\]",
+    )
+    .expect("valid citation pattern")
+});
+
+/// A memory reference found in an answer, with the sentence it backs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Citation {
+    /// The cited ID or ID prefix, lowercased.
+    pub reference: String,
+    /// The sentence around the citation, without citation markers.
+    pub claim: String,
+}
+
+/// Outcome of checking one citation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CitationVerdict {
+    /// The memory exists, is active and backs the claim.
+    Supported,
+    /// The memory exists but doesn't back the claim.
+    Unsupported,
+    /// The memory is archived, superseded or otherwise not active.
+    Inactive,
+    /// No visible memory has this ID.
+    Missing,
+    /// The short ID matches more than one memory.
+    Ambiguous,
+}
+
+impl CitationVerdict {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Supported => "supported",
+            Self::Unsupported => "unsupported",
+            Self::Inactive => "inactive",
+            Self::Missing => "missing",
+            Self::Ambiguous => "ambiguous",
+        }
+    }
+}
+
+/// One checked citation.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct CitationCheck {
+    pub reference: String,
+    pub claim: String,
+    pub verdict: CitationVerdict,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<MemoryStatus>,
+    /// Share of the claim's significant words found in the memory.
+    pub overlap: f32,
+    /// Embedding similarity between claim and memory, when checked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f32>,
+}
+
+impl CitationCheck {
+    /// Whether the citation should be looked at by a person.
+    pub fn is_flagged(&self) -> bool {
+        self.verdict != CitationVerdict::Supported
+    }
+}
+
+/// Every citation in `answer`, in order. The same reference cited twice for
+/// the same claim is listed once.
+pub fn extract_citations(answer: &str) -> Vec<Citation> {
+    let spans = sentences(answer);
+    let mut citations: Vec<Citation> = Vec::new();
+    for caps in CITATION_RE.captures_iter(answer) {
+        let start = caps.get(0).map_or(0, |m| m.start());
+        let Some(reference) = caps.get(1).or_else(|| caps.get(2)) else {
+            continue;
+        };
+        let index = spans.iter().rposition(|&(s, _)| s <= start).unwrap_or(0);
+        // A citation standing alone after a full stop backs the sentence before it
+        let claim = spans[..=index]
+            .iter()
+            .rev()
+            .map(|&(s, e)| claim_text(&answer[s..e]))
+            .find(|claim| !claim.is_empty())
+            .unwrap_or_default();
+        let citation = Citation {
+            reference: reference.as_str().to_lowercase(),
+            claim,
+        };
+        if !citations.contains(&citation) {
+            citations.push(citation);
+        }
+    }
+    citations
+}
+
+/// Share of `claim`'s significant words found in `memory` (fuzzy, as in
+/// keyword ranking), from 0.0 to 1.0.
+pub fn overlap(claim: &str, memory: &Memory) -> f32 {
+    let words: Vec<String> = claim
+        .split(|c: char| !c.is_alphanumeric())
+        .map(|w| w.to_lowercase())
+        .filter(|w| w.chars().count() >= 3 && !STOPWORDS.contains(&w.as_str()))
+        .collect();
+    if words.is_empty() {
+        return 0.0;
+    }
+    ranking::keyword_score(&words.join(" "), memory)
+}
+
+/// Check every citation in `answer` against the memories `user_id` can see.
+/// With an `embedder`, claims that share few words with their memory can
+/// still pass on embedding similarity.
+pub async fn verify_citations(
+    storage: &Storage,
+    embedder: Option<&EmbeddingService>,
+    user_id: &str,
+    answer: &str,
+) -> Result<Vec<CitationCheck>> {
+    let mut checks = Vec::new();
+    for citation in extract_citations(answer) {
+        let memory = match resolve(storage, &citation.reference, user_id).await? {
+            Ok(memory) => memory,
+            Err(verdict) => {
+                checks.push(CitationCheck {
+                    reference: citation.reference,
+                    claim: citation.claim,
+                    verdict,
+                    memory_id: None,
+                    title: None,
+                    status: None,
+                    overlap: 0.0,
+                    similarity: None,
+                });
+                continue;
+            }
+        };
+
+        let overlap = overlap(&citation.claim, &memory);
+        let similarity = match embedder {
+            Some(embedder) if !citation.claim.is_empty() => {
+                semantic_similarity(embedder, &citation.claim, &memory).await
+            }
+            _ => None,
+        };
+        let verdict = if memory.status != MemoryStatus::Active {
+            CitationVerdict::Inactive
+        } else if overlap >= MIN_OVERLAP || similarity.is_some_and(|s| s >= MIN_SIMILARITY) {
+            CitationVerdict::Supported
+        } else {
+            CitationVerdict::Unsupported
+        };
+        checks.push(CitationCheck {
+            reference: citation.reference,
+            claim: citation.claim,
+            verdict,
+            memory_id: Some(memory.id),
+            title: Some(memory.title),
+            status: Some(memory.status),
+            overlap,
+            similarity,
+        });
+    }
+    Ok(checks)
+}
+
+/// The memory a reference points at, or the verdict explaining why there
+/// isn't exactly one visible memory.
+async fn resolve(
+    storage: &Storage,
+    reference: &str,
+    user_id: &str,
+) -> Result<std::result::Result<Memory, CitationVerdict>> {
+    let id = match Uuid::parse_str(reference) {
+        Ok(id) => id,
+        Err(_) => match storage.find_by_id_prefix(reference, 2).await?.as_slice() {
+            [] => return Ok(Err(CitationVerdict::Missing)),
+            [only] => only.id,
+            _ => return Ok(Err(CitationVerdict::Ambiguous)),
+        },
+    };
+    match storage.get_memory(id).await {
+        Ok(memory) if sharing::is_visible(memory.privacy, &memory.created_by, user_id) => {
+            Ok(Ok(memory))
+        }
+        Ok(_) | Err(ShabkaError::NotFound(_)) => Ok(Err(CitationVerdict::Missing)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Embedding similarity between a claim and a memory; `None` if embedding
+/// fails, so the lexical check still decides.
+async fn semantic_similarity(
+    embedder: &EmbeddingService,
+    claim: &str,
+    memory: &Memory,
+) -> Option<f32> {
+    let claim = embedder.embed(claim).await.ok()?;
+    let memory = embedder.embed(&memory.embedding_text()).await.ok()?;
+    (claim.len() == memory.len()).then(|| similarity::score(&claim, &memory))
+}
+
+/// Byte spans of the sentences in `text`. A sentence ends at a newline or
+/// at `.`, `!` or `?` followed by whitespace.
+fn sentences(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let end = match c {
+            '\n' => Some(i),
+            '.' | '!' | '?' if !matches!(chars.peek(), Some((_, n)) if !n.is_whitespace()) => {
+                Some(i + 1)
+            }
+            _ => None,
+        };
+        if let Some(end) = end {
+            spans.push((start, end));
+            start = i + c.len_utf8();
+        }
+    }
+    spans.push((start, text.len()));
+    spans
+}
+
+/// A sentence with citation markers removed and whitespace collapsed.
+fn claim_text(sentence: &str) -> String {
+    let stripped = CITATION_RE.replace_all(sentence, "");
+    let claim = stripped.split_whitespace().collect::<Vec<_>>().join(" ");
+    claim
+        .trim_matches(|c: char| c.is_ascii_punctuation() || c.is_whitespace())
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::MemoryKind;
+    use crate::storage::SqliteStorage;
+
+    fn memory(title: &str, content: &str) -> Memory {
+        Memory::new(
+            title.to_string(),
+            content.to_string(),
+            MemoryKind::Decision,
+            "test-user".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_extract_citations() {
+        let id = "0192a4b7-c3d4-7e5f-8a9b-0c1d2e3f4a5b";
+        let answer = format!(
+            "Use a pool of 20 connections [0192a4b7]. Retries back off exponentially ({id}).\n\
+             Timeouts are 5 seconds. [^0192A4B8]\n\
+             Plain hex like deadbeef isn't a citation."
+        );
+        let citations = extract_citations(&answer);
+        let found: Vec<(&str, &str)> = citations
+            .iter()
+            .map(|c| (c.reference.as_str(), c.claim.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("0192a4b7", "Use a pool of 20 connections"),
+                (id, "Retries back off exponentially"),
+                ("0192a4b8", "Timeouts are 5 seconds"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overlap() {
+        let m = memory(
+            "Connection pool size",
+            "We settled on a pool of 20 connections after load testing.",
+        );
+        assert_eq!(overlap("Use a pool of 20 connections", &m), 1.0);
+        assert!(overlap("Deploys happen every Friday", &m) < MIN_OVERLAP);
+        assert_eq!(overlap("the and", &m), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_verify_citations() {
+        let storage = Storage::Sqlite(SqliteStorage::open_in_memory().unwrap());
+        let mut pool = memory(
+            "Connection pool size",
+            "We settled on a pool of 20 connections after load testing.",
+        );
+        pool.id = Uuid::parse_str("0aaaaaaa-0000-7000-8000-000000000001").unwrap();
+        let mut old = memory("Old retry policy", "Retries were linear, every second.");
+        old.status = MemoryStatus::Superseded;
+        storage.save_memory(&pool, None).await.unwrap();
+        storage.save_memory(&old, None).await.unwrap();
+
+        let answer = format!(
+            "Use a pool of 20 connections [0aaaaaaa]. Deploys happen on Fridays [{}]. \
+             Retries are linear [{}]. Caches expire hourly [{}].",
+            pool.id,
+            old.id,
+            Uuid::now_v7(),
+        );
+        let checks = verify_citations(&storage, None, "test-user", &answer)
+            .await
+            .unwrap();
+        let verdicts: Vec<CitationVerdict> = checks.iter().map(|c| c.verdict).collect();
+        assert_eq!(
+            verdicts,
+            vec![
+                CitationVerdict::Supported,
+                CitationVerdict::Unsupported,
+                CitationVerdict::Inactive,
+                CitationVerdict::Missing,
+            ]
+        );
+        assert_eq!(checks[0].memory_id, Some(pool.id));
+        assert!(!checks[0].is_flagged());
+        assert_eq!(checks.iter().filter(|c| c.is_flagged()).count(), 3);
+    }
+}
@@ -0,0 +1,466 @@
+//! High-level library API.
+//!
+//! [`Shabka`] wraps the pipeline the CLI and MCP server run by hand —
+//! storage, embeddings, smart dedup, auto-relate, ranking and the history
+//! log — behind four calls, for agents that embed Shabka as a library:
+//!
+//! ```no_run
+//! # async fn demo() -> shabka_core::error::Result<()> {
+//! use shabka_core::client::Shabka;
+//! use shabka_core::model::{CreateMemoryInput, MemoryKind};
+//!
+//! let shabka = Shabka::open(None)?;
+//! let saved = shabka
+//!     .remember(CreateMemoryInput {
+//!         title: "Retry budget".into(),
+//!         content: "Cap retries at three; upstream rate-limits after that.".into(),
+//!         kind: MemoryKind::Decision,
+//!         tags: vec!["http".into()],
+//!         importance: 0.7,
+//!         scope: None,
+//!         related_to: Vec::new(),
+//!         project_id: None,
+//!         privacy: None,
+//!     })
+//!     .await?;
+//! for hit in shabka.recall("retries", 5).await? {
+//!     println!("{:.2} {}", hit.score, hit.memory.title);
+//! }
+//! let pack = shabka.context_pack("retries", 2000).await?;
+//! # let _ = (saved, pack);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::Path;
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::config::ShabkaConfig;
+use crate::context_pack::{build_context_pack, ContextPack, PackFilter};
+use crate::dedup::{self, DedupDecision};
+use crate::embedding;
+use crate::error::Result;
+use crate::graph;
+use crate::history::{EventAction, MemoryEvent};
+use crate::model::*;
+use crate::ranking::RankedResult;
+use crate::retrieval::{self, SearchRequest, SearchResults};
+use crate::services::Services;
+use crate::sharing;
+use crate::storage::StorageBackend;
+use crate::trust;
+
+/// Candidates considered for a context pack.
+const PACK_CANDIDATES: usize = 50;
+
+/// What [`Shabka::remember`] did with a memory.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Remembered {
+    /// Saved as a new memory, linked to `auto_related` similar ones.
+    Added { id: Uuid, auto_related: usize },
+    /// A near-duplicate already exists; it was counted as re-asserted.
+    Skipped { existing_id: Uuid, similarity: f32 },
+    /// Saved, replacing an older version now marked superseded.
+    Superseded {
+        id: Uuid,
+        superseded_id: Uuid,
+        similarity: f32,
+    },
+    /// Merged into an existing memory (needs an LLM).
+    Merged { existing_id: Uuid, similarity: f32 },
+    /// Saved, and linked as contradicting an existing memory.
+    Contradicted {
+        id: Uuid,
+        contradicted_id: Uuid,
+        reason: String,
+    },
+}
+
+impl Remembered {
+    /// The memory that now holds the content.
+    pub fn id(&self) -> Uuid {
+        match self {
+            Self::Added { id, .. }
+            | Self::Superseded { id, .. }
+            | Self::Contradicted { id, .. } => *id,
+            Self::Skipped { existing_id, .. } | Self::Merged { existing_id, .. } => *existing_id,
+        }
+    }
+}
+
+/// Shabka memory for library users.
+#[derive(Debug, Clone)]
+pub struct Shabka {
+    services: Arc<Services>,
+}
+
+impl Shabka {
+    /// Open the store configured for `project_dir` (global config plus that
+    /// directory's `.shabka/` layer), or the global configuration.
+    pub fn open(project_dir: Option<&Path>) -> Result<Self> {
+        Ok(Self::new(ShabkaConfig::load(project_dir)?))
+    }
+
+    /// A client for `config`. Storage and providers open on first use.
+    pub fn new(config: ShabkaConfig) -> Self {
+        Self::with_services(Services::new(config))
+    }
+
+    /// A client over an existing service registry, e.g. one shared with an
+    /// MCP server in the same process.
+    pub fn with_services(services: Arc<Services>) -> Self {
+        Self { services }
+    }
+
+    pub fn services(&self) -> &Arc<Services> {
+        &self.services
+    }
+
+    /// Save a memory the way `save_memory` does: validate, auto-tag (with
+    /// an LLM, when no tags are given), embed, check for duplicates, save,
+    /// link `related_to` and similar memories, and log history.
+    pub async fn remember(&self, input: CreateMemoryInput) -> Result<Remembered> {
+        validate_create_input(&input.title, &input.content, input.importance)?;
+        let config = self.services.config();
+        let storage = self.services.storage()?;
+        let embedder = self.services.embedding()?;
+        let llm = self.services.llm();
+        let history = self.services.history();
+        let user_id = self.services.user_id().to_string();
+
+        let privacy = input
+            .privacy
+            .unwrap_or_else(|| sharing::parse_default_privacy(&config.privacy));
+        let mut memory = Memory::new(input.title, input.content, input.kind, user_id.clone())
+            .with_tags(input.tags)
+            .with_importance(input.importance)
+            .with_privacy(privacy);
+        if let Some(scope) = input.scope {
+            memory = memory.with_scope(scope);
+        }
+        if let Some(project) = input.project_id {
+            memory = memory.with_project(project);
+        }
+        config.labels.apply(&mut memory);
+        crate::devices::stamp(&mut memory);
+
+        if memory.tags.is_empty() {
+            if let Some(llm) = &llm {
+                if let Some(result) = crate::auto_tag::auto_tag(&memory, llm).await {
+                    memory.tags = result.tags;
+                    memory.importance = result.importance;
+                }
+            }
+        }
+
+        let embedding = embedder.embed(&memory.embedding_text()).await?;
+        let decision = dedup::check_duplicate(
+            storage.as_ref(),
+            &embedding,
+            &config.graph,
+            None,
+            llm.as_deref(),
+            &memory.title,
+            &memory.content,
+        )
+        .await;
+
+        let created = |memory: &Memory| {
+            history.log(
+                &MemoryEvent::new(memory.id, EventAction::Created, user_id.clone())
+                    .with_title(&memory.title),
+            )
+        };
+        let outcome = match decision {
+            DedupDecision::Skip {
+                existing_id,
+                similarity,
+                ..
+            } => {
+                // Saving the same fact again corroborates it.
+                let _ = trust::record_assertion(
+                    storage.as_ref(),
+                    existing_id,
+                    Assertion::new(user_id.clone(), "library"),
+                )
+                .await;
+                Remembered::Skipped {
+                    existing_id,
+                    similarity,
+                }
+            }
+            DedupDecision::Update {
+                existing_id,
+                merged_content,
+                merged_title,
+                similarity,
+                ..
+            } => {
+                let merged = storage
+                    .update_memory(
+                        existing_id,
+                        &UpdateMemoryInput {
+                            title: Some(merged_title.clone()),
+                            content: Some(merged_content),
+                            ..Default::default()
+                        },
+                    )
+                    .await?;
+                embedding::refresh_after_edit(
+                    storage.as_ref(),
+                    &embedder,
+                    &config.embedding,
+                    None,
+                    &merged,
+                )
+                .await?;
+                history.log(
+                    &MemoryEvent::new(existing_id, EventAction::Updated, user_id.clone())
+                        .with_title(&merged_title),
+                );
+                Remembered::Merged {
+                    existing_id,
+                    similarity,
+                }
+            }
+            DedupDecision::Supersede {
+                existing_id,
+                existing_title,
+                similarity,
+            } => {
+                storage.save_memory(&memory, Some(&embedding)).await?;
+                let _ = storage
+                    .update_memory(
+                        existing_id,
+                        &UpdateMemoryInput {
+                            status: Some(MemoryStatus::Superseded),
+                            ..Default::default()
+                        },
+                    )
+                    .await;
+                let _ = storage
+                    .add_relation(&MemoryRelation {
+                        source_id: memory.id,
+                        target_id: existing_id,
+                        relation_type: RelationType::Supersedes,
+                        strength: similarity,
+                    })
+                    .await;
+                created(&memory);
+                history.log(
+                    &MemoryEvent::new(existing_id, EventAction::Superseded, user_id.clone())
+                        .with_title(&existing_title),
+                );
+                Remembered::Superseded {
+                    id: memory.id,
+                    superseded_id: existing_id,
+                    similarity,
+                }
+            }
+            DedupDecision::Contradict {
+                existing_id,
+                similarity,
+                reason,
+                ..
+            } => {
+                storage.save_memory(&memory, Some(&embedding)).await?;
+                let _ = storage
+                    .add_relation(&MemoryRelation {
+                        source_id: memory.id,
+                        target_id: existing_id,
+                        relation_type: RelationType::Contradicts,
+                        strength: similarity,
+                    })
+                    .await;
+                created(&memory);
+                Remembered::Contradicted {
+                    id: memory.id,
+                    contradicted_id: existing_id,
+                    reason,
+                }
+            }
+            DedupDecision::Add => {
+                storage.save_memory(&memory, Some(&embedding)).await?;
+                created(&memory);
+                for target_id in &input.related_to {
+                    let _ = self
+                        .relate(memory.id, *target_id, RelationType::Related, 0.5)
+                        .await;
+                }
+                let auto_related = graph::semantic_auto_relate(
+                    storage.as_ref(),
+                    memory.id,
+                    &embedding,
+                    Some(config.graph.similarity_threshold),
+                    Some(config.graph.max_relations),
+                )
+                .await;
+                Remembered::Added {
+                    id: memory.id,
+                    auto_related,
+                }
+            }
+        };
+        Ok(outcome)
+    }
+
+    /// Run `request` as this user against the configured store, demoting
+    /// contradicted results if `[retrieval] demote_contradicted` says so and
+    /// blending in the `[wasm_plugins]` ranker if one is set.
+    pub async fn search(&self, request: SearchRequest) -> Result<SearchResults> {
+        let demote =
+            request.demote_contradicted || self.services.config().retrieval.demote_contradicted;
+        let ranker = request
+            .ranker
+            .clone()
+            .or_else(|| self.services.wasm_ranker());
+        let request = request
+            .visible_to(self.services.user_id())
+            .demote_contradicted(demote)
+            .ranker(ranker);
+        let storage = self.services.storage()?;
+        let embedder = self.services.embedding()?;
+        retrieval::search(storage.as_ref(), &embedder, &request).await
+    }
+
+    /// Hybrid search ranked as `shabka search` ranks it, best first.
+    /// `query` accepts the same field operators (`kind:`, `project:`, ...).
+    pub async fn recall(&self, query: &str, limit: usize) -> Result<Vec<RankedResult>> {
+        Ok(self
+            .search(SearchRequest::parse(query)?.limit(limit))
+            .await?
+            .top())
+    }
+
+    /// Link two memories.
+    pub async fn relate(
+        &self,
+        source_id: Uuid,
+        target_id: Uuid,
+        relation_type: RelationType,
+        strength: f32,
+    ) -> Result<()> {
+        self.services
+            .storage()?
+            .add_relation(&MemoryRelation {
+                source_id,
+                target_id,
+                relation_type,
+                strength,
+            })
+            .await
+    }
+
+    /// The memories most relevant to `query` that fit in `token_budget`,
+    /// with trust scores; format with
+    /// [`format_context_pack`](crate::context_pack::format_context_pack).
+    /// Quarantined and suspicious memories are left out, as are unverified
+    /// captures below `[capture] min_confidence`.
+    pub async fn context_pack(&self, query: &str, token_budget: usize) -> Result<ContextPack> {
+        let request = SearchRequest::parse(query)?
+            .fetch_limit(PACK_CANDIDATES)
+            .filter(PackFilter {
+                exclude_suspicious: true,
+                min_confidence: self.services.config().capture.min_confidence,
+                ..Default::default()
+            });
+        let project = request.query.project.clone();
+        let results = self.search(request).await?;
+        let memories = results.ranked.into_iter().map(|r| r.memory).collect();
+        Ok(build_context_pack(memories, token_budget, project)
+            .with_trust(&results.contradiction_counts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{SqliteStorage, Storage};
+
+    fn test_client() -> Shabka {
+        let storage = Storage::Sqlite(SqliteStorage::open_in_memory().unwrap());
+        let mut config = ShabkaConfig::default_config();
+        config.history.enabled = false;
+        Shabka::with_services(Services::with_storage(config, storage))
+    }
+
+    fn input(title: &str, content: &str) -> CreateMemoryInput {
+        CreateMemoryInput {
+            title: title.into(),
+            content: content.into(),
+            kind: MemoryKind::Fact,
+            tags: vec!["test".into()],
+            importance: 0.5,
+            scope: None,
+            related_to: Vec::new(),
+            project_id: Some("api".into()),
+            privacy: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remember_recall_and_context_pack() {
+        let shabka = test_client();
+        let saved = shabka
+            .remember(input("Retry budget", "Cap retries at three"))
+            .await
+            .unwrap();
+        let Remembered::Added { id, .. } = saved else {
+            panic!("expected Added, got {saved:?}");
+        };
+
+        let again = shabka
+            .remember(input("Retry budget", "Cap retries at three"))
+            .await
+            .unwrap();
+        assert_eq!(again.id(), id);
+        assert!(matches!(again, Remembered::Skipped { .. }));
+
+        let hits = shabka.recall("retry budget", 5).await.unwrap();
+        assert_eq!(hits[0].memory.id, id);
+
+        let pack = shabka
+            .context_pack("project:api retries", 2000)
+            .await
+            .unwrap();
+        assert_eq!(pack.memories[0].id, id);
+        assert!(pack.trust.contains_key(&id));
+    }
+
+    #[tokio::test]
+    async fn test_relate() {
+        let shabka = test_client();
+        let a = shabka
+            .remember(input("Pool exhaustion", "The importer leaks connections"))
+            .await
+            .unwrap()
+            .id();
+        let b = shabka
+            .remember(input(
+                "Batch failures",
+                "Failed batches skip cleanup entirely",
+            ))
+            .await
+            .unwrap()
+            .id();
+        shabka
+            .relate(b, a, RelationType::CausedBy, 0.9)
+            .await
+            .unwrap();
+        let relations = shabka
+            .services()
+            .storage()
+            .unwrap()
+            .get_relations(b)
+            .await
+            .unwrap();
+        assert!(relations
+            .iter()
+            .any(|r| r.target_id == a && r.relation_type == RelationType::CausedBy));
+
+        let err = shabka.remember(input("", "no title")).await.unwrap_err();
+        assert!(err.to_string().contains("title"));
+    }
+}
@@ -0,0 +1,540 @@
+//! Code-aware memory fields.
+//!
+//! Memories captured from edits carry a [`CodeSnippet`] with the language,
+//! file and enclosing symbol. This module derives those fields, prepares the
+//! snippet for embedding (identifiers split into words so `parseConfig`
+//! matches a search for "parse config"), and tokenizes it for highlighting.
+//!
+//! Symbol names mentioned anywhere in a memory are also extracted here
+//! ([`memory_symbols`]) for the storage symbol index behind `symbol:` search.
+
+use std::path::Path;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::model::{CodeSnippet, Memory};
+
+/// Longest snippet kept on a memory, in bytes.
+pub const MAX_SNIPPET_LENGTH: usize = 4_000;
+
+/// Longest slice of a snippet fed to the embedding model, in bytes.
+const MAX_EMBEDDED_SNIPPET: usize = 1_000;
+
+/// Language name for a file path, from its extension.
+pub fn language_for_path(path: &str) -> Option<&'static str> {
+    let path = Path::new(path);
+    if path.file_name().and_then(|n| n.to_str()) == Some("Dockerfile") {
+        return Some("dockerfile");
+    }
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    let language = match ext.as_str() {
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "js" | "mjs" | "cjs" | "jsx" => "javascript",
+        "ts" | "tsx" | "mts" => "typescript",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "swift" => "swift",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "cpp",
+        "cs" => "csharp",
+        "rb" => "ruby",
+        "php" => "php",
+        "sh" | "bash" | "zsh" => "bash",
+        "sql" => "sql",
+        "html" | "htm" => "html",
+        "css" | "scss" => "css",
+        "json" => "json",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "md" => "markdown",
+        _ => return None,
+    };
+    Some(language)
+}
+
+static SYMBOL_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?m)^\s*(?:pub(?:\([a-z]+\))?\s+|export\s+(?:default\s+)?|async\s+|static\s+|public\s+|private\s+|protected\s+|unsafe\s+|const\s+)*(?:fn|def|class|function|func|struct|enum|trait|interface|type|impl|mod)\s+(?:\([^)]*\)\s*)?([A-Za-z_][A-Za-z0-9_]*)",
+    )
+    .unwrap()
+});
+
+/// First symbol defined in `snippet` (function, class, struct, ...), if any.
+pub fn find_symbol(snippet: &str) -> Option<String> {
+    SYMBOL_RE
+        .captures(snippet)
+        .and_then(|caps| caps.get(1))
+        .map(|m| m.as_str().to_string())
+}
+
+impl CodeSnippet {
+    /// Build a snippet for an edit to `path`, inferring language and symbol.
+    /// Returns `None` for blank snippets.
+    pub fn from_edit(path: &str, snippet: &str) -> Option<Self> {
+        if snippet.trim().is_empty() {
+            return None;
+        }
+        let snippet = truncate(snippet, MAX_SNIPPET_LENGTH);
+        Some(Self {
+            language: language_for_path(path).map(str::to_string),
+            file: (!path.is_empty()).then(|| path.to_string()),
+            symbol: find_symbol(snippet),
+            snippet: snippet.to_string(),
+        })
+    }
+}
+
+fn truncate(s: &str, max: usize) -> &str {
+    if s.len() <= max {
+        return s;
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Split an identifier into lowercase words: `parseConfig`, `parse_config`
+/// and `PARSE_CONFIG` all become `parse config`.
+pub fn split_identifier(ident: &str) -> String {
+    let mut words: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let chars: Vec<char> = ident.chars().collect();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' || c == '-' {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        let boundary = c.is_uppercase()
+            && !current.is_empty()
+            && (chars[i - 1].is_lowercase()
+                || chars[i - 1].is_ascii_digit()
+                || chars.get(i + 1).is_some_and(|n| n.is_lowercase()));
+        if boundary {
+            words.push(std::mem::take(&mut current));
+        }
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words.join(" ")
+}
+
+static IDENT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[A-Za-z_][A-Za-z0-9_]*").unwrap());
+
+/// Text to embed for a snippet: its metadata, then the code with every
+/// compound identifier expanded into words alongside the original.
+pub fn embedding_text(code: &CodeSnippet) -> String {
+    let mut lines = Vec::new();
+    if let Some(language) = &code.language {
+        lines.push(format!("language: {language}"));
+    }
+    if let Some(file) = &code.file {
+        lines.push(format!("file: {file}"));
+    }
+    if let Some(symbol) = &code.symbol {
+        lines.push(format!("symbol: {symbol} ({})", split_identifier(symbol)));
+    }
+    let snippet = truncate(&code.snippet, MAX_EMBEDDED_SNIPPET);
+    let expanded = IDENT_RE.replace_all(snippet, |caps: &regex::Captures| {
+        let ident = &caps[0];
+        let words = split_identifier(ident);
+        if words.contains(' ') {
+            format!("{ident} {words}")
+        } else {
+            ident.to_string()
+        }
+    });
+    lines.push(expanded.into_owned());
+    lines.join("\n")
+}
+
+/// Canonical form of a symbol name, used as its index key: the last path
+/// segment, split into words and joined with `_`. `Config::parseFile`,
+/// `parse_file` and `ParseFile` all map to `parse_file`.
+pub fn symbol_key(symbol: &str) -> String {
+    let last = symbol
+        .trim_matches(|c: char| !c.is_alphanumeric() && c != '_')
+        .rsplit([':', '.'])
+        .next()
+        .unwrap_or_default();
+    split_identifier(last).replace(' ', "_")
+}
+
+/// Whether an identifier looks like a code symbol rather than a word:
+/// `snake_case`, `camelCase` or `PascalCase` with an inner capital.
+fn is_compound_identifier(ident: &str) -> bool {
+    let trimmed = ident.trim_matches('_');
+    if trimmed.contains('_') {
+        return trimmed.chars().any(|c| c.is_alphabetic());
+    }
+    let chars: Vec<char> = trimmed.chars().collect();
+    chars
+        .windows(2)
+        .any(|w| w[0].is_lowercase() && w[1].is_uppercase())
+}
+
+/// Symbol names mentioned in free text, as index keys (see [`symbol_key`]).
+///
+/// An identifier counts when it is compound (`parse_config`, `parseConfig`),
+/// is called (`load(`), or is quoted in backticks (`` `Pool` ``). Plain
+/// words are left out so prose doesn't flood the index.
+pub fn symbols_in(text: &str) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    for m in IDENT_RE.find_iter(text) {
+        let ident = m.as_str();
+        if ident.len() < 3 {
+            continue;
+        }
+        let called = text[m.end()..].starts_with('(');
+        let quoted = text[..m.start()].ends_with('`') && text[m.end()..].starts_with('`');
+        if is_compound_identifier(ident) || called || quoted {
+            let key = symbol_key(ident);
+            if !key.is_empty() && !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+    keys
+}
+
+/// Every symbol a memory references: its code snippet's symbol first, then
+/// symbols mentioned in the title, content and snippet.
+pub fn memory_symbols(memory: &Memory) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    let code = memory.code.as_ref();
+    if let Some(symbol) = code.and_then(|c| c.symbol.as_deref()) {
+        keys.push(symbol_key(symbol));
+    }
+    let texts = [
+        memory.title.as_str(),
+        memory.content.as_str(),
+        code.map(|c| c.snippet.as_str()).unwrap_or_default(),
+    ];
+    for key in texts.iter().flat_map(|t| symbols_in(t)) {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+/// How strongly a memory references `symbol`, from 0 to 1: the symbol its
+/// code snippet defines (1.0), a symbol it mentions (0.7), or just the
+/// symbol's words in its text (0.3).
+pub fn symbol_score(memory: &Memory, symbol: &str) -> f32 {
+    let key = symbol_key(symbol);
+    if key.is_empty() {
+        return 0.0;
+    }
+    let defined = memory
+        .code
+        .as_ref()
+        .and_then(|c| c.symbol.as_deref())
+        .is_some_and(|s| symbol_key(s) == key);
+    if defined {
+        return 1.0;
+    }
+    if memory_symbols(memory).contains(&key) {
+        return 0.7;
+    }
+    let words = key.replace('_', " ");
+    let text = format!("{} {}", memory.title, memory.content).to_lowercase();
+    if text.contains(&words) {
+        0.3
+    } else {
+        0.0
+    }
+}
+
+/// Kind of a highlighted token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Plain,
+    Keyword,
+    String,
+    Comment,
+    Number,
+}
+
+const KEYWORDS: &[&str] = &[
+    "as",
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "def",
+    "default",
+    "defer",
+    "do",
+    "elif",
+    "else",
+    "enum",
+    "except",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "fn",
+    "for",
+    "from",
+    "func",
+    "function",
+    "go",
+    "if",
+    "impl",
+    "import",
+    "in",
+    "interface",
+    "let",
+    "loop",
+    "match",
+    "mod",
+    "mut",
+    "new",
+    "nil",
+    "None",
+    "null",
+    "package",
+    "pass",
+    "pub",
+    "raise",
+    "return",
+    "self",
+    "Self",
+    "static",
+    "struct",
+    "switch",
+    "throw",
+    "trait",
+    "True",
+    "False",
+    "true",
+    "try",
+    "type",
+    "use",
+    "var",
+    "where",
+    "while",
+    "with",
+    "yield",
+];
+
+fn comment_prefix(language: Option<&str>) -> &'static str {
+    match language {
+        Some("python" | "ruby" | "bash" | "toml" | "yaml" | "dockerfile") => "#",
+        Some("sql") => "--",
+        _ => "//",
+    }
+}
+
+/// Split one line of code into highlighted tokens.
+///
+/// This is a lightweight lexer for display: it knows line comments, quoted
+/// strings, numbers and a shared keyword list, which is enough to make
+/// snippets readable in a terminal without a full grammar per language.
+pub fn highlight_line<'a>(line: &'a str, language: Option<&str>) -> Vec<(TokenKind, &'a str)> {
+    let comment = comment_prefix(language);
+    let mut tokens = Vec::new();
+    let mut plain_start = 0;
+    let mut i = 0;
+    let bytes = line.as_bytes();
+
+    let flush = |tokens: &mut Vec<(TokenKind, &'a str)>, start: usize, end: usize| {
+        if start < end {
+            tokens.push((TokenKind::Plain, &line[start..end]));
+        }
+    };
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        if line[i..].starts_with(comment) {
+            flush(&mut tokens, plain_start, i);
+            tokens.push((TokenKind::Comment, &line[i..]));
+            return tokens;
+        }
+        if c == b'"' || c == b'\'' || c == b'`' {
+            let mut end = i + 1;
+            while end < bytes.len() && bytes[end] != c {
+                if bytes[end] == b'\\' {
+                    end += 1;
+                }
+                end += 1;
+            }
+            // Rust lifetimes (`'a`) are not char literals.
+            let lifetime = c == b'\'' && language == Some("rust") && end - i > 5;
+            if !lifetime {
+                flush(&mut tokens, plain_start, i);
+                let end = (end + 1).min(bytes.len());
+                tokens.push((TokenKind::String, &line[i..end]));
+                i = end;
+                plain_start = i;
+                continue;
+            }
+        }
+        if c.is_ascii_alphanumeric() || c == b'_' {
+            let mut end = i;
+            while end < bytes.len() && (bytes[end].is_ascii_alphanumeric() || bytes[end] == b'_') {
+                end += 1;
+            }
+            let word = &line[i..end];
+            let kind = if c.is_ascii_digit() {
+                TokenKind::Number
+            } else if KEYWORDS.contains(&word) {
+                TokenKind::Keyword
+            } else {
+                TokenKind::Plain
+            };
+            if kind != TokenKind::Plain {
+                flush(&mut tokens, plain_start, i);
+                tokens.push((kind, word));
+                plain_start = end;
+            }
+            i = end;
+            continue;
+        }
+        i += line[i..].chars().next().map_or(1, char::len_utf8);
+    }
+    flush(&mut tokens, plain_start, bytes.len());
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_and_symbol() {
+        assert_eq!(language_for_path("src/main.rs"), Some("rust"));
+        assert_eq!(language_for_path("app/View.TSX"), Some("typescript"));
+        assert_eq!(language_for_path("README"), None);
+
+        assert_eq!(
+            find_symbol("    pub(crate) async fn load_config(path: &Path) {}").as_deref(),
+            Some("load_config")
+        );
+        assert_eq!(
+            find_symbol("class UserRepo:\n    pass").as_deref(),
+            Some("UserRepo")
+        );
+        assert_eq!(
+            find_symbol("func (s *Server) Handle(w http.ResponseWriter) {").as_deref(),
+            Some("Handle")
+        );
+        assert_eq!(find_symbol("let x = 1;"), None);
+    }
+
+    #[test]
+    fn test_from_edit() {
+        let code = CodeSnippet::from_edit("src/lib.rs", "fn parse_config() {}").unwrap();
+        assert_eq!(code.language.as_deref(), Some("rust"));
+        assert_eq!(code.file.as_deref(), Some("src/lib.rs"));
+        assert_eq!(code.symbol.as_deref(), Some("parse_config"));
+        assert!(CodeSnippet::from_edit("src/lib.rs", "  \n").is_none());
+
+        let long = "é".repeat(MAX_SNIPPET_LENGTH);
+        let code = CodeSnippet::from_edit("a.txt", &long).unwrap();
+        assert!(code.snippet.len() <= MAX_SNIPPET_LENGTH);
+        assert_eq!(code.language, None);
+    }
+
+    #[test]
+    fn test_split_identifier() {
+        assert_eq!(split_identifier("parseConfig"), "parse config");
+        assert_eq!(split_identifier("parse_config"), "parse config");
+        assert_eq!(split_identifier("PARSE_CONFIG"), "parse config");
+        assert_eq!(split_identifier("HTTPServer"), "http server");
+        assert_eq!(split_identifier("utf8Decode"), "utf8 decode");
+        assert_eq!(split_identifier("x"), "x");
+    }
+
+    #[test]
+    fn test_symbol_key() {
+        assert_eq!(symbol_key("parse_config"), "parse_config");
+        assert_eq!(symbol_key("parseConfig"), "parse_config");
+        assert_eq!(symbol_key("Config::parseConfig"), "parse_config");
+        assert_eq!(symbol_key("`self.ParseConfig()`"), "parse_config");
+    }
+
+    #[test]
+    fn test_symbols_in() {
+        let text =
+            "Fixed load_config so retryCount resets; call `Pool` via open( not the Pool word.";
+        assert_eq!(
+            symbols_in(text),
+            vec!["load_config", "retry_count", "pool", "open"]
+        );
+        assert!(symbols_in("The pool ran dry under load").is_empty());
+    }
+
+    #[test]
+    fn test_symbol_score() {
+        let defining = Memory::new(
+            "Raise pool size".into(),
+            "Bumped it".into(),
+            crate::model::MemoryKind::Decision,
+            "u".into(),
+        )
+        .with_code(
+            CodeSnippet::from_edit("src/pool.rs", "fn maxPoolSize() -> u32 { 16 }").unwrap(),
+        );
+        let mentioning = Memory::new(
+            "Pool exhaustion".into(),
+            "max_pool_size was too low".into(),
+            crate::model::MemoryKind::Error,
+            "u".into(),
+        );
+        let prose = Memory::new(
+            "Sizing".into(),
+            "The max pool size matters".into(),
+            crate::model::MemoryKind::Lesson,
+            "u".into(),
+        );
+        assert_eq!(symbol_score(&defining, "max_pool_size"), 1.0);
+        assert_eq!(symbol_score(&mentioning, "maxPoolSize"), 0.7);
+        assert_eq!(symbol_score(&prose, "max_pool_size"), 0.3);
+        assert_eq!(symbol_score(&prose, "open_pool"), 0.0);
+    }
+
+    #[test]
+    fn test_embedding_text_expands_identifiers() {
+        let code =
+            CodeSnippet::from_edit("src/db.rs", "fn openPool() { retry_count += 1; }").unwrap();
+        let text = embedding_text(&code);
+        assert!(text.contains("language: rust"));
+        assert!(text.contains("symbol: openPool (open pool)"));
+        assert!(text.contains("retry_count retry count"));
+    }
+
+    #[test]
+    fn test_highlight_line() {
+        let tokens = highlight_line(r#"let s = "a // b"; // note"#, Some("rust"));
+        assert_eq!(tokens[0], (TokenKind::Keyword, "let"));
+        assert!(tokens.contains(&(TokenKind::String, r#""a // b""#)));
+        assert_eq!(tokens.last(), Some(&(TokenKind::Comment, "// note")));
+        let rebuilt: String = tokens.iter().map(|(_, t)| *t).collect();
+        assert_eq!(rebuilt, r#"let s = "a // b"; // note"#);
+
+        let rust = highlight_line("fn f<'a>(s: &'a str) -> char { 'x' }", Some("rust"));
+        assert!(rust.contains(&(TokenKind::String, "'x'")));
+        assert_eq!(
+            rust.iter().filter(|(k, _)| *k == TokenKind::String).count(),
+            1
+        );
+
+        let py = highlight_line("x = 42  # answer", Some("python"));
+        assert!(py.contains(&(TokenKind::Number, "42")));
+        assert_eq!(py.last(), Some(&(TokenKind::Comment, "# answer")));
+    }
+}
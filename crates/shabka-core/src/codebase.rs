@@ -0,0 +1,352 @@
+//! Checks memories against a working tree for file paths and symbols that no
+//! longer exist.
+//!
+//! A [`Codebase`] indexes the files of a project (tracked and untracked but
+//! not ignored, via git when available) and every identifier in its source
+//! files. Memories that mention paths or symbols missing from that index are
+//! likely describing code that has since been renamed or removed.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::code;
+use crate::model::Memory;
+
+/// Source files larger than this are skipped when indexing symbols.
+const MAX_INDEXED_FILE: u64 = 1_000_000;
+
+/// Directories never descended into when walking a tree without git.
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules", "dist", "build", "vendor"];
+
+static PATH_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z0-9_./~-]*[A-Za-z0-9_-]\.[A-Za-z0-9]+").unwrap());
+
+static IDENT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[A-Za-z_][A-Za-z0-9_]*").unwrap());
+
+/// Referents of a memory that are missing from the codebase.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MissingReferents {
+    /// Referenced paths that are not in the working tree.
+    pub paths: Vec<String>,
+    /// The subset of `paths` that git history shows were deleted.
+    pub deleted: Vec<String>,
+    /// Referenced symbols (as index keys) that no source file contains.
+    pub symbols: Vec<String>,
+}
+
+impl MissingReferents {
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty() && self.symbols.is_empty()
+    }
+}
+
+/// Index of the files and identifiers in a working tree.
+#[derive(Debug, Default)]
+pub struct Codebase {
+    root: PathBuf,
+    files: HashSet<String>,
+    deleted: HashSet<String>,
+    symbols: HashSet<String>,
+}
+
+impl Codebase {
+    /// Index the working tree at `root`.
+    ///
+    /// Inside a git repository the file list comes from `git ls-files` and
+    /// deleted paths from `git log`; elsewhere the directory is walked,
+    /// skipping hidden and build directories.
+    pub fn scan(root: &Path) -> std::io::Result<Self> {
+        let root = root.canonicalize()?;
+        let (files, deleted) = match git_files(&root) {
+            Some(files) => (files, git_deleted(&root)),
+            None => {
+                let mut files = HashSet::new();
+                walk(&root, &root, &mut files)?;
+                (files, HashSet::new())
+            }
+        };
+
+        let mut symbols = HashSet::new();
+        for file in &files {
+            if code::language_for_path(file).is_none() {
+                continue;
+            }
+            let path = root.join(file);
+            let too_large = std::fs::metadata(&path).map_or(true, |m| m.len() > MAX_INDEXED_FILE);
+            if too_large {
+                continue;
+            }
+            let Ok(text) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            let idents: HashSet<&str> = IDENT_RE.find_iter(&text).map(|m| m.as_str()).collect();
+            symbols.extend(idents.into_iter().map(code::symbol_key));
+        }
+
+        Ok(Self::from_parts(root, files, deleted, symbols))
+    }
+
+    /// Build an index from known contents, without touching the filesystem.
+    pub fn from_parts(
+        root: PathBuf,
+        files: HashSet<String>,
+        deleted: HashSet<String>,
+        symbols: HashSet<String>,
+    ) -> Self {
+        Self {
+            root,
+            files,
+            deleted,
+            symbols,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Number of indexed files.
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether git history shows `path` was deleted.
+    pub fn was_deleted(&self, path: &str) -> bool {
+        self.deleted.contains(path)
+    }
+
+    /// Resolve a referenced path to one relative to the root.
+    ///
+    /// Returns `None` for absolute paths outside the root, which belong to
+    /// some other project and can't be checked here.
+    fn relative<'a>(&self, path: &'a str) -> Option<&'a str> {
+        if Path::new(path).is_absolute() {
+            let rest = path.strip_prefix(self.root.to_str()?)?;
+            return Some(rest.trim_start_matches('/'));
+        }
+        Some(path.trim_start_matches("./"))
+    }
+
+    /// Whether a referenced path exists in the tree. Relative paths may name
+    /// a file by any trailing part of its path (`src/main.rs` matches
+    /// `crates/cli/src/main.rs`); directories match any file beneath them.
+    pub fn has_path(&self, path: &str) -> bool {
+        let Some(rel) = self.relative(path) else {
+            return true;
+        };
+        let rel = rel.trim_end_matches('/');
+        if rel.is_empty() || self.files.contains(rel) {
+            return true;
+        }
+        let suffix = format!("/{rel}");
+        let dir = format!("{rel}/");
+        let inner_dir = format!("/{rel}/");
+        self.files
+            .iter()
+            .any(|f| f.ends_with(&suffix) || f.starts_with(&dir) || f.contains(&inner_dir))
+    }
+
+    /// Whether any source file contains the symbol (compared by index key).
+    pub fn has_symbol(&self, symbol: &str) -> bool {
+        self.symbols.contains(&code::symbol_key(symbol))
+    }
+
+    /// Paths and symbols a memory references that are missing from the tree.
+    pub fn missing_referents(&self, memory: &Memory) -> MissingReferents {
+        let mut missing = MissingReferents::default();
+        for path in referenced_paths(memory) {
+            if self.has_path(&path) {
+                continue;
+            }
+            if self
+                .relative(&path)
+                .is_some_and(|rel| self.was_deleted(rel))
+            {
+                missing.deleted.push(path.clone());
+            }
+            missing.paths.push(path);
+        }
+        missing.symbols = code::memory_symbols(memory)
+            .into_iter()
+            .filter(|key| !self.symbols.contains(key))
+            .collect();
+        missing
+    }
+}
+
+/// File paths a memory references: its code snippet's file, then paths with
+/// a known source extension mentioned in the title or content. URLs and bare
+/// domain-like words (`example.com`) are left out.
+pub fn referenced_paths(memory: &Memory) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    if let Some(file) = memory.code.as_ref().and_then(|c| c.file.as_deref()) {
+        paths.push(file.to_string());
+    }
+    for text in [memory.title.as_str(), memory.content.as_str()] {
+        for m in PATH_RE.find_iter(text) {
+            let candidate = m.as_str().trim_end_matches('.');
+            if candidate.starts_with("//") {
+                continue;
+            }
+            if candidate.starts_with('~') || code::language_for_path(candidate).is_none() {
+                continue;
+            }
+            if !paths.iter().any(|p| p == candidate) {
+                paths.push(candidate.to_string());
+            }
+        }
+    }
+    paths
+}
+
+/// Tracked and untracked-but-not-ignored files, or `None` outside a git repo.
+fn git_files(root: &Path) -> Option<HashSet<String>> {
+    let output = Command::new("git")
+        .args(["ls-files", "--cached", "--others", "--exclude-standard"])
+        .current_dir(root)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Some(
+        stdout
+            .lines()
+            .filter(|l| !l.is_empty())
+            .filter(|l| root.join(l).exists())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// Paths that git history shows were deleted at some point.
+fn git_deleted(root: &Path) -> HashSet<String> {
+    let Ok(output) = Command::new("git")
+        .args(["log", "--diff-filter=D", "--name-only", "--pretty=format:"])
+        .current_dir(root)
+        .output()
+    else {
+        return HashSet::new();
+    };
+    if !output.status.success() {
+        return HashSet::new();
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn walk(root: &Path, dir: &Path, files: &mut HashSet<String>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if !SKIPPED_DIRS.contains(&name.as_ref()) {
+                walk(root, &path, files)?;
+            }
+        } else if file_type.is_file() {
+            if let Ok(rel) = path.strip_prefix(root) {
+                files.insert(rel.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{CodeSnippet, MemoryKind};
+
+    fn codebase(files: &[&str], deleted: &[&str], symbols: &[&str]) -> Codebase {
+        Codebase::from_parts(
+            PathBuf::from("/work/app"),
+            files.iter().map(|s| s.to_string()).collect(),
+            deleted.iter().map(|s| s.to_string()).collect(),
+            symbols.iter().map(|s| code::symbol_key(s)).collect(),
+        )
+    }
+
+    fn memory(content: &str) -> Memory {
+        Memory::new(
+            "Note".into(),
+            content.into(),
+            MemoryKind::Fact,
+            "test".into(),
+        )
+    }
+
+    #[test]
+    fn test_referenced_paths() {
+        let mut m = memory(
+            "Config lives in src/config.rs and docs/setup.md; see https://example.com/a.html or example.com.",
+        );
+        m.code = Some(CodeSnippet {
+            file: Some("/work/app/src/main.rs".into()),
+            snippet: "fn main() {}".into(),
+            ..Default::default()
+        });
+        assert_eq!(
+            referenced_paths(&m),
+            vec!["/work/app/src/main.rs", "src/config.rs", "docs/setup.md"]
+        );
+    }
+
+    #[test]
+    fn test_has_path() {
+        let cb = codebase(&["crates/cli/src/main.rs", "README.md"], &[], &[]);
+        assert!(cb.has_path("crates/cli/src/main.rs"));
+        assert!(cb.has_path("./README.md"));
+        assert!(cb.has_path("src/main.rs"));
+        assert!(cb.has_path("crates/cli/"));
+        assert!(cb.has_path("/work/app/README.md"));
+        // Outside the root: not ours to judge.
+        assert!(cb.has_path("/elsewhere/lib.rs"));
+        assert!(!cb.has_path("src/lib.rs"));
+        assert!(!cb.has_path("/work/app/src/lib.rs"));
+    }
+
+    #[test]
+    fn test_missing_referents() {
+        let cb = codebase(&["src/main.rs"], &["src/legacy.rs"], &["parse_config"]);
+        let m = memory(
+            "`parse_config` moved out of src/legacy.rs into src/main.rs; load_settings( is gone.",
+        );
+        let missing = cb.missing_referents(&m);
+        assert_eq!(missing.paths, vec!["src/legacy.rs"]);
+        assert_eq!(missing.deleted, vec!["src/legacy.rs"]);
+        assert_eq!(missing.symbols, vec!["load_settings"]);
+
+        let fine = memory("parseConfig lives in src/main.rs");
+        assert!(cb.missing_referents(&fine).is_empty());
+    }
+
+    #[test]
+    fn test_scan_walks_tree_without_git() {
+        let dir = std::env::temp_dir().join(format!("shabka-codebase-{}", uuid::Uuid::now_v7()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::create_dir_all(dir.join("target")).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "pub fn load_settings() {}").unwrap();
+        std::fs::write(dir.join("target/out.rs"), "fn generated_only() {}").unwrap();
+
+        let cb = Codebase::scan(&dir).unwrap();
+        assert!(cb.has_path("src/lib.rs"));
+        assert!(cb.has_symbol("loadSettings"));
+        assert!(!cb.has_symbol("generated_only"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,22 +2,31 @@
 //!
 //! Finds groups of similar memories via vector search, then uses an LLM to merge each
 //! cluster into a single comprehensive memory. Original memories are superseded.
+//!
+//! Each run that writes summaries is recorded in
+//! `~/.config/shabka/consolidate_runs.json`, so [`undo`] can restore the
+//! sources and retire the summaries later.
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
 use crate::embedding::EmbeddingService;
-use crate::error::Result;
+use crate::error::{Result, ShabkaError};
 use crate::graph;
-use crate::history::{EventAction, HistoryLogger, MemoryEvent};
+use crate::history::{self, EventAction, HistoryLogger, MemoryEvent};
 use crate::llm::LlmService;
+use crate::llm_budget::LlmFeature;
+use crate::maintenance::MaintenanceRun;
 use crate::model::*;
+use crate::plugins::LifecycleEvent;
 use crate::storage::StorageBackend;
 
 /// Raw JSON response from the LLM for consolidation.
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug)]
 struct ConsolidateLlmResponse {
     title: String,
     content: String,
@@ -58,6 +67,36 @@ pub struct ConsolidateConfig {
     /// How often to run auto-consolidation: "daily", "weekly", or "on_startup".
     #[serde(default = "default_interval")]
     pub interval: String,
+    /// Memories with any of these tags are never consolidated.
+    #[serde(default)]
+    pub exclude_tags: Vec<String>,
+    /// Memories of these kinds are never consolidated.
+    #[serde(default)]
+    pub exclude_kinds: Vec<MemoryKind>,
+}
+
+/// Tag that keeps a memory out of consolidation regardless of config.
+pub const PINNED_TAG: &str = "pinned";
+
+/// Verified memories at or above this importance are never consolidated.
+pub const CRITICAL_IMPORTANCE: f32 = 0.9;
+
+impl ConsolidateConfig {
+    /// Whether `memory` must stay out of consolidation: excluded by tag or
+    /// kind, pinned, or verified and critical.
+    pub fn is_excluded(&self, memory: &Memory) -> bool {
+        let tagged = memory.tags.iter().any(|tag| {
+            tag.eq_ignore_ascii_case(PINNED_TAG)
+                || self
+                    .exclude_tags
+                    .iter()
+                    .any(|excluded| tag.eq_ignore_ascii_case(excluded))
+        });
+        tagged
+            || self.exclude_kinds.contains(&memory.kind)
+            || (memory.verification == VerificationStatus::Verified
+                && memory.importance >= CRITICAL_IMPORTANCE)
+    }
 }
 
 fn default_interval() -> String {
@@ -86,17 +125,159 @@ impl Default for ConsolidateConfig {
             min_age_days: default_min_age(),
             auto: false,
             interval: default_interval(),
+            exclude_tags: Vec::new(),
+            exclude_kinds: Vec::new(),
         }
     }
 }
 
 /// Result of a consolidation run.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, schemars::JsonSchema)]
 pub struct ConsolidateResult {
     pub clusters_found: usize,
     pub clusters_consolidated: usize,
     pub memories_superseded: usize,
     pub memories_created: usize,
+    /// Run record for `shabka consolidate undo`; absent for dry runs and
+    /// runs that created nothing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<Uuid>,
+}
+
+impl ConsolidateResult {
+    /// Copy the counts into a maintenance run record.
+    pub fn record_counts(&self, run: &mut MaintenanceRun) {
+        run.count("clusters_found", self.clusters_found);
+        run.count("clusters_consolidated", self.clusters_consolidated);
+        run.count("memories_superseded", self.memories_superseded);
+        run.count("memories_created", self.memories_created);
+    }
+}
+
+/// A summary written by a consolidation run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RunSummary {
+    pub summary_id: Uuid,
+    pub title: String,
+    /// Memories the summary superseded.
+    pub sources: Vec<Uuid>,
+}
+
+/// A consolidation run that changed memories.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ConsolidationRun {
+    pub id: Uuid,
+    pub ran_at: DateTime<Utc>,
+    pub summaries: Vec<RunSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undone_at: Option<DateTime<Utc>>,
+}
+
+/// Persistent record of consolidation runs, oldest first.
+#[derive(Debug, Clone)]
+pub struct ConsolidationLog {
+    path: PathBuf,
+}
+
+impl Default for ConsolidationLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConsolidationLog {
+    pub fn new() -> Self {
+        Self::with_path(
+            dirs::config_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join("shabka")
+                .join("consolidate_runs.json"),
+        )
+    }
+
+    /// A log stored at `path` instead of the config directory.
+    pub fn with_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// All recorded runs, oldest first.
+    pub fn all(&self) -> Vec<ConsolidationRun> {
+        std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    /// The run whose ID starts with `prefix`, or the latest run not yet
+    /// undone when `prefix` is `None`.
+    pub fn find(&self, prefix: Option<&str>) -> Result<ConsolidationRun> {
+        let runs = self.all();
+        match prefix.map(|p| p.trim().to_lowercase()) {
+            None => runs
+                .into_iter()
+                .rev()
+                .find(|run| run.undone_at.is_none())
+                .ok_or_else(|| ShabkaError::NotFound("no consolidation run to undo".into())),
+            Some(prefix) => {
+                let mut matches: Vec<ConsolidationRun> = runs
+                    .into_iter()
+                    .filter(|run| {
+                        run.id.to_string().starts_with(&prefix)
+                            || run.id.simple().to_string().starts_with(&prefix)
+                    })
+                    .collect();
+                match matches.len() {
+                    0 => Err(ShabkaError::NotFound(format!(
+                        "no consolidation run matches '{prefix}'"
+                    ))),
+                    1 => Ok(matches.remove(0)),
+                    n => Err(ShabkaError::InvalidInput(format!(
+                        "'{prefix}' matches {n} consolidation runs; use more characters"
+                    ))),
+                }
+            }
+        }
+    }
+
+    pub fn record(&self, run: &ConsolidationRun) -> std::io::Result<()> {
+        let mut runs = self.all();
+        runs.push(run.clone());
+        self.save(&runs)
+    }
+
+    /// Mark run `id` as undone now.
+    pub fn mark_undone(&self, id: Uuid) -> std::io::Result<()> {
+        let mut runs = self.all();
+        for run in runs.iter_mut().filter(|run| run.id == id) {
+            run.undone_at = Some(Utc::now());
+        }
+        self.save(&runs)
+    }
+
+    fn save(&self, runs: &[ConsolidationRun]) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(runs)? + "\n")
+    }
+}
+
+/// Result of undoing a consolidation run.
+#[derive(Debug, Clone, Serialize, schemars::JsonSchema)]
+pub struct UndoResult {
+    pub run_id: Uuid,
+    /// Superseded sources set back to active.
+    pub memories_restored: usize,
+    pub summaries_archived: usize,
+    pub summaries_deleted: usize,
+    /// Supersedes links removed from archived summaries.
+    pub relations_removed: usize,
+    /// Summaries deleted since the run, so there was nothing to retire.
+    pub summaries_missing: usize,
 }
 
 /// A consolidated memory produced by the LLM.
@@ -130,6 +311,20 @@ pub async fn find_clusters(
     embedding_svc: &EmbeddingService,
     config: &ConsolidateConfig,
 ) -> Vec<Vec<Memory>> {
+    find_scored_clusters(storage, embedding_svc, config)
+        .await
+        .into_iter()
+        .map(|cluster| cluster.into_iter().map(|(memory, _)| memory).collect())
+        .collect()
+}
+
+/// Like [`find_clusters`], pairing each member with its similarity to the
+/// cluster's first memory (1.0 for that memory itself).
+pub async fn find_scored_clusters(
+    storage: &impl StorageBackend,
+    embedding_svc: &EmbeddingService,
+    config: &ConsolidateConfig,
+) -> Vec<Vec<(Memory, f32)>> {
     let cutoff = Utc::now() - chrono::Duration::days(config.min_age_days as i64);
 
     // Fetch all active memories
@@ -153,11 +348,13 @@ pub async fn find_clusters(
     // Only consider active memories old enough
     let eligible: Vec<&Memory> = all_memories
         .iter()
-        .filter(|m| m.status == MemoryStatus::Active && m.created_at < cutoff)
+        .filter(|m| {
+            m.status == MemoryStatus::Active && m.created_at < cutoff && !config.is_excluded(m)
+        })
         .collect();
 
     let mut used: HashSet<Uuid> = HashSet::new();
-    let mut clusters: Vec<Vec<Memory>> = Vec::new();
+    let mut clusters: Vec<Vec<(Memory, f32)>> = Vec::new();
 
     for memory in &eligible {
         if used.contains(&memory.id) {
@@ -178,7 +375,7 @@ pub async fn find_clusters(
             Err(_) => continue,
         };
 
-        let mut cluster: Vec<Memory> = vec![(*memory).clone()];
+        let mut cluster: Vec<(Memory, f32)> = vec![((*memory).clone(), 1.0)];
         for (candidate, score) in results {
             if candidate.id == memory.id || used.contains(&candidate.id) {
                 continue;
@@ -186,17 +383,20 @@ pub async fn find_clusters(
             if score < config.similarity_threshold {
                 continue;
             }
-            if candidate.status != MemoryStatus::Active || candidate.created_at >= cutoff {
+            if candidate.status != MemoryStatus::Active
+                || candidate.created_at >= cutoff
+                || config.is_excluded(&candidate)
+            {
                 continue;
             }
-            cluster.push(candidate);
+            cluster.push((candidate, score));
             if cluster.len() >= config.max_cluster_size {
                 break;
             }
         }
 
         if cluster.len() >= config.min_cluster_size {
-            for m in &cluster {
+            for (m, _) in &cluster {
                 used.insert(m.id);
             }
             clusters.push(cluster);
@@ -206,6 +406,121 @@ pub async fn find_clusters(
     clusters
 }
 
+/// What a cluster would merge, for `shabka consolidate preview`.
+#[derive(Debug, Clone, Serialize, schemars::JsonSchema)]
+pub struct ClusterPreview {
+    /// The cluster's first memory comes first; the rest were found by
+    /// similarity to it.
+    pub members: Vec<ClusterMember>,
+    /// Similarity of the other members to the first.
+    pub similarity: SimilarityStats,
+    pub oldest: DateTime<Utc>,
+    pub newest: DateTime<Utc>,
+    /// Members per kind.
+    pub kinds: BTreeMap<String, usize>,
+}
+
+/// One memory in a [`ClusterPreview`].
+#[derive(Debug, Clone, Serialize, schemars::JsonSchema)]
+pub struct ClusterMember {
+    pub id: Uuid,
+    pub title: String,
+    pub kind: MemoryKind,
+    pub created_at: DateTime<Utc>,
+    pub similarity: f32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, schemars::JsonSchema)]
+pub struct SimilarityStats {
+    pub min: f32,
+    pub mean: f32,
+    pub max: f32,
+}
+
+impl ClusterPreview {
+    /// Summarize a cluster from [`find_scored_clusters`].
+    pub fn from_scored(cluster: &[(Memory, f32)]) -> Self {
+        let others: Vec<f32> = cluster.iter().skip(1).map(|(_, score)| *score).collect();
+        let similarity = if others.is_empty() {
+            SimilarityStats {
+                min: 1.0,
+                mean: 1.0,
+                max: 1.0,
+            }
+        } else {
+            SimilarityStats {
+                min: others.iter().copied().fold(f32::INFINITY, f32::min),
+                mean: others.iter().sum::<f32>() / others.len() as f32,
+                max: others.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+            }
+        };
+        let mut kinds = BTreeMap::new();
+        for (memory, _) in cluster {
+            *kinds.entry(memory.kind.to_string()).or_insert(0) += 1;
+        }
+        let created = cluster.iter().map(|(memory, _)| memory.created_at);
+        Self {
+            members: cluster
+                .iter()
+                .map(|(memory, score)| ClusterMember {
+                    id: memory.id,
+                    title: memory.title.clone(),
+                    kind: memory.kind,
+                    created_at: memory.created_at,
+                    similarity: *score,
+                })
+                .collect(),
+            similarity,
+            oldest: created.clone().min().unwrap_or_else(Utc::now),
+            newest: created.max().unwrap_or_else(Utc::now),
+            kinds,
+        }
+    }
+}
+
+/// Clusters a consolidation run would merge, without calling the LLM.
+pub async fn preview(
+    storage: &impl StorageBackend,
+    embedding_svc: &EmbeddingService,
+    config: &ConsolidateConfig,
+) -> Vec<ClusterPreview> {
+    find_scored_clusters(storage, embedding_svc, config)
+        .await
+        .iter()
+        .map(|cluster| ClusterPreview::from_scored(cluster))
+        .collect()
+}
+
+/// IDs of the memories `memory` was built from: the sources recorded by
+/// consolidation, plus any memory it supersedes according to `relations`
+/// (which also covers summaries from before sources were recorded).
+pub fn source_ids(memory: &Memory, relations: &[MemoryRelation]) -> Vec<Uuid> {
+    let mut ids = match &memory.source {
+        MemorySource::Consolidated { sources } => sources.clone(),
+        _ => Vec::new(),
+    };
+    for relation in relations {
+        if relation.relation_type == RelationType::Supersedes
+            && relation.source_id == memory.id
+            && !ids.contains(&relation.target_id)
+        {
+            ids.push(relation.target_id);
+        }
+    }
+    ids
+}
+
+/// The memories `memory` was built from (see [`source_ids`]), skipping
+/// any that have since been deleted.
+pub async fn sources(storage: &impl StorageBackend, memory: &Memory) -> Result<Vec<Memory>> {
+    let relations = storage.get_relations(memory.id).await?;
+    let ids = source_ids(memory, &relations);
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    storage.get_memories(&ids).await
+}
+
 /// Use LLM to merge a cluster into a single comprehensive memory.
 pub async fn consolidate_cluster(
     cluster: &[Memory],
@@ -225,7 +540,11 @@ pub async fn consolidate_cluster(
     prompt.push_str("Merge these into a single comprehensive memory.");
 
     let response: ConsolidateLlmResponse = llm
-        .generate_structured(&prompt, Some(CONSOLIDATE_SYSTEM_PROMPT))
+        .generate_structured(
+            LlmFeature::Consolidation,
+            &prompt,
+            Some(CONSOLIDATE_SYSTEM_PROMPT),
+        )
         .await
         .map_err(|e| format!("LLM call failed: {e}"))?;
 
@@ -261,6 +580,7 @@ pub async fn consolidate(
     let mut clusters_consolidated = 0;
     let mut memories_superseded = 0;
     let mut memories_created = 0;
+    let mut summaries = Vec::new();
 
     for cluster in &clusters {
         let consolidated = match consolidate_cluster(cluster, llm).await {
@@ -287,8 +607,8 @@ pub async fn consolidate(
         )
         .with_tags(consolidated.tags)
         .with_importance(consolidated.importance)
-        .with_source(MemorySource::AutoCapture {
-            hook: "Consolidation".to_string(),
+        .with_source(MemorySource::Consolidated {
+            sources: cluster.iter().map(|m| m.id).collect(),
         });
 
         // Embed and save
@@ -328,7 +648,9 @@ pub async fn consolidate(
                 relation_type: RelationType::Supersedes,
                 strength: 1.0,
             };
-            let _ = storage.add_relation(&relation).await;
+            if let Err(e) = storage.add_relation(&relation).await {
+                tracing::warn!("failed to link consolidated memory to its source: {e}");
+            }
 
             history.log(
                 &MemoryEvent::new(original.id, EventAction::Superseded, user_id.to_string())
@@ -341,16 +663,148 @@ pub async fn consolidate(
         // Auto-relate the new memory
         graph::semantic_auto_relate(storage, new_memory.id, &embedding, None, None).await;
 
+        summaries.push(RunSummary {
+            summary_id: new_memory.id,
+            title: new_memory.title.clone(),
+            sources: cluster.iter().map(|m| m.id).collect(),
+        });
         clusters_consolidated += 1;
         memories_created += 1;
     }
 
-    Ok(ConsolidateResult {
+    let run_id = if summaries.is_empty() {
+        None
+    } else {
+        let run = ConsolidationRun {
+            id: Uuid::now_v7(),
+            ran_at: Utc::now(),
+            summaries,
+            undone_at: None,
+        };
+        if let Err(e) = ConsolidationLog::new().record(&run) {
+            tracing::warn!("failed to record consolidation run: {e}");
+        }
+        Some(run.id)
+    };
+
+    let result = ConsolidateResult {
         clusters_found,
         clusters_consolidated,
         memories_superseded,
         memories_created,
-    })
+        run_id,
+    };
+    if !dry_run {
+        history
+            .plugins()
+            .emit(&LifecycleEvent::consolidation_done(&result));
+    }
+    Ok(result)
+}
+
+/// Reverse a consolidation run: sources it superseded become active again,
+/// and each summary is archived (with its Supersedes links removed) or,
+/// with `delete`, deleted. Sources that were changed since (archived,
+/// deleted, superseded by something else) are left alone. The run is
+/// marked undone in `log`.
+pub async fn undo(
+    storage: &impl StorageBackend,
+    log: &ConsolidationLog,
+    run: &ConsolidationRun,
+    delete: bool,
+    user_id: &str,
+    history: &HistoryLogger,
+) -> Result<UndoResult> {
+    if run.undone_at.is_some() {
+        return Err(ShabkaError::InvalidInput(format!(
+            "consolidation run {} was already undone",
+            &run.id.simple().to_string()[..8]
+        )));
+    }
+    let mut result = UndoResult {
+        run_id: run.id,
+        memories_restored: 0,
+        summaries_archived: 0,
+        summaries_deleted: 0,
+        relations_removed: 0,
+        summaries_missing: 0,
+    };
+
+    for summary in &run.summaries {
+        let sources = storage.get_memories(&summary.sources).await?;
+        for source in &sources {
+            if source.status != MemoryStatus::Superseded {
+                continue;
+            }
+            // A newer summary (or dedup) may have superseded it since.
+            let relations = storage.get_relations(source.id).await.unwrap_or_default();
+            let superseded_elsewhere = relations.iter().any(|r| {
+                r.relation_type == RelationType::Supersedes
+                    && r.target_id == source.id
+                    && r.source_id != summary.summary_id
+            });
+            if superseded_elsewhere {
+                continue;
+            }
+            let input = UpdateMemoryInput {
+                status: Some(MemoryStatus::Active),
+                ..Default::default()
+            };
+            storage.update_memory(source.id, &input).await?;
+            history.log(
+                &MemoryEvent::new(source.id, EventAction::Updated, user_id.to_string())
+                    .with_title(&source.title)
+                    .with_changes(history::diff_update(source, &input)),
+            );
+            result.memories_restored += 1;
+        }
+
+        let memory = match storage.get_memory(summary.summary_id).await {
+            Ok(memory) => memory,
+            Err(ShabkaError::NotFound(_)) => {
+                result.summaries_missing += 1;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        if delete {
+            storage.delete_memory(memory.id).await?;
+            history.log(
+                &MemoryEvent::new(memory.id, EventAction::Deleted, user_id.to_string())
+                    .with_snapshot(&memory),
+            );
+            result.summaries_deleted += 1;
+            continue;
+        }
+        for source_id in &summary.sources {
+            if storage
+                .remove_relation(memory.id, *source_id, RelationType::Supersedes)
+                .await?
+            {
+                result.relations_removed += 1;
+            }
+        }
+        if memory.status != MemoryStatus::Archived {
+            storage
+                .update_memory(
+                    memory.id,
+                    &UpdateMemoryInput {
+                        status: Some(MemoryStatus::Archived),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            history.log(
+                &MemoryEvent::new(memory.id, EventAction::Archived, user_id.to_string())
+                    .with_title(&memory.title),
+            );
+        }
+        result.summaries_archived += 1;
+    }
+
+    log.mark_undone(run.id)
+        .map_err(|e| ShabkaError::Storage(format!("failed to record undo: {e}")))?;
+    Ok(result)
 }
 
 #[cfg(test)]
@@ -383,6 +837,171 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_source_ids_merge_recorded_and_relations() {
+        let a = Uuid::now_v7();
+        let b = Uuid::now_v7();
+        let c = Uuid::now_v7();
+        let summary = Memory::new("s".into(), "c".into(), MemoryKind::Fact, "alice".into())
+            .with_source(MemorySource::Consolidated {
+                sources: vec![a, b],
+            });
+        let relation = |source_id, target_id, relation_type| MemoryRelation {
+            source_id,
+            target_id,
+            relation_type,
+            strength: 1.0,
+        };
+        let relations = vec![
+            relation(summary.id, b, RelationType::Supersedes),
+            relation(summary.id, c, RelationType::Supersedes),
+            relation(summary.id, Uuid::now_v7(), RelationType::Related),
+            relation(Uuid::now_v7(), summary.id, RelationType::Supersedes),
+        ];
+        assert_eq!(source_ids(&summary, &relations), vec![a, b, c]);
+    }
+
+    fn temp_log() -> ConsolidationLog {
+        ConsolidationLog::with_path(
+            std::env::temp_dir().join(format!("shabka-consolidate-{}.json", Uuid::now_v7())),
+        )
+    }
+
+    fn run(summary_id: Uuid, sources: Vec<Uuid>) -> ConsolidationRun {
+        // Random IDs: two v7 IDs made in the same millisecond share the
+        // 12-character prefix the lookup test uses.
+        ConsolidationRun {
+            id: Uuid::new_v4(),
+            ran_at: Utc::now(),
+            summaries: vec![RunSummary {
+                summary_id,
+                title: "summary".into(),
+                sources,
+            }],
+            undone_at: None,
+        }
+    }
+
+    #[test]
+    fn test_log_finds_latest_open_run() {
+        let log = temp_log();
+        assert!(log.find(None).is_err());
+        let first = run(Uuid::now_v7(), vec![]);
+        let second = run(Uuid::now_v7(), vec![]);
+        log.record(&first).unwrap();
+        log.record(&second).unwrap();
+        assert_eq!(log.find(None).unwrap().id, second.id);
+
+        log.mark_undone(second.id).unwrap();
+        assert_eq!(log.find(None).unwrap().id, first.id);
+        let prefix = second.id.simple().to_string();
+        assert!(log.find(Some(&prefix[..12])).unwrap().undone_at.is_some());
+        assert!(log.find(Some("zzzz")).is_err());
+        let _ = std::fs::remove_file(log.path());
+    }
+
+    #[tokio::test]
+    async fn test_undo_restores_sources_and_archives_summary() {
+        let storage = crate::storage::SqliteStorage::open_in_memory().unwrap();
+        let history = HistoryLogger::new(false);
+        let log = temp_log();
+        let memory =
+            |title: &str| Memory::new(title.into(), "c".into(), MemoryKind::Fact, "alice".into());
+
+        let sources = [memory("a"), memory("b")];
+        let summary = memory("summary").with_source(MemorySource::Consolidated {
+            sources: sources.iter().map(|m| m.id).collect(),
+        });
+        storage.save_memory(&summary, None).await.unwrap();
+        for source in &sources {
+            let mut source = source.clone();
+            source.status = MemoryStatus::Superseded;
+            storage.save_memory(&source, None).await.unwrap();
+            storage
+                .add_relation(&MemoryRelation {
+                    source_id: summary.id,
+                    target_id: source.id,
+                    relation_type: RelationType::Supersedes,
+                    strength: 1.0,
+                })
+                .await
+                .unwrap();
+        }
+        let run = run(summary.id, sources.iter().map(|m| m.id).collect());
+        log.record(&run).unwrap();
+
+        let result = undo(&storage, &log, &run, false, "alice", &history)
+            .await
+            .unwrap();
+        assert_eq!(result.memories_restored, 2);
+        assert_eq!(result.summaries_archived, 1);
+        assert_eq!(result.relations_removed, 2);
+        for source in &sources {
+            let restored = storage.get_memory(source.id).await.unwrap();
+            assert_eq!(restored.status, MemoryStatus::Active);
+        }
+        let archived = storage.get_memory(summary.id).await.unwrap();
+        assert_eq!(archived.status, MemoryStatus::Archived);
+        assert!(storage.get_relations(summary.id).await.unwrap().is_empty());
+
+        let logged = log.find(None);
+        assert!(logged.is_err(), "the only run is undone");
+        let again = log.find(Some(&run.id.to_string())).unwrap();
+        assert!(undo(&storage, &log, &again, false, "alice", &history)
+            .await
+            .is_err());
+        let _ = std::fs::remove_file(log.path());
+    }
+
+    #[test]
+    fn test_is_excluded() {
+        let config = ConsolidateConfig {
+            exclude_tags: vec!["Runbook".into()],
+            exclude_kinds: vec![MemoryKind::Decision],
+            ..Default::default()
+        };
+        let memory = |kind: MemoryKind, tags: &[&str]| {
+            Memory::new("t".into(), "c".into(), kind, "alice".into())
+                .with_tags(tags.iter().map(|t| t.to_string()).collect())
+        };
+        assert!(!config.is_excluded(&memory(MemoryKind::Fact, &["rust"])));
+        assert!(config.is_excluded(&memory(MemoryKind::Fact, &["runbook"])));
+        assert!(config.is_excluded(&memory(MemoryKind::Fact, &["pinned"])));
+        assert!(config.is_excluded(&memory(MemoryKind::Decision, &[])));
+
+        let critical = memory(MemoryKind::Fact, &[])
+            .with_importance(0.95)
+            .with_verification(VerificationStatus::Verified);
+        assert!(config.is_excluded(&critical));
+        let routine = memory(MemoryKind::Fact, &[])
+            .with_importance(0.5)
+            .with_verification(VerificationStatus::Verified);
+        assert!(!config.is_excluded(&routine));
+    }
+
+    #[test]
+    fn test_cluster_preview_stats() {
+        let memory = |title: &str, kind: MemoryKind, days_ago: i64| {
+            let mut m = Memory::new(title.into(), "c".into(), kind, "alice".into());
+            m.created_at = Utc::now() - chrono::Duration::days(days_ago);
+            m
+        };
+        let cluster = vec![
+            (memory("seed", MemoryKind::Fact, 30), 1.0),
+            (memory("a", MemoryKind::Fact, 10), 0.9),
+            (memory("b", MemoryKind::Lesson, 60), 0.7),
+        ];
+        let preview = ClusterPreview::from_scored(&cluster);
+        assert_eq!(preview.members[0].title, "seed");
+        assert!((preview.similarity.min - 0.7).abs() < 1e-6);
+        assert!((preview.similarity.mean - 0.8).abs() < 1e-6);
+        assert!((preview.similarity.max - 0.9).abs() < 1e-6);
+        assert_eq!(preview.oldest, cluster[2].0.created_at);
+        assert_eq!(preview.newest, cluster[1].0.created_at);
+        assert_eq!(preview.kinds["fact"], 2);
+        assert_eq!(preview.kinds["lesson"], 1);
+    }
+
     #[test]
     fn test_consolidate_config_defaults() {
         let config = ConsolidateConfig::default();
@@ -432,4 +1051,30 @@ mod tests {
         assert!(result.tags.is_empty());
         assert!((result.importance - 0.5).abs() < f32::EPSILON);
     }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_consolidate_cluster_with_mock_llm() {
+        use crate::testing::MockLlm;
+
+        let mock = MockLlm::new().reply("not json").reply(
+            r#"{"title":"Pool","content":"Merged","kind":"error","tags":["DB"],"importance":3}"#,
+        );
+        let llm = LlmService::mock(mock.clone());
+        let cluster = vec![
+            Memory::new("Pool A".into(), "a".into(), MemoryKind::Fact, "u".into()),
+            Memory::new("Pool B".into(), "b".into(), MemoryKind::Fact, "u".into()),
+        ];
+
+        let merged = consolidate_cluster(&cluster, &llm).await.unwrap();
+        assert_eq!(merged.kind, MemoryKind::Error);
+        assert_eq!(merged.tags, vec!["db"]);
+        assert!((merged.importance - 1.0).abs() < f32::EPSILON);
+
+        // The unparseable first reply was sent back for repair.
+        let calls = mock.calls();
+        assert_eq!(calls.len(), 2);
+        assert!(calls[0].prompt.contains("Title: Pool B"));
+        assert!(calls[1].prompt.contains("not json"));
+    }
 }
@@ -1,14 +1,67 @@
-use crate::model::Memory;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::model::{Memory, MemoryStatus, VerificationStatus};
+use crate::screen::SUSPICIOUS_TAG;
 use crate::tokens::estimate_memory_tokens;
+use crate::trust;
 use serde::Serialize;
+use uuid::Uuid;
 
 /// A packed set of memories that fits within a token budget.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, schemars::JsonSchema)]
 pub struct ContextPack {
     pub memories: Vec<Memory>,
     pub total_tokens: usize,
     pub budget: usize,
     pub project_id: Option<String>,
+    /// Trust score per memory, when computed with [`ContextPack::with_trust`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub trust: BTreeMap<Uuid, f32>,
+}
+
+impl ContextPack {
+    /// Score each packed memory's trust, given contradiction counts by ID.
+    pub fn with_trust(mut self, contradiction_counts: &HashMap<Uuid, usize>) -> Self {
+        self.trust = self
+            .memories
+            .iter()
+            .map(|m| {
+                let contradictions = contradiction_counts.get(&m.id).copied().unwrap_or(0);
+                (m.id, trust::trust_score(m, contradictions))
+            })
+            .collect();
+        self
+    }
+}
+
+/// Which memories may go into a pack.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PackFilter {
+    /// Only memories whose verification status is `verified`.
+    pub verified_only: bool,
+    /// Leave out quarantined memories and those screening tagged suspicious.
+    pub exclude_suspicious: bool,
+    /// Leave out auto-captured memories with a confidence below this until
+    /// they are verified (`[capture] min_confidence`; 0.0 keeps them all).
+    pub min_confidence: f32,
+}
+
+impl PackFilter {
+    pub fn allows(&self, memory: &Memory) -> bool {
+        if self.verified_only && memory.verification != VerificationStatus::Verified {
+            return false;
+        }
+        if self.exclude_suspicious
+            && (memory.status == MemoryStatus::Quarantined
+                || memory.tags.iter().any(|t| t == SUSPICIOUS_TAG))
+        {
+            return false;
+        }
+        if memory.needs_confirmation(self.min_confidence) {
+            return false;
+        }
+        true
+    }
 }
 
 /// Build a context pack by greedily packing ranked memories into a token budget.
@@ -35,11 +88,22 @@ pub fn build_context_pack(
         total_tokens: total,
         budget: token_budget,
         project_id,
+        trust: BTreeMap::new(),
     }
 }
 
 /// Format a context pack as paste-ready markdown.
 pub fn format_context_pack(pack: &ContextPack) -> String {
+    render(pack, true)
+}
+
+/// [`format_context_pack`] without memory dates, so the text only changes
+/// when the memories themselves do (`--stable-output`).
+pub fn format_context_pack_stable(pack: &ContextPack) -> String {
+    render(pack, false)
+}
+
+fn render(pack: &ContextPack, dated: bool) -> String {
     let mut out = String::new();
 
     // Header
@@ -61,25 +125,50 @@ pub fn format_context_pack(pack: &ContextPack) -> String {
         out.push_str(&format!("## [{}] {}\n", memory.kind, memory.title));
 
         // Metadata line
-        let date = memory.created_at.format("%Y-%m-%d");
+        let date = if dated {
+            format!("{} | ", memory.created_at.format("%Y-%m-%d"))
+        } else {
+            String::new()
+        };
         let tags_str = if memory.tags.is_empty() {
             String::new()
         } else {
             format!(" | tags: {}", memory.tags.join(", "))
         };
         out.push_str(&format!(
-            "*{} | importance: {}{}*\n\n",
+            "*{}importance: {}{}*\n\n",
             date, memory.importance, tags_str,
         ));
 
         // Content
         out.push_str(&memory.content);
-        out.push_str("\n\n");
+        out.push_str(&format!(" [^{}]\n\n", i + 1));
+    }
+
+    // Provenance footnotes
+    for (i, memory) in pack.memories.iter().enumerate() {
+        out.push_str(&format!("[^{}]: {}\n", i + 1, provenance(pack, memory)));
     }
 
     out.trim_end().to_string()
 }
 
+/// Where a memory came from and how far to trust it, for its footnote.
+fn provenance(pack: &ContextPack, memory: &Memory) -> String {
+    let mut parts = vec![
+        format!("source: {}", memory.source),
+        format!("verification: {}", memory.verification),
+    ];
+    if let Some(score) = pack.trust.get(&memory.id) {
+        parts.push(format!("trust: {score:.2}"));
+    }
+    if memory.status == MemoryStatus::Quarantined || memory.tags.iter().any(|t| t == SUSPICIOUS_TAG)
+    {
+        parts.push("flagged by screening".to_string());
+    }
+    parts.join(" | ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -95,6 +184,54 @@ mod tests {
         .with_tags(vec!["test".to_string()])
     }
 
+    /// A memory with a fixed ID and timestamps, for snapshots.
+    fn fixture(n: u128, title: &str, content: &str, kind: MemoryKind, day: u32) -> Memory {
+        use chrono::TimeZone;
+
+        let mut m = Memory::new(title.to_string(), content.to_string(), kind, "alice".into())
+            .with_project("api".to_string());
+        m.id = Uuid::from_u128(n);
+        m.created_at = chrono::Utc
+            .with_ymd_and_hms(2026, 1, day, 9, 30, 0)
+            .unwrap();
+        m.updated_at = m.created_at;
+        m.accessed_at = m.created_at;
+        m
+    }
+
+    fn fixture_pack() -> ContextPack {
+        let pool = fixture(
+            1,
+            "Pool sizing",
+            "Keep the pool at 20 connections; more starves the database.",
+            MemoryKind::Decision,
+            15,
+        )
+        .with_tags(vec!["db".to_string(), "pool".to_string()])
+        .with_importance(0.8);
+        let retry = fixture(
+            2,
+            "Retry budget",
+            "Retry idempotent calls twice with jitter.",
+            MemoryKind::Pattern,
+            16,
+        )
+        .with_tags(vec!["http".to_string()])
+        .with_importance(0.6);
+        build_context_pack(vec![pool, retry], 2000, Some("api".to_string()))
+    }
+
+    #[test]
+    fn test_context_pack_snapshots() {
+        let pack = fixture_pack();
+        insta::assert_snapshot!("context_pack_markdown", format_context_pack_stable(&pack));
+        insta::assert_snapshot!(
+            "context_pack_json",
+            serde_json::to_string_pretty(&pack).unwrap()
+        );
+        assert!(format_context_pack(&pack).contains("*2026-01-15 | importance: 0.8"));
+    }
+
     #[test]
     fn test_build_context_pack_fits_all() {
         let memories = vec![
@@ -222,6 +359,63 @@ mod tests {
         assert!(output.contains("tags: rust, error"));
     }
 
+    #[test]
+    fn test_format_context_pack_provenance_footnotes() {
+        let mut verified = test_memory("Pool size", "Use 20 connections.");
+        verified.verification = VerificationStatus::Verified;
+        let captured = test_memory("Retry", "Retry twice.").with_source(
+            crate::model::MemorySource::AutoCapture {
+                hook: "PostToolUse".to_string(),
+            },
+        );
+        let pack =
+            build_context_pack(vec![verified, captured], 10000, None).with_trust(&HashMap::new());
+        let output = format_context_pack(&pack);
+
+        assert!(output.contains("Use 20 connections. [^1]"));
+        assert!(output.contains("[^1]: source: manual | verification: verified | trust: "));
+        assert!(output.contains(
+            "[^2]: source: auto-capture (PostToolUse) | verification: unverified | trust: "
+        ));
+    }
+
+    #[test]
+    fn test_pack_filter() {
+        let mut verified = test_memory("Verified", "ok");
+        verified.verification = VerificationStatus::Verified;
+        let unverified = test_memory("Unverified", "maybe");
+        let mut suspicious = test_memory("Suspicious", "hmm");
+        suspicious.verification = VerificationStatus::Verified;
+        suspicious.tags.push(SUSPICIOUS_TAG.to_string());
+
+        let all = PackFilter::default();
+        assert!(all.allows(&unverified) && all.allows(&suspicious));
+
+        let verified_only = PackFilter {
+            verified_only: true,
+            ..Default::default()
+        };
+        assert!(verified_only.allows(&verified));
+        assert!(!verified_only.allows(&unverified));
+
+        let safe = PackFilter {
+            exclude_suspicious: true,
+            ..Default::default()
+        };
+        assert!(safe.allows(&unverified));
+        assert!(!safe.allows(&suspicious));
+
+        let confident = PackFilter {
+            min_confidence: 0.5,
+            ..Default::default()
+        };
+        let mut guessed = test_memory("Guessed", "probably").with_confidence(0.3);
+        assert!(confident.allows(&unverified));
+        assert!(!confident.allows(&guessed));
+        guessed.verification = VerificationStatus::Verified;
+        assert!(confident.allows(&guessed));
+    }
+
     #[test]
     fn test_format_context_pack_no_tags() {
         let mut m = Memory::new(
@@ -2,12 +2,23 @@
 //!
 //! Memories that haven't been accessed in a configurable number of days
 //! can be automatically archived. Optionally, their importance can also
-//! be decayed based on how long since they were last accessed.
+//! be decayed based on how long since they were last accessed. Memories that
+//! then stay archived past `delete_after_days` are hard-deleted, so pruning
+//! runs in two phases: archive first, delete after a grace period.
+//!
+//! Per-kind `[retention]` policies add limits on top of inactivity: a maximum
+//! age, a maximum number of active memories, or an exemption from pruning.
+//!
+//! The same staleness signal drives resurfacing: [`pick_resurface`] favours
+//! important memories that nobody has looked at in a long time.
+
+use std::collections::HashMap;
 
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::model::{Memory, MemoryStatus};
+use crate::model::{Memory, MemoryKind, MemoryStatus, UpdateMemoryInput};
 
 /// Default number of days of inactivity before a memory is considered stale.
 const DEFAULT_INACTIVE_DAYS: u64 = 90;
@@ -16,6 +27,114 @@ const DEFAULT_INACTIVE_DAYS: u64 = 90;
 /// After this many days without access, importance is halved.
 const DEFAULT_IMPORTANCE_HALF_LIFE_DAYS: f64 = 30.0;
 
+/// Retention limits for one memory kind.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Archive memories of this kind created more than this many days ago.
+    #[serde(default)]
+    pub max_age_days: Option<u64>,
+    /// Keep at most this many active memories of this kind; the least
+    /// recently accessed beyond the cap are archived.
+    #[serde(default)]
+    pub max_count: Option<usize>,
+    /// Never prune this kind, not even for inactivity. Overrides the limits.
+    #[serde(default)]
+    pub forever: bool,
+    /// Days a verification of this kind holds before it needs renewing.
+    /// Past that, trust decays and `shabka review` lists the memory again.
+    #[serde(default)]
+    pub verification_expires_days: Option<u64>,
+}
+
+/// The `[retention]` config section: one policy per memory kind.
+///
+/// ```toml
+/// [retention]
+/// observation = { max_age_days = 30, max_count = 500 }
+/// decision = { forever = true, verification_expires_days = 180 }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RetentionConfig {
+    pub kinds: HashMap<MemoryKind, RetentionPolicy>,
+}
+
+impl RetentionConfig {
+    pub fn policy(&self, kind: MemoryKind) -> Option<&RetentionPolicy> {
+        self.kinds.get(&kind)
+    }
+
+    /// Whether the kind is exempt from pruning.
+    pub fn keeps_forever(&self, kind: MemoryKind) -> bool {
+        self.policy(kind).is_some_and(|p| p.forever)
+    }
+
+    /// When a verification made at `now` expires, if the kind sets
+    /// `verification_expires_days`.
+    pub fn verification_deadline(
+        &self,
+        kind: MemoryKind,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        let days = self.policy(kind)?.verification_expires_days?;
+        let days = chrono::Duration::try_days(i64::try_from(days).ok()?)?;
+        now.checked_add_signed(days)
+    }
+}
+
+/// Which retention limit a memory exceeds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionLimit {
+    MaxAge { days: u64 },
+    MaxCount { limit: usize },
+}
+
+impl std::fmt::Display for RetentionLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MaxAge { days } => write!(f, "older than {days}d"),
+            Self::MaxCount { limit } => write!(f, "beyond {limit} kept"),
+        }
+    }
+}
+
+/// Active memories that exceed their kind's retention policy.
+pub fn retention_violations(
+    memories: &[Memory],
+    retention: &RetentionConfig,
+    now: DateTime<Utc>,
+) -> HashMap<Uuid, RetentionLimit> {
+    let mut violations = HashMap::new();
+    let mut by_kind: HashMap<MemoryKind, Vec<&Memory>> = HashMap::new();
+    for m in memories.iter().filter(|m| m.status == MemoryStatus::Active) {
+        let Some(policy) = retention.policy(m.kind).filter(|p| !p.forever) else {
+            continue;
+        };
+        if let Some(days) = policy.max_age_days {
+            if (now - m.created_at).num_days().max(0) as u64 >= days {
+                violations.insert(m.id, RetentionLimit::MaxAge { days });
+                continue;
+            }
+        }
+        if policy.max_count.is_some() {
+            by_kind.entry(m.kind).or_default().push(m);
+        }
+    }
+
+    for (kind, mut kept) in by_kind {
+        let Some(limit) = retention.policy(kind).and_then(|p| p.max_count) else {
+            continue;
+        };
+        // Memories already over the age limit were left out above, so they
+        // don't count against the cap.
+        kept.sort_by_key(|m| std::cmp::Reverse(m.accessed_at));
+        for m in kept.into_iter().skip(limit) {
+            violations.insert(m.id, RetentionLimit::MaxCount { limit });
+        }
+    }
+    violations
+}
+
 /// Configuration for the prune operation.
 #[derive(Debug, Clone)]
 pub struct PruneConfig {
@@ -25,6 +144,10 @@ pub struct PruneConfig {
     pub decay_importance: bool,
     /// Half-life in days for importance decay. Default: 30.
     pub importance_half_life_days: f64,
+    /// Days an archived memory is kept before deletion. `None` keeps it forever.
+    pub delete_after_days: Option<u64>,
+    /// Per-kind retention policies.
+    pub retention: RetentionConfig,
 }
 
 impl Default for PruneConfig {
@@ -33,6 +156,8 @@ impl Default for PruneConfig {
             inactive_days: DEFAULT_INACTIVE_DAYS,
             decay_importance: false,
             importance_half_life_days: DEFAULT_IMPORTANCE_HALF_LIFE_DAYS,
+            delete_after_days: None,
+            retention: RetentionConfig::default(),
         }
     }
 }
@@ -42,16 +167,22 @@ impl Default for PruneConfig {
 pub struct PruneAction {
     pub memory_id: Uuid,
     pub title: String,
+    /// Days since last access, or since archival for deletions.
     pub days_inactive: u64,
     pub should_archive: bool,
+    /// Archived past the grace period: delete instead of archiving.
+    pub should_delete: bool,
     pub current_importance: f32,
     pub decayed_importance: Option<f32>,
+    /// Retention limit that triggered archival, if any (otherwise inactivity).
+    pub retention: Option<RetentionLimit>,
 }
 
 /// Summary of a completed prune operation.
 #[derive(Debug, Clone, Default)]
 pub struct PruneResult {
     pub archived: usize,
+    pub deleted: usize,
     pub importance_decayed: usize,
     pub skipped: usize,
     pub errors: usize,
@@ -59,14 +190,37 @@ pub struct PruneResult {
 
 /// Analyze memories and return recommended prune actions.
 ///
-/// Only considers `Active` memories. Already-archived or superseded memories are skipped.
+/// `Active` memories past `inactive_days` are archived. With `delete_after_days`
+/// set, `Archived` memories untouched for that long are deleted; archiving
+/// stamps `updated_at`, so it marks when the grace period started. Superseded
+/// and pending memories are skipped. Memories over their kind's retention
+/// policy are archived regardless of activity; `forever` kinds never are.
 pub fn analyze(memories: &[Memory], config: &PruneConfig, now: DateTime<Utc>) -> Vec<PruneAction> {
+    let violations = retention_violations(memories, &config.retention, now);
     memories
         .iter()
-        .filter(|m| m.status == MemoryStatus::Active)
         .filter_map(|m| {
+            if m.status == MemoryStatus::Archived {
+                let delete_after = config.delete_after_days?;
+                let days_archived = (now - m.updated_at).num_days().max(0) as u64;
+                return (days_archived >= delete_after).then(|| PruneAction {
+                    memory_id: m.id,
+                    title: m.title.clone(),
+                    days_inactive: days_archived,
+                    should_archive: false,
+                    should_delete: true,
+                    current_importance: m.importance,
+                    decayed_importance: None,
+                    retention: None,
+                });
+            }
+            if m.status != MemoryStatus::Active || config.retention.keeps_forever(m.kind) {
+                return None;
+            }
+
             let days_inactive = (now - m.accessed_at).num_days().max(0) as u64;
-            if days_inactive < config.inactive_days {
+            let retention = violations.get(&m.id).copied();
+            if retention.is_none() && days_inactive < config.inactive_days {
                 return None;
             }
 
@@ -85,8 +239,10 @@ pub fn analyze(memories: &[Memory], config: &PruneConfig, now: DateTime<Utc>) ->
                 title: m.title.clone(),
                 days_inactive,
                 should_archive: true,
+                should_delete: false,
                 current_importance: m.importance,
                 decayed_importance: decayed,
+                retention,
             })
         })
         .collect()
@@ -102,6 +258,55 @@ pub fn decayed_importance(importance: f32, days_since_access: f64, half_life_day
     (importance as f64 * decay).clamp(0.0, 1.0) as f32
 }
 
+/// Update that restores an archived memory to active.
+///
+/// The last-access time is reset so the next prune doesn't archive it straight
+/// away. `min_importance` re-boosts importance that prune may have decayed; it
+/// never lowers it.
+pub fn restore_input(
+    memory: &Memory,
+    min_importance: Option<f32>,
+    now: DateTime<Utc>,
+) -> UpdateMemoryInput {
+    let importance = min_importance
+        .map(|min| min.clamp(0.0, 1.0))
+        .filter(|min| *min > memory.importance);
+    UpdateMemoryInput {
+        status: Some(MemoryStatus::Active),
+        importance,
+        accessed_at: Some(now),
+        ..Default::default()
+    }
+}
+
+/// Weight of a memory when resurfacing: importance scaled by how long it has
+/// gone unread, so important and forgotten memories come up most often.
+pub fn resurface_weight(memory: &Memory, now: DateTime<Utc>) -> f64 {
+    let days_inactive = (now - memory.accessed_at).num_days().max(0) as f64;
+    memory.importance.clamp(0.01, 1.0) as f64 * (2.0 + days_inactive).ln()
+}
+
+/// Pick one active memory at random, weighted by [`resurface_weight`].
+///
+/// `roll` is a uniform sample in `[0, 1)`; callers supply the randomness so
+/// the pick is deterministic under test.
+pub fn pick_resurface(memories: &[Memory], now: DateTime<Utc>, roll: f64) -> Option<&Memory> {
+    let candidates: Vec<(&Memory, f64)> = memories
+        .iter()
+        .filter(|m| m.status == MemoryStatus::Active)
+        .map(|m| (m, resurface_weight(m, now)))
+        .collect();
+    let total: f64 = candidates.iter().map(|(_, w)| w).sum();
+    let mut target = roll.clamp(0.0, 1.0) * total;
+    for (memory, weight) in &candidates {
+        if target < *weight {
+            return Some(memory);
+        }
+        target -= weight;
+    }
+    candidates.last().map(|(m, _)| *m)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -129,13 +334,23 @@ mod tests {
             importance,
             status: MemoryStatus::Active,
             privacy: crate::model::MemoryPrivacy::Private,
+            label: None,
+            device_id: None,
+            owner: None,
+            confidence: None,
             verification: crate::model::VerificationStatus::default(),
+            verification_expires_at: None,
+            dispute: None,
             project_id: None,
             session_id: None,
             created_by: "test".to_string(),
             created_at: created,
             updated_at: created,
             accessed_at: accessed,
+            attachments: Vec::new(),
+            code: None,
+            assertions: Vec::new(),
+            endorsements: Vec::new(),
         }
     }
 
@@ -164,7 +379,7 @@ mod tests {
     fn test_decayed_importance_clamped() {
         // Should never go below 0 or above 1
         let result = decayed_importance(1.0, 1000.0, 30.0);
-        assert!(result >= 0.0 && result <= 1.0);
+        assert!((0.0..=1.0).contains(&result));
     }
 
     #[test]
@@ -207,6 +422,8 @@ mod tests {
             inactive_days: 90,
             decay_importance: true,
             importance_half_life_days: 30.0,
+            delete_after_days: None,
+            retention: RetentionConfig::default(),
         };
 
         let memories = vec![test_memory_at(now, "stale", 0.8, 200, 120)];
@@ -236,4 +453,181 @@ mod tests {
         assert!(!config.decay_importance);
         assert!((config.importance_half_life_days - 30.0).abs() < 0.01);
     }
+
+    #[test]
+    fn test_resurface_weight_favours_stale_important() {
+        let now = Utc::now();
+        let fresh = test_memory_at(now, "fresh", 0.9, 10, 0);
+        let forgotten = test_memory_at(now, "forgotten", 0.9, 400, 300);
+        let trivial = test_memory_at(now, "trivial", 0.1, 400, 300);
+        assert!(resurface_weight(&forgotten, now) > resurface_weight(&fresh, now));
+        assert!(resurface_weight(&forgotten, now) > resurface_weight(&trivial, now));
+    }
+
+    #[test]
+    fn test_pick_resurface_uses_roll() {
+        let now = Utc::now();
+        let memories = vec![
+            test_memory_at(now, "first", 0.5, 100, 100),
+            test_memory_at(now, "second", 0.5, 100, 100),
+        ];
+        assert_eq!(pick_resurface(&memories, now, 0.0).unwrap().title, "first");
+        assert_eq!(
+            pick_resurface(&memories, now, 0.99).unwrap().title,
+            "second"
+        );
+    }
+
+    #[test]
+    fn test_pick_resurface_skips_inactive() {
+        let now = Utc::now();
+        let mut archived = test_memory_at(now, "archived", 1.0, 400, 400);
+        archived.status = MemoryStatus::Archived;
+        assert!(pick_resurface(&[archived.clone()], now, 0.5).is_none());
+
+        let active = test_memory_at(now, "active", 0.2, 1, 1);
+        let memories = [archived, active];
+        let picked = pick_resurface(&memories, now, 0.5).unwrap();
+        assert_eq!(picked.title, "active");
+    }
+
+    #[test]
+    fn test_restore_input_reactivates_and_boosts() {
+        let now = Utc::now();
+        let mut memory = test_memory_at(now, "archived", 0.2, 200, 150);
+        memory.status = MemoryStatus::Archived;
+
+        let input = restore_input(&memory, None, now);
+        assert_eq!(input.status, Some(MemoryStatus::Active));
+        assert_eq!(input.accessed_at, Some(now));
+        assert!(input.importance.is_none());
+
+        assert_eq!(restore_input(&memory, Some(0.7), now).importance, Some(0.7));
+        // A boost never lowers importance.
+        assert!(restore_input(&memory, Some(0.1), now).importance.is_none());
+    }
+
+    #[test]
+    fn test_analyze_deletes_long_archived_memories() {
+        let now = Utc::now();
+        let config = PruneConfig {
+            delete_after_days: Some(30),
+            ..Default::default()
+        };
+
+        let mut expired = test_memory_at(now, "expired", 0.5, 400, 200);
+        expired.status = MemoryStatus::Archived;
+        expired.updated_at = now - Duration::days(45);
+        let mut recent = test_memory_at(now, "recently archived", 0.5, 400, 200);
+        recent.status = MemoryStatus::Archived;
+        recent.updated_at = now - Duration::days(5);
+
+        let actions = analyze(&[expired.clone(), recent], &config, now);
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].title, "expired");
+        assert!(actions[0].should_delete);
+        assert!(!actions[0].should_archive);
+        assert_eq!(actions[0].days_inactive, 45);
+
+        // Without a grace period, archives are kept forever.
+        assert!(analyze(&[expired], &PruneConfig::default(), now).is_empty());
+    }
+
+    #[test]
+    fn test_retention_max_age_and_count() {
+        let now = Utc::now();
+        let mut retention = RetentionConfig::default();
+        retention.kinds.insert(
+            MemoryKind::Fact,
+            RetentionPolicy {
+                max_age_days: Some(30),
+                max_count: Some(1),
+                forever: false,
+                verification_expires_days: None,
+            },
+        );
+
+        let old = test_memory_at(now, "old", 0.5, 40, 1);
+        let newest = test_memory_at(now, "newest", 0.5, 5, 0);
+        let older_read = test_memory_at(now, "older read", 0.5, 5, 3);
+        let violations = retention_violations(
+            &[old.clone(), newest.clone(), older_read.clone()],
+            &retention,
+            now,
+        );
+
+        assert_eq!(
+            violations.get(&old.id),
+            Some(&RetentionLimit::MaxAge { days: 30 })
+        );
+        assert!(!violations.contains_key(&newest.id));
+        assert_eq!(
+            violations.get(&older_read.id),
+            Some(&RetentionLimit::MaxCount { limit: 1 })
+        );
+    }
+
+    #[test]
+    fn test_analyze_applies_retention() {
+        let now = Utc::now();
+        let mut retention = RetentionConfig::default();
+        retention.kinds.insert(
+            MemoryKind::Fact,
+            RetentionPolicy {
+                max_age_days: Some(10),
+                ..Default::default()
+            },
+        );
+        retention.kinds.insert(
+            MemoryKind::Decision,
+            RetentionPolicy {
+                forever: true,
+                ..Default::default()
+            },
+        );
+        let config = PruneConfig {
+            retention,
+            ..Default::default()
+        };
+
+        // Recently read, but past the fact age limit.
+        let fact = test_memory_at(now, "fact", 0.5, 20, 1);
+        // Long inactive, but decisions are kept forever.
+        let mut decision = test_memory_at(now, "decision", 0.5, 400, 400);
+        decision.kind = MemoryKind::Decision;
+
+        let actions = analyze(&[fact, decision], &config, now);
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].title, "fact");
+        assert_eq!(
+            actions[0].retention,
+            Some(RetentionLimit::MaxAge { days: 10 })
+        );
+    }
+
+    #[cfg(feature = "runtime")]
+    #[test]
+    fn test_retention_config_toml() {
+        let config: RetentionConfig = toml::from_str(
+            r#"
+observation = { max_age_days = 30, max_count = 500 }
+decision = { forever = true, verification_expires_days = 180 }
+"#,
+        )
+        .unwrap();
+        let obs = config.policy(MemoryKind::Observation).unwrap();
+        assert_eq!(obs.max_count, Some(500));
+        assert!(config.keeps_forever(MemoryKind::Decision));
+        assert!(config.policy(MemoryKind::Fact).is_none());
+
+        let now = Utc::now();
+        assert_eq!(
+            config.verification_deadline(MemoryKind::Decision, now),
+            Some(now + chrono::Duration::days(180))
+        );
+        assert_eq!(
+            config.verification_deadline(MemoryKind::Observation, now),
+            None
+        );
+    }
 }
@@ -10,16 +10,18 @@
 //! - Send new + existing memories to LLM for ADD/UPDATE/SKIP decision
 //! - Falls back to threshold-based on LLM failure
 
+use schemars::JsonSchema;
 use serde::Deserialize;
 use uuid::Uuid;
 
 use crate::config::GraphConfig;
 use crate::llm::LlmService;
+use crate::llm_budget::LlmFeature;
 use crate::model::Memory;
 use crate::storage::StorageBackend;
 
 /// Raw JSON response from the LLM for dedup decisions.
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug)]
 struct DedupLlmResponse {
     decision: String,
     target_id: Option<serde_json::Value>,
@@ -185,7 +187,7 @@ async fn check_duplicate_with_llm(
     let (prompt, id_mapping) = build_dedup_prompt(new_title, new_content, candidates);
 
     let response: DedupLlmResponse = llm
-        .generate_structured(&prompt, Some(DEDUP_SYSTEM_PROMPT))
+        .generate_structured(LlmFeature::Dedup, &prompt, Some(DEDUP_SYSTEM_PROMPT))
         .await
         .map_err(|e| format!("LLM call failed: {e}"))?;
 
@@ -0,0 +1,291 @@
+//! Device identity and per-device statistics.
+//!
+//! Each installation gets a random device ID on first use, stored with a
+//! display name in `~/.config/shabka/device.json`. New memories and history
+//! events record it, so after syncing between machines you can tell which
+//! one produced what, and filter out a machine you no longer trust
+//! (`-device:<id>` in queries, `[devices] exclude` for sync).
+//!
+//! Names of other machines are learned through sync and kept in
+//! `~/.config/shabka/devices.json`.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::model::Memory;
+
+/// Device settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeviceConfig {
+    /// Devices (ID prefixes) whose memories `shabka sync pull` skips.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl DeviceConfig {
+    /// Whether `memory` came from an excluded device.
+    pub fn is_excluded(&self, memory: &Memory) -> bool {
+        self.exclude
+            .iter()
+            .any(|pattern| matches(memory.device_id.as_deref(), pattern))
+    }
+}
+
+/// A machine running Shabka.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Device {
+    pub id: String,
+    pub name: String,
+}
+
+impl Device {
+    /// A new device named after this machine's host name.
+    pub fn generate() -> Self {
+        Self {
+            id: Uuid::now_v7().simple().to_string(),
+            name: host_name(),
+        }
+    }
+}
+
+/// First eight characters of a device ID.
+pub fn short_id(id: &str) -> &str {
+    id.get(..8).unwrap_or(id)
+}
+
+/// Whether `device_id` is the device `pattern` names (an ID prefix of at
+/// least four characters, or the whole ID).
+pub fn matches(device_id: Option<&str>, pattern: &str) -> bool {
+    let pattern = pattern.trim().to_lowercase();
+    match device_id {
+        Some(id) if !pattern.is_empty() => {
+            id == pattern || (pattern.len() >= 4 && id.starts_with(&pattern))
+        }
+        _ => false,
+    }
+}
+
+fn config_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("shabka")
+}
+
+/// This machine's device, created on first use.
+pub fn local() -> &'static Device {
+    static LOCAL: OnceLock<Device> = OnceLock::new();
+    LOCAL.get_or_init(|| load_or_create(&config_dir().join("device.json")))
+}
+
+fn load_or_create(path: &Path) -> Device {
+    if let Some(device) = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+    {
+        return device;
+    }
+    let device = Device::generate();
+    if let Err(e) = save_device(path, &device) {
+        tracing::warn!("devices: failed to save device identity: {e}");
+    }
+    device
+}
+
+fn save_device(path: &Path, device: &Device) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(device)? + "\n")
+}
+
+/// Rename this machine's device.
+pub fn rename_local(name: &str) -> std::io::Result<Device> {
+    let device = Device {
+        id: local().id.clone(),
+        name: name.trim().to_string(),
+    };
+    save_device(&config_dir().join("device.json"), &device)?;
+    Ok(device)
+}
+
+/// Record this machine as the memory's device, unless one is already set.
+pub fn stamp(memory: &mut Memory) {
+    if memory.device_id.is_none() {
+        memory.device_id = Some(local().id.clone());
+    }
+}
+
+fn host_name() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unnamed".to_string())
+}
+
+/// Other machines' names, learned through sync.
+pub struct DeviceRegistry {
+    path: PathBuf,
+}
+
+impl Default for DeviceRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DeviceRegistry {
+    pub fn new() -> Self {
+        Self::with_path(config_dir().join("devices.json"))
+    }
+
+    /// A registry stored at `path` instead of the config directory.
+    pub fn with_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Known devices, this one included.
+    pub fn all(&self) -> Vec<Device> {
+        let mut devices: Vec<Device> = std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        let local = local();
+        devices.retain(|d| d.id != local.id);
+        devices.insert(0, local.clone());
+        devices
+    }
+
+    /// Add or rename `devices`.
+    pub fn remember(&self, devices: &[Device]) -> std::io::Result<()> {
+        let mut known: Vec<Device> = std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        for device in devices {
+            match known.iter_mut().find(|d| d.id == device.id) {
+                Some(existing) => existing.name = device.name.clone(),
+                None => known.push(device.clone()),
+            }
+        }
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&known)? + "\n")
+    }
+}
+
+/// What one device contributed.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct DeviceStats {
+    pub id: String,
+    /// Display name, when the device is known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Whether this is the machine running the command.
+    pub current: bool,
+    pub memories: usize,
+    pub first_capture: DateTime<Utc>,
+    pub last_capture: DateTime<Utc>,
+}
+
+/// Memories per device, most recently active first. `local_id` marks the
+/// current machine. Memories from before device tracking are left out.
+pub fn stats(memories: &[Memory], known: &[Device], local_id: &str) -> Vec<DeviceStats> {
+    let mut by_device: HashMap<&str, DeviceStats> = HashMap::new();
+    for memory in memories {
+        let Some(id) = memory.device_id.as_deref() else {
+            continue;
+        };
+        let entry = by_device.entry(id).or_insert_with(|| DeviceStats {
+            id: id.to_string(),
+            name: known.iter().find(|d| d.id == id).map(|d| d.name.clone()),
+            current: id == local_id,
+            memories: 0,
+            first_capture: memory.created_at,
+            last_capture: memory.created_at,
+        });
+        entry.memories += 1;
+        entry.first_capture = entry.first_capture.min(memory.created_at);
+        entry.last_capture = entry.last_capture.max(memory.created_at);
+    }
+    let mut stats: Vec<DeviceStats> = by_device.into_values().collect();
+    stats.sort_by_key(|s| Reverse(s.last_capture));
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::MemoryKind;
+
+    fn memory(device: Option<&str>) -> Memory {
+        Memory::new(
+            "t".to_string(),
+            "c".to_string(),
+            MemoryKind::Fact,
+            "alice".to_string(),
+        )
+        .with_device(device.map(str::to_string))
+    }
+
+    #[test]
+    fn test_matches_prefix() {
+        let id = "0192a4b7c3d4e5f6a7b8c9d0e1f2a3b4";
+        assert!(matches(Some(id), "0192a4b7"));
+        assert!(matches(Some(id), " 0192A4B7 "));
+        assert!(!matches(Some(id), "019"));
+        assert!(!matches(Some(id), "ffff"));
+        assert!(!matches(None, "0192a4b7"));
+    }
+
+    #[test]
+    fn test_stats_group_by_device() {
+        let memories = vec![
+            memory(Some("aaaa1111")),
+            memory(Some("bbbb2222")),
+            memory(Some("aaaa1111")),
+            memory(None),
+        ];
+        let known = vec![Device {
+            id: "aaaa1111".to_string(),
+            name: "laptop".to_string(),
+        }];
+        let stats = stats(&memories, &known, "bbbb2222");
+        assert_eq!(stats.len(), 2);
+        let laptop = stats.iter().find(|s| s.id == "aaaa1111").unwrap();
+        assert_eq!(laptop.memories, 2);
+        assert_eq!(laptop.name.as_deref(), Some("laptop"));
+        assert!(!laptop.current);
+        assert!(stats
+            .iter()
+            .any(|s| s.id == "bbbb2222" && s.name.is_none() && s.current));
+    }
+
+    #[test]
+    fn test_excluded_devices() {
+        let config = DeviceConfig {
+            exclude: vec!["bbbb".to_string()],
+        };
+        assert!(config.is_excluded(&memory(Some("bbbb2222"))));
+        assert!(!config.is_excluded(&memory(Some("aaaa1111"))));
+        assert!(!config.is_excluded(&memory(None)));
+    }
+
+    #[test]
+    fn test_load_or_create_persists() {
+        let path = std::env::temp_dir().join(format!("shabka-device-{}.json", Uuid::now_v7()));
+        let first = load_or_create(&path);
+        assert_eq!(load_or_create(&path), first);
+        let _ = std::fs::remove_file(&path);
+    }
+}
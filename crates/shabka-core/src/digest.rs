@@ -0,0 +1,420 @@
+//! Activity digest: what happened to the memory base over a period.
+//!
+//! [`build`] reads the history log for a window and summarizes it per
+//! project — memories captured, edited, merged (superseded), archived or
+//! deleted, and new memories that contradict something. [`render_markdown`]
+//! turns that into a short report and [`deliver`] posts it to a webhook
+//! and/or appends it to a file. `shabka digest` runs it on demand; with
+//! `[digest] enabled`, `shabka-web` sends one a day ([`run_scheduled`]).
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{Result, ShabkaError};
+use crate::history::{EventAction, HistoryFilter, HistoryLogger};
+use crate::storage::StorageBackend;
+
+/// Titles of new memories listed per project.
+const MAX_HIGHLIGHTS: usize = 5;
+
+/// Digest settings (`[digest]`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestConfig {
+    /// Send a digest every day while `shabka-web` is running.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Hour of day (UTC, 0-23) the daily digest goes out.
+    #[serde(default = "default_hour")]
+    pub hour: u32,
+    /// URL the digest is POSTed to as JSON (`text` holds the markdown, so
+    /// Slack and Mattermost incoming webhooks work as-is).
+    #[serde(default)]
+    pub webhook: Option<String>,
+    /// File each digest is appended to as markdown.
+    #[serde(default)]
+    pub file: Option<String>,
+}
+
+fn default_hour() -> u32 {
+    8
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hour: default_hour(),
+            webhook: None,
+            file: None,
+        }
+    }
+}
+
+/// Activity in one project (`None` for memories without one).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ProjectDigest {
+    pub project: Option<String>,
+    /// Created or imported.
+    pub captured: usize,
+    pub updated: usize,
+    /// Superseded by a newer or consolidated memory.
+    pub merged: usize,
+    /// Archived or deleted, by pruning or by hand.
+    pub pruned: usize,
+    /// New memories that contradict an existing one.
+    pub contradictions: usize,
+    /// Titles of new memories, newest first.
+    pub highlights: Vec<String>,
+}
+
+impl ProjectDigest {
+    fn total(&self) -> usize {
+        self.captured + self.updated + self.merged + self.pruned
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Digest {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    /// Busiest first; memories without a project last.
+    pub projects: Vec<ProjectDigest>,
+}
+
+impl Digest {
+    pub fn is_empty(&self) -> bool {
+        self.projects.is_empty()
+    }
+}
+
+/// Summarize history events in `[since, until)`.
+pub async fn build(
+    storage: &impl StorageBackend,
+    history: &HistoryLogger,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<Digest> {
+    let filter = HistoryFilter {
+        since: Some(since),
+        until: Some(until),
+        ..Default::default()
+    };
+    let events = history.query(&filter, usize::MAX);
+
+    let ids: Vec<Uuid> = events
+        .iter()
+        .map(|e| e.memory_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let projects: HashMap<Uuid, Option<String>> = storage
+        .get_memories(&ids)
+        .await?
+        .into_iter()
+        .map(|m| (m.id, m.project_id))
+        .collect();
+
+    let mut by_project: BTreeMap<Option<String>, ProjectDigest> = BTreeMap::new();
+    let mut captured: Vec<(Uuid, Option<String>)> = Vec::new();
+    // Events are newest first, so highlights are too.
+    for event in &events {
+        let project = match projects.get(&event.memory_id) {
+            Some(project) => project.clone(),
+            None => event.snapshot.as_ref().and_then(|m| m.project_id.clone()),
+        };
+        let entry = by_project
+            .entry(project.clone())
+            .or_insert_with(|| ProjectDigest {
+                project: project.clone(),
+                ..Default::default()
+            });
+        match event.action {
+            EventAction::Created | EventAction::Imported => {
+                entry.captured += 1;
+                if entry.highlights.len() < MAX_HIGHLIGHTS {
+                    if let Some(title) = &event.memory_title {
+                        entry.highlights.push(title.clone());
+                    }
+                }
+                captured.push((event.memory_id, project));
+            }
+            EventAction::Updated => entry.updated += 1,
+            EventAction::Superseded => entry.merged += 1,
+            EventAction::Archived | EventAction::Deleted => entry.pruned += 1,
+        }
+    }
+
+    let captured_ids: Vec<Uuid> = captured.iter().map(|(id, _)| *id).collect();
+    let contradicted: HashSet<Uuid> = storage
+        .count_contradictions(&captured_ids)
+        .await?
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(id, _)| id)
+        .collect();
+    for (id, project) in captured {
+        if contradicted.contains(&id) {
+            if let Some(entry) = by_project.get_mut(&project) {
+                entry.contradictions += 1;
+            }
+        }
+    }
+
+    let mut projects: Vec<ProjectDigest> = by_project.into_values().collect();
+    projects.sort_by(|a, b| {
+        a.project
+            .is_none()
+            .cmp(&b.project.is_none())
+            .then(b.total().cmp(&a.total()))
+            .then_with(|| a.project.cmp(&b.project))
+    });
+    Ok(Digest {
+        since,
+        until,
+        projects,
+    })
+}
+
+/// The digest as a short markdown report.
+pub fn render_markdown(digest: &Digest) -> String {
+    let mut out = format!(
+        "# Shabka digest — {}\n\n_{} to {} UTC_\n",
+        digest.until.format("%Y-%m-%d"),
+        digest.since.format("%Y-%m-%d %H:%M"),
+        digest.until.format("%Y-%m-%d %H:%M"),
+    );
+    if digest.is_empty() {
+        out.push_str("\nNo activity.\n");
+        return out;
+    }
+    for project in &digest.projects {
+        out.push_str(&format!(
+            "\n## {}\n\n",
+            project.project.as_deref().unwrap_or("(no project)")
+        ));
+        let counts: Vec<String> = [
+            (project.captured, "captured"),
+            (project.updated, "updated"),
+            (project.merged, "merged"),
+            (project.contradictions, "contradicting"),
+            (project.pruned, "archived or deleted"),
+        ]
+        .into_iter()
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, label)| format!("{n} {label}"))
+        .collect();
+        out.push_str(&format!("- {}\n", counts.join(", ")));
+        if !project.highlights.is_empty() {
+            out.push_str(&format!("- New: {}\n", project.highlights.join("; ")));
+        }
+    }
+    out
+}
+
+/// Send `digest` wherever `config` says. Returns where it went.
+pub async fn deliver(digest: &Digest, config: &DigestConfig) -> Result<Vec<String>> {
+    let markdown = render_markdown(digest);
+    let mut delivered = Vec::new();
+
+    if let Some(file) = &config.file {
+        let path = PathBuf::from(file);
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(file_error)?;
+        }
+        let mut f = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(file_error)?;
+        writeln!(f, "{markdown}").map_err(file_error)?;
+        delivered.push(path.display().to_string());
+    }
+
+    if let Some(url) = &config.webhook {
+        reqwest::Client::new()
+            .post(url)
+            .json(&serde_json::json!({ "text": markdown, "digest": digest }))
+            .send()
+            .await?
+            .error_for_status()?;
+        delivered.push(url.clone());
+    }
+
+    Ok(delivered)
+}
+
+fn file_error(e: std::io::Error) -> ShabkaError {
+    ShabkaError::Config(format!("digest file: {e}"))
+}
+
+/// The first `hour`:00 UTC strictly after `now`.
+pub fn next_run(now: DateTime<Utc>, hour: u32) -> DateTime<Utc> {
+    let today = now
+        .date_naive()
+        .and_hms_opt(hour.min(23), 0, 0)
+        .unwrap_or_default()
+        .and_utc();
+    if today > now {
+        today
+    } else {
+        today + Duration::days(1)
+    }
+}
+
+/// Send a digest of the previous 24 hours every day at `config.hour`,
+/// forever. Failures are logged and retried the next day.
+pub async fn run_scheduled<S: StorageBackend + Send + Sync + 'static>(
+    storage: Arc<S>,
+    history: Arc<HistoryLogger>,
+    config: DigestConfig,
+) {
+    loop {
+        let run_at = next_run(Utc::now(), config.hour);
+        let wait = (run_at - Utc::now()).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).await;
+
+        let result = async {
+            let digest = build(
+                storage.as_ref(),
+                &history,
+                run_at - Duration::days(1),
+                run_at,
+            )
+            .await?;
+            deliver(&digest, &config).await
+        }
+        .await;
+        match result {
+            Ok(targets) => tracing::info!("digest: sent to {}", targets.join(", ")),
+            Err(e) => tracing::warn!("digest: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::MemoryEvent;
+    use crate::model::{Memory, MemoryKind, MemoryRelation, RelationType};
+    use crate::storage::SqliteStorage;
+
+    fn memory(title: &str, project: &str) -> Memory {
+        Memory::new(
+            title.to_string(),
+            "Content".to_string(),
+            MemoryKind::Fact,
+            "alice".to_string(),
+        )
+        .with_project(project.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_build_summarizes_per_project() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let path = std::env::temp_dir().join(format!("shabka-digest-{}.jsonl", Uuid::now_v7()));
+        let config = crate::config::HistoryConfig {
+            snapshot_on_delete: true,
+            ..Default::default()
+        };
+        let history = HistoryLogger::from_config_at(&config, path.clone());
+        let now = Utc::now();
+
+        let a = memory("Pool size is 20", "api");
+        let b = memory("Pool size is 50", "api");
+        let c = memory("Use flexbox", "web");
+        for m in [&a, &b, &c] {
+            storage.save_memory(m, None).await.unwrap();
+            history.log(
+                &MemoryEvent::new(m.id, EventAction::Created, "alice".into()).with_title(&m.title),
+            );
+        }
+        storage
+            .add_relation(&MemoryRelation {
+                source_id: b.id,
+                target_id: a.id,
+                relation_type: RelationType::Contradicts,
+                strength: 0.8,
+            })
+            .await
+            .unwrap();
+        history.log(&MemoryEvent::new(
+            c.id,
+            EventAction::Updated,
+            "alice".into(),
+        ));
+        let gone = memory("Old note", "web");
+        history.log(
+            &MemoryEvent::new(gone.id, EventAction::Deleted, "alice".into()).with_snapshot(&gone),
+        );
+        let mut stale = MemoryEvent::new(a.id, EventAction::Superseded, "alice".into());
+        stale.timestamp = now - Duration::days(3);
+        history.log(&stale);
+
+        let digest = build(
+            &storage,
+            &history,
+            now - Duration::days(1),
+            now + Duration::minutes(1),
+        )
+        .await
+        .unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(digest.projects.len(), 2);
+        let web = &digest.projects[0];
+        assert_eq!(web.project.as_deref(), Some("web"), "busiest first");
+        assert_eq!((web.captured, web.updated, web.pruned), (1, 1, 1));
+        let api = &digest.projects[1];
+        assert_eq!(api.project.as_deref(), Some("api"));
+        assert_eq!(api.captured, 2);
+        assert_eq!(api.merged, 0, "outside the window");
+        assert_eq!(api.contradictions, 1);
+        assert_eq!(api.highlights, vec!["Pool size is 50", "Pool size is 20"]);
+
+        let markdown = render_markdown(&digest);
+        assert!(markdown.contains("## api"));
+        assert!(markdown.contains("2 captured, 1 contradicting"));
+        assert!(markdown.contains("1 captured, 1 updated, 1 archived or deleted"));
+    }
+
+    #[tokio::test]
+    async fn test_deliver_appends_to_file() {
+        let dir = std::env::temp_dir().join(format!("shabka-digest-{}", Uuid::now_v7()));
+        let config = DigestConfig {
+            file: Some(dir.join("digest.md").display().to_string()),
+            ..Default::default()
+        };
+        let now = Utc::now();
+        let digest = Digest {
+            since: now - Duration::days(1),
+            until: now,
+            projects: Vec::new(),
+        };
+        deliver(&digest, &config).await.unwrap();
+        let targets = deliver(&digest, &config).await.unwrap();
+        let text = std::fs::read_to_string(dir.join("digest.md")).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(targets.len(), 1);
+        assert_eq!(text.matches("No activity.").count(), 2);
+    }
+
+    #[test]
+    fn test_next_run() {
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        assert_eq!(
+            next_run(at("2025-03-10T06:30:00Z"), 8),
+            at("2025-03-10T08:00:00Z")
+        );
+        assert_eq!(
+            next_run(at("2025-03-10T08:00:00Z"), 8),
+            at("2025-03-11T08:00:00Z")
+        );
+    }
+}
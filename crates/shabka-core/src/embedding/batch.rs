@@ -0,0 +1,399 @@
+//! OpenAI Batch API for large re-embedding jobs.
+//!
+//! Instead of one request per chunk, every text goes into a JSONL file that
+//! is uploaded and run as an asynchronous batch at half the price of the
+//! synchronous endpoint. A batch can take up to a day, so the pending job is
+//! recorded in `~/.config/shabka/embedding_batch.json` and a later run picks
+//! it up instead of submitting a new one.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::config::{self, EmbeddingConfig};
+use crate::error::{Result, ShabkaError};
+
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// Most requests the Batch API accepts in one file.
+pub const MAX_BATCH_REQUESTS: usize = 50_000;
+
+/// Client for submitting embedding batches to an OpenAI-compatible API.
+pub struct OpenAiBatchClient {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+    dimensions: Option<usize>,
+}
+
+/// Where a batch is in its lifecycle.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchStatus {
+    pub id: String,
+    /// `validating`, `in_progress`, `finalizing`, `completed`, `failed`,
+    /// `expired`, `cancelling` or `cancelled`.
+    pub status: String,
+    #[serde(default)]
+    pub output_file_id: Option<String>,
+    #[serde(default)]
+    pub error_file_id: Option<String>,
+    #[serde(default)]
+    pub request_counts: RequestCounts,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RequestCounts {
+    #[serde(default)]
+    pub total: usize,
+    #[serde(default)]
+    pub completed: usize,
+    #[serde(default)]
+    pub failed: usize,
+}
+
+impl BatchStatus {
+    /// Whether the batch has stopped, successfully or not.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.status.as_str(),
+            "completed" | "failed" | "expired" | "cancelled"
+        )
+    }
+}
+
+/// Embeddings from a finished batch, keyed by the request's custom ID.
+#[derive(Debug, Default)]
+pub struct BatchResults {
+    pub embeddings: HashMap<String, Vec<f32>>,
+    /// Requests that failed, with the error message.
+    pub errors: Vec<(String, String)>,
+}
+
+impl OpenAiBatchClient {
+    /// Build a client from the `[embedding]` config. Only the `openai`
+    /// provider has a batch endpoint.
+    pub fn from_config(config: &EmbeddingConfig) -> Result<Self> {
+        if config.provider != "openai" {
+            return Err(ShabkaError::Config(format!(
+                "the batch API needs the 'openai' embedding provider (configured: '{}')",
+                config.provider
+            )));
+        }
+        let api_key = config::resolve_api_key(
+            config.api_key.as_deref(),
+            config.env_var.as_deref(),
+            "OPENAI_API_KEY",
+            "openai",
+            "embedding",
+        )?;
+        Ok(Self {
+            http: reqwest::Client::new(),
+            base_url: config
+                .base_url
+                .clone()
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            api_key,
+            model: config.model.clone(),
+            dimensions: config.dimensions,
+        })
+    }
+
+    /// Upload `items` (custom ID, text) and start a batch. Returns the batch ID.
+    pub async fn submit(&self, items: &[(String, String)]) -> Result<String> {
+        if items.len() > MAX_BATCH_REQUESTS {
+            return Err(ShabkaError::InvalidInput(format!(
+                "a batch holds at most {MAX_BATCH_REQUESTS} requests, got {}",
+                items.len()
+            )));
+        }
+        let jsonl = build_requests(&self.model, self.dimensions, items)?;
+        let file_id = self.upload(&jsonl).await?;
+
+        let body = json!({
+            "input_file_id": file_id,
+            "endpoint": "/v1/embeddings",
+            "completion_window": "24h",
+            "metadata": { "source": "shabka reembed" },
+        });
+        let resp = self
+            .http
+            .post(format!("{}/batches", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&body)
+            .send()
+            .await?;
+        let status: BatchStatus = parse_response("create batch", resp).await?;
+        Ok(status.id)
+    }
+
+    /// Current state of a batch.
+    pub async fn status(&self, batch_id: &str) -> Result<BatchStatus> {
+        let resp = self
+            .http
+            .get(format!("{}/batches/{batch_id}", self.base_url))
+            .bearer_auth(&self.api_key)
+            .send()
+            .await?;
+        parse_response("batch status", resp).await
+    }
+
+    /// Download and parse a finished batch's output and error files.
+    pub async fn results(&self, status: &BatchStatus) -> Result<BatchResults> {
+        let mut results = BatchResults::default();
+        for file_id in [&status.output_file_id, &status.error_file_id]
+            .into_iter()
+            .flatten()
+        {
+            let text = self.download(file_id).await?;
+            let parsed = parse_results(&text);
+            results.embeddings.extend(parsed.embeddings);
+            results.errors.extend(parsed.errors);
+        }
+        Ok(results)
+    }
+
+    async fn upload(&self, jsonl: &str) -> Result<String> {
+        // reqwest is built without multipart support; the form is small
+        // enough to write by hand.
+        let boundary = format!("shabka-{}", uuid::Uuid::now_v7().simple());
+        let body = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"purpose\"\r\n\r\nbatch\r\n\
+             --{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"shabka-reembed.jsonl\"\r\n\
+             Content-Type: application/jsonl\r\n\r\n{jsonl}\r\n--{boundary}--\r\n"
+        );
+        let resp = self
+            .http
+            .post(format!("{}/files", self.base_url))
+            .bearer_auth(&self.api_key)
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={boundary}"),
+            )
+            .body(body)
+            .send()
+            .await?;
+
+        #[derive(Deserialize)]
+        struct FileObject {
+            id: String,
+        }
+        let file: FileObject = parse_response("file upload", resp).await?;
+        Ok(file.id)
+    }
+
+    async fn download(&self, file_id: &str) -> Result<String> {
+        let resp = self
+            .http
+            .get(format!("{}/files/{file_id}/content", self.base_url))
+            .bearer_auth(&self.api_key)
+            .send()
+            .await?;
+        let status = resp.status();
+        let body = resp.text().await?;
+        if !status.is_success() {
+            return Err(ShabkaError::provider(
+                "embedding",
+                "openai",
+                format!("API error {status} downloading batch file: {body}"),
+            ));
+        }
+        Ok(body)
+    }
+}
+
+async fn parse_response<R: serde::de::DeserializeOwned>(
+    what: &str,
+    resp: reqwest::Response,
+) -> Result<R> {
+    let status = resp.status();
+    let body = resp.text().await?;
+    if !status.is_success() {
+        return Err(ShabkaError::provider(
+            "embedding",
+            "openai",
+            format!("API error {status} ({what}): {body}"),
+        ));
+    }
+    serde_json::from_str(&body)
+        .map_err(|e| ShabkaError::Embedding(format!("unexpected {what} response: {e}")))
+}
+
+/// The batch input file: one embeddings request per line.
+pub fn build_requests(
+    model: &str,
+    dimensions: Option<usize>,
+    items: &[(String, String)],
+) -> Result<String> {
+    let mut jsonl = String::new();
+    for (custom_id, text) in items {
+        let mut body = json!({ "model": model, "input": text });
+        if let Some(dimensions) = dimensions {
+            body["dimensions"] = json!(dimensions);
+        }
+        let line = json!({
+            "custom_id": custom_id,
+            "method": "POST",
+            "url": "/v1/embeddings",
+            "body": body,
+        });
+        jsonl.push_str(&serde_json::to_string(&line)?);
+        jsonl.push('\n');
+    }
+    Ok(jsonl)
+}
+
+/// Parse a batch output or error file.
+pub fn parse_results(text: &str) -> BatchResults {
+    #[derive(Deserialize)]
+    struct Line {
+        custom_id: String,
+        #[serde(default)]
+        response: Option<Response>,
+        #[serde(default)]
+        error: Option<serde_json::Value>,
+    }
+    #[derive(Deserialize)]
+    struct Response {
+        status_code: u16,
+        body: serde_json::Value,
+    }
+
+    let mut results = BatchResults::default();
+    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+        let Ok(line) = serde_json::from_str::<Line>(line) else {
+            continue;
+        };
+        let embedding = line
+            .response
+            .as_ref()
+            .filter(|r| r.status_code == 200)
+            .and_then(|r| r.body.pointer("/data/0/embedding"))
+            .and_then(|v| v.as_array())
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|x| x.as_f64().map(|x| x as f32))
+                    .collect::<Vec<f32>>()
+            });
+        match embedding {
+            Some(embedding) if !embedding.is_empty() => {
+                results.embeddings.insert(line.custom_id, embedding);
+            }
+            _ => {
+                let message = line
+                    .error
+                    .as_ref()
+                    .or_else(|| line.response.as_ref().map(|r| &r.body))
+                    .and_then(|v| {
+                        v.pointer("/error/message")
+                            .or_else(|| v.get("message"))
+                            .and_then(|m| m.as_str())
+                    })
+                    .unwrap_or("no embedding in response")
+                    .to_string();
+                results.errors.push((line.custom_id, message));
+            }
+        }
+    }
+    results
+}
+
+/// A submitted batch not yet applied, so an interrupted run can resume it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PendingBatch {
+    pub id: String,
+    pub submitted_at: String,
+    /// Provider/model the batch embeds with; a batch for another model is
+    /// not resumed.
+    pub model: String,
+    pub requests: usize,
+}
+
+impl PendingBatch {
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|p| p.join("shabka").join("embedding_batch.json"))
+    }
+
+    pub fn load() -> Option<Self> {
+        let text = std::fs::read_to_string(Self::path()?).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()
+            .ok_or_else(|| ShabkaError::Config("cannot determine config directory".to_string()))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| ShabkaError::Config(format!("failed to create config dir: {e}")))?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| ShabkaError::Config(format!("failed to record pending batch: {e}")))
+    }
+
+    pub fn clear() {
+        if let Some(path) = Self::path() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_requests() {
+        let items = vec![
+            ("a".to_string(), "first text".to_string()),
+            ("b".to_string(), "second".to_string()),
+        ];
+        let jsonl = build_requests("text-embedding-3-small", Some(512), &items).unwrap();
+        let lines: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["custom_id"], "a");
+        assert_eq!(lines[0]["url"], "/v1/embeddings");
+        assert_eq!(lines[0]["body"]["input"], "first text");
+        assert_eq!(lines[1]["body"]["dimensions"], 512);
+
+        let plain = build_requests("m", None, &items[..1]).unwrap();
+        assert!(!plain.contains("dimensions"));
+    }
+
+    #[test]
+    fn test_parse_results() {
+        let text = r#"{"custom_id":"a","response":{"status_code":200,"body":{"data":[{"embedding":[0.5,-0.25]}]}},"error":null}
+{"custom_id":"b","response":{"status_code":400,"body":{"error":{"message":"input too long"}}},"error":null}
+{"custom_id":"c","response":null,"error":{"code":"expired","message":"batch expired"}}
+not json
+"#;
+        let results = parse_results(text);
+        assert_eq!(results.embeddings.get("a"), Some(&vec![0.5, -0.25]));
+        assert_eq!(results.errors.len(), 2);
+        assert_eq!(
+            results.errors[0],
+            ("b".to_string(), "input too long".to_string())
+        );
+        assert_eq!(
+            results.errors[1],
+            ("c".to_string(), "batch expired".to_string())
+        );
+    }
+
+    #[test]
+    fn test_requires_openai() {
+        let config = EmbeddingConfig {
+            provider: "ollama".to_string(),
+            ..Default::default()
+        };
+        let err = OpenAiBatchClient::from_config(&config).err().unwrap();
+        assert!(err.to_string().contains("openai"));
+    }
+}
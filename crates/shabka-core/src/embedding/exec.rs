@@ -0,0 +1,156 @@
+use std::process::Stdio;
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+
+use super::provider::EmbeddingProvider;
+use crate::error::{Result, ShabkaError};
+
+/// Embedding provider that runs a user-supplied command per text.
+///
+/// The command gets the text on stdin and must print the embedding as a
+/// JSON array of numbers on stdout, e.g. `[0.12, -0.03, ...]`. It runs
+/// through the shell (`sh -c`, or `cmd /C` on Windows), so arguments and
+/// pipes work. Vectors must have the declared number of dimensions.
+pub struct ExecEmbeddingProvider {
+    command: String,
+    dimensions: usize,
+    model_id: String,
+    timeout: Duration,
+}
+
+/// How long one invocation may take before it's killed.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+impl ExecEmbeddingProvider {
+    pub fn new(command: impl Into<String>, dimensions: usize, model_id: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            dimensions,
+            model_id: model_id.into(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    fn shell(&self) -> tokio::process::Command {
+        #[cfg(windows)]
+        let mut cmd = {
+            let mut cmd = tokio::process::Command::new("cmd");
+            cmd.arg("/C").arg(&self.command);
+            cmd
+        };
+        #[cfg(not(windows))]
+        let mut cmd = {
+            let mut cmd = tokio::process::Command::new("sh");
+            cmd.arg("-c").arg(&self.command);
+            cmd
+        };
+        cmd.stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        cmd
+    }
+
+    async fn run(&self, text: &str) -> Result<Vec<f32>> {
+        let mut child = self.shell().spawn().map_err(|e| {
+            ShabkaError::Embedding(format!("failed to run '{}': {e}", self.command))
+        })?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A command that exits without reading its input is reported by
+            // its exit status below, not as a broken pipe here.
+            if let Err(e) = stdin.write_all(text.as_bytes()).await {
+                if e.kind() != std::io::ErrorKind::BrokenPipe {
+                    return Err(ShabkaError::Embedding(format!(
+                        "failed to write to command: {e}"
+                    )));
+                }
+            }
+            // Dropping stdin closes it so the command sees end of input.
+        }
+        let output = tokio::time::timeout(self.timeout, child.wait_with_output())
+            .await
+            .map_err(|_| {
+                ShabkaError::Embedding(format!(
+                    "embedding command timed out after {}s",
+                    self.timeout.as_secs()
+                ))
+            })?
+            .map_err(|e| ShabkaError::Embedding(format!("embedding command failed: {e}")))?;
+        if !output.status.success() {
+            return Err(ShabkaError::Embedding(format!(
+                "embedding command exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        parse_vector(&String::from_utf8_lossy(&output.stdout), self.dimensions)
+    }
+}
+
+/// Parse a command's output into a vector of the expected size.
+fn parse_vector(stdout: &str, dimensions: usize) -> Result<Vec<f32>> {
+    let vector: Vec<f32> = serde_json::from_str(stdout.trim()).map_err(|e| {
+        ShabkaError::Embedding(format!(
+            "embedding command must print a JSON array of numbers: {e}"
+        ))
+    })?;
+    if vector.len() != dimensions {
+        return Err(ShabkaError::Embedding(format!(
+            "embedding command returned {} dimensions, expected {dimensions} (embedding.dimensions)",
+            vector.len()
+        )));
+    }
+    Ok(vector)
+}
+
+impl EmbeddingProvider for ExecEmbeddingProvider {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.run(text).await
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        for text in texts {
+            vectors.push(self.run(text).await?);
+        }
+        Ok(vectors)
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vector() {
+        assert_eq!(
+            parse_vector("[0.5, -1, 2e-1]\n", 3).unwrap(),
+            vec![0.5, -1.0, 0.2]
+        );
+        let err = parse_vector("[0.5]", 3).unwrap_err().to_string();
+        assert!(err.contains("returned 1 dimensions"));
+        assert!(parse_vector("not json", 3).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_runs_command_with_stdin() {
+        // Prints the input length and a constant, proving stdin arrived.
+        let provider =
+            ExecEmbeddingProvider::new(r#"n=$(wc -c | tr -d ' '); echo "[$n, 1]""#, 2, "test");
+        assert_eq!(provider.embed("hello").await.unwrap(), vec![5.0, 1.0]);
+
+        let failing = ExecEmbeddingProvider::new("echo oops >&2; exit 3", 2, "test");
+        let err = failing.embed("x").await.unwrap_err().to_string();
+        assert!(err.contains("oops"));
+    }
+}
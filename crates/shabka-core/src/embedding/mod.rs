@@ -1,13 +1,18 @@
 mod provider;
 
+pub mod batch;
+mod exec;
 mod hash;
 
+pub use exec::ExecEmbeddingProvider;
 pub use hash::HashEmbeddingProvider;
 pub use provider::EmbeddingProvider;
 
 use crate::config::{self, EmbeddingConfig};
 use crate::error::{Result, ShabkaError};
+use crate::model::Memory;
 use crate::retry::with_retry;
+use crate::storage::StorageBackend;
 use std::future::Future;
 use std::pin::Pin;
 
@@ -70,6 +75,11 @@ enum EmbeddingInner {
     Rig(Box<dyn RigEmbedAdapter>),
     /// Local deterministic hash provider (no network).
     Hash(HashEmbeddingProvider),
+    /// User-supplied command (`exec` provider).
+    Exec(ExecEmbeddingProvider),
+    /// Scripted test double (`testing` feature).
+    #[cfg(feature = "testing")]
+    Mock(crate::testing::MockEmbedding),
 }
 
 /// Concrete embedding service that dispatches to the configured provider.
@@ -92,7 +102,7 @@ impl EmbeddingService {
     pub fn from_config(config: &EmbeddingConfig) -> Result<Self> {
         match config.provider.as_str() {
             "local" => Err(ShabkaError::Config(
-                "local embedding provider has been removed; use 'ollama', 'openai', 'gemini', 'cohere', or 'hash'".into(),
+                "local embedding provider has been removed; use 'ollama', 'openai', 'gemini', 'cohere', 'exec', or 'hash'".into(),
             )),
 
             "openai" => {
@@ -254,13 +264,55 @@ impl EmbeddingService {
                 dimensions: 128,
             }),
 
+            "exec" => {
+                let command = config
+                    .command
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|c| !c.is_empty())
+                    .ok_or_else(|| {
+                        ShabkaError::Config(
+                            "exec embedding provider requires embedding.command".into(),
+                        )
+                    })?;
+                let dims = config.dimensions.ok_or_else(|| {
+                    ShabkaError::Config(
+                        "exec embedding provider requires embedding.dimensions".into(),
+                    )
+                })?;
+                // Without a model name, identify the model by its command so
+                // changing the command prompts a re-embed.
+                let model_id = if config.model.is_empty() || config.model == "hash-128d" {
+                    command.to_string()
+                } else {
+                    config.model.clone()
+                };
+                Ok(Self {
+                    inner: EmbeddingInner::Exec(ExecEmbeddingProvider::new(
+                        command, dims, model_id,
+                    )),
+                    provider: "exec",
+                    dimensions: dims,
+                })
+            }
+
             other => Err(ShabkaError::Config(format!(
                 "unknown embedding provider: '{other}' \
-                 (expected 'openai', 'ollama', 'gemini', 'cohere', or 'hash')"
+                 (expected 'openai', 'ollama', 'gemini', 'cohere', 'exec', or 'hash')"
             ))),
         }
     }
 
+    /// A service backed by `mock`, retried like a remote provider.
+    #[cfg(feature = "testing")]
+    pub fn mock(mock: crate::testing::MockEmbedding) -> Self {
+        Self {
+            dimensions: mock.dimensions(),
+            inner: EmbeddingInner::Mock(mock),
+            provider: "mock",
+        }
+    }
+
     /// Whether this provider makes remote API calls (and should use retry logic).
     fn is_remote(&self) -> bool {
         matches!(self.inner, EmbeddingInner::Rig(_))
@@ -271,10 +323,13 @@ impl EmbeddingService {
             return with_retry(3, 200, || async {
                 match &self.inner {
                     EmbeddingInner::Rig(adapter) => {
-                        let vecs = adapter
-                            .embed_texts(vec![text.to_string()])
-                            .await
-                            .map_err(ShabkaError::Embedding)?;
+                        let vecs =
+                            adapter
+                                .embed_texts(vec![text.to_string()])
+                                .await
+                                .map_err(|e| {
+                                    ShabkaError::provider("embedding", self.provider_name(), e)
+                                })?;
                         vecs.into_iter()
                             .next()
                             .map(|v| v.into_iter().map(|x| x as f32).collect())
@@ -289,6 +344,9 @@ impl EmbeddingService {
         }
         match &self.inner {
             EmbeddingInner::Hash(p) => p.embed(text).await,
+            EmbeddingInner::Exec(p) => p.embed(text).await,
+            #[cfg(feature = "testing")]
+            EmbeddingInner::Mock(m) => with_retry(3, 200, || m.embed(text)).await,
             _ => Err(ShabkaError::Embedding(
                 "unexpected non-local variant in local embed path".into(),
             )),
@@ -301,10 +359,9 @@ impl EmbeddingService {
                 match &self.inner {
                     EmbeddingInner::Rig(adapter) => {
                         let owned: Vec<String> = texts.iter().map(|s| s.to_string()).collect();
-                        let vecs = adapter
-                            .embed_texts(owned)
-                            .await
-                            .map_err(ShabkaError::Embedding)?;
+                        let vecs = adapter.embed_texts(owned).await.map_err(|e| {
+                            ShabkaError::provider("embedding", self.provider_name(), e)
+                        })?;
                         Ok(vecs
                             .into_iter()
                             .map(|v| v.into_iter().map(|x| x as f32).collect())
@@ -319,6 +376,9 @@ impl EmbeddingService {
         }
         match &self.inner {
             EmbeddingInner::Hash(p) => p.embed_batch(texts).await,
+            EmbeddingInner::Exec(p) => p.embed_batch(texts).await,
+            #[cfg(feature = "testing")]
+            EmbeddingInner::Mock(m) => with_retry(3, 200, || m.embed_batch(texts)).await,
             _ => Err(ShabkaError::Embedding(
                 "unexpected non-local variant in local embed path".into(),
             )),
@@ -333,6 +393,9 @@ impl EmbeddingService {
         match &self.inner {
             EmbeddingInner::Rig(adapter) => adapter.model_id(),
             EmbeddingInner::Hash(p) => p.model_id(),
+            EmbeddingInner::Exec(p) => p.model_id(),
+            #[cfg(feature = "testing")]
+            EmbeddingInner::Mock(m) => m.model_id(),
         }
     }
 
@@ -342,11 +405,73 @@ impl EmbeddingService {
     }
 }
 
+/// Store a fresh vector for `memory` after an edit, so search matches its
+/// new title and content. Skipped when `previous` (the memory before the
+/// edit) had the same embedding text, or when `[embedding] reembed_on_edit`
+/// is off. Returns whether it re-embedded.
+///
+/// The edit is already saved when this runs; if it fails, the memory keeps
+/// its old vector until the next `shabka reembed`.
+pub async fn refresh_after_edit(
+    storage: &impl StorageBackend,
+    embedder: &EmbeddingService,
+    config: &EmbeddingConfig,
+    previous: Option<&Memory>,
+    memory: &Memory,
+) -> Result<bool> {
+    let text = memory.embedding_text();
+    if !config.reembed_on_edit || previous.is_some_and(|p| p.embedding_text() == text) {
+        return Ok(false);
+    }
+    let embedding = embedder.embed(&text).await?;
+    storage.save_memory(memory, Some(&embedding)).await?;
+    Ok(true)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config::EmbeddingConfig;
 
+    #[tokio::test]
+    async fn test_refresh_after_edit() {
+        use crate::model::{MemoryKind, UpdateMemoryInput};
+        use crate::storage::SqliteStorage;
+
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let embedder = EmbeddingService::from_config(&EmbeddingConfig::default()).unwrap();
+        let config = EmbeddingConfig::default();
+        let memory = Memory::new("Pool".into(), "Old".into(), MemoryKind::Fact, "u".into());
+        let vector = embedder.embed(&memory.embedding_text()).await.unwrap();
+        storage.save_memory(&memory, Some(&vector)).await.unwrap();
+
+        let reweighted = UpdateMemoryInput {
+            importance: Some(0.9),
+            ..Default::default()
+        };
+        let updated = storage.update_memory(memory.id, &reweighted).await.unwrap();
+        let refresh = refresh_after_edit(&storage, &embedder, &config, Some(&memory), &updated);
+        assert!(!refresh.await.unwrap());
+
+        let edit = UpdateMemoryInput {
+            content: Some("Connection pool ran dry".into()),
+            ..Default::default()
+        };
+        let edited = storage.update_memory(memory.id, &edit).await.unwrap();
+        let deferred = EmbeddingConfig {
+            reembed_on_edit: false,
+            ..Default::default()
+        };
+        let refresh = refresh_after_edit(&storage, &embedder, &deferred, Some(&memory), &edited);
+        assert!(!refresh.await.unwrap());
+        let refresh = refresh_after_edit(&storage, &embedder, &config, Some(&memory), &edited);
+        assert!(refresh.await.unwrap());
+
+        let query = embedder.embed(&edited.embedding_text()).await.unwrap();
+        let hits = storage.vector_search(&query, 1).await.unwrap();
+        assert!(hits[0].1 > 0.99);
+    }
+
     #[test]
     fn test_unknown_provider_errors() {
         let config = EmbeddingConfig {
@@ -356,6 +481,8 @@ mod tests {
             base_url: None,
             dimensions: None,
             env_var: None,
+            command: None,
+            reembed_on_edit: true,
         };
         let result = EmbeddingService::from_config(&config);
         assert!(result.is_err());
@@ -376,6 +503,8 @@ mod tests {
             base_url: None,
             dimensions: None,
             env_var: None,
+            command: None,
+            reembed_on_edit: true,
         };
         let result = EmbeddingService::from_config(&config);
         assert!(result.is_err());
@@ -388,6 +517,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_exec_requires_command_and_dimensions() {
+        let mut config = EmbeddingConfig {
+            provider: "exec".to_string(),
+            dimensions: Some(384),
+            ..Default::default()
+        };
+        let err = EmbeddingService::from_config(&config).err().unwrap();
+        assert!(err.to_string().contains("embedding.command"));
+
+        config.command = Some("my-embedder --stdin".to_string());
+        let service = EmbeddingService::from_config(&config).unwrap();
+        assert_eq!(service.provider_name(), "exec");
+        assert_eq!(service.dimensions(), 384);
+        assert_eq!(service.model_id(), "my-embedder --stdin");
+
+        config.dimensions = None;
+        let err = EmbeddingService::from_config(&config).err().unwrap();
+        assert!(err.to_string().contains("embedding.dimensions"));
+    }
+
     #[test]
     fn test_local_provider_removed() {
         let config = EmbeddingConfig {
@@ -397,6 +547,8 @@ mod tests {
             base_url: None,
             dimensions: None,
             env_var: None,
+            command: None,
+            reembed_on_edit: true,
         };
         let result = EmbeddingService::from_config(&config);
         assert!(result.is_err());
@@ -413,6 +565,8 @@ mod tests {
             base_url: None,
             dimensions: None,
             env_var: None,
+            command: None,
+            reembed_on_edit: true,
         };
         let result = EmbeddingService::from_config(&config);
         assert!(result.is_ok());
@@ -432,6 +586,8 @@ mod tests {
             base_url: None,
             dimensions: None,
             env_var: None,
+            command: None,
+            reembed_on_edit: true,
         };
         let result = EmbeddingService::from_config(&config);
         assert!(result.is_err());
@@ -452,6 +608,8 @@ mod tests {
             base_url: Some("http://localhost:8000/v1".to_string()),
             dimensions: Some(1024),
             env_var: None,
+            command: None,
+            reembed_on_edit: true,
         };
         let result = EmbeddingService::from_config(&config);
         assert!(result.is_ok());
@@ -470,6 +628,8 @@ mod tests {
             base_url: None,
             dimensions: None,
             env_var: None,
+            command: None,
+            reembed_on_edit: true,
         };
         let result = EmbeddingService::from_config(&config);
         assert!(result.is_ok());
@@ -486,6 +646,8 @@ mod tests {
             base_url: None,
             dimensions: None,
             env_var: None,
+            command: None,
+            reembed_on_edit: true,
         };
         let result = EmbeddingService::from_config(&config);
         assert!(result.is_ok());
@@ -507,6 +669,8 @@ mod tests {
             base_url: None,
             dimensions: None,
             env_var: None,
+            command: None,
+            reembed_on_edit: true,
         };
         let result = EmbeddingService::from_config(&config);
         assert!(result.is_err());
@@ -527,6 +691,8 @@ mod tests {
             base_url: None,
             dimensions: None,
             env_var: None,
+            command: None,
+            reembed_on_edit: true,
         };
         let result = EmbeddingService::from_config(&config);
         assert!(result.is_ok());
@@ -545,6 +711,8 @@ mod tests {
             base_url: None,
             dimensions: None,
             env_var: None,
+            command: None,
+            reembed_on_edit: true,
         };
         let result = EmbeddingService::from_config(&config);
         assert!(result.is_ok());
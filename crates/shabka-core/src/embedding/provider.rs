@@ -6,6 +6,7 @@ use crate::error::Result;
 /// - `OpenAIEmbeddingProvider`: text-embedding-3-small (or Ollama-compatible), requires API key
 /// - `GeminiEmbeddingProvider`: text-embedding-004, requires API key
 /// - `HashEmbeddingProvider`: deterministic hash-based, for testing
+/// - `ExecEmbeddingProvider`: user-supplied command, text on stdin and JSON vector on stdout
 /// - HelixDB native `Embed()` — embedding happens inside HelixQL queries (no provider needed)
 pub trait EmbeddingProvider: Send + Sync {
     /// Generate an embedding vector for the given text.
@@ -5,9 +5,11 @@ pub enum ShabkaError {
     #[error("Storage error: {0}")]
     Storage(String),
 
+    #[cfg(feature = "runtime")]
     #[error("HelixDB error: {0}")]
     Helix(#[from] helix_rs::HelixError),
 
+    #[cfg(feature = "runtime")]
     #[error("HTTP error: {0}")]
     Http(#[from] reqwest::Error),
 
@@ -28,20 +30,151 @@ pub enum ShabkaError {
 
     #[error("Invalid input: {0}")]
     InvalidInput(String),
+
+    /// The storage backend could not be reached.
+    #[error("Storage unavailable: {0}")]
+    StorageUnavailable(String),
+
+    /// An embedding or LLM provider rejected the credentials, or none are set.
+    #[error("{service} provider '{provider}' authentication failed: {detail}")]
+    ProviderAuth {
+        /// `embedding` or `llm`, the config section holding the key.
+        service: String,
+        provider: String,
+        detail: String,
+    },
+
+    /// Stored embeddings have a different size than the current provider's.
+    #[error(
+        "Dimension mismatch: stored embeddings are {stored}d ({stored_model}), \
+         but the current config produces {current}d ({current_model})"
+    )]
+    DimensionMismatch {
+        stored: usize,
+        stored_model: String,
+        current: usize,
+        current_model: String,
+    },
+
+    /// A configured limit is used up until it resets.
+    #[error("Quota exceeded: {message}")]
+    QuotaExceeded {
+        message: String,
+        /// The config key that sets the limit.
+        setting: String,
+    },
 }
 
 impl ShabkaError {
+    /// A failure reported by an embedding or LLM provider: `ProviderAuth`
+    /// when `detail` reads as rejected credentials, otherwise `Embedding` or
+    /// `Llm` by `service`.
+    pub fn provider(service: &str, provider: &str, detail: String) -> Self {
+        if is_auth_message(&detail) {
+            Self::ProviderAuth {
+                service: service.to_lowercase(),
+                provider: provider.to_string(),
+                detail,
+            }
+        } else if service.eq_ignore_ascii_case("llm") {
+            Self::Llm(detail)
+        } else {
+            Self::Embedding(detail)
+        }
+    }
+
+    /// Stable snake_case name of the variant, for machine-readable errors.
+    pub fn error_type(&self) -> &'static str {
+        match self {
+            Self::Storage(_) => "storage_error",
+            #[cfg(feature = "runtime")]
+            Self::Helix(_) => "helix_error",
+            #[cfg(feature = "runtime")]
+            Self::Http(_) => "http_error",
+            Self::Serialization(_) => "serialization_error",
+            Self::Embedding(_) => "embedding_error",
+            Self::Llm(_) => "llm_error",
+            Self::Config(_) => "config_error",
+            Self::NotFound(_) => "not_found",
+            Self::InvalidInput(_) => "invalid_input",
+            Self::StorageUnavailable(_) => "storage_unavailable",
+            Self::ProviderAuth { .. } => "provider_auth",
+            Self::DimensionMismatch { .. } => "dimension_mismatch",
+            Self::QuotaExceeded { .. } => "quota_exceeded",
+        }
+    }
+
+    /// What the user can do about the error, when there is a known fix.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            Self::StorageUnavailable(_) => Some(
+                "start the storage backend (`just db` for HelixDB) or set \
+                 storage.backend = \"sqlite\", then run `shabka doctor`"
+                    .to_string(),
+            ),
+            Self::ProviderAuth {
+                service, provider, ..
+            } => Some(format!(
+                "check the {provider} API key ({service}.api_key or its environment \
+                 variable), then run `shabka doctor`"
+            )),
+            Self::DimensionMismatch { .. } => {
+                Some("run `shabka reembed` to re-embed all memories".to_string())
+            }
+            Self::QuotaExceeded { setting, .. } => {
+                Some(format!("wait for the daily reset or raise {setting}"))
+            }
+            _ => None,
+        }
+    }
+
     /// Returns `true` when the error is likely transient and worth retrying
     /// (e.g. HTTP 429/5xx, network timeouts, connection refused).
     pub fn is_transient(&self) -> bool {
         match self {
             // reqwest errors are almost always network-level / transient
+            #[cfg(feature = "runtime")]
             Self::Http(_) => true,
+            // The backend may be starting up
+            Self::StorageUnavailable(_) => true,
             // Check embedded error messages for transient HTTP status codes
             Self::Embedding(msg) | Self::Storage(msg) | Self::Llm(msg) => is_transient_message(msg),
             _ => false,
         }
     }
+
+    /// Returns `true` when SQLite reported the database as locked by another
+    /// connection (SQLITE_BUSY / SQLITE_LOCKED) past its busy timeout.
+    pub fn is_busy(&self) -> bool {
+        match self {
+            Self::Storage(msg) => is_busy_message(msg),
+            _ => false,
+        }
+    }
+}
+
+fn is_busy_message(msg: &str) -> bool {
+    let msg_lower = msg.to_lowercase();
+    msg_lower.contains("database is locked")
+        || msg_lower.contains("database table is locked")
+        || msg_lower.contains("database is busy")
+}
+
+fn is_auth_message(msg: &str) -> bool {
+    let msg_lower = msg.to_lowercase();
+    let patterns = [
+        "401",
+        "403",
+        "unauthorized",
+        "unauthenticated",
+        "invalid api key",
+        "invalid_api_key",
+        "incorrect api key",
+        "invalid x-api-key",
+        "requires an api key",
+        "permission denied",
+    ];
+    patterns.iter().any(|p| msg_lower.contains(p))
 }
 
 fn is_transient_message(msg: &str) -> bool {
@@ -61,7 +194,7 @@ fn is_transient_message(msg: &str) -> bool {
         "broken pipe",
         "temporarily unavailable",
     ];
-    patterns.iter().any(|p| msg_lower.contains(p))
+    patterns.iter().any(|p| msg_lower.contains(p)) || is_busy_message(msg)
 }
 
 pub type Result<T> = std::result::Result<T, ShabkaError>;
@@ -94,6 +227,14 @@ mod tests {
         assert!(err.is_transient());
     }
 
+    #[test]
+    fn test_sqlite_busy() {
+        let err = ShabkaError::Storage("failed to insert memory: database is locked".into());
+        assert!(err.is_busy());
+        assert!(err.is_transient());
+        assert!(!ShabkaError::Embedding("database is locked".into()).is_busy());
+    }
+
     #[test]
     fn test_permanent_401() {
         let err = ShabkaError::Embedding("API error 401: unauthorized".into());
@@ -129,4 +270,35 @@ mod tests {
         let err = ShabkaError::Llm("API error 401: unauthorized".into());
         assert!(!err.is_transient());
     }
+
+    #[test]
+    fn test_provider_auth() {
+        let err = ShabkaError::provider("embedding", "openai", "API error 401: invalid".into());
+        assert_eq!(err.error_type(), "provider_auth");
+        assert!(!err.is_transient());
+        assert!(err.hint().unwrap().contains("embedding.api_key"));
+
+        let err = ShabkaError::provider("llm", "ollama", "API error 503: overloaded".into());
+        assert!(matches!(err, ShabkaError::Llm(_)));
+        assert!(err.hint().is_none());
+    }
+
+    #[test]
+    fn test_hints() {
+        let err = ShabkaError::DimensionMismatch {
+            stored: 768,
+            stored_model: "ollama/nomic-embed-text".into(),
+            current: 1536,
+            current_model: "openai/text-embedding-3-small".into(),
+        };
+        assert!(err.to_string().contains("768d"));
+        assert!(err.hint().unwrap().contains("shabka reembed"));
+
+        let err = ShabkaError::QuotaExceeded {
+            message: "daily token budget of 1000 used".into(),
+            setting: "llm.daily_token_budget".into(),
+        };
+        assert!(err.hint().unwrap().contains("llm.daily_token_budget"));
+        assert!(ShabkaError::StorageUnavailable("refused".into()).is_transient());
+    }
 }
@@ -0,0 +1,321 @@
+//! Knowledge-gap report: where a project saw heavy work but no captured
+//! decisions or lessons.
+//!
+//! Activity comes from the edit logs the hooks write (raw file-change
+//! captures and heuristic session summaries). Each edited file's directory
+//! is an *area*; an area is documented when a decision or lesson references
+//! a file in it or carries its name as a tag.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::codebase;
+use crate::model::{Memory, MemoryKind, MemorySource};
+
+/// Edits an undocumented area needs before it counts as a gap.
+pub const DEFAULT_MIN_EDITS: usize = 3;
+
+/// Work and documentation in one directory of a project.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, schemars::JsonSchema)]
+pub struct AreaActivity {
+    /// Directory relative to the project root (`.` for the root itself).
+    pub area: String,
+    /// Edit logs referencing a file in the area.
+    pub edits: usize,
+    /// Distinct sessions those edits were made in.
+    pub sessions: usize,
+    pub decisions: usize,
+    pub lessons: usize,
+}
+
+impl AreaActivity {
+    pub fn is_documented(&self) -> bool {
+        self.decisions + self.lessons > 0
+    }
+}
+
+/// Memory kind and tag distribution of a project set against its activity.
+#[derive(Debug, Clone, Default, Serialize, schemars::JsonSchema)]
+pub struct GapReport {
+    pub project: String,
+    /// Memories in the project, edit logs included.
+    pub memories: usize,
+    /// Edit logs among them.
+    pub edit_logs: usize,
+    /// Distinct sessions any memory was captured in.
+    pub sessions: usize,
+    /// Counts per kind, edit logs excluded.
+    pub kinds: BTreeMap<String, usize>,
+    /// Most used tags, edit logs and system tags excluded.
+    pub tags: Vec<(String, usize)>,
+    /// Every edited area, busiest first.
+    pub areas: Vec<AreaActivity>,
+}
+
+impl GapReport {
+    /// Undocumented areas with at least `min_edits` edits, busiest first.
+    pub fn gaps(&self, min_edits: usize) -> Vec<&AreaActivity> {
+        self.areas
+            .iter()
+            .filter(|a| !a.is_documented() && a.edits >= min_edits)
+            .collect()
+    }
+}
+
+/// Tags the hooks add to every capture; they say nothing about the subject.
+const SYSTEM_TAGS: &[&str] = &[
+    "auto-capture",
+    "file-change",
+    "session-compressed",
+    "llm-summarized",
+];
+
+/// Tags shown in a report.
+const TOP_TAGS: usize = 10;
+
+/// True for the hooks' records of file edits: raw captures, and the per-file
+/// summaries session compression writes without an LLM. These log activity
+/// rather than knowledge, even though they're stored as decisions.
+pub fn is_edit_log(memory: &Memory) -> bool {
+    if !matches!(memory.source, MemorySource::AutoCapture { .. }) {
+        return false;
+    }
+    let has = |tag: &str| memory.tags.iter().any(|t| t == tag);
+    has("file-change")
+        || (memory.kind == MemoryKind::Decision
+            && has("session-compressed")
+            && !has("llm-summarized"))
+}
+
+/// Build the gap report for `project` from its memories.
+pub fn analyze(project: &str, memories: &[Memory]) -> GapReport {
+    let mut report = GapReport {
+        project: project.to_string(),
+        memories: memories.len(),
+        ..Default::default()
+    };
+
+    let mut all_sessions: HashSet<Uuid> = HashSet::new();
+    let mut area_sessions: HashMap<String, HashSet<Uuid>> = HashMap::new();
+    let mut areas: HashMap<String, AreaActivity> = HashMap::new();
+    let mut tags: HashMap<&str, usize> = HashMap::new();
+    let mut documentation: Vec<&Memory> = Vec::new();
+
+    for memory in memories {
+        all_sessions.extend(memory.session_id);
+        if is_edit_log(memory) {
+            report.edit_logs += 1;
+            for area in memory_areas(memory, project) {
+                if let Some(session) = memory.session_id {
+                    area_sessions
+                        .entry(area.clone())
+                        .or_default()
+                        .insert(session);
+                }
+                let entry = areas.entry(area.clone()).or_insert_with(|| AreaActivity {
+                    area,
+                    ..Default::default()
+                });
+                entry.edits += 1;
+            }
+            continue;
+        }
+        *report.kinds.entry(memory.kind.to_string()).or_default() += 1;
+        for tag in &memory.tags {
+            if !SYSTEM_TAGS.contains(&tag.as_str()) {
+                *tags.entry(tag.as_str()).or_default() += 1;
+            }
+        }
+        if matches!(memory.kind, MemoryKind::Decision | MemoryKind::Lesson) {
+            documentation.push(memory);
+        }
+    }
+
+    for memory in documentation {
+        let referenced = memory_areas(memory, project);
+        for activity in areas.values_mut() {
+            let covered = referenced.contains(&activity.area)
+                || area_name(&activity.area)
+                    .is_some_and(|name| memory.tags.iter().any(|t| t.eq_ignore_ascii_case(name)));
+            if !covered {
+                continue;
+            }
+            match memory.kind {
+                MemoryKind::Lesson => activity.lessons += 1,
+                _ => activity.decisions += 1,
+            }
+        }
+    }
+
+    for (area, sessions) in area_sessions {
+        if let Some(activity) = areas.get_mut(&area) {
+            activity.sessions = sessions.len();
+        }
+    }
+
+    report.sessions = all_sessions.len();
+    report.areas = areas.into_values().collect();
+    report
+        .areas
+        .sort_by(|a, b| b.edits.cmp(&a.edits).then_with(|| a.area.cmp(&b.area)));
+
+    let mut tags: Vec<(String, usize)> = tags
+        .into_iter()
+        .map(|(tag, count)| (tag.to_string(), count))
+        .collect();
+    tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    tags.truncate(TOP_TAGS);
+    report.tags = tags;
+
+    report
+}
+
+/// Areas of the files a memory references, deduplicated.
+fn memory_areas(memory: &Memory, project: &str) -> Vec<String> {
+    let mut areas: Vec<String> = Vec::new();
+    for path in codebase::referenced_paths(memory) {
+        let area = area_of(&path, project);
+        if !areas.contains(&area) {
+            areas.push(area);
+        }
+    }
+    areas
+}
+
+/// Directory of `path` relative to the project root.
+///
+/// Hooks record absolute paths; everything up to the last directory named
+/// after the project is dropped. Paths outside such a directory keep their
+/// full directory, minus any leading `/` or `./`.
+fn area_of(path: &str, project: &str) -> String {
+    let components: Vec<&str> = path
+        .split('/')
+        .filter(|c| !c.is_empty() && *c != ".")
+        .collect();
+    let start = components
+        .iter()
+        .rposition(|c| *c == project)
+        .filter(|&i| i + 1 < components.len())
+        .map_or(0, |i| i + 1);
+    let dirs = &components[start..components.len().saturating_sub(1)];
+    if dirs.is_empty() {
+        ".".to_string()
+    } else {
+        dirs.join("/")
+    }
+}
+
+/// Last component of an area, matched against tags.
+fn area_name(area: &str) -> Option<&str> {
+    area.rsplit('/').next().filter(|name| *name != ".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(path: &str, session: Uuid) -> Memory {
+        Memory::new(
+            format!("Edit {path}"),
+            format!("File modified via Edit: {path}"),
+            MemoryKind::Decision,
+            "user".to_string(),
+        )
+        .with_source(MemorySource::AutoCapture {
+            hook: "PostToolUse".to_string(),
+        })
+        .with_tags(vec!["auto-capture".to_string(), "file-change".to_string()])
+        .with_session(session)
+    }
+
+    fn note(kind: MemoryKind, content: &str, tags: &[&str]) -> Memory {
+        Memory::new(
+            "A note".to_string(),
+            content.to_string(),
+            kind,
+            "user".to_string(),
+        )
+        .with_tags(tags.iter().map(|t| t.to_string()).collect())
+    }
+
+    #[test]
+    fn test_area_of_strips_project_root() {
+        assert_eq!(
+            area_of("/home/me/shabka/crates/core/src/lib.rs", "shabka"),
+            "crates/core/src"
+        );
+        assert_eq!(area_of("/home/me/shabka/Cargo.toml", "shabka"), ".");
+        assert_eq!(area_of("./src/main.rs", "other"), "src");
+        assert_eq!(area_of("main.rs", "shabka"), ".");
+    }
+
+    #[test]
+    fn test_analyze_finds_undocumented_areas() {
+        let (s1, s2) = (Uuid::now_v7(), Uuid::now_v7());
+        let memories = vec![
+            edit("/work/app/src/storage/sqlite.rs", s1),
+            edit("/work/app/src/storage/sqlite.rs", s2),
+            edit("/work/app/src/storage/mod.rs", s2),
+            edit("/work/app/src/web/routes.rs", s1),
+            edit("/work/app/src/web/routes.rs", s1),
+            edit("/work/app/src/web/routes.rs", s2),
+            note(
+                MemoryKind::Decision,
+                "Routes live in src/web/routes.rs",
+                &["web"],
+            ),
+            note(MemoryKind::Fact, "Unrelated", &["misc"]),
+        ];
+
+        let report = analyze("app", &memories);
+        assert_eq!(report.memories, 8);
+        assert_eq!(report.edit_logs, 6);
+        assert_eq!(report.sessions, 2);
+        assert_eq!(report.kinds.get("decision"), Some(&1));
+        assert_eq!(report.kinds.get("fact"), Some(&1));
+
+        let storage = report
+            .areas
+            .iter()
+            .find(|a| a.area == "src/storage")
+            .unwrap();
+        assert_eq!(storage.edits, 3);
+        assert_eq!(storage.sessions, 2);
+        assert!(!storage.is_documented());
+
+        let web = report.areas.iter().find(|a| a.area == "src/web").unwrap();
+        assert_eq!(web.decisions, 1);
+
+        let gaps = report.gaps(DEFAULT_MIN_EDITS);
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].area, "src/storage");
+        assert!(report.gaps(4).is_empty());
+    }
+
+    #[test]
+    fn test_tag_documents_area() {
+        let s = Uuid::now_v7();
+        let memories = vec![
+            edit("/work/app/src/storage/sqlite.rs", s),
+            note(
+                MemoryKind::Lesson,
+                "WAL mode needs a busy timeout",
+                &["Storage"],
+            ),
+        ];
+        let report = analyze("app", &memories);
+        assert_eq!(report.areas[0].lessons, 1);
+        assert_eq!(report.tags, vec![("Storage".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_llm_summaries_are_not_edit_logs() {
+        let mut m = edit("/work/app/src/lib.rs", Uuid::now_v7());
+        m.tags = vec!["auto-capture".into(), "session-compressed".into()];
+        assert!(is_edit_log(&m));
+        m.tags.push("llm-summarized".into());
+        assert!(!is_edit_log(&m));
+    }
+}
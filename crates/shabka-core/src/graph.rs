@@ -1,14 +1,20 @@
 //! Graph intelligence — automatic relationship discovery and traversal.
 //!
 //! - `semantic_auto_relate`: vector-search for similar memories and create edges.
-//! - `follow_chain`: BFS traversal along typed edges for debugging narratives.
+//! - `follow_chain`: BFS traversal along typed edges for debugging narratives,
+//!   marking edges that close a cycle.
+//! - `find_similar`: nearest neighbours of a memory, marked with existing edges.
+//! - `group_by_chain`: fold search results linked into one chain into a group.
+//! - `refresh_centrality`: recompute and store every memory's centrality.
 
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use uuid::Uuid;
 
-use crate::model::{MemoryRelation, RelationType};
-use crate::storage::StorageBackend;
+use crate::centrality::{self, Centrality};
+use crate::error::Result;
+use crate::model::{Memory, MemoryRelation, RelationType, TimelineQuery};
+use crate::storage::{Storage, StorageBackend};
 
 /// Default similarity threshold for auto-relating memories (0.0–1.0).
 /// Only memories with vector similarity above this value get linked.
@@ -17,6 +23,9 @@ const DEFAULT_SIMILARITY_THRESHOLD: f32 = 0.6;
 /// Maximum number of auto-created relations per memory.
 const DEFAULT_MAX_RELATIONS: usize = 3;
 
+/// Most memories [`refresh_centrality`] scores.
+const CENTRALITY_SCAN_LIMIT: usize = 10_000;
+
 /// Find semantically similar memories and create `Related` edges.
 ///
 /// - `storage`: the storage backend for search and relation creation
@@ -109,12 +118,38 @@ pub struct ChainLink {
     pub strength: f32,
     /// How many hops from the starting memory (1-based).
     pub depth: usize,
+    /// The edge leads back to a memory already in the chain, which is not
+    /// followed again.
+    pub cycle: bool,
+}
+
+/// The result of [`follow_chain`].
+#[derive(Debug, Clone, Default)]
+pub struct Chain {
+    /// Links in order of discovery, cycle links included.
+    pub links: Vec<ChainLink>,
+    /// The traversal stopped at `max_nodes` memories with more to visit.
+    pub truncated: bool,
+}
+
+impl Chain {
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+
+    /// Memories in the chain, not counting the starting one.
+    pub fn node_count(&self) -> usize {
+        self.links.iter().filter(|l| !l.cycle).count()
+    }
 }
 
 /// Follow a chain of relations from a starting memory via BFS.
 ///
-/// Traverses edges of the given types up to `max_depth` hops.
-/// Returns links in order of discovery. Avoids cycles.
+/// Traverses edges of the given types up to `max_depth` hops, visiting at
+/// most `max_nodes` memories besides the start. Each memory is visited
+/// once: an edge back to one already in the chain is returned as a
+/// `cycle` link instead of being followed. Returns links in order of
+/// discovery.
 ///
 /// Use cases:
 /// - Follow `Fixes` → `CausedBy` chains for debugging narratives
@@ -125,14 +160,18 @@ pub async fn follow_chain(
     start_id: Uuid,
     relation_types: &[RelationType],
     max_depth: Option<usize>,
-) -> Vec<ChainLink> {
+    max_nodes: Option<usize>,
+) -> Chain {
     let max_depth = max_depth.unwrap_or(DEFAULT_MAX_CHAIN_DEPTH);
 
     let mut visited = HashSet::new();
     visited.insert(start_id);
+    // Edges already walked or reported, as (lower id, higher id, type):
+    // storage returns an edge from both of its ends.
+    let mut edges = HashSet::new();
     let mut queue = VecDeque::new();
     queue.push_back((start_id, 0usize));
-    let mut chain = Vec::new();
+    let mut chain = Chain::default();
 
     while let Some((current_id, depth)) = queue.pop_front() {
         if depth >= max_depth {
@@ -154,27 +193,197 @@ pub async fn follow_chain(
             } else {
                 rel.source_id
             };
-
-            if visited.contains(&next_id) {
+            if next_id == current_id
+                || !edges.insert((
+                    current_id.min(next_id),
+                    current_id.max(next_id),
+                    rel.relation_type,
+                ))
+            {
                 continue;
             }
-            visited.insert(next_id);
 
-            chain.push(ChainLink {
+            let cycle = visited.contains(&next_id);
+            if !cycle {
+                if max_nodes.is_some_and(|max| visited.len() > max) {
+                    chain.truncated = true;
+                    continue;
+                }
+                visited.insert(next_id);
+                queue.push_back((next_id, depth + 1));
+            }
+            chain.links.push(ChainLink {
                 memory_id: next_id,
                 from_id: current_id,
                 relation_type: rel.relation_type,
                 strength: rel.strength,
                 depth: depth + 1,
+                cycle,
             });
-
-            queue.push_back((next_id, depth + 1));
         }
     }
 
     chain
 }
 
+/// A nearest neighbour found by [`find_similar`].
+#[derive(Debug, Clone)]
+pub struct SimilarMemory {
+    pub memory: Memory,
+    /// Vector similarity to the source memory (0.0–1.0).
+    pub score: f32,
+    /// The relation already linking the two memories, if any.
+    pub relation: Option<RelationType>,
+}
+
+/// List the memories closest to `memory_id` by vector similarity.
+///
+/// `embedding` is the source memory's embedding. The source itself is
+/// skipped, and each neighbour carries the relation (in either direction)
+/// that already connects it, so callers can tell near-duplicates that were
+/// never linked from ones that already are.
+pub async fn find_similar(
+    storage: &impl StorageBackend,
+    memory_id: Uuid,
+    embedding: &[f32],
+    limit: usize,
+) -> Result<Vec<SimilarMemory>> {
+    // One extra to make room for the self-match
+    let results = storage.vector_search(embedding, limit + 1).await?;
+
+    let existing: HashMap<Uuid, RelationType> = storage
+        .get_relations(memory_id)
+        .await?
+        .into_iter()
+        .map(|r| {
+            let other = if r.source_id == memory_id {
+                r.target_id
+            } else {
+                r.source_id
+            };
+            (other, r.relation_type)
+        })
+        .collect();
+
+    Ok(results
+        .into_iter()
+        .filter(|(m, _)| m.id != memory_id)
+        .take(limit)
+        .map(|(memory, score)| SimilarMemory {
+            relation: existing.get(&memory.id).copied(),
+            memory,
+            score,
+        })
+        .collect())
+}
+
+/// Relation types that make memories one story for [`group_by_chain`]: an
+/// error, what caused it, what fixed it and the lesson drawn from it.
+/// `Related` is left out because auto-relate links merely similar memories
+/// with it.
+pub const CHAIN_RELATIONS: [RelationType; 2] = [RelationType::CausedBy, RelationType::Fixes];
+
+/// Search results tied together by [`CHAIN_RELATIONS`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResultGroup {
+    /// Index of the best-ranked result in the group.
+    pub primary: usize,
+    /// Indices of the other results in the group, best first.
+    pub members: Vec<usize>,
+}
+
+/// Group ranked results (`ids`, best first) that `relations` link through
+/// [`CHAIN_RELATIONS`], directly or via other results. Each group is led by
+/// its best-ranked result and groups keep the order of their leaders, so an
+/// error, its fix and its lesson take one slot instead of three.
+pub fn group_by_chain(ids: &[Uuid], relations: &[MemoryRelation]) -> Vec<ResultGroup> {
+    let index: HashMap<Uuid, usize> = ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+    // Union-find over result indices; the root is always the lowest index,
+    // i.e. the best-ranked member.
+    let mut parent: Vec<usize> = (0..ids.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for relation in relations {
+        if !CHAIN_RELATIONS.contains(&relation.relation_type) {
+            continue;
+        }
+        let (Some(&a), Some(&b)) = (
+            index.get(&relation.source_id),
+            index.get(&relation.target_id),
+        ) else {
+            continue;
+        };
+        let (a, b) = (root(&mut parent, a), root(&mut parent, b));
+        parent[a.max(b)] = a.min(b);
+    }
+
+    let mut groups: Vec<ResultGroup> = Vec::new();
+    let mut group_of: HashMap<usize, usize> = HashMap::new();
+    for i in 0..ids.len() {
+        let leader = root(&mut parent, i);
+        match group_of.get(&leader) {
+            Some(&g) => groups[g].members.push(i),
+            None => {
+                group_of.insert(leader, groups.len());
+                groups.push(ResultGroup {
+                    primary: i,
+                    members: Vec::new(),
+                });
+            }
+        }
+    }
+    groups
+}
+
+/// Fetch the relations of `ids` and [`group_by_chain`] them. Results whose
+/// relations can't be read stay on their own.
+pub async fn chain_groups(storage: &impl StorageBackend, ids: &[Uuid]) -> Vec<ResultGroup> {
+    let mut relations = Vec::new();
+    for id in ids {
+        if let Ok(found) = storage.get_relations(*id).await {
+            relations.extend(found);
+        }
+    }
+    group_by_chain(ids, &relations)
+}
+
+/// Recompute the centrality of every active memory from its relations and
+/// store it, replacing the previous scores. Returns the new scores, most
+/// central first.
+pub async fn refresh_centrality(storage: &Storage) -> Result<Vec<Centrality>> {
+    let entries = storage
+        .timeline(&TimelineQuery {
+            limit: CENTRALITY_SCAN_LIMIT,
+            ..Default::default()
+        })
+        .await?;
+    let ids: Vec<Uuid> = entries.iter().map(|e| e.id).collect();
+
+    // Each edge comes back from both of its ends; count it once.
+    let mut seen: HashSet<(Uuid, Uuid, RelationType)> = HashSet::new();
+    let mut relations = Vec::new();
+    for id in &ids {
+        for relation in storage.get_relations(*id).await? {
+            if seen.insert((
+                relation.source_id,
+                relation.target_id,
+                relation.relation_type,
+            )) {
+                relations.push(relation);
+            }
+        }
+    }
+
+    let scores = centrality::compute(&ids, &relations);
+    storage.save_centrality(&scores).await?;
+    Ok(scores)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -302,6 +511,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn test_default_constants() {
         assert!(DEFAULT_SIMILARITY_THRESHOLD > 0.0 && DEFAULT_SIMILARITY_THRESHOLD < 1.0);
         assert!(DEFAULT_MAX_RELATIONS > 0);
@@ -316,11 +526,37 @@ mod tests {
             relation_type: RelationType::Fixes,
             strength: 0.9,
             depth: 1,
+            cycle: false,
         };
         assert_eq!(link.depth, 1);
         assert_eq!(link.relation_type, RelationType::Fixes);
     }
 
+    // -- find_similar tests --
+
+    #[tokio::test]
+    async fn test_find_similar_skips_self_and_marks_relations() {
+        let source = make_memory("Source");
+        let linked = make_memory("Linked");
+        let unlinked = make_memory("Unlinked");
+        let storage = MockGraphStorage::with_search_results(vec![
+            (source.clone(), 1.0),
+            (linked.clone(), 0.9),
+            (unlinked.clone(), 0.8),
+        ]);
+        storage.add_mock_relation(source.id, linked.id, RelationType::Fixes, 0.7);
+
+        let similar = find_similar(&storage, source.id, &[0.0], 5).await.unwrap();
+        assert_eq!(similar.len(), 2);
+        assert_eq!(similar[0].memory.id, linked.id);
+        assert_eq!(similar[0].relation, Some(RelationType::Fixes));
+        assert_eq!(similar[1].memory.id, unlinked.id);
+        assert_eq!(similar[1].relation, None);
+
+        let top = find_similar(&storage, source.id, &[0.0], 1).await.unwrap();
+        assert_eq!(top.len(), 1);
+    }
+
     // -- follow_chain tests --
 
     #[tokio::test]
@@ -328,7 +564,9 @@ mod tests {
         let storage = MockGraphStorage::new();
         let start = Uuid::now_v7();
 
-        let chain = follow_chain(&storage, start, &[RelationType::Related], None).await;
+        let chain = follow_chain(&storage, start, &[RelationType::Related], None, None)
+            .await
+            .links;
         assert!(chain.is_empty(), "empty graph should return no links");
     }
 
@@ -340,7 +578,9 @@ mod tests {
 
         storage.add_mock_relation(a, b, RelationType::Fixes, 0.9);
 
-        let chain = follow_chain(&storage, a, &[RelationType::Fixes], None).await;
+        let chain = follow_chain(&storage, a, &[RelationType::Fixes], None, None)
+            .await
+            .links;
         assert_eq!(chain.len(), 1);
         assert_eq!(chain[0].memory_id, b);
         assert_eq!(chain[0].from_id, a);
@@ -358,7 +598,9 @@ mod tests {
         storage.add_mock_relation(a, b, RelationType::Related, 0.8);
         storage.add_mock_relation(b, c, RelationType::Related, 0.7);
 
-        let chain = follow_chain(&storage, a, &[RelationType::Related], None).await;
+        let chain = follow_chain(&storage, a, &[RelationType::Related], None, None)
+            .await
+            .links;
         assert_eq!(chain.len(), 2);
 
         let ids: Vec<Uuid> = chain.iter().map(|l| l.memory_id).collect();
@@ -382,10 +624,54 @@ mod tests {
         storage.add_mock_relation(a, b, RelationType::Related, 0.8);
         storage.add_mock_relation(b, a, RelationType::Related, 0.8);
 
-        let chain = follow_chain(&storage, a, &[RelationType::Related], None).await;
+        let chain = follow_chain(&storage, a, &[RelationType::Related], None, None)
+            .await
+            .links;
         // Should only visit b once, not loop
         assert_eq!(chain.len(), 1);
         assert_eq!(chain[0].memory_id, b);
+        assert!(!chain[0].cycle);
+    }
+
+    #[tokio::test]
+    async fn test_follow_chain_marks_cycle_links() {
+        let storage = MockGraphStorage::new();
+        let (a, b, c) = (Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7());
+
+        // a -> b -> c -> a
+        storage.add_mock_relation(a, b, RelationType::CausedBy, 0.9);
+        storage.add_mock_relation(b, c, RelationType::Fixes, 0.8);
+        storage.add_mock_relation(c, a, RelationType::Related, 0.5);
+
+        let types = [
+            RelationType::CausedBy,
+            RelationType::Fixes,
+            RelationType::Related,
+        ];
+        let chain = follow_chain(&storage, a, &types, None, None).await;
+        assert_eq!(chain.links.len(), 3);
+        assert_eq!(chain.node_count(), 2);
+        let back = &chain.links[2];
+        assert!(back.cycle);
+        assert_eq!((back.from_id, back.memory_id), (c, a));
+        assert!(!chain.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_follow_chain_max_nodes() {
+        let storage = MockGraphStorage::new();
+        let hub = Uuid::now_v7();
+        for _ in 0..5 {
+            storage.add_mock_relation(hub, Uuid::now_v7(), RelationType::Related, 0.5);
+        }
+
+        let chain = follow_chain(&storage, hub, &[RelationType::Related], None, Some(2)).await;
+        assert_eq!(chain.node_count(), 2);
+        assert!(chain.truncated);
+
+        let chain = follow_chain(&storage, hub, &[RelationType::Related], None, Some(5)).await;
+        assert_eq!(chain.node_count(), 5);
+        assert!(!chain.truncated);
     }
 
     #[tokio::test]
@@ -401,7 +687,9 @@ mod tests {
         storage.add_mock_relation(c, d, RelationType::Related, 0.6);
 
         // Limit to depth 2 — should only reach b and c, not d
-        let chain = follow_chain(&storage, a, &[RelationType::Related], Some(2)).await;
+        let chain = follow_chain(&storage, a, &[RelationType::Related], Some(2), None)
+            .await
+            .links;
         let ids: Vec<Uuid> = chain.iter().map(|l| l.memory_id).collect();
         assert!(ids.contains(&b));
         assert!(ids.contains(&c));
@@ -419,7 +707,9 @@ mod tests {
         storage.add_mock_relation(a, c, RelationType::Related, 0.7);
 
         // Only follow Fixes relations
-        let chain = follow_chain(&storage, a, &[RelationType::Fixes], None).await;
+        let chain = follow_chain(&storage, a, &[RelationType::Fixes], None, None)
+            .await
+            .links;
         assert_eq!(chain.len(), 1);
         assert_eq!(chain[0].memory_id, b);
     }
@@ -439,8 +729,10 @@ mod tests {
             a,
             &[RelationType::CausedBy, RelationType::Fixes],
             None,
+            None,
         )
-        .await;
+        .await
+        .links;
         assert_eq!(chain.len(), 2);
     }
 
@@ -453,7 +745,9 @@ mod tests {
         storage.add_mock_relation(a, b, RelationType::Related, 0.8);
 
         // Depth 0 means don't traverse at all
-        let chain = follow_chain(&storage, a, &[RelationType::Related], Some(0)).await;
+        let chain = follow_chain(&storage, a, &[RelationType::Related], Some(0), None)
+            .await
+            .links;
         assert!(chain.is_empty());
     }
 
@@ -526,4 +820,46 @@ mod tests {
         let created = semantic_auto_relate(&storage, m1.id, &[0.0; 128], Some(0.5), None).await;
         assert_eq!(created, 0, "should skip already-related memory");
     }
+
+    #[test]
+    fn test_group_by_chain() {
+        let ids: Vec<Uuid> = (0..5).map(|_| Uuid::now_v7()).collect();
+        let link = |a: usize, b: usize, relation_type| MemoryRelation {
+            source_id: ids[a],
+            target_id: ids[b],
+            relation_type,
+            strength: 1.0,
+        };
+        let relations = vec![
+            // fix (3) fixes error (1); lesson (4) caused by error (1)
+            link(3, 1, RelationType::Fixes),
+            link(4, 1, RelationType::CausedBy),
+            // Related never groups
+            link(0, 2, RelationType::Related),
+            // Links to memories outside the results are ignored
+            MemoryRelation {
+                target_id: Uuid::now_v7(),
+                ..link(0, 0, RelationType::Fixes)
+            },
+        ];
+
+        let groups = group_by_chain(&ids, &relations);
+        assert_eq!(
+            groups,
+            vec![
+                ResultGroup {
+                    primary: 0,
+                    members: vec![]
+                },
+                ResultGroup {
+                    primary: 1,
+                    members: vec![3, 4]
+                },
+                ResultGroup {
+                    primary: 2,
+                    members: vec![]
+                },
+            ]
+        );
+    }
 }
@@ -2,17 +2,28 @@
 //!
 //! Events are stored as JSONL at `~/.config/shabka/history.jsonl`.
 //! Each line is a self-contained [`MemoryEvent`] that records who did what and when.
+//! With `[history] snapshot_on_delete`, deletion events also carry the full
+//! memory as it was, so it can be restored or audited afterwards.
+//! Changes made through an API token (see [`crate::api_tokens`]) record
+//! the token's name alongside the actor. Creations and supersessions are
+//! also handed to the `[plugins]` commands (see [`crate::plugins`]).
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::io::Write;
 use std::path::PathBuf;
 use uuid::Uuid;
 
-use crate::model::{Memory, UpdateMemoryInput};
+use crate::config::HistoryConfig;
+use crate::error::{Result, ShabkaError};
+use crate::model::{CodeSnippet, Memory, TimelineQuery, UpdateMemoryInput};
+use crate::plugins::{LifecycleEvent, Plugins};
+use crate::storage::StorageBackend;
 
 /// What happened to the memory.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum EventAction {
     Created,
@@ -23,6 +34,24 @@ pub enum EventAction {
     Superseded,
 }
 
+impl std::str::FromStr for EventAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "created" => Ok(Self::Created),
+            "updated" => Ok(Self::Updated),
+            "deleted" => Ok(Self::Deleted),
+            "archived" => Ok(Self::Archived),
+            "imported" => Ok(Self::Imported),
+            "superseded" => Ok(Self::Superseded),
+            _ => Err(format!(
+                "unknown action: {s} (created, updated, deleted, archived, imported, superseded)"
+            )),
+        }
+    }
+}
+
 impl std::fmt::Display for EventAction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -37,7 +66,7 @@ impl std::fmt::Display for EventAction {
 }
 
 /// A single field change in an update.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FieldChange {
     pub field: String,
     pub old_value: String,
@@ -45,7 +74,7 @@ pub struct FieldChange {
 }
 
 /// A single audit event.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MemoryEvent {
     pub id: Uuid,
     pub memory_id: Uuid,
@@ -56,6 +85,19 @@ pub struct MemoryEvent {
     pub changes: Vec<FieldChange>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub memory_title: Option<String>,
+    /// Machine the change was made on; filled in by [`HistoryLogger::log`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
+    /// The memory as it was before deletion (see [`MemoryEvent::with_snapshot`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<Box<Memory>>,
+    /// Whether the snapshot's content was cut to fit `snapshot_max_bytes`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub snapshot_truncated: bool,
+    /// Name of the API token the change was made with; filled in by
+    /// [`HistoryLogger::log`] inside [`with_token`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
 }
 
 impl MemoryEvent {
@@ -68,6 +110,10 @@ impl MemoryEvent {
             timestamp: Utc::now(),
             changes: Vec::new(),
             memory_title: None,
+            device_id: None,
+            snapshot: None,
+            snapshot_truncated: false,
+            token: None,
         }
     }
 
@@ -80,12 +126,157 @@ impl MemoryEvent {
         self.changes = changes;
         self
     }
+
+    /// Attach the memory's title and full state. The logger keeps the
+    /// snapshot only when `snapshot_on_delete` is set.
+    pub fn with_snapshot(mut self, memory: &Memory) -> Self {
+        self.memory_title = Some(memory.title.clone());
+        self.snapshot = Some(Box::new(memory.clone()));
+        self
+    }
+}
+
+/// Cut `memory`'s content until it serializes to at most `max_bytes`.
+/// Returns whether anything was cut, or `None` if it can't be made to fit.
+fn cap_snapshot(memory: &mut Memory, max_bytes: usize) -> Option<bool> {
+    let size = |m: &Memory| {
+        serde_json::to_string(m)
+            .map(|j| j.len())
+            .unwrap_or(usize::MAX)
+    };
+    let over = size(memory).saturating_sub(max_bytes);
+    if over == 0 {
+        return Some(false);
+    }
+    // Escaping can make content longer in JSON than in memory; cut the
+    // overflow plus a margin, then check again.
+    let mut keep = memory.content.len().checked_sub(over + 64)?;
+    while !memory.content.is_char_boundary(keep) {
+        keep -= 1;
+    }
+    memory.content.truncate(keep);
+    memory.content.push('…');
+    (size(memory) <= max_bytes).then_some(true)
+}
+
+/// Which events [`HistoryLogger::query`] returns. Empty fields match
+/// everything.
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    pub memory_id: Option<Uuid>,
+    /// Actor or API token name, compared case-insensitively.
+    pub actor: Option<String>,
+    pub actions: Vec<EventAction>,
+    pub since: Option<DateTime<Utc>>,
+    /// Exclusive upper bound.
+    pub until: Option<DateTime<Utc>>,
+    pub project: Option<String>,
+    /// Memories currently in `project`, filled in by
+    /// [`HistoryFilter::resolve_project`]. Deleted memories match through
+    /// their snapshot instead.
+    pub project_memories: HashSet<Uuid>,
+}
+
+impl HistoryFilter {
+    /// Look up which stored memories belong to `project`.
+    pub async fn resolve_project(&mut self, storage: &impl StorageBackend) -> Result<()> {
+        let Some(project) = &self.project else {
+            return Ok(());
+        };
+        let entries = storage
+            .timeline(&TimelineQuery {
+                project_id: Some(project.clone()),
+                limit: 100_000,
+                ..Default::default()
+            })
+            .await?;
+        self.project_memories = entries.into_iter().map(|e| e.id).collect();
+        Ok(())
+    }
+
+    pub fn matches(&self, event: &MemoryEvent) -> bool {
+        if self.memory_id.is_some_and(|id| id != event.memory_id) {
+            return false;
+        }
+        if let Some(actor) = &self.actor {
+            let by_token = event
+                .token
+                .as_deref()
+                .is_some_and(|t| t.eq_ignore_ascii_case(actor));
+            if !event.actor.eq_ignore_ascii_case(actor) && !by_token {
+                return false;
+            }
+        }
+        if !self.actions.is_empty() && !self.actions.contains(&event.action) {
+            return false;
+        }
+        if self.since.is_some_and(|since| event.timestamp < since) {
+            return false;
+        }
+        if self.until.is_some_and(|until| event.timestamp >= until) {
+            return false;
+        }
+        if let Some(project) = &self.project {
+            let in_snapshot = event
+                .snapshot
+                .as_ref()
+                .is_some_and(|m| m.project_id.as_deref() == Some(project.as_str()));
+            if !in_snapshot && !self.project_memories.contains(&event.memory_id) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Parse a history time bound: `YYYY-MM-DD`, RFC 3339, or a span back from
+/// `now` such as `12h`, `7d` or `2w`. With `end_of_day`, a bare date means
+/// the end of that day, so `--until 2025-01-07` includes the 7th.
+pub fn parse_time(value: &str, end_of_day: bool, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+    if let Some(span) = parse_span(value) {
+        return Ok(now - span?);
+    }
+    let time = crate::query::parse_date("time", value)?;
+    let bare_date = value.len() == "YYYY-MM-DD".len();
+    Ok(if end_of_day && bare_date {
+        time + Duration::days(1)
+    } else {
+        time
+    })
+}
+
+tokio::task_local! {
+    static TOKEN: String;
+}
+
+/// Run `fut` so that events it logs record API token `name` (see
+/// [`MemoryEvent::token`]).
+pub async fn with_token<F: std::future::Future>(name: String, fut: F) -> F::Output {
+    TOKEN.scope(name, fut).await
+}
+
+/// A span such as `12h`, `7d` or `2w`; `None` when `value` isn't one.
+pub(crate) fn parse_span(value: &str) -> Option<Result<Duration>> {
+    let unit = value.chars().last().filter(char::is_ascii_alphabetic)?;
+    let n = value[..value.len() - 1].parse::<i64>().ok()?;
+    Some(match unit.to_ascii_lowercase() {
+        'h' => Ok(Duration::hours(n)),
+        'd' => Ok(Duration::days(n)),
+        'w' => Ok(Duration::weeks(n)),
+        _ => Err(ShabkaError::InvalidInput(format!(
+            "unknown time unit in '{value}' (use h, d or w)"
+        ))),
+    })
 }
 
 /// Append-only JSONL logger for memory events.
 pub struct HistoryLogger {
     path: PathBuf,
     enabled: bool,
+    /// Snapshot size cap; `None` drops snapshots.
+    snapshot_max_bytes: Option<usize>,
+    plugins: Plugins,
 }
 
 impl HistoryLogger {
@@ -94,18 +285,89 @@ impl HistoryLogger {
             .unwrap_or_else(|| PathBuf::from("."))
             .join("shabka")
             .join("history.jsonl");
-        Self { path, enabled }
+        Self::with_path(path, enabled)
     }
 
-    /// Log a single event by appending one JSON line.
+    /// A logger writing to `path`, without snapshots.
+    pub fn with_path(path: PathBuf, enabled: bool) -> Self {
+        Self {
+            path,
+            enabled,
+            snapshot_max_bytes: None,
+            plugins: Plugins::default(),
+        }
+    }
+
+    /// Run `plugins` on the lifecycle events this logger sees.
+    pub fn with_plugins(mut self, plugins: Plugins) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    /// The plugins run on lifecycle events, for events that aren't memory
+    /// changes (e.g. a finished consolidation).
+    pub fn plugins(&self) -> &Plugins {
+        &self.plugins
+    }
+
+    /// A logger following `[history]`.
+    pub fn from_config(config: &HistoryConfig) -> Self {
+        let logger = Self::new(config.enabled);
+        Self::from_config_at(config, logger.path)
+    }
+
+    /// A logger following `[history]`, writing to `path`.
+    pub fn from_config_at(config: &HistoryConfig, path: PathBuf) -> Self {
+        let mut logger = Self::with_path(path, config.enabled);
+        if config.snapshot_on_delete {
+            logger.snapshot_max_bytes = Some(config.snapshot_max_bytes);
+        }
+        logger
+    }
+
+    /// Whether deletion events keep snapshots, so callers know whether
+    /// fetching the full memory before deleting is worth it.
+    pub fn snapshots_enabled(&self) -> bool {
+        self.enabled && self.snapshot_max_bytes.is_some()
+    }
+
+    /// Log a single event by appending one JSON line. Events without a
+    /// device are recorded as made on this machine. Plugins subscribed to
+    /// the event run even when history is disabled.
     pub fn log(&self, event: &MemoryEvent) {
+        if !self.plugins.is_empty() {
+            if let Some(lifecycle) = LifecycleEvent::from_history(event) {
+                self.plugins.emit(&lifecycle);
+            }
+        }
         if !self.enabled {
             return;
         }
         if let Some(parent) = self.path.parent() {
             let _ = std::fs::create_dir_all(parent);
         }
-        let line = match serde_json::to_string(event) {
+        let mut event = event.clone();
+        if event.device_id.is_none() {
+            event.device_id = Some(crate::devices::local().id.clone());
+        }
+        if event.token.is_none() {
+            event.token = TOKEN.try_with(String::clone).ok();
+        }
+        if let Some(mut snapshot) = event.snapshot.take() {
+            if let Some(max_bytes) = self.snapshot_max_bytes {
+                match cap_snapshot(&mut snapshot, max_bytes) {
+                    Some(truncated) => {
+                        event.snapshot = Some(snapshot);
+                        event.snapshot_truncated = truncated;
+                    }
+                    None => tracing::debug!(
+                        "history: snapshot of {} exceeds {max_bytes} bytes, dropped",
+                        event.memory_id
+                    ),
+                }
+            }
+        }
+        let line = match serde_json::to_string(&event) {
             Ok(l) => l,
             Err(e) => {
                 tracing::debug!("history: failed to serialize event: {e}");
@@ -142,6 +404,22 @@ impl HistoryLogger {
         events
     }
 
+    /// Events matching `filter`, most recent first, at most `limit`.
+    pub fn query(&self, filter: &HistoryFilter, limit: usize) -> Vec<MemoryEvent> {
+        let mut events = self.read_all();
+        events.retain(|e| filter.matches(e));
+        events.reverse();
+        events.truncate(limit);
+        events
+    }
+
+    /// The memory as it was when last deleted, if a snapshot was kept.
+    pub fn last_snapshot(&self, memory_id: Uuid) -> Option<MemoryEvent> {
+        self.history_for(memory_id)
+            .into_iter()
+            .find(|e| e.action == EventAction::Deleted && e.snapshot.is_some())
+    }
+
     fn read_all(&self) -> Vec<MemoryEvent> {
         let contents = match std::fs::read_to_string(&self.path) {
             Ok(c) => c,
@@ -187,6 +465,35 @@ pub fn diff_update(old: &Memory, input: &UpdateMemoryInput) -> Vec<FieldChange>
             });
         }
     }
+    if let Some(ref new_attachments) = input.attachments {
+        let names = |a: &[crate::model::Attachment]| {
+            a.iter()
+                .map(|a| a.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let (old_names, new_names) = (names(&old.attachments), names(new_attachments));
+        if old_names != new_names {
+            changes.push(FieldChange {
+                field: "attachments".to_string(),
+                old_value: old_names,
+                new_value: new_names,
+            });
+        }
+    }
+    if let Some(ref new_code) = input.code {
+        let symbol = |c: Option<&CodeSnippet>| {
+            c.map(|c| c.symbol.clone().unwrap_or_else(|| "snippet".to_string()))
+                .unwrap_or_default()
+        };
+        if old.code.as_ref() != Some(new_code) {
+            changes.push(FieldChange {
+                field: "code".to_string(),
+                old_value: symbol(old.code.as_ref()),
+                new_value: symbol(Some(new_code)),
+            });
+        }
+    }
     if let Some(new_importance) = input.importance {
         if (new_importance - old.importance).abs() > f32::EPSILON {
             changes.push(FieldChange {
@@ -214,6 +521,26 @@ pub fn diff_update(old: &Memory, input: &UpdateMemoryInput) -> Vec<FieldChange>
             });
         }
     }
+    if let Some(ref new_label) = input.label {
+        let old_label = old.label.clone().unwrap_or_default();
+        if *new_label != old_label {
+            changes.push(FieldChange {
+                field: "label".to_string(),
+                old_value: old_label,
+                new_value: new_label.clone(),
+            });
+        }
+    }
+    if let Some(ref new_owner) = input.owner {
+        let old_owner = old.owner.clone().unwrap_or_default();
+        if *new_owner != old_owner {
+            changes.push(FieldChange {
+                field: "owner".to_string(),
+                old_value: old_owner,
+                new_value: new_owner.clone(),
+            });
+        }
+    }
 
     changes
 }
@@ -288,6 +615,140 @@ mod tests {
         logger.log(&event);
     }
 
+    #[test]
+    fn test_snapshot_kept_only_when_enabled() {
+        let path = std::env::temp_dir().join(format!("shabka-history-{}.jsonl", Uuid::now_v7()));
+        let memory = Memory::new(
+            "Pool size".to_string(),
+            "x".repeat(4000),
+            MemoryKind::Fact,
+            "user".to_string(),
+        );
+        let event = MemoryEvent::new(memory.id, EventAction::Deleted, "user".to_string())
+            .with_snapshot(&memory);
+
+        HistoryLogger::with_path(path.clone(), true).log(&event);
+        let logger = HistoryLogger {
+            snapshot_max_bytes: Some(2048),
+            ..HistoryLogger::with_path(path.clone(), true)
+        };
+        assert!(logger.snapshots_enabled());
+        logger.log(&event);
+
+        let events = logger.history_for(memory.id);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(events.len(), 2);
+        assert!(events[1].snapshot.is_none(), "disabled logger drops it");
+        assert_eq!(events[1].memory_title.as_deref(), Some("Pool size"));
+        let kept = events[0].snapshot.as_ref().unwrap();
+        assert!(events[0].snapshot_truncated);
+        assert!(serde_json::to_string(kept).unwrap().len() <= 2048);
+        assert!(kept.content.ends_with('…'));
+        assert_eq!(kept.title, "Pool size");
+    }
+
+    #[tokio::test]
+    async fn test_with_token_records_token_name() {
+        let path = std::env::temp_dir().join(format!("shabka-history-{}.jsonl", Uuid::now_v7()));
+        let logger = HistoryLogger::with_path(path.clone(), true);
+        let id = Uuid::now_v7();
+        logger.log(&MemoryEvent::new(id, EventAction::Updated, "user".into()));
+        with_token("ci-bot".to_string(), async {
+            logger.log(&MemoryEvent::new(id, EventAction::Deleted, "user".into()));
+        })
+        .await;
+
+        let events = logger.history_for(id);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(events[0].token.as_deref(), Some("ci-bot"));
+        assert_eq!(events[1].token, None);
+        let by_token = HistoryFilter {
+            actor: Some("CI-BOT".to_string()),
+            ..Default::default()
+        };
+        assert!(by_token.matches(&events[0]));
+        assert!(!by_token.matches(&events[1]));
+    }
+
+    #[test]
+    fn test_cap_snapshot_leaves_small_memories_alone() {
+        let mut memory = Memory::new(
+            "T".to_string(),
+            "short".to_string(),
+            MemoryKind::Fact,
+            "user".to_string(),
+        );
+        assert_eq!(cap_snapshot(&mut memory, 64 * 1024), Some(false));
+        assert_eq!(memory.content, "short");
+        assert_eq!(cap_snapshot(&mut memory, 10), None);
+    }
+
+    #[test]
+    fn test_filter_matches_actor_action_time_and_project() {
+        let now = Utc::now();
+        let mut memory = Memory::new(
+            "Pool size".to_string(),
+            "20".to_string(),
+            MemoryKind::Fact,
+            "user".to_string(),
+        );
+        memory.project_id = Some("api".to_string());
+        let mut created = MemoryEvent::new(memory.id, EventAction::Created, "Agent".to_string());
+        created.timestamp = now - Duration::days(10);
+        let deleted = MemoryEvent::new(memory.id, EventAction::Deleted, "alice".to_string())
+            .with_snapshot(&memory);
+
+        let by_actor = HistoryFilter {
+            actor: Some("agent".to_string()),
+            ..Default::default()
+        };
+        assert!(by_actor.matches(&created));
+        assert!(!by_actor.matches(&deleted));
+
+        let last_week = HistoryFilter {
+            since: Some(now - Duration::days(7)),
+            actions: vec![EventAction::Deleted, EventAction::Updated],
+            ..Default::default()
+        };
+        assert!(!last_week.matches(&created));
+        assert!(last_week.matches(&deleted));
+
+        let mut api = HistoryFilter {
+            project: Some("api".to_string()),
+            ..Default::default()
+        };
+        assert!(!api.matches(&created));
+        assert!(api.matches(&deleted), "snapshot carries the project");
+        api.project_memories.insert(memory.id);
+        assert!(api.matches(&created));
+    }
+
+    #[test]
+    fn test_parse_time() {
+        let now = DateTime::parse_from_rfc3339("2025-03-10T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            parse_time("7d", false, now).unwrap(),
+            now - Duration::days(7)
+        );
+        assert_eq!(
+            parse_time("2w", false, now).unwrap(),
+            now - Duration::weeks(2)
+        );
+        assert_eq!(
+            parse_time("2025-03-01", true, now).unwrap().to_rfc3339(),
+            "2025-03-02T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_time("2025-03-01", false, now).unwrap().to_rfc3339(),
+            "2025-03-01T00:00:00+00:00"
+        );
+        assert!(parse_time("7y", false, now).is_err());
+        assert!(parse_time("yesterday", false, now).is_err());
+        assert_eq!("Deleted".parse::<EventAction>(), Ok(EventAction::Deleted));
+    }
+
     #[test]
     fn test_diff_update_status_change() {
         let old = Memory::new(
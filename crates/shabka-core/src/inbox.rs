@@ -0,0 +1,589 @@
+//! The inbox: everything in the memory base waiting on a person.
+//!
+//! [`collect`] gathers actionable items from storage — the review queue
+//! (auto-captured memories held as pending), quarantined memories, likely
+//! duplicates, contradiction pairs, verifications that have lapsed or are
+//! about to, unsettled memories assigned to the user and captures whose
+//! kind looks wrong — plus captures parked in the quota backlog and sync
+//! records left unreadable by a conflicted pull. The CLI (`shabka inbox`), TUI, web
+//! dashboard and MCP server all show this one list.
+//!
+//! Consolidation candidates are expensive to find (every memory is
+//! embedded), so callers add them separately with
+//! [`Inbox::add_consolidation`] when they have a preview.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{DateTime, Duration, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::assess;
+use crate::config::ShabkaConfig;
+use crate::consolidate::ClusterPreview;
+use crate::error::Result;
+use crate::model::{Memory, MemoryStatus, RelationType, TimelineQuery, VerificationStatus};
+use crate::quota::CaptureBacklog;
+use crate::sharing;
+use crate::similarity;
+use crate::storage::{Storage, StorageBackend};
+use crate::sync::SyncRepo;
+
+/// Most memories scanned per status.
+const SCAN_LIMIT: usize = 10_000;
+
+/// Verified memories expiring within this many days are listed by default.
+pub const DEFAULT_EXPIRING_DAYS: i64 = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InboxKind {
+    /// Auto-captured memory awaiting approval (`shabka review`).
+    Review,
+    /// Memory held back by content screening (`shabka quarantine`).
+    Quarantine,
+    /// Two active, unrelated memories similar enough to be the same thing.
+    Duplicate,
+    /// Two active memories marked as contradicting each other.
+    Contradiction,
+    /// Verified memory whose verification has expired.
+    VerificationExpired,
+    /// Verified memory whose verification expires soon.
+    VerificationExpiring,
+    /// Memory assigned to the user (`shabka assign`) that is unverified,
+    /// disputed or due for re-verification.
+    Assigned,
+    /// Auto-captured memory whose content reads like another kind
+    /// ([`crate::assess::suggest_kind`]).
+    Reclassify,
+    /// Capture parked by a quota, not yet saved.
+    Backlog,
+    /// Sync record that can't be read, usually after a conflicted pull.
+    SyncConflict,
+    /// Similar memories a consolidation run would merge.
+    Consolidation,
+}
+
+impl InboxKind {
+    pub const ALL: [InboxKind; 11] = [
+        InboxKind::Review,
+        InboxKind::Quarantine,
+        InboxKind::Duplicate,
+        InboxKind::Contradiction,
+        InboxKind::VerificationExpired,
+        InboxKind::VerificationExpiring,
+        InboxKind::Assigned,
+        InboxKind::Reclassify,
+        InboxKind::Backlog,
+        InboxKind::SyncConflict,
+        InboxKind::Consolidation,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InboxKind::Review => "review",
+            InboxKind::Quarantine => "quarantine",
+            InboxKind::Duplicate => "duplicate",
+            InboxKind::Contradiction => "contradiction",
+            InboxKind::VerificationExpired => "verification_expired",
+            InboxKind::VerificationExpiring => "verification_expiring",
+            InboxKind::Assigned => "assigned",
+            InboxKind::Reclassify => "reclassify",
+            InboxKind::Backlog => "backlog",
+            InboxKind::SyncConflict => "sync_conflict",
+            InboxKind::Consolidation => "consolidation",
+        }
+    }
+
+    /// Heading for lists grouped by kind.
+    pub fn label(self) -> &'static str {
+        match self {
+            InboxKind::Review => "Awaiting review",
+            InboxKind::Quarantine => "Quarantined",
+            InboxKind::Duplicate => "Possible duplicates",
+            InboxKind::Contradiction => "Contradictions",
+            InboxKind::VerificationExpired => "Verification expired",
+            InboxKind::VerificationExpiring => "Verification expiring",
+            InboxKind::Assigned => "Assigned to me",
+            InboxKind::Reclassify => "Kind suggestions",
+            InboxKind::Backlog => "Capture backlog",
+            InboxKind::SyncConflict => "Sync conflicts",
+            InboxKind::Consolidation => "Consolidation candidates",
+        }
+    }
+
+    /// The command that deals with this kind of item.
+    pub fn hint(self) -> &'static str {
+        match self {
+            InboxKind::Review => "shabka review --approve <id> | --reject <id>",
+            InboxKind::Quarantine => "shabka quarantine --release <id> | --delete <id>",
+            InboxKind::Duplicate => "shabka inbox --triage",
+            InboxKind::Consolidation => "shabka consolidate",
+            InboxKind::Reclassify => "shabka assess --fix",
+            InboxKind::Contradiction => "shabka verify <id> --status outdated",
+            InboxKind::VerificationExpired
+            | InboxKind::VerificationExpiring
+            | InboxKind::Assigned => "shabka verify <id> --status verified",
+            InboxKind::Backlog => "drained on the next capture once the quota allows",
+            InboxKind::SyncConflict => "resolve the file in the sync repo, then shabka sync",
+        }
+    }
+}
+
+impl std::str::FromStr for InboxKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        InboxKind::ALL
+            .into_iter()
+            .find(|k| k.as_str() == s.replace('-', "_"))
+            .ok_or_else(|| format!("unknown inbox kind: {s}"))
+    }
+}
+
+/// One thing to act on.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct InboxItem {
+    pub kind: InboxKind,
+    /// Stored memories involved; the first is the one to open. Empty for
+    /// backlog captures and sync conflicts.
+    pub memory_ids: Vec<Uuid>,
+    pub title: String,
+    pub detail: String,
+    /// When the item became actionable, as near as can be told.
+    pub since: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct Inbox {
+    pub total: usize,
+    /// Items per [`InboxKind::as_str`].
+    pub counts: BTreeMap<String, usize>,
+    /// Newest first.
+    pub items: Vec<InboxItem>,
+}
+
+impl Inbox {
+    fn push(&mut self, item: InboxItem) {
+        *self
+            .counts
+            .entry(item.kind.as_str().to_string())
+            .or_insert(0) += 1;
+        self.total += 1;
+        self.items.push(item);
+    }
+
+    fn sort(&mut self) {
+        self.items.sort_by_key(|item| Reverse(item.since));
+    }
+
+    /// Add consolidation candidates from [`crate::consolidate::preview`].
+    pub fn add_consolidation(&mut self, previews: &[ClusterPreview]) {
+        for preview in previews {
+            let Some(first) = preview.members.first() else {
+                continue;
+            };
+            self.push(InboxItem {
+                kind: InboxKind::Consolidation,
+                memory_ids: preview.members.iter().map(|m| m.id).collect(),
+                title: first.title.clone(),
+                detail: format!(
+                    "{} similar memories could be merged (mean similarity {:.2})",
+                    preview.members.len(),
+                    preview.similarity.mean
+                ),
+                since: preview.newest,
+            });
+        }
+        self.sort();
+    }
+
+    /// Keep only items of `kinds` (all when empty).
+    pub fn retain_kinds(&mut self, kinds: &[InboxKind]) {
+        if kinds.is_empty() {
+            return;
+        }
+        self.items.retain(|i| kinds.contains(&i.kind));
+        self.counts
+            .retain(|k, _| kinds.iter().any(|kind| kind.as_str() == k));
+        self.total = self.items.len();
+    }
+
+    /// Items of one kind, newest first.
+    pub fn of_kind(&self, kind: InboxKind) -> impl Iterator<Item = &InboxItem> {
+        self.items.iter().filter(move |i| i.kind == kind)
+    }
+}
+
+/// What [`collect`] looks at besides storage.
+pub struct InboxOptions {
+    pub now: DateTime<Utc>,
+    /// Verified memories expiring within this many days are listed.
+    pub expiring_days: i64,
+    /// Similarity above which two active memories are listed as duplicates;
+    /// `None` skips the check.
+    pub duplicate_threshold: Option<f32>,
+    pub backlog: Option<CaptureBacklog>,
+    pub sync: Option<SyncRepo>,
+}
+
+impl InboxOptions {
+    /// Defaults for `config`: the update-dedup threshold for duplicates, the
+    /// standard backlog file and the sync repository if one is set up.
+    pub fn from_config(config: &ShabkaConfig) -> Self {
+        Self {
+            now: Utc::now(),
+            expiring_days: DEFAULT_EXPIRING_DAYS,
+            duplicate_threshold: Some(config.graph.dedup_update_threshold),
+            backlog: Some(CaptureBacklog::new()),
+            sync: SyncRepo::new(&config.sync)
+                .ok()
+                .filter(SyncRepo::is_initialized),
+        }
+    }
+}
+
+async fn memories_with_status(
+    storage: &Storage,
+    status: MemoryStatus,
+    user_id: &str,
+) -> Result<Vec<Memory>> {
+    let mut entries = storage
+        .timeline(&TimelineQuery {
+            status: Some(status),
+            limit: SCAN_LIMIT,
+            ..Default::default()
+        })
+        .await?;
+    entries.retain(|e| sharing::is_visible(e.privacy, &e.created_by, user_id));
+    if entries.is_empty() {
+        return Ok(Vec::new());
+    }
+    let ids: Vec<Uuid> = entries.iter().map(|e| e.id).collect();
+    storage.get_memories(&ids).await
+}
+
+/// Why a memory assigned to someone still needs them, if it does.
+fn assignment_reason(memory: &Memory, now: DateTime<Utc>) -> Option<&'static str> {
+    match memory.verification {
+        VerificationStatus::Unverified => Some("Unverified"),
+        VerificationStatus::Disputed => Some("Disputed"),
+        VerificationStatus::Verified if crate::trust::verification_due(memory, now) => {
+            Some("Verification expired")
+        }
+        VerificationStatus::Verified | VerificationStatus::Outdated => None,
+    }
+}
+
+/// Everything actionable that `user_id` can see, newest first.
+pub async fn collect(storage: &Storage, user_id: &str, options: &InboxOptions) -> Result<Inbox> {
+    let now = options.now;
+    let mut inbox = Inbox::default();
+
+    for memory in memories_with_status(storage, MemoryStatus::Pending, user_id).await? {
+        inbox.push(InboxItem {
+            kind: InboxKind::Review,
+            memory_ids: vec![memory.id],
+            detail: format!("Captured {} awaiting approval", memory.kind),
+            title: memory.title,
+            since: memory.created_at,
+        });
+    }
+
+    for memory in memories_with_status(storage, MemoryStatus::Quarantined, user_id).await? {
+        inbox.push(InboxItem {
+            kind: InboxKind::Quarantine,
+            memory_ids: vec![memory.id],
+            detail: "Held back by content screening".to_string(),
+            title: memory.title,
+            since: memory.updated_at,
+        });
+    }
+
+    let active = memories_with_status(storage, MemoryStatus::Active, user_id).await?;
+    let soon = now + Duration::days(options.expiring_days);
+    for memory in &active {
+        if memory.owner.as_deref() == Some(user_id) {
+            if let Some(reason) = assignment_reason(memory, now) {
+                inbox.push(InboxItem {
+                    kind: InboxKind::Assigned,
+                    memory_ids: vec![memory.id],
+                    title: memory.title.clone(),
+                    detail: format!("{reason}, assigned to you"),
+                    since: memory.updated_at,
+                });
+                continue;
+            }
+        }
+        let Some(expires_at) = memory.verification_expires_at else {
+            continue;
+        };
+        if crate::trust::verification_due(memory, now) {
+            inbox.push(InboxItem {
+                kind: InboxKind::VerificationExpired,
+                memory_ids: vec![memory.id],
+                title: memory.title.clone(),
+                detail: format!("Verification expired on {}", expires_at.format("%Y-%m-%d")),
+                since: expires_at,
+            });
+        } else if memory.verification == VerificationStatus::Verified && expires_at <= soon {
+            inbox.push(InboxItem {
+                kind: InboxKind::VerificationExpiring,
+                memory_ids: vec![memory.id],
+                title: memory.title.clone(),
+                detail: format!("Verification expires on {}", expires_at.format("%Y-%m-%d")),
+                since: now,
+            });
+        }
+    }
+
+    for memory in &active {
+        if let Some(suggested) = assess::suggest_kind(memory) {
+            inbox.push(InboxItem {
+                kind: InboxKind::Reclassify,
+                memory_ids: vec![memory.id],
+                title: memory.title.clone(),
+                detail: format!("Suggested kind: {suggested} (captured as {})", memory.kind),
+                since: memory.created_at,
+            });
+        }
+    }
+
+    let by_id: HashMap<Uuid, &Memory> = active.iter().map(|m| (m.id, m)).collect();
+    let ids: Vec<Uuid> = active.iter().map(|m| m.id).collect();
+    let mut seen: HashSet<(Uuid, Uuid)> = HashSet::new();
+    for (id, count) in storage.count_contradictions(&ids).await? {
+        if count == 0 {
+            continue;
+        }
+        for relation in storage.get_relations(id).await? {
+            if relation.relation_type != RelationType::Contradicts {
+                continue;
+            }
+            let (Some(a), Some(b)) = (
+                by_id.get(&relation.source_id),
+                by_id.get(&relation.target_id),
+            ) else {
+                continue;
+            };
+            let key = (a.id.min(b.id), a.id.max(b.id));
+            if a.id == b.id || !seen.insert(key) {
+                continue;
+            }
+            inbox.push(InboxItem {
+                kind: InboxKind::Contradiction,
+                memory_ids: vec![a.id, b.id],
+                title: format!("{} ↔ {}", a.title, b.title),
+                detail: "Active memories contradict each other".to_string(),
+                since: a.created_at.max(b.created_at),
+            });
+        }
+    }
+
+    if let Some(threshold) = options.duplicate_threshold {
+        let embeddings = storage.stored_embeddings().await?;
+        let vectors: Vec<(Uuid, &[f32])> = active
+            .iter()
+            .filter_map(|m| embeddings.get(&m.id).map(|v| (m.id, v.as_slice())))
+            .collect();
+        for pair in similarity::similar_pairs(&vectors, threshold) {
+            let (a, b) = (by_id[&pair.source_id], by_id[&pair.target_id]);
+            // A pair already linked by any relation has been looked at.
+            let linked = storage
+                .get_relations(a.id)
+                .await?
+                .iter()
+                .any(|r| r.source_id == b.id || r.target_id == b.id);
+            if linked {
+                continue;
+            }
+            inbox.push(InboxItem {
+                kind: InboxKind::Duplicate,
+                memory_ids: vec![a.id, b.id],
+                title: format!("{} ≈ {}", a.title, b.title),
+                detail: format!("{:.0}% similar", pair.similarity * 100.0),
+                since: a.created_at.max(b.created_at),
+            });
+        }
+    }
+
+    if let Some(backlog) = &options.backlog {
+        for memory in backlog.read_all() {
+            inbox.push(InboxItem {
+                kind: InboxKind::Backlog,
+                memory_ids: Vec::new(),
+                detail: format!("{} held back by a capture quota", memory.kind),
+                title: memory.title,
+                since: memory.created_at,
+            });
+        }
+    }
+
+    if let Some(sync) = &options.sync {
+        for path in sync.conflicts() {
+            let since = std::fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .map(DateTime::<Utc>::from)
+                .unwrap_or(now);
+            inbox.push(InboxItem {
+                kind: InboxKind::SyncConflict,
+                memory_ids: Vec::new(),
+                title: path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                detail: format!("Unreadable sync record at {}", path.display()),
+                since,
+            });
+        }
+    }
+
+    inbox.sort();
+    Ok(inbox)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{MemoryKind, MemoryPrivacy, MemoryRelation, MemorySource};
+    use crate::storage::SqliteStorage;
+
+    fn memory(title: &str, status: MemoryStatus) -> Memory {
+        let mut m = Memory::new(
+            title.to_string(),
+            "Content".to_string(),
+            MemoryKind::Fact,
+            "alice".to_string(),
+        );
+        m.status = status;
+        m
+    }
+
+    fn options(now: DateTime<Utc>) -> InboxOptions {
+        InboxOptions {
+            now,
+            expiring_days: DEFAULT_EXPIRING_DAYS,
+            duplicate_threshold: Some(0.9),
+            backlog: None,
+            sync: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect_gathers_every_kind() {
+        let storage = Storage::Sqlite(SqliteStorage::open_in_memory().unwrap());
+        let now = Utc::now();
+
+        let pending = memory("Captured", MemoryStatus::Pending);
+        let quarantined = memory("Suspicious", MemoryStatus::Quarantined);
+        let mut lapsed = memory("Old fact", MemoryStatus::Active);
+        lapsed.verification = VerificationStatus::Verified;
+        lapsed.verification_expires_at = Some(now - Duration::days(3));
+        let mut expiring = memory("Fresh fact", MemoryStatus::Active);
+        expiring.verification = VerificationStatus::Verified;
+        expiring.verification_expires_at = Some(now + Duration::days(2));
+        let a = memory("Use JWT", MemoryStatus::Active);
+        let b = memory("Use sessions", MemoryStatus::Active);
+        let c = memory("Pool size is 20", MemoryStatus::Active);
+        let d = memory("Pool holds 20 connections", MemoryStatus::Active);
+        let e = memory("Retry twice", MemoryStatus::Active);
+        let f = memory("Retry two times", MemoryStatus::Active);
+        let mut assigned = memory("Disputed fact", MemoryStatus::Active);
+        assigned.verification = VerificationStatus::Disputed;
+        assigned.owner = Some("alice".to_string());
+        let mut bobs = memory("Bob's fact", MemoryStatus::Active);
+        bobs.owner = Some("bob".to_string());
+        let mut misfiled = memory("Switched to JWT", MemoryStatus::Active);
+        misfiled.kind = MemoryKind::Observation;
+        misfiled.content = "Decided on JWT instead of sessions".to_string();
+        misfiled.source = MemorySource::AutoCapture {
+            hook: "Stop".to_string(),
+        };
+        let mut hidden = memory("Bob's pending", MemoryStatus::Pending);
+        hidden.created_by = "bob".to_string();
+        hidden.privacy = MemoryPrivacy::Private;
+        for m in [
+            &pending,
+            &quarantined,
+            &lapsed,
+            &expiring,
+            &a,
+            &b,
+            &assigned,
+            &bobs,
+            &misfiled,
+            &hidden,
+        ] {
+            storage.save_memory(m, None).await.unwrap();
+        }
+        storage.save_memory(&c, Some(&[1.0, 0.0])).await.unwrap();
+        storage.save_memory(&d, Some(&[1.0, 0.01])).await.unwrap();
+        storage.save_memory(&e, Some(&[0.0, 1.0])).await.unwrap();
+        storage.save_memory(&f, Some(&[0.01, 1.0])).await.unwrap();
+        for (source, target, relation_type) in [
+            (a.id, b.id, RelationType::Contradicts),
+            (b.id, a.id, RelationType::Contradicts),
+            // Already looked at, so no longer a duplicate candidate.
+            (e.id, f.id, RelationType::Related),
+        ] {
+            storage
+                .add_relation(&MemoryRelation {
+                    source_id: source,
+                    target_id: target,
+                    relation_type,
+                    strength: 0.8,
+                })
+                .await
+                .unwrap();
+        }
+
+        let backlog_path =
+            std::env::temp_dir().join(format!("shabka-inbox-backlog-{}.jsonl", Uuid::now_v7()));
+        let backlog = CaptureBacklog::with_path(backlog_path.clone());
+        backlog
+            .push(&memory("Parked", MemoryStatus::Active))
+            .unwrap();
+        let options = InboxOptions {
+            backlog: Some(backlog),
+            ..options(now)
+        };
+
+        let inbox = collect(&storage, "alice", &options).await.unwrap();
+        let _ = std::fs::remove_file(backlog_path);
+        for kind in [
+            InboxKind::Review,
+            InboxKind::Quarantine,
+            InboxKind::Duplicate,
+            InboxKind::Contradiction,
+            InboxKind::VerificationExpired,
+            InboxKind::VerificationExpiring,
+            InboxKind::Assigned,
+            InboxKind::Reclassify,
+            InboxKind::Backlog,
+        ] {
+            assert_eq!(inbox.of_kind(kind).count(), 1, "{}", kind.as_str());
+        }
+        assert_eq!(inbox.total, 9);
+        assert_eq!(inbox.counts["contradiction"], 1);
+        assert!(inbox.items.windows(2).all(|w| w[0].since >= w[1].since));
+
+        let mut reviews = inbox.clone();
+        reviews.retain_kinds(&[InboxKind::Review]);
+        assert_eq!(reviews.total, 1);
+        assert_eq!(reviews.counts.len(), 1);
+    }
+
+    #[test]
+    fn test_kind_parses_from_str() {
+        for kind in InboxKind::ALL {
+            assert_eq!(kind.as_str().parse::<InboxKind>(), Ok(kind));
+        }
+        assert_eq!(
+            "sync-conflict".parse::<InboxKind>(),
+            Ok(InboxKind::SyncConflict)
+        );
+        assert!("nope".parse::<InboxKind>().is_err());
+    }
+}
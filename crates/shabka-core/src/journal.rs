@@ -0,0 +1,440 @@
+//! Write-ahead journal for multi-step capture operations.
+//!
+//! Superseding, merging and contradicting an existing memory each take more
+//! than one storage call. The hooks journal the intent before the first call
+//! and the outcome after the last, so an entry left open by a crashed process
+//! can be finished or rolled back by [`recover`] on a later run.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{Result, ShabkaError};
+use crate::model::{MemoryRelation, MemoryStatus, RelationType, UpdateMemoryInput};
+use crate::storage::StorageBackend;
+
+/// Open entries younger than this may still be in flight in another process,
+/// so recovery leaves them alone.
+pub const RECOVERY_GRACE_MINUTES: i64 = 5;
+
+/// A multi-step operation against an existing memory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum JournalOp {
+    /// Save `memory_id`, then mark `existing_id` superseded by it.
+    Supersede {
+        memory_id: Uuid,
+        existing_id: Uuid,
+        previous_status: MemoryStatus,
+        strength: f32,
+    },
+    /// Overwrite `existing_id` with a merged title and content.
+    Merge {
+        existing_id: Uuid,
+        title: String,
+        content: String,
+    },
+    /// Save `memory_id`, then link it as contradicting `existing_id`.
+    Contradict {
+        memory_id: Uuid,
+        existing_id: Uuid,
+        strength: f32,
+    },
+}
+
+impl JournalOp {
+    /// The new memory this operation saves first, if any.
+    fn saved_memory(&self) -> Option<Uuid> {
+        match self {
+            Self::Supersede { memory_id, .. } | Self::Contradict { memory_id, .. } => {
+                Some(*memory_id)
+            }
+            Self::Merge { .. } => None,
+        }
+    }
+
+    /// Apply the steps that follow saving the new memory. Idempotent, so
+    /// recovery can re-run it after a partial application.
+    pub async fn complete(&self, storage: &impl StorageBackend) -> Result<()> {
+        match self {
+            Self::Supersede {
+                memory_id,
+                existing_id,
+                strength,
+                ..
+            } => {
+                storage
+                    .update_memory(
+                        *existing_id,
+                        &UpdateMemoryInput {
+                            status: Some(MemoryStatus::Superseded),
+                            ..Default::default()
+                        },
+                    )
+                    .await?;
+                storage
+                    .add_relation(&MemoryRelation {
+                        source_id: *memory_id,
+                        target_id: *existing_id,
+                        relation_type: RelationType::Supersedes,
+                        strength: *strength,
+                    })
+                    .await
+            }
+            Self::Merge {
+                existing_id,
+                title,
+                content,
+            } => storage
+                .update_memory(
+                    *existing_id,
+                    &UpdateMemoryInput {
+                        title: Some(title.clone()),
+                        content: Some(content.clone()),
+                        ..Default::default()
+                    },
+                )
+                .await
+                .map(|_| ()),
+            Self::Contradict {
+                memory_id,
+                existing_id,
+                strength,
+            } => {
+                storage
+                    .add_relation(&MemoryRelation {
+                        source_id: *memory_id,
+                        target_id: *existing_id,
+                        relation_type: RelationType::Contradicts,
+                        strength: *strength,
+                    })
+                    .await
+            }
+        }
+    }
+
+    /// Undo whatever was applied when the new memory never got saved.
+    async fn roll_back(&self, storage: &impl StorageBackend) -> Result<()> {
+        if let Self::Supersede {
+            existing_id,
+            previous_status,
+            ..
+        } = self
+        {
+            match storage.get_memory(*existing_id).await {
+                Ok(existing) if existing.status == MemoryStatus::Superseded => {
+                    storage
+                        .update_memory(
+                            *existing_id,
+                            &UpdateMemoryInput {
+                                status: Some(*previous_status),
+                                ..Default::default()
+                            },
+                        )
+                        .await?;
+                }
+                Ok(_) | Err(ShabkaError::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How a journaled operation ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalOutcome {
+    /// Completed by the process that started it.
+    Applied,
+    /// Completed by a later recovery.
+    Finished,
+    /// Undone, because the new memory was never saved.
+    RolledBack,
+}
+
+impl std::fmt::Display for JournalOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Applied => write!(f, "applied"),
+            Self::Finished => write!(f, "finished"),
+            Self::RolledBack => write!(f, "rolled back"),
+        }
+    }
+}
+
+/// An operation whose intent was journaled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: Uuid,
+    pub started_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub op: JournalOp,
+}
+
+/// One line of the journal: an intent, or the outcome of an earlier intent.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum JournalRecord {
+    Intent(JournalEntry),
+    Outcome {
+        id: Uuid,
+        outcome: JournalOutcome,
+        at: DateTime<Utc>,
+    },
+}
+
+/// Append-only JSONL journal at `~/.config/shabka/capture_journal.jsonl`.
+/// The file is removed once every entry in it has an outcome.
+pub struct CaptureJournal {
+    path: PathBuf,
+}
+
+impl Default for CaptureJournal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CaptureJournal {
+    pub fn new() -> Self {
+        let path = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("/tmp"))
+            .join("shabka")
+            .join("capture_journal.jsonl");
+        Self { path }
+    }
+
+    pub fn with_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Journal the intent to run `op`. Returns the entry ID to finish it with.
+    pub fn begin(&self, op: JournalOp) -> std::io::Result<Uuid> {
+        let entry = JournalEntry {
+            id: Uuid::now_v7(),
+            started_at: Utc::now(),
+            op,
+        };
+        self.append(&JournalRecord::Intent(entry.clone()))?;
+        Ok(entry.id)
+    }
+
+    /// Record the outcome of entry `id`, dropping the file when nothing is left open.
+    pub fn finish(&self, id: Uuid, outcome: JournalOutcome) -> std::io::Result<()> {
+        self.append(&JournalRecord::Outcome {
+            id,
+            outcome,
+            at: Utc::now(),
+        })?;
+        if self.unfinished().is_empty() {
+            match std::fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Entries with no outcome yet, oldest first. Malformed lines are skipped.
+    pub fn unfinished(&self) -> Vec<JournalEntry> {
+        let Ok(contents) = std::fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        let mut open: Vec<JournalEntry> = Vec::new();
+        for line in contents.lines() {
+            match serde_json::from_str(line) {
+                Ok(JournalRecord::Intent(entry)) => open.push(entry),
+                Ok(JournalRecord::Outcome { id, .. }) => open.retain(|e| e.id != id),
+                Err(_) => {}
+            }
+        }
+        open
+    }
+
+    fn append(&self, record: &JournalRecord) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let line = serde_json::to_string(record)?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{line}")
+    }
+}
+
+/// Finish or roll back unfinished entries started before `before`.
+///
+/// An operation whose new memory made it into storage is completed; one whose
+/// new memory is missing is rolled back. Entries hitting other storage errors
+/// stay open for the next attempt. Returns the resolved entries.
+pub async fn recover(
+    storage: &impl StorageBackend,
+    journal: &CaptureJournal,
+    before: DateTime<Utc>,
+) -> Vec<(JournalEntry, JournalOutcome)> {
+    let mut resolved = Vec::new();
+    for entry in journal.unfinished() {
+        if entry.started_at >= before {
+            continue;
+        }
+        let saved = match entry.op.saved_memory() {
+            Some(id) => match storage.get_memory(id).await {
+                Ok(_) => true,
+                Err(ShabkaError::NotFound(_)) => false,
+                Err(e) => {
+                    tracing::warn!("journal entry {} left open: {e}", entry.id);
+                    continue;
+                }
+            },
+            None => true,
+        };
+        let result = if saved {
+            entry
+                .op
+                .complete(storage)
+                .await
+                .map(|()| JournalOutcome::Finished)
+        } else {
+            entry
+                .op
+                .roll_back(storage)
+                .await
+                .map(|()| JournalOutcome::RolledBack)
+        };
+        match result {
+            Ok(outcome) => {
+                if let Err(e) = journal.finish(entry.id, outcome) {
+                    tracing::warn!("failed to record journal outcome: {e}");
+                }
+                resolved.push((entry, outcome));
+            }
+            Err(ShabkaError::NotFound(_)) => {
+                // The existing memory is gone; nothing left to apply.
+                if let Err(e) = journal.finish(entry.id, JournalOutcome::RolledBack) {
+                    tracing::warn!("failed to record journal outcome: {e}");
+                }
+                resolved.push((entry, JournalOutcome::RolledBack));
+            }
+            Err(e) => tracing::warn!("journal entry {} left open: {e}", entry.id),
+        }
+    }
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Memory, MemoryKind};
+    use crate::storage::SqliteStorage;
+
+    fn temp_journal() -> CaptureJournal {
+        CaptureJournal::with_path(
+            std::env::temp_dir().join(format!("shabka-journal-{}.jsonl", Uuid::now_v7())),
+        )
+    }
+
+    fn memory(title: &str) -> Memory {
+        Memory::new(
+            title.into(),
+            "content".into(),
+            MemoryKind::Fact,
+            "user".into(),
+        )
+    }
+
+    #[test]
+    fn test_finished_entries_are_dropped() {
+        let journal = temp_journal();
+        let op = JournalOp::Merge {
+            existing_id: Uuid::now_v7(),
+            title: "t".into(),
+            content: "c".into(),
+        };
+        let a = journal.begin(op.clone()).unwrap();
+        let b = journal.begin(op).unwrap();
+        assert_eq!(journal.unfinished().len(), 2);
+
+        journal.finish(a, JournalOutcome::Applied).unwrap();
+        let open = journal.unfinished();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].id, b);
+
+        journal.finish(b, JournalOutcome::Applied).unwrap();
+        assert!(journal.unfinished().is_empty());
+        assert!(!journal.path().exists());
+    }
+
+    #[tokio::test]
+    async fn test_recover_finishes_saved_supersede() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let old = memory("old");
+        let new = memory("new");
+        storage.save_memory(&old, None).await.unwrap();
+        storage.save_memory(&new, None).await.unwrap();
+
+        // Crashed after saving the new memory, before retiring the old one.
+        let journal = temp_journal();
+        journal
+            .begin(JournalOp::Supersede {
+                memory_id: new.id,
+                existing_id: old.id,
+                previous_status: MemoryStatus::Active,
+                strength: 0.9,
+            })
+            .unwrap();
+
+        let resolved = recover(&storage, &journal, Utc::now()).await;
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].1, JournalOutcome::Finished);
+        let old = storage.get_memory(old.id).await.unwrap();
+        assert_eq!(old.status, MemoryStatus::Superseded);
+        assert!(journal.unfinished().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_recover_rolls_back_unsaved_supersede() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let old = memory("old");
+        storage.save_memory(&old, None).await.unwrap();
+        storage
+            .update_memory(
+                old.id,
+                &UpdateMemoryInput {
+                    status: Some(MemoryStatus::Superseded),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        // The old memory was retired but its replacement never saved.
+        let journal = temp_journal();
+        journal
+            .begin(JournalOp::Supersede {
+                memory_id: Uuid::now_v7(),
+                existing_id: old.id,
+                previous_status: MemoryStatus::Active,
+                strength: 0.9,
+            })
+            .unwrap();
+
+        // Entries newer than the cutoff may still be in flight.
+        let cutoff = Utc::now() - chrono::Duration::minutes(5);
+        assert!(recover(&storage, &journal, cutoff).await.is_empty());
+
+        let resolved = recover(&storage, &journal, Utc::now()).await;
+        assert_eq!(resolved[0].1, JournalOutcome::RolledBack);
+        let old = storage.get_memory(old.id).await.unwrap();
+        assert_eq!(old.status, MemoryStatus::Active);
+    }
+}
@@ -0,0 +1,293 @@
+//! Migration from Kaizen, the project Shabka grew out of.
+//!
+//! Kaizen's HelixDB schema is the one Shabka still uses, and its SQLite
+//! stores are earlier versions of Shabka's schema, so both open with
+//! Shabka's own backends. [`open_sqlite`] never writes to the Kaizen file:
+//! it copies it and upgrades the copy, noting any columns the current
+//! schema has no place for. [`migrate`] then copies memories (timestamps,
+//! authors and all) and their relations into the target store, and
+//! [`migrate_history`] appends Kaizen's audit trail to Shabka's.
+
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::embedding::EmbeddingService;
+use crate::error::{Result, ShabkaError};
+use crate::history::{HistoryLogger, MemoryEvent};
+use crate::model::{MemoryRelation, TimelineQuery};
+use crate::storage::{table_columns, SqliteStorage, StorageBackend};
+
+/// Most memories read from a Kaizen store.
+const MAX_MEMORIES: usize = 1_000_000;
+
+/// A column in a Kaizen database that Shabka's schema has no place for.
+/// Its values are left behind.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct UnmappedField {
+    pub table: String,
+    pub column: String,
+}
+
+/// A Kaizen SQLite store, copied and upgraded to Shabka's schema. The copy
+/// is deleted on drop.
+pub struct KaizenSqlite {
+    pub storage: SqliteStorage,
+    /// Source columns missing from Shabka's schema, by table.
+    pub unmapped: Vec<UnmappedField>,
+    dir: PathBuf,
+}
+
+impl Drop for KaizenSqlite {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Open the Kaizen SQLite database at `path` through an upgraded copy.
+pub fn open_sqlite(path: &Path) -> Result<KaizenSqlite> {
+    if !path.is_file() {
+        return Err(ShabkaError::Config(format!(
+            "no Kaizen database at {}",
+            path.display()
+        )));
+    }
+    let storage_err = |e: rusqlite::Error| {
+        ShabkaError::Storage(format!("failed to read {}: {e}", path.display()))
+    };
+
+    // A fresh store first: it registers the SQLite extensions every later
+    // connection needs, and its columns are what Shabka can hold.
+    let current = SqliteStorage::open_in_memory()?.columns()?;
+
+    let source =
+        Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(storage_err)?;
+    let mut unmapped = Vec::new();
+    for (table, columns) in table_columns(&source)? {
+        let known = current.get(&table).cloned().unwrap_or_default();
+        unmapped.extend(columns.difference(&known).map(|column| UnmappedField {
+            table: table.clone(),
+            column: column.clone(),
+        }));
+    }
+
+    let dir = std::env::temp_dir().join(format!("shabka-kaizen-{}", Uuid::now_v7()));
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| ShabkaError::Storage(format!("failed to create {}: {e}", dir.display())))?;
+    let copy = dir.join("kaizen.db");
+    let copied = source
+        .execute("VACUUM INTO ?1", [copy.to_string_lossy()])
+        .map_err(storage_err);
+    drop(source);
+    if let Err(e) = copied {
+        let _ = std::fs::remove_dir_all(&dir);
+        return Err(e);
+    }
+
+    match SqliteStorage::open(&copy) {
+        Ok(storage) => Ok(KaizenSqlite {
+            storage,
+            unmapped,
+            dir,
+        }),
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&dir);
+            Err(e)
+        }
+    }
+}
+
+/// What [`migrate`] copied, or would copy on a dry run.
+#[derive(Debug, Default, Serialize)]
+pub struct MigrationReport {
+    pub memories: usize,
+    /// Memories the target already held, by ID; left untouched.
+    pub already_present: usize,
+    pub relations: usize,
+    pub history_events: usize,
+    /// History lines that didn't parse as events.
+    pub history_skipped: usize,
+    pub unmapped: Vec<UnmappedField>,
+}
+
+/// Copy every memory in `source` that `target` doesn't already hold, with
+/// its timestamps and author, then the relations between memories the
+/// target ends up with and doesn't already hold. Nothing is written when `dry_run` is set.
+pub async fn migrate(
+    source: &impl StorageBackend,
+    target: &impl StorageBackend,
+    embedder: &EmbeddingService,
+    dry_run: bool,
+) -> Result<MigrationReport> {
+    let entries = source.timeline(&TimelineQuery::new(MAX_MEMORIES)).await?;
+    let ids: Vec<Uuid> = entries.iter().map(|e| e.id).collect();
+    let memories = source.get_memories(&ids).await?;
+
+    let mut report = MigrationReport::default();
+    let mut present: HashSet<Uuid> = HashSet::new();
+    for memory in &memories {
+        present.insert(memory.id);
+        if target.get_memory(memory.id).await.is_ok() {
+            report.already_present += 1;
+            continue;
+        }
+        if !dry_run {
+            let embedding = embedder.embed(&memory.embedding_text()).await?;
+            target.save_memory(memory, Some(&embedding)).await?;
+        }
+        report.memories += 1;
+    }
+
+    let mut seen: BTreeSet<(Uuid, Uuid, String)> = BTreeSet::new();
+    for memory in &memories {
+        // Relations the target already holds, from an earlier run.
+        for relation in target.get_relations(memory.id).await.unwrap_or_default() {
+            seen.insert(relation_key(&relation));
+        }
+        for relation in source.get_relations(memory.id).await? {
+            if !present.contains(&relation.source_id) || !present.contains(&relation.target_id) {
+                continue;
+            }
+            if !seen.insert(relation_key(&relation)) {
+                continue;
+            }
+            if !dry_run {
+                target.add_relation(&relation).await?;
+            }
+            report.relations += 1;
+        }
+    }
+    Ok(report)
+}
+
+fn relation_key(relation: &MemoryRelation) -> (Uuid, Uuid, String) {
+    (
+        relation.source_id,
+        relation.target_id,
+        relation.relation_type.to_string(),
+    )
+}
+
+/// Append the events in Kaizen's history file at `path` to `history`,
+/// keeping their IDs and timestamps. Returns the events copied and the
+/// lines skipped as unreadable.
+pub fn migrate_history(
+    path: &Path,
+    history: &HistoryLogger,
+    dry_run: bool,
+) -> Result<(usize, usize)> {
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        ShabkaError::Config(format!("failed to read history {}: {e}", path.display()))
+    })?;
+    let (mut copied, mut skipped) = (0, 0);
+    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+        match serde_json::from_str::<MemoryEvent>(line) {
+            Ok(event) => {
+                if !dry_run {
+                    history.log(&event);
+                }
+                copied += 1;
+            }
+            Err(_) => skipped += 1,
+        }
+    }
+    Ok((copied, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EmbeddingConfig;
+    use crate::history::EventAction;
+    use crate::model::{Memory, MemoryKind, RelationType};
+
+    #[tokio::test]
+    async fn test_migrate_from_kaizen_sqlite() {
+        let dir = std::env::temp_dir().join(format!("kaizen-test-{}", Uuid::now_v7()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("kaizen.db");
+
+        let embedder = EmbeddingService::from_config(&EmbeddingConfig::default()).unwrap();
+        let mut old = Memory::new(
+            "Pool sizing".into(),
+            "Keep the pool at 20.".into(),
+            MemoryKind::Decision,
+            "kai".into(),
+        );
+        old.created_at -= chrono::Duration::days(400);
+        let newer = Memory::new(
+            "Retry budget".into(),
+            "Retry twice.".into(),
+            MemoryKind::Pattern,
+            "kai".into(),
+        );
+        {
+            let kaizen = SqliteStorage::open(&path).unwrap();
+            for m in [&old, &newer] {
+                let emb = embedder.embed(&m.embedding_text()).await.unwrap();
+                kaizen.save_memory(m, Some(&emb)).await.unwrap();
+            }
+            kaizen
+                .add_relation(&MemoryRelation {
+                    source_id: newer.id,
+                    target_id: old.id,
+                    relation_type: RelationType::Related,
+                    strength: 0.5,
+                })
+                .await
+                .unwrap();
+        }
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch("ALTER TABLE memories ADD COLUMN mood TEXT;")
+            .unwrap();
+
+        let kaizen = open_sqlite(&path).unwrap();
+        assert_eq!(
+            kaizen.unmapped,
+            vec![UnmappedField {
+                table: "memories".into(),
+                column: "mood".into()
+            }]
+        );
+
+        let target = SqliteStorage::open_in_memory().unwrap();
+        let dry = migrate(&kaizen.storage, &target, &embedder, true)
+            .await
+            .unwrap();
+        assert_eq!((dry.memories, dry.relations), (2, 1));
+        assert!(target.get_memory(old.id).await.is_err());
+
+        let report = migrate(&kaizen.storage, &target, &embedder, false)
+            .await
+            .unwrap();
+        assert_eq!((report.memories, report.relations), (2, 1));
+        let copied = target.get_memory(old.id).await.unwrap();
+        assert_eq!(copied.created_at.timestamp(), old.created_at.timestamp());
+        assert_eq!(copied.created_by, "kai");
+        assert_eq!(target.get_relations(newer.id).await.unwrap().len(), 1);
+
+        let again = migrate(&kaizen.storage, &target, &embedder, false)
+            .await
+            .unwrap();
+        assert_eq!((again.memories, again.already_present), (0, 2));
+        assert_eq!(again.relations, 0);
+
+        let events = dir.join("history.jsonl");
+        let event = MemoryEvent::new(old.id, EventAction::Created, "kai".into());
+        std::fs::write(
+            &events,
+            format!("{}\nnot json\n", serde_json::to_string(&event).unwrap()),
+        )
+        .unwrap();
+        let history = HistoryLogger::with_path(dir.join("shabka-history.jsonl"), true);
+        assert_eq!(migrate_history(&events, &history, false).unwrap(), (1, 1));
+        assert_eq!(history.history_for(old.id)[0].timestamp, event.timestamp);
+
+        drop(kaizen);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
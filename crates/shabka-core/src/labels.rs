@@ -0,0 +1,272 @@
+//! Classification labels and the data-handling policies attached to them.
+//!
+//! A label (`internal`, `confidential`, `public-ok`, ...) says how a memory
+//! may be handled, separately from privacy, which says who may see it.
+//! Policies are configured per label under `[labels.policies]` and checked
+//! at the points where memories leave Shabka's control: exports, sharing
+//! with other users, and saving to a storage backend on another machine.
+//! Labels without a policy, and unlabeled memories, are unrestricted.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::{Memory, MemoryPrivacy, UpdateMemoryInput};
+use crate::serde_defaults::default_true;
+
+/// Labels and their handling rules.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LabelConfig {
+    /// Label given to new memories that don't set one (default none).
+    #[serde(default)]
+    pub default: Option<String>,
+    /// Handling rules per label.
+    #[serde(default)]
+    pub policies: BTreeMap<String, LabelPolicy>,
+}
+
+/// What a label allows. Everything is allowed unless turned off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LabelPolicy {
+    /// May appear in `shabka export` output.
+    #[serde(default = "default_true")]
+    pub export: bool,
+    /// May be visible to other users; when off, the memory is kept private.
+    #[serde(default = "default_true")]
+    pub share: bool,
+    /// May be stored on a backend that isn't on this machine.
+    #[serde(default = "default_true")]
+    pub sync: bool,
+}
+
+impl Default for LabelPolicy {
+    fn default() -> Self {
+        Self {
+            export: true,
+            share: true,
+            sync: true,
+        }
+    }
+}
+
+/// A point where a memory leaves this installation's control.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boundary {
+    Export,
+    Share,
+    Sync,
+}
+
+impl std::fmt::Display for Boundary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Export => write!(f, "export"),
+            Self::Share => write!(f, "share"),
+            Self::Sync => write!(f, "sync"),
+        }
+    }
+}
+
+impl LabelPolicy {
+    fn allows(&self, boundary: Boundary) -> bool {
+        match boundary {
+            Boundary::Export => self.export,
+            Boundary::Share => self.share,
+            Boundary::Sync => self.sync,
+        }
+    }
+}
+
+impl LabelConfig {
+    /// The policy for `label`, if one is configured.
+    pub fn policy(&self, label: &str) -> Option<&LabelPolicy> {
+        let label = normalize(label)?;
+        self.policies
+            .iter()
+            .find(|(name, _)| normalize(name).as_deref() == Some(label.as_str()))
+            .map(|(_, policy)| policy)
+    }
+
+    /// Whether a memory labelled `label` may cross `boundary`.
+    pub fn allows(&self, label: Option<&str>, boundary: Boundary) -> bool {
+        match label.and_then(|l| self.policy(l)) {
+            Some(policy) => policy.allows(boundary),
+            None => true,
+        }
+    }
+
+    /// Whether `memory` may cross `boundary`.
+    pub fn permits(&self, memory: &Memory, boundary: Boundary) -> bool {
+        self.allows(memory.label.as_deref(), boundary)
+    }
+
+    /// Labels whose policy keeps them from crossing `boundary`.
+    pub fn withheld(&self, boundary: Boundary) -> Vec<String> {
+        self.policies
+            .iter()
+            .filter(|(_, policy)| !policy.allows(boundary))
+            .filter_map(|(name, _)| normalize(name))
+            .collect()
+    }
+
+    /// The label to store for a new memory: `label` normalized, else the
+    /// configured default.
+    pub fn resolve(&self, label: Option<&str>) -> Option<String> {
+        label
+            .and_then(normalize)
+            .or_else(|| self.default.as_deref().and_then(normalize))
+    }
+
+    /// `privacy`, lowered to private when `label` may not be shared.
+    pub fn clamp_privacy(&self, label: Option<&str>, privacy: MemoryPrivacy) -> MemoryPrivacy {
+        if self.allows(label, Boundary::Share) {
+            privacy
+        } else {
+            MemoryPrivacy::Private
+        }
+    }
+
+    /// Give a new memory its label and keep it private if the label
+    /// forbids sharing.
+    pub fn apply(&self, memory: &mut Memory) {
+        memory.label = self.resolve(memory.label.as_deref());
+        memory.privacy = self.clamp_privacy(memory.label.as_deref(), memory.privacy);
+    }
+
+    /// Normalize an update's label (keeping an empty string, which removes
+    /// it) and make the update keep `memory` private if the label it ends
+    /// up with forbids sharing.
+    pub fn constrain_update(&self, memory: &Memory, input: &mut UpdateMemoryInput) {
+        if let Some(label) = &input.label {
+            input.label = Some(normalize(label).unwrap_or_default());
+        }
+        let label = match &input.label {
+            Some(label) => (!label.is_empty()).then_some(label.as_str()),
+            None => memory.label.as_deref(),
+        };
+        let privacy = input.privacy.unwrap_or(memory.privacy);
+        let clamped = self.clamp_privacy(label, privacy);
+        if clamped != privacy || input.privacy.is_some() {
+            input.privacy = Some(clamped);
+        }
+    }
+}
+
+/// Canonical form of a label: trimmed and lowercased. `None` when blank.
+pub fn normalize(label: &str) -> Option<String> {
+    let label = label.trim().to_lowercase();
+    (!label.is_empty()).then_some(label)
+}
+
+/// Whether `url` points at this machine.
+pub fn is_local_url(url: &str) -> bool {
+    let host = url.split("://").nth(1).unwrap_or(url);
+    let host = host.split(['/', '?']).next().unwrap_or(host);
+    let host = match host.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or(v6),
+        None => host.rsplit_once(':').map_or(host, |(h, _)| h),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1" | "0.0.0.0") || host.starts_with("127.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::MemoryKind;
+
+    fn config() -> LabelConfig {
+        LabelConfig {
+            default: Some("Internal".to_string()),
+            policies: BTreeMap::from([
+                (
+                    "Confidential".to_string(),
+                    LabelPolicy {
+                        export: false,
+                        share: false,
+                        sync: false,
+                    },
+                ),
+                (
+                    "internal".to_string(),
+                    LabelPolicy {
+                        export: false,
+                        ..Default::default()
+                    },
+                ),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_policies_gate_boundaries() {
+        let config = config();
+        assert!(!config.allows(Some("confidential"), Boundary::Sync));
+        assert!(!config.allows(Some(" CONFIDENTIAL "), Boundary::Share));
+        assert!(!config.allows(Some("internal"), Boundary::Export));
+        assert!(config.allows(Some("internal"), Boundary::Sync));
+        assert!(config.allows(Some("public-ok"), Boundary::Export));
+        assert!(config.allows(None, Boundary::Export));
+        assert_eq!(
+            config.withheld(Boundary::Export),
+            vec!["confidential", "internal"]
+        );
+        assert_eq!(config.withheld(Boundary::Sync), vec!["confidential"]);
+    }
+
+    #[test]
+    fn test_apply_defaults_label_and_clamps_privacy() {
+        let config = config();
+        let mut memory = Memory::new(
+            "t".to_string(),
+            "c".to_string(),
+            MemoryKind::Fact,
+            "alice".to_string(),
+        )
+        .with_privacy(MemoryPrivacy::Team);
+        config.apply(&mut memory);
+        assert_eq!(memory.label.as_deref(), Some("internal"));
+        assert_eq!(memory.privacy, MemoryPrivacy::Team);
+
+        memory.label = Some("Confidential".to_string());
+        config.apply(&mut memory);
+        assert_eq!(memory.label.as_deref(), Some("confidential"));
+        assert_eq!(memory.privacy, MemoryPrivacy::Private);
+    }
+
+    #[test]
+    fn test_constrain_update() {
+        let config = config();
+        let memory = Memory::new(
+            "t".to_string(),
+            "c".to_string(),
+            MemoryKind::Fact,
+            "alice".to_string(),
+        )
+        .with_privacy(MemoryPrivacy::Public);
+
+        let mut input = UpdateMemoryInput {
+            label: Some("CONFIDENTIAL".to_string()),
+            ..Default::default()
+        };
+        config.constrain_update(&memory, &mut input);
+        assert_eq!(input.label.as_deref(), Some("confidential"));
+        assert_eq!(input.privacy, Some(MemoryPrivacy::Private));
+
+        let mut clear = UpdateMemoryInput {
+            label: Some(" ".to_string()),
+            ..Default::default()
+        };
+        config.constrain_update(&memory, &mut clear);
+        assert_eq!(clear.label.as_deref(), Some(""));
+        assert_eq!(clear.privacy, None);
+    }
+
+    #[test]
+    fn test_is_local_url() {
+        assert!(is_local_url("http://localhost"));
+        assert!(is_local_url("http://127.0.0.1:6969"));
+        assert!(is_local_url("http://[::1]:6969/"));
+        assert!(!is_local_url("https://helix.example.com"));
+        assert!(!is_local_url("http://10.0.0.5:6969"));
+    }
+}
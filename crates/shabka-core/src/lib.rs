@@ -1,20 +1,106 @@
+//! Core library for Shabka.
+//!
+//! With default features this is everything the CLI, MCP server and web
+//! dashboard share. Without the `runtime` feature only the pure logic is
+//! built — the data model, ranking, centrality, trust, scrubbing, screening
+//! patterns and context-pack formatting — with no storage, network or async
+//! runtime, so it compiles to `wasm32-unknown-unknown` (see `shabka-wasm`).
+
+#[cfg(feature = "runtime")]
+pub mod api_tokens;
+#[cfg(feature = "runtime")]
 pub mod assess;
+#[cfg(feature = "runtime")]
+pub mod attachments;
+#[cfg(feature = "runtime")]
+pub mod audit;
+#[cfg(feature = "runtime")]
 pub mod auto_tag;
+#[cfg(feature = "runtime")]
+pub mod backfill;
+pub mod centrality;
+#[cfg(feature = "runtime")]
+pub mod citations;
+#[cfg(feature = "runtime")]
+pub mod client;
+pub mod code;
+#[cfg(feature = "runtime")]
+pub mod codebase;
+#[cfg(feature = "runtime")]
 pub mod config;
+#[cfg(feature = "runtime")]
 pub mod consolidate;
 pub mod context_pack;
 pub mod decay;
+#[cfg(feature = "runtime")]
 pub mod dedup;
+#[cfg(feature = "runtime")]
+pub mod devices;
+#[cfg(feature = "runtime")]
+pub mod digest;
+#[cfg(feature = "runtime")]
 pub mod embedding;
 pub mod error;
+#[cfg(feature = "runtime")]
+pub mod gaps;
+#[cfg(feature = "runtime")]
 pub mod graph;
+#[cfg(feature = "runtime")]
 pub mod history;
+#[cfg(feature = "runtime")]
+pub mod inbox;
+#[cfg(feature = "runtime")]
+pub mod journal;
+#[cfg(feature = "runtime")]
+pub mod kaizen;
+#[cfg(feature = "runtime")]
+pub mod labels;
+#[cfg(feature = "runtime")]
 pub mod llm;
+#[cfg(feature = "runtime")]
+pub mod llm_budget;
+#[cfg(feature = "runtime")]
+pub mod maintenance;
 pub mod model;
+#[cfg(feature = "runtime")]
+pub mod plugins;
+#[cfg(feature = "runtime")]
+pub mod query;
+#[cfg(feature = "runtime")]
+pub mod questions;
+#[cfg(feature = "runtime")]
+pub mod quota;
 pub mod ranking;
+#[cfg(feature = "runtime")]
+pub mod retrieval;
+#[cfg(feature = "runtime")]
+pub mod retrieval_session;
+#[cfg(feature = "runtime")]
 pub mod retry;
+pub mod screen;
 pub mod scrub;
+mod serde_defaults;
+#[cfg(feature = "runtime")]
+pub mod services;
+#[cfg(feature = "runtime")]
 pub mod sharing;
+#[cfg(feature = "runtime")]
+pub mod similarity;
+#[cfg(feature = "runtime")]
+pub mod snapshot;
+#[cfg(feature = "runtime")]
 pub mod storage;
+#[cfg(feature = "runtime")]
+pub mod sync;
+#[cfg(feature = "runtime")]
+pub mod tag_map;
+#[cfg(feature = "runtime")]
+pub mod telemetry;
+#[cfg(feature = "runtime")]
+pub mod tenants;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod tokens;
 pub mod trust;
+#[cfg(feature = "runtime")]
+pub mod wasm_plugins;
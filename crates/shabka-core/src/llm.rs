@@ -1,6 +1,9 @@
 use crate::config::{self, LlmConfig};
 use crate::error::{Result, ShabkaError};
+use crate::llm_budget::{LlmFeature, ParseOutcome, TokenLedger};
 use crate::retry::with_retry;
+use crate::tokens::estimate_tokens;
+use schemars::JsonSchema;
 use std::future::Future;
 use std::pin::Pin;
 
@@ -20,11 +23,14 @@ type GenerateFuture<'a> =
 /// `Box<dyn RigCompletionAdapter>`.
 trait RigCompletionAdapter: Send + Sync {
     /// Generate text from a prompt with an optional system message.
+    /// `schema`, when given, is the JSON schema the reply must match, for
+    /// providers that can enforce it natively.
     fn generate(
         &self,
         prompt: String,
         system: Option<String>,
         max_tokens: u64,
+        schema: Option<serde_json::Value>,
     ) -> GenerateFuture<'_>;
 }
 
@@ -47,6 +53,7 @@ where
         prompt: String,
         system: Option<String>,
         max_tokens: u64,
+        _schema: Option<serde_json::Value>,
     ) -> GenerateFuture<'_> {
         Box::pin(async move {
             use rig::completion::AssistantContent;
@@ -75,15 +82,111 @@ where
     }
 }
 
+#[cfg(feature = "testing")]
+impl RigCompletionAdapter for crate::testing::MockLlm {
+    fn generate(
+        &self,
+        prompt: String,
+        system: Option<String>,
+        _max_tokens: u64,
+        _schema: Option<serde_json::Value>,
+    ) -> GenerateFuture<'_> {
+        Box::pin(crate::testing::MockLlm::generate(self, prompt, system))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Generic OpenAI-compatible endpoint
+// ---------------------------------------------------------------------------
+
+/// Chat completions against any server speaking the OpenAI dialect
+/// (llama.cpp server, vLLM, LM Studio, OpenRouter, ...).
+///
+/// Talks to `{base_url}/chat/completions` directly rather than through Rig,
+/// since Rig's OpenAI client targets the Responses API that these servers
+/// mostly lack. The API key is optional; local servers rarely need one.
+struct OpenAiCompatibleModel {
+    client: reqwest::Client,
+    endpoint: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+impl OpenAiCompatibleModel {
+    fn new(base_url: &str, model: &str, api_key: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint: format!("{}/chat/completions", base_url.trim_end_matches('/')),
+            model: model.to_string(),
+            api_key,
+        }
+    }
+}
+
+impl RigCompletionAdapter for OpenAiCompatibleModel {
+    fn generate(
+        &self,
+        prompt: String,
+        system: Option<String>,
+        max_tokens: u64,
+        schema: Option<serde_json::Value>,
+    ) -> GenerateFuture<'_> {
+        Box::pin(async move {
+            let mut messages = Vec::new();
+            if let Some(sys) = system {
+                messages.push(serde_json::json!({ "role": "system", "content": sys }));
+            }
+            messages.push(serde_json::json!({ "role": "user", "content": prompt }));
+            let mut body = serde_json::json!({
+                "model": self.model,
+                "messages": messages,
+                "max_tokens": max_tokens,
+            });
+            if let Some(schema) = schema {
+                body["response_format"] = serde_json::json!({
+                    "type": "json_schema",
+                    "json_schema": { "name": "response", "schema": schema },
+                });
+            }
+
+            let mut request = self.client.post(&self.endpoint).json(&body);
+            if let Some(ref key) = self.api_key {
+                request = request.bearer_auth(key);
+            }
+            let response = request.send().await.map_err(|e| e.to_string())?;
+            let status = response.status();
+            let text = response.text().await.map_err(|e| e.to_string())?;
+            if !status.is_success() {
+                return Err(format!("{} returned {status}: {text}", self.endpoint));
+            }
+            parse_chat_completion(&text)
+        })
+    }
+}
+
+/// Extract the reply from a chat completions response body.
+fn parse_chat_completion(body: &str) -> std::result::Result<String, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(body).map_err(|e| format!("invalid completion response: {e}"))?;
+    value["choices"][0]["message"]["content"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| "completion response contained no text content".to_string())
+}
+
 // ---------------------------------------------------------------------------
 // LlmService — public API (unchanged from callers' perspective)
 // ---------------------------------------------------------------------------
 
 /// LLM text generation service. Uses Rig's CompletionModel under the hood
-/// to support Ollama, OpenAI, Gemini, and Anthropic providers.
+/// to support Ollama, OpenAI, Gemini, and Anthropic providers, plus any
+/// OpenAI-compatible server via `generic-openai`.
 pub struct LlmService {
     inner: Box<dyn RigCompletionAdapter>,
     config: LlmConfig,
+    ledger: TokenLedger,
+    /// `[output] language`, for features that write prose.
+    language: Option<String>,
 }
 
 impl std::fmt::Debug for LlmService {
@@ -294,10 +397,33 @@ impl LlmService {
                 Box::new(RigCompletionWrapper { model, model_name })
             }
 
+            "generic-openai" => {
+                let base_url = config.base_url.as_deref().ok_or_else(|| {
+                    ShabkaError::Config(
+                        "generic-openai LLM provider requires llm.base_url \
+                         (e.g. http://localhost:8080/v1)"
+                            .into(),
+                    )
+                })?;
+                // Optional: use a key only when one is configured.
+                let api_key = config
+                    .api_key
+                    .clone()
+                    .filter(|k| !k.is_empty())
+                    .or_else(|| {
+                        config
+                            .env_var
+                            .as_deref()
+                            .and_then(|var| std::env::var(var).ok())
+                    });
+
+                Box::new(OpenAiCompatibleModel::new(base_url, &config.model, api_key))
+            }
+
             other => {
                 return Err(ShabkaError::Config(format!(
                     "unknown LLM provider: '{other}' (expected 'ollama', 'openai', 'gemini', \
-                     'anthropic', 'deepseek', 'groq', 'xai', or 'cohere')"
+                     'anthropic', 'deepseek', 'groq', 'xai', 'cohere', or 'generic-openai')"
                 )));
             }
         };
@@ -305,48 +431,234 @@ impl LlmService {
         Ok(Self {
             inner,
             config: config.clone(),
+            ledger: TokenLedger::new(),
+            language: None,
         })
     }
 
+    /// A service answering from `mock`, with default settings and a
+    /// throwaway token ledger.
+    #[cfg(feature = "testing")]
+    pub fn mock(mock: crate::testing::MockLlm) -> Self {
+        let ledger =
+            std::env::temp_dir().join(format!("shabka-mock-llm-{}.json", uuid::Uuid::now_v7()));
+        Self {
+            inner: Box::new(mock),
+            config: LlmConfig {
+                enabled: true,
+                provider: "mock".to_string(),
+                ..LlmConfig::default()
+            },
+            ledger: TokenLedger::with_path(ledger),
+            language: None,
+        }
+    }
+
+    /// Record usage in `ledger` instead of the default location.
+    pub fn with_ledger(mut self, ledger: TokenLedger) -> Self {
+        self.ledger = ledger;
+        self
+    }
+
+    /// Ask for summaries and merged memories in `language` (`[output]
+    /// language`, e.g. `de`) rather than English.
+    pub fn with_language(mut self, language: Option<&str>) -> Self {
+        self.language = language.map(str::to_string);
+        self
+    }
+
     /// Generate text from a prompt with an optional system message.
     /// Wraps the Rig call with retry logic (3 retries, 200ms base delay).
-    pub async fn generate(&self, prompt: &str, system: Option<&str>) -> Result<String> {
+    ///
+    /// Fails without calling the provider once `llm.daily_token_budget` is
+    /// used up; callers fall back to their heuristics on error.
+    pub async fn generate(
+        &self,
+        feature: LlmFeature,
+        prompt: &str,
+        system: Option<&str>,
+    ) -> Result<String> {
+        self.complete(feature, prompt, system, None).await
+    }
+
+    async fn complete(
+        &self,
+        feature: LlmFeature,
+        prompt: &str,
+        system: Option<&str>,
+        schema: Option<&serde_json::Value>,
+    ) -> Result<String> {
+        let budget = self.config.daily_token_budget;
+        if self.ledger.today().is_exhausted(budget) {
+            return Err(ShabkaError::QuotaExceeded {
+                message: format!(
+                    "daily LLM token budget of {} used up",
+                    budget.unwrap_or_default()
+                ),
+                setting: "llm.daily_token_budget".to_string(),
+            });
+        }
+
         let max_tokens = self.config.max_tokens as u64;
         let prompt_owned = prompt.to_string();
-        let system_owned = system.map(|s| s.to_string());
+        let instruction = self
+            .language
+            .as_deref()
+            .filter(|_| feature.writes_prose())
+            .and_then(language_instruction);
+        let system_owned = match (system, instruction) {
+            (Some(sys), Some(instruction)) => Some(format!("{sys}\n\n{instruction}")),
+            (None, Some(instruction)) => Some(instruction),
+            (sys, None) => sys.map(|s| s.to_string()),
+        };
+        let system = system_owned.as_deref();
 
-        with_retry(3, 200, || {
+        let text = with_retry(3, 200, || {
             let p = prompt_owned.clone();
             let s = system_owned.clone();
+            let schema = schema.cloned();
             async move {
                 self.inner
-                    .generate(p, s, max_tokens)
+                    .generate(p, s, max_tokens, schema)
                     .await
-                    .map_err(ShabkaError::Llm)
+                    .map_err(|e| ShabkaError::provider("llm", &self.config.provider, e))
             }
         })
-        .await
+        .await?;
+
+        // Providers report usage differently (or not at all), so count
+        // every call the same way: an estimate of what went in and out.
+        let tokens =
+            estimate_tokens(prompt) + system.map_or(0, estimate_tokens) + estimate_tokens(&text);
+        if let Err(e) = self.ledger.record(feature, tokens as u64) {
+            tracing::warn!("llm: failed to record token usage: {e}");
+        }
+        Ok(text)
     }
 
     /// Generate structured output from the LLM.
     ///
-    /// Calls `generate()` and deserializes the JSON response into `T`.
-    /// Strips markdown fences if present.
-    pub async fn generate_structured<T: serde::de::DeserializeOwned>(
+    /// The JSON schema of `T` goes into the system message, and to the
+    /// provider itself where it can constrain its output. A reply that
+    /// doesn't parse is sent back with the error for another try, up to
+    /// [`STRUCTURED_ATTEMPTS`] calls in all. Outcomes are counted per
+    /// feature in the token ledger.
+    pub async fn generate_structured<T: serde::de::DeserializeOwned + JsonSchema>(
         &self,
+        feature: LlmFeature,
         prompt: &str,
         system: Option<&str>,
     ) -> Result<T> {
-        let raw = self.generate(prompt, system).await?;
-        let cleaned = raw
-            .trim()
-            .trim_start_matches("```json")
-            .trim_start_matches("```")
-            .trim_end_matches("```")
-            .trim();
-        serde_json::from_str(cleaned)
-            .map_err(|e| ShabkaError::Llm(format!("failed to parse structured LLM response: {e}")))
+        let schema = serde_json::to_value(schemars::schema_for!(T))?;
+        let schema_text = serde_json::to_string(&schema)?;
+        let system = match system {
+            Some(sys) => format!("{sys}\n\nThe JSON must match this schema:\n{schema_text}"),
+            None => format!("Reply with JSON matching this schema:\n{schema_text}"),
+        };
+
+        let mut request = prompt.to_string();
+        let mut last_error = String::new();
+        for attempt in 0..STRUCTURED_ATTEMPTS {
+            let raw = self
+                .complete(feature, &request, Some(&system), Some(&schema))
+                .await?;
+            match parse_structured(&raw) {
+                Ok(value) => {
+                    self.record_parse(feature, ParseOutcome::parsed(attempt));
+                    return Ok(value);
+                }
+                Err(e) => {
+                    tracing::debug!("llm: unparseable {} reply: {e}", feature.as_str());
+                    request = format!(
+                        "{prompt}\n\nYour previous reply could not be used:\n{raw}\n\n\
+                         Error: {e}\nReply again with only the corrected JSON."
+                    );
+                    last_error = e.to_string();
+                }
+            }
+        }
+        self.record_parse(feature, ParseOutcome::Failed);
+        Err(ShabkaError::Llm(format!(
+            "failed to parse structured LLM response: {last_error}"
+        )))
+    }
+
+    fn record_parse(&self, feature: LlmFeature, outcome: ParseOutcome) {
+        if let Err(e) = self.ledger.record_parse(feature, outcome) {
+            tracing::warn!("llm: failed to record parse outcome: {e}");
+        }
+    }
+}
+
+/// English names of common languages, by primary language subtag.
+const LANGUAGE_NAMES: &[(&str, &str)] = &[
+    ("ar", "Arabic"),
+    ("cs", "Czech"),
+    ("da", "Danish"),
+    ("de", "German"),
+    ("es", "Spanish"),
+    ("fa", "Persian"),
+    ("fi", "Finnish"),
+    ("fr", "French"),
+    ("he", "Hebrew"),
+    ("hi", "Hindi"),
+    ("id", "Indonesian"),
+    ("it", "Italian"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+    ("nl", "Dutch"),
+    ("no", "Norwegian"),
+    ("pl", "Polish"),
+    ("pt", "Portuguese"),
+    ("ru", "Russian"),
+    ("sv", "Swedish"),
+    ("tr", "Turkish"),
+    ("uk", "Ukrainian"),
+    ("vi", "Vietnamese"),
+    ("zh", "Chinese"),
+];
+
+/// The system-message line asking for prose in the language tagged `tag`,
+/// or `None` for English, which prompts already ask for.
+fn language_instruction(tag: &str) -> Option<String> {
+    let primary = tag.split(['-', '_']).next()?.to_ascii_lowercase();
+    if primary.is_empty() || primary == "en" {
+        return None;
     }
+    let language = match LANGUAGE_NAMES.iter().find(|(code, _)| *code == primary) {
+        Some((_, name)) if primary.len() < tag.len() => format!("{name} ({tag})"),
+        Some((_, name)) => name.to_string(),
+        None => format!("the language with tag '{tag}'"),
+    };
+    Some(format!(
+        "Write titles, summaries and other prose in {language}. Keep code, identifiers, \
+         file paths, tags, JSON keys and enum values such as memory kinds unchanged."
+    ))
+}
+
+/// Calls `generate_structured` makes before giving up on a reply.
+pub const STRUCTURED_ATTEMPTS: usize = 2;
+
+/// Parse a structured reply, tolerating markdown fences and prose around
+/// the JSON value.
+fn parse_structured<T: serde::de::DeserializeOwned>(raw: &str) -> serde_json::Result<T> {
+    let cleaned = raw
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+    serde_json::from_str(cleaned).or_else(|e| {
+        // Fall back to the outermost object or array in the reply.
+        let start = cleaned.find(['{', '[']);
+        let end = cleaned.rfind(['}', ']']);
+        match (start, end) {
+            (Some(start), Some(end)) if start < end => {
+                serde_json::from_str(&cleaned[start..=end]).map_err(|_| e)
+            }
+            _ => Err(e),
+        }
+    })
 }
 
 #[cfg(test)]
@@ -365,6 +677,19 @@ mod tests {
         assert!(service.is_ok());
     }
 
+    #[test]
+    fn test_language_instruction() {
+        assert_eq!(language_instruction("en"), None);
+        assert_eq!(language_instruction("en-GB"), None);
+        assert!(language_instruction("de").unwrap().contains("in German."));
+        assert!(language_instruction("pt_BR")
+            .unwrap()
+            .contains("in Portuguese (pt_BR)."));
+        assert!(language_instruction("tlh")
+            .unwrap()
+            .contains("the language with tag 'tlh'"));
+    }
+
     #[test]
     fn test_from_config_unknown_provider() {
         let config = LlmConfig {
@@ -689,4 +1014,131 @@ mod tests {
         assert_eq!(result.language, "rust");
         assert_eq!(result.snippet, "fn main() {}");
     }
+
+    #[test]
+    fn test_from_config_generic_openai_requires_base_url() {
+        let mut config = LlmConfig {
+            provider: "generic-openai".into(),
+            model: "qwen2.5-7b-instruct".into(),
+            ..Default::default()
+        };
+        let err = LlmService::from_config(&config).unwrap_err().to_string();
+        assert!(err.contains("llm.base_url"));
+
+        // No API key needed for local servers.
+        config.base_url = Some("http://localhost:8080/v1/".into());
+        assert!(LlmService::from_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_parse_chat_completion() {
+        let body = r#"{"choices":[{"index":0,"message":{"role":"assistant","content":"OK"}}]}"#;
+        assert_eq!(parse_chat_completion(body).unwrap(), "OK");
+        assert!(parse_chat_completion(r#"{"choices":[]}"#).is_err());
+        assert!(parse_chat_completion("<html>").is_err());
+    }
+
+    struct EchoModel;
+
+    impl RigCompletionAdapter for EchoModel {
+        fn generate(
+            &self,
+            prompt: String,
+            _system: Option<String>,
+            _max_tokens: u64,
+            _schema: Option<serde_json::Value>,
+        ) -> GenerateFuture<'_> {
+            Box::pin(async move { Ok(prompt) })
+        }
+    }
+
+    /// Replies from a script, one per call.
+    struct ScriptedModel(std::sync::Mutex<Vec<&'static str>>);
+
+    impl RigCompletionAdapter for ScriptedModel {
+        fn generate(
+            &self,
+            _prompt: String,
+            _system: Option<String>,
+            _max_tokens: u64,
+            _schema: Option<serde_json::Value>,
+        ) -> GenerateFuture<'_> {
+            let reply = self.0.lock().unwrap().remove(0).to_string();
+            Box::pin(async move { Ok(reply) })
+        }
+    }
+
+    #[derive(serde::Deserialize, JsonSchema, Debug, PartialEq)]
+    struct Verdict {
+        ok: bool,
+    }
+
+    fn scripted(replies: Vec<&'static str>, ledger: &TokenLedger) -> LlmService {
+        LlmService {
+            inner: Box::new(ScriptedModel(std::sync::Mutex::new(replies))),
+            config: LlmConfig::default(),
+            ledger: ledger.clone(),
+            language: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_structured_repairs_and_counts() {
+        let path = std::env::temp_dir().join(format!("shabka-llm-{}.json", uuid::Uuid::now_v7()));
+        let ledger = TokenLedger::with_path(path.clone());
+
+        let service = scripted(vec!["Sure! {\"ok\": true} Hope that helps."], &ledger);
+        let verdict: Verdict = service
+            .generate_structured(LlmFeature::AutoTag, "p", None)
+            .await
+            .unwrap();
+        assert_eq!(verdict, Verdict { ok: true });
+
+        let service = scripted(vec!["not json", "{\"ok\": false}"], &ledger);
+        let verdict: Verdict = service
+            .generate_structured(LlmFeature::AutoTag, "p", None)
+            .await
+            .unwrap();
+        assert_eq!(verdict, Verdict { ok: false });
+
+        let service = scripted(vec!["nope", "still nope"], &ledger);
+        let result: Result<Verdict> = service
+            .generate_structured(LlmFeature::AutoTag, "p", None)
+            .await;
+        assert!(result.is_err());
+
+        let stats = &ledger.today().structured["auto-tag"];
+        assert_eq!((stats.calls, stats.repaired, stats.failed), (3, 1, 1));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_generate_stops_at_daily_budget() {
+        let path = std::env::temp_dir().join(format!("shabka-llm-{}.json", uuid::Uuid::now_v7()));
+        let ledger = TokenLedger::with_path(path.clone());
+        let service = LlmService {
+            inner: Box::new(EchoModel),
+            config: LlmConfig {
+                daily_token_budget: Some(10),
+                ..Default::default()
+            },
+            ledger: ledger.clone(),
+            language: None,
+        };
+
+        let prompt = "a prompt long enough to spend the whole budget at once";
+        assert!(service
+            .generate(LlmFeature::AutoTag, prompt, None)
+            .await
+            .is_ok());
+        assert!(ledger.today().by_feature["auto-tag"] >= 10);
+
+        let err = service
+            .generate(LlmFeature::Dedup, "again", None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("budget"));
+        assert!(!ledger.today().by_feature.contains_key("dedup"));
+        let _ = std::fs::remove_file(path);
+    }
 }
@@ -0,0 +1,238 @@
+//! Daily token budget for LLM features.
+//!
+//! Every LLM call (auto-tagging, LLM dedup, session compression,
+//! consolidation, screening) adds an estimate of its tokens to a ledger in
+//! `~/.config/shabka/llm_usage.json`, kept per UTC day. Once
+//! `llm.daily_token_budget` is used up, `LlmService` refuses further calls
+//! and each feature falls back to its heuristic until the next day.
+//!
+//! The ledger also counts how structured (JSON) replies parsed, so a model
+//! that keeps producing malformed output shows up in `shabka doctor`.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The feature an LLM call is made for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlmFeature {
+    AutoTag,
+    Dedup,
+    Compression,
+    Consolidation,
+    Screening,
+    /// `shabka backfill` over existing memories.
+    Backfill,
+    /// Connectivity checks such as `shabka doctor`.
+    Check,
+}
+
+impl LlmFeature {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::AutoTag => "auto-tag",
+            Self::Dedup => "dedup",
+            Self::Compression => "compression",
+            Self::Consolidation => "consolidation",
+            Self::Screening => "screening",
+            Self::Backfill => "backfill",
+            Self::Check => "check",
+        }
+    }
+
+    /// Whether replies hold text people read (session summaries, merged
+    /// memories), which `[output] language` applies to.
+    pub fn writes_prose(self) -> bool {
+        matches!(self, Self::Compression | Self::Consolidation | Self::Dedup)
+    }
+}
+
+/// Tokens spent on one day.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub date: Option<NaiveDate>,
+    pub total: u64,
+    /// Tokens per feature, keyed by [`LlmFeature::as_str`].
+    #[serde(default)]
+    pub by_feature: BTreeMap<String, u64>,
+    /// Structured reply outcomes per feature.
+    #[serde(default)]
+    pub structured: BTreeMap<String, ParseStats>,
+}
+
+impl TokenUsage {
+    /// Whether `budget` (if any) has been used up.
+    pub fn is_exhausted(&self, budget: Option<u64>) -> bool {
+        budget.is_some_and(|budget| self.total >= budget)
+    }
+}
+
+/// How a structured LLM reply turned out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseOutcome {
+    /// Parsed on the first reply.
+    Parsed,
+    /// Parsed after sending a malformed reply back for repair.
+    Repaired,
+    /// Never parsed; the feature fell back.
+    Failed,
+}
+
+impl ParseOutcome {
+    /// The outcome of a reply that parsed on attempt `attempt` (from 0).
+    pub fn parsed(attempt: usize) -> Self {
+        if attempt == 0 {
+            Self::Parsed
+        } else {
+            Self::Repaired
+        }
+    }
+}
+
+/// Structured reply counts for one feature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseStats {
+    pub calls: u64,
+    pub repaired: u64,
+    pub failed: u64,
+}
+
+impl ParseStats {
+    /// Share of calls whose first reply didn't parse.
+    pub fn failure_rate(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            (self.repaired + self.failed) as f64 / self.calls as f64
+        }
+    }
+}
+
+/// Persistent record of today's token usage.
+#[derive(Debug, Clone)]
+pub struct TokenLedger {
+    path: PathBuf,
+}
+
+impl Default for TokenLedger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TokenLedger {
+    pub fn new() -> Self {
+        Self::with_path(
+            dirs::config_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join("shabka")
+                .join("llm_usage.json"),
+        )
+    }
+
+    /// A ledger stored at `path` instead of the config directory.
+    pub fn with_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Usage so far today (UTC); empty once the day has rolled over.
+    pub fn today(&self) -> TokenUsage {
+        let today = Utc::now().date_naive();
+        std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|text| serde_json::from_str::<TokenUsage>(&text).ok())
+            .filter(|usage| usage.date == Some(today))
+            .unwrap_or_else(|| TokenUsage {
+                date: Some(today),
+                ..Default::default()
+            })
+    }
+
+    /// Add `tokens` spent on `feature` to today's usage.
+    pub fn record(&self, feature: LlmFeature, tokens: u64) -> std::io::Result<TokenUsage> {
+        let mut usage = self.today();
+        usage.total += tokens;
+        *usage
+            .by_feature
+            .entry(feature.as_str().to_string())
+            .or_insert(0) += tokens;
+        self.save(&usage)?;
+        Ok(usage)
+    }
+
+    /// Count a structured reply for `feature`.
+    pub fn record_parse(&self, feature: LlmFeature, outcome: ParseOutcome) -> std::io::Result<()> {
+        let mut usage = self.today();
+        let stats = usage
+            .structured
+            .entry(feature.as_str().to_string())
+            .or_default();
+        stats.calls += 1;
+        match outcome {
+            ParseOutcome::Parsed => {}
+            ParseOutcome::Repaired => stats.repaired += 1,
+            ParseOutcome::Failed => stats.failed += 1,
+        }
+        self.save(&usage)
+    }
+
+    fn save(&self, usage: &TokenUsage) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(usage)? + "\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_ledger() -> TokenLedger {
+        TokenLedger::with_path(
+            std::env::temp_dir().join(format!("shabka-llm-usage-{}.json", uuid::Uuid::now_v7())),
+        )
+    }
+
+    #[test]
+    fn test_record_accumulates_per_feature() {
+        let ledger = temp_ledger();
+        ledger.record(LlmFeature::AutoTag, 100).unwrap();
+        ledger.record(LlmFeature::Dedup, 50).unwrap();
+        let usage = ledger.record(LlmFeature::AutoTag, 25).unwrap();
+        assert_eq!(usage.total, 175);
+        assert_eq!(usage.by_feature["auto-tag"], 125);
+        assert_eq!(ledger.today(), usage);
+        let _ = std::fs::remove_file(ledger.path());
+    }
+
+    #[test]
+    fn test_previous_day_is_reset() {
+        let ledger = temp_ledger();
+        let stale = TokenUsage {
+            date: NaiveDate::from_ymd_opt(2020, 1, 1),
+            total: 999,
+            ..Default::default()
+        };
+        std::fs::write(ledger.path(), serde_json::to_string(&stale).unwrap()).unwrap();
+        assert_eq!(ledger.today().total, 0);
+        let _ = std::fs::remove_file(ledger.path());
+    }
+
+    #[test]
+    fn test_is_exhausted() {
+        let usage = TokenUsage {
+            total: 1000,
+            ..Default::default()
+        };
+        assert!(usage.is_exhausted(Some(1000)));
+        assert!(!usage.is_exhausted(Some(1001)));
+        assert!(!usage.is_exhausted(None));
+    }
+}
@@ -0,0 +1,191 @@
+//! Run history for maintenance operations.
+//!
+//! Prune, consolidate, reembed, backfill, check, centrality and assess each
+//! leave a [`MaintenanceRun`] in the SQLite `maintenance_runs` table: when
+//! it started and finished, what it changed, what went wrong, and the
+//! settings it ran with. `shabka status --maintenance` lists them, the MCP
+//! server's auto-consolidation and centrality refresh use the last run to
+//! decide when they are due again, and assess runs keep the quality score
+//! that `shabka assess --trend` charts.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::ShabkaError;
+
+/// A maintenance operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MaintenanceOp {
+    Prune,
+    Consolidate,
+    Reembed,
+    Backfill,
+    Check,
+    Centrality,
+    Assess,
+}
+
+impl MaintenanceOp {
+    pub const ALL: [MaintenanceOp; 7] = [
+        Self::Prune,
+        Self::Consolidate,
+        Self::Reembed,
+        Self::Backfill,
+        Self::Check,
+        Self::Centrality,
+        Self::Assess,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Prune => "prune",
+            Self::Consolidate => "consolidate",
+            Self::Reembed => "reembed",
+            Self::Backfill => "backfill",
+            Self::Check => "check",
+            Self::Centrality => "centrality",
+            Self::Assess => "assess",
+        }
+    }
+}
+
+impl std::fmt::Display for MaintenanceOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for MaintenanceOp {
+    type Err = ShabkaError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|op| op.as_str() == s.trim().to_lowercase())
+            .ok_or_else(|| {
+                ShabkaError::InvalidInput(format!(
+                    "unknown maintenance operation '{s}' (prune, consolidate, reembed, backfill, check, centrality, assess)"
+                ))
+            })
+    }
+}
+
+/// One run of a maintenance operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct MaintenanceRun {
+    pub id: Uuid,
+    pub operation: MaintenanceOp,
+    pub started_at: DateTime<Utc>,
+    /// Unset while the run is in progress, or if it never finished.
+    pub finished_at: Option<DateTime<Utc>>,
+    /// What the run did, e.g. `archived` or `memories_superseded`.
+    #[serde(default)]
+    pub counts: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub errors: Vec<String>,
+    /// The settings the run used.
+    #[serde(default)]
+    pub config: serde_json::Value,
+}
+
+impl MaintenanceRun {
+    /// A run of `operation` starting now with the given settings.
+    pub fn start(operation: MaintenanceOp, config: serde_json::Value) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            operation,
+            started_at: Utc::now(),
+            finished_at: None,
+            counts: BTreeMap::new(),
+            errors: Vec::new(),
+            config,
+        }
+    }
+
+    /// Set count `name` to `value`.
+    pub fn count(&mut self, name: &str, value: usize) {
+        self.counts.insert(name.to_string(), value as u64);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.errors.push(message.into());
+    }
+
+    /// Mark the run finished now.
+    pub fn finish(&mut self) {
+        self.finished_at = Some(Utc::now());
+    }
+
+    /// Finished without errors.
+    pub fn succeeded(&self) -> bool {
+        self.finished_at.is_some() && self.errors.is_empty()
+    }
+
+    /// How long the run took, if it finished.
+    pub fn duration(&self) -> Option<chrono::Duration> {
+        self.finished_at.map(|at| at - self.started_at)
+    }
+}
+
+/// Whether an operation scheduled every `interval` (`"daily"`, `"weekly"`
+/// or `"on_startup"`) is due, given its most recent successful run.
+pub fn is_due(last_success: Option<&MaintenanceRun>, interval: &str, now: DateTime<Utc>) -> bool {
+    if interval == "on_startup" {
+        return true;
+    }
+    let Some(finished) = last_success.and_then(|run| run.finished_at) else {
+        return true;
+    };
+    let age = now - finished;
+    match interval {
+        "weekly" => age.num_days() >= 7,
+        _ => age.num_hours() >= 24,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operation_round_trip() {
+        for op in MaintenanceOp::ALL {
+            assert_eq!(op.as_str().parse::<MaintenanceOp>().unwrap(), op);
+            assert_eq!(
+                serde_json::to_string(&op).unwrap(),
+                format!("\"{}\"", op.as_str())
+            );
+        }
+        assert!("vacuum".parse::<MaintenanceOp>().is_err());
+    }
+
+    #[test]
+    fn test_is_due() {
+        let now = Utc::now();
+        let mut run = MaintenanceRun::start(MaintenanceOp::Consolidate, serde_json::Value::Null);
+        assert!(is_due(None, "daily", now));
+
+        run.finished_at = Some(now - chrono::Duration::hours(2));
+        assert!(!is_due(Some(&run), "daily", now));
+        assert!(is_due(Some(&run), "on_startup", now));
+
+        run.finished_at = Some(now - chrono::Duration::days(2));
+        assert!(is_due(Some(&run), "daily", now));
+        assert!(!is_due(Some(&run), "weekly", now));
+    }
+
+    #[test]
+    fn test_succeeded() {
+        let mut run = MaintenanceRun::start(MaintenanceOp::Prune, serde_json::Value::Null);
+        assert!(!run.succeeded());
+        run.finish();
+        assert!(run.succeeded());
+        run.error("archive failed");
+        assert!(!run.succeeded());
+    }
+}
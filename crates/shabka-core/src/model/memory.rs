@@ -1,4 +1,5 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -31,6 +32,16 @@ pub fn validate_create_input(title: &str, content: &str, importance: f32) -> Res
     Ok(())
 }
 
+/// The summary derived from `content`: its first 200 bytes, marked with
+/// `...` when cut.
+pub fn summarize(content: &str) -> String {
+    if content.len() > 200 {
+        format!("{}...", &content[..200])
+    } else {
+        content.to_string()
+    }
+}
+
 /// Validate inputs for updating an existing memory.
 pub fn validate_update_input(input: &UpdateMemoryInput) -> Result<()> {
     if let Some(ref title) = input.title {
@@ -61,8 +72,109 @@ pub fn validate_update_input(input: &UpdateMemoryInput) -> Result<()> {
     Ok(())
 }
 
+/// A small artifact attached to a memory, stored by content hash in the
+/// blob store (see [`crate::attachments`]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Attachment {
+    /// Hex SHA-256 of the content; also its key in the blob store.
+    pub sha256: String,
+    /// Original file name.
+    pub name: String,
+    pub size: u64,
+    #[serde(default)]
+    pub content_type: String,
+}
+
+/// Structured code context for a memory captured from an edit: the snippet
+/// plus where it came from (see [`crate::code`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CodeSnippet {
+    /// Language name, e.g. `rust` or `python`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Path of the file the snippet was taken from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Enclosing or defined symbol, e.g. `parse_config`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    pub snippet: String,
+}
+
+/// Why a memory was disputed: a note and/or a memory holding the
+/// counter-evidence, which is linked with a `Contradicts` relation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Dispute {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evidence_id: Option<Uuid>,
+    pub disputed_by: String,
+    pub disputed_at: DateTime<Utc>,
+}
+
+impl Dispute {
+    pub fn new(disputed_by: String) -> Self {
+        Self {
+            reason: None,
+            evidence_id: None,
+            disputed_by,
+            disputed_at: Utc::now(),
+        }
+    }
+
+    pub fn with_reason(mut self, reason: Option<String>) -> Self {
+        self.reason = reason
+            .map(|r| r.trim().to_string())
+            .filter(|r| !r.is_empty());
+        self
+    }
+
+    pub fn with_evidence(mut self, evidence_id: Option<Uuid>) -> Self {
+        self.evidence_id = evidence_id;
+        self
+    }
+}
+
+/// Someone re-asserting a memory: saving the same fact again, which dedup
+/// folds into the existing memory. Independent assertions raise trust.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Assertion {
+    pub asserted_by: String,
+    /// Where it came from: `mcp`, `web`, `hooks`, ...
+    pub channel: String,
+    pub asserted_at: DateTime<Utc>,
+}
+
+impl Assertion {
+    pub fn new(asserted_by: impl Into<String>, channel: impl Into<String>) -> Self {
+        Self {
+            asserted_by: asserted_by.into(),
+            channel: channel.into(),
+            asserted_at: Utc::now(),
+        }
+    }
+}
+
+/// A teammate vouching for a memory. Endorsements from people other than
+/// the creator raise trust.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Endorsement {
+    pub endorsed_by: String,
+    pub endorsed_at: DateTime<Utc>,
+}
+
+impl Endorsement {
+    pub fn new(endorsed_by: impl Into<String>) -> Self {
+        Self {
+            endorsed_by: endorsed_by.into(),
+            endorsed_at: Utc::now(),
+        }
+    }
+}
+
 /// The core entity in Shabka. Represents a unit of captured knowledge.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Memory {
     pub id: Uuid,
     pub kind: MemoryKind,
@@ -73,26 +185,53 @@ pub struct Memory {
     pub source: MemorySource,
     pub scope: MemoryScope,
     pub importance: f32,
+    /// How sure auto-capture is that it extracted the memory correctly
+    /// (0.0–1.0). `None` for memories saved by hand.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
     pub status: MemoryStatus,
     pub privacy: MemoryPrivacy,
+    /// Classification label (`internal`, `confidential`, ...), checked
+    /// against `[labels.policies]` at export, share and sync boundaries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Machine the memory was captured on (see [`crate::devices`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
+    /// Person responsible for keeping the memory verified (`shabka assign`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
     #[serde(default)]
     pub verification: VerificationStatus,
+    /// When a `Verified` status lapses and the memory is due for
+    /// re-verification. Set from the kind's `verification_expires_days`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification_expires_at: Option<DateTime<Utc>>,
+    /// Context for a `Disputed` status.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dispute: Option<Dispute>,
     pub project_id: Option<String>,
     pub session_id: Option<Uuid>,
     pub created_by: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub accessed_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<CodeSnippet>,
+    /// Re-assertions after creation, one per asserter and channel.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assertions: Vec<Assertion>,
+    /// Teammates who endorsed the memory, one entry each.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub endorsements: Vec<Endorsement>,
 }
 
 impl Memory {
     pub fn new(title: String, content: String, kind: MemoryKind, created_by: String) -> Self {
         let now = Utc::now();
-        let summary = if content.len() > 200 {
-            format!("{}...", &content[..200])
-        } else {
-            content.clone()
-        };
+        let summary = summarize(&content);
 
         Self {
             id: Uuid::now_v7(),
@@ -104,15 +243,25 @@ impl Memory {
             source: MemorySource::Manual,
             scope: MemoryScope::Global,
             importance: 0.5,
+            confidence: None,
             status: MemoryStatus::Active,
             privacy: MemoryPrivacy::Private,
+            label: None,
+            device_id: None,
+            owner: None,
             verification: VerificationStatus::default(),
+            verification_expires_at: None,
+            dispute: None,
             project_id: None,
             session_id: None,
             created_by,
             created_at: now,
             updated_at: now,
             accessed_at: now,
+            attachments: Vec::new(),
+            code: None,
+            assertions: Vec::new(),
+            endorsements: Vec::new(),
         }
     }
 
@@ -126,6 +275,11 @@ impl Memory {
         self
     }
 
+    pub fn with_confidence(mut self, confidence: f32) -> Self {
+        self.confidence = Some(confidence.clamp(0.0, 1.0));
+        self
+    }
+
     pub fn with_scope(mut self, scope: MemoryScope) -> Self {
         self.scope = scope;
         self
@@ -156,19 +310,54 @@ impl Memory {
         self
     }
 
+    pub fn with_label(mut self, label: Option<String>) -> Self {
+        self.label = label;
+        self
+    }
+
+    pub fn with_device(mut self, device_id: Option<String>) -> Self {
+        self.device_id = device_id;
+        self
+    }
+
     pub fn with_verification(mut self, verification: VerificationStatus) -> Self {
         self.verification = verification;
         self
     }
 
-    /// Text used for generating embeddings: title + summary + tags.
+    pub fn with_code(mut self, code: CodeSnippet) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Text used for generating embeddings: title + summary + tags, plus the
+    /// preprocessed code snippet when there is one.
     pub fn embedding_text(&self) -> String {
         let tags = self.tags.join(", ");
-        format!("{}\n{}\n{}", self.title, self.summary, tags)
+        let text = format!("{}\n{}\n{}", self.title, self.summary, tags);
+        match &self.code {
+            Some(code) => format!("{text}\n{}", crate::code::embedding_text(code)),
+            None => text,
+        }
+    }
+
+    /// Whether the memory was captured with confidence below
+    /// `min_confidence` and hasn't been verified since, so it should stay
+    /// out of auto-relate and context packs.
+    pub fn needs_confirmation(&self, min_confidence: f32) -> bool {
+        self.verification != VerificationStatus::Verified
+            && self.confidence.is_some_and(|c| c < min_confidence)
+    }
+
+    /// SHA-256 of the content as lowercase hex. Clients that already hold a
+    /// memory's text can pass this to leave it out of further retrieval.
+    pub fn content_hash(&self) -> String {
+        use sha2::{Digest, Sha256};
+        format!("{:x}", Sha256::digest(self.content.as_bytes()))
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum MemoryKind {
     Observation,
@@ -220,13 +409,21 @@ impl std::str::FromStr for MemoryKind {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum MemorySource {
     Manual,
-    AutoCapture { hook: String },
+    AutoCapture {
+        hook: String,
+    },
     Import,
-    Derived { from: Uuid },
+    Derived {
+        from: Uuid,
+    },
+    /// A summary written by consolidation from the listed memories.
+    Consolidated {
+        sources: Vec<Uuid>,
+    },
 }
 
 impl std::fmt::Display for MemorySource {
@@ -236,11 +433,14 @@ impl std::fmt::Display for MemorySource {
             Self::AutoCapture { hook } => write!(f, "auto-capture ({hook})"),
             Self::Import => write!(f, "import"),
             Self::Derived { from } => write!(f, "derived ({from})"),
+            Self::Consolidated { sources } => {
+                write!(f, "consolidated ({} sources)", sources.len())
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum MemoryScope {
     Global,
@@ -258,7 +458,7 @@ impl std::fmt::Display for MemoryScope {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum MemoryStatus {
     #[default]
@@ -266,6 +466,8 @@ pub enum MemoryStatus {
     Archived,
     Superseded,
     Pending,
+    /// Held back by capture screening until reviewed.
+    Quarantined,
 }
 
 impl std::fmt::Display for MemoryStatus {
@@ -275,11 +477,12 @@ impl std::fmt::Display for MemoryStatus {
             Self::Archived => write!(f, "archived"),
             Self::Superseded => write!(f, "superseded"),
             Self::Pending => write!(f, "pending"),
+            Self::Quarantined => write!(f, "quarantined"),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum MemoryPrivacy {
     Public,
@@ -310,7 +513,7 @@ impl std::str::FromStr for MemoryPrivacy {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum VerificationStatus {
     #[default]
@@ -346,7 +549,7 @@ impl std::str::FromStr for VerificationStatus {
 }
 
 /// Compact representation for Layer 1 search results (~50-100 tokens).
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MemoryIndex {
     pub id: Uuid,
     pub title: String,
@@ -356,6 +559,9 @@ pub struct MemoryIndex {
     pub tags: Vec<String>,
     #[serde(default)]
     pub verification: VerificationStatus,
+    /// Other results this one is linked to by a `Contradicts` relation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contradicts: Vec<Uuid>,
 }
 
 impl From<(&Memory, f32)> for MemoryIndex {
@@ -368,6 +574,7 @@ impl From<(&Memory, f32)> for MemoryIndex {
             score,
             tags: memory.tags.clone(),
             verification: memory.verification,
+            contradicts: Vec::new(),
         }
     }
 }
@@ -406,7 +613,54 @@ pub struct UpdateMemoryInput {
     pub status: Option<MemoryStatus>,
     pub kind: Option<MemoryKind>,
     pub privacy: Option<MemoryPrivacy>,
+    /// New classification label; an empty string removes it.
+    #[serde(default)]
+    pub label: Option<String>,
+    /// New owner; an empty string unassigns the memory.
+    #[serde(default)]
+    pub owner: Option<String>,
     pub verification: Option<VerificationStatus>,
+    /// Expiry stored alongside `verification`; ignored without it. Leaving
+    /// it `None` while setting `verification` clears any previous expiry.
+    #[serde(default)]
+    pub verification_expires_at: Option<DateTime<Utc>>,
+    /// Dispute context stored alongside `verification`, like the expiry.
+    #[serde(default)]
+    pub dispute: Option<Dispute>,
+    /// Mark the memory as accessed at this time (e.g. when it is resurfaced).
+    #[serde(default)]
+    pub accessed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub attachments: Option<Vec<Attachment>>,
+    #[serde(default)]
+    pub code: Option<CodeSnippet>,
+    /// Replaces the memory's assertions (see [`crate::trust::record_assertion`]).
+    #[serde(default)]
+    pub assertions: Option<Vec<Assertion>>,
+    /// Replaces the memory's endorsements (see [`crate::trust::endorse`]).
+    #[serde(default)]
+    pub endorsements: Option<Vec<Endorsement>>,
+}
+
+impl UpdateMemoryInput {
+    /// True when the update only records an access, possibly with a
+    /// re-assertion or endorsement. None is an edit, so backends leave `updated_at`
+    /// alone for these.
+    pub fn is_access_only(&self) -> bool {
+        self.accessed_at.is_some()
+            && self.title.is_none()
+            && self.content.is_none()
+            && self.tags.is_none()
+            && self.importance.is_none()
+            && self.status.is_none()
+            && self.kind.is_none()
+            && self.privacy.is_none()
+            && self.label.is_none()
+            && self.owner.is_none()
+            && self.verification.is_none()
+            && self.attachments.is_none()
+            && self.code.is_none()
+    }
 }
 
 /// Search query parameters.
@@ -472,8 +726,66 @@ impl Default for TimelineQuery {
     }
 }
 
+impl TimelineQuery {
+    /// The `limit` most recent entries, unfiltered.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Entries around this memory.
+    pub fn with_memory(mut self, memory_id: Option<Uuid>) -> Self {
+        self.memory_id = memory_id;
+        self
+    }
+
+    /// Entries created between `start` and `end`; either bound may be open.
+    pub fn with_range(mut self, start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) -> Self {
+        self.start = start;
+        self.end = end;
+        self
+    }
+
+    pub fn with_session(mut self, session_id: Option<Uuid>) -> Self {
+        self.session_id = session_id;
+        self
+    }
+
+    pub fn with_project(mut self, project_id: Option<String>) -> Self {
+        self.project_id = project_id;
+        self
+    }
+
+    pub fn with_kind(mut self, kind: Option<MemoryKind>) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_status(mut self, status: Option<MemoryStatus>) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn with_privacy(mut self, privacy: Option<MemoryPrivacy>) -> Self {
+        self.privacy = privacy;
+        self
+    }
+
+    pub fn with_created_by(mut self, created_by: Option<String>) -> Self {
+        self.created_by = created_by;
+        self
+    }
+}
+
 /// Timeline entry with context (~200-300 tokens).
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TimelineEntry {
     pub id: Uuid,
     pub title: String,
@@ -493,6 +805,16 @@ pub struct TimelineEntry {
     pub verification: VerificationStatus,
 }
 
+/// Number of memories created in one project on one (UTC) day, for
+/// activity calendars.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct DailyActivity {
+    pub day: NaiveDate,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    pub count: usize,
+}
+
 impl From<(&Memory, usize)> for TimelineEntry {
     fn from((memory, related_count): (&Memory, usize)) -> Self {
         Self {
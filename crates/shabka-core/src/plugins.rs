@@ -0,0 +1,306 @@
+//! External commands run on memory lifecycle events.
+//!
+//! `[plugins]` maps each [`LifecycleEvent`] kind to shell commands:
+//!
+//! ```toml
+//! [plugins]
+//! memory_created = ["~/bin/open-ticket.sh"]
+//! consolidation_done = ["curl -s -d @- https://chat.example.com/hooks/shabka"]
+//! ```
+//!
+//! Each command runs through the shell (`sh -c`, or `cmd /C` on Windows)
+//! with the event as one JSON object on stdin and its kind in
+//! `SHABKA_EVENT`. Commands run in the background and never fail the change
+//! that triggered them: a command that can't start, exits non-zero or runs
+//! past `timeout_secs` is logged and otherwise ignored. Memory events come
+//! from [`crate::history::HistoryLogger::log`], so they fire for every
+//! surface (CLI, MCP, web, hooks) and whether or not history is kept.
+//!
+//! `[plugins]` is only read from the global and local configs, never from a
+//! committed `.shabka/config.toml`.
+
+use std::io::Write;
+use std::process::{Child, Stdio};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::consolidate::ConsolidateResult;
+use crate::history::{EventAction, MemoryEvent};
+
+/// How often a background command is checked for exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Commands to run per lifecycle event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginConfig {
+    /// Run when a memory is saved or imported.
+    #[serde(default)]
+    pub memory_created: Vec<String>,
+    /// Run when a memory is superseded by a newer or consolidated one.
+    #[serde(default)]
+    pub memory_superseded: Vec<String>,
+    /// Run after a consolidation run that wasn't a dry run.
+    #[serde(default)]
+    pub consolidation_done: Vec<String>,
+    /// Kill a command still running after this many seconds (default 30).
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self {
+            memory_created: Vec::new(),
+            memory_superseded: Vec::new(),
+            consolidation_done: Vec::new(),
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+}
+
+/// What a plugin command receives on stdin.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LifecycleEvent {
+    MemoryCreated {
+        timestamp: DateTime<Utc>,
+        memory: MemoryEvent,
+    },
+    MemorySuperseded {
+        timestamp: DateTime<Utc>,
+        memory: MemoryEvent,
+    },
+    ConsolidationDone {
+        timestamp: DateTime<Utc>,
+        result: ConsolidateResult,
+    },
+}
+
+impl LifecycleEvent {
+    /// The event for a history entry, if plugins can subscribe to its
+    /// action. Snapshots are left out; a plugin that needs the memory can
+    /// fetch it with `shabka get`.
+    pub fn from_history(event: &MemoryEvent) -> Option<Self> {
+        let mut memory = event.clone();
+        memory.snapshot = None;
+        memory.snapshot_truncated = false;
+        let timestamp = Utc::now();
+        match event.action {
+            EventAction::Created | EventAction::Imported => {
+                Some(Self::MemoryCreated { timestamp, memory })
+            }
+            EventAction::Superseded => Some(Self::MemorySuperseded { timestamp, memory }),
+            _ => None,
+        }
+    }
+
+    pub fn consolidation_done(result: &ConsolidateResult) -> Self {
+        Self::ConsolidationDone {
+            timestamp: Utc::now(),
+            result: result.clone(),
+        }
+    }
+
+    /// The config key and `SHABKA_EVENT` value, e.g. `memory_created`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::MemoryCreated { .. } => "memory_created",
+            Self::MemorySuperseded { .. } => "memory_superseded",
+            Self::ConsolidationDone { .. } => "consolidation_done",
+        }
+    }
+}
+
+/// Runs the configured commands for lifecycle events.
+#[derive(Debug, Clone, Default)]
+pub struct Plugins {
+    config: PluginConfig,
+}
+
+impl Plugins {
+    pub fn new(config: &PluginConfig) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+
+    /// No commands are configured for any event.
+    pub fn is_empty(&self) -> bool {
+        self.config.memory_created.is_empty()
+            && self.config.memory_superseded.is_empty()
+            && self.config.consolidation_done.is_empty()
+    }
+
+    /// Commands subscribed to `event`.
+    pub fn commands(&self, event: &LifecycleEvent) -> &[String] {
+        match event {
+            LifecycleEvent::MemoryCreated { .. } => &self.config.memory_created,
+            LifecycleEvent::MemorySuperseded { .. } => &self.config.memory_superseded,
+            LifecycleEvent::ConsolidationDone { .. } => &self.config.consolidation_done,
+        }
+    }
+
+    /// Start every command subscribed to `event` with the event on stdin,
+    /// without waiting for them to finish.
+    pub fn emit(&self, event: &LifecycleEvent) {
+        let commands = self.commands(event);
+        if commands.is_empty() {
+            return;
+        }
+        let payload = match serde_json::to_vec(event) {
+            Ok(payload) => payload,
+            Err(e) => {
+                tracing::warn!("plugins: failed to serialize {} event: {e}", event.kind());
+                return;
+            }
+        };
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        for command in commands {
+            match spawn(command, event.kind(), &payload) {
+                Ok(child) => watch(child, command.clone(), timeout),
+                Err(e) => tracing::warn!("plugins: failed to run '{command}': {e}"),
+            }
+        }
+    }
+}
+
+fn shell(command: &str) -> std::process::Command {
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut cmd = std::process::Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    };
+    #[cfg(not(windows))]
+    let mut cmd = {
+        let mut cmd = std::process::Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+    cmd.stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    cmd
+}
+
+/// Start `command` and hand it the whole payload before returning, so a
+/// short-lived CLI process can exit without cutting its input short.
+fn spawn(command: &str, kind: &str, payload: &[u8]) -> std::io::Result<Child> {
+    let mut child = shell(command).env("SHABKA_EVENT", kind).spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that ignores its input is fine.
+        if let Err(e) = stdin.write_all(payload) {
+            if e.kind() != std::io::ErrorKind::BrokenPipe {
+                return Err(e);
+            }
+        }
+    }
+    Ok(child)
+}
+
+/// Wait for `child` on a background thread, killing it after `timeout` and
+/// logging a failure.
+fn watch(mut child: Child, command: String, timeout: Duration) {
+    std::thread::spawn(move || {
+        let started = Instant::now();
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) if status.success() => return,
+                Ok(Some(status)) => {
+                    tracing::warn!("plugins: '{command}' exited with {status}");
+                    return;
+                }
+                Ok(None) if started.elapsed() >= timeout => {
+                    tracing::warn!(
+                        "plugins: '{command}' timed out after {}s",
+                        timeout.as_secs()
+                    );
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return;
+                }
+                Ok(None) => std::thread::sleep(POLL_INTERVAL),
+                Err(e) => {
+                    tracing::warn!("plugins: failed to wait for '{command}': {e}");
+                    return;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_history_events() {
+        let memory_id = Uuid::now_v7();
+        let created = MemoryEvent::new(memory_id, EventAction::Imported, "alice".into());
+        let event = LifecycleEvent::from_history(&created).unwrap();
+        assert_eq!(event.kind(), "memory_created");
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["event"], "memory_created");
+        assert_eq!(json["memory"]["memory_id"], memory_id.to_string());
+
+        let superseded = MemoryEvent::new(memory_id, EventAction::Superseded, "alice".into());
+        assert_eq!(
+            LifecycleEvent::from_history(&superseded).unwrap().kind(),
+            "memory_superseded"
+        );
+        let updated = MemoryEvent::new(memory_id, EventAction::Updated, "alice".into());
+        assert!(LifecycleEvent::from_history(&updated).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_emit_pipes_event_to_command() {
+        let dir = std::env::temp_dir().join(format!("shabka-plugins-{}", Uuid::now_v7()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let out = dir.join("event.json");
+        let plugins = Plugins::new(&PluginConfig {
+            consolidation_done: vec![format!(
+                "echo \"$SHABKA_EVENT\" > '{0}.tmp' && cat >> '{0}.tmp' && mv '{0}.tmp' '{0}'",
+                out.display()
+            )],
+            ..Default::default()
+        });
+        let result = ConsolidateResult {
+            clusters_found: 2,
+            clusters_consolidated: 1,
+            memories_superseded: 3,
+            memories_created: 1,
+            run_id: None,
+        };
+        plugins.emit(&LifecycleEvent::consolidation_done(&result));
+        // Nothing subscribed, nothing run.
+        plugins.emit(
+            &LifecycleEvent::from_history(&MemoryEvent::new(
+                Uuid::now_v7(),
+                EventAction::Created,
+                "alice".into(),
+            ))
+            .unwrap(),
+        );
+
+        let started = Instant::now();
+        while !out.exists() && started.elapsed() < Duration::from_secs(10) {
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        let written = std::fs::read_to_string(&out).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let (kind, json) = written.split_once('\n').unwrap();
+        assert_eq!(kind, "consolidation_done");
+        let value: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(value["event"], "consolidation_done");
+        assert_eq!(value["result"]["memories_superseded"], 3);
+    }
+}
@@ -0,0 +1,863 @@
+//! Search query language shared by the CLI, TUI, MCP server and web API.
+//!
+//! A query mixes structured field operators with free text:
+//!
+//! ```text
+//! kind:error tag:auth after:2025-01-01 "connection pool"
+//! ```
+//!
+//! Field operators become filters; everything else (bare words and quoted
+//! phrases) is the semantic part that gets embedded and keyword-scored.
+//! Quoted phrases must also appear literally in the title or content, and
+//! matching memories are fetched even when the embedding misses them.
+//! Unknown `word:value` tokens (e.g. URLs) are kept as free text.
+//!
+//! | Operator            | Meaning                                       |
+//! |---------------------|-----------------------------------------------|
+//! | `kind:<kind>`       | Memory kind; repeat to allow several          |
+//! | `tag:<tag>`         | Has the tag; repeat to match any of several   |
+//! | `project:<id>`      | Belongs to the project                        |
+//! | `status:<status>`   | active, archived, superseded, pending, quarantined |
+//! | `author:<user>`     | Created by the user                           |
+//! | `device:<id>`       | Captured on the device (ID prefix, see `shabka devices`) |
+//! | `after:<date>`      | Created on or after (YYYY-MM-DD or RFC 3339)  |
+//! | `before:<date>`     | Created before (YYYY-MM-DD or RFC 3339)       |
+//! | `importance:<0-1>`  | Importance at least this value                |
+//! | `symbol:<name>`     | Boost memories referencing the code symbol    |
+//! | `title:<text>`      | Title contains the text (case-insensitive)    |
+//!
+//! Prefix `kind:`, `tag:`, `project:` or `device:` with `-` to exclude instead
+//! (`-kind:observation`), and write `-"some phrase"` to drop memories whose
+//! title or content contains the phrase (case-insensitive). Memories the
+//! client already has in context are excluded by ID or content hash through
+//! [`SearchQuery::without_ids`] and [`SearchQuery::without_hashes`].
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::embedding::EmbeddingService;
+use crate::error::{Result, ShabkaError};
+use crate::model::{Memory, MemoryKind, MemoryStatus, TimelineQuery};
+use crate::storage::StorageBackend;
+
+/// A parsed search query: free text plus structured filters.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    /// Free-text part used for embedding and keyword scoring.
+    pub text: String,
+    pub kinds: Vec<MemoryKind>,
+    pub tags: Vec<String>,
+    pub project: Option<String>,
+    pub status: Option<MemoryStatus>,
+    pub author: Option<String>,
+    /// Device ID prefix (see [`crate::devices::matches`]).
+    pub device: Option<String>,
+    pub after: Option<DateTime<Utc>>,
+    pub before: Option<DateTime<Utc>>,
+    pub min_importance: Option<f32>,
+    pub exclude_kinds: Vec<MemoryKind>,
+    pub exclude_tags: Vec<String>,
+    pub exclude_projects: Vec<String>,
+    pub exclude_devices: Vec<String>,
+    /// Phrases that must not appear in the title or content (lowercased).
+    pub exclude_phrases: Vec<String>,
+    /// Memory ID prefixes to leave out (lowercased).
+    pub exclude_ids: Vec<String>,
+    /// [`Memory::content_hash`] prefixes to leave out (lowercased).
+    pub exclude_hashes: Vec<String>,
+    /// Quoted phrases that must appear in the title or content (lowercased).
+    pub phrases: Vec<String>,
+    /// Text the title must contain, from `title:` (lowercased).
+    pub title_terms: Vec<String>,
+    /// Code symbol to boost (not a filter; see [`crate::code::symbol_score`]).
+    pub symbol: Option<String>,
+}
+
+impl SearchQuery {
+    /// Parse a query string. Fails only when a known operator has an invalid
+    /// value (e.g. `kind:bogus` or `after:yesterday`).
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut query = Self::default();
+        let mut text = Vec::new();
+
+        for token in tokenize(input) {
+            if token.negated {
+                query.exclude_phrases.push(token.text.to_lowercase());
+                continue;
+            }
+            let Some((field, value, negated)) = split_operator(&token) else {
+                if token.quoted {
+                    query.phrases.push(token.text.to_lowercase());
+                }
+                text.push(token.text);
+                continue;
+            };
+            if negated {
+                match field.as_str() {
+                    "kind" => query.exclude_kinds.push(parse_kind(&value)?),
+                    "tag" => query.exclude_tags.push(value),
+                    "project" => query.exclude_projects.push(value),
+                    "device" => query.exclude_devices.push(value),
+                    _ => text.push(token.text),
+                }
+                continue;
+            }
+            match field.as_str() {
+                "kind" => query.kinds.push(parse_kind(&value)?),
+                "tag" => query.tags.push(value),
+                "project" => query.project = Some(value),
+                "status" => query.status = Some(parse_status(&value)?),
+                "author" => query.author = Some(value),
+                "device" => query.device = Some(value),
+                "symbol" => query.symbol = Some(value),
+                "title" => {
+                    query.title_terms.push(value.to_lowercase());
+                    text.push(value);
+                }
+                "after" => query.after = Some(parse_date("after", &value)?),
+                "before" => query.before = Some(parse_date("before", &value)?),
+                "importance" => {
+                    let min: f32 = value.parse().map_err(|_| {
+                        ShabkaError::InvalidInput(format!(
+                            "importance: expected a number between 0 and 1, got '{value}'"
+                        ))
+                    })?;
+                    query.min_importance = Some(min.clamp(0.0, 1.0));
+                }
+                _ => text.push(token.text),
+            }
+        }
+
+        query.text = text.join(" ");
+        Ok(query)
+    }
+
+    /// Whether any structured filter is set.
+    pub fn has_filters(&self) -> bool {
+        !self.kinds.is_empty()
+            || !self.tags.is_empty()
+            || self.project.is_some()
+            || self.status.is_some()
+            || self.author.is_some()
+            || self.device.is_some()
+            || self.after.is_some()
+            || self.before.is_some()
+            || self.min_importance.is_some()
+            || self.has_exclusions()
+    }
+
+    /// Whether any exclusion (`-kind:`, `-tag:`, `-project:`, `-device:`,
+    /// `-"phrase"`, IDs or content hashes) is set.
+    pub fn has_exclusions(&self) -> bool {
+        !self.exclude_kinds.is_empty()
+            || !self.exclude_tags.is_empty()
+            || !self.exclude_projects.is_empty()
+            || !self.exclude_devices.is_empty()
+            || !self.exclude_phrases.is_empty()
+            || !self.exclude_ids.is_empty()
+            || !self.exclude_hashes.is_empty()
+    }
+
+    /// Whether the query requires literal matches (quoted phrases or
+    /// `title:`).
+    pub fn has_literals(&self) -> bool {
+        !self.phrases.is_empty() || !self.title_terms.is_empty()
+    }
+
+    /// Add a kind filter from an explicit parameter (CLI flag, API field).
+    pub fn with_kind(mut self, kind: Option<MemoryKind>) -> Self {
+        if let Some(kind) = kind {
+            if !self.kinds.contains(&kind) {
+                self.kinds.push(kind);
+            }
+        }
+        self
+    }
+
+    /// Add tag filters from an explicit parameter.
+    pub fn with_tags(mut self, tags: impl IntoIterator<Item = String>) -> Self {
+        for tag in tags {
+            if !tag.is_empty() && !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+        self
+    }
+
+    /// Use `project` unless the query already names one.
+    pub fn with_project(mut self, project: Option<String>) -> Self {
+        if self.project.is_none() {
+            self.project = project;
+        }
+        self
+    }
+
+    /// Use `symbol` unless the query already names one.
+    pub fn with_symbol(mut self, symbol: Option<String>) -> Self {
+        if self.symbol.is_none() {
+            self.symbol = symbol.filter(|s| !s.is_empty());
+        }
+        self
+    }
+
+    /// Text to embed: the free text, or the symbol's words when the query
+    /// only names a symbol.
+    pub fn embedding_text(&self) -> String {
+        match &self.symbol {
+            Some(symbol) if self.text.trim().is_empty() => {
+                crate::code::symbol_key(symbol).replace('_', " ")
+            }
+            _ => self.text.clone(),
+        }
+    }
+
+    /// Exclude memories of these kinds.
+    pub fn without_kinds(mut self, kinds: impl IntoIterator<Item = MemoryKind>) -> Self {
+        for kind in kinds {
+            if !self.exclude_kinds.contains(&kind) {
+                self.exclude_kinds.push(kind);
+            }
+        }
+        self
+    }
+
+    /// Exclude memories carrying any of these tags.
+    pub fn without_tags(mut self, tags: impl IntoIterator<Item = String>) -> Self {
+        for tag in tags {
+            if !tag.is_empty() && !self.exclude_tags.contains(&tag) {
+                self.exclude_tags.push(tag);
+            }
+        }
+        self
+    }
+
+    /// Exclude memories belonging to any of these projects.
+    pub fn without_projects(mut self, projects: impl IntoIterator<Item = String>) -> Self {
+        for project in projects {
+            if !project.is_empty() && !self.exclude_projects.contains(&project) {
+                self.exclude_projects.push(project);
+            }
+        }
+        self
+    }
+
+    /// Exclude memories captured on any of these devices (ID prefixes).
+    pub fn without_devices(mut self, devices: impl IntoIterator<Item = String>) -> Self {
+        for device in devices {
+            if !device.is_empty() && !self.exclude_devices.contains(&device) {
+                self.exclude_devices.push(device);
+            }
+        }
+        self
+    }
+
+    /// Exclude memories by ID or ID prefix, e.g. ones the client already has
+    /// in context.
+    pub fn without_ids(mut self, ids: impl IntoIterator<Item = String>) -> Self {
+        for id in ids {
+            let id = id.trim().to_lowercase();
+            if !id.is_empty() && !self.exclude_ids.contains(&id) {
+                self.exclude_ids.push(id);
+            }
+        }
+        self
+    }
+
+    /// Exclude memories whose [`Memory::content_hash`] starts with any of
+    /// these.
+    pub fn without_hashes(mut self, hashes: impl IntoIterator<Item = String>) -> Self {
+        for hash in hashes {
+            let hash = hash.trim().to_lowercase();
+            if !hash.is_empty() && !self.exclude_hashes.contains(&hash) {
+                self.exclude_hashes.push(hash);
+            }
+        }
+        self
+    }
+
+    /// Check a memory against the structured filters and literal matches
+    /// (the rest of the free text is not considered here — it only drives
+    /// ranking).
+    pub fn matches(&self, memory: &Memory) -> bool {
+        if self.is_excluded(memory) || !self.matches_literals(memory) {
+            return false;
+        }
+        if !self.kinds.is_empty() && !self.kinds.contains(&memory.kind) {
+            return false;
+        }
+        if !self.tags.is_empty() && !self.tags.iter().any(|t| memory.tags.contains(t)) {
+            return false;
+        }
+        if let Some(ref p) = self.project {
+            if memory.project_id.as_deref() != Some(p.as_str()) {
+                return false;
+            }
+        }
+        if let Some(status) = self.status {
+            if memory.status != status {
+                return false;
+            }
+        }
+        if let Some(ref author) = self.author {
+            if memory.created_by != *author {
+                return false;
+            }
+        }
+        if let Some(ref device) = self.device {
+            if !crate::devices::matches(memory.device_id.as_deref(), device) {
+                return false;
+            }
+        }
+        if let Some(after) = self.after {
+            if memory.created_at < after {
+                return false;
+            }
+        }
+        if let Some(before) = self.before {
+            if memory.created_at >= before {
+                return false;
+            }
+        }
+        if let Some(min) = self.min_importance {
+            if memory.importance < min {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Whether the memory contains every quoted phrase and `title:` text.
+    fn matches_literals(&self, memory: &Memory) -> bool {
+        if !self.has_literals() {
+            return true;
+        }
+        let title = memory.title.to_lowercase();
+        let content = memory.content.to_lowercase();
+        self.title_terms.iter().all(|t| title.contains(t.as_str()))
+            && self
+                .phrases
+                .iter()
+                .all(|p| title.contains(p.as_str()) || content.contains(p.as_str()))
+    }
+
+    /// Whether an exclusion rules the memory out.
+    fn is_excluded(&self, memory: &Memory) -> bool {
+        if self.exclude_kinds.contains(&memory.kind) {
+            return true;
+        }
+        if self.exclude_tags.iter().any(|t| memory.tags.contains(t)) {
+            return true;
+        }
+        if let Some(ref p) = memory.project_id {
+            if self.exclude_projects.contains(p) {
+                return true;
+            }
+        }
+        if self
+            .exclude_devices
+            .iter()
+            .any(|d| crate::devices::matches(memory.device_id.as_deref(), d))
+        {
+            return true;
+        }
+        if !self.exclude_ids.is_empty() {
+            let id = memory.id.to_string();
+            if self.exclude_ids.iter().any(|p| id.starts_with(p.as_str())) {
+                return true;
+            }
+        }
+        if !self.exclude_hashes.is_empty() {
+            let hash = memory.content_hash();
+            if self
+                .exclude_hashes
+                .iter()
+                .any(|p| hash.starts_with(p.as_str()))
+            {
+                return true;
+            }
+        }
+        if !self.exclude_phrases.is_empty() {
+            let title = memory.title.to_lowercase();
+            let content = memory.content.to_lowercase();
+            if self
+                .exclude_phrases
+                .iter()
+                .any(|p| title.contains(p.as_str()) || content.contains(p.as_str()))
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Timeline query with the filters the storage layer can apply itself.
+    pub fn timeline_query(&self, limit: usize) -> TimelineQuery {
+        let kind = match self.kinds.as_slice() {
+            [kind] => Some(*kind),
+            _ => None,
+        };
+        TimelineQuery::new(limit)
+            .with_range(self.after, self.before)
+            .with_project(self.project.clone())
+            .with_kind(kind)
+            .with_status(self.status)
+            .with_created_by(self.author.clone())
+    }
+}
+
+/// Fetch `(memory, vector_score)` candidates for a parsed query, with the
+/// structured filters applied. Free text is embedded and vector-searched
+/// (over-fetching `fetch_limit`); a filter-only query is served from the
+/// timeline instead, with a vector score of zero. Memories containing the
+/// query's quoted phrases and `title:` text, and memories whose titles are
+/// within a few typos of a query word, are added with a vector score of zero;
+/// with a `symbol:`, so are memories from the backend's symbol index.
+pub async fn fetch_candidates(
+    storage: &impl StorageBackend,
+    embedder: &EmbeddingService,
+    query: &SearchQuery,
+    fetch_limit: usize,
+) -> Result<Vec<(Memory, f32)>> {
+    let text = query.embedding_text();
+    let mut candidates: Vec<(Memory, f32)> = if text.trim().is_empty() {
+        let entries = storage.timeline(&query.timeline_query(fetch_limit)).await?;
+        let ids: Vec<_> = entries.iter().map(|e| e.id).collect();
+        storage
+            .get_memories(&ids)
+            .await?
+            .into_iter()
+            .map(|m| (m, 0.0))
+            .collect()
+    } else {
+        let embedding = embedder.embed(&text).await?;
+        storage.vector_search(&embedding, fetch_limit).await?
+    };
+    if query.has_literals() {
+        // Exact matches count whatever their similarity to the embedding.
+        for memory in storage
+            .literal_search(&query.phrases, &query.title_terms, fetch_limit)
+            .await?
+        {
+            if !candidates.iter().any(|(m, _)| m.id == memory.id) {
+                candidates.push((memory, 0.0));
+            }
+        }
+    }
+    if !text.trim().is_empty() {
+        // Misspelled words miss both the embedding and exact keyword
+        // matches; bring in memories whose titles are a few typos away so
+        // keyword_score can rank them.
+        let terms: Vec<String> = text.split_whitespace().map(str::to_string).collect();
+        for memory in storage.title_typo_search(&terms, fetch_limit).await? {
+            if !candidates.iter().any(|(m, _)| m.id == memory.id) {
+                candidates.push((memory, 0.0));
+            }
+        }
+    }
+    if let Some(ref symbol) = query.symbol {
+        let key = crate::code::symbol_key(symbol);
+        for memory in storage.symbol_search(&key, fetch_limit).await? {
+            if !candidates.iter().any(|(m, _)| m.id == memory.id) {
+                candidates.push((memory, 0.0));
+            }
+        }
+    }
+    candidates.retain(|(m, _)| query.matches(m));
+    Ok(candidates)
+}
+
+// ---------------------------------------------------------------------------
+// Tokenizer
+// ---------------------------------------------------------------------------
+
+struct Token {
+    /// Token text with quotes removed.
+    text: String,
+    /// Whether the token started with a quote (so `"kind:x"` stays text).
+    quoted: bool,
+    /// Whether the token was written `-"phrase"` (an exclusion).
+    negated: bool,
+}
+
+/// Split on whitespace, keeping double-quoted runs together. Quotes may also
+/// start mid-token, as in `tag:"two words"`.
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut quoted = false;
+    let mut negated = false;
+    let mut started = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => {
+                if !started {
+                    quoted = true;
+                } else if !in_quotes && !quoted && current == "-" {
+                    current.clear();
+                    quoted = true;
+                    negated = true;
+                }
+                in_quotes = !in_quotes;
+                started = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if started {
+                    tokens.push(Token {
+                        text: std::mem::take(&mut current),
+                        quoted,
+                        negated,
+                    });
+                }
+                quoted = false;
+                negated = false;
+                started = false;
+            }
+            c => {
+                current.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        tokens.push(Token {
+            text: current,
+            quoted,
+            negated,
+        });
+    }
+    tokens.retain(|t| !t.text.is_empty());
+    tokens
+}
+
+/// Split `field:value` (or `-field:value`, flagged as negated) for unquoted
+/// tokens with a non-empty value.
+fn split_operator(token: &Token) -> Option<(String, String, bool)> {
+    if token.quoted {
+        return None;
+    }
+    let (text, negated) = match token.text.strip_prefix('-') {
+        Some(rest) => (rest, true),
+        None => (token.text.as_str(), false),
+    };
+    let (field, value) = text.split_once(':')?;
+    if field.is_empty() || value.is_empty() || !field.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some((field.to_ascii_lowercase(), value.to_string(), negated))
+}
+
+fn parse_kind(value: &str) -> Result<MemoryKind> {
+    value
+        .parse()
+        .map_err(|e: String| ShabkaError::InvalidInput(e))
+}
+
+fn parse_status(value: &str) -> Result<MemoryStatus> {
+    serde_json::from_str(&format!("\"{}\"", value.to_ascii_lowercase())).map_err(|_| {
+        ShabkaError::InvalidInput(format!(
+            "status: unknown status '{value}' (active, archived, superseded, pending, quarantined)"
+        ))
+    })
+}
+
+pub(crate) fn parse_date(field: &str, value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|_| {
+            ShabkaError::InvalidInput(format!(
+                "{field}: expected YYYY-MM-DD or RFC 3339 date, got '{value}'"
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(kind: MemoryKind, tags: &[&str]) -> Memory {
+        Memory::new(
+            "Pool exhaustion".to_string(),
+            "Connection pool ran dry under load".to_string(),
+            kind,
+            "alice".to_string(),
+        )
+        .with_tags(tags.iter().map(|t| t.to_string()).collect())
+        .with_project("api".to_string())
+    }
+
+    #[test]
+    fn test_parse_mixed_query() {
+        let q = SearchQuery::parse(r#"kind:error tag:auth after:2025-01-01 "connection pool""#)
+            .unwrap();
+        assert_eq!(q.text, "connection pool");
+        assert_eq!(q.kinds, vec![MemoryKind::Error]);
+        assert_eq!(q.tags, vec!["auth"]);
+        assert_eq!(q.after.unwrap().to_rfc3339(), "2025-01-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn test_parse_plain_text() {
+        let q = SearchQuery::parse("how do we retry  failed jobs").unwrap();
+        assert_eq!(q.text, "how do we retry failed jobs");
+        assert!(!q.has_filters());
+    }
+
+    #[test]
+    fn test_parse_unknown_operator_is_text() {
+        let q = SearchQuery::parse("see https://example.com foo:bar").unwrap();
+        assert_eq!(q.text, "see https://example.com foo:bar");
+        assert!(!q.has_filters());
+    }
+
+    #[test]
+    fn test_parse_quoted_operator_is_text() {
+        let q = SearchQuery::parse(r#""kind:error" handling"#).unwrap();
+        assert_eq!(q.text, "kind:error handling");
+        assert!(q.kinds.is_empty());
+        assert_eq!(q.phrases, vec!["kind:error"]);
+    }
+
+    #[test]
+    fn test_literal_matches() {
+        let q = SearchQuery::parse(r#""Pool RAN dry" title:exhaustion"#).unwrap();
+        assert_eq!(q.phrases, vec!["pool ran dry"]);
+        assert_eq!(q.title_terms, vec!["exhaustion"]);
+        assert_eq!(q.text, "Pool RAN dry exhaustion");
+        assert!(q.matches(&memory(MemoryKind::Error, &[])));
+
+        // The phrase must appear as written, not just its words.
+        let q = SearchQuery::parse(r#""dry pool""#).unwrap();
+        assert!(!q.matches(&memory(MemoryKind::Error, &[])));
+        // title: ignores the content.
+        let q = SearchQuery::parse("title:load").unwrap();
+        assert!(!q.matches(&memory(MemoryKind::Error, &[])));
+    }
+
+    #[test]
+    fn test_parse_quoted_value() {
+        let q = SearchQuery::parse(r#"tag:"rate limit" retries"#).unwrap();
+        assert_eq!(q.tags, vec!["rate limit"]);
+        assert_eq!(q.text, "retries");
+    }
+
+    #[test]
+    fn test_parse_all_fields() {
+        let q = SearchQuery::parse(
+            "project:api status:archived author:alice before:2025-06-01T12:00:00Z importance:0.7",
+        )
+        .unwrap();
+        assert_eq!(q.project.as_deref(), Some("api"));
+        assert_eq!(q.status, Some(MemoryStatus::Archived));
+        assert_eq!(q.author.as_deref(), Some("alice"));
+        assert!(q.before.is_some());
+        assert_eq!(q.min_importance, Some(0.7));
+        assert!(q.text.is_empty());
+    }
+
+    #[test]
+    fn test_parse_invalid_values() {
+        assert!(SearchQuery::parse("kind:bogus").is_err());
+        assert!(SearchQuery::parse("after:yesterday").is_err());
+        assert!(SearchQuery::parse("status:gone").is_err());
+        assert!(SearchQuery::parse("importance:high").is_err());
+    }
+
+    #[test]
+    fn test_operator_field_is_case_insensitive() {
+        let q = SearchQuery::parse("KIND:fix").unwrap();
+        assert_eq!(q.kinds, vec![MemoryKind::Fix]);
+    }
+
+    #[test]
+    fn test_matches_filters() {
+        let m = memory(MemoryKind::Error, &["auth", "db"]);
+        assert!(SearchQuery::parse("kind:error").unwrap().matches(&m));
+        assert!(SearchQuery::parse("kind:fix kind:error")
+            .unwrap()
+            .matches(&m));
+        assert!(!SearchQuery::parse("kind:fix").unwrap().matches(&m));
+        assert!(SearchQuery::parse("tag:db").unwrap().matches(&m));
+        assert!(!SearchQuery::parse("tag:ui").unwrap().matches(&m));
+        assert!(SearchQuery::parse("project:api author:alice")
+            .unwrap()
+            .matches(&m));
+        assert!(!SearchQuery::parse("project:web").unwrap().matches(&m));
+        assert!(!SearchQuery::parse("after:2999-01-01").unwrap().matches(&m));
+        assert!(SearchQuery::parse("before:2999-01-01").unwrap().matches(&m));
+        assert!(!SearchQuery::parse("importance:0.9").unwrap().matches(&m));
+    }
+
+    #[test]
+    fn test_with_explicit_params() {
+        let q = SearchQuery::parse("project:api pool")
+            .unwrap()
+            .with_kind(Some(MemoryKind::Error))
+            .with_tags(vec!["auth".to_string()])
+            .with_project(Some("web".to_string()));
+        assert_eq!(q.kinds, vec![MemoryKind::Error]);
+        assert_eq!(q.tags, vec!["auth"]);
+        assert_eq!(q.project.as_deref(), Some("api"), "query wins over param");
+    }
+
+    #[test]
+    fn test_parse_exclusions() {
+        let q = SearchQuery::parse(
+            r#"-kind:observation -tag:auto -project:scratch -"pool size" retries -1"#,
+        )
+        .unwrap();
+        assert_eq!(q.exclude_kinds, vec![MemoryKind::Observation]);
+        assert_eq!(q.exclude_tags, vec!["auto"]);
+        assert_eq!(q.exclude_projects, vec!["scratch"]);
+        assert_eq!(q.exclude_phrases, vec!["pool size"]);
+        assert_eq!(q.text, "retries -1");
+        assert!(q.has_exclusions());
+        assert!(SearchQuery::parse("-kind:bogus").is_err());
+    }
+
+    #[test]
+    fn test_exclusions_filter_memories() {
+        let m = memory(MemoryKind::Error, &["auth", "db"]);
+        assert!(!SearchQuery::parse("-kind:error").unwrap().matches(&m));
+        assert!(SearchQuery::parse("-kind:fix").unwrap().matches(&m));
+        assert!(!SearchQuery::parse("-tag:db").unwrap().matches(&m));
+        assert!(!SearchQuery::parse("-project:api").unwrap().matches(&m));
+        assert!(!SearchQuery::parse(r#"-"RAN DRY""#).unwrap().matches(&m));
+        assert!(SearchQuery::parse(r#"-"pool size""#).unwrap().matches(&m));
+
+        let q = SearchQuery::default()
+            .without_kinds([MemoryKind::Error])
+            .without_tags(vec!["ui".to_string()]);
+        assert!(!q.matches(&m));
+        let q = SearchQuery::default().without_projects(vec!["web".to_string()]);
+        assert!(q.matches(&m));
+    }
+
+    #[test]
+    fn test_exclude_ids_and_hashes() {
+        let m = memory(MemoryKind::Error, &[]);
+        let id = m.id.to_string();
+        let hash = m.content_hash();
+        assert_eq!(hash.len(), 64);
+
+        let q = SearchQuery::default().without_ids(vec![id[..8].to_uppercase()]);
+        assert!(q.has_exclusions());
+        assert!(!q.matches(&m));
+        let q = SearchQuery::default().without_hashes(vec![hash[..12].to_string()]);
+        assert!(!q.matches(&m));
+        let q = SearchQuery::default()
+            .without_ids(vec!["ffffffff".to_string(), " ".to_string()])
+            .without_hashes(vec!["zz".to_string()]);
+        assert_eq!(q.exclude_ids, vec!["ffffffff"]);
+        assert!(q.matches(&m));
+    }
+
+    #[test]
+    fn test_device_filters() {
+        let m = memory(MemoryKind::Error, &[]).with_device(Some("0192a4b7c3d4".to_string()));
+        assert!(SearchQuery::parse("device:0192a4b7").unwrap().matches(&m));
+        assert!(!SearchQuery::parse("device:ffff0000").unwrap().matches(&m));
+        assert!(!SearchQuery::parse("-device:0192").unwrap().matches(&m));
+        let q = SearchQuery::default().without_devices(vec!["ffff".to_string()]);
+        assert!(q.matches(&m));
+        assert!(q.has_exclusions());
+    }
+
+    #[test]
+    fn test_timeline_query_pushdown() {
+        let q = SearchQuery::parse("kind:error project:api status:active").unwrap();
+        let tq = q.timeline_query(50);
+        assert_eq!(tq.limit, 50);
+        assert_eq!(tq.kind, Some(MemoryKind::Error));
+        assert_eq!(tq.project_id.as_deref(), Some("api"));
+        assert_eq!(tq.status, Some(MemoryStatus::Active));
+
+        let multi = SearchQuery::parse("kind:error kind:fix").unwrap();
+        assert_eq!(multi.timeline_query(10).kind, None);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_candidates_filter_only() {
+        let storage = crate::storage::SqliteStorage::open_in_memory().unwrap();
+        let embedder =
+            EmbeddingService::from_config(&crate::config::EmbeddingConfig::default()).unwrap();
+        for kind in [MemoryKind::Error, MemoryKind::Fact] {
+            let m = memory(kind, &["auth"]);
+            let emb = embedder.embed(&m.embedding_text()).await.unwrap();
+            storage.save_memory(&m, Some(&emb)).await.unwrap();
+        }
+
+        let q = SearchQuery::parse("kind:error").unwrap();
+        let results = fetch_candidates(&storage, &embedder, &q, 30).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.kind, MemoryKind::Error);
+
+        let q = SearchQuery::parse("tag:auth pool").unwrap();
+        let results = fetch_candidates(&storage, &embedder, &q, 30).await.unwrap();
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_symbol() {
+        let q = SearchQuery::parse("symbol:parseConfig retries").unwrap();
+        assert_eq!(q.symbol.as_deref(), Some("parseConfig"));
+        assert_eq!(q.text, "retries");
+        assert_eq!(q.embedding_text(), "retries");
+        assert!(!q.has_filters(), "a symbol boosts, it does not filter");
+
+        let q = SearchQuery::default().with_symbol(Some("parseConfig".into()));
+        assert_eq!(q.embedding_text(), "parse config");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_candidates_symbol_index() {
+        let storage = crate::storage::SqliteStorage::open_in_memory().unwrap();
+        let embedder =
+            EmbeddingService::from_config(&crate::config::EmbeddingConfig::default()).unwrap();
+        let mut m = memory(MemoryKind::Fix, &[]);
+        m.content = "Raised the limit in `open_pool`".to_string();
+        // No embedding: only the symbol index can find it.
+        storage.save_memory(&m, None).await.unwrap();
+
+        let q = SearchQuery::parse("symbol:openPool").unwrap();
+        let results = fetch_candidates(&storage, &embedder, &q, 30).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.id, m.id);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_candidates_exact_phrase() {
+        let storage = crate::storage::SqliteStorage::open_in_memory().unwrap();
+        let embedder =
+            EmbeddingService::from_config(&crate::config::EmbeddingConfig::default()).unwrap();
+        let mut m = memory(MemoryKind::Error, &[]);
+        m.content = "Failed with ECONNRESET: socket hang up".to_string();
+        // No embedding: only the literal search can find it.
+        storage.save_memory(&m, None).await.unwrap();
+        storage
+            .save_memory(&memory(MemoryKind::Error, &[]), None)
+            .await
+            .unwrap();
+
+        let q = SearchQuery::parse(r#""socket hang up""#).unwrap();
+        let results = fetch_candidates(&storage, &embedder, &q, 30).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.id, m.id);
+    }
+
+    #[test]
+    fn test_timeline_query_builder() {
+        let q = TimelineQuery::new(20)
+            .with_kind(Some(MemoryKind::Fix))
+            .with_project(Some("api".into()))
+            .with_offset(40);
+        assert_eq!(q.limit, 20);
+        assert_eq!(q.offset, 40);
+        assert_eq!(q.kind, Some(MemoryKind::Fix));
+        assert_eq!(q.project_id.as_deref(), Some("api"));
+        assert!(q.start.is_none() && q.created_by.is_none());
+    }
+}
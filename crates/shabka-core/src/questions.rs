@@ -0,0 +1,279 @@
+//! Log of searches that came back empty or with only weak matches.
+//!
+//! Questions are stored as JSONL at `~/.config/shabka/questions.jsonl`, one
+//! [`Question`] per line. Grouped by query, they show what agents and users
+//! keep looking for without finding it.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::serde_defaults::default_true;
+
+/// When to log a search as unanswered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestionConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Searches whose best result scores below this count as unanswered
+    /// (default 0.4).
+    #[serde(default = "default_min_score")]
+    pub min_score: f32,
+}
+
+fn default_min_score() -> f32 {
+    0.4
+}
+
+impl Default for QuestionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_score: default_min_score(),
+        }
+    }
+}
+
+/// A search that went unanswered.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Question {
+    pub query: String,
+    /// Where it was asked: `cli`, `mcp` or `web`.
+    pub channel: String,
+    pub asked_by: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    pub asked_at: DateTime<Utc>,
+    /// Results returned.
+    pub results: usize,
+    /// Score of the best result, if there was one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_score: Option<f32>,
+}
+
+impl Question {
+    pub fn new(
+        query: impl Into<String>,
+        channel: impl Into<String>,
+        asked_by: impl Into<String>,
+    ) -> Self {
+        Self {
+            query: query.into(),
+            channel: channel.into(),
+            asked_by: asked_by.into(),
+            project_id: None,
+            asked_at: Utc::now(),
+            results: 0,
+            top_score: None,
+        }
+    }
+
+    pub fn with_project(mut self, project_id: Option<String>) -> Self {
+        self.project_id = project_id;
+        self
+    }
+
+    /// Record what the search returned: the number of results and their
+    /// scores (best first or in any order).
+    pub fn with_scores(mut self, scores: impl IntoIterator<Item = f32>) -> Self {
+        let (results, top_score) = scores.into_iter().fold((0, None), |(n, top), s| {
+            (n + 1, Some(top.map_or(s, |t: f32| t.max(s))))
+        });
+        self.results = results;
+        self.top_score = top_score;
+        self
+    }
+}
+
+/// One query asked, possibly many times, without a good answer.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct OpenQuestion {
+    /// The query as first asked.
+    pub query: String,
+    pub times_asked: usize,
+    pub last_asked: DateTime<Utc>,
+    /// Channels it was asked through, in first-seen order.
+    pub channels: Vec<String>,
+    /// Best score any of the searches reached.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub best_score: Option<f32>,
+}
+
+/// Append-only JSONL log of unanswered searches.
+pub struct QuestionLog {
+    path: PathBuf,
+    config: QuestionConfig,
+}
+
+impl QuestionLog {
+    pub fn new(config: &QuestionConfig) -> Self {
+        let path = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("shabka")
+            .join("questions.jsonl");
+        Self::with_path(path, config)
+    }
+
+    /// A log stored at `path` instead of the config directory.
+    pub fn with_path(path: PathBuf, config: &QuestionConfig) -> Self {
+        Self {
+            path,
+            config: config.clone(),
+        }
+    }
+
+    /// Whether a search returning `question`'s results went unanswered.
+    pub fn is_unanswered(&self, question: &Question) -> bool {
+        match question.top_score {
+            None => true,
+            Some(score) => score < self.config.min_score,
+        }
+    }
+
+    /// Log `question` if its search went unanswered. Empty queries (filter-only
+    /// searches) are never logged. Returns whether it was logged.
+    pub fn record(&self, question: &Question) -> bool {
+        if !self.config.enabled || question.query.trim().is_empty() {
+            return false;
+        }
+        if !self.is_unanswered(question) {
+            return false;
+        }
+        if let Some(parent) = self.path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let line = match serde_json::to_string(question) {
+            Ok(l) => l,
+            Err(e) => {
+                tracing::debug!("questions: failed to serialize question: {e}");
+                return false;
+            }
+        };
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path);
+        match file {
+            Ok(mut f) => writeln!(f, "{}", line).is_ok(),
+            Err(e) => {
+                tracing::debug!("questions: failed to open log: {e}");
+                false
+            }
+        }
+    }
+
+    /// Unanswered questions grouped by query (case and whitespace folded),
+    /// most asked first. With `project`, only questions asked in that project
+    /// or outside any project are included.
+    pub fn open_questions(&self, project: Option<&str>) -> Vec<OpenQuestion> {
+        let mut groups: HashMap<String, OpenQuestion> = HashMap::new();
+        for q in self.read_all() {
+            if let (Some(project), Some(own)) = (project, q.project_id.as_deref()) {
+                if own != project {
+                    continue;
+                }
+            }
+            let key = normalize(&q.query);
+            let group = groups.entry(key).or_insert_with(|| OpenQuestion {
+                query: q.query.clone(),
+                times_asked: 0,
+                last_asked: q.asked_at,
+                channels: Vec::new(),
+                best_score: None,
+            });
+            group.times_asked += 1;
+            group.last_asked = group.last_asked.max(q.asked_at);
+            if !group.channels.contains(&q.channel) {
+                group.channels.push(q.channel);
+            }
+            if let Some(score) = q.top_score {
+                group.best_score = Some(group.best_score.map_or(score, |b| b.max(score)));
+            }
+        }
+        let mut open: Vec<OpenQuestion> = groups.into_values().collect();
+        open.sort_by(|a, b| {
+            b.times_asked
+                .cmp(&a.times_asked)
+                .then_with(|| b.last_asked.cmp(&a.last_asked))
+        });
+        open
+    }
+
+    fn read_all(&self) -> Vec<Question> {
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(c) => c,
+            Err(_) => return Vec::new(),
+        };
+        contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+}
+
+fn normalize(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_log() -> QuestionLog {
+        let path =
+            std::env::temp_dir().join(format!("shabka-questions-{}.jsonl", uuid::Uuid::now_v7()));
+        QuestionLog::with_path(path, &QuestionConfig::default())
+    }
+
+    #[test]
+    fn test_records_only_unanswered() {
+        let log = test_log();
+        assert!(log.record(&Question::new("pool timeout", "cli", "alice")));
+        assert!(log.record(&Question::new("pool timeout", "mcp", "bot").with_scores([0.2, 0.1])));
+        assert!(!log.record(&Question::new("pool timeout", "web", "alice").with_scores([0.9])));
+        assert!(!log.record(&Question::new("  ", "cli", "alice")));
+
+        let open = log.open_questions(None);
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].times_asked, 2);
+        assert_eq!(open[0].channels, vec!["cli", "mcp"]);
+        assert_eq!(open[0].best_score, Some(0.2));
+        let _ = std::fs::remove_file(&log.path);
+    }
+
+    #[test]
+    fn test_open_questions_group_and_filter() {
+        let log = test_log();
+        log.record(&Question::new("Deploy steps", "cli", "a").with_project(Some("app".into())));
+        log.record(&Question::new("deploy   STEPS", "mcp", "b"));
+        log.record(&Question::new("auth flow", "web", "a").with_project(Some("other".into())));
+
+        let open = log.open_questions(Some("app"));
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].query, "Deploy steps");
+        assert_eq!(open[0].times_asked, 2);
+        assert_eq!(log.open_questions(None).len(), 2);
+        let _ = std::fs::remove_file(&log.path);
+    }
+
+    #[test]
+    fn test_disabled_log_records_nothing() {
+        let path =
+            std::env::temp_dir().join(format!("shabka-questions-{}.jsonl", uuid::Uuid::now_v7()));
+        let config = QuestionConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        let log = QuestionLog::with_path(path, &config);
+        assert!(!log.record(&Question::new("anything", "cli", "a")));
+        assert!(log.open_questions(None).is_empty());
+    }
+}
@@ -0,0 +1,274 @@
+//! Daily quotas for auto-captured memories.
+//!
+//! `capture.max_memories_per_day` and `capture.project_quotas` cap how many
+//! memories the hooks may save per UTC day. Captures over the limit are parked
+//! in a JSONL backlog instead of being saved, and drained once the quota frees up.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+
+use crate::config::CaptureConfig;
+use crate::error::Result;
+use crate::model::{Memory, MemorySource, TimelineQuery};
+use crate::storage::StorageBackend;
+
+/// Which quota a capture ran into.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuotaLimit {
+    Daily { limit: usize },
+    Project { project: String, limit: usize },
+}
+
+impl std::fmt::Display for QuotaLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Daily { limit } => write!(f, "daily quota of {limit} reached"),
+            Self::Project { project, limit } => {
+                write!(f, "quota of {limit} for project '{project}' reached")
+            }
+        }
+    }
+}
+
+/// Auto-captured memories saved so far today, overall and per project.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureUsage {
+    pub total: usize,
+    pub by_project: HashMap<String, usize>,
+}
+
+impl CaptureUsage {
+    /// Count auto-captured memories created at or after `since`.
+    pub fn from_memories(memories: &[Memory], since: DateTime<Utc>) -> Self {
+        let mut usage = Self::default();
+        for memory in memories {
+            if memory.created_at >= since
+                && matches!(memory.source, MemorySource::AutoCapture { .. })
+            {
+                usage.record(memory.project_id.as_deref());
+            }
+        }
+        usage
+    }
+
+    /// Count one more capture against the quotas.
+    pub fn record(&mut self, project: Option<&str>) {
+        self.total += 1;
+        if let Some(project) = project {
+            *self.by_project.entry(project.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    /// The first quota that another capture for `project` would exceed.
+    pub fn exceeded(&self, capture: &CaptureConfig, project: Option<&str>) -> Option<QuotaLimit> {
+        if let Some(limit) = capture.max_memories_per_day {
+            if self.total >= limit {
+                return Some(QuotaLimit::Daily { limit });
+            }
+        }
+        let project = project?;
+        let limit = *capture.project_quotas.get(project)?;
+        let used = self.by_project.get(project).copied().unwrap_or(0);
+        (used >= limit).then(|| QuotaLimit::Project {
+            project: project.to_string(),
+            limit,
+        })
+    }
+}
+
+impl CaptureConfig {
+    /// Whether any capture quota is configured.
+    pub fn has_quota(&self) -> bool {
+        self.max_memories_per_day.is_some() || !self.project_quotas.is_empty()
+    }
+}
+
+/// Midnight UTC of the day containing `now`.
+pub fn start_of_day(now: DateTime<Utc>) -> DateTime<Utc> {
+    now.date_naive()
+        .and_hms_opt(0, 0, 0)
+        .map(|midnight| midnight.and_utc())
+        .unwrap_or(now)
+}
+
+/// Load today's capture usage from storage.
+pub async fn usage_today(
+    storage: &impl StorageBackend,
+    now: DateTime<Utc>,
+) -> Result<CaptureUsage> {
+    let since = start_of_day(now);
+    let entries = storage
+        .timeline(&TimelineQuery {
+            start: Some(since),
+            limit: 10000,
+            ..Default::default()
+        })
+        .await?;
+    let ids: Vec<_> = entries.iter().map(|e| e.id).collect();
+    let memories = storage.get_memories(&ids).await?;
+    Ok(CaptureUsage::from_memories(&memories, since))
+}
+
+/// Append-only JSONL file of captures held back by a quota.
+pub struct CaptureBacklog {
+    path: PathBuf,
+}
+
+impl Default for CaptureBacklog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CaptureBacklog {
+    /// The backlog at `~/.config/shabka/capture_backlog.jsonl`.
+    pub fn new() -> Self {
+        let path = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("/tmp"))
+            .join("shabka")
+            .join("capture_backlog.jsonl");
+        Self { path }
+    }
+
+    pub fn with_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Park a memory in the backlog.
+    pub fn push(&self, memory: &Memory) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let line = serde_json::to_string(memory)?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{line}")
+    }
+
+    /// All parked memories, oldest first. Malformed lines are skipped.
+    pub fn read_all(&self) -> Vec<Memory> {
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(c) => c,
+            Err(_) => return Vec::new(),
+        };
+        contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+
+    /// Number of parked memories.
+    pub fn len(&self) -> usize {
+        self.read_all().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Replace the backlog with `memories`, removing the file when empty.
+    pub fn replace(&self, memories: &[Memory]) -> std::io::Result<()> {
+        if memories.is_empty() {
+            return match std::fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            };
+        }
+        let mut out = String::new();
+        for memory in memories {
+            out.push_str(&serde_json::to_string(memory)?);
+            out.push('\n');
+        }
+        std::fs::write(&self.path, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::MemoryKind;
+
+    fn captured(title: &str, project: &str) -> Memory {
+        Memory::new(
+            title.into(),
+            "content".into(),
+            MemoryKind::Observation,
+            "user".into(),
+        )
+        .with_source(MemorySource::AutoCapture {
+            hook: "PostToolUse".into(),
+        })
+        .with_project(project.into())
+    }
+
+    #[test]
+    fn test_usage_counts_only_todays_auto_captures() {
+        let now = Utc::now();
+        let mut old = captured("yesterday", "shabka");
+        old.created_at = start_of_day(now) - chrono::Duration::hours(1);
+        let manual = Memory::new(
+            "manual".into(),
+            "content".into(),
+            MemoryKind::Fact,
+            "user".into(),
+        );
+        let memories = vec![captured("a", "shabka"), captured("b", "other"), old, manual];
+
+        let usage = CaptureUsage::from_memories(&memories, start_of_day(now));
+        assert_eq!(usage.total, 2);
+        assert_eq!(usage.by_project.get("shabka"), Some(&1));
+    }
+
+    #[test]
+    fn test_exceeded_daily_and_project_quotas() {
+        let mut capture = CaptureConfig::default();
+        let mut usage = CaptureUsage::default();
+        assert!(!capture.has_quota());
+        assert_eq!(usage.exceeded(&capture, Some("shabka")), None);
+
+        capture.project_quotas.insert("shabka".into(), 1);
+        usage.record(Some("shabka"));
+        assert_eq!(
+            usage.exceeded(&capture, Some("shabka")),
+            Some(QuotaLimit::Project {
+                project: "shabka".into(),
+                limit: 1
+            })
+        );
+        assert_eq!(usage.exceeded(&capture, Some("other")), None);
+
+        capture.max_memories_per_day = Some(1);
+        assert_eq!(
+            usage.exceeded(&capture, Some("other")),
+            Some(QuotaLimit::Daily { limit: 1 })
+        );
+    }
+
+    #[test]
+    fn test_backlog_push_and_replace() {
+        let path =
+            std::env::temp_dir().join(format!("shabka-backlog-{}.jsonl", uuid::Uuid::now_v7()));
+        let backlog = CaptureBacklog::with_path(path.clone());
+        assert!(backlog.is_empty());
+
+        backlog.push(&captured("first", "shabka")).unwrap();
+        backlog.push(&captured("second", "shabka")).unwrap();
+        assert_eq!(backlog.len(), 2);
+
+        let remaining = backlog.read_all().split_off(1);
+        backlog.replace(&remaining).unwrap();
+        assert_eq!(backlog.read_all()[0].title, "second");
+
+        backlog.replace(&[]).unwrap();
+        assert!(!path.exists());
+    }
+}
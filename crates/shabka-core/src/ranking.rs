@@ -1,9 +1,11 @@
 use crate::model::{Memory, MemoryIndex};
 use crate::trust::trust_score;
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
 /// Weights for the fusion ranking formula.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RankingWeights {
     pub similarity: f32,
     pub keyword: f32,
@@ -12,6 +14,7 @@ pub struct RankingWeights {
     pub access_freq: f32,
     pub graph_proximity: f32,
     pub trust: f32,
+    pub centrality: f32,
 }
 
 impl Default for RankingWeights {
@@ -21,9 +24,10 @@ impl Default for RankingWeights {
             keyword: 0.15,
             recency: 0.15,
             importance: 0.15,
-            access_freq: 0.10,
+            access_freq: 0.05,
             graph_proximity: 0.05,
             trust: 0.15,
+            centrality: 0.05,
         }
     }
 }
@@ -35,10 +39,12 @@ pub struct RankCandidate {
     pub keyword_score: f32,
     pub relation_count: usize,
     pub contradiction_count: usize,
+    /// Stored PageRank (see [`crate::centrality`]); 0.0 if never computed.
+    pub centrality: f32,
 }
 
 /// Breakdown of how each component contributed to the final score.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ScoreBreakdown {
     pub similarity: f32,
     pub keyword: f32,
@@ -47,6 +53,7 @@ pub struct ScoreBreakdown {
     pub access_freq: f32,
     pub graph_proximity: f32,
     pub trust: f32,
+    pub centrality: f32,
 }
 
 /// Output of the ranking function.
@@ -118,24 +125,65 @@ pub fn damerau_levenshtein(a: &str, b: &str) -> usize {
     d[n][m]
 }
 
+/// Sørensen–Dice coefficient over character bigrams: 1.0 for identical
+/// strings, 0.0 when they share no bigram. Tolerates several scattered typos
+/// in a long word better than an edit distance does.
+pub fn dice_coefficient(a: &str, b: &str) -> f32 {
+    fn bigrams(s: &str) -> Vec<(char, char)> {
+        let chars: Vec<char> = s.chars().collect();
+        chars.windows(2).map(|w| (w[0], w[1])).collect()
+    }
+    let a = bigrams(a);
+    let mut b = bigrams(b);
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let total = a.len() + b.len();
+    let mut shared = 0;
+    for bigram in &a {
+        if let Some(pos) = b.iter().position(|other| other == bigram) {
+            b.swap_remove(pos);
+            shared += 1;
+        }
+    }
+    2.0 * shared as f32 / total as f32
+}
+
+/// Edits a query term may be from a title word and still match it in
+/// typo-tolerant title search: none for short terms, where a single edit
+/// matches too much, one up to six characters, two beyond.
+pub fn typo_allowance(term: &str) -> usize {
+    match term.chars().count() {
+        0..=3 => 0,
+        4..=6 => 1,
+        _ => 2,
+    }
+}
+
+/// Dice coefficient a title word needs before it earns fuzzy credit.
+const MIN_TITLE_DICE: f32 = 0.6;
+
 /// Keyword match score: fraction of query terms found in the memory's title + content.
 /// Case-insensitive. Exact substring match scores 1.0 per term.
 /// If no exact match, fuzzy matching via Damerau-Levenshtein gives partial credit:
-/// distance 1 = 0.6, distance 2 = 0.3.
+/// distance 1 = 0.6, distance 2 = 0.3. Title words also earn up to 0.6 from
+/// their bigram Dice coefficient, so a title word with several typos still counts.
 pub fn keyword_score(query: &str, memory: &Memory) -> f32 {
     let terms: Vec<&str> = query.split_whitespace().collect();
     if terms.is_empty() {
         return 0.0;
     }
 
+    let title = memory.title.to_lowercase();
     let haystack = format!(
         "{} {} {}",
-        memory.title.to_lowercase(),
+        title,
         memory.content.to_lowercase(),
         memory.tags.join(" ").to_lowercase(),
     );
 
     let haystack_words: Vec<&str> = haystack.split_whitespace().collect();
+    let title_words: Vec<&str> = title.split_whitespace().collect();
 
     let mut total_score = 0.0_f32;
     for term in &terms {
@@ -162,6 +210,12 @@ pub fn keyword_score(query: &str, memory: &Memory) -> f32 {
                     best_credit = credit;
                 }
             }
+            for word in &title_words {
+                let dice = dice_coefficient(&term_lower, word);
+                if dice >= MIN_TITLE_DICE {
+                    best_credit = best_credit.max(0.6 * dice);
+                }
+            }
             total_score += best_credit;
         }
     }
@@ -190,6 +244,7 @@ pub fn rank(candidates: Vec<RankCandidate>, weights: &RankingWeights) -> Vec<Ran
             let acc = access_score(c.memory.accessed_at, c.memory.created_at, now);
             let graph = graph_score(c.relation_count);
             let tru = trust_score(&c.memory, c.contradiction_count);
+            let cen = c.centrality.clamp(0.0, 1.0);
 
             let score = weights.similarity * sim
                 + weights.keyword * kw
@@ -197,7 +252,8 @@ pub fn rank(candidates: Vec<RankCandidate>, weights: &RankingWeights) -> Vec<Ran
                 + weights.importance * imp
                 + weights.access_freq * acc
                 + weights.graph_proximity * graph
-                + weights.trust * tru;
+                + weights.trust * tru
+                + weights.centrality * cen;
 
             RankedResult {
                 memory: c.memory,
@@ -210,6 +266,7 @@ pub fn rank(candidates: Vec<RankCandidate>, weights: &RankingWeights) -> Vec<Ran
                     access_freq: acc,
                     graph_proximity: graph,
                     trust: tru,
+                    centrality: cen,
                 },
             }
         })
@@ -223,6 +280,27 @@ pub fn rank(candidates: Vec<RankCandidate>, weights: &RankingWeights) -> Vec<Ran
     results
 }
 
+/// Share of the final score given to the symbol match in [`boost_symbol`].
+pub const SYMBOL_WEIGHT: f32 = 0.4;
+
+/// Blend a [`symbol_score`](crate::code::symbol_score) into ranked results
+/// and re-sort, so memories referencing `symbol` rise to the top. A no-op
+/// without a symbol.
+pub fn boost_symbol(results: &mut [RankedResult], symbol: Option<&str>) {
+    let Some(symbol) = symbol else {
+        return;
+    };
+    for r in results.iter_mut() {
+        let s = crate::code::symbol_score(&r.memory, symbol);
+        r.score = r.score * (1.0 - SYMBOL_WEIGHT) + SYMBOL_WEIGHT * s;
+    }
+    results.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
+
 /// Greedily pack ranked results into a token budget.
 /// Results must already be sorted by score (descending).
 /// Stops as soon as the next result would exceed the remaining budget.
@@ -263,13 +341,23 @@ mod tests {
             importance,
             status: crate::model::MemoryStatus::Active,
             privacy: crate::model::MemoryPrivacy::Private,
+            label: None,
+            device_id: None,
+            owner: None,
+            confidence: None,
             verification: crate::model::VerificationStatus::default(),
+            verification_expires_at: None,
+            dispute: None,
             project_id: None,
             session_id: None,
             created_by: "test".to_string(),
             created_at: created,
             updated_at: created,
             accessed_at: created,
+            attachments: Vec::new(),
+            code: None,
+            assertions: Vec::new(),
+            endorsements: Vec::new(),
         }
     }
 
@@ -324,6 +412,7 @@ mod tests {
                 keyword_score: 0.8,
                 relation_count: 3,
                 contradiction_count: 0,
+                centrality: 0.0,
             },
             RankCandidate {
                 memory: test_memory("old-low", 0.3, 30),
@@ -331,6 +420,7 @@ mod tests {
                 keyword_score: 0.2,
                 relation_count: 0,
                 contradiction_count: 0,
+                centrality: 0.0,
             },
         ];
 
@@ -385,6 +475,32 @@ mod tests {
         assert!(score < 1.0, "fuzzy typo should score < 1.0, got {score}");
     }
 
+    #[test]
+    fn test_keyword_score_title_dice() {
+        let mem = test_memory("Authentication flow with JWT tokens", 0.5, 1);
+        // Three edits away, but most bigrams survive.
+        let score = keyword_score("authenticashun", &mem);
+        assert!(
+            score > 0.3,
+            "title word with several typos should score, got {score}"
+        );
+        assert!(score <= 0.6);
+    }
+
+    #[test]
+    fn test_dice_coefficient() {
+        assert_eq!(dice_coefficient("night", "night"), 1.0);
+        assert_eq!(dice_coefficient("night", "nacht"), 0.25);
+        assert_eq!(dice_coefficient("a", "ab"), 0.0);
+    }
+
+    #[test]
+    fn test_typo_allowance() {
+        assert_eq!(typo_allowance("jwt"), 0);
+        assert_eq!(typo_allowance("token"), 1);
+        assert_eq!(typo_allowance("authetication"), 2);
+    }
+
     #[test]
     fn test_keyword_score_fuzzy_no_match() {
         let mem = test_memory("Authentication flow with JWT tokens", 0.5, 1);
@@ -405,7 +521,8 @@ mod tests {
             + w.importance
             + w.access_freq
             + w.graph_proximity
-            + w.trust;
+            + w.trust
+            + w.centrality;
         assert!((sum - 1.0).abs() < 0.001);
     }
 
@@ -420,6 +537,7 @@ mod tests {
                 score: 0.9,
                 tags: vec![],
                 verification: VerificationStatus::default(),
+                contradicts: vec![],
             },
             MemoryIndex {
                 id: uuid::Uuid::now_v7(),
@@ -429,6 +547,7 @@ mod tests {
                 score: 0.8,
                 tags: vec![],
                 verification: VerificationStatus::default(),
+                contradicts: vec![],
             },
         ];
         let packed = budget_truncate(results, 10000);
@@ -446,6 +565,7 @@ mod tests {
                 score: 0.9,
                 tags: vec![],
                 verification: VerificationStatus::default(),
+                contradicts: vec![],
             },
             MemoryIndex {
                 id: uuid::Uuid::now_v7(),
@@ -455,6 +575,7 @@ mod tests {
                 score: 0.8,
                 tags: vec![],
                 verification: VerificationStatus::default(),
+                contradicts: vec![],
             },
         ];
         // Each index: ~25 title tokens + 15 overhead = ~40 tokens
@@ -474,6 +595,7 @@ mod tests {
             score: 0.9,
             tags: vec![],
             verification: VerificationStatus::default(),
+            contradicts: vec![],
         }];
         let packed = budget_truncate(results, 0);
         assert!(packed.is_empty());
@@ -502,6 +624,7 @@ mod tests {
                 keyword_score: 0.8,
                 relation_count: 2,
                 contradiction_count: 2,
+                centrality: 0.0,
             },
             RankCandidate {
                 memory: verified_mem,
@@ -509,6 +632,7 @@ mod tests {
                 keyword_score: 0.8,
                 relation_count: 2,
                 contradiction_count: 0,
+                centrality: 0.0,
             },
         ];
 
@@ -516,4 +640,37 @@ mod tests {
         assert_eq!(results[0].memory.title, "verified");
         assert!(results[0].breakdown.trust > results[1].breakdown.trust);
     }
+
+    #[test]
+    fn test_boost_symbol() {
+        let weights = RankingWeights::default();
+        let mut mentions = test_memory("Pool exhaustion", 0.5, 0);
+        mentions.content = "load_config retries were too low".to_string();
+        let candidates = vec![
+            RankCandidate {
+                memory: test_memory("unrelated but similar", 0.9, 0),
+                vector_score: 0.9,
+                keyword_score: 0.8,
+                relation_count: 0,
+                contradiction_count: 0,
+                centrality: 0.0,
+            },
+            RankCandidate {
+                memory: mentions,
+                vector_score: 0.3,
+                keyword_score: 0.0,
+                relation_count: 0,
+                contradiction_count: 0,
+                centrality: 0.0,
+            },
+        ];
+
+        let mut results = rank(candidates, &weights);
+        assert_eq!(results[0].memory.title, "unrelated but similar");
+        boost_symbol(&mut results, None);
+        assert_eq!(results[0].memory.title, "unrelated but similar");
+        boost_symbol(&mut results, Some("loadConfig"));
+        assert_eq!(results[0].memory.title, "Pool exhaustion");
+        assert!(results.iter().all(|r| r.score <= 1.0));
+    }
 }
@@ -0,0 +1,440 @@
+//! The search pipeline shared by the CLI, TUI, MCP server, web API and
+//! [`client`](crate::client).
+//!
+//! [`search`] runs a [`SearchRequest`] end to end: fetch candidates for the
+//! parsed query, drop excluded, invisible and filtered-out memories, count
+//! relations and contradictions, rank, boost `symbol:` matches, blend in a
+//! `[wasm_plugins]` ranker, and find results that contradict each other.
+//! Callers only decide what to do with the ranked list — group it, pack it,
+//! or cut it to a limit and token budget with [`SearchResults::index`].
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::context_pack::PackFilter;
+use crate::embedding::EmbeddingService;
+use crate::error::Result;
+use crate::model::{MemoryIndex, RelationType};
+use crate::query::{fetch_candidates, SearchQuery};
+use crate::ranking::{self, RankCandidate, RankedResult, RankingWeights};
+use crate::sharing;
+use crate::storage::StorageBackend;
+use crate::wasm_plugins::WasmRanker;
+
+/// Results returned when a request doesn't set a limit.
+pub const DEFAULT_SEARCH_LIMIT: usize = 10;
+
+/// Candidates fetched per requested result, leaving room for filtering.
+const OVERFETCH: usize = 3;
+
+/// Score multiplier for the lower-trust side of a contradiction, with
+/// [`SearchRequest::demote_contradicted`].
+pub const CONTRADICTED_DEMOTION: f32 = 0.5;
+
+/// A search to run: the parsed query, how many results, who is looking and
+/// how to rank. Build one with [`SearchRequest::parse`] or
+/// [`SearchRequest::new`] and the chained setters, then pass it to
+/// [`search`].
+#[derive(Debug, Clone)]
+pub struct SearchRequest {
+    pub query: SearchQuery,
+    pub limit: usize,
+    /// Candidates to fetch before ranking; `limit * 3` when unset.
+    pub fetch_limit: Option<usize>,
+    /// Only memories this user may see. Unset means no privacy filtering.
+    pub viewer: Option<String>,
+    /// Memories to leave out by exact ID, e.g. ones already shown.
+    pub exclude: HashSet<Uuid>,
+    /// Verification and screening filter, as for context packs.
+    pub filter: PackFilter,
+    /// Token budget for [`SearchResults::index`], on top of `limit`.
+    pub token_budget: Option<usize>,
+    pub weights: RankingWeights,
+    /// When two results contradict each other, demote the one with lower
+    /// trust (`[retrieval] demote_contradicted`).
+    pub demote_contradicted: bool,
+    /// `[wasm_plugins]` ranker blended into the scores after ranking.
+    pub ranker: Option<Arc<WasmRanker>>,
+}
+
+impl SearchRequest {
+    pub fn new(query: SearchQuery) -> Self {
+        Self {
+            query,
+            limit: DEFAULT_SEARCH_LIMIT,
+            fetch_limit: None,
+            viewer: None,
+            exclude: HashSet::new(),
+            filter: PackFilter::default(),
+            token_budget: None,
+            weights: RankingWeights::default(),
+            demote_contradicted: false,
+            ranker: None,
+        }
+    }
+
+    /// Parse `input` as a [`SearchQuery`].
+    pub fn parse(input: &str) -> Result<Self> {
+        Ok(Self::new(SearchQuery::parse(input)?))
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn fetch_limit(mut self, fetch_limit: usize) -> Self {
+        self.fetch_limit = Some(fetch_limit);
+        self
+    }
+
+    pub fn visible_to(mut self, user_id: impl Into<String>) -> Self {
+        self.viewer = Some(user_id.into());
+        self
+    }
+
+    pub fn excluding(mut self, ids: impl IntoIterator<Item = Uuid>) -> Self {
+        self.exclude.extend(ids);
+        self
+    }
+
+    pub fn filter(mut self, filter: PackFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn token_budget(mut self, token_budget: Option<usize>) -> Self {
+        self.token_budget = token_budget;
+        self
+    }
+
+    pub fn weights(mut self, weights: RankingWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    pub fn demote_contradicted(mut self, demote: bool) -> Self {
+        self.demote_contradicted = demote;
+        self
+    }
+
+    pub fn ranker(mut self, ranker: Option<Arc<WasmRanker>>) -> Self {
+        self.ranker = ranker;
+        self
+    }
+
+    /// Change the query, e.g. to add filters from separate parameters:
+    /// `.map_query(|q| q.with_kind(kind).with_tags(tags))`.
+    pub fn map_query(mut self, f: impl FnOnce(SearchQuery) -> SearchQuery) -> Self {
+        self.query = f(self.query);
+        self
+    }
+}
+
+/// Ranked candidates from [`search`], best first. `ranked` holds every
+/// candidate, not just the first `limit`, for callers that group or
+/// re-order before cutting; [`top`](Self::top) and [`index`](Self::index)
+/// cut.
+pub struct SearchResults {
+    pub ranked: Vec<RankedResult>,
+    pub relation_counts: HashMap<Uuid, usize>,
+    pub contradiction_counts: HashMap<Uuid, usize>,
+    /// For each candidate linked to others by a `Contradicts` relation,
+    /// those others, in either direction.
+    pub conflicts: HashMap<Uuid, Vec<Uuid>>,
+    limit: usize,
+    token_budget: Option<usize>,
+}
+
+impl SearchResults {
+    /// The request's `limit` best results.
+    pub fn top(mut self) -> Vec<RankedResult> {
+        self.ranked.truncate(self.limit);
+        self.ranked
+    }
+
+    /// The request's `limit` best results as index entries, cut further to
+    /// its token budget, with the contradictions among them.
+    pub fn index(mut self) -> Vec<MemoryIndex> {
+        let token_budget = self.token_budget;
+        let conflicts = std::mem::take(&mut self.conflicts);
+        let index = self
+            .top()
+            .iter()
+            .map(|r| MemoryIndex::from((&r.memory, r.score)))
+            .collect();
+        let mut index = match token_budget {
+            Some(budget) => ranking::budget_truncate(index, budget),
+            None => index,
+        };
+        mark_conflicts(&mut index, &conflicts);
+        index
+    }
+}
+
+/// Set each entry's `contradicts` to the other entries it conflicts with,
+/// in rank order, so a result is only flagged against what the caller
+/// actually shows.
+pub fn mark_conflicts(index: &mut [MemoryIndex], conflicts: &HashMap<Uuid, Vec<Uuid>>) {
+    let shown: Vec<Uuid> = index.iter().map(|e| e.id).collect();
+    for entry in index.iter_mut() {
+        entry.contradicts = match conflicts.get(&entry.id) {
+            Some(others) => shown
+                .iter()
+                .copied()
+                .filter(|id| others.contains(id))
+                .collect(),
+            None => Vec::new(),
+        };
+    }
+}
+
+/// Run `request` against `storage`: fetch, filter, count and rank.
+pub async fn search(
+    storage: &impl StorageBackend,
+    embedder: &EmbeddingService,
+    request: &SearchRequest,
+) -> Result<SearchResults> {
+    let fetch = request.fetch_limit.unwrap_or(request.limit * OVERFETCH) + request.exclude.len();
+    let mut candidates = fetch_candidates(storage, embedder, &request.query, fetch).await?;
+    candidates.retain(|(m, _)| !request.exclude.contains(&m.id) && request.filter.allows(m));
+    if let Some(viewer) = &request.viewer {
+        sharing::filter_search_results(&mut candidates, viewer);
+    }
+
+    let ids: Vec<Uuid> = candidates.iter().map(|(m, _)| m.id).collect();
+    let relation_counts: HashMap<Uuid, usize> = storage
+        .count_relations(&ids)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+    let contradiction_counts: HashMap<Uuid, usize> = storage
+        .count_contradictions(&ids)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+    let centrality: HashMap<Uuid, f32> = storage
+        .centrality(&ids)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|c| (c.memory_id, c.pagerank))
+        .collect();
+
+    let candidates = candidates
+        .into_iter()
+        .map(|(memory, vector_score)| RankCandidate {
+            relation_count: relation_counts.get(&memory.id).copied().unwrap_or(0),
+            keyword_score: ranking::keyword_score(&request.query.text, &memory),
+            contradiction_count: contradiction_counts.get(&memory.id).copied().unwrap_or(0),
+            centrality: centrality.get(&memory.id).copied().unwrap_or(0.0),
+            memory,
+            vector_score,
+        })
+        .collect();
+    let mut ranked = ranking::rank(candidates, &request.weights);
+    ranking::boost_symbol(&mut ranked, request.query.symbol.as_deref());
+    if let Some(ranker) = &request.ranker {
+        ranker.rerank(&mut ranked, &request.query.text);
+    }
+
+    let conflicts = find_conflicts(storage, &ids, &contradiction_counts).await;
+    if request.demote_contradicted {
+        demote_contradicted(&mut ranked, &conflicts);
+    }
+
+    Ok(SearchResults {
+        ranked,
+        relation_counts,
+        contradiction_counts,
+        conflicts,
+        limit: request.limit,
+        token_budget: request.token_budget,
+    })
+}
+
+/// Pairs among `ids` linked by a `Contradicts` relation. Only memories
+/// with an outgoing contradiction are looked up, which is usually none.
+async fn find_conflicts(
+    storage: &impl StorageBackend,
+    ids: &[Uuid],
+    contradiction_counts: &HashMap<Uuid, usize>,
+) -> HashMap<Uuid, Vec<Uuid>> {
+    let candidates: HashSet<Uuid> = ids.iter().copied().collect();
+    let mut conflicts: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    for (&id, &count) in contradiction_counts {
+        if count == 0 {
+            continue;
+        }
+        let relations = storage.get_relations(id).await.unwrap_or_default();
+        for r in relations {
+            let linked = r.relation_type == RelationType::Contradicts
+                && r.source_id == id
+                && r.target_id != id
+                && candidates.contains(&r.target_id);
+            if !linked {
+                continue;
+            }
+            for (a, b) in [(r.source_id, r.target_id), (r.target_id, r.source_id)] {
+                let others = conflicts.entry(a).or_default();
+                if !others.contains(&b) {
+                    others.push(b);
+                }
+            }
+        }
+    }
+    conflicts
+}
+
+/// Scale down the lower-trust side of every contradicting pair by
+/// [`CONTRADICTED_DEMOTION`] and re-sort. Pairs with equal trust are left
+/// alone.
+fn demote_contradicted(ranked: &mut [RankedResult], conflicts: &HashMap<Uuid, Vec<Uuid>>) {
+    let trust: HashMap<Uuid, f32> = ranked
+        .iter()
+        .map(|r| (r.memory.id, r.breakdown.trust))
+        .collect();
+    for r in ranked.iter_mut() {
+        let Some(others) = conflicts.get(&r.memory.id) else {
+            continue;
+        };
+        let own = r.breakdown.trust;
+        if others
+            .iter()
+            .any(|o| trust.get(o).is_some_and(|&t| t > own))
+        {
+            r.score *= CONTRADICTED_DEMOTION;
+        }
+    }
+    ranked.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EmbeddingConfig;
+    use crate::model::{Memory, MemoryKind, MemoryPrivacy, MemoryRelation, VerificationStatus};
+    use crate::screen::SUSPICIOUS_TAG;
+    use crate::storage::SqliteStorage;
+
+    fn memory(kind: MemoryKind) -> Memory {
+        Memory::new(
+            "Pool exhaustion".to_string(),
+            "Connection pool ran dry under load".to_string(),
+            kind,
+            "alice".to_string(),
+        )
+        .with_privacy(MemoryPrivacy::Team)
+    }
+
+    async fn seed(storage: &SqliteStorage, embedder: &EmbeddingService, memories: &[&Memory]) {
+        for m in memories {
+            let emb = embedder.embed(&m.embedding_text()).await.unwrap();
+            storage.save_memory(m, Some(&emb)).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_excludes_hides_and_filters() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let embedder = EmbeddingService::from_config(&EmbeddingConfig::default()).unwrap();
+        let shown = memory(MemoryKind::Error);
+        let private = memory(MemoryKind::Error).with_privacy(MemoryPrivacy::Private);
+        let suspicious = memory(MemoryKind::Error).with_tags(vec![SUSPICIOUS_TAG.to_string()]);
+        let visible = memory(MemoryKind::Error);
+        let fact = memory(MemoryKind::Fact);
+        seed(
+            &storage,
+            &embedder,
+            &[&shown, &private, &suspicious, &visible, &fact],
+        )
+        .await;
+
+        let request = SearchRequest::parse("kind:error pool")
+            .unwrap()
+            .limit(1)
+            .visible_to("bob")
+            .excluding([shown.id])
+            .filter(PackFilter {
+                exclude_suspicious: true,
+                ..Default::default()
+            });
+        let results = search(&storage, &embedder, &request).await.unwrap();
+        let ids: Vec<Uuid> = results.ranked.iter().map(|r| r.memory.id).collect();
+        assert_eq!(ids, vec![visible.id]);
+        assert!(results.contradiction_counts.is_empty());
+
+        // Without a viewer or filter nothing is hidden; `top` still honours
+        // the limit.
+        let request = SearchRequest::parse("kind:error pool").unwrap().limit(2);
+        let results = search(&storage, &embedder, &request).await.unwrap();
+        assert_eq!(results.ranked.len(), 4);
+        assert_eq!(results.top().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_search_index_respects_token_budget() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let embedder = EmbeddingService::from_config(&EmbeddingConfig::default()).unwrap();
+        let memories: Vec<Memory> = (0..5).map(|_| memory(MemoryKind::Fact)).collect();
+        seed(&storage, &embedder, &memories.iter().collect::<Vec<_>>()).await;
+
+        let request = SearchRequest::parse("pool").unwrap().limit(4);
+        let unbudgeted = search(&storage, &embedder, &request).await.unwrap().index();
+        assert_eq!(unbudgeted.len(), 4);
+
+        let one = crate::tokens::estimate_index_tokens(&unbudgeted[0]);
+        let request = request.token_budget(Some(one * 2));
+        let budgeted = search(&storage, &embedder, &request).await.unwrap().index();
+        assert_eq!(budgeted.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_search_flags_and_demotes_contradictions() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let embedder = EmbeddingService::from_config(&EmbeddingConfig::default()).unwrap();
+        let trusted = memory(MemoryKind::Fact).with_verification(VerificationStatus::Verified);
+        let disputed = memory(MemoryKind::Fact).with_verification(VerificationStatus::Disputed);
+        seed(&storage, &embedder, &[&trusted, &disputed]).await;
+        storage
+            .add_relation(&MemoryRelation {
+                source_id: disputed.id,
+                target_id: trusted.id,
+                relation_type: RelationType::Contradicts,
+                strength: 1.0,
+            })
+            .await
+            .unwrap();
+
+        let request = SearchRequest::parse("pool").unwrap().limit(2);
+        let results = search(&storage, &embedder, &request).await.unwrap();
+        assert_eq!(results.conflicts[&trusted.id], vec![disputed.id]);
+        assert_eq!(results.conflicts[&disputed.id], vec![trusted.id]);
+        let score_of = |ranked: &[RankedResult], id: Uuid| {
+            ranked.iter().find(|r| r.memory.id == id).unwrap().score
+        };
+        let undemoted = score_of(&results.ranked, disputed.id);
+        let index = results.index();
+        assert!(index.iter().all(|e| e.contradicts.len() == 1));
+
+        // Cut to one result, the other side isn't shown, so nothing is flagged.
+        let results = search(&storage, &embedder, &request.clone().limit(1))
+            .await
+            .unwrap();
+        assert!(results.index()[0].contradicts.is_empty());
+
+        let request = request.demote_contradicted(true);
+        let results = search(&storage, &embedder, &request).await.unwrap();
+        let demoted = score_of(&results.ranked, disputed.id);
+        assert!((demoted - undemoted * CONTRADICTED_DEMOTION).abs() < 1e-6);
+        assert_eq!(results.ranked[0].memory.id, trusted.id);
+    }
+}
@@ -0,0 +1,214 @@
+//! Retrieval sessions: state carried across successive searches in one
+//! conversation.
+//!
+//! A client names a session with any ID it likes and passes it to each
+//! search. The session remembers which memories it has already been shown,
+//! so follow-up searches surface new ones, and builds an interest profile
+//! from the memories the client went on to use (fetched in full): results
+//! sharing their kind or tags get a boost. Sessions live in memory only and
+//! are dropped after [`SESSION_IDLE_MINUTES`] without activity.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::model::{Memory, MemoryKind};
+use crate::ranking::RankedResult;
+
+/// Sessions idle this long are forgotten.
+pub const SESSION_IDLE_MINUTES: i64 = 60;
+
+/// Most sessions kept at once; the least recently active is dropped first.
+pub const MAX_SESSIONS: usize = 256;
+
+/// Share of the final score given to [`RetrievalSession::interest`].
+pub const INTEREST_WEIGHT: f32 = 0.2;
+
+/// What one session has seen and used so far.
+#[derive(Debug, Clone)]
+pub struct RetrievalSession {
+    shown: HashSet<Uuid>,
+    used: HashSet<Uuid>,
+    kinds: HashMap<MemoryKind, usize>,
+    tags: HashMap<String, usize>,
+    last_active: DateTime<Utc>,
+}
+
+impl Default for RetrievalSession {
+    fn default() -> Self {
+        Self {
+            shown: HashSet::new(),
+            used: HashSet::new(),
+            kinds: HashMap::new(),
+            tags: HashMap::new(),
+            last_active: Utc::now(),
+        }
+    }
+}
+
+impl RetrievalSession {
+    /// Memories already returned in this session.
+    pub fn shown(&self) -> &HashSet<Uuid> {
+        &self.shown
+    }
+
+    pub fn record_shown(&mut self, ids: impl IntoIterator<Item = Uuid>) {
+        self.shown.extend(ids);
+    }
+
+    /// Count a memory the client went on to use towards the interest
+    /// profile. Using the same memory twice counts once.
+    pub fn record_used(&mut self, memory: &Memory) {
+        self.shown.insert(memory.id);
+        if !self.used.insert(memory.id) {
+            return;
+        }
+        *self.kinds.entry(memory.kind).or_insert(0) += 1;
+        for tag in &memory.tags {
+            *self.tags.entry(tag.to_lowercase()).or_insert(0) += 1;
+        }
+    }
+
+    /// How closely `memory` matches what the session used, from 0.0 to 1.0:
+    /// the share of used memories with its kind, averaged with the share
+    /// carrying its most common tag.
+    pub fn interest(&self, memory: &Memory) -> f32 {
+        if self.used.is_empty() {
+            return 0.0;
+        }
+        let used = self.used.len() as f32;
+        let kind = self.kinds.get(&memory.kind).copied().unwrap_or(0) as f32 / used;
+        let tag = memory
+            .tags
+            .iter()
+            .filter_map(|t| self.tags.get(&t.to_lowercase()))
+            .max()
+            .copied()
+            .unwrap_or(0) as f32
+            / used;
+        (kind + tag) / 2.0
+    }
+
+    /// Blend [`interest`](Self::interest) into ranked results and re-sort.
+    /// A no-op until the session has used something.
+    pub fn boost(&self, results: &mut [RankedResult]) {
+        if self.used.is_empty() {
+            return;
+        }
+        for r in results.iter_mut() {
+            r.score =
+                r.score * (1.0 - INTEREST_WEIGHT) + INTEREST_WEIGHT * self.interest(&r.memory);
+        }
+        results.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+}
+
+/// Retrieval sessions keyed by client-chosen ID.
+#[derive(Debug, Default)]
+pub struct RetrievalSessions {
+    sessions: Mutex<HashMap<String, RetrievalSession>>,
+}
+
+impl RetrievalSessions {
+    /// Run `f` on session `id`, starting it if needed. Expired sessions are
+    /// dropped first.
+    pub fn with<T>(&self, id: &str, f: impl FnOnce(&mut RetrievalSession) -> T) -> T {
+        let now = Utc::now();
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.retain(|_, s| now - s.last_active < Duration::minutes(SESSION_IDLE_MINUTES));
+        if !sessions.contains_key(id) && sessions.len() >= MAX_SESSIONS {
+            let oldest = sessions
+                .iter()
+                .min_by_key(|(_, s)| s.last_active)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                sessions.remove(&oldest);
+            }
+        }
+        let session = sessions.entry(id.to_string()).or_default();
+        session.last_active = now;
+        f(session)
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.lock().map_or(0, |s| s.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ranking::ScoreBreakdown;
+
+    fn memory(kind: MemoryKind, tags: &[&str]) -> Memory {
+        Memory::new(
+            "Title".to_string(),
+            "Content".to_string(),
+            kind,
+            "user".to_string(),
+        )
+        .with_tags(tags.iter().map(|t| t.to_string()).collect())
+    }
+
+    #[test]
+    fn test_interest_follows_used_memories() {
+        let mut session = RetrievalSession::default();
+        let error = memory(MemoryKind::Error, &["auth"]);
+        assert_eq!(session.interest(&error), 0.0);
+
+        session.record_used(&memory(MemoryKind::Error, &["Auth", "db"]));
+        session.record_used(&memory(MemoryKind::Fix, &["auth"]));
+        assert_eq!(session.shown().len(), 2);
+        assert_eq!(session.interest(&error), 0.75);
+        assert_eq!(
+            session.interest(&memory(MemoryKind::Decision, &["ui"])),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_boost_reorders_by_interest() {
+        let mut session = RetrievalSession::default();
+        session.record_used(&memory(MemoryKind::Fix, &["pool"]));
+        let result = |m: Memory, score: f32| RankedResult {
+            memory: m,
+            score,
+            breakdown: ScoreBreakdown {
+                similarity: score,
+                keyword: 0.0,
+                recency: 0.0,
+                importance: 0.0,
+                access_freq: 0.0,
+                graph_proximity: 0.0,
+                trust: 0.0,
+                centrality: 0.0,
+            },
+        };
+        let mut results = vec![
+            result(memory(MemoryKind::Decision, &[]), 0.6),
+            result(memory(MemoryKind::Fix, &["pool"]), 0.55),
+        ];
+        session.boost(&mut results);
+        assert_eq!(results[0].memory.kind, MemoryKind::Fix);
+    }
+
+    #[test]
+    fn test_sessions_are_kept_apart() {
+        let sessions = RetrievalSessions::default();
+        let id = Uuid::now_v7();
+        sessions.with("a", |s| s.record_shown([id]));
+        assert!(sessions.with("a", |s| s.shown().contains(&id)));
+        assert!(sessions.with("b", |s| s.shown().is_empty()));
+        assert_eq!(sessions.len(), 2);
+    }
+}
@@ -4,6 +4,8 @@ use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::sync::LazyLock;
 
+use crate::serde_defaults::default_true;
+
 /// Configuration for PII scrubbing patterns.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScrubConfig {
@@ -36,10 +38,6 @@ pub struct ScrubConfig {
     pub replacement: String,
 }
 
-fn default_true() -> bool {
-    true
-}
-
 fn default_replacement() -> String {
     "[REDACTED]".to_string()
 }
@@ -123,6 +121,7 @@ pub fn scrub(text: &str, config: &ScrubConfig) -> String {
 }
 
 /// Summary of what was scrubbed from a text.
+#[derive(Debug, Serialize)]
 pub struct ScrubReport {
     pub emails_found: usize,
     pub api_keys_found: usize,
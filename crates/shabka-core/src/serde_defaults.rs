@@ -0,0 +1,5 @@
+//! Shared `#[serde(default = "...")]` helpers for config structs.
+
+pub(crate) fn default_true() -> bool {
+    true
+}
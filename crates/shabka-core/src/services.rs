@@ -0,0 +1,264 @@
+//! Lazily-initialized registry of the services a process needs.
+//!
+//! Opening storage and building embedding/LLM clients is not free (SQLite
+//! migrations, HTTP clients, model loading). `Services` builds each one on
+//! first use and hands out shared `Arc`s afterwards, so the web server, the
+//! MCP sessions it hosts and the CLI reuse a single set per process.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::attachments::BlobStore;
+use crate::audit::AuditLog;
+use crate::config::{self, ShabkaConfig};
+use crate::embedding::EmbeddingService;
+use crate::error::Result;
+use crate::history::HistoryLogger;
+use crate::llm::LlmService;
+use crate::plugins::Plugins;
+use crate::questions::QuestionLog;
+use crate::storage::{create_backend, Storage};
+use crate::wasm_plugins::{self, WasmRanker};
+
+/// Shared, lazily-built storage and services for one configuration.
+pub struct Services {
+    config: Arc<ShabkaConfig>,
+    user_id: String,
+    storage: OnceLock<Arc<Storage>>,
+    embedding: OnceLock<Arc<EmbeddingService>>,
+    llm: OnceLock<Option<Arc<LlmService>>>,
+    history: OnceLock<Arc<HistoryLogger>>,
+    questions: OnceLock<Arc<QuestionLog>>,
+    audit: OnceLock<Arc<AuditLog>>,
+    blobs: OnceLock<Arc<BlobStore>>,
+    wasm_ranker: OnceLock<Option<Arc<WasmRanker>>>,
+    /// Where a tenant's history, questions and attachments live; `None`
+    /// for the config directory.
+    data_dir: Option<PathBuf>,
+    /// Serializes initialization so concurrent first calls build one instance.
+    init: Mutex<()>,
+}
+
+impl std::fmt::Debug for Services {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Services")
+            .field("user_id", &self.user_id)
+            .field("storage", &self.storage.get().is_some())
+            .field("embedding", &self.embedding.get().is_some())
+            .field("llm", &self.llm.get().is_some())
+            .finish()
+    }
+}
+
+impl Services {
+    /// A registry for `config`. Nothing is built until first requested.
+    pub fn new(config: ShabkaConfig) -> Arc<Self> {
+        let user_id = config::resolve_user_id(&config.sharing);
+        Arc::new(Self {
+            config: Arc::new(config),
+            user_id,
+            storage: OnceLock::new(),
+            embedding: OnceLock::new(),
+            llm: OnceLock::new(),
+            history: OnceLock::new(),
+            questions: OnceLock::new(),
+            audit: OnceLock::new(),
+            blobs: OnceLock::new(),
+            wasm_ranker: OnceLock::new(),
+            data_dir: None,
+            init: Mutex::new(()),
+        })
+    }
+
+    /// A registry for one tenant of a multi-tenant server: its own SQLite
+    /// database, history, questions and attachments under `dir`, sharing
+    /// this registry's configuration, embedding and LLM services and audit
+    /// log.
+    pub fn for_tenant(&self, dir: &Path) -> Result<Arc<Self>> {
+        let mut config = (*self.config).clone();
+        config.storage.backend = "sqlite".to_string();
+        config.storage.path = Some(dir.join("shabka.db").to_string_lossy().into_owned());
+        config.storage.snapshot_path = None;
+        let embedding = self.embedding()?;
+        let llm = self.llm();
+        let services = Arc::new(Self {
+            config: Arc::new(config),
+            user_id: self.user_id.clone(),
+            storage: OnceLock::new(),
+            embedding: OnceLock::from(embedding),
+            llm: OnceLock::from(llm),
+            history: OnceLock::new(),
+            questions: OnceLock::new(),
+            audit: OnceLock::from(self.audit()),
+            blobs: OnceLock::new(),
+            wasm_ranker: OnceLock::from(self.wasm_ranker()),
+            data_dir: Some(dir.to_path_buf()),
+            init: Mutex::new(()),
+        });
+        Ok(services)
+    }
+
+    /// A registry around an already-open storage handle (tests, in-memory stores).
+    pub fn with_storage(config: ShabkaConfig, storage: Storage) -> Arc<Self> {
+        let services = Self::new(config);
+        let _ = services.storage.set(Arc::new(storage));
+        services
+    }
+
+    /// A registry with storage and providers supplied up front, e.g. the
+    /// mocks from [`testing`](crate::testing). `llm` of `None` behaves as
+    /// LLM features being disabled.
+    pub fn with_providers(
+        config: ShabkaConfig,
+        storage: Storage,
+        embedding: EmbeddingService,
+        llm: Option<LlmService>,
+    ) -> Arc<Self> {
+        let services = Self::with_storage(config, storage);
+        let _ = services.embedding.set(Arc::new(embedding));
+        let _ = services.llm.set(llm.map(Arc::new));
+        services
+    }
+
+    pub fn config(&self) -> &Arc<ShabkaConfig> {
+        &self.config
+    }
+
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    /// The configured storage backend, opened on first use.
+    pub fn storage(&self) -> Result<Arc<Storage>> {
+        self.get_or_try_init(&self.storage, || create_backend(&self.config).map(Arc::new))
+    }
+
+    /// The configured embedding service, built on first use.
+    pub fn embedding(&self) -> Result<Arc<EmbeddingService>> {
+        self.get_or_try_init(&self.embedding, || {
+            EmbeddingService::from_config(&self.config.embedding).map(Arc::new)
+        })
+    }
+
+    /// The LLM service, or `None` when disabled or misconfigured.
+    pub fn llm(&self) -> Option<Arc<LlmService>> {
+        let llm = self.get_or_try_init(&self.llm, || {
+            Ok(if self.config.llm.enabled {
+                LlmService::from_config(&self.config.llm)
+                    .ok()
+                    .map(|llm| Arc::new(llm.with_language(self.config.output.language.as_deref())))
+            } else {
+                None
+            })
+        });
+        llm.ok().flatten()
+    }
+
+    pub fn history(&self) -> Arc<HistoryLogger> {
+        self.history
+            .get_or_init(|| {
+                let config = &self.config.history;
+                let logger = match &self.data_dir {
+                    Some(dir) => HistoryLogger::from_config_at(config, dir.join("history.jsonl")),
+                    None => HistoryLogger::from_config(config),
+                };
+                Arc::new(logger.with_plugins(Plugins::new(&self.config.plugins)))
+            })
+            .clone()
+    }
+
+    /// The log of unanswered searches.
+    pub fn questions(&self) -> Arc<QuestionLog> {
+        self.questions
+            .get_or_init(|| {
+                let config = &self.config.questions;
+                Arc::new(match &self.data_dir {
+                    Some(dir) => QuestionLog::with_path(dir.join("questions.jsonl"), config),
+                    None => QuestionLog::new(config),
+                })
+            })
+            .clone()
+    }
+
+    /// The request audit log.
+    pub fn audit(&self) -> Arc<AuditLog> {
+        self.audit
+            .get_or_init(|| Arc::new(AuditLog::new(&self.config.audit)))
+            .clone()
+    }
+
+    /// The attachment blob store.
+    pub fn blobs(&self) -> Arc<BlobStore> {
+        self.blobs
+            .get_or_init(|| {
+                let config = &self.config.attachments;
+                Arc::new(match &self.data_dir {
+                    Some(dir) => BlobStore::with_path(dir.join("blobs"), config),
+                    None => BlobStore::new(config),
+                })
+            })
+            .clone()
+    }
+
+    /// The `[wasm_plugins]` ranker, or `None` when none is configured or it
+    /// fails to load.
+    pub fn wasm_ranker(&self) -> Option<Arc<WasmRanker>> {
+        self.wasm_ranker
+            .get_or_init(|| wasm_plugins::load_ranker(&self.config.wasm_plugins))
+            .clone()
+    }
+
+    fn get_or_try_init<T: Clone>(
+        &self,
+        cell: &OnceLock<T>,
+        init: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        if let Some(value) = cell.get() {
+            return Ok(value.clone());
+        }
+        let _guard = self.init.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(value) = cell.get() {
+            return Ok(value.clone());
+        }
+        let value = init()?;
+        Ok(cell.get_or_init(|| value).clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::SqliteStorage;
+
+    #[test]
+    fn test_services_are_built_once_and_shared() {
+        let services = Services::new(ShabkaConfig::default_config());
+        let a = services.embedding().unwrap();
+        let b = services.embedding().unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(Arc::ptr_eq(&services.history(), &services.history()));
+    }
+
+    #[test]
+    fn test_with_storage_reuses_handle() {
+        let storage = Storage::Sqlite(SqliteStorage::open_in_memory().unwrap());
+        let services = Services::with_storage(ShabkaConfig::default_config(), storage);
+        let a = services.storage().unwrap();
+        let b = services.storage().unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+    }
+
+    #[test]
+    fn test_memory_backend_from_config() {
+        let mut config = ShabkaConfig::default_config();
+        config.storage.backend = "memory".to_string();
+        let services = Services::new(config);
+        assert_eq!(services.storage().unwrap().backend_name(), "memory");
+    }
+
+    #[test]
+    fn test_llm_disabled_by_default() {
+        let services = Services::new(ShabkaConfig::default_config());
+        assert!(services.llm().is_none());
+    }
+}
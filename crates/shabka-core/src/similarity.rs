@@ -0,0 +1,134 @@
+//! Similarity between stored memory vectors, computed locally.
+//!
+//! Scores are `1 / (1 + L2 distance)`, the same as SQLite vector search, so
+//! thresholds like `graph.similarity_threshold` mean the same thing here.
+//! Vectors of different sizes (left over from an embedding provider switch)
+//! are never compared. Work is spread across all cores.
+
+use uuid::Uuid;
+
+/// Two memories whose vectors score above a threshold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarPair {
+    pub source_id: Uuid,
+    pub target_id: Uuid,
+    pub similarity: f32,
+}
+
+/// `1 / (1 + L2 distance)`: 1.0 for identical vectors, towards 0 for distant ones.
+pub fn score(a: &[f32], b: &[f32]) -> f32 {
+    let distance = a
+        .iter()
+        .zip(b)
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum::<f32>()
+        .sqrt();
+    1.0 / (1.0 + distance)
+}
+
+/// For each vector, the indices and scores of its `limit` closest others
+/// scoring above `threshold`, best first.
+pub fn nearest_neighbours(
+    vectors: &[&[f32]],
+    threshold: f32,
+    limit: usize,
+) -> Vec<Vec<(usize, f32)>> {
+    par_rows(vectors.len(), |i| {
+        let mut found = matches(vectors, i, 0..vectors.len(), threshold);
+        found.sort_by(|a, b| b.1.total_cmp(&a.1));
+        found.truncate(limit);
+        found
+    })
+}
+
+/// Every pair of vectors scoring above `threshold`, once each, most similar
+/// first.
+pub fn similar_pairs(vectors: &[(Uuid, &[f32])], threshold: f32) -> Vec<SimilarPair> {
+    let slices: Vec<&[f32]> = vectors.iter().map(|(_, v)| *v).collect();
+    let mut pairs: Vec<SimilarPair> = par_rows(slices.len(), |i| {
+        matches(&slices, i, i + 1..slices.len(), threshold)
+    })
+    .into_iter()
+    .enumerate()
+    .flat_map(|(i, found)| {
+        found.into_iter().map(move |(j, similarity)| SimilarPair {
+            source_id: vectors[i].0,
+            target_id: vectors[j].0,
+            similarity,
+        })
+    })
+    .collect();
+    pairs.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    pairs
+}
+
+/// Indices in `candidates` (other than `i`) whose vector scores above
+/// `threshold` against vector `i`.
+fn matches(
+    vectors: &[&[f32]],
+    i: usize,
+    candidates: std::ops::Range<usize>,
+    threshold: f32,
+) -> Vec<(usize, f32)> {
+    let query = vectors[i];
+    candidates
+        .filter(|&j| j != i && vectors[j].len() == query.len())
+        .map(|j| (j, score(query, vectors[j])))
+        .filter(|(_, score)| *score > threshold)
+        .collect()
+}
+
+/// `row(i)` for every `i` in `0..len`, in order, split across threads.
+fn par_rows<T, F>(len: usize, row: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize) -> T + Sync,
+{
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let rows_per_thread = len.div_ceil(threads).max(1);
+    let row = &row;
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..len)
+            .step_by(rows_per_thread)
+            .map(|start| {
+                let end = (start + rows_per_thread).min(len);
+                scope.spawn(move || (start..end).map(row).collect::<Vec<_>>())
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("similarity worker panicked"))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score() {
+        assert_eq!(score(&[1.0, 2.0], &[1.0, 2.0]), 1.0);
+        assert_eq!(score(&[0.0, 0.0], &[3.0, 4.0]), 1.0 / 6.0);
+    }
+
+    #[test]
+    fn test_nearest_neighbours() {
+        let vectors: [&[f32]; 5] = [&[0.0, 0.0], &[0.1, 0.0], &[0.0, 0.2], &[9.0, 9.0], &[0.0]];
+        let found = nearest_neighbours(&vectors, 0.5, 1);
+        assert_eq!(found.len(), 5);
+        assert_eq!(found[0].iter().map(|m| m.0).collect::<Vec<_>>(), vec![1]);
+        assert!(found[3].is_empty(), "too far from everything");
+        assert!(found[4].is_empty(), "different size is never compared");
+    }
+
+    #[test]
+    fn test_similar_pairs_lists_each_pair_once() {
+        let (a, b, c) = (Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7());
+        let vectors: [(Uuid, &[f32]); 3] = [(a, &[0.0, 0.0]), (b, &[0.0, 0.5]), (c, &[0.0, 0.1])];
+        let pairs = similar_pairs(&vectors, 0.6);
+        let ids: Vec<(Uuid, Uuid)> = pairs.iter().map(|p| (p.source_id, p.target_id)).collect();
+        assert_eq!(ids, vec![(a, c), (b, c), (a, b)]);
+        assert!(pairs.windows(2).all(|w| w[0].similarity >= w[1].similarity));
+    }
+}
@@ -0,0 +1,309 @@
+//! Named snapshots of Shabka's local state, for rolling back experiments.
+//!
+//! A snapshot holds a consistent copy of the SQLite database plus the files
+//! that decide how it is read and written: the config layers and the
+//! embedding state. Snapshots live in `~/.config/shabka/snapshots/<name>/`
+//! with a `snapshot.json` manifest. Restoring puts every file back where it
+//! was taken from, and removes the ones that didn't exist yet, so a new
+//! project config added during an experiment goes away too.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{self, EmbeddingState};
+use crate::error::{Result, ShabkaError};
+use crate::storage::SqliteStorage;
+
+const MANIFEST: &str = "snapshot.json";
+const DATABASE: &str = "shabka.db";
+
+/// A file captured in a snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    /// Where the file lives.
+    pub path: PathBuf,
+    /// Its copy in the snapshot directory; `None` if it didn't exist when
+    /// the snapshot was taken, so restoring removes it.
+    pub stored: Option<String>,
+}
+
+/// The `snapshot.json` of a snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    /// Shabka version that took it.
+    pub version: String,
+    /// The database the snapshot was taken from, and is restored to.
+    pub database: PathBuf,
+    pub files: Vec<SnapshotFile>,
+}
+
+/// The state files captured alongside the database for `project_dir`.
+pub fn state_files(project_dir: Option<&Path>) -> Vec<PathBuf> {
+    let mut files = config::config_files(project_dir);
+    files.extend(EmbeddingState::path());
+    files
+}
+
+/// A directory of named snapshots.
+pub struct SnapshotStore {
+    root: PathBuf,
+}
+
+impl SnapshotStore {
+    /// The store at `~/.config/shabka/snapshots`.
+    pub fn open() -> Result<Self> {
+        let root = dirs::config_dir()
+            .map(|p| p.join("shabka").join("snapshots"))
+            .ok_or_else(|| ShabkaError::Config("cannot determine config directory".to_string()))?;
+        Ok(Self::at(root))
+    }
+
+    /// A store rooted at `root`.
+    pub fn at(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn dir(&self, name: &str) -> Result<PathBuf> {
+        let valid = !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            return Err(ShabkaError::InvalidInput(format!(
+                "invalid snapshot name '{name}': use letters, digits, '-', '_' and '.'"
+            )));
+        }
+        Ok(self.root.join(name))
+    }
+
+    /// Snapshot the database at `database` and the `files` beside it as
+    /// `name`. Fails if `name` is taken.
+    pub fn create(
+        &self,
+        name: &str,
+        database: &Path,
+        files: &[PathBuf],
+    ) -> Result<SnapshotManifest> {
+        let dir = self.dir(name)?;
+        if dir.exists() {
+            return Err(ShabkaError::InvalidInput(format!(
+                "snapshot '{name}' already exists"
+            )));
+        }
+        if !database.is_file() {
+            return Err(ShabkaError::Storage(format!(
+                "no database at {}",
+                database.display()
+            )));
+        }
+        std::fs::create_dir_all(&dir).map_err(io_err(&dir))?;
+
+        let result = (|| -> Result<SnapshotManifest> {
+            SqliteStorage::open(database)?.snapshot_to(dir.join(DATABASE))?;
+            let mut stored = Vec::with_capacity(files.len());
+            for (i, path) in files.iter().enumerate() {
+                let copy = path.is_file().then(|| format!("{i}-{}", file_name(path)));
+                if let Some(copy) = &copy {
+                    std::fs::copy(path, dir.join(copy)).map_err(io_err(path))?;
+                }
+                stored.push(SnapshotFile {
+                    path: path.clone(),
+                    stored: copy,
+                });
+            }
+            let manifest = SnapshotManifest {
+                name: name.to_string(),
+                created_at: Utc::now(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                database: database.to_path_buf(),
+                files: stored,
+            };
+            let json = serde_json::to_string_pretty(&manifest)?;
+            std::fs::write(dir.join(MANIFEST), json + "\n").map_err(io_err(&dir))?;
+            Ok(manifest)
+        })();
+        if result.is_err() {
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+        result
+    }
+
+    /// The manifest of snapshot `name`.
+    pub fn get(&self, name: &str) -> Result<SnapshotManifest> {
+        let path = self.dir(name)?.join(MANIFEST);
+        let text = std::fs::read_to_string(&path)
+            .map_err(|_| ShabkaError::NotFound(format!("snapshot '{name}'")))?;
+        serde_json::from_str(&text)
+            .map_err(|e| ShabkaError::Storage(format!("corrupt snapshot manifest: {e}")))
+    }
+
+    /// Put the database and files of snapshot `name` back. Nothing else
+    /// should have the database open.
+    pub fn restore(&self, name: &str) -> Result<SnapshotManifest> {
+        let manifest = self.get(name)?;
+        let dir = self.dir(name)?;
+
+        for file in &manifest.files {
+            match &file.stored {
+                Some(copy) => {
+                    if let Some(parent) = file.path.parent() {
+                        std::fs::create_dir_all(parent).map_err(io_err(parent))?;
+                    }
+                    std::fs::copy(dir.join(copy), &file.path).map_err(io_err(&file.path))?;
+                }
+                None if file.path.exists() => {
+                    std::fs::remove_file(&file.path).map_err(io_err(&file.path))?;
+                }
+                None => {}
+            }
+        }
+
+        // Copy beside the database and rename over it, then drop the WAL
+        // files, which belong to the database being replaced. They're kept
+        // until the rename succeeds: without them the old database loses
+        // its un-checkpointed writes.
+        let database = &manifest.database;
+        if let Some(parent) = database.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(io_err(parent))?;
+        }
+        let tmp = with_suffix(database, ".restore");
+        std::fs::copy(dir.join(DATABASE), &tmp).map_err(io_err(&tmp))?;
+        if let Err(e) = std::fs::rename(&tmp, database) {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(io_err(database)(e));
+        }
+        for suffix in ["-wal", "-shm"] {
+            let _ = std::fs::remove_file(with_suffix(database, suffix));
+        }
+        Ok(manifest)
+    }
+
+    /// Every snapshot, oldest first.
+    pub fn list(&self) -> Result<Vec<SnapshotManifest>> {
+        let entries = match std::fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(io_err(&self.root)(e)),
+        };
+        let mut snapshots: Vec<SnapshotManifest> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| self.get(&entry.file_name().to_string_lossy()).ok())
+            .collect();
+        snapshots.sort_by_key(|s| s.created_at);
+        Ok(snapshots)
+    }
+
+    /// Delete snapshot `name`.
+    pub fn delete(&self, name: &str) -> Result<()> {
+        self.get(name)?;
+        let dir = self.dir(name)?;
+        std::fs::remove_dir_all(&dir).map_err(io_err(&dir))
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "file".to_string())
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn io_err(path: &Path) -> impl Fn(std::io::Error) -> ShabkaError + '_ {
+    move |e| ShabkaError::Storage(format!("{}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Memory, MemoryKind};
+    use crate::storage::StorageBackend;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_snapshot_create_and_restore() {
+        let dir = std::env::temp_dir().join(format!("shabka-snapshot-{}", Uuid::now_v7()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let database = dir.join("shabka.db");
+        let config = dir.join("config.toml");
+        let added = dir.join(".shabka").join("config.local.toml");
+        std::fs::write(&config, "[capture]\nenabled = true\n").unwrap();
+
+        let kept = Memory::new("Kept".into(), "c".into(), MemoryKind::Fact, "u".into());
+        {
+            let storage = SqliteStorage::open(&database).unwrap();
+            storage.save_memory(&kept, None).await.unwrap();
+        }
+
+        let store = SnapshotStore::at(dir.join("snapshots"));
+        let files = vec![config.clone(), added.clone()];
+        let manifest = store.create("before", &database, &files).unwrap();
+        assert!(manifest.files[1].stored.is_none());
+        assert!(store.create("before", &database, &files).is_err());
+        assert!(store.create("../escape", &database, &files).is_err());
+
+        // The experiment: a new memory, a changed config and a new layer.
+        let scratch = Memory::new("Scratch".into(), "c".into(), MemoryKind::Fact, "u".into());
+        {
+            let storage = SqliteStorage::open(&database).unwrap();
+            storage.save_memory(&scratch, None).await.unwrap();
+        }
+        std::fs::write(&config, "[capture]\nenabled = false\n").unwrap();
+        std::fs::create_dir_all(added.parent().unwrap()).unwrap();
+        std::fs::write(&added, "").unwrap();
+
+        store.restore("before").unwrap();
+        let storage = SqliteStorage::open(&database).unwrap();
+        assert!(storage.get_memory(kept.id).await.is_ok());
+        assert!(storage.get_memory(scratch.id).await.is_err());
+        assert!(std::fs::read_to_string(&config)
+            .unwrap()
+            .contains("enabled = true"));
+        assert!(!added.exists());
+
+        assert_eq!(store.list().unwrap().len(), 1);
+        store.delete("before").unwrap();
+        assert!(store.list().unwrap().is_empty());
+        assert!(matches!(
+            store.restore("before"),
+            Err(ShabkaError::NotFound(_))
+        ));
+
+        drop(storage);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_restore_keeps_wal() {
+        let dir = std::env::temp_dir().join(format!("shabka-snapshot-{}", Uuid::now_v7()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let database = dir.join("shabka.db");
+        SqliteStorage::open(&database).unwrap();
+        let store = SnapshotStore::at(dir.join("snapshots"));
+        store.create("before", &database, &[]).unwrap();
+
+        // A non-empty directory where the database was makes the rename fail.
+        std::fs::remove_file(&database).unwrap();
+        std::fs::create_dir_all(database.join("blocker")).unwrap();
+        let wal = with_suffix(&database, "-wal");
+        std::fs::write(&wal, "un-checkpointed writes").unwrap();
+
+        assert!(store.restore("before").is_err());
+        assert_eq!(
+            std::fs::read_to_string(&wal).unwrap(),
+            "un-checkpointed writes"
+        );
+        assert!(!with_suffix(&database, ".restore").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,10 +1,123 @@
+use serde::Serialize;
+
+use crate::centrality::Centrality;
 use crate::error::Result;
 use crate::model::*;
 use uuid::Uuid;
 
+/// Optional features a storage backend may not support.
+///
+/// Lets callers report what is unavailable up front instead of discovering
+/// it inside a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct Capabilities {
+    /// Integrity check and repair.
+    pub integrity_check: bool,
+    /// Versioned schema with recorded writer version.
+    pub schema_info: bool,
+    /// Indexed short-ID and title lookups (otherwise a full scan).
+    pub indexed_lookup: bool,
+    /// Native filtered counts (otherwise fetch-and-count).
+    pub native_count: bool,
+    /// Persists [`Memory::attachments`].
+    pub attachments: bool,
+    /// Persists [`Memory::code`].
+    pub code_snippets: bool,
+    /// Indexes referenced symbols (see [`StorageBackend::symbol_search`]).
+    pub symbol_index: bool,
+    /// Persists [`Memory::verification_expires_at`].
+    pub verification_expiry: bool,
+    /// Persists [`Memory::dispute`].
+    pub disputes: bool,
+    /// Persists [`Memory::assertions`].
+    pub assertions: bool,
+    /// Persists [`Memory::endorsements`].
+    pub endorsements: bool,
+    /// Persists [`Memory::label`].
+    pub labels: bool,
+    /// Persists [`Memory::device_id`].
+    pub devices: bool,
+    /// Persists [`Memory::owner`].
+    pub owners: bool,
+    /// Persists [`Memory::confidence`].
+    pub confidence: bool,
+    /// Deletes single relations (see [`StorageBackend::remove_relation`]).
+    pub relation_removal: bool,
+    /// Records maintenance runs (see [`crate::maintenance`]).
+    pub maintenance_log: bool,
+    /// Typo-tolerant title search (see [`StorageBackend::title_typo_search`]).
+    pub typo_search: bool,
+    /// Exact phrase and title lookups (see [`StorageBackend::literal_search`]).
+    pub literal_search: bool,
+    /// Stores memory centrality (see [`StorageBackend::centrality`]).
+    pub centrality: bool,
+}
+
+impl Capabilities {
+    pub fn all() -> Self {
+        Self {
+            integrity_check: true,
+            schema_info: true,
+            indexed_lookup: true,
+            native_count: true,
+            attachments: true,
+            code_snippets: true,
+            symbol_index: true,
+            verification_expiry: true,
+            disputes: true,
+            assertions: true,
+            endorsements: true,
+            labels: true,
+            devices: true,
+            owners: true,
+            confidence: true,
+            relation_removal: true,
+            maintenance_log: true,
+            typo_search: true,
+            literal_search: true,
+            centrality: true,
+        }
+    }
+
+    /// Human-readable names of the features this backend lacks.
+    pub fn unavailable(&self) -> Vec<&'static str> {
+        [
+            (self.integrity_check, "integrity check"),
+            (self.schema_info, "schema versioning"),
+            (self.indexed_lookup, "indexed lookups"),
+            (self.native_count, "native counts"),
+            (self.attachments, "attachments"),
+            (self.code_snippets, "code snippets"),
+            (self.symbol_index, "symbol index"),
+            (self.verification_expiry, "verification expiry"),
+            (self.disputes, "dispute notes"),
+            (self.assertions, "assertions"),
+            (self.endorsements, "endorsements"),
+            (self.labels, "classification labels"),
+            (self.devices, "device tracking"),
+            (self.owners, "ownership"),
+            (self.confidence, "capture confidence"),
+            (self.relation_removal, "relation removal"),
+            (self.maintenance_log, "maintenance history"),
+            (self.typo_search, "typo-tolerant search"),
+            (self.literal_search, "exact-match search"),
+            (self.centrality, "centrality scores"),
+        ]
+        .into_iter()
+        .filter(|(supported, _)| !supported)
+        .map(|(_, name)| name)
+        .collect()
+    }
+}
+
 /// Abstract storage backend. HelixDB is the primary implementation,
 /// but this trait allows swapping to SQLite or other backends.
 pub trait StorageBackend: Send + Sync {
+    /// Optional features this backend supports. Defaults to none.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
     // -- Memory CRUD --
 
     fn save_memory(
@@ -37,6 +150,44 @@ pub trait StorageBackend: Send + Sync {
         limit: usize,
     ) -> impl std::future::Future<Output = Result<Vec<(Memory, f32)>>> + Send;
 
+    /// Memories referencing a symbol, by index key (see
+    /// [`crate::code::symbol_key`]). Backends without a symbol index return
+    /// nothing; callers still rank by [`crate::code::symbol_score`].
+    fn symbol_search(
+        &self,
+        key: &str,
+        limit: usize,
+    ) -> impl std::future::Future<Output = Result<Vec<Memory>>> + Send {
+        let _ = (key, limit);
+        async { Ok(Vec::new()) }
+    }
+
+    /// Memories with a title word within [`crate::ranking::typo_allowance`]
+    /// edits of one of `terms`, so a misspelled query still reaches them.
+    /// Backends without fuzzy matching return nothing.
+    fn title_typo_search(
+        &self,
+        terms: &[String],
+        limit: usize,
+    ) -> impl std::future::Future<Output = Result<Vec<Memory>>> + Send {
+        let _ = (terms, limit);
+        async { Ok(Vec::new()) }
+    }
+
+    /// Memories whose title or content contains every one of `phrases` and
+    /// whose title contains every one of `title_terms`, case-insensitively,
+    /// most recently updated first. Backends without text lookups return
+    /// nothing; callers still filter with [`crate::query::SearchQuery::matches`].
+    fn literal_search(
+        &self,
+        phrases: &[String],
+        title_terms: &[String],
+        limit: usize,
+    ) -> impl std::future::Future<Output = Result<Vec<Memory>>> + Send {
+        let _ = (phrases, title_terms, limit);
+        async { Ok(Vec::new()) }
+    }
+
     // -- Timeline --
 
     fn timeline(
@@ -56,6 +207,19 @@ pub trait StorageBackend: Send + Sync {
         memory_id: Uuid,
     ) -> impl std::future::Future<Output = Result<Vec<MemoryRelation>>> + Send;
 
+    /// Delete the relation of `relation_type` from `source_id` to
+    /// `target_id`. Returns whether one was removed; backends that can't
+    /// delete single edges keep it and return `false`.
+    fn remove_relation(
+        &self,
+        source_id: Uuid,
+        target_id: Uuid,
+        relation_type: RelationType,
+    ) -> impl std::future::Future<Output = Result<bool>> + Send {
+        let _ = (source_id, target_id, relation_type);
+        async { Ok(false) }
+    }
+
     /// Count outgoing relations for a batch of memory IDs.
     /// Returns (id, count) pairs for each input ID.
     fn count_relations(
@@ -70,6 +234,16 @@ pub trait StorageBackend: Send + Sync {
         memory_ids: &[Uuid],
     ) -> impl std::future::Future<Output = Result<Vec<(Uuid, usize)>>> + Send;
 
+    /// Stored centrality of each of `memory_ids` that has been scored (see
+    /// [`crate::centrality`]). Backends that don't store it return nothing.
+    fn centrality(
+        &self,
+        memory_ids: &[Uuid],
+    ) -> impl std::future::Future<Output = Result<Vec<Centrality>>> + Send {
+        let _ = memory_ids;
+        async { Ok(Vec::new()) }
+    }
+
     // -- Session --
 
     fn save_session(
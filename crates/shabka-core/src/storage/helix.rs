@@ -13,6 +13,8 @@ use super::StorageBackend;
 pub struct HelixStorage {
     base_url: String,
     http: reqwest::Client,
+    /// Labels refused on save (see [`HelixStorage::withhold_labels`]).
+    withheld_labels: Vec<String>,
 }
 
 impl HelixStorage {
@@ -26,6 +28,25 @@ impl HelixStorage {
                 .connect_timeout(std::time::Duration::from_secs(5))
                 .build()
                 .expect("failed to build HTTP client"),
+            withheld_labels: Vec::new(),
+        }
+    }
+
+    /// Refuse to store memories carrying any of `labels`. Used when the
+    /// server is on another machine and their policy forbids sync.
+    pub fn withhold_labels(mut self, labels: Vec<String>) -> Self {
+        self.withheld_labels = labels;
+        self
+    }
+
+    fn check_label(&self, label: Option<&str>) -> Result<()> {
+        match label.and_then(crate::labels::normalize) {
+            Some(label) if self.withheld_labels.contains(&label) => {
+                Err(ShabkaError::InvalidInput(format!(
+                    "label '{label}' may not be synced off this machine ([labels.policies] sync = false)"
+                )))
+            }
+            _ => Ok(()),
         }
     }
 
@@ -47,7 +68,16 @@ impl HelixStorage {
         data: &T,
     ) -> Result<R> {
         let url = format!("{}/{}", self.base_url, endpoint);
-        let resp = self.http.post(&url).json(data).send().await?;
+        let resp = self.http.post(&url).json(data).send().await.map_err(|e| {
+            if e.is_connect() || e.is_timeout() {
+                ShabkaError::StorageUnavailable(format!(
+                    "cannot reach HelixDB at {}: {e}",
+                    self.base_url
+                ))
+            } else {
+                ShabkaError::Http(e)
+            }
+        })?;
         let status = resp.status();
         let body = resp.text().await?;
 
@@ -289,6 +319,8 @@ fn record_to_memory(r: &MemoryRecord) -> Result<Memory> {
             .as_deref()
             .and_then(|s| s.parse().ok())
             .unwrap_or_default(),
+        verification_expires_at: None,
+        dispute: None,
         project_id: r.project_id.clone(),
         session_id: r.session_id.as_ref().and_then(|s| Uuid::parse_str(s).ok()),
         created_by: r.created_by.clone(),
@@ -301,6 +333,16 @@ fn record_to_memory(r: &MemoryRecord) -> Result<Memory> {
         accessed_at: DateTime::parse_from_rfc3339(&r.accessed_at)
             .map(|dt| dt.with_timezone(&chrono::Utc))
             .map_err(|e| ShabkaError::Storage(e.to_string()))?,
+        // Helix has no label, device, attachments, code, expiry, dispute
+        // or assertion fields; see `Capabilities`.
+        label: None,
+        device_id: None,
+        owner: None,
+        confidence: None,
+        attachments: Vec::new(),
+        code: None,
+        assertions: Vec::new(),
+        endorsements: Vec::new(),
     })
 }
 
@@ -328,6 +370,7 @@ fn record_to_session(r: &SessionRecord) -> Result<Session> {
 
 impl StorageBackend for HelixStorage {
     async fn save_memory(&self, memory: &Memory, embedding: Option<&[f32]>) -> Result<()> {
+        self.check_label(memory.label.as_deref())?;
         let req = SaveMemoryRequest {
             id: memory.id.to_string(),
             kind: memory.kind.to_string(),
@@ -373,6 +416,7 @@ impl StorageBackend for HelixStorage {
     }
 
     async fn update_memory(&self, id: Uuid, input: &UpdateMemoryInput) -> Result<Memory> {
+        self.check_label(input.label.as_deref())?;
         // Fetch existing, apply updates
         let mut memory = self.get_memory(id).await?;
 
@@ -380,14 +424,11 @@ impl StorageBackend for HelixStorage {
             memory.title = title.clone();
         }
         if let Some(content) = &input.content {
-            memory.content = content.clone();
-            if memory.summary == memory.content || memory.summary.ends_with("...") {
-                memory.summary = if content.len() > 200 {
-                    format!("{}...", &content[..200])
-                } else {
-                    content.clone()
-                };
+            // Only a summary derived from the old content follows the edit.
+            if memory.summary == summarize(&memory.content) {
+                memory.summary = summarize(content);
             }
+            memory.content = content.clone();
         }
         if let Some(tags) = &input.tags {
             memory.tags = tags.clone();
@@ -406,8 +447,24 @@ impl StorageBackend for HelixStorage {
         }
         if let Some(verification) = input.verification {
             memory.verification = verification;
+            memory.verification_expires_at = input.verification_expires_at;
+            memory.dispute = input.dispute.clone();
+        }
+        if let Some(assertions) = &input.assertions {
+            memory.assertions = assertions.clone();
+        }
+        if let Some(endorsements) = &input.endorsements {
+            memory.endorsements = endorsements.clone();
+        }
+        if let Some(owner) = &input.owner {
+            memory.owner = (!owner.is_empty()).then(|| owner.clone());
+        }
+        if let Some(accessed_at) = input.accessed_at {
+            memory.accessed_at = accessed_at;
+        }
+        if !input.is_access_only() {
+            memory.updated_at = chrono::Utc::now();
         }
-        memory.updated_at = chrono::Utc::now();
 
         // HelixDB has no UPDATE — delete old node, then create new one (node-only, preserves vector).
         self.delete_memory(id).await?;
@@ -502,7 +559,7 @@ impl StorageBackend for HelixStorage {
         if let Some(ref pid) = query.project_id {
             memories.retain(|m| m.project_id.as_ref() == Some(pid));
         }
-        memories.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        memories.sort_by_key(|m| std::cmp::Reverse(m.created_at));
         memories.truncate(query.limit);
 
         // Batch-fetch relation counts
@@ -0,0 +1,224 @@
+//! The HelixDB schema and queries Shabka expects.
+//!
+//! The `.hx` files are bundled into the crate so `shabka helix init` can
+//! bootstrap a fresh HelixDB instance without a checkout of the repository.
+//! A fingerprint of the bundled files is recorded after each push, which lets
+//! `shabka doctor` spot an instance still running an older schema.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{HelixStorage, StorageBackend};
+use crate::error::{Result, ShabkaError};
+use crate::model::TimelineQuery;
+
+/// Node, edge and vector definitions (`schema.hx`).
+pub const SCHEMA: &str = include_str!("../../helix/schema.hx");
+/// Query definitions (`queries.hx`).
+pub const QUERIES: &str = include_str!("../../helix/queries.hx");
+/// Helix project file (`helix.toml`).
+pub const PROJECT: &str = include_str!("../../helix/helix.toml");
+
+/// Stable fingerprint of the bundled schema and queries (FNV-1a, hex).
+pub fn fingerprint() -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in SCHEMA.bytes().chain([0]).chain(QUERIES.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{hash:016x}")
+}
+
+/// Write the bundled Helix project into `dir`, using `port` for the dev instance.
+///
+/// Returns the paths written.
+pub fn write_project(dir: &Path, port: u16) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| ShabkaError::Config(format!("failed to create {}: {e}", dir.display())))?;
+    let project = PROJECT
+        .lines()
+        .map(|line| {
+            if line.trim_start().starts_with("port =") {
+                format!("port = {port}")
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "\n";
+    let files = [
+        ("helix.toml", project.as_str()),
+        ("schema.hx", SCHEMA),
+        ("queries.hx", QUERIES),
+    ];
+    let mut written = Vec::with_capacity(files.len());
+    for (name, contents) in files {
+        let path = dir.join(name);
+        std::fs::write(&path, contents)
+            .map_err(|e| ShabkaError::Config(format!("failed to write {}: {e}", path.display())))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Check that a running instance answers the queries Shabka relies on.
+pub async fn verify(storage: &HelixStorage) -> Result<()> {
+    storage
+        .timeline(&TimelineQuery {
+            limit: 1,
+            ..Default::default()
+        })
+        .await?;
+    storage.get_relations(Uuid::nil()).await?;
+    Ok(())
+}
+
+/// Record of the last schema pushed with `shabka helix init`.
+///
+/// Stored at `~/.config/shabka/helix_schema.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct HelixSchemaState {
+    #[serde(default)]
+    pub fingerprint: String,
+    /// Shabka version that pushed the schema.
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub pushed_at: String,
+}
+
+/// How the recorded schema compares to the bundled one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaStatus {
+    Current,
+    /// No push has been recorded on this machine.
+    Unknown,
+    /// The instance was initialized from a different schema.
+    Drifted {
+        version: String,
+    },
+}
+
+impl HelixSchemaState {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|p| p.join("shabka").join("helix_schema.toml"))
+    }
+
+    /// State for the bundled schema, pushed now.
+    pub fn current() -> Self {
+        Self {
+            fingerprint: fingerprint(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            pushed_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Load from disk. Returns `Default` if the file is missing or unparseable.
+    pub fn load() -> Self {
+        Self::path()
+            .map(|p| Self::load_from(&p))
+            .unwrap_or_default()
+    }
+
+    pub fn load_from(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save to disk, creating the parent directory if needed.
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()
+            .ok_or_else(|| ShabkaError::Config("cannot determine config directory".to_string()))?;
+        self.save_to(&path)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| ShabkaError::Config(format!("failed to create config dir: {e}")))?;
+        }
+        let toml_str = toml::to_string_pretty(self).map_err(|e| {
+            ShabkaError::Config(format!("failed to serialize helix schema state: {e}"))
+        })?;
+        std::fs::write(path, toml_str)
+            .map_err(|e| ShabkaError::Config(format!("failed to write helix schema state: {e}")))
+    }
+
+    pub fn status(&self) -> SchemaStatus {
+        if self.fingerprint.is_empty() {
+            SchemaStatus::Unknown
+        } else if self.fingerprint == fingerprint() {
+            SchemaStatus::Current
+        } else {
+            SchemaStatus::Drifted {
+                version: self.version.clone(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_schema_matches_repo_project() {
+        // The top-level `helix/` directory is the dev project used by `just
+        // db`; the bundled copy must not drift from it.
+        let repo = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../helix");
+        for (name, bundled) in [("schema.hx", SCHEMA), ("queries.hx", QUERIES)] {
+            if let Ok(contents) = std::fs::read_to_string(repo.join(name)) {
+                assert_eq!(
+                    contents, bundled,
+                    "crates/shabka-core/helix/{name} is stale"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_is_stable() {
+        assert_eq!(fingerprint(), fingerprint());
+        assert_eq!(fingerprint().len(), 16);
+    }
+
+    #[test]
+    fn test_write_project_sets_port() {
+        let dir = std::env::temp_dir().join(format!("shabka-helix-{}", Uuid::now_v7()));
+        let written = write_project(&dir, 7070).unwrap();
+        assert_eq!(written.len(), 3);
+        let project = std::fs::read_to_string(dir.join("helix.toml")).unwrap();
+        assert!(project.contains("port = 7070"));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("schema.hx")).unwrap(),
+            SCHEMA
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_state_status() {
+        assert_eq!(HelixSchemaState::default().status(), SchemaStatus::Unknown);
+        assert_eq!(HelixSchemaState::current().status(), SchemaStatus::Current);
+
+        let path = std::env::temp_dir().join(format!("shabka-helix-{}.toml", Uuid::now_v7()));
+        let stale = HelixSchemaState {
+            fingerprint: "0000000000000000".into(),
+            version: "0.1.0".into(),
+            pushed_at: String::new(),
+        };
+        stale.save_to(&path).unwrap();
+        assert_eq!(
+            HelixSchemaState::load_from(&path).status(),
+            SchemaStatus::Drifted {
+                version: "0.1.0".into()
+            }
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+}
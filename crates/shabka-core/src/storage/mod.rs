@@ -1,14 +1,24 @@
 mod backend;
 mod helix;
+pub mod helix_schema;
 mod sqlite;
 
-pub use backend::StorageBackend;
+pub use backend::{Capabilities, StorageBackend};
 pub use helix::HelixStorage;
-pub use sqlite::{IntegrityReport, SqliteStorage};
+pub(crate) use sqlite::table_columns;
+pub use sqlite::{IntegrityReport, SqliteOptions, SqliteStorage};
 
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::api_tokens::ApiToken;
+use crate::centrality::Centrality;
 use crate::config::ShabkaConfig;
 use crate::error::{Result, ShabkaError};
+use crate::maintenance::{MaintenanceOp, MaintenanceRun};
 use crate::model::*;
+use crate::tag_map::{TagPair, TagUsage};
 use uuid::Uuid;
 
 /// Enum wrapper for storage backends. Dispatches to the concrete implementation.
@@ -19,6 +29,13 @@ pub enum Storage {
 }
 
 impl StorageBackend for Storage {
+    fn capabilities(&self) -> Capabilities {
+        match self {
+            Storage::Sqlite(s) => s.capabilities(),
+            Storage::Helix(s) => s.capabilities(),
+        }
+    }
+
     async fn save_memory(&self, memory: &Memory, embedding: Option<&[f32]>) -> Result<()> {
         match self {
             Storage::Sqlite(s) => s.save_memory(memory, embedding).await,
@@ -61,6 +78,32 @@ impl StorageBackend for Storage {
         }
     }
 
+    async fn symbol_search(&self, key: &str, limit: usize) -> Result<Vec<Memory>> {
+        match self {
+            Storage::Sqlite(s) => s.symbol_search(key, limit).await,
+            Storage::Helix(s) => s.symbol_search(key, limit).await,
+        }
+    }
+
+    async fn title_typo_search(&self, terms: &[String], limit: usize) -> Result<Vec<Memory>> {
+        match self {
+            Storage::Sqlite(s) => s.title_typo_search(terms, limit).await,
+            Storage::Helix(s) => s.title_typo_search(terms, limit).await,
+        }
+    }
+
+    async fn literal_search(
+        &self,
+        phrases: &[String],
+        title_terms: &[String],
+        limit: usize,
+    ) -> Result<Vec<Memory>> {
+        match self {
+            Storage::Sqlite(s) => s.literal_search(phrases, title_terms, limit).await,
+            Storage::Helix(s) => s.literal_search(phrases, title_terms, limit).await,
+        }
+    }
+
     async fn timeline(&self, query: &TimelineQuery) -> Result<Vec<TimelineEntry>> {
         match self {
             Storage::Sqlite(s) => s.timeline(query).await,
@@ -82,6 +125,18 @@ impl StorageBackend for Storage {
         }
     }
 
+    async fn remove_relation(
+        &self,
+        source_id: Uuid,
+        target_id: Uuid,
+        relation_type: RelationType,
+    ) -> Result<bool> {
+        match self {
+            Storage::Sqlite(s) => s.remove_relation(source_id, target_id, relation_type).await,
+            Storage::Helix(s) => s.remove_relation(source_id, target_id, relation_type).await,
+        }
+    }
+
     async fn count_relations(&self, memory_ids: &[Uuid]) -> Result<Vec<(Uuid, usize)>> {
         match self {
             Storage::Sqlite(s) => s.count_relations(memory_ids).await,
@@ -96,6 +151,13 @@ impl StorageBackend for Storage {
         }
     }
 
+    async fn centrality(&self, memory_ids: &[Uuid]) -> Result<Vec<Centrality>> {
+        match self {
+            Storage::Sqlite(s) => s.centrality(memory_ids).await,
+            Storage::Helix(s) => s.centrality(memory_ids).await,
+        }
+    }
+
     async fn save_session(&self, session: &Session) -> Result<()> {
         match self {
             Storage::Sqlite(s) => s.save_session(session).await,
@@ -112,6 +174,15 @@ impl StorageBackend for Storage {
 }
 
 impl Storage {
+    /// Backend name as used in `[storage] backend`.
+    pub fn backend_name(&self) -> &'static str {
+        match self {
+            Storage::Sqlite(s) if s.is_in_memory() => "memory",
+            Storage::Sqlite(_) => "sqlite",
+            Storage::Helix(_) => "helix",
+        }
+    }
+
     /// Return `(schema_version, last_writer_version)` for SQLite, `None` for Helix.
     pub fn schema_info(&self) -> Option<(i32, Option<String>)> {
         match self {
@@ -141,6 +212,85 @@ impl Storage {
         }
     }
 
+    /// Record a maintenance run (SQLite only; Helix keeps no history).
+    pub async fn record_maintenance_run(&self, run: &MaintenanceRun) -> Result<()> {
+        match self {
+            Storage::Sqlite(s) => s.record_maintenance_run(run).await,
+            Storage::Helix(_) => Ok(()),
+        }
+    }
+
+    /// Insert or replace an API token (SQLite only).
+    pub async fn save_api_token(&self, token: &ApiToken) -> Result<()> {
+        match self {
+            Storage::Sqlite(s) => s.save_api_token(token).await,
+            Storage::Helix(_) => Err(ShabkaError::Storage(
+                "API tokens need the SQLite backend".into(),
+            )),
+        }
+    }
+
+    /// Every API token, newest first. Always empty for Helix storage.
+    pub async fn api_tokens(&self) -> Result<Vec<ApiToken>> {
+        match self {
+            Storage::Sqlite(s) => s.api_tokens().await,
+            Storage::Helix(_) => Ok(Vec::new()),
+        }
+    }
+
+    /// Maintenance runs, newest first. Always empty for Helix storage.
+    pub async fn maintenance_runs(
+        &self,
+        operation: Option<MaintenanceOp>,
+        limit: usize,
+    ) -> Result<Vec<MaintenanceRun>> {
+        match self {
+            Storage::Sqlite(s) => s.maintenance_runs(operation, limit).await,
+            Storage::Helix(_) => Ok(Vec::new()),
+        }
+    }
+
+    /// Replace the stored centrality of every memory with `scores`
+    /// (SQLite only).
+    pub async fn save_centrality(&self, scores: &[Centrality]) -> Result<()> {
+        match self {
+            Storage::Sqlite(s) => s.save_centrality(scores).await,
+            Storage::Helix(_) => Err(ShabkaError::Storage(
+                "centrality needs the SQLite backend".into(),
+            )),
+        }
+    }
+
+    /// Every stored centrality, most central first. Always empty for Helix
+    /// storage.
+    pub async fn centrality_ranking(&self) -> Result<Vec<Centrality>> {
+        match self {
+            Storage::Sqlite(s) => s.centrality_ranking().await,
+            Storage::Helix(_) => Ok(Vec::new()),
+        }
+    }
+
+    /// Every stored embedding, keyed by memory ID. Always empty for Helix
+    /// storage, which doesn't expose its vectors.
+    pub async fn stored_embeddings(&self) -> Result<HashMap<Uuid, Vec<f32>>> {
+        match self {
+            Storage::Sqlite(s) => s.stored_embeddings().await,
+            Storage::Helix(_) => Ok(HashMap::new()),
+        }
+    }
+
+    /// The most recent run of `operation` that finished without errors.
+    pub async fn last_successful_run(
+        &self,
+        operation: MaintenanceOp,
+    ) -> Result<Option<MaintenanceRun>> {
+        Ok(self
+            .maintenance_runs(Some(operation), 50)
+            .await?
+            .into_iter()
+            .find(MaintenanceRun::succeeded))
+    }
+
     /// Return the total count of timeline entries matching the given filters,
     /// ignoring `limit` and `offset`. For Helix, falls back to fetching all
     /// entries and counting them.
@@ -154,25 +304,143 @@ impl Storage {
             }
         }
     }
+
+    /// Memories created since `since`, counted per day and project (oldest
+    /// day first). Pending and quarantined memories are left out. SQLite
+    /// groups in SQL; Helix falls back to a timeline scan.
+    pub async fn daily_activity(&self, since: DateTime<Utc>) -> Result<Vec<DailyActivity>> {
+        match self {
+            Storage::Sqlite(s) => s.daily_activity(since).await,
+            Storage::Helix(s) => {
+                let mut counts: BTreeMap<(NaiveDate, Option<String>), usize> = BTreeMap::new();
+                for entry in helix_scan(s).await? {
+                    if entry.created_at >= since {
+                        *counts
+                            .entry((entry.created_at.date_naive(), entry.project_id))
+                            .or_insert(0) += 1;
+                    }
+                }
+                Ok(counts
+                    .into_iter()
+                    .map(|((day, project_id), count)| DailyActivity {
+                        day,
+                        project_id,
+                        count,
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    /// Tag usage per project and tag co-occurrence, for the knowledge map.
+    /// Pending and quarantined memories are left out. SQLite aggregates in
+    /// SQL; Helix falls back to loading every memory.
+    pub async fn tag_aggregates(&self) -> Result<(Vec<TagUsage>, Vec<TagPair>)> {
+        match self {
+            Storage::Sqlite(s) => Ok((s.tag_usage().await?, s.tag_pairs().await?)),
+            Storage::Helix(s) => {
+                let ids: Vec<Uuid> = helix_scan(s).await?.iter().map(|e| e.id).collect();
+                if ids.is_empty() {
+                    return Ok(Default::default());
+                }
+                let memories = s.get_memories(&ids).await?;
+                Ok(crate::tag_map::aggregate(&memories))
+            }
+        }
+    }
+
+    /// Find memories whose ID starts with `prefix` (for short-ID lookups).
+    /// SQLite uses the primary-key index; Helix falls back to a timeline scan.
+    pub async fn find_by_id_prefix(
+        &self,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<TimelineEntry>> {
+        match self {
+            Storage::Sqlite(s) => s.find_by_id_prefix(prefix, limit).await,
+            Storage::Helix(s) => {
+                let prefix = prefix.to_ascii_lowercase();
+                let mut entries = helix_scan(s).await?;
+                entries.retain(|e| e.id.to_string().starts_with(&prefix));
+                entries.truncate(limit);
+                Ok(entries)
+            }
+        }
+    }
+
+    /// Find memories whose title contains every word of `needle`
+    /// (case-insensitive), best matches first.
+    pub async fn find_by_title(&self, needle: &str, limit: usize) -> Result<Vec<TimelineEntry>> {
+        match self {
+            Storage::Sqlite(s) => s.find_by_title(needle, limit).await,
+            Storage::Helix(s) => {
+                let needle = needle.trim().to_lowercase();
+                let words: Vec<&str> = needle.split_whitespace().collect();
+                if words.is_empty() {
+                    return Ok(Vec::new());
+                }
+                let mut entries = helix_scan(s).await?;
+                entries.retain(|e| {
+                    let title = e.title.to_lowercase();
+                    words.iter().all(|w| title.contains(w))
+                });
+                entries.sort_by_key(|e| {
+                    let title = e.title.to_lowercase();
+                    (title != needle, !title.starts_with(&needle))
+                });
+                entries.truncate(limit);
+                Ok(entries)
+            }
+        }
+    }
+}
+
+/// Every memory on a Helix backend, newest first (Helix has no prefix or
+/// text index to push lookups down to).
+async fn helix_scan(storage: &HelixStorage) -> Result<Vec<TimelineEntry>> {
+    storage
+        .timeline(&TimelineQuery {
+            limit: 10_000,
+            ..Default::default()
+        })
+        .await
+}
+
+fn sqlite_options(config: &ShabkaConfig) -> SqliteOptions {
+    SqliteOptions {
+        busy_timeout: std::time::Duration::from_millis(config.storage.busy_timeout_ms),
+        busy_retries: config.storage.busy_retries,
+        writer_lock: config.storage.writer_lock,
+    }
 }
 
 /// Create a storage backend from the given configuration.
 pub fn create_backend(config: &ShabkaConfig) -> Result<Storage> {
     match config.storage.backend.as_str() {
         "sqlite" => {
-            let path = match &config.storage.path {
-                Some(p) => std::path::PathBuf::from(p),
-                None => default_sqlite_path()?,
+            let path = sqlite_path(config)?;
+            let storage = SqliteStorage::open_with(&path, sqlite_options(config))?;
+            Ok(Storage::Sqlite(storage))
+        }
+        "memory" => {
+            let storage = match &config.storage.snapshot_path {
+                Some(path) => {
+                    SqliteStorage::open_in_memory_from_snapshot(path, sqlite_options(config))?
+                }
+                None => SqliteStorage::open_in_memory_with(sqlite_options(config))?,
             };
-            let storage = SqliteStorage::open(&path)?;
             Ok(Storage::Sqlite(storage))
         }
         "helix" => {
-            let storage = HelixStorage::new(
+            let mut storage = HelixStorage::new(
                 Some(&config.helix.url),
                 Some(config.helix.port),
                 config.helix.api_key.as_deref(),
             );
+            if !crate::labels::is_local_url(&config.helix.url) {
+                storage =
+                    storage.withhold_labels(config.labels.withheld(crate::labels::Boundary::Sync));
+            }
             Ok(Storage::Helix(storage))
         }
         other => Err(ShabkaError::Config(format!(
@@ -181,6 +449,15 @@ pub fn create_backend(config: &ShabkaConfig) -> Result<Storage> {
     }
 }
 
+/// The database file of the `sqlite` backend: `storage.path`, or the
+/// default.
+pub fn sqlite_path(config: &ShabkaConfig) -> Result<std::path::PathBuf> {
+    match &config.storage.path {
+        Some(p) => Ok(std::path::PathBuf::from(p)),
+        None => default_sqlite_path(),
+    }
+}
+
 /// Default SQLite path: `~/.config/shabka/shabka.db`
 fn default_sqlite_path() -> Result<std::path::PathBuf> {
     dirs::config_dir()
@@ -1,15 +1,21 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use rusqlite::{params, Connection};
 use uuid::Uuid;
 
 use std::sync::Once;
 
+use crate::api_tokens::ApiToken;
+use crate::centrality::Centrality;
 use crate::error::{Result, ShabkaError};
+use crate::maintenance::{MaintenanceOp, MaintenanceRun};
 use crate::model::*;
-use crate::storage::StorageBackend;
+use crate::storage::{Capabilities, StorageBackend};
+use crate::tag_map::{TagPair, TagUsage};
 
 /// Report from a database integrity check (SQLite only).
 #[derive(Debug, Default)]
@@ -26,7 +32,7 @@ pub struct IntegrityReport {
 
 /// Current schema version. Bump this when adding migrations.
 /// Existing DBs at version 0 get stamped to this on first open.
-const SCHEMA_VERSION: i32 = 1;
+const SCHEMA_VERSION: i32 = 16;
 
 static EXTENSIONS_REGISTERED: Once = Once::new();
 
@@ -93,6 +99,100 @@ fn register_extensions() {
     });
 }
 
+/// Settings for several processes (CLI, hooks, web, MCP) sharing one
+/// database file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqliteOptions {
+    /// How long SQLite waits on a locked database before failing with SQLITE_BUSY.
+    pub busy_timeout: Duration,
+    /// Extra attempts for an operation that still fails with SQLITE_BUSY.
+    pub busy_retries: u32,
+    /// Serialize writes across processes with a lock file next to the database.
+    pub writer_lock: bool,
+}
+
+impl Default for SqliteOptions {
+    fn default() -> Self {
+        Self {
+            busy_timeout: Duration::from_millis(5000),
+            busy_retries: 3,
+            writer_lock: false,
+        }
+    }
+}
+
+/// A writer lock older than this is assumed to belong to a dead process.
+const STALE_WRITER_LOCK: Duration = Duration::from_secs(30);
+
+/// Cross-process writer lock: a `<db>.writer.lock` file created exclusively
+/// and removed on drop.
+struct WriterLock {
+    path: PathBuf,
+}
+
+impl WriterLock {
+    /// Wait up to `timeout` for the lock. Locks left behind by a crashed
+    /// process are broken once they are [`STALE_WRITER_LOCK`] old.
+    fn acquire(path: PathBuf, timeout: Duration) -> Result<Self> {
+        let started = Instant::now();
+        loop {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    use std::io::Write;
+                    let _ = write!(file, "{}", std::process::id());
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let stale = std::fs::metadata(&path)
+                        .and_then(|m| m.modified())
+                        .ok()
+                        .and_then(|t| t.elapsed().ok())
+                        .is_some_and(|age| age > STALE_WRITER_LOCK);
+                    if stale {
+                        tracing::warn!("breaking stale writer lock {}", path.display());
+                        let _ = std::fs::remove_file(&path);
+                        continue;
+                    }
+                    if started.elapsed() >= timeout {
+                        return Err(ShabkaError::Storage(format!(
+                            "database is locked: writer lock {} is held",
+                            path.display()
+                        )));
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => {
+                    return Err(ShabkaError::Storage(format!(
+                        "failed to create writer lock {}: {e}",
+                        path.display()
+                    )))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for WriterLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl Drop for SqliteStorage {
+    fn drop(&mut self) {
+        if let Some(path) = self.snapshot_on_drop.take() {
+            match self.snapshot_to(&path) {
+                Ok(()) => tracing::info!("wrote database snapshot to {}", path.display()),
+                Err(e) => tracing::warn!("failed to snapshot database: {e}"),
+            }
+        }
+    }
+}
+
 /// SQLite-backed storage for Shabka memories.
 ///
 /// Uses a single `Connection` behind `Arc<Mutex<>>` so it can be shared
@@ -102,6 +202,9 @@ fn register_extensions() {
 pub struct SqliteStorage {
     conn: Arc<Mutex<Connection>>,
     path: PathBuf,
+    options: SqliteOptions,
+    /// Written with [`snapshot_to`](Self::snapshot_to) when the storage is dropped.
+    snapshot_on_drop: Option<PathBuf>,
 }
 
 impl SqliteStorage {
@@ -110,22 +213,62 @@ impl SqliteStorage {
     /// Sets WAL journal mode and enables foreign keys, then creates all
     /// tables and indexes if they don't already exist.
     pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with(path, SqliteOptions::default())
+    }
+
+    /// Like [`open`](Self::open), with explicit concurrency settings.
+    pub fn open_with(path: impl AsRef<Path>, options: SqliteOptions) -> Result<Self> {
         register_extensions();
         let path = path.as_ref().to_path_buf();
         let conn = Connection::open(&path)
             .map_err(|e| ShabkaError::Storage(format!("failed to open SQLite database: {e}")))?;
 
-        Self::configure_and_init(conn, path)
+        Self::configure_and_init(conn, path, options)
     }
 
     /// Open an in-memory SQLite database (useful for tests).
     pub fn open_in_memory() -> Result<Self> {
+        Self::open_in_memory_with(SqliteOptions::default())
+    }
+
+    /// Like [`open_in_memory`](Self::open_in_memory), with explicit settings.
+    pub fn open_in_memory_with(options: SqliteOptions) -> Result<Self> {
         register_extensions();
         let conn = Connection::open_in_memory().map_err(|e| {
             ShabkaError::Storage(format!("failed to open in-memory SQLite database: {e}"))
         })?;
 
-        Self::configure_and_init(conn, PathBuf::from(":memory:"))
+        Self::configure_and_init(conn, PathBuf::from(":memory:"), options)
+    }
+
+    /// Open an in-memory database that starts from the snapshot at `path`, if
+    /// there is one, and writes back to it when dropped (see
+    /// [`snapshot_on_drop`](Self::snapshot_on_drop)). Each process builds on
+    /// the memories the previous one left instead of replacing them.
+    pub fn open_in_memory_from_snapshot(
+        path: impl AsRef<Path>,
+        options: SqliteOptions,
+    ) -> Result<Self> {
+        register_extensions();
+        let path = path.as_ref();
+        let mut conn = Connection::open_in_memory().map_err(|e| {
+            ShabkaError::Storage(format!("failed to open in-memory SQLite database: {e}"))
+        })?;
+        if path.exists() {
+            conn.restore(
+                rusqlite::MAIN_DB,
+                path,
+                None::<fn(rusqlite::backup::Progress)>,
+            )
+            .map_err(|e| {
+                ShabkaError::Storage(format!("failed to load snapshot {}: {e}", path.display()))
+            })?;
+        }
+
+        Ok(
+            Self::configure_and_init(conn, PathBuf::from(":memory:"), options)?
+                .snapshot_on_drop(path),
+        )
     }
 
     /// Return the path this database was opened with (`:memory:` for in-memory).
@@ -133,10 +276,56 @@ impl SqliteStorage {
         &self.path
     }
 
+    /// Whether this is an in-memory database.
+    pub fn is_in_memory(&self) -> bool {
+        self.path == Path::new(":memory:")
+    }
+
+    /// Snapshot the database to `path` when this storage is dropped,
+    /// replacing what is there; failures are logged, not raised.
+    ///
+    /// Meant for in-memory databases that should leave a file behind on
+    /// shutdown. Use [`open_in_memory_from_snapshot`](Self::open_in_memory_from_snapshot)
+    /// to keep an earlier snapshot's memories.
+    pub fn snapshot_on_drop(mut self, path: impl Into<PathBuf>) -> Self {
+        self.snapshot_on_drop = Some(path.into());
+        self
+    }
+
+    /// Write a consistent copy of the database to `path`, replacing any
+    /// existing file.
+    pub fn snapshot_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| ShabkaError::Storage(format!("failed to create snapshot dir: {e}")))?;
+        }
+        // VACUUM INTO refuses to overwrite, so write beside the target and rename.
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let _ = std::fs::remove_file(&tmp);
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| ShabkaError::Storage(format!("failed to acquire database lock: {e}")))?;
+        conn.execute("VACUUM INTO ?1", params![tmp.to_string_lossy()])
+            .map_err(|e| ShabkaError::Storage(format!("failed to snapshot database: {e}")))?;
+        drop(conn);
+
+        std::fs::rename(&tmp, path)
+            .map_err(|e| ShabkaError::Storage(format!("failed to write snapshot: {e}")))
+    }
+
     // ── helpers ────────────────────────────────────────────────────────
 
     /// Shared initialisation: pragmas + table creation.
-    fn configure_and_init(conn: Connection, path: PathBuf) -> Result<Self> {
+    fn configure_and_init(conn: Connection, path: PathBuf, options: SqliteOptions) -> Result<Self> {
+        // Wait for other processes' locks instead of failing with SQLITE_BUSY.
+        conn.busy_timeout(options.busy_timeout)
+            .map_err(|e| ShabkaError::Storage(format!("failed to set busy timeout: {e}")))?;
+
         // WAL mode for better concurrent-read performance.
         conn.execute_batch("PRAGMA journal_mode = WAL;")
             .map_err(|e| ShabkaError::Storage(format!("failed to set WAL mode: {e}")))?;
@@ -148,6 +337,8 @@ impl SqliteStorage {
         let storage = Self {
             conn: Arc::new(Mutex::new(conn)),
             path,
+            options,
+            snapshot_on_drop: None,
         };
 
         storage.create_tables()?;
@@ -181,7 +372,17 @@ impl SqliteStorage {
                 created_by TEXT NOT NULL DEFAULT '',
                 created_at TEXT NOT NULL,
                 updated_at TEXT NOT NULL,
-                accessed_at TEXT NOT NULL
+                accessed_at TEXT NOT NULL,
+                attachments TEXT NOT NULL DEFAULT '[]',
+                code TEXT,
+                verification_expires_at TEXT,
+                dispute TEXT,
+                assertions TEXT NOT NULL DEFAULT '[]',
+                label TEXT,
+                device_id TEXT,
+                endorsements TEXT NOT NULL DEFAULT '[]',
+                owner TEXT,
+                confidence REAL
             );
 
             CREATE TABLE IF NOT EXISTS embeddings (
@@ -199,6 +400,12 @@ impl SqliteStorage {
                 UNIQUE(source_id, target_id, relation_type)
             );
 
+            CREATE TABLE IF NOT EXISTS memory_symbols (
+                memory_id TEXT NOT NULL REFERENCES memories(id) ON DELETE CASCADE,
+                symbol TEXT NOT NULL,
+                PRIMARY KEY (memory_id, symbol)
+            );
+
             CREATE TABLE IF NOT EXISTS sessions (
                 id TEXT PRIMARY KEY,
                 project_id TEXT,
@@ -208,12 +415,43 @@ impl SqliteStorage {
                 memory_count INTEGER NOT NULL DEFAULT 0
             );
 
+            CREATE TABLE IF NOT EXISTS maintenance_runs (
+                id TEXT PRIMARY KEY,
+                operation TEXT NOT NULL,
+                started_at TEXT NOT NULL,
+                finished_at TEXT,
+                counts TEXT NOT NULL DEFAULT '{}',
+                errors TEXT NOT NULL DEFAULT '[]',
+                config TEXT NOT NULL DEFAULT 'null'
+            );
+
+            CREATE TABLE IF NOT EXISTS api_tokens (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                hash TEXT NOT NULL UNIQUE,
+                scope TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at TEXT,
+                last_used_at TEXT,
+                revoked_at TEXT,
+                tenant TEXT
+            );
+
+            CREATE TABLE IF NOT EXISTS memory_centrality (
+                memory_id TEXT PRIMARY KEY REFERENCES memories(id) ON DELETE CASCADE,
+                degree INTEGER NOT NULL,
+                pagerank REAL NOT NULL
+            );
+
             CREATE INDEX IF NOT EXISTS idx_memories_created_at ON memories(created_at DESC);
             CREATE INDEX IF NOT EXISTS idx_memories_project_id ON memories(project_id);
             CREATE INDEX IF NOT EXISTS idx_memories_status ON memories(status);
             CREATE INDEX IF NOT EXISTS idx_memories_session_id ON memories(session_id);
             CREATE INDEX IF NOT EXISTS idx_relations_source ON relations(source_id);
             CREATE INDEX IF NOT EXISTS idx_relations_target ON relations(target_id);
+            CREATE INDEX IF NOT EXISTS idx_memory_symbols_symbol ON memory_symbols(symbol);
+            CREATE INDEX IF NOT EXISTS idx_maintenance_runs_operation
+                ON maintenance_runs(operation, started_at DESC);
             ",
         )
         .map_err(|e| ShabkaError::Storage(format!("failed to create tables: {e}")))?;
@@ -347,16 +585,112 @@ impl SqliteStorage {
     /// Run sequential migrations from `from_version` up to `SCHEMA_VERSION`.
     /// Each version bump gets its own match arm.
     #[allow(clippy::needless_range_loop)]
-    fn run_migrations(_conn: &Connection, from_version: i32) -> Result<()> {
+    fn run_migrations(conn: &Connection, from_version: i32) -> Result<()> {
         let mut version = from_version;
         while version < SCHEMA_VERSION {
-            // Future migrations go here:
-            // if version == 1 { conn.execute_batch("ALTER TABLE ...")?; }
+            if version == 1 {
+                Self::add_column_if_missing(
+                    conn,
+                    "memories",
+                    "attachments",
+                    "TEXT NOT NULL DEFAULT '[]'",
+                )?;
+            }
+            if version == 2 {
+                Self::add_column_if_missing(conn, "memories", "code", "TEXT")?;
+            }
+            if version == 3 {
+                Self::reindex_symbols(conn)?;
+            }
+            if version == 4 {
+                Self::add_column_if_missing(conn, "memories", "verification_expires_at", "TEXT")?;
+            }
+            if version == 5 {
+                Self::add_column_if_missing(conn, "memories", "dispute", "TEXT")?;
+            }
+            if version == 6 {
+                Self::add_column_if_missing(
+                    conn,
+                    "memories",
+                    "assertions",
+                    "TEXT NOT NULL DEFAULT '[]'",
+                )?;
+            }
+            if version == 7 {
+                Self::add_column_if_missing(conn, "memories", "label", "TEXT")?;
+            }
+            if version == 8 {
+                Self::add_column_if_missing(conn, "memories", "device_id", "TEXT")?;
+            }
+            // Version 9 -> 10 added `maintenance_runs`, which `create_tables`
+            // creates before migrations run.
+            if version == 10 {
+                Self::add_column_if_missing(
+                    conn,
+                    "memories",
+                    "endorsements",
+                    "TEXT NOT NULL DEFAULT '[]'",
+                )?;
+            }
+            if version == 11 {
+                Self::add_column_if_missing(conn, "memories", "owner", "TEXT")?;
+            }
+            // Version 12 -> 13 added `api_tokens`, also created by
+            // `create_tables`.
+            if version == 13 {
+                Self::add_column_if_missing(conn, "api_tokens", "tenant", "TEXT")?;
+            }
+            if version == 14 {
+                Self::add_column_if_missing(conn, "memories", "confidence", "REAL")?;
+            }
+            // Version 15 -> 16 added `memory_centrality`, also created by
+            // `create_tables`.
             version += 1;
         }
         Ok(())
     }
 
+    /// Rebuild `memory_symbols` from every stored memory.
+    fn reindex_symbols(conn: &Connection) -> Result<()> {
+        let memories = {
+            let mut stmt = conn
+                .prepare("SELECT * FROM memories")
+                .map_err(|e| ShabkaError::Storage(format!("failed to read memories: {e}")))?;
+            let rows = stmt
+                .query_map([], row_to_memory)
+                .map_err(|e| ShabkaError::Storage(format!("failed to read memories: {e}")))?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|e| ShabkaError::Storage(format!("failed to read memory row: {e}")))?
+        };
+        for memory in &memories {
+            index_symbols(conn, memory)?;
+        }
+        tracing::info!(memories = memories.len(), "indexed memory symbols");
+        Ok(())
+    }
+
+    /// `ALTER TABLE ... ADD COLUMN` unless the column already exists.
+    fn add_column_if_missing(
+        conn: &Connection,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> Result<()> {
+        let exists = conn
+            .prepare(&format!(
+                "SELECT 1 FROM pragma_table_info('{table}') WHERE name = ?1"
+            ))
+            .and_then(|mut stmt| stmt.exists(params![column]))
+            .map_err(|e| ShabkaError::Storage(format!("failed to inspect {table}: {e}")))?;
+        if !exists {
+            conn.execute_batch(&format!(
+                "ALTER TABLE {table} ADD COLUMN {column} {definition};"
+            ))
+            .map_err(|e| ShabkaError::Storage(format!("failed to add {table}.{column}: {e}")))?;
+        }
+        Ok(())
+    }
+
     /// Return `(schema_version, last_writer_version)` for status display.
     pub fn schema_info(&self) -> Result<(i32, Option<String>)> {
         let conn = self
@@ -379,6 +713,16 @@ impl SqliteStorage {
         Ok((version, writer))
     }
 
+    /// Column names per table, e.g. to see what another database holds that
+    /// this schema has no place for.
+    pub fn columns(&self) -> Result<BTreeMap<String, BTreeSet<String>>> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| ShabkaError::Storage(format!("failed to acquire database lock: {e}")))?;
+        table_columns(&conn)
+    }
+
     /// Run a full integrity check on the SQLite database.
     ///
     /// Returns an [`IntegrityReport`] with counts, orphaned embeddings,
@@ -504,17 +848,60 @@ impl SqliteStorage {
     /// Run a blocking closure against the SQLite connection on the Tokio
     /// blocking thread-pool.  This is the primary way trait methods will
     /// interact with the database.
+    ///
+    /// If another process holds the database past the busy timeout, the
+    /// closure is re-run up to `busy_retries` times with backoff.
     pub(crate) async fn with_conn<F, T>(&self, f: F) -> Result<T>
     where
-        F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+        F: Fn(&Connection) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        self.run_blocking(None, f).await
+    }
+
+    /// Like [`with_conn`](Self::with_conn) for operations that write. With
+    /// `writer_lock` enabled, each attempt holds the cross-process writer lock.
+    pub(crate) async fn with_write_conn<F, T>(&self, f: F) -> Result<T>
+    where
+        F: Fn(&Connection) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let lock_path = (self.options.writer_lock && !self.is_in_memory()).then(|| {
+            let mut name = self.path.clone().into_os_string();
+            name.push(".writer.lock");
+            PathBuf::from(name)
+        });
+        self.run_blocking(lock_path, f).await
+    }
+
+    async fn run_blocking<F, T>(&self, lock_path: Option<PathBuf>, f: F) -> Result<T>
+    where
+        F: Fn(&Connection) -> Result<T> + Send + 'static,
         T: Send + 'static,
     {
         let conn = Arc::clone(&self.conn);
+        let options = self.options.clone();
         tokio::task::spawn_blocking(move || {
             let conn = conn.lock().map_err(|e| {
                 ShabkaError::Storage(format!("failed to acquire database lock: {e}"))
             })?;
-            f(&conn)
+            let mut attempt = 0;
+            loop {
+                let result = match &lock_path {
+                    Some(path) => WriterLock::acquire(path.clone(), options.busy_timeout)
+                        .and_then(|_lock| f(&conn)),
+                    None => f(&conn),
+                };
+                match result {
+                    Err(e) if e.is_busy() && attempt < options.busy_retries => {
+                        attempt += 1;
+                        let delay = Duration::from_millis(25 * 2u64.pow(attempt));
+                        tracing::debug!(attempt, "database busy, retrying in {delay:?}");
+                        std::thread::sleep(delay);
+                    }
+                    result => return result,
+                }
+            }
         })
         .await
         .map_err(|e| ShabkaError::Storage(format!("task join error: {e}")))?
@@ -533,6 +920,16 @@ fn row_to_memory(row: &rusqlite::Row) -> rusqlite::Result<Memory> {
     let source_json: String = row.get("source")?;
     let scope_json: String = row.get("scope")?;
     let tags_json: String = row.get("tags")?;
+    let attachments_json: String = row.get("attachments")?;
+    let code_json: Option<String> = row.get("code")?;
+    let expires_at_str: Option<String> = row.get("verification_expires_at")?;
+    let dispute_json: Option<String> = row.get("dispute")?;
+    let assertions_json: String = row.get("assertions")?;
+    let endorsements_json: String = row.get("endorsements")?;
+    let label: Option<String> = row.get("label")?;
+    let device_id: Option<String> = row.get("device_id")?;
+    let owner: Option<String> = row.get("owner")?;
+    let confidence: Option<f64> = row.get("confidence")?;
     let created_at_str: String = row.get("created_at")?;
     let updated_at_str: String = row.get("updated_at")?;
     let accessed_at_str: String = row.get("accessed_at")?;
@@ -568,6 +965,16 @@ fn row_to_memory(row: &rusqlite::Row) -> rusqlite::Result<Memory> {
         rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e))
     })?;
 
+    let attachments: Vec<Attachment> = serde_json::from_str(&attachments_json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(18, rusqlite::types::Type::Text, Box::new(e))
+    })?;
+    let code: Option<CodeSnippet> = code_json
+        .map(|json| serde_json::from_str(&json))
+        .transpose()
+        .map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(19, rusqlite::types::Type::Text, Box::new(e))
+        })?;
+
     // UUID fields
     let id = Uuid::parse_str(&id_str).map_err(|e| {
         rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
@@ -596,6 +1003,28 @@ fn row_to_memory(row: &rusqlite::Row) -> rusqlite::Result<Memory> {
             rusqlite::Error::FromSqlConversionFailure(17, rusqlite::types::Type::Text, Box::new(e))
         })?;
 
+    let verification_expires_at: Option<DateTime<Utc>> = expires_at_str
+        .map(|s| DateTime::parse_from_rfc3339(&s).map(|dt| dt.with_timezone(&Utc)))
+        .transpose()
+        .map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(20, rusqlite::types::Type::Text, Box::new(e))
+        })?;
+
+    let dispute: Option<Dispute> = dispute_json
+        .map(|json| serde_json::from_str(&json))
+        .transpose()
+        .map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(21, rusqlite::types::Type::Text, Box::new(e))
+        })?;
+
+    let assertions: Vec<Assertion> = serde_json::from_str(&assertions_json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(22, rusqlite::types::Type::Text, Box::new(e))
+    })?;
+
+    let endorsements: Vec<Endorsement> = serde_json::from_str(&endorsements_json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(25, rusqlite::types::Type::Text, Box::new(e))
+    })?;
+
     // importance stored as f64 in SQLite, coerce to f32
     let importance: f64 = row.get("importance")?;
 
@@ -609,18 +1038,70 @@ fn row_to_memory(row: &rusqlite::Row) -> rusqlite::Result<Memory> {
         source,
         scope,
         importance: importance as f32,
+        confidence: confidence.map(|c| c as f32),
         status,
         privacy,
+        label,
+        device_id,
+        owner,
         verification,
+        verification_expires_at,
+        dispute,
         project_id,
         session_id,
         created_by: row.get("created_by")?,
         created_at,
         updated_at,
         accessed_at,
+        attachments,
+        code,
+        assertions,
+        endorsements,
     })
 }
 
+/// The `(memory_id, degree, pagerank)` columns of a `memory_centrality` row.
+fn row_to_centrality(row: &rusqlite::Row) -> rusqlite::Result<(String, i64, f64)> {
+    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+}
+
+/// Centrality from [`row_to_centrality`] rows, skipping unparseable IDs.
+fn collect_centrality(
+    rows: impl Iterator<Item = rusqlite::Result<(String, i64, f64)>>,
+) -> Result<Vec<Centrality>> {
+    let mut scores = Vec::new();
+    for row in rows {
+        let (id_str, degree, pagerank) =
+            row.map_err(|e| ShabkaError::Storage(format!("failed to read centrality row: {e}")))?;
+        if let Ok(memory_id) = Uuid::parse_str(&id_str) {
+            scores.push(Centrality {
+                memory_id,
+                degree: degree.max(0) as usize,
+                pagerank: pagerank as f32,
+            });
+        }
+    }
+    Ok(scores)
+}
+
+/// Replace a memory's rows in `memory_symbols`.
+fn index_symbols(conn: &Connection, memory: &Memory) -> Result<()> {
+    let id = memory.id.to_string();
+    conn.execute(
+        "DELETE FROM memory_symbols WHERE memory_id = ?1",
+        params![id],
+    )
+    .map_err(|e| ShabkaError::Storage(format!("failed to clear symbols: {e}")))?;
+    let mut stmt = conn
+        .prepare_cached("INSERT OR IGNORE INTO memory_symbols (memory_id, symbol) VALUES (?1, ?2)")
+        .map_err(|e| ShabkaError::Storage(format!("failed to prepare symbol insert: {e}")))?;
+    for key in crate::code::memory_symbols(memory) {
+        stmt.execute(params![id, key])
+            .map_err(|e| ShabkaError::Storage(format!("failed to index symbol: {e}")))?;
+    }
+    Ok(())
+}
+
 /// Serialize a simple serde enum to its snake_case string value (no quotes).
 fn kind_to_str(kind: &MemoryKind) -> String {
     serde_json::to_string(kind)
@@ -653,13 +1134,17 @@ fn verification_to_str(verification: &VerificationStatus) -> String {
 // ── StorageBackend impl ─────────────────────────────────────────────────
 
 impl StorageBackend for SqliteStorage {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::all()
+    }
+
     // -- Memory CRUD --
 
     async fn save_memory(&self, memory: &Memory, embedding: Option<&[f32]>) -> Result<()> {
         let memory = memory.clone();
         let embedding = embedding.map(|e| e.to_vec());
 
-        self.with_conn(move |conn| {
+        self.with_write_conn(move |conn| {
             let tx = conn
                 .unchecked_transaction()
                 .map_err(|e| ShabkaError::Storage(format!("failed to begin transaction: {e}")))?;
@@ -667,8 +1152,10 @@ impl StorageBackend for SqliteStorage {
             tx.execute(
                 "INSERT OR REPLACE INTO memories (id, kind, title, content, summary, tags, source, scope,
                     importance, status, privacy, verification, project_id, session_id,
-                    created_by, created_at, updated_at, accessed_at)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+                    created_by, created_at, updated_at, accessed_at, attachments, code,
+                    verification_expires_at, dispute, assertions, label, device_id, endorsements, owner,
+                    confidence)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28)",
                 params![
                     memory.id.to_string(),
                     kind_to_str(&memory.kind),
@@ -688,11 +1175,22 @@ impl StorageBackend for SqliteStorage {
                     memory.created_at.to_rfc3339(),
                     memory.updated_at.to_rfc3339(),
                     memory.accessed_at.to_rfc3339(),
+                    serde_json::to_string(&memory.attachments).unwrap_or_else(|_| "[]".to_string()),
+                    memory.code.as_ref().and_then(|c| serde_json::to_string(c).ok()),
+                    memory.verification_expires_at.map(|t| t.to_rfc3339()),
+                    memory.dispute.as_ref().and_then(|d| serde_json::to_string(d).ok()),
+                    serde_json::to_string(&memory.assertions).unwrap_or_else(|_| "[]".to_string()),
+                    memory.label,
+                    memory.device_id,
+                    serde_json::to_string(&memory.endorsements).unwrap_or_else(|_| "[]".to_string()),
+                    memory.owner,
+                    memory.confidence.map(|c| c as f64),
                 ],
             )
             .map_err(|e| ShabkaError::Storage(format!("failed to insert memory: {e}")))?;
+            index_symbols(&tx, &memory)?;
 
-            if let Some(emb) = embedding {
+            if let Some(emb) = &embedding {
                 let dimensions = emb.len() as i64;
                 // Serialize f32 vec to little-endian bytes
                 let blob: Vec<u8> = emb.iter().flat_map(|f| f.to_le_bytes()).collect();
@@ -792,7 +1290,7 @@ impl StorageBackend for SqliteStorage {
         let id_str = id.to_string();
         let input = input.clone();
 
-        self.with_conn(move |conn| {
+        self.with_write_conn(move |conn| {
             // Build dynamic SET clause from non-None fields
             let mut set_clauses: Vec<String> = Vec::new();
             let mut param_values: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
@@ -807,6 +1305,24 @@ impl StorageBackend for SqliteStorage {
                 set_clauses.push(format!("content = ?{idx}"));
                 param_values.push(Box::new(content.clone()));
                 idx += 1;
+                // Only a summary derived from the old content follows the
+                // edit; it feeds the embedding text.
+                let derived: Option<bool> = conn
+                    .query_row(
+                        "SELECT content, summary FROM memories WHERE id = ?1",
+                        params![id_str],
+                        |row| {
+                            let old: String = row.get(0)?;
+                            let summary: String = row.get(1)?;
+                            Ok(summary == summarize(&old))
+                        },
+                    )
+                    .ok();
+                if derived == Some(true) {
+                    set_clauses.push(format!("summary = ?{idx}"));
+                    param_values.push(Box::new(summarize(content)));
+                    idx += 1;
+                }
             }
             if let Some(ref tags) = input.tags {
                 set_clauses.push(format!("tags = ?{idx}"));
@@ -839,13 +1355,70 @@ impl StorageBackend for SqliteStorage {
                 set_clauses.push(format!("verification = ?{idx}"));
                 param_values.push(Box::new(verification_to_str(verification)));
                 idx += 1;
+                // Every verification change restarts (or clears) the expiry
+                // and dispute context.
+                set_clauses.push(format!("verification_expires_at = ?{idx}"));
+                param_values.push(Box::new(
+                    input.verification_expires_at.map(|t| t.to_rfc3339()),
+                ));
+                idx += 1;
+                set_clauses.push(format!("dispute = ?{idx}"));
+                param_values.push(Box::new(
+                    input
+                        .dispute
+                        .as_ref()
+                        .and_then(|d| serde_json::to_string(d).ok()),
+                ));
+                idx += 1;
+            }
+            if let Some(ref attachments) = input.attachments {
+                set_clauses.push(format!("attachments = ?{idx}"));
+                param_values.push(Box::new(
+                    serde_json::to_string(attachments).unwrap_or_else(|_| "[]".to_string()),
+                ));
+                idx += 1;
+            }
+            if let Some(ref code) = input.code {
+                set_clauses.push(format!("code = ?{idx}"));
+                param_values.push(Box::new(serde_json::to_string(code).ok()));
+                idx += 1;
+            }
+            if let Some(ref label) = input.label {
+                set_clauses.push(format!("label = ?{idx}"));
+                param_values.push(Box::new((!label.is_empty()).then(|| label.clone())));
+                idx += 1;
+            }
+            if let Some(ref owner) = input.owner {
+                set_clauses.push(format!("owner = ?{idx}"));
+                param_values.push(Box::new((!owner.is_empty()).then(|| owner.clone())));
+                idx += 1;
+            }
+            if let Some(ref assertions) = input.assertions {
+                set_clauses.push(format!("assertions = ?{idx}"));
+                param_values.push(Box::new(
+                    serde_json::to_string(assertions).unwrap_or_else(|_| "[]".to_string()),
+                ));
+                idx += 1;
+            }
+            if let Some(ref endorsements) = input.endorsements {
+                set_clauses.push(format!("endorsements = ?{idx}"));
+                param_values.push(Box::new(
+                    serde_json::to_string(endorsements).unwrap_or_else(|_| "[]".to_string()),
+                ));
+                idx += 1;
+            }
+            if let Some(accessed_at) = input.accessed_at {
+                set_clauses.push(format!("accessed_at = ?{idx}"));
+                param_values.push(Box::new(accessed_at.to_rfc3339()));
+                idx += 1;
             }
 
-            // Always update updated_at
-            let now = Utc::now().to_rfc3339();
-            set_clauses.push(format!("updated_at = ?{idx}"));
-            param_values.push(Box::new(now));
-            idx += 1;
+            if !input.is_access_only() {
+                let now = Utc::now().to_rfc3339();
+                set_clauses.push(format!("updated_at = ?{idx}"));
+                param_values.push(Box::new(now));
+                idx += 1;
+            }
 
             // WHERE id = ?N
             let sql = format!(
@@ -866,19 +1439,24 @@ impl StorageBackend for SqliteStorage {
             }
 
             // Return the updated row
-            conn.query_row(
-                "SELECT * FROM memories WHERE id = ?1",
-                params![id_str],
-                row_to_memory,
-            )
-            .map_err(|e| ShabkaError::Storage(format!("failed to read updated memory: {e}")))
+            let memory = conn
+                .query_row(
+                    "SELECT * FROM memories WHERE id = ?1",
+                    params![id_str],
+                    row_to_memory,
+                )
+                .map_err(|e| ShabkaError::Storage(format!("failed to read updated memory: {e}")))?;
+            if input.title.is_some() || input.content.is_some() || input.code.is_some() {
+                index_symbols(conn, &memory)?;
+            }
+            Ok(memory)
         })
         .await
     }
 
     async fn delete_memory(&self, id: Uuid) -> Result<()> {
         let id_str = id.to_string();
-        self.with_conn(move |conn| {
+        self.with_write_conn(move |conn| {
             // Delete from vec_memories first — vec0 virtual tables don't support
             // ON DELETE CASCADE, so we must clean up explicitly.
             conn.execute(
@@ -935,14 +1513,14 @@ impl StorageBackend for SqliteStorage {
             let query_blob: Vec<u8> = query_vec.iter().flat_map(|f| f.to_le_bytes()).collect();
 
             // KNN search via vec_memories, JOIN with memories for full records.
-            // Exclude Pending memories — they require explicit approval first.
+            // Exclude Pending and Quarantined memories — they require review first.
             let sql = "
                 SELECT m.*, v.distance
                 FROM vec_memories AS v
                 JOIN memories AS m ON m.id = v.memory_id
                 WHERE v.embedding MATCH ?1
                   AND v.k = ?2
-                  AND m.status != 'pending'
+                  AND m.status NOT IN ('pending', 'quarantined')
                 ORDER BY v.distance
             ";
 
@@ -973,6 +1551,137 @@ impl StorageBackend for SqliteStorage {
         .await
     }
 
+    async fn symbol_search(&self, key: &str, limit: usize) -> Result<Vec<Memory>> {
+        let key = key.to_string();
+        self.with_conn(move |conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT m.* FROM memory_symbols AS s
+                     JOIN memories AS m ON m.id = s.memory_id
+                     WHERE s.symbol = ?1 AND m.status NOT IN ('pending', 'quarantined')
+                     ORDER BY m.updated_at DESC
+                     LIMIT ?2",
+                )
+                .map_err(|e| {
+                    ShabkaError::Storage(format!("failed to prepare symbol search: {e}"))
+                })?;
+            let rows = stmt
+                .query_map(params![key, limit as i64], row_to_memory)
+                .map_err(|e| {
+                    ShabkaError::Storage(format!("failed to execute symbol search: {e}"))
+                })?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|e| ShabkaError::Storage(format!("failed to read symbol search row: {e}")))
+        })
+        .await
+    }
+
+    async fn title_typo_search(&self, terms: &[String], limit: usize) -> Result<Vec<Memory>> {
+        let terms: Vec<(String, usize)> = terms
+            .iter()
+            .map(|t| (t.to_lowercase(), crate::ranking::typo_allowance(t)))
+            .filter(|(_, allowance)| *allowance > 0)
+            .collect();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.with_conn(move |conn| {
+            // Split titles into words with a recursive CTE and compare each
+            // to the term with sqlean's fuzzy_damlev (Damerau-Levenshtein).
+            let mut stmt = conn
+                .prepare(
+                    "WITH RECURSIVE words(id, word, rest) AS (
+                         SELECT id, '', lower(title) || ' ' FROM memories
+                         WHERE status NOT IN ('pending', 'quarantined')
+                         UNION ALL
+                         SELECT id, substr(rest, 1, instr(rest, ' ') - 1),
+                                substr(rest, instr(rest, ' ') + 1)
+                         FROM words WHERE rest <> ''
+                     ),
+                     matched AS (
+                         SELECT id, MIN(fuzzy_damlev(?1, word)) AS distance FROM words
+                         WHERE word <> '' AND abs(length(word) - length(?1)) <= ?2
+                         GROUP BY id
+                     )
+                     SELECT m.* FROM matched
+                     JOIN memories AS m ON m.id = matched.id
+                     WHERE matched.distance <= ?2
+                     ORDER BY matched.distance, m.updated_at DESC
+                     LIMIT ?3",
+                )
+                .map_err(|e| ShabkaError::Storage(format!("failed to prepare typo search: {e}")))?;
+
+            let mut found: Vec<Memory> = Vec::new();
+            for (term, allowance) in &terms {
+                let rows = stmt
+                    .query_map(
+                        params![term, *allowance as i64, limit as i64],
+                        row_to_memory,
+                    )
+                    .map_err(|e| {
+                        ShabkaError::Storage(format!("failed to execute typo search: {e}"))
+                    })?;
+                for row in rows {
+                    let memory = row.map_err(|e| {
+                        ShabkaError::Storage(format!("failed to read typo search row: {e}"))
+                    })?;
+                    if !found.iter().any(|m| m.id == memory.id) {
+                        found.push(memory);
+                    }
+                }
+            }
+            found.truncate(limit);
+            Ok(found)
+        })
+        .await
+    }
+
+    async fn literal_search(
+        &self,
+        phrases: &[String],
+        title_terms: &[String],
+        limit: usize,
+    ) -> Result<Vec<Memory>> {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        for phrase in phrases {
+            let n = values.len() + 1;
+            conditions.push(format!(
+                "(m.title LIKE ?{n} ESCAPE '\\' OR m.content LIKE ?{n} ESCAPE '\\')"
+            ));
+            values.push(format!("%{}%", escape_like(phrase)));
+        }
+        for term in title_terms {
+            conditions.push(format!("m.title LIKE ?{} ESCAPE '\\'", values.len() + 1));
+            values.push(format!("%{}%", escape_like(term)));
+        }
+        if conditions.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.with_conn(move |conn| {
+            let sql = format!(
+                "SELECT m.* FROM memories AS m
+                 WHERE m.status NOT IN ('pending', 'quarantined') AND {}
+                 ORDER BY m.updated_at DESC
+                 LIMIT {limit}",
+                conditions.join(" AND ")
+            );
+            let mut stmt = conn.prepare(&sql).map_err(|e| {
+                ShabkaError::Storage(format!("failed to prepare literal search: {e}"))
+            })?;
+            let rows = stmt
+                .query_map(rusqlite::params_from_iter(values.iter()), row_to_memory)
+                .map_err(|e| {
+                    ShabkaError::Storage(format!("failed to execute literal search: {e}"))
+                })?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|e| {
+                    ShabkaError::Storage(format!("failed to read literal search row: {e}"))
+                })
+        })
+        .await
+    }
+
     // -- Timeline --
 
     async fn timeline(&self, query: &TimelineQuery) -> Result<Vec<TimelineEntry>> {
@@ -1019,8 +1728,8 @@ impl StorageBackend for SqliteStorage {
                 params.push(Box::new(status_to_str(status)));
                 idx += 1;
             } else {
-                // Exclude Pending memories by default
-                conditions.push("m.status != 'pending'".to_string());
+                // Exclude Pending and Quarantined memories by default
+                conditions.push("m.status NOT IN ('pending', 'quarantined')".to_string());
             }
             if let Some(ref privacy) = query.privacy {
                 conditions.push(format!("m.privacy = ?{idx}"));
@@ -1082,7 +1791,7 @@ impl StorageBackend for SqliteStorage {
 
     async fn add_relation(&self, relation: &MemoryRelation) -> Result<()> {
         let relation = relation.clone();
-        self.with_conn(move |conn| {
+        self.with_write_conn(move |conn| {
             let rel_type = serde_json::to_string(&relation.relation_type)
                 .unwrap_or_default()
                 .trim_matches('"')
@@ -1142,6 +1851,29 @@ impl StorageBackend for SqliteStorage {
         .await
     }
 
+    async fn remove_relation(
+        &self,
+        source_id: Uuid,
+        target_id: Uuid,
+        relation_type: RelationType,
+    ) -> Result<bool> {
+        self.with_write_conn(move |conn| {
+            let rel_type = serde_json::to_string(&relation_type)
+                .unwrap_or_default()
+                .trim_matches('"')
+                .to_string();
+            let removed = conn
+                .execute(
+                    "DELETE FROM relations
+                     WHERE source_id = ?1 AND target_id = ?2 AND relation_type = ?3",
+                    rusqlite::params![source_id.to_string(), target_id.to_string(), rel_type],
+                )
+                .map_err(|e| ShabkaError::Storage(format!("failed to remove relation: {e}")))?;
+            Ok(removed > 0)
+        })
+        .await
+    }
+
     async fn count_relations(&self, memory_ids: &[Uuid]) -> Result<Vec<(Uuid, usize)>> {
         if memory_ids.is_empty() {
             return Ok(Vec::new());
@@ -1228,24 +1960,52 @@ impl StorageBackend for SqliteStorage {
         .await
     }
 
-    // -- Session --
-
-    async fn save_session(&self, session: &Session) -> Result<()> {
-        let session = session.clone();
+    async fn centrality(&self, memory_ids: &[Uuid]) -> Result<Vec<Centrality>> {
+        if memory_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ids: Vec<String> = memory_ids.iter().map(|id| id.to_string()).collect();
         self.with_conn(move |conn| {
-            conn.execute(
-                "INSERT OR REPLACE INTO sessions (id, project_id, started_at, ended_at, summary, memory_count)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-                rusqlite::params![
-                    session.id.to_string(),
-                    session.project_id,
-                    session.started_at.to_rfc3339(),
-                    session.ended_at.map(|dt| dt.to_rfc3339()),
-                    session.summary,
-                    session.memory_count as i64,
-                ],
-            )
-            .map_err(|e| ShabkaError::Storage(format!("failed to save session: {e}")))?;
+            let placeholders: Vec<String> = (1..=ids.len()).map(|i| format!("?{i}")).collect();
+            let sql = format!(
+                "SELECT memory_id, degree, pagerank FROM memory_centrality
+                 WHERE memory_id IN ({})",
+                placeholders.join(", ")
+            );
+            let params: Vec<&dyn rusqlite::types::ToSql> = ids
+                .iter()
+                .map(|s| s as &dyn rusqlite::types::ToSql)
+                .collect();
+
+            let mut stmt = conn
+                .prepare(&sql)
+                .map_err(|e| ShabkaError::Storage(format!("failed to prepare query: {e}")))?;
+            let rows = stmt
+                .query_map(params.as_slice(), row_to_centrality)
+                .map_err(|e| ShabkaError::Storage(format!("failed to query centrality: {e}")))?;
+            collect_centrality(rows)
+        })
+        .await
+    }
+
+    // -- Session --
+
+    async fn save_session(&self, session: &Session) -> Result<()> {
+        let session = session.clone();
+        self.with_write_conn(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO sessions (id, project_id, started_at, ended_at, summary, memory_count)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                rusqlite::params![
+                    session.id.to_string(),
+                    session.project_id,
+                    session.started_at.to_rfc3339(),
+                    session.ended_at.map(|dt| dt.to_rfc3339()),
+                    session.summary,
+                    session.memory_count as i64,
+                ],
+            )
+            .map_err(|e| ShabkaError::Storage(format!("failed to save session: {e}")))?;
             Ok(())
         })
         .await
@@ -1335,8 +2095,8 @@ impl SqliteStorage {
                 params.push(Box::new(status_to_str(status)));
                 idx += 1;
             } else {
-                // Exclude Pending memories by default
-                conditions.push("m.status != 'pending'".to_string());
+                // Exclude Pending and Quarantined memories by default
+                conditions.push("m.status NOT IN ('pending', 'quarantined')".to_string());
             }
             if let Some(ref privacy) = query.privacy {
                 conditions.push(format!("m.privacy = ?{idx}"));
@@ -1369,6 +2129,471 @@ impl SqliteStorage {
         })
         .await
     }
+
+    /// Memories created since `since`, grouped by UTC day and project.
+    pub async fn daily_activity(&self, since: DateTime<Utc>) -> Result<Vec<DailyActivity>> {
+        let since = since.to_rfc3339();
+        self.with_conn(move |conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT date(created_at) AS day, project_id, COUNT(*) FROM memories
+                     WHERE created_at >= ?1 AND status NOT IN ('pending', 'quarantined')
+                     GROUP BY day, project_id
+                     ORDER BY day, project_id",
+                )
+                .map_err(|e| ShabkaError::Storage(format!("failed to prepare query: {e}")))?;
+            let rows = stmt
+                .query_map(params![since], |row| {
+                    let day: String = row.get(0)?;
+                    let project_id: Option<String> = row.get(1)?;
+                    let count: i64 = row.get(2)?;
+                    Ok((day, project_id, count as usize))
+                })
+                .map_err(|e| ShabkaError::Storage(format!("failed to count activity: {e}")))?;
+
+            let mut activity = Vec::new();
+            for row in rows {
+                let (day, project_id, count) = row.map_err(|e| {
+                    ShabkaError::Storage(format!("failed to read activity row: {e}"))
+                })?;
+                if let Ok(day) = NaiveDate::parse_from_str(&day, "%Y-%m-%d") {
+                    activity.push(DailyActivity {
+                        day,
+                        project_id,
+                        count,
+                    });
+                }
+            }
+            Ok(activity)
+        })
+        .await
+    }
+
+    /// Memories per lowercased tag and project.
+    pub async fn tag_usage(&self) -> Result<Vec<TagUsage>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT lower(t.value) AS tag, m.project_id, COUNT(DISTINCT m.id)
+                     FROM memories m, json_each(m.tags) t
+                     WHERE m.status NOT IN ('pending', 'quarantined')
+                     GROUP BY tag, m.project_id
+                     ORDER BY tag, m.project_id",
+                )
+                .map_err(|e| ShabkaError::Storage(format!("failed to prepare query: {e}")))?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok(TagUsage {
+                        tag: row.get(0)?,
+                        project_id: row.get(1)?,
+                        count: row.get::<_, i64>(2)? as usize,
+                    })
+                })
+                .map_err(|e| ShabkaError::Storage(format!("failed to count tags: {e}")))?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|e| ShabkaError::Storage(format!("failed to read tag row: {e}")))
+        })
+        .await
+    }
+
+    /// Memories per pair of lowercased tags used together.
+    pub async fn tag_pairs(&self) -> Result<Vec<TagPair>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT lower(a.value) AS x, lower(b.value) AS y, COUNT(DISTINCT m.id)
+                     FROM memories m, json_each(m.tags) a, json_each(m.tags) b
+                     WHERE lower(a.value) < lower(b.value)
+                       AND m.status NOT IN ('pending', 'quarantined')
+                     GROUP BY x, y
+                     ORDER BY x, y",
+                )
+                .map_err(|e| ShabkaError::Storage(format!("failed to prepare query: {e}")))?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok(TagPair {
+                        a: row.get(0)?,
+                        b: row.get(1)?,
+                        count: row.get::<_, i64>(2)? as usize,
+                    })
+                })
+                .map_err(|e| ShabkaError::Storage(format!("failed to count tag pairs: {e}")))?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|e| ShabkaError::Storage(format!("failed to read tag pair row: {e}")))
+        })
+        .await
+    }
+
+    /// Memories (any status) whose ID starts with `prefix`, resolved as a
+    /// range scan on the primary key rather than a full-table filter.
+    pub async fn find_by_id_prefix(
+        &self,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<TimelineEntry>> {
+        let lower = prefix.to_ascii_lowercase();
+        // UUID text only contains [0-9a-f-], all of which sort before '~'.
+        let upper = format!("{lower}~");
+        self.with_conn(move |conn| {
+            query_entries(
+                conn,
+                "SELECT m.*,
+                    (SELECT COUNT(*) FROM relations r WHERE r.source_id = m.id) as related_count
+                 FROM memories m
+                 WHERE m.id >= ?1 AND m.id < ?2
+                 ORDER BY m.id
+                 LIMIT ?3",
+                params![lower, upper, limit as i64],
+            )
+        })
+        .await
+    }
+
+    /// Memories (any status) whose title contains every word of `needle`,
+    /// case-insensitively. Exact and prefix title matches rank first, then
+    /// the most recently updated.
+    pub async fn find_by_title(&self, needle: &str, limit: usize) -> Result<Vec<TimelineEntry>> {
+        let words: Vec<String> = needle
+            .split_whitespace()
+            .map(|w| format!("%{}%", escape_like(w)))
+            .collect();
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+        let needle = needle.trim().to_string();
+        self.with_conn(move |conn| {
+            let conditions: Vec<String> = (0..words.len())
+                .map(|i| format!("m.title LIKE ?{} ESCAPE '\\'", i + 3))
+                .collect();
+            let sql = format!(
+                "SELECT m.*,
+                    (SELECT COUNT(*) FROM relations r WHERE r.source_id = m.id) as related_count
+                 FROM memories m
+                 WHERE {}
+                 ORDER BY (m.title = ?1 COLLATE NOCASE) DESC,
+                          (m.title LIKE ?2 ESCAPE '\\') DESC,
+                          m.updated_at DESC
+                 LIMIT {limit}",
+                conditions.join(" AND ")
+            );
+            let mut values: Vec<String> =
+                vec![needle.clone(), format!("{}%", escape_like(&needle))];
+            values.extend(words.iter().cloned());
+            query_entries(conn, &sql, rusqlite::params_from_iter(values))
+        })
+        .await
+    }
+
+    /// Insert or replace a maintenance run record.
+    pub async fn record_maintenance_run(&self, run: &MaintenanceRun) -> Result<()> {
+        let run = run.clone();
+        self.with_write_conn(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO maintenance_runs
+                    (id, operation, started_at, finished_at, counts, errors, config)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    run.id.to_string(),
+                    run.operation.as_str(),
+                    run.started_at.to_rfc3339(),
+                    run.finished_at.map(|at| at.to_rfc3339()),
+                    serde_json::to_string(&run.counts)?,
+                    serde_json::to_string(&run.errors)?,
+                    serde_json::to_string(&run.config)?,
+                ],
+            )
+            .map_err(|e| ShabkaError::Storage(format!("failed to record maintenance run: {e}")))?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Replace every stored centrality with `scores`.
+    pub async fn save_centrality(&self, scores: &[Centrality]) -> Result<()> {
+        let scores = scores.to_vec();
+        self.with_write_conn(move |conn| {
+            let tx = conn
+                .unchecked_transaction()
+                .map_err(|e| ShabkaError::Storage(format!("failed to begin transaction: {e}")))?;
+            tx.execute("DELETE FROM memory_centrality", [])
+                .map_err(|e| ShabkaError::Storage(format!("failed to clear centrality: {e}")))?;
+            for score in &scores {
+                tx.execute(
+                    "INSERT OR REPLACE INTO memory_centrality (memory_id, degree, pagerank)
+                     VALUES (?1, ?2, ?3)",
+                    params![
+                        score.memory_id.to_string(),
+                        score.degree as i64,
+                        score.pagerank as f64,
+                    ],
+                )
+                .map_err(|e| ShabkaError::Storage(format!("failed to save centrality: {e}")))?;
+            }
+            tx.commit()
+                .map_err(|e| ShabkaError::Storage(format!("failed to commit centrality: {e}")))?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Every stored centrality, most central first.
+    pub async fn centrality_ranking(&self) -> Result<Vec<Centrality>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT memory_id, degree, pagerank FROM memory_centrality
+                     ORDER BY pagerank DESC, degree DESC",
+                )
+                .map_err(|e| ShabkaError::Storage(format!("failed to prepare query: {e}")))?;
+            let rows = stmt
+                .query_map([], row_to_centrality)
+                .map_err(|e| ShabkaError::Storage(format!("failed to query centrality: {e}")))?;
+            collect_centrality(rows)
+        })
+        .await
+    }
+
+    /// Insert or replace an API token record.
+    pub async fn save_api_token(&self, token: &ApiToken) -> Result<()> {
+        let token = token.clone();
+        self.with_write_conn(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO api_tokens
+                    (id, name, hash, scope, created_at, expires_at, last_used_at, revoked_at,
+                     tenant)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    token.id.to_string(),
+                    token.name,
+                    token.hash,
+                    token.scope.as_str(),
+                    token.created_at.to_rfc3339(),
+                    token.expires_at.map(|at| at.to_rfc3339()),
+                    token.last_used_at.map(|at| at.to_rfc3339()),
+                    token.revoked_at.map(|at| at.to_rfc3339()),
+                    token.tenant,
+                ],
+            )
+            .map_err(|e| ShabkaError::Storage(format!("failed to save API token: {e}")))?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Every API token, newest first.
+    pub async fn api_tokens(&self) -> Result<Vec<ApiToken>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, name, hash, scope, created_at, expires_at, last_used_at, revoked_at,
+                            tenant
+                     FROM api_tokens
+                     ORDER BY created_at DESC, id DESC",
+                )
+                .map_err(|e| ShabkaError::Storage(format!("failed to prepare query: {e}")))?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get::<_, Option<String>>(5)?,
+                        row.get::<_, Option<String>>(6)?,
+                        row.get::<_, Option<String>>(7)?,
+                        row.get::<_, Option<String>>(8)?,
+                    ))
+                })
+                .map_err(|e| ShabkaError::Storage(format!("failed to query API tokens: {e}")))?;
+
+            let mut tokens = Vec::new();
+            for row in rows {
+                let (
+                    id,
+                    name,
+                    hash,
+                    scope,
+                    created_at,
+                    expires_at,
+                    last_used_at,
+                    revoked_at,
+                    tenant,
+                ) = row
+                    .map_err(|e| ShabkaError::Storage(format!("failed to read API token: {e}")))?;
+                let Ok(scope) = scope.parse() else {
+                    continue;
+                };
+                tokens.push(ApiToken {
+                    id: Uuid::parse_str(&id).unwrap_or_default(),
+                    name,
+                    scope,
+                    created_at: parse_timestamp(&created_at),
+                    expires_at: expires_at.as_deref().map(parse_timestamp),
+                    last_used_at: last_used_at.as_deref().map(parse_timestamp),
+                    revoked_at: revoked_at.as_deref().map(parse_timestamp),
+                    tenant,
+                    hash,
+                });
+            }
+            Ok(tokens)
+        })
+        .await
+    }
+
+    /// Maintenance runs, newest first, optionally of one operation only.
+    pub async fn maintenance_runs(
+        &self,
+        operation: Option<MaintenanceOp>,
+        limit: usize,
+    ) -> Result<Vec<MaintenanceRun>> {
+        self.with_conn(move |conn| {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT id, operation, started_at, finished_at, counts, errors, config
+                     FROM maintenance_runs
+                     WHERE ?1 IS NULL OR operation = ?1
+                     ORDER BY started_at DESC, id DESC
+                     LIMIT {limit}"
+                ))
+                .map_err(|e| ShabkaError::Storage(format!("failed to prepare query: {e}")))?;
+            let rows = stmt
+                .query_map(params![operation.map(MaintenanceOp::as_str)], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get::<_, String>(5)?,
+                        row.get::<_, String>(6)?,
+                    ))
+                })
+                .map_err(|e| {
+                    ShabkaError::Storage(format!("failed to query maintenance runs: {e}"))
+                })?;
+
+            let mut runs = Vec::new();
+            for row in rows {
+                let (id, operation, started_at, finished_at, counts, errors, config) = row
+                    .map_err(|e| {
+                        ShabkaError::Storage(format!("failed to read maintenance run: {e}"))
+                    })?;
+                let Ok(operation) = operation.parse() else {
+                    continue;
+                };
+                runs.push(MaintenanceRun {
+                    id: Uuid::parse_str(&id).unwrap_or_default(),
+                    operation,
+                    started_at: parse_timestamp(&started_at),
+                    finished_at: finished_at.as_deref().map(parse_timestamp),
+                    counts: serde_json::from_str(&counts).unwrap_or_default(),
+                    errors: serde_json::from_str(&errors).unwrap_or_default(),
+                    config: serde_json::from_str(&config).unwrap_or_default(),
+                });
+            }
+            Ok(runs)
+        })
+        .await
+    }
+
+    /// Every stored embedding, keyed by memory ID. Lets callers compare
+    /// vectors locally instead of re-embedding and searching one by one.
+    pub async fn stored_embeddings(&self) -> Result<HashMap<Uuid, Vec<f32>>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare("SELECT memory_id, vector FROM embeddings")
+                .map_err(|e| ShabkaError::Storage(format!("failed to prepare query: {e}")))?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
+                })
+                .map_err(|e| ShabkaError::Storage(format!("failed to query embeddings: {e}")))?;
+
+            let mut embeddings = HashMap::new();
+            for row in rows {
+                let (id, blob) = row
+                    .map_err(|e| ShabkaError::Storage(format!("failed to read embedding: {e}")))?;
+                let Ok(id) = Uuid::parse_str(&id) else {
+                    continue;
+                };
+                // Stored as little-endian f32s (see save_memory).
+                let vector = blob
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect();
+                embeddings.insert(id, vector);
+            }
+            Ok(embeddings)
+        })
+        .await
+    }
+}
+
+/// Parse a stored RFC 3339 timestamp, falling back to the epoch.
+/// Column names of every ordinary table in `conn`. Tables that can't be
+/// inspected, such as virtual tables whose module isn't loaded, are skipped.
+pub(crate) fn table_columns(conn: &Connection) -> Result<BTreeMap<String, BTreeSet<String>>> {
+    let tables: Vec<String> = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()
+        })
+        .map_err(|e| ShabkaError::Storage(format!("failed to list tables: {e}")))?;
+
+    let mut columns = BTreeMap::new();
+    for table in tables {
+        let names = conn
+            .prepare(&format!(
+                "SELECT name FROM pragma_table_info('{}')",
+                table.replace('\'', "''")
+            ))
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| row.get(0))?
+                    .collect::<rusqlite::Result<BTreeSet<String>>>()
+            });
+        match names {
+            Ok(names) => {
+                columns.insert(table, names);
+            }
+            Err(e) => tracing::debug!("sqlite: skipping columns of {table}: {e}"),
+        }
+    }
+    Ok(columns)
+}
+
+fn parse_timestamp(s: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_default()
+}
+
+/// Escape `%`, `_` and `\` for use in a `LIKE ... ESCAPE '\'` pattern.
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Run a `SELECT m.*, related_count` query and map rows to timeline entries.
+fn query_entries(
+    conn: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+) -> Result<Vec<TimelineEntry>> {
+    let mut stmt = conn
+        .prepare(sql)
+        .map_err(|e| ShabkaError::Storage(format!("failed to prepare lookup query: {e}")))?;
+    let rows = stmt
+        .query_map(params, |row| {
+            let memory = row_to_memory(row)?;
+            let related_count: i64 = row.get("related_count")?;
+            Ok(TimelineEntry::from((&memory, related_count as usize)))
+        })
+        .map_err(|e| ShabkaError::Storage(format!("failed to run lookup query: {e}")))?;
+    rows.collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| ShabkaError::Storage(format!("failed to read lookup row: {e}")))
 }
 
 #[cfg(test)]
@@ -1391,15 +2616,25 @@ mod tests {
             source: MemorySource::Manual,
             scope: MemoryScope::Global,
             importance: 0.7,
+            confidence: None,
             status: MemoryStatus::Active,
             privacy: MemoryPrivacy::Private,
+            label: None,
+            device_id: None,
+            owner: None,
             verification: VerificationStatus::Unverified,
+            verification_expires_at: None,
+            dispute: None,
             project_id: None,
             session_id: None,
             created_by: "tester".to_string(),
             created_at: Utc::now(),
             updated_at: Utc::now(),
             accessed_at: Utc::now(),
+            attachments: Vec::new(),
+            code: None,
+            assertions: Vec::new(),
+            endorsements: Vec::new(),
         }
     }
 
@@ -1623,6 +2858,41 @@ mod tests {
         assert!(updated.updated_at >= mem.updated_at);
     }
 
+    #[tokio::test]
+    async fn test_update_content_refreshes_derived_summary() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let derived = Memory::new("t".into(), "Old".into(), MemoryKind::Fact, "u".into());
+        let custom = test_memory();
+        storage.save_memory(&derived, None).await.unwrap();
+        storage.save_memory(&custom, None).await.unwrap();
+
+        let input = UpdateMemoryInput {
+            content: Some("New".to_string()),
+            ..Default::default()
+        };
+        let updated = storage.update_memory(derived.id, &input).await.unwrap();
+        assert_eq!(updated.summary, "New");
+        let updated = storage.update_memory(custom.id, &input).await.unwrap();
+        assert_eq!(updated.summary, custom.summary);
+    }
+
+    #[tokio::test]
+    async fn test_update_access_only_keeps_updated_at() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let mem = test_memory();
+        storage.save_memory(&mem, None).await.unwrap();
+        let before = storage.get_memory(mem.id).await.unwrap();
+
+        let accessed = before.accessed_at + chrono::Duration::days(3);
+        let input = UpdateMemoryInput {
+            accessed_at: Some(accessed),
+            ..Default::default()
+        };
+        let updated = storage.update_memory(mem.id, &input).await.unwrap();
+        assert_eq!(updated.accessed_at, accessed);
+        assert_eq!(updated.updated_at, before.updated_at);
+    }
+
     #[tokio::test]
     async fn test_delete_memory() {
         let storage = SqliteStorage::open_in_memory().unwrap();
@@ -1832,6 +3102,114 @@ mod tests {
         assert!((relations[0].strength - 0.8).abs() < f32::EPSILON);
     }
 
+    #[tokio::test]
+    async fn test_maintenance_runs() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let mut prune = MaintenanceRun::start(
+            MaintenanceOp::Prune,
+            serde_json::json!({ "inactive_days": 90 }),
+        );
+        prune.count("archived", 3);
+        prune.finish();
+        storage.record_maintenance_run(&prune).await.unwrap();
+
+        let mut check = MaintenanceRun::start(MaintenanceOp::Check, serde_json::Value::Null);
+        storage.record_maintenance_run(&check).await.unwrap();
+        check.error("integrity check failed");
+        check.finish();
+        storage.record_maintenance_run(&check).await.unwrap();
+
+        let all = storage.maintenance_runs(None, 10).await.unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].id, check.id, "newest first");
+        assert_eq!(all[0].errors, vec!["integrity check failed"]);
+
+        let prunes = storage
+            .maintenance_runs(Some(MaintenanceOp::Prune), 10)
+            .await
+            .unwrap();
+        assert_eq!(prunes.len(), 1);
+        assert_eq!(prunes[0].counts["archived"], 3);
+        assert_eq!(prunes[0].config["inactive_days"], 90);
+        assert!(prunes[0].succeeded());
+    }
+
+    #[tokio::test]
+    async fn test_centrality_round_trip() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let (hub, leaf) = (test_memory(), test_memory());
+        storage.save_memory(&hub, None).await.unwrap();
+        storage.save_memory(&leaf, None).await.unwrap();
+        let score = |memory: &Memory, degree, pagerank| Centrality {
+            memory_id: memory.id,
+            degree,
+            pagerank,
+        };
+        storage
+            .save_centrality(&[score(&leaf, 1, 0.4), score(&hub, 3, 1.0)])
+            .await
+            .unwrap();
+        let ranking = storage.centrality_ranking().await.unwrap();
+        assert_eq!(ranking, vec![score(&hub, 3, 1.0), score(&leaf, 1, 0.4)]);
+
+        // A refresh replaces the previous scores; deleted memories drop out.
+        storage
+            .save_centrality(&[score(&leaf, 2, 1.0)])
+            .await
+            .unwrap();
+        assert!(storage.centrality(&[hub.id]).await.unwrap().is_empty());
+        storage.delete_memory(leaf.id).await.unwrap();
+        assert!(storage.centrality_ranking().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stored_embeddings() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let with = test_memory();
+        let without = test_memory();
+        storage
+            .save_memory(&with, Some(&[0.5, -1.0, 2.0]))
+            .await
+            .unwrap();
+        storage.save_memory(&without, None).await.unwrap();
+
+        let embeddings = storage.stored_embeddings().await.unwrap();
+        assert_eq!(embeddings.len(), 1);
+        assert_eq!(embeddings[&with.id], vec![0.5, -1.0, 2.0]);
+    }
+
+    #[tokio::test]
+    async fn test_remove_relation() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let m1 = test_memory();
+        let m2 = test_memory();
+        storage.save_memory(&m1, None).await.unwrap();
+        storage.save_memory(&m2, None).await.unwrap();
+        for relation_type in [RelationType::Supersedes, RelationType::Related] {
+            storage
+                .add_relation(&MemoryRelation {
+                    source_id: m1.id,
+                    target_id: m2.id,
+                    relation_type,
+                    strength: 1.0,
+                })
+                .await
+                .unwrap();
+        }
+
+        assert!(storage
+            .remove_relation(m1.id, m2.id, RelationType::Supersedes)
+            .await
+            .unwrap());
+        assert!(!storage
+            .remove_relation(m1.id, m2.id, RelationType::Supersedes)
+            .await
+            .unwrap());
+        let relations = storage.get_relations(m1.id).await.unwrap();
+        assert_eq!(relations.len(), 1);
+        assert_eq!(relations[0].relation_type, RelationType::Related);
+    }
+
     #[tokio::test]
     async fn test_count_relations() {
         let storage = SqliteStorage::open_in_memory().unwrap();
@@ -2024,6 +3402,353 @@ mod tests {
         assert_eq!(writer.unwrap(), env!("CARGO_PKG_VERSION"));
     }
 
+    #[tokio::test]
+    async fn test_in_memory_snapshot_on_drop() {
+        let dir = std::env::temp_dir().join(format!("shabka-snapshot-{}", Uuid::now_v7()));
+        let path = dir.join("snapshot.db");
+        let storage = SqliteStorage::open_in_memory()
+            .unwrap()
+            .snapshot_on_drop(&path);
+        assert!(storage.is_in_memory());
+        let memory = test_memory();
+        storage.save_memory(&memory, None).await.unwrap();
+        drop(storage);
+
+        let reopened = SqliteStorage::open(&path).unwrap();
+        assert_eq!(
+            reopened.get_memory(memory.id).await.unwrap().title,
+            memory.title
+        );
+        drop(reopened);
+
+        // A second snapshot replaces the first.
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        storage.snapshot_to(&path).unwrap();
+        let reopened = SqliteStorage::open(&path).unwrap();
+        assert!(reopened.get_memory(memory.id).await.is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_snapshot_keeps_earlier_processes_memories() {
+        let dir = std::env::temp_dir().join(format!("shabka-snapshot-{}", Uuid::now_v7()));
+        let path = dir.join("snapshot.db");
+        let first = test_memory();
+        let second = test_memory();
+        for memory in [&first, &second] {
+            let storage =
+                SqliteStorage::open_in_memory_from_snapshot(&path, SqliteOptions::default())
+                    .unwrap();
+            assert!(storage.is_in_memory());
+            storage.save_memory(memory, None).await.unwrap();
+            drop(storage);
+        }
+
+        let reopened =
+            SqliteStorage::open_in_memory_from_snapshot(&path, SqliteOptions::default()).unwrap();
+        assert!(reopened.get_memory(first.id).await.is_ok());
+        assert!(reopened.get_memory(second.id).await.is_ok());
+        drop(reopened);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        assert_eq!(storage.capabilities(), Capabilities::all());
+        assert!(storage.capabilities().unavailable().is_empty());
+
+        let helix = crate::storage::HelixStorage::new(None, None, None);
+        assert!(!helix.capabilities().integrity_check);
+        assert_eq!(helix.capabilities().unavailable().len(), 20);
+    }
+
+    #[tokio::test]
+    async fn test_code_snippet_roundtrip() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let code = CodeSnippet::from_edit("src/pool.rs", "fn max_size() -> u32 { 16 }").unwrap();
+        let memory = Memory::new("t".into(), "c".into(), MemoryKind::Decision, "u".into())
+            .with_code(code.clone());
+        storage.save_memory(&memory, None).await.unwrap();
+        assert_eq!(
+            storage.get_memory(memory.id).await.unwrap().code,
+            Some(code)
+        );
+
+        let plain = Memory::new("p".into(), "c".into(), MemoryKind::Decision, "u".into());
+        storage.save_memory(&plain, None).await.unwrap();
+        assert_eq!(storage.get_memory(plain.id).await.unwrap().code, None);
+
+        let replaced = CodeSnippet::from_edit("app.py", "def main():\n    pass").unwrap();
+        let updated = storage
+            .update_memory(
+                plain.id,
+                &UpdateMemoryInput {
+                    code: Some(replaced.clone()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated.code, Some(replaced));
+    }
+
+    #[tokio::test]
+    async fn test_verification_expiry_roundtrip() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let memory = Memory::new("t".into(), "c".into(), MemoryKind::Fact, "u".into());
+        storage.save_memory(&memory, None).await.unwrap();
+
+        let expires = Utc::now() + chrono::Duration::days(90);
+        let verified = storage
+            .update_memory(
+                memory.id,
+                &UpdateMemoryInput {
+                    verification: Some(VerificationStatus::Verified),
+                    verification_expires_at: Some(expires),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(verified.verification_expires_at, Some(expires));
+
+        // Other edits keep the expiry; a new status replaces it.
+        let retitled = storage
+            .update_memory(
+                memory.id,
+                &UpdateMemoryInput {
+                    title: Some("renamed".into()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(retitled.verification_expires_at, Some(expires));
+        let disputed = storage
+            .update_memory(
+                memory.id,
+                &UpdateMemoryInput {
+                    verification: Some(VerificationStatus::Disputed),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(disputed.verification_expires_at, None);
+    }
+
+    #[tokio::test]
+    async fn test_dispute_roundtrip() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let memory = Memory::new("t".into(), "c".into(), MemoryKind::Fact, "u".into());
+        storage.save_memory(&memory, None).await.unwrap();
+
+        let dispute = Dispute::new("reviewer".into())
+            .with_reason(Some("  Measured otherwise  ".into()))
+            .with_evidence(Some(Uuid::now_v7()));
+        let disputed = storage
+            .update_memory(
+                memory.id,
+                &UpdateMemoryInput {
+                    verification: Some(VerificationStatus::Disputed),
+                    dispute: Some(dispute.clone()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(disputed.dispute, Some(dispute));
+        assert_eq!(
+            disputed.dispute.unwrap().reason.as_deref(),
+            Some("Measured otherwise")
+        );
+
+        let verified = storage
+            .update_memory(
+                memory.id,
+                &UpdateMemoryInput {
+                    verification: Some(VerificationStatus::Verified),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(verified.dispute, None);
+    }
+
+    #[tokio::test]
+    async fn test_title_typo_search() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let auth = Memory {
+            title: "Authentication uses JWT tokens".to_string(),
+            ..test_memory()
+        };
+        let deploy = Memory {
+            title: "Deploy window".to_string(),
+            ..test_memory()
+        };
+        storage.save_memory(&auth, None).await.unwrap();
+        storage.save_memory(&deploy, None).await.unwrap();
+
+        let found = storage
+            .title_typo_search(&["authetication".to_string()], 10)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, auth.id);
+
+        // Short terms are too ambiguous to match with typos.
+        let found = storage
+            .title_typo_search(&["jwx".to_string()], 10)
+            .await
+            .unwrap();
+        assert!(found.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_literal_search() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let error = Memory {
+            title: "Pool timeout in worker".to_string(),
+            content: "Error: connection reset by peer (100% of retries)".to_string(),
+            ..test_memory()
+        };
+        storage.save_memory(&error, None).await.unwrap();
+        storage.save_memory(&test_memory(), None).await.unwrap();
+
+        let phrase = |p: &str| vec![p.to_string()];
+        let found = storage
+            .literal_search(&phrase("Connection Reset"), &[], 10)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, error.id);
+        // LIKE wildcards in the phrase are literal.
+        assert_eq!(
+            storage
+                .literal_search(&phrase("100% of"), &[], 10)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(storage
+            .literal_search(&phrase("100%retries"), &[], 10)
+            .await
+            .unwrap()
+            .is_empty());
+        // title: terms ignore the content.
+        assert!(storage
+            .literal_search(&[], &phrase("reset"), 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_symbol_index_follows_updates() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let memory = Memory::new(
+            "Pool fix".into(),
+            "Raised the limit in open_pool".into(),
+            MemoryKind::Fix,
+            "u".into(),
+        );
+        storage.save_memory(&memory, None).await.unwrap();
+        assert_eq!(
+            storage.symbol_search("open_pool", 10).await.unwrap().len(),
+            1
+        );
+
+        storage
+            .update_memory(
+                memory.id,
+                &UpdateMemoryInput {
+                    content: Some("Now handled by `PoolBuilder`".into()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(storage
+            .symbol_search("open_pool", 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            storage
+                .symbol_search("pool_builder", 10)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        storage.delete_memory(memory.id).await.unwrap();
+        assert!(storage
+            .symbol_search("pool_builder", 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_migration_backfills_symbol_index() {
+        let path = std::env::temp_dir().join(format!("shabka-migrate-{}.db", Uuid::now_v7()));
+        let memory = Memory::new(
+            "Config".into(),
+            "parse_config ignores env".into(),
+            MemoryKind::Error,
+            "u".into(),
+        );
+        {
+            let storage = SqliteStorage::open(&path).unwrap();
+            storage.save_memory(&memory, None).await.unwrap();
+        }
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch("DELETE FROM memory_symbols; PRAGMA user_version = 3;")
+                .unwrap();
+        }
+        let storage = SqliteStorage::open(&path).unwrap();
+        let found = storage.symbol_search("parse_config", 10).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, memory.id);
+        drop(storage);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_migration_adds_attachments_column() {
+        let path = std::env::temp_dir().join(format!("shabka-migrate-{}.db", Uuid::now_v7()));
+        // Recreate a v1 database: same schema, without the attachments column.
+        drop(SqliteStorage::open(&path).unwrap());
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "ALTER TABLE memories DROP COLUMN attachments;
+                 PRAGMA user_version = 1;",
+            )
+            .unwrap();
+        }
+        let storage = SqliteStorage::open(&path).unwrap();
+        assert_eq!(storage.schema_info().unwrap().0, SCHEMA_VERSION);
+        let conn = storage.conn.lock().unwrap();
+        let has_column: bool = conn
+            .query_row(
+                "SELECT COUNT(*) FROM pragma_table_info('memories') WHERE name = 'attachments'",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .unwrap()
+            == 1;
+        assert!(has_column);
+        drop(conn);
+        drop(storage);
+        let _ = std::fs::remove_file(&path);
+    }
+
     #[test]
     fn test_metadata_table_exists() {
         let storage = SqliteStorage::open_in_memory().unwrap();
@@ -2060,6 +3785,138 @@ mod tests {
         // We have 5 items ordered newest-first, offset 2 gives items at index 2,3
     }
 
+    #[tokio::test]
+    async fn test_find_by_id_prefix() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let mut pending = test_memory();
+        pending.status = MemoryStatus::Pending;
+        let other = test_memory();
+        storage.save_memory(&pending, None).await.unwrap();
+        storage.save_memory(&other, None).await.unwrap();
+
+        let id = pending.id.to_string();
+        let found = storage.find_by_id_prefix(&id[..30], 10).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, pending.id, "pending memories are resolvable");
+
+        let upper = storage
+            .find_by_id_prefix(&id[..30].to_uppercase(), 10)
+            .await
+            .unwrap();
+        assert_eq!(upper.len(), 1);
+
+        // UUIDv7 IDs created together share a timestamp prefix
+        let both = storage.find_by_id_prefix(&id[..4], 10).await.unwrap();
+        assert_eq!(both.len(), 2);
+        assert!(storage
+            .find_by_id_prefix("zzzz", 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_find_by_title() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        for title in [
+            "Connection pool exhaustion under load",
+            "Pool exhaustion",
+            "Thread pool sizing",
+            "100% CPU in worker_loop",
+        ] {
+            let mut mem = test_memory();
+            mem.title = title.to_string();
+            storage.save_memory(&mem, None).await.unwrap();
+        }
+
+        let found = storage.find_by_title("pool exhaustion", 10).await.unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].title, "Pool exhaustion", "exact match first");
+
+        let found = storage
+            .find_by_title("EXHAUSTION connection", 10)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1, "words match in any order, any case");
+
+        let found = storage.find_by_title("100%", 10).await.unwrap();
+        assert_eq!(found.len(), 1);
+        let found = storage.find_by_title("worker_", 10).await.unwrap();
+        assert_eq!(found.len(), 1);
+        let found = storage.find_by_title("worker_x", 10).await.unwrap();
+        assert!(found.is_empty(), "LIKE wildcards are escaped");
+        assert!(storage.find_by_title("  ", 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_daily_activity() {
+        use chrono::Datelike;
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let day = |d: u32| {
+            chrono::NaiveDate::from_ymd_opt(2026, 3, d)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap()
+                .and_utc()
+        };
+        for (created, project, status) in [
+            (day(1), Some("api"), MemoryStatus::Active),
+            (day(1), Some("api"), MemoryStatus::Archived),
+            (day(1), None, MemoryStatus::Active),
+            (day(3), Some("api"), MemoryStatus::Active),
+            (day(3), Some("api"), MemoryStatus::Pending),
+            (
+                day(1) - chrono::Duration::days(30),
+                Some("api"),
+                MemoryStatus::Active,
+            ),
+        ] {
+            let mut mem = test_memory();
+            mem.created_at = created;
+            mem.project_id = project.map(String::from);
+            mem.status = status;
+            storage.save_memory(&mem, None).await.unwrap();
+        }
+
+        let activity = storage
+            .daily_activity(day(1) - chrono::Duration::days(1))
+            .await
+            .unwrap();
+        let counts: Vec<(u32, Option<&str>, usize)> = activity
+            .iter()
+            .map(|a| (a.day.day(), a.project_id.as_deref(), a.count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![(1, None, 1), (1, Some("api"), 2), (3, Some("api"), 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tag_aggregates_match_in_memory_count() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let mut memories = Vec::new();
+        for (project, tags) in [
+            (Some("api"), vec!["Auth", "jwt", "auth"]),
+            (Some("api"), vec!["auth", "jwt"]),
+            (None, vec!["auth"]),
+        ] {
+            let mut mem = test_memory();
+            mem.project_id = project.map(String::from);
+            mem.tags = tags.into_iter().map(String::from).collect();
+            storage.save_memory(&mem, None).await.unwrap();
+            memories.push(mem);
+        }
+        let mut pending = test_memory();
+        pending.status = MemoryStatus::Pending;
+        storage.save_memory(&pending, None).await.unwrap();
+
+        let (usage, pairs) = crate::tag_map::aggregate(&memories);
+        assert_eq!(storage.tag_usage().await.unwrap(), usage);
+        assert_eq!(storage.tag_pairs().await.unwrap(), pairs);
+        assert_eq!(pairs[0].count, 2);
+    }
+
     #[tokio::test]
     async fn test_timeline_with_privacy_filter() {
         let storage = SqliteStorage::open_in_memory().unwrap();
@@ -2242,6 +4099,81 @@ mod tests {
 
     // ── Pending status filtering tests ──────────────────────────────────
 
+    #[tokio::test]
+    async fn test_device_roundtrip() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let memory = test_memory().with_device(Some("0192a4b7c3d4".to_string()));
+        storage.save_memory(&memory, None).await.unwrap();
+        let loaded = storage.get_memory(memory.id).await.unwrap();
+        assert_eq!(loaded.device_id.as_deref(), Some("0192a4b7c3d4"));
+    }
+
+    #[tokio::test]
+    async fn test_confidence_roundtrip() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let captured = test_memory().with_confidence(0.4);
+        let manual = test_memory();
+        storage.save_memory(&captured, None).await.unwrap();
+        storage.save_memory(&manual, None).await.unwrap();
+        let loaded = storage.get_memory(captured.id).await.unwrap();
+        assert!((loaded.confidence.unwrap() - 0.4).abs() < 1e-6);
+        assert!(loaded.needs_confirmation(0.5));
+        assert!(!loaded.needs_confirmation(0.3));
+        let loaded = storage.get_memory(manual.id).await.unwrap();
+        assert_eq!(loaded.confidence, None);
+        assert!(!loaded.needs_confirmation(0.5));
+    }
+
+    #[tokio::test]
+    async fn test_label_roundtrip_and_clear() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let memory = test_memory().with_label(Some("confidential".to_string()));
+        storage.save_memory(&memory, None).await.unwrap();
+        let loaded = storage.get_memory(memory.id).await.unwrap();
+        assert_eq!(loaded.label.as_deref(), Some("confidential"));
+
+        let cleared = storage
+            .update_memory(
+                memory.id,
+                &UpdateMemoryInput {
+                    label: Some(String::new()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(cleared.label, None);
+    }
+
+    #[tokio::test]
+    async fn test_quarantined_excluded_unless_requested() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+
+        let mut quarantined = test_memory();
+        quarantined.status = MemoryStatus::Quarantined;
+        storage.save_memory(&quarantined, None).await.unwrap();
+
+        let default = storage
+            .timeline(&TimelineQuery {
+                limit: 100,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(default.is_empty());
+
+        let held = storage
+            .timeline(&TimelineQuery {
+                status: Some(MemoryStatus::Quarantined),
+                limit: 100,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].id, quarantined.id);
+    }
+
     #[tokio::test]
     async fn test_pending_excluded_from_timeline() {
         let storage = SqliteStorage::open_in_memory().unwrap();
@@ -0,0 +1,290 @@
+//! Knowledge map: which tags occur together and which projects they belong to.
+//!
+//! Storage supplies two aggregates — per-tag counts split by project
+//! ([`TagUsage`]) and co-occurrence counts for tag pairs ([`TagPair`]) — and
+//! [`build`] turns them into a [`TagMap`]: the most used tags, the pairs
+//! between them, and clusters of tags that are regularly used together, each
+//! with the projects it covers. Tags are compared case-insensitively.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::model::Memory;
+
+/// Fewest shared memories for a pair to link two tags into a cluster.
+pub const MIN_PAIR_COUNT: usize = 2;
+
+/// Least Jaccard overlap (shared / either) for a pair to link two tags into
+/// a cluster, so one tag used everywhere doesn't pull everything together.
+pub const MIN_JACCARD: f32 = 0.2;
+
+/// Memories carrying `tag` in one project.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TagUsage {
+    pub tag: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    pub count: usize,
+}
+
+/// Memories carrying both `a` and `b` (`a < b`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TagPair {
+    pub a: String,
+    pub b: String,
+    pub count: usize,
+}
+
+/// Memory count for one project (`None` for memories without one).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ProjectCount {
+    pub project: Option<String>,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TagNode {
+    pub tag: String,
+    pub count: usize,
+    /// Index into [`TagMap::clusters`], if the tag belongs to one.
+    pub cluster: Option<usize>,
+    pub projects: Vec<ProjectCount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TagCluster {
+    /// Most used first.
+    pub tags: Vec<String>,
+    /// Tag uses summed over the cluster's tags.
+    pub weight: usize,
+    /// Where the cluster's tags are used, largest share first.
+    pub projects: Vec<ProjectCount>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TagMap {
+    pub tags: Vec<TagNode>,
+    pub pairs: Vec<TagPair>,
+    pub clusters: Vec<TagCluster>,
+}
+
+/// Count tag usage and co-occurrence over `memories` directly, for backends
+/// that can't aggregate in a query.
+pub fn aggregate(memories: &[Memory]) -> (Vec<TagUsage>, Vec<TagPair>) {
+    let mut usage: BTreeMap<(String, Option<String>), usize> = BTreeMap::new();
+    let mut pairs: BTreeMap<(String, String), usize> = BTreeMap::new();
+    for memory in memories {
+        let mut tags: Vec<String> = memory.tags.iter().map(|t| t.to_lowercase()).collect();
+        tags.sort();
+        tags.dedup();
+        for (i, tag) in tags.iter().enumerate() {
+            *usage
+                .entry((tag.clone(), memory.project_id.clone()))
+                .or_insert(0) += 1;
+            for other in &tags[i + 1..] {
+                *pairs.entry((tag.clone(), other.clone())).or_insert(0) += 1;
+            }
+        }
+    }
+    (
+        usage
+            .into_iter()
+            .map(|((tag, project_id), count)| TagUsage {
+                tag,
+                project_id,
+                count,
+            })
+            .collect(),
+        pairs
+            .into_iter()
+            .map(|((a, b), count)| TagPair { a, b, count })
+            .collect(),
+    )
+}
+
+/// Build the map from storage aggregates, keeping the `max_tags` most used
+/// tags and the pairs among them.
+pub fn build(usage: Vec<TagUsage>, pairs: Vec<TagPair>, max_tags: usize) -> TagMap {
+    let mut by_tag: BTreeMap<String, Vec<ProjectCount>> = BTreeMap::new();
+    for u in usage {
+        by_tag.entry(u.tag).or_default().push(ProjectCount {
+            project: u.project_id,
+            count: u.count,
+        });
+    }
+    let mut tags: Vec<TagNode> = by_tag
+        .into_iter()
+        .map(|(tag, mut projects)| {
+            projects.sort_by_key(|p| Reverse(p.count));
+            TagNode {
+                count: projects.iter().map(|p| p.count).sum(),
+                tag,
+                cluster: None,
+                projects,
+            }
+        })
+        .collect();
+    tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+    tags.truncate(max_tags);
+
+    let index: HashMap<String, usize> = tags
+        .iter()
+        .enumerate()
+        .map(|(i, t)| (t.tag.clone(), i))
+        .collect();
+    let mut pairs: Vec<TagPair> = pairs
+        .into_iter()
+        .filter(|p| index.contains_key(&p.a) && index.contains_key(&p.b))
+        .collect();
+    pairs.sort_by_key(|p| Reverse(p.count));
+
+    // Union-find over strong pairs.
+    let mut parent: Vec<usize> = (0..tags.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for pair in &pairs {
+        let (a, b) = (index[&pair.a], index[&pair.b]);
+        let either = tags[a].count + tags[b].count - pair.count;
+        let jaccard = pair.count as f32 / either.max(1) as f32;
+        if pair.count >= MIN_PAIR_COUNT && jaccard >= MIN_JACCARD {
+            let (ra, rb) = (root(&mut parent, a), root(&mut parent, b));
+            parent[ra] = rb;
+        }
+    }
+
+    let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for i in 0..tags.len() {
+        let r = root(&mut parent, i);
+        groups.entry(r).or_default().push(i);
+    }
+    let mut clusters: Vec<(Vec<usize>, usize)> = groups
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|members| {
+            let weight = members.iter().map(|&i| tags[i].count).sum();
+            (members, weight)
+        })
+        .collect();
+    clusters.sort_by_key(|c| Reverse(c.1));
+
+    let clusters = clusters
+        .into_iter()
+        .enumerate()
+        .map(|(ci, (members, weight))| {
+            let mut projects: BTreeMap<Option<String>, usize> = BTreeMap::new();
+            for &i in &members {
+                tags[i].cluster = Some(ci);
+                for p in &tags[i].projects {
+                    *projects.entry(p.project.clone()).or_insert(0) += p.count;
+                }
+            }
+            let mut projects: Vec<ProjectCount> = projects
+                .into_iter()
+                .map(|(project, count)| ProjectCount { project, count })
+                .collect();
+            projects.sort_by_key(|p| Reverse(p.count));
+            TagCluster {
+                // `members` are indices into `tags`, which is sorted by use.
+                tags: members.iter().map(|&i| tags[i].tag.clone()).collect(),
+                weight,
+                projects,
+            }
+        })
+        .collect();
+
+    TagMap {
+        tags,
+        pairs,
+        clusters,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::MemoryKind;
+
+    fn memory(project: &str, tags: &[&str]) -> Memory {
+        let mut m = Memory::new(
+            "Title".to_string(),
+            "Content".to_string(),
+            MemoryKind::Fact,
+            "user".to_string(),
+        )
+        .with_tags(tags.iter().map(|t| t.to_string()).collect());
+        m.project_id = Some(project.to_string());
+        m
+    }
+
+    #[test]
+    fn test_aggregate_counts_case_insensitively() {
+        let (usage, pairs) = aggregate(&[
+            memory("api", &["Auth", "jwt", "auth"]),
+            memory("web", &["auth"]),
+        ]);
+        assert_eq!(
+            usage,
+            vec![
+                TagUsage {
+                    tag: "auth".into(),
+                    project_id: Some("api".into()),
+                    count: 1
+                },
+                TagUsage {
+                    tag: "auth".into(),
+                    project_id: Some("web".into()),
+                    count: 1
+                },
+                TagUsage {
+                    tag: "jwt".into(),
+                    project_id: Some("api".into()),
+                    count: 1
+                },
+            ]
+        );
+        assert_eq!(
+            pairs,
+            vec![TagPair {
+                a: "auth".into(),
+                b: "jwt".into(),
+                count: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn test_build_clusters_tags_used_together() {
+        let memories = vec![
+            memory("api", &["auth", "jwt"]),
+            memory("api", &["auth", "jwt", "session"]),
+            memory("api", &["session", "auth"]),
+            memory("web", &["css", "layout"]),
+            memory("web", &["css", "layout"]),
+            memory("web", &["css", "auth"]),
+            memory("cli", &["misc"]),
+        ];
+        let (usage, pairs) = aggregate(&memories);
+        let map = build(usage, pairs, 50);
+
+        assert_eq!(map.tags[0].tag, "auth");
+        assert_eq!(map.tags[0].count, 4);
+        assert_eq!(map.clusters.len(), 2);
+        assert_eq!(map.clusters[0].tags, vec!["auth", "jwt", "session"]);
+        assert_eq!(map.clusters[0].projects[0].project.as_deref(), Some("api"));
+        assert_eq!(map.clusters[1].tags, vec!["css", "layout"]);
+        let misc = map.tags.iter().find(|t| t.tag == "misc").unwrap();
+        assert_eq!(misc.cluster, None);
+
+        let top = build(aggregate(&memories).0, aggregate(&memories).1, 2);
+        assert_eq!(top.tags.len(), 2);
+        assert!(top.pairs.iter().all(|p| p.a == "auth" || p.b == "auth"));
+    }
+}
@@ -0,0 +1,328 @@
+//! Opt-in, local usage telemetry for tuning defaults.
+//!
+//! With `[telemetry] enabled`, each `shabka` command appends one
+//! [`TelemetryEvent`] to `~/.config/shabka/telemetry.jsonl`: the command
+//! name, how long it took, whether it succeeded and a few result counts
+//! (results returned, memories archived, ...). Never arguments, queries or
+//! memory content, and nothing leaves the machine. `shabka telemetry
+//! report` summarizes the log per command, e.g. how often searches come back
+//! empty or how many results they usually return, to tune limits and
+//! thresholds against real use. The file is rotated to `telemetry.jsonl.1`
+//! once it reaches `max_bytes`.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Count recording how many results a command returned, used for the
+/// empty-result share in [`report`].
+pub const RESULTS: &str = "results";
+
+/// Whether and where telemetry is recorded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Rotate the log once it reaches this size (default 5 MiB).
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: u64,
+}
+
+fn default_max_bytes() -> u64 {
+    5 * 1024 * 1024
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_bytes: default_max_bytes(),
+        }
+    }
+}
+
+/// One command run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TelemetryEvent {
+    pub timestamp: DateTime<Utc>,
+    /// Command and subcommand names, e.g. `search` or `graph top`.
+    pub command: String,
+    pub success: bool,
+    pub latency_ms: u64,
+    /// Result counts, e.g. `results` or `archived`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub counts: BTreeMap<String, u64>,
+}
+
+impl TelemetryEvent {
+    pub fn new(
+        command: impl Into<String>,
+        success: bool,
+        latency: std::time::Duration,
+        counts: BTreeMap<String, u64>,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            command: command.into(),
+            success,
+            latency_ms: latency.as_millis() as u64,
+            counts,
+        }
+    }
+}
+
+/// Append-only JSONL telemetry log, rotated once to `.1`.
+pub struct TelemetryLog {
+    path: PathBuf,
+    config: TelemetryConfig,
+}
+
+impl TelemetryLog {
+    pub fn new(config: &TelemetryConfig) -> Self {
+        let path = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("shabka")
+            .join("telemetry.jsonl");
+        Self::with_path(path, config)
+    }
+
+    /// A log stored at `path` instead of the config directory.
+    pub fn with_path(path: PathBuf, config: &TelemetryConfig) -> Self {
+        Self {
+            path,
+            config: config.clone(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn rotated(&self) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(".1");
+        PathBuf::from(name)
+    }
+
+    /// Append `event`, rotating first if the log is full. Failures are
+    /// logged and otherwise ignored; telemetry never fails a command.
+    pub fn record(&self, event: &TelemetryEvent) {
+        if !self.config.enabled {
+            return;
+        }
+        if let Some(parent) = self.path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let full = std::fs::metadata(&self.path)
+            .map(|m| m.len() >= self.config.max_bytes)
+            .unwrap_or(false);
+        if full {
+            if let Err(e) = std::fs::rename(&self.path, self.rotated()) {
+                tracing::debug!("telemetry: failed to rotate log: {e}");
+            }
+        }
+        let line = match serde_json::to_string(event) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::debug!("telemetry: failed to serialize event: {e}");
+                return;
+            }
+        };
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path);
+        match file {
+            Ok(mut f) => {
+                let _ = writeln!(f, "{line}");
+            }
+            Err(e) => tracing::debug!("telemetry: failed to open log: {e}"),
+        }
+    }
+
+    /// Every event since `since` (all when `None`), in the rotated and live
+    /// files, oldest first.
+    pub fn events(&self, since: Option<DateTime<Utc>>) -> Vec<TelemetryEvent> {
+        let mut events = read_events(&self.rotated());
+        events.extend(read_events(&self.path));
+        if let Some(since) = since {
+            events.retain(|e| e.timestamp >= since);
+        }
+        events
+    }
+
+    /// Delete the log and its rotated file.
+    pub fn clear(&self) -> std::io::Result<()> {
+        for path in [self.path.clone(), self.rotated()] {
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_events(path: &Path) -> Vec<TelemetryEvent> {
+    let Ok(file) = std::fs::File::open(path) else {
+        return Vec::new();
+    };
+    std::io::BufReader::new(file)
+        .lines()
+        .map_while(|line| line.ok())
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect()
+}
+
+/// Usage of one command, from [`report`].
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct CommandUsage {
+    pub command: String,
+    pub runs: usize,
+    pub failures: usize,
+    pub median_latency_ms: u64,
+    pub p95_latency_ms: u64,
+    /// Mean of each recorded count over the runs that recorded it.
+    pub mean_counts: BTreeMap<String, f64>,
+    /// Runs that returned no [`RESULTS`], among those that counted them.
+    pub empty_results: usize,
+}
+
+/// Per-command usage in `events`, most used first.
+pub fn report(events: &[TelemetryEvent]) -> Vec<CommandUsage> {
+    let mut by_command: BTreeMap<&str, Vec<&TelemetryEvent>> = BTreeMap::new();
+    for event in events {
+        by_command.entry(&event.command).or_default().push(event);
+    }
+
+    let mut usage: Vec<CommandUsage> = by_command
+        .into_iter()
+        .map(|(command, events)| {
+            let mut latencies: Vec<u64> = events.iter().map(|e| e.latency_ms).collect();
+            latencies.sort_unstable();
+            let mut sums: BTreeMap<String, (u64, usize)> = BTreeMap::new();
+            for event in &events {
+                for (name, value) in &event.counts {
+                    let (sum, n) = sums.entry(name.clone()).or_default();
+                    *sum += value;
+                    *n += 1;
+                }
+            }
+            CommandUsage {
+                command: command.to_string(),
+                runs: events.len(),
+                failures: events.iter().filter(|e| !e.success).count(),
+                median_latency_ms: percentile(&latencies, 50),
+                p95_latency_ms: percentile(&latencies, 95),
+                mean_counts: sums
+                    .into_iter()
+                    .map(|(name, (sum, n))| (name, sum as f64 / n as f64))
+                    .collect(),
+                empty_results: events
+                    .iter()
+                    .filter(|e| e.counts.get(RESULTS) == Some(&0))
+                    .count(),
+            }
+        })
+        .collect();
+    usage.sort_by(|a, b| b.runs.cmp(&a.runs).then(a.command.cmp(&b.command)));
+    usage
+}
+
+/// The `pct`th percentile of sorted `values` (nearest rank), 0 if empty.
+fn percentile(values: &[u64], pct: usize) -> u64 {
+    if values.is_empty() {
+        return 0;
+    }
+    let rank = (pct * values.len()).div_ceil(100).max(1);
+    values[rank.min(values.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_log(config: &TelemetryConfig) -> TelemetryLog {
+        let dir = std::env::temp_dir().join(format!("shabka-telemetry-{}", uuid::Uuid::now_v7()));
+        TelemetryLog::with_path(dir.join("telemetry.jsonl"), config)
+    }
+
+    fn event(
+        command: &str,
+        success: bool,
+        latency_ms: u64,
+        results: Option<u64>,
+    ) -> TelemetryEvent {
+        let counts = results
+            .map(|n| (RESULTS.to_string(), n))
+            .into_iter()
+            .collect();
+        TelemetryEvent::new(command, success, Duration::from_millis(latency_ms), counts)
+    }
+
+    #[test]
+    fn test_disabled_log_writes_nothing() {
+        let log = temp_log(&TelemetryConfig::default());
+        log.record(&event("search", true, 5, Some(3)));
+        assert!(!log.path().exists());
+    }
+
+    #[test]
+    fn test_record_rotate_and_clear() {
+        let log = temp_log(&TelemetryConfig {
+            enabled: true,
+            max_bytes: 1,
+        });
+        log.record(&event("search", true, 5, Some(3)));
+        log.record(&event("list", true, 2, None));
+        log.record(&event("prune", false, 9, None));
+        // The first event was dropped with the second rotation.
+        let commands: Vec<String> = log.events(None).into_iter().map(|e| e.command).collect();
+        assert_eq!(commands, ["list", "prune"]);
+        assert!(log
+            .events(Some(Utc::now() + chrono::Duration::hours(1)))
+            .is_empty());
+
+        log.clear().unwrap();
+        assert!(log.events(None).is_empty());
+        let _ = std::fs::remove_dir_all(log.path().parent().unwrap());
+    }
+
+    #[test]
+    fn test_report() {
+        let events = vec![
+            event("search", true, 10, Some(4)),
+            event("search", true, 30, Some(0)),
+            event("search", false, 20, None),
+            event("list", true, 5, Some(10)),
+        ];
+        let usage = report(&events);
+        assert_eq!(usage.len(), 2);
+        let search = &usage[0];
+        assert_eq!(search.command, "search");
+        assert_eq!((search.runs, search.failures), (3, 1));
+        assert_eq!(search.median_latency_ms, 20);
+        assert_eq!(search.p95_latency_ms, 30);
+        assert_eq!(search.mean_counts[RESULTS], 2.0);
+        assert_eq!(search.empty_results, 1);
+        assert_eq!(usage[1].mean_counts[RESULTS], 10.0);
+    }
+
+    #[test]
+    fn test_percentile() {
+        assert_eq!(percentile(&[], 50), 0);
+        assert_eq!(percentile(&[7], 95), 7);
+        let values: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&values, 50), 50);
+        assert_eq!(percentile(&values, 95), 95);
+    }
+}
@@ -0,0 +1,257 @@
+//! Multi-tenant mode for a shared `shabka-web` / MCP deployment.
+//!
+//! With `[tenants] enabled`, each API token may name a tenant (`shabka token
+//! create --tenant acme`). Requests made with it are served from that
+//! tenant's own SQLite database, history, questions and attachments under
+//! `~/.config/shabka/tenants/<tenant>/`, so no query can reach another
+//! tenant's memories. Tokens without a tenant keep using the configured
+//! store. Tokens themselves, and the audit log, stay with the operator's
+//! store; audit entries record the tenant, which [`usage`] counts.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::api_tokens::ApiToken;
+use crate::audit::{AuditFilter, AuditLog};
+use crate::error::{Result, ShabkaError};
+use crate::model::TimelineQuery;
+use crate::services::Services;
+use crate::storage::SqliteStorage;
+
+/// The database file in each tenant's directory.
+const DATABASE: &str = "shabka.db";
+
+/// Multi-tenant settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TenantConfig {
+    /// Serve tokens that name a tenant from that tenant's store. Requires
+    /// `web.require_token`.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Directory holding one subdirectory per tenant (default
+    /// `~/.config/shabka/tenants`).
+    #[serde(default)]
+    pub dir: Option<String>,
+}
+
+impl TenantConfig {
+    /// The directory holding the tenants' stores.
+    pub fn root(&self) -> PathBuf {
+        match &self.dir {
+            Some(dir) => PathBuf::from(dir),
+            None => dirs::config_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join("shabka")
+                .join("tenants"),
+        }
+    }
+}
+
+/// Check that `name` can name a tenant: lowercase letters, digits, `-` and
+/// `_`, as it becomes a directory name.
+pub fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_'));
+    if valid {
+        Ok(())
+    } else {
+        Err(ShabkaError::InvalidInput(format!(
+            "invalid tenant name '{name}': use lowercase letters, digits, '-' and '_'"
+        )))
+    }
+}
+
+/// The per-tenant service registries of a server, built on first use.
+pub struct Tenants {
+    services: Arc<Services>,
+    root: PathBuf,
+    registries: Mutex<HashMap<String, Arc<Services>>>,
+}
+
+impl Tenants {
+    /// Tenants alongside the operator's `services`, rooted where its
+    /// config says.
+    pub fn new(services: Arc<Services>) -> Self {
+        let root = services.config().tenants.root();
+        Self::at(services, root)
+    }
+
+    /// Tenants rooted at `root`.
+    pub fn at(services: Arc<Services>, root: impl Into<PathBuf>) -> Self {
+        Self {
+            services,
+            root: root.into(),
+            registries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The directory of `tenant`'s store.
+    pub fn dir(&self, tenant: &str) -> Result<PathBuf> {
+        validate_name(tenant)?;
+        Ok(self.root.join(tenant))
+    }
+
+    /// The registry serving `tenant`, creating its directory on first use.
+    pub fn services(&self, tenant: &str) -> Result<Arc<Services>> {
+        let dir = self.dir(tenant)?;
+        let mut registries = self.registries.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(services) = registries.get(tenant) {
+            return Ok(services.clone());
+        }
+        std::fs::create_dir_all(&dir).map_err(|e| {
+            ShabkaError::Storage(format!("failed to create {}: {e}", dir.display()))
+        })?;
+        let services = self.services.for_tenant(&dir)?;
+        registries.insert(tenant.to_string(), services.clone());
+        Ok(services)
+    }
+}
+
+/// What one tenant holds and how much it has been used.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct TenantUsage {
+    pub tenant: String,
+    pub memories: usize,
+    /// Size of the tenant's database file.
+    pub database_bytes: u64,
+    /// Active tokens for the tenant.
+    pub tokens: usize,
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// Requests and MCP tool calls in the audit log.
+    pub requests: usize,
+    pub failed_requests: usize,
+}
+
+/// Usage of every tenant that has a store under `root` or a token in
+/// `tokens`, by name. Request counts come from `audit`, so they are zero
+/// unless `[audit] enabled`.
+pub async fn usage(root: &Path, tokens: &[ApiToken], audit: &AuditLog) -> Result<Vec<TenantUsage>> {
+    let now = Utc::now();
+    let mut names: BTreeSet<String> = tokens.iter().filter_map(|t| t.tenant.clone()).collect();
+    if let Ok(entries) = std::fs::read_dir(root) {
+        names.extend(
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().join(DATABASE).is_file())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned()),
+        );
+    }
+
+    let mut usage = Vec::with_capacity(names.len());
+    for tenant in names {
+        let database = root.join(&tenant).join(DATABASE);
+        let (memories, database_bytes) = match std::fs::metadata(&database) {
+            Ok(meta) => {
+                let storage = SqliteStorage::open(&database)?;
+                let count = storage.timeline_count(&TimelineQuery::default()).await?;
+                (count, meta.len())
+            }
+            Err(_) => (0, 0),
+        };
+        let own: Vec<&ApiToken> = tokens
+            .iter()
+            .filter(|t| t.tenant.as_deref() == Some(tenant.as_str()))
+            .collect();
+        let requests = audit.entries(&AuditFilter {
+            tenant: Some(tenant.clone()),
+            ..Default::default()
+        });
+        usage.push(TenantUsage {
+            memories,
+            database_bytes,
+            tokens: own.iter().filter(|t| t.is_active(now)).count(),
+            last_used_at: own.iter().filter_map(|t| t.last_used_at).max(),
+            failed_requests: requests.iter().filter(|e| !e.success).count(),
+            requests: requests.len(),
+            tenant,
+        });
+    }
+    Ok(usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_tokens::{self, TokenScope};
+    use crate::audit::{AuditConfig, AuditEntry};
+    use crate::config::ShabkaConfig;
+    use crate::model::{Memory, MemoryKind};
+    use crate::storage::{Storage, StorageBackend};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("acme-2").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("../acme").is_err());
+        assert!(validate_name("Acme").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tenants_are_isolated_and_counted() {
+        let root = std::env::temp_dir().join(format!("shabka-tenants-{}", Uuid::now_v7()));
+        let mut config = ShabkaConfig::default_config();
+        config.history.enabled = false;
+        let operator = Storage::Sqlite(SqliteStorage::open_in_memory().unwrap());
+        let services = Services::with_storage(config, operator);
+        let tenants = Tenants::at(services.clone(), &root);
+
+        let acme = tenants.services("acme").unwrap();
+        assert!(Arc::ptr_eq(&acme, &tenants.services("acme").unwrap()));
+        let globex = tenants.services("globex").unwrap();
+        assert!(tenants.services("../escape").is_err());
+
+        let memory = Memory::new("Acme only".into(), "c".into(), MemoryKind::Fact, "u".into());
+        let storage = acme.storage().unwrap();
+        storage.save_memory(&memory, None).await.unwrap();
+        assert!(globex
+            .storage()
+            .unwrap()
+            .get_memory(memory.id)
+            .await
+            .is_err());
+        assert!(services
+            .storage()
+            .unwrap()
+            .get_memory(memory.id)
+            .await
+            .is_err());
+
+        let operator = services.storage().unwrap();
+        api_tokens::create(&operator, "acme-ci", TokenScope::Read, None, Some("acme"))
+            .await
+            .unwrap();
+        let tokens = api_tokens::list(&operator).await.unwrap();
+        let audit = AuditLog::with_path(
+            root.join("audit.jsonl"),
+            &AuditConfig {
+                enabled: true,
+                ..Default::default()
+            },
+        );
+        audit.log(&AuditEntry::new("web", "GET /api/v1/memories").with_tenant(Some("acme".into())));
+        audit.log(&AuditEntry::new("web", "GET /api/v1/memories"));
+
+        drop(storage);
+        let usage = usage(&root, &tokens, &audit).await.unwrap();
+        let names: Vec<&str> = usage.iter().map(|u| u.tenant.as_str()).collect();
+        assert_eq!(names, vec!["acme", "globex"]);
+        assert_eq!((usage[0].memories, usage[0].tokens), (1, 1));
+        assert_eq!(usage[0].requests, 1);
+        assert_eq!((usage[1].memories, usage[1].requests), (0, 0));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,337 @@
+//! Deterministic stand-ins for the embedding and LLM providers, for tests
+//! that must run offline.
+//!
+//! Enabled by the `testing` feature. Downstream crates add it to their
+//! dev-dependencies and hand the mocks to a [`Services`](crate::services::Services)
+//! registry, so hooks, MCP tools and consolidation run their LLM-dependent
+//! paths without a network:
+//!
+//! ```ignore
+//! let llm = MockLlm::new().reply(r#"{"action": "merge"}"#);
+//! let services = Services::with_providers(
+//!     config,
+//!     storage,
+//!     EmbeddingService::mock(MockEmbedding::new(128)),
+//!     Some(LlmService::mock(llm.clone())),
+//! );
+//! // ... run the code under test ...
+//! assert_eq!(llm.calls().len(), 1);
+//! ```
+//!
+//! Both mocks are cheap handles over shared state: keep a clone to script
+//! more replies or inspect calls after the service has taken the original.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+
+use crate::error::{Result, ShabkaError};
+
+/// Sleep for `latency`, if any, before answering.
+async fn delay(latency: Option<Duration>) {
+    if let Some(latency) = latency {
+        tokio::time::sleep(latency).await;
+    }
+}
+
+fn lock<T>(state: &Mutex<T>) -> MutexGuard<'_, T> {
+    // A test that panicked mid-call shouldn't poison every later assertion.
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// ---------------------------------------------------------------------------
+// Embeddings
+// ---------------------------------------------------------------------------
+
+/// A deterministic embedding provider.
+///
+/// Each text maps to a fixed unit vector derived from its SHA-256, so equal
+/// texts are identical and different texts are close to orthogonal. Pin a
+/// vector for a text with [`vector`](Self::vector) to control similarity,
+/// and queue failures with [`fail_next`](Self::fail_next).
+#[derive(Clone)]
+pub struct MockEmbedding {
+    dimensions: usize,
+    model_id: String,
+    state: Arc<Mutex<EmbeddingState>>,
+}
+
+#[derive(Default)]
+struct EmbeddingState {
+    pinned: HashMap<String, Vec<f32>>,
+    failures: VecDeque<String>,
+    latency: Option<Duration>,
+    calls: Vec<String>,
+}
+
+impl MockEmbedding {
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions,
+            model_id: format!("mock-{dimensions}d"),
+            state: Arc::default(),
+        }
+    }
+
+    /// Report `model_id` as the model, e.g. to exercise dimension checks.
+    pub fn with_model_id(mut self, model_id: impl Into<String>) -> Self {
+        self.model_id = model_id.into();
+        self
+    }
+
+    /// Return `vector` for exactly `text`. Its length must match the
+    /// mock's dimensions.
+    pub fn vector(self, text: impl Into<String>, vector: Vec<f32>) -> Self {
+        assert_eq!(
+            vector.len(),
+            self.dimensions,
+            "pinned vector has the wrong dimensions"
+        );
+        lock(&self.state).pinned.insert(text.into(), vector);
+        self
+    }
+
+    /// Fail the next call with `error` (as the provider's message). Queued
+    /// failures are used up in order, one per call.
+    pub fn fail_next(self, error: impl Into<String>) -> Self {
+        lock(&self.state).failures.push_back(error.into());
+        self
+    }
+
+    /// Wait `latency` before every call.
+    pub fn latency(self, latency: Duration) -> Self {
+        lock(&self.state).latency = Some(latency);
+        self
+    }
+
+    /// Every text embedded so far, in order.
+    pub fn calls(&self) -> Vec<String> {
+        lock(&self.state).calls.clone()
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    pub async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let mut vectors = self.embed_batch(&[text]).await?;
+        Ok(vectors.remove(0))
+    }
+
+    pub async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let latency = lock(&self.state).latency;
+        delay(latency).await;
+
+        let mut state = lock(&self.state);
+        if let Some(error) = state.failures.pop_front() {
+            return Err(ShabkaError::provider("embedding", "mock", error));
+        }
+        state.calls.extend(texts.iter().map(|t| t.to_string()));
+        Ok(texts
+            .iter()
+            .map(|t| {
+                state
+                    .pinned
+                    .get(*t)
+                    .cloned()
+                    .unwrap_or_else(|| self.hash_vector(t))
+            })
+            .collect())
+    }
+
+    fn hash_vector(&self, text: &str) -> Vec<f32> {
+        let mut vector = Vec::with_capacity(self.dimensions);
+        let mut block = 0u32;
+        while vector.len() < self.dimensions {
+            let digest = Sha256::new()
+                .chain_update(block.to_le_bytes())
+                .chain_update(text.as_bytes())
+                .finalize();
+            vector.extend(
+                digest
+                    .iter()
+                    .take(self.dimensions - vector.len())
+                    .map(|b| *b as f32 - 127.5),
+            );
+            block += 1;
+        }
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|x| *x /= norm);
+        }
+        vector
+    }
+}
+
+// ---------------------------------------------------------------------------
+// LLM
+// ---------------------------------------------------------------------------
+
+/// One prompt the mock LLM received.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockCall {
+    pub prompt: String,
+    pub system: Option<String>,
+}
+
+/// A scripted LLM.
+///
+/// Each call takes the first matching rule added with [`when`](Self::when),
+/// else the next reply queued with [`reply`](Self::reply) or
+/// [`fail`](Self::fail), else the [`fallback`](Self::fallback). With none
+/// of those the call fails, so an unexpected prompt shows up as an error
+/// rather than a silent default.
+#[derive(Clone, Default)]
+pub struct MockLlm {
+    state: Arc<Mutex<LlmState>>,
+}
+
+#[derive(Default)]
+struct LlmState {
+    rules: Vec<(String, String)>,
+    script: VecDeque<std::result::Result<String, String>>,
+    fallback: Option<String>,
+    latency: Option<Duration>,
+    calls: Vec<MockCall>,
+}
+
+impl MockLlm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue `reply` for the next call.
+    pub fn reply(self, reply: impl Into<String>) -> Self {
+        lock(&self.state).script.push_back(Ok(reply.into()));
+        self
+    }
+
+    /// Queue a provider error for the next call. Errors that look
+    /// transient (timeouts, 5xx) are retried by the service and use up
+    /// further script entries.
+    pub fn fail(self, error: impl Into<String>) -> Self {
+        lock(&self.state).script.push_back(Err(error.into()));
+        self
+    }
+
+    /// Always answer `reply` when the prompt contains `needle`, ahead of
+    /// the script.
+    pub fn when(self, needle: impl Into<String>, reply: impl Into<String>) -> Self {
+        lock(&self.state).rules.push((needle.into(), reply.into()));
+        self
+    }
+
+    /// Answer `reply` once the script is used up.
+    pub fn fallback(self, reply: impl Into<String>) -> Self {
+        lock(&self.state).fallback = Some(reply.into());
+        self
+    }
+
+    /// Wait `latency` before every reply.
+    pub fn latency(self, latency: Duration) -> Self {
+        lock(&self.state).latency = Some(latency);
+        self
+    }
+
+    /// Every call received so far, in order.
+    pub fn calls(&self) -> Vec<MockCall> {
+        lock(&self.state).calls.clone()
+    }
+
+    pub(crate) async fn generate(
+        &self,
+        prompt: String,
+        system: Option<String>,
+    ) -> std::result::Result<String, String> {
+        let latency = lock(&self.state).latency;
+        delay(latency).await;
+
+        let mut state = lock(&self.state);
+        let reply = match state
+            .rules
+            .iter()
+            .find(|(needle, _)| prompt.contains(needle))
+        {
+            Some((_, reply)) => Ok(reply.clone()),
+            None => match state.script.pop_front() {
+                Some(scripted) => scripted,
+                None => state
+                    .fallback
+                    .clone()
+                    .ok_or_else(|| format!("mock LLM has no reply for prompt: {prompt}")),
+            },
+        };
+        state.calls.push(MockCall { prompt, system });
+        reply
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedding::EmbeddingService;
+    use crate::llm::LlmService;
+    use crate::llm_budget::LlmFeature;
+    use crate::similarity;
+
+    #[tokio::test]
+    async fn test_mock_embedding_is_deterministic_and_scriptable() {
+        let mock = MockEmbedding::new(16)
+            .vector("pinned", vec![1.0; 16])
+            .fail_next("503 service unavailable");
+        let service = EmbeddingService::mock(mock.clone());
+        assert_eq!(service.dimensions(), 16);
+        assert_eq!(service.model_id(), "mock-16d");
+
+        // The queued transient failure is retried away.
+        let a = service.embed("pool exhaustion").await.unwrap();
+        let b = service.embed("pool exhaustion").await.unwrap();
+        let c = service.embed("retry budget").await.unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.len(), 16);
+        assert!(similarity::score(&a, &c) < 0.99);
+        assert_eq!(service.embed("pinned").await.unwrap(), vec![1.0; 16]);
+        assert_eq!(mock.calls().len(), 4);
+
+        let service = EmbeddingService::mock(MockEmbedding::new(8).fail_next("invalid api key"));
+        assert!(matches!(
+            service.embed("x").await,
+            Err(ShabkaError::ProviderAuth { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_mock_llm_rules_script_and_calls() {
+        let mock = MockLlm::new()
+            .when("classify", "fact")
+            .reply("first")
+            .fail("invalid api key")
+            .latency(Duration::from_millis(1));
+        let llm = LlmService::mock(mock.clone());
+
+        let reply = llm.generate(LlmFeature::AutoTag, "classify this", None);
+        assert_eq!(reply.await.unwrap(), "fact");
+        let reply = llm.generate(LlmFeature::AutoTag, "summarise", Some("be brief"));
+        assert_eq!(reply.await.unwrap(), "first");
+        assert!(llm
+            .generate(LlmFeature::AutoTag, "again", None)
+            .await
+            .is_err());
+        assert!(llm
+            .generate(LlmFeature::AutoTag, "unscripted", None)
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("no reply"));
+
+        let calls = mock.calls();
+        assert_eq!(calls.len(), 4);
+        assert_eq!(calls[1].system.as_deref(), Some("be brief"));
+    }
+}
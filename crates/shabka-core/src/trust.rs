@@ -1,15 +1,205 @@
-use crate::model::{Memory, MemorySource, VerificationStatus};
+use chrono::{DateTime, Utc};
+#[cfg(feature = "runtime")]
+use uuid::Uuid;
+
+use crate::decay::RetentionConfig;
+#[cfg(feature = "runtime")]
+use crate::error::{Result, ShabkaError};
+#[cfg(feature = "runtime")]
+use crate::model::{Assertion, Endorsement, UpdateMemoryInput};
+use crate::model::{Memory, MemoryKind, MemorySource, VerificationStatus};
+#[cfg(feature = "runtime")]
+use crate::storage::StorageBackend;
+
+/// Days for an expired verification to lose half its remaining edge over
+/// an unverified memory.
+const EXPIRED_VERIFICATION_HALF_LIFE_DAYS: f64 = 30.0;
+
+/// Expiry to store when a memory of `kind` is set to `status`: the kind's
+/// `verification_expires_days` from now for `Verified`, otherwise none.
+pub fn verification_expiry(
+    status: VerificationStatus,
+    kind: MemoryKind,
+    retention: &RetentionConfig,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    if status != VerificationStatus::Verified {
+        return None;
+    }
+    retention.verification_deadline(kind, now)
+}
+
+/// Whether a verified memory's verification has lapsed and needs renewing.
+pub fn verification_due(memory: &Memory, now: DateTime<Utc>) -> bool {
+    memory.verification == VerificationStatus::Verified
+        && memory.verification_expires_at.is_some_and(|at| at <= now)
+}
+
+/// Weight of a `Verified` status: 1.0 until it expires, then decaying
+/// toward the unverified weight (0.5).
+fn verified_weight(memory: &Memory, now: DateTime<Utc>) -> f32 {
+    let Some(expires_at) = memory.verification_expires_at.filter(|at| *at <= now) else {
+        return 1.0;
+    };
+    let overdue_days = (now - expires_at).num_seconds() as f64 / 86_400.0;
+    let decay = 0.5_f64.powf(overdue_days / EXPIRED_VERIFICATION_HALF_LIFE_DAYS);
+    (0.5 + 0.5 * decay) as f32
+}
+
+/// Most the corroboration bonus can add to a trust score.
+const CORROBORATION_BONUS: f32 = 0.10;
+
+/// Independent asserters at which corroboration saturates.
+const CORROBORATION_SATURATION: usize = 3;
+
+/// Record that someone re-asserted memory `id`, counting as an access.
+///
+/// Repeats from the same asserter on the same channel only refresh the
+/// timestamp, so the list stays one entry per (asserter, channel).
+#[cfg(feature = "runtime")]
+pub async fn record_assertion(
+    storage: &impl StorageBackend,
+    id: Uuid,
+    assertion: Assertion,
+) -> Result<Memory> {
+    let memory = storage.get_memory(id).await?;
+    let mut assertions = memory.assertions;
+    match assertions
+        .iter_mut()
+        .find(|a| a.asserted_by == assertion.asserted_by && a.channel == assertion.channel)
+    {
+        Some(existing) => existing.asserted_at = assertion.asserted_at,
+        None => assertions.push(assertion),
+    }
+    storage
+        .update_memory(
+            id,
+            &UpdateMemoryInput {
+                assertions: Some(assertions),
+                accessed_at: Some(Utc::now()),
+                ..Default::default()
+            },
+        )
+        .await
+}
+
+/// Distinct people or agents, other than the creator, who re-asserted the
+/// memory, in order of first assertion.
+pub fn independent_asserters(memory: &Memory) -> Vec<&str> {
+    let mut asserters: Vec<&str> = Vec::new();
+    for a in &memory.assertions {
+        if a.asserted_by != memory.created_by && !asserters.contains(&a.asserted_by.as_str()) {
+            asserters.push(&a.asserted_by);
+        }
+    }
+    asserters
+}
+
+/// Corroboration from independent re-assertions, from 0 to 1: grows with
+/// the number of independent asserters (saturating at three), discounted
+/// by a quarter when they all came through a single channel.
+pub fn corroboration(memory: &Memory) -> f32 {
+    let asserters = independent_asserters(memory);
+    if asserters.is_empty() {
+        return 0.0;
+    }
+    let mut channels: Vec<&str> = memory
+        .assertions
+        .iter()
+        .filter(|a| asserters.contains(&a.asserted_by.as_str()))
+        .map(|a| a.channel.as_str())
+        .collect();
+    channels.sort_unstable();
+    channels.dedup();
+    let count =
+        asserters.len().min(CORROBORATION_SATURATION) as f32 / CORROBORATION_SATURATION as f32;
+    let diversity = if channels.len() > 1 { 1.0 } else { 0.75 };
+    count * diversity
+}
+
+/// Most the endorsement bonus can add to a trust score.
+const ENDORSEMENT_BONUS: f32 = 0.10;
+
+/// Endorsers at which the endorsement bonus saturates.
+const ENDORSEMENT_SATURATION: usize = 3;
+
+/// Record that `user` endorses memory `id`. Endorsing twice is a no-op;
+/// endorsing your own memory is rejected.
+#[cfg(feature = "runtime")]
+pub async fn endorse(storage: &impl StorageBackend, id: Uuid, user: &str) -> Result<Memory> {
+    let memory = storage.get_memory(id).await?;
+    if memory.created_by == user {
+        return Err(ShabkaError::InvalidInput(
+            "you can't endorse your own memory".into(),
+        ));
+    }
+    if memory.endorsements.iter().any(|e| e.endorsed_by == user) {
+        return Ok(memory);
+    }
+    let mut endorsements = memory.endorsements;
+    endorsements.push(Endorsement::new(user));
+    set_endorsements(storage, id, endorsements).await
+}
+
+/// Withdraw `user`'s endorsement of memory `id`, if there is one.
+#[cfg(feature = "runtime")]
+pub async fn withdraw_endorsement(
+    storage: &impl StorageBackend,
+    id: Uuid,
+    user: &str,
+) -> Result<Memory> {
+    let memory = storage.get_memory(id).await?;
+    if !memory.endorsements.iter().any(|e| e.endorsed_by == user) {
+        return Ok(memory);
+    }
+    let mut endorsements = memory.endorsements;
+    endorsements.retain(|e| e.endorsed_by != user);
+    set_endorsements(storage, id, endorsements).await
+}
+
+#[cfg(feature = "runtime")]
+async fn set_endorsements(
+    storage: &impl StorageBackend,
+    id: Uuid,
+    endorsements: Vec<Endorsement>,
+) -> Result<Memory> {
+    storage
+        .update_memory(
+            id,
+            &UpdateMemoryInput {
+                endorsements: Some(endorsements),
+                accessed_at: Some(Utc::now()),
+                ..Default::default()
+            },
+        )
+        .await
+}
+
+/// Social proof from endorsements, from 0 to 1: the number of teammates
+/// other than the creator who endorsed the memory, saturating at three.
+pub fn endorsement(memory: &Memory) -> f32 {
+    let endorsers = memory
+        .endorsements
+        .iter()
+        .filter(|e| e.endorsed_by != memory.created_by)
+        .count();
+    endorsers.min(ENDORSEMENT_SATURATION) as f32 / ENDORSEMENT_SATURATION as f32
+}
 
 /// Compute a trust score (0.0--1.0) for a memory.
 ///
 /// Factors:
-/// - Verification status (40%): Verified=1.0, Unverified=0.5, Disputed=0.2, Outdated=0.1
+/// - Verification status (40%): Verified=1.0, Unverified=0.5, Disputed=0.2, Outdated=0.1.
+///   An expired verification decays from 1.0 toward 0.5.
 /// - Source reliability (30%): Manual=0.9, Derived=0.7, Import=0.6, AutoCapture=0.5
 /// - Contradiction penalty (20%): 0=1.0, 1=0.5, 2+=0.2
 /// - Content quality (10%): has_tags + decent content length
+///
+/// Independent re-assertions add a bonus of up to 0.10 (see [`corroboration`]),
+/// and teammates' endorsements another 0.10 (see [`endorsement`]).
 pub fn trust_score(memory: &Memory, contradiction_count: usize) -> f32 {
     let verification_weight = match memory.verification {
-        VerificationStatus::Verified => 1.0,
+        VerificationStatus::Verified => verified_weight(memory, Utc::now()),
         VerificationStatus::Unverified => 0.5,
         VerificationStatus::Disputed => 0.2,
         VerificationStatus::Outdated => 0.1,
@@ -19,7 +209,8 @@ pub fn trust_score(memory: &Memory, contradiction_count: usize) -> f32 {
         MemorySource::Manual => 0.9,
         MemorySource::Derived { .. } => 0.7,
         MemorySource::Import => 0.6,
-        MemorySource::AutoCapture { .. } => 0.5,
+        // LLM-written either way.
+        MemorySource::AutoCapture { .. } | MemorySource::Consolidated { .. } => 0.5,
     };
 
     let contradiction_weight = match contradiction_count {
@@ -39,7 +230,9 @@ pub fn trust_score(memory: &Memory, contradiction_count: usize) -> f32 {
     let score: f32 = 0.40 * verification_weight
         + 0.30 * source_weight
         + 0.20 * contradiction_weight
-        + 0.10 * quality;
+        + 0.10 * quality
+        + CORROBORATION_BONUS * corroboration(memory)
+        + ENDORSEMENT_BONUS * endorsement(memory);
 
     score.clamp(0.0, 1.0)
 }
@@ -47,7 +240,6 @@ pub fn trust_score(memory: &Memory, contradiction_count: usize) -> f32 {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::model::MemoryKind;
 
     fn base_memory() -> Memory {
         Memory::new(
@@ -122,4 +314,121 @@ mod tests {
         let score_1 = trust_score(&m, 1);
         assert!(score_0 > score_1);
     }
+
+    #[test]
+    fn test_expired_verification_decays() {
+        let now = Utc::now();
+        let fresh = base_memory().with_verification(VerificationStatus::Verified);
+        let mut lapsed = fresh.clone();
+        lapsed.verification_expires_at = Some(now - chrono::Duration::days(30));
+        let mut pending = fresh.clone();
+        pending.verification_expires_at = Some(now + chrono::Duration::days(30));
+
+        assert!((verified_weight(&pending, now) - 1.0).abs() < 1e-6);
+        // One half-life past expiry: halfway between verified and unverified.
+        assert!((verified_weight(&lapsed, now) - 0.75).abs() < 0.01);
+        assert!(trust_score(&lapsed, 0) < trust_score(&fresh, 0));
+        assert!(verification_due(&lapsed, now));
+        assert!(!verification_due(&pending, now));
+        assert!(!verification_due(&fresh, now));
+    }
+
+    #[cfg(feature = "runtime")]
+    #[test]
+    fn test_verification_expiry_only_for_verified() {
+        let retention: RetentionConfig =
+            toml::from_str("fact = { verification_expires_days = 90 }").unwrap();
+        let now = Utc::now();
+        assert_eq!(
+            verification_expiry(
+                VerificationStatus::Verified,
+                MemoryKind::Fact,
+                &retention,
+                now
+            ),
+            Some(now + chrono::Duration::days(90))
+        );
+        assert_eq!(
+            verification_expiry(
+                VerificationStatus::Disputed,
+                MemoryKind::Fact,
+                &retention,
+                now
+            ),
+            None
+        );
+        assert_eq!(
+            verification_expiry(
+                VerificationStatus::Verified,
+                MemoryKind::Decision,
+                &retention,
+                now
+            ),
+            None
+        );
+    }
+
+    #[cfg(feature = "runtime")]
+    #[test]
+    fn test_corroboration_counts_independent_asserters() {
+        let mut m = base_memory();
+        let alone = trust_score(&m, 0);
+
+        // The creator re-asserting their own memory doesn't count.
+        m.assertions.push(Assertion::new("user", "mcp"));
+        assert_eq!(corroboration(&m), 0.0);
+
+        m.assertions.push(Assertion::new("alice", "mcp"));
+        m.assertions.push(Assertion::new("bob", "mcp"));
+        assert_eq!(independent_asserters(&m), vec!["alice", "bob"]);
+        // Two of three asserters, single channel.
+        assert!((corroboration(&m) - 0.5).abs() < 1e-6);
+
+        m.assertions.push(Assertion::new("carol", "web"));
+        m.assertions.push(Assertion::new("dave", "hooks"));
+        assert!((corroboration(&m) - 1.0).abs() < 1e-6);
+        assert!((trust_score(&m, 0) - (alone + CORROBORATION_BONUS)).abs() < 1e-6);
+    }
+
+    #[cfg(feature = "runtime")]
+    #[tokio::test]
+    async fn test_record_assertion_refreshes_repeats() {
+        let storage = crate::storage::SqliteStorage::open_in_memory().unwrap();
+        let m = base_memory();
+        let updated_at = m.updated_at;
+        storage.save_memory(&m, None).await.unwrap();
+
+        record_assertion(&storage, m.id, Assertion::new("alice", "mcp"))
+            .await
+            .unwrap();
+        record_assertion(&storage, m.id, Assertion::new("alice", "mcp"))
+            .await
+            .unwrap();
+        let stored = record_assertion(&storage, m.id, Assertion::new("alice", "web"))
+            .await
+            .unwrap();
+
+        assert_eq!(stored.assertions.len(), 2);
+        // Re-asserting isn't an edit.
+        assert_eq!(stored.updated_at, updated_at);
+    }
+
+    #[cfg(feature = "runtime")]
+    #[tokio::test]
+    async fn test_endorsements_raise_trust() {
+        let storage = crate::storage::SqliteStorage::open_in_memory().unwrap();
+        let m = base_memory();
+        let alone = trust_score(&m, 0);
+        storage.save_memory(&m, None).await.unwrap();
+
+        assert!(endorse(&storage, m.id, "user").await.is_err());
+        endorse(&storage, m.id, "alice").await.unwrap();
+        let stored = endorse(&storage, m.id, "alice").await.unwrap();
+        assert_eq!(stored.endorsements.len(), 1);
+        assert_eq!(stored.updated_at, m.updated_at);
+        assert!((trust_score(&stored, 0) - (alone + ENDORSEMENT_BONUS / 3.0)).abs() < 1e-6);
+
+        let stored = withdraw_endorsement(&storage, m.id, "alice").await.unwrap();
+        assert!(stored.endorsements.is_empty());
+    }
 }
@@ -0,0 +1,576 @@
+//! Sandboxed WebAssembly plugins for capture classification and ranking.
+//!
+//! `[wasm_plugins]` points at up to two modules, usually per project in
+//! `.shabka/config.toml`:
+//!
+//! ```toml
+//! [wasm_plugins]
+//! classifier = ".shabka/plugins/classify.wasm"
+//! ranker = ".shabka/plugins/rank.wasm"
+//! ```
+//!
+//! A [`WasmClassifier`] sees every hook event with the built-in capture
+//! intent and may keep it, skip the event or change what gets captured. A
+//! [`WasmRanker`] scores each search result against the query, and the score
+//! is blended into the ranking with `ranker_weight`.
+//!
+//! Modules get no imports at all, so they can't touch files, the network or
+//! the clock; one that imports anything fails to load. Each call runs in a
+//! fresh instance with `fuel` instructions and `max_memory_mb` of memory, and
+//! a call that traps, runs out of fuel or returns garbage counts as no
+//! opinion. A module exports `memory`, `shabka_alloc(len: i32) -> i32` for
+//! the host to place its JSON input, and one of:
+//!
+//! - `classify(ptr: i32, len: i32) -> i64`: input `{"event": ..., "intent":
+//!   ...}`, output a [`ClassifierVerdict`] as JSON at `ptr << 32 | len`, or 0
+//!   to keep the built-in intent.
+//! - `score(ptr: i32, len: i32) -> f32`: input `{"query": ..., "memory":
+//!   ...}`, output a relevance in 0.0–1.0, or a negative value for no opinion.
+//!
+//! Plugins need a build with the `wasm-plugins` feature; without it a
+//! configured plugin is reported and ignored.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, ShabkaError};
+use crate::model::{Memory, MemoryKind};
+use crate::ranking::RankedResult;
+
+/// Largest classifier output read back from a module.
+#[cfg(feature = "wasm-plugins")]
+const MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// Which modules to load and how to run them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WasmPluginConfig {
+    /// Module exporting `classify`. Relative paths are resolved against the
+    /// project root.
+    #[serde(default)]
+    pub classifier: Option<String>,
+    /// Module exporting `score`. Relative paths are resolved against the
+    /// project root.
+    #[serde(default)]
+    pub ranker: Option<String>,
+    /// Share of the final search score taken from the ranker (default 0.2).
+    #[serde(default = "default_ranker_weight")]
+    pub ranker_weight: f32,
+    /// Instructions a single call may run (default 100 million).
+    #[serde(default = "default_fuel")]
+    pub fuel: u64,
+    /// Memory a module instance may grow to (default 32 MiB).
+    #[serde(default = "default_max_memory_mb")]
+    pub max_memory_mb: u32,
+}
+
+fn default_ranker_weight() -> f32 {
+    0.2
+}
+
+fn default_fuel() -> u64 {
+    100_000_000
+}
+
+fn default_max_memory_mb() -> u32 {
+    32
+}
+
+impl Default for WasmPluginConfig {
+    fn default() -> Self {
+        Self {
+            classifier: None,
+            ranker: None,
+            ranker_weight: default_ranker_weight(),
+            fuel: default_fuel(),
+            max_memory_mb: default_max_memory_mb(),
+        }
+    }
+}
+
+impl WasmPluginConfig {
+    /// Resolve relative module paths against `root`.
+    pub fn resolve_paths(&mut self, root: &std::path::Path) {
+        for path in [&mut self.classifier, &mut self.ranker]
+            .into_iter()
+            .flatten()
+        {
+            if std::path::Path::new(path.as_str()).is_relative() {
+                *path = root.join(&*path).to_string_lossy().into_owned();
+            }
+        }
+    }
+}
+
+/// What a classifier decided about one hook event.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "intent", rename_all = "snake_case")]
+pub enum ClassifierVerdict {
+    /// Use the built-in intent unchanged.
+    Keep,
+    /// Capture nothing for this event.
+    Skip {
+        #[serde(default)]
+        reason: Option<String>,
+    },
+    /// Capture the event, overriding the built-in intent's fields.
+    Capture(CaptureOverride),
+}
+
+/// Fields a classifier sets on a capture. Unset fields keep the built-in
+/// intent's values; when the built-in intent skipped the event, `title` and
+/// `content` are required.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct CaptureOverride {
+    #[serde(default)]
+    pub kind: Option<MemoryKind>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub content: Option<String>,
+    #[serde(default)]
+    pub importance: Option<f32>,
+    #[serde(default)]
+    pub confidence: Option<f32>,
+    /// Added to the built-in tags.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A module exporting `classify`.
+#[derive(Debug)]
+pub struct WasmClassifier {
+    module: WasmModule,
+}
+
+impl WasmClassifier {
+    /// The configured classifier, or `None` when there isn't one.
+    pub fn load(config: &WasmPluginConfig) -> Result<Option<Self>> {
+        let Some(path) = &config.classifier else {
+            return Ok(None);
+        };
+        let module = WasmModule::load(path, config)?;
+        module.require_export("classify")?;
+        Ok(Some(Self { module }))
+    }
+
+    /// Ask the module about `event`, given the built-in `intent`.
+    pub fn classify(
+        &self,
+        event: &serde_json::Value,
+        intent: &serde_json::Value,
+    ) -> Result<ClassifierVerdict> {
+        let input = serde_json::to_vec(&serde_json::json!({ "event": event, "intent": intent }))?;
+        let output = self.module.call_bytes("classify", &input)?;
+        if output.is_empty() {
+            return Ok(ClassifierVerdict::Keep);
+        }
+        serde_json::from_slice(&output).map_err(|e| {
+            ShabkaError::InvalidInput(format!(
+                "{}: classify returned an invalid verdict: {e}",
+                self.module.path
+            ))
+        })
+    }
+}
+
+/// A module exporting `score`, blended into search ranking.
+#[derive(Debug)]
+pub struct WasmRanker {
+    module: WasmModule,
+    weight: f32,
+}
+
+impl WasmRanker {
+    /// The configured ranker, or `None` when there isn't one.
+    pub fn load(config: &WasmPluginConfig) -> Result<Option<Self>> {
+        let Some(path) = &config.ranker else {
+            return Ok(None);
+        };
+        let module = WasmModule::load(path, config)?;
+        module.require_export("score")?;
+        Ok(Some(Self {
+            module,
+            weight: config.ranker_weight.clamp(0.0, 1.0),
+        }))
+    }
+
+    /// The module's relevance of `memory` to `query`, or `None` when it has
+    /// no opinion.
+    pub fn score(&self, memory: &Memory, query: &str) -> Result<Option<f32>> {
+        let input = serde_json::to_vec(&serde_json::json!({ "query": query, "memory": memory }))?;
+        let score = self.module.call_f32("score", &input)?;
+        Ok((score.is_finite() && score >= 0.0).then(|| score.min(1.0)))
+    }
+
+    /// Blend the module's score into each result and re-sort. Results it
+    /// fails on or has no opinion about keep their score.
+    pub fn rerank(&self, ranked: &mut [RankedResult], query: &str) {
+        for r in ranked.iter_mut() {
+            match self.score(&r.memory, query) {
+                Ok(Some(score)) => r.score = (1.0 - self.weight) * r.score + self.weight * score,
+                Ok(None) => {}
+                Err(e) => tracing::warn!("wasm ranker: {e}"),
+            }
+        }
+        ranked.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+}
+
+/// Load the configured ranker, logging instead of failing when it can't be.
+pub fn load_ranker(config: &WasmPluginConfig) -> Option<Arc<WasmRanker>> {
+    match WasmRanker::load(config) {
+        Ok(ranker) => ranker.map(Arc::new),
+        Err(e) => {
+            tracing::warn!("wasm ranker disabled: {e}");
+            None
+        }
+    }
+}
+
+fn plugin_error(path: &str, e: impl std::fmt::Display) -> ShabkaError {
+    ShabkaError::Config(format!("wasm plugin {path}: {e}"))
+}
+
+/// A compiled module and its limits.
+#[cfg(feature = "wasm-plugins")]
+struct WasmModule {
+    path: String,
+    engine: wasmtime::Engine,
+    module: wasmtime::Module,
+    fuel: u64,
+    max_memory_bytes: usize,
+}
+
+#[cfg(feature = "wasm-plugins")]
+impl std::fmt::Debug for WasmModule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmModule")
+            .field("path", &self.path)
+            .field("fuel", &self.fuel)
+            .field("max_memory_bytes", &self.max_memory_bytes)
+            .finish()
+    }
+}
+
+#[cfg(feature = "wasm-plugins")]
+impl WasmModule {
+    fn load(path: &str, config: &WasmPluginConfig) -> Result<Self> {
+        let mut engine_config = wasmtime::Config::new();
+        engine_config.consume_fuel(true);
+        let engine = wasmtime::Engine::new(&engine_config).map_err(|e| plugin_error(path, e))?;
+        let module =
+            wasmtime::Module::from_file(&engine, path).map_err(|e| plugin_error(path, e))?;
+        if let Some(import) = module.imports().next() {
+            return Err(plugin_error(
+                path,
+                format!(
+                    "imports '{}::{}', but plugins get no imports",
+                    import.module(),
+                    import.name()
+                ),
+            ));
+        }
+        Ok(Self {
+            path: path.to_string(),
+            engine,
+            module,
+            fuel: config.fuel,
+            max_memory_bytes: config.max_memory_mb as usize * 1024 * 1024,
+        })
+    }
+
+    fn require_export(&self, name: &str) -> Result<()> {
+        for export in [name, "memory", "shabka_alloc"] {
+            if self.module.get_export(export).is_none() {
+                return Err(plugin_error(
+                    &self.path,
+                    format!("missing export '{export}'"),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// A fresh instance with `input` copied into its memory.
+    fn instantiate(
+        &self,
+        input: &[u8],
+    ) -> Result<(
+        wasmtime::Store<wasmtime::StoreLimits>,
+        wasmtime::Instance,
+        i32,
+        i32,
+    )> {
+        let err = |e: wasmtime::Error| plugin_error(&self.path, format!("{e:#}"));
+        let limits = wasmtime::StoreLimitsBuilder::new()
+            .memory_size(self.max_memory_bytes)
+            .instances(1)
+            .build();
+        let mut store = wasmtime::Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.fuel).map_err(err)?;
+        let instance = wasmtime::Instance::new(&mut store, &self.module, &[]).map_err(err)?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "shabka_alloc")
+            .map_err(err)?;
+        let len =
+            i32::try_from(input.len()).map_err(|_| plugin_error(&self.path, "input too large"))?;
+        let ptr = alloc.call(&mut store, len).map_err(err)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| plugin_error(&self.path, "missing export 'memory'"))?;
+        memory
+            .write(&mut store, ptr as u32 as usize, input)
+            .map_err(|e| plugin_error(&self.path, e))?;
+        Ok((store, instance, ptr, len))
+    }
+
+    /// Call `export(ptr, len) -> i64` and read back the bytes it points at.
+    fn call_bytes(&self, export: &str, input: &[u8]) -> Result<Vec<u8>> {
+        let err = |e: wasmtime::Error| plugin_error(&self.path, format!("{export}: {e:#}"));
+        let (mut store, instance, ptr, len) = self.instantiate(input)?;
+        let func = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, export)
+            .map_err(err)?;
+        let packed = func.call(&mut store, (ptr, len)).map_err(err)? as u64;
+        let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        if out_len == 0 {
+            return Ok(Vec::new());
+        }
+        if out_len > MAX_OUTPUT_BYTES {
+            return Err(plugin_error(
+                &self.path,
+                format!("{export}: output of {out_len} bytes is too large"),
+            ));
+        }
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| plugin_error(&self.path, "missing export 'memory'"))?;
+        let mut output = vec![0; out_len];
+        memory
+            .read(&store, out_ptr, &mut output)
+            .map_err(|e| plugin_error(&self.path, format!("{export}: {e}")))?;
+        Ok(output)
+    }
+
+    /// Call `export(ptr, len) -> f32`.
+    fn call_f32(&self, export: &str, input: &[u8]) -> Result<f32> {
+        let err = |e: wasmtime::Error| plugin_error(&self.path, format!("{export}: {e:#}"));
+        let (mut store, instance, ptr, len) = self.instantiate(input)?;
+        let func = instance
+            .get_typed_func::<(i32, i32), f32>(&mut store, export)
+            .map_err(err)?;
+        func.call(&mut store, (ptr, len)).map_err(err)
+    }
+}
+
+/// Stand-in for builds without the `wasm-plugins` feature: loading fails.
+#[cfg(not(feature = "wasm-plugins"))]
+#[derive(Debug)]
+struct WasmModule {
+    path: String,
+}
+
+#[cfg(not(feature = "wasm-plugins"))]
+impl WasmModule {
+    fn load(path: &str, _config: &WasmPluginConfig) -> Result<Self> {
+        Err(plugin_error(
+            path,
+            "this build has no wasm runtime; rebuild with the `wasm-plugins` feature",
+        ))
+    }
+
+    fn require_export(&self, _name: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn call_bytes(&self, _export: &str, _input: &[u8]) -> Result<Vec<u8>> {
+        Err(plugin_error(&self.path, "no wasm runtime"))
+    }
+
+    fn call_f32(&self, _export: &str, _input: &[u8]) -> Result<f32> {
+        Err(plugin_error(&self.path, "no wasm runtime"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verdict_json() {
+        let skip: ClassifierVerdict =
+            serde_json::from_str(r#"{"intent": "skip", "reason": "generated file"}"#).unwrap();
+        assert_eq!(
+            skip,
+            ClassifierVerdict::Skip {
+                reason: Some("generated file".into())
+            }
+        );
+        let capture: ClassifierVerdict =
+            serde_json::from_str(r#"{"intent": "capture", "kind": "decision", "tags": ["adr"]}"#)
+                .unwrap();
+        assert_eq!(
+            capture,
+            ClassifierVerdict::Capture(CaptureOverride {
+                kind: Some(MemoryKind::Decision),
+                tags: vec!["adr".into()],
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            serde_json::from_str::<ClassifierVerdict>(r#"{"intent": "keep"}"#).unwrap(),
+            ClassifierVerdict::Keep
+        );
+    }
+
+    #[test]
+    fn test_resolve_paths() {
+        let mut config = WasmPluginConfig {
+            classifier: Some(".shabka/classify.wasm".into()),
+            ranker: Some("/opt/rank.wasm".into()),
+            ..Default::default()
+        };
+        config.resolve_paths(std::path::Path::new("/work/app"));
+        assert_eq!(
+            config.classifier.as_deref(),
+            Some("/work/app/.shabka/classify.wasm")
+        );
+        assert_eq!(config.ranker.as_deref(), Some("/opt/rank.wasm"));
+    }
+
+    #[cfg(not(feature = "wasm-plugins"))]
+    #[test]
+    fn test_load_fails_without_runtime() {
+        let config = WasmPluginConfig {
+            ranker: Some("rank.wasm".into()),
+            ..Default::default()
+        };
+        assert!(WasmRanker::load(&config).is_err());
+        assert!(load_ranker(&config).is_none());
+        assert!(WasmClassifier::load(&config).unwrap().is_none());
+    }
+
+    #[cfg(feature = "wasm-plugins")]
+    mod runtime {
+        use super::*;
+
+        /// Bump allocator shared by the test modules.
+        const ALLOC: &str = r#"
+            (memory (export "memory") 1)
+            (global $next (mut i32) (i32.const 1024))
+            (func (export "shabka_alloc") (param $len i32) (result i32)
+              (local $ptr i32)
+              global.get $next
+              local.set $ptr
+              global.get $next
+              local.get $len
+              i32.add
+              global.set $next
+              local.get $ptr)
+        "#;
+
+        fn module_file(body: &str) -> std::path::PathBuf {
+            let path =
+                std::env::temp_dir().join(format!("shabka-wasm-{}.wat", uuid::Uuid::now_v7()));
+            std::fs::write(&path, format!("(module {body} {ALLOC})")).unwrap();
+            path
+        }
+
+        fn config(
+            classifier: Option<&std::path::Path>,
+            ranker: Option<&std::path::Path>,
+        ) -> WasmPluginConfig {
+            WasmPluginConfig {
+                classifier: classifier.map(|p| p.to_string_lossy().into_owned()),
+                ranker: ranker.map(|p| p.to_string_lossy().into_owned()),
+                fuel: 1_000_000,
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn test_classifier_returns_verdict() {
+            let verdict = r#"{"intent":"skip","reason":"noise"}"#;
+            let path = module_file(&format!(
+                r#"(data (i32.const 16) "{}")
+                (func (export "classify") (param i32 i32) (result i64)
+                  i64.const {})"#,
+                verdict.replace('"', "\\\""),
+                (16u64 << 32) | verdict.len() as u64
+            ));
+            let classifier = WasmClassifier::load(&config(Some(&path), None))
+                .unwrap()
+                .unwrap();
+            let result = classifier.classify(&serde_json::json!({}), &serde_json::json!({}));
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(
+                result.unwrap(),
+                ClassifierVerdict::Skip {
+                    reason: Some("noise".into())
+                }
+            );
+        }
+
+        #[test]
+        fn test_ranker_scores_by_input() {
+            // Scores the input length, so a longer memory scores higher.
+            let path = module_file(
+                r#"(func (export "score") (param i32 i32) (result f32)
+                  local.get 1
+                  f32.convert_i32_u
+                  f32.const 10000
+                  f32.div)"#,
+            );
+            let ranker = WasmRanker::load(&config(None, Some(&path)))
+                .unwrap()
+                .unwrap();
+            let short = Memory::new("a".into(), "b".into(), MemoryKind::Fact, "alice".into());
+            let long = Memory::new(
+                "a".into(),
+                "b".repeat(500),
+                MemoryKind::Fact,
+                "alice".into(),
+            );
+            let short_score = ranker.score(&short, "q").unwrap().unwrap();
+            let long_score = ranker.score(&long, "q").unwrap().unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert!(long_score > short_score);
+        }
+
+        #[test]
+        fn test_runaway_module_runs_out_of_fuel() {
+            let path = module_file(
+                r#"(func (export "score") (param i32 i32) (result f32)
+                  (loop $spin br $spin)
+                  f32.const 1)"#,
+            );
+            let ranker = WasmRanker::load(&config(None, Some(&path)))
+                .unwrap()
+                .unwrap();
+            let memory = Memory::new("a".into(), "b".into(), MemoryKind::Fact, "alice".into());
+            let result = ranker.score(&memory, "q");
+            std::fs::remove_file(&path).unwrap();
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn test_modules_with_imports_are_rejected() {
+            let path = module_file(
+                r#"(import "env" "read_file" (func (param i32)))
+                (func (export "score") (param i32 i32) (result f32) f32.const 1)"#,
+            );
+            let result = WasmRanker::load(&config(None, Some(&path)));
+            std::fs::remove_file(&path).unwrap();
+            assert!(result
+                .unwrap_err()
+                .to_string()
+                .contains("imports 'env::read_file'"));
+        }
+    }
+}
@@ -56,6 +56,8 @@ pub fn ollama_embedder() -> EmbeddingService {
         base_url: None,
         dimensions: None,
         env_var: None,
+        command: None,
+        reembed_on_edit: true,
     };
     EmbeddingService::from_config(&config).expect("ollama embedder config should be valid")
 }
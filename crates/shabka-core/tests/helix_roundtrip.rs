@@ -2,7 +2,9 @@
 //!
 //! Requires HelixDB running at localhost:6969 (`just db`) and Ollama with nomic-embed-text.
 //!
-//! Run: `cargo test -p shabka-core --no-default-features --test helix_roundtrip -- --ignored`
+//! Run: `cargo test -p shabka-core --no-default-features --features runtime --test helix_roundtrip -- --ignored`
+
+#![cfg(feature = "runtime")]
 
 mod common;
 
@@ -3,7 +3,9 @@
 //!
 //! Requires HelixDB running at localhost:6969 (`just db`) and Ollama with nomic-embed-text.
 //!
-//! Run: `cargo test -p shabka-core --no-default-features --test mcp_integration -- --ignored`
+//! Run: `cargo test -p shabka-core --no-default-features --features runtime --test mcp_integration -- --ignored`
+
+#![cfg(feature = "runtime")]
 
 mod common;
 
@@ -203,8 +205,10 @@ async fn test_follow_chain_traversal() {
         m_a.id,
         &[RelationType::CausedBy, RelationType::Fixes],
         None,
+        None,
     )
-    .await;
+    .await
+    .links;
 
     // Chain should find at least B (direct neighbor of A)
     if !chain.is_empty() {
@@ -259,6 +263,7 @@ async fn test_search_ranking_ordering() {
             keyword_score: 0.0,
             relation_count: 0,
             contradiction_count: 0,
+            centrality: 0.0,
         })
         .collect();
 
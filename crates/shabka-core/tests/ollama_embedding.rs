@@ -4,7 +4,9 @@
 //! - Ollama running at localhost:11434 with `nomic-embed-text` model pulled
 //! - For `test_ollama_search_with_helix`: HelixDB running at localhost:6969
 //!
-//! Run: `cargo test -p shabka-core --no-default-features --test ollama_embedding -- --ignored`
+//! Run: `cargo test -p shabka-core --no-default-features --features runtime --test ollama_embedding -- --ignored`
+
+#![cfg(feature = "runtime")]
 
 mod common;
 
@@ -0,0 +1,98 @@
+//! Stress test for several processes sharing one SQLite file.
+//!
+//! Each `SqliteStorage` owns its own connection, so separate instances on one
+//! path contend for SQLite's file locks just like the hooks, CLI, web server
+//! and MCP server do.
+//!
+//! Run: `cargo test -p shabka-core --test sqlite_concurrency`
+
+#![cfg(feature = "runtime")]
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use shabka_core::model::{Memory, MemoryKind, TimelineQuery};
+use shabka_core::storage::{SqliteOptions, SqliteStorage, StorageBackend};
+
+const WRITERS: usize = 4;
+const SAVES_PER_WRITER: usize = 25;
+const READERS: usize = 2;
+
+fn temp_db() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("shabka-concurrency-{}", uuid::Uuid::now_v7()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join("shabka.db")
+}
+
+async fn hammer(path: PathBuf, options: SqliteOptions) {
+    // Create the schema once before the contenders start.
+    drop(SqliteStorage::open_with(&path, options.clone()).unwrap());
+
+    let mut tasks = Vec::new();
+    for writer in 0..WRITERS {
+        let storage = SqliteStorage::open_with(&path, options.clone()).unwrap();
+        tasks.push(tokio::spawn(async move {
+            for i in 0..SAVES_PER_WRITER {
+                // Simulates a hook capture: a memory with its embedding.
+                let memory = Memory::new(
+                    format!("capture {writer}-{i}"),
+                    "content".into(),
+                    MemoryKind::Observation,
+                    "hooks".into(),
+                );
+                let embedding = vec![writer as f32, i as f32, 1.0];
+                storage
+                    .save_memory(&memory, Some(&embedding))
+                    .await
+                    .expect("concurrent save failed");
+            }
+        }));
+    }
+    for _ in 0..READERS {
+        let storage = SqliteStorage::open_with(&path, options.clone()).unwrap();
+        tasks.push(tokio::spawn(async move {
+            // Simulates the web dashboard polling the timeline.
+            for _ in 0..SAVES_PER_WRITER {
+                storage
+                    .timeline(&TimelineQuery {
+                        limit: 50,
+                        ..Default::default()
+                    })
+                    .await
+                    .expect("concurrent read failed");
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    let storage = SqliteStorage::open(&path).unwrap();
+    let count = storage
+        .timeline_count(&TimelineQuery::default())
+        .await
+        .unwrap();
+    assert_eq!(count, WRITERS * SAVES_PER_WRITER);
+
+    // Every writer released its lock.
+    let mut lock = path.clone().into_os_string();
+    lock.push(".writer.lock");
+    assert!(!PathBuf::from(lock).exists());
+
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_captures_and_reads() {
+    hammer(temp_db(), SqliteOptions::default()).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_captures_with_short_timeout_and_writer_lock() {
+    let options = SqliteOptions {
+        busy_timeout: Duration::from_millis(50),
+        busy_retries: 10,
+        writer_lock: true,
+    };
+    hammer(temp_db(), options).await;
+}
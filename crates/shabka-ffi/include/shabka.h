@@ -0,0 +1,44 @@
+/*
+ * C bindings for Shabka (crates/shabka-ffi).
+ *
+ * Strings returned by these functions are NUL-terminated UTF-8 owned by the
+ * library; release them with shabka_string_free(). On failure a function
+ * returns NULL and shabka_last_error() describes why (per thread, valid
+ * until the next failing call on that thread).
+ */
+#ifndef SHABKA_H
+#define SHABKA_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct Shabka Shabka;
+
+/* ABI version; bumped on incompatible changes. */
+int shabka_abi_version(void);
+
+/* Open the store configured for project_dir (NULL: global configuration). */
+Shabka *shabka_open(const char *project_dir);
+void shabka_close(Shabka *handle);
+
+/* JSON array of hits: id, title, content, kind, tags, project_id, score. */
+char *shabka_search(const Shabka *handle, const char *query, size_t limit);
+
+/* Store a JSON object {title, content, kind?, tags?, importance?, project_id?};
+ * returns the new memory's ID. */
+char *shabka_store(const Shabka *handle, const char *memory_json);
+
+/* Markdown context pack for query within token_budget tokens. */
+char *shabka_context_pack(const Shabka *handle, const char *query, size_t token_budget);
+
+void shabka_string_free(char *s);
+const char *shabka_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SHABKA_H */
@@ -0,0 +1,5 @@
+"""Embedded Shabka memory: search, store and context packs without the CLI or MCP server."""
+
+from .shabka import Shabka, __version__
+
+__all__ = ["Shabka", "__version__"]
@@ -0,0 +1,40 @@
+"""LangChain retriever over an embedded Shabka store.
+
+    from shabka.langchain import ShabkaRetriever
+    retriever = ShabkaRetriever(limit=5)
+    docs = retriever.invoke("connection pool errors")
+
+Requires ``langchain-core``; the rest of the package does not.
+"""
+
+from typing import Any, List, Optional
+
+from langchain_core.callbacks import CallbackManagerForRetrieverRun
+from langchain_core.documents import Document
+from langchain_core.retrievers import BaseRetriever
+
+from . import Shabka
+
+
+class ShabkaRetriever(BaseRetriever):
+    """Returns Shabka search hits as documents, with the hit's fields as metadata."""
+
+    project_dir: Optional[str] = None
+    limit: int = 10
+    store: Any = None
+
+    def model_post_init(self, __context: Any) -> None:
+        if self.store is None:
+            self.store = Shabka(self.project_dir)
+
+    def _get_relevant_documents(
+        self, query: str, *, run_manager: CallbackManagerForRetrieverRun
+    ) -> List[Document]:
+        hits = self.store.search(query, limit=self.limit)
+        return [
+            Document(
+                page_content=hit["content"],
+                metadata={k: v for k, v in hit.items() if k != "content"},
+            )
+            for hit in hits
+        ]
@@ -0,0 +1,215 @@
+//! C ABI over [`Shabka`], declared in `include/shabka.h`.
+//!
+//! Results cross the boundary as NUL-terminated UTF-8 JSON owned by the
+//! library: free them with [`shabka_string_free`]. Functions that fail
+//! return `NULL` and leave a message for [`shabka_last_error`], which is
+//! per thread and valid until the next failing call on it.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::path::Path;
+use std::ptr;
+
+use crate::{NewMemory, Shabka};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(message: impl std::fmt::Display) {
+    let message = CString::new(message.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// Borrow a C string argument; `NULL` reads as `None`.
+///
+/// # Safety
+/// `s` must be `NULL` or a valid NUL-terminated string.
+unsafe fn arg<'a>(s: *const c_char, name: &str) -> Result<Option<&'a str>, String> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map(Some)
+        .map_err(|_| format!("{name} is not valid UTF-8"))
+}
+
+/// Run `f`, turning errors and panics into `NULL` plus a last error.
+fn guard(f: impl FnOnce() -> Result<String, String>) -> *mut c_char {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(Ok(out)) => CString::new(out).map_or(ptr::null_mut(), CString::into_raw),
+        Ok(Err(e)) => {
+            set_error(e);
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_error("panic inside shabka");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Open the store configured for `project_dir` (may be `NULL`). Returns
+/// `NULL` on failure. Close it with [`shabka_close`].
+///
+/// # Safety
+/// `project_dir` must be `NULL` or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn shabka_open(project_dir: *const c_char) -> *mut Shabka {
+    let dir = match arg(project_dir, "project_dir") {
+        Ok(dir) => dir,
+        Err(e) => {
+            set_error(e);
+            return ptr::null_mut();
+        }
+    };
+    match Shabka::open(dir.map(Path::new)) {
+        Ok(shabka) => Box::into_raw(Box::new(shabka)),
+        Err(e) => {
+            set_error(format!("{e:#}"));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Close a store opened with [`shabka_open`]. `NULL` is ignored.
+///
+/// # Safety
+/// `handle` must come from [`shabka_open`] and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn shabka_close(handle: *mut Shabka) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Search; returns a JSON array of hits (`id`, `title`, `content`, `kind`,
+/// `tags`, `project_id`, `score`), best first.
+///
+/// # Safety
+/// `handle` must be a live store and `query` a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn shabka_search(
+    handle: *const Shabka,
+    query: *const c_char,
+    limit: usize,
+) -> *mut c_char {
+    let Some(shabka) = handle.as_ref() else {
+        set_error("handle is NULL");
+        return ptr::null_mut();
+    };
+    guard(|| {
+        let query = arg(query, "query")?.unwrap_or_default();
+        let hits = shabka.search(query, limit).map_err(|e| format!("{e:#}"))?;
+        serde_json::to_string(&hits).map_err(|e| e.to_string())
+    })
+}
+
+/// Store a memory given as a JSON object (`title`, `content`, and optional
+/// `kind`, `tags`, `importance`, `project_id`); returns its ID.
+///
+/// # Safety
+/// `handle` must be a live store and `memory_json` a valid NUL-terminated
+/// string.
+#[no_mangle]
+pub unsafe extern "C" fn shabka_store(
+    handle: *const Shabka,
+    memory_json: *const c_char,
+) -> *mut c_char {
+    let Some(shabka) = handle.as_ref() else {
+        set_error("handle is NULL");
+        return ptr::null_mut();
+    };
+    guard(|| {
+        let json = arg(memory_json, "memory_json")?.ok_or("memory_json is NULL")?;
+        let memory: NewMemory =
+            serde_json::from_str(json).map_err(|e| format!("invalid memory JSON: {e}"))?;
+        let id = shabka.store(memory).map_err(|e| format!("{e:#}"))?;
+        Ok(id.to_string())
+    })
+}
+
+/// A markdown context pack for `query` within `token_budget` tokens.
+///
+/// # Safety
+/// `handle` must be a live store and `query` a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn shabka_context_pack(
+    handle: *const Shabka,
+    query: *const c_char,
+    token_budget: usize,
+) -> *mut c_char {
+    let Some(shabka) = handle.as_ref() else {
+        set_error("handle is NULL");
+        return ptr::null_mut();
+    };
+    guard(|| {
+        let query = arg(query, "query")?.unwrap_or_default();
+        shabka
+            .context_pack(query, token_budget)
+            .map_err(|e| format!("{e:#}"))
+    })
+}
+
+/// Free a string returned by this library. `NULL` is ignored.
+///
+/// # Safety
+/// `s` must come from this library and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn shabka_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// The last error on this thread, or `NULL`. Owned by the library.
+#[no_mangle]
+pub extern "C" fn shabka_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+}
+
+/// Version of the bindings' ABI, bumped on incompatible changes.
+#[no_mangle]
+pub extern "C" fn shabka_abi_version() -> c_int {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_shabka;
+
+    unsafe fn take(s: *mut c_char) -> String {
+        assert!(!s.is_null(), "{:?}", CStr::from_ptr(shabka_last_error()));
+        let out = CStr::from_ptr(s).to_str().unwrap().to_string();
+        shabka_string_free(s);
+        out
+    }
+
+    #[test]
+    fn test_c_api_round_trip() {
+        let handle = Box::into_raw(Box::new(test_shabka()));
+        unsafe {
+            let memory = CString::new(r#"{"title": "Retry budget", "content": "Cap retries at three.", "kind": "decision"}"#)
+                .unwrap();
+            let id = take(shabka_store(handle, memory.as_ptr()));
+            assert_eq!(id.len(), 36);
+
+            let query = CString::new("retry budget").unwrap();
+            let hits: serde_json::Value =
+                serde_json::from_str(&take(shabka_search(handle, query.as_ptr(), 5))).unwrap();
+            assert_eq!(hits[0]["id"], id);
+
+            let bad = CString::new("{}").unwrap();
+            assert!(shabka_store(handle, bad.as_ptr()).is_null());
+            let error = CStr::from_ptr(shabka_last_error()).to_str().unwrap();
+            assert!(error.contains("invalid memory JSON"));
+
+            assert!(
+                take(shabka_context_pack(handle, query.as_ptr(), 1000)).contains("Retry budget")
+            );
+            shabka_close(handle);
+        }
+    }
+}
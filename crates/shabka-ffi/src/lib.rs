@@ -0,0 +1,188 @@
+//! Language bindings for Shabka.
+//!
+//! [`Shabka`] is a small, blocking API over `shabka-core` — search, store
+//! and context packs — meant to stay stable while the core crate changes.
+//! It is exposed twice: as a C ABI in [`c_api`] (declared in
+//! `include/shabka.h`), and, with the `python` feature, as the `shabka`
+//! Python module built by maturin. Both open the same store, with the same
+//! configuration, as the CLI and MCP server.
+
+pub mod c_api;
+#[cfg(feature = "python")]
+mod python;
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use shabka_core::client;
+use shabka_core::config::ShabkaConfig;
+use shabka_core::context_pack::format_context_pack;
+use shabka_core::model::{CreateMemoryInput, MemoryKind};
+use shabka_core::services::Services;
+use uuid::Uuid;
+
+/// One search result, with its content so callers need no second lookup.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SearchHit {
+    pub id: Uuid,
+    pub title: String,
+    pub content: String,
+    pub kind: String,
+    pub tags: Vec<String>,
+    pub project_id: Option<String>,
+    pub score: f32,
+}
+
+/// A memory to store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewMemory {
+    pub title: String,
+    pub content: String,
+    /// Memory kind, e.g. `observation`, `decision` or `fact`.
+    #[serde(default = "default_kind")]
+    pub kind: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default = "default_importance")]
+    pub importance: f32,
+    #[serde(default)]
+    pub project_id: Option<String>,
+}
+
+fn default_kind() -> String {
+    "observation".to_string()
+}
+
+fn default_importance() -> f32 {
+    0.5
+}
+
+/// An embedded Shabka store: a blocking [`client::Shabka`].
+pub struct Shabka {
+    client: client::Shabka,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl Shabka {
+    /// Open the store configured for `project_dir` (global config plus that
+    /// directory's `.shabka/` layer), or the global configuration.
+    pub fn open(project_dir: Option<&Path>) -> Result<Self> {
+        let config = ShabkaConfig::load(project_dir).context("failed to load configuration")?;
+        Self::with_services(Services::new(config))
+    }
+
+    /// A store over an existing service registry.
+    pub fn with_services(services: Arc<Services>) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()
+            .context("failed to start runtime")?;
+        Ok(Self {
+            client: client::Shabka::with_services(services),
+            runtime,
+        })
+    }
+
+    /// Hybrid search, best first. `query` accepts the same field operators
+    /// as `shabka search`.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        let ranked = self.runtime.block_on(self.client.recall(query, limit))?;
+        Ok(ranked
+            .into_iter()
+            .map(|r| SearchHit {
+                id: r.memory.id,
+                kind: r.memory.kind.to_string(),
+                title: r.memory.title,
+                content: r.memory.content,
+                tags: r.memory.tags,
+                project_id: r.memory.project_id,
+                score: r.score,
+            })
+            .collect())
+    }
+
+    /// Save a memory with dedup and auto-relate, returning the ID of the
+    /// memory that holds it (an existing one for a near-duplicate).
+    pub fn store(&self, input: NewMemory) -> Result<Uuid> {
+        let kind: MemoryKind = input.kind.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+        let outcome = self
+            .runtime
+            .block_on(self.client.remember(CreateMemoryInput {
+                title: input.title,
+                content: input.content,
+                kind,
+                tags: input.tags,
+                importance: input.importance,
+                scope: None,
+                related_to: Vec::new(),
+                project_id: input.project_id,
+                privacy: None,
+            }))
+            .context("failed to save memory")?;
+        Ok(outcome.id())
+    }
+
+    /// A markdown context pack of the memories most relevant to `query`
+    /// that fit in `token_budget`.
+    pub fn context_pack(&self, query: &str, token_budget: usize) -> Result<String> {
+        let pack = self
+            .runtime
+            .block_on(self.client.context_pack(query, token_budget))?;
+        Ok(format_context_pack(&pack))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shabka_core::storage::{SqliteStorage, Storage};
+
+    pub(crate) fn test_shabka() -> Shabka {
+        let storage = Storage::Sqlite(SqliteStorage::open_in_memory().unwrap());
+        let mut config = ShabkaConfig::default_config();
+        config.history.enabled = false;
+        Shabka::with_services(Services::with_storage(config, storage)).unwrap()
+    }
+
+    #[test]
+    fn test_store_search_and_context_pack() {
+        let shabka = test_shabka();
+        let id = shabka
+            .store(NewMemory {
+                title: "Connection pool exhaustion".into(),
+                content: "The importer leaks connections when a batch fails.".into(),
+                kind: "error".into(),
+                tags: vec!["db".into()],
+                importance: 0.8,
+                project_id: Some("importer".into()),
+            })
+            .unwrap();
+
+        let hits = shabka.search("connection pool", 5).unwrap();
+        assert_eq!(hits[0].id, id);
+        assert_eq!(hits[0].kind, "error");
+        assert!(hits[0].content.contains("leaks"));
+
+        let pack = shabka.context_pack("project:importer pool", 2000).unwrap();
+        assert!(pack.contains("Connection pool exhaustion"));
+    }
+
+    #[test]
+    fn test_store_rejects_unknown_kind() {
+        let shabka = test_shabka();
+        let err = shabka
+            .store(NewMemory {
+                title: "T".into(),
+                content: "C".into(),
+                kind: "gossip".into(),
+                tags: Vec::new(),
+                importance: 0.5,
+                project_id: None,
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("gossip"));
+    }
+}
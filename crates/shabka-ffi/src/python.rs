@@ -0,0 +1,116 @@
+//! The `shabka` Python module (`python` feature, built with maturin).
+//!
+//! ```python
+//! import shabka
+//!
+//! memory = shabka.Shabka()                 # or Shabka(project_dir="...")
+//! memory_id = memory.store("Retry budget", "Cap retries at three.", kind="decision")
+//! for hit in memory.search("retries", limit=5):
+//!     print(hit["score"], hit["title"])
+//! prompt_context = memory.context_pack("retries", token_budget=2000)
+//! ```
+//!
+//! Calls release the GIL while they search or embed, so other Python
+//! threads keep running.
+
+use std::path::PathBuf;
+
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::{NewMemory, SearchHit};
+
+fn runtime_error(e: anyhow::Error) -> PyErr {
+    PyRuntimeError::new_err(format!("{e:#}"))
+}
+
+fn hit_to_dict<'py>(py: Python<'py>, hit: &SearchHit) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("id", hit.id.to_string())?;
+    dict.set_item("title", &hit.title)?;
+    dict.set_item("content", &hit.content)?;
+    dict.set_item("kind", &hit.kind)?;
+    dict.set_item("tags", &hit.tags)?;
+    dict.set_item("project_id", &hit.project_id)?;
+    dict.set_item("score", hit.score)?;
+    Ok(dict)
+}
+
+/// An embedded Shabka memory store.
+#[pyclass(name = "Shabka", module = "shabka", frozen)]
+struct PyShabka {
+    inner: crate::Shabka,
+}
+
+#[pymethods]
+impl PyShabka {
+    /// Open the store configured for `project_dir`, or the global one.
+    #[new]
+    #[pyo3(signature = (project_dir=None))]
+    fn new(py: Python<'_>, project_dir: Option<PathBuf>) -> PyResult<Self> {
+        let inner = py
+            .allow_threads(|| crate::Shabka::open(project_dir.as_deref()))
+            .map_err(runtime_error)?;
+        Ok(Self { inner })
+    }
+
+    /// Search memories; returns dicts with `id`, `title`, `content`, `kind`,
+    /// `tags`, `project_id` and `score`, best first.
+    #[pyo3(signature = (query, limit=10))]
+    fn search<'py>(
+        &self,
+        py: Python<'py>,
+        query: &str,
+        limit: usize,
+    ) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let hits = py
+            .allow_threads(|| self.inner.search(query, limit))
+            .map_err(runtime_error)?;
+        hits.iter().map(|hit| hit_to_dict(py, hit)).collect()
+    }
+
+    /// Store a memory and return its ID.
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (title, content, kind="observation", tags=Vec::new(), importance=0.5, project_id=None))]
+    fn store(
+        &self,
+        py: Python<'_>,
+        title: String,
+        content: String,
+        kind: &str,
+        tags: Vec<String>,
+        importance: f32,
+        project_id: Option<String>,
+    ) -> PyResult<String> {
+        if !(0.0..=1.0).contains(&importance) {
+            return Err(PyValueError::new_err("importance must be between 0 and 1"));
+        }
+        let memory = NewMemory {
+            title,
+            content,
+            kind: kind.to_string(),
+            tags,
+            importance,
+            project_id,
+        };
+        py.allow_threads(|| self.inner.store(memory))
+            .map(|id| id.to_string())
+            .map_err(runtime_error)
+    }
+
+    /// Markdown context of the memories most relevant to `query` that fit
+    /// in `token_budget` tokens, ready to paste into a prompt.
+    #[pyo3(signature = (query, token_budget=2000))]
+    fn context_pack(&self, py: Python<'_>, query: &str, token_budget: usize) -> PyResult<String> {
+        py.allow_threads(|| self.inner.context_pack(query, token_budget))
+            .map_err(runtime_error)
+    }
+}
+
+#[pymodule]
+fn shabka(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyShabka>()?;
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    Ok(())
+}
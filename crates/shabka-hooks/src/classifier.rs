@@ -0,0 +1,254 @@
+//! The `[wasm_plugins] classifier`, consulted after the built-in rules.
+//!
+//! The module sees the raw hook event and what [`handlers::classify`]
+//! decided, and its [`ClassifierVerdict`] keeps, skips or reshapes the
+//! capture. A classifier that fails to load or errors is logged and the
+//! built-in intent stands.
+//!
+//! [`handlers::classify`]: crate::handlers::classify
+
+use shabka_core::model::MemoryKind;
+use shabka_core::wasm_plugins::{
+    CaptureOverride, ClassifierVerdict, WasmClassifier, WasmPluginConfig,
+};
+
+use crate::event::CaptureIntent;
+
+/// Importance and confidence of a capture the built-in rules skipped.
+const DEFAULT_IMPORTANCE: f32 = 0.5;
+const DEFAULT_CONFIDENCE: f32 = 0.5;
+
+/// Let `classifier` keep, skip or reshape the built-in `intent` for the raw
+/// hook `event`.
+pub fn refine(
+    classifier: &WasmClassifier,
+    event: &serde_json::Value,
+    intent: CaptureIntent,
+) -> CaptureIntent {
+    match classifier.classify(event, &intent_json(&intent)) {
+        Ok(verdict) => apply(intent, verdict),
+        Err(e) => {
+            tracing::warn!("shabka-hooks: {e}");
+            intent
+        }
+    }
+}
+
+/// Load the classifier named in `[wasm_plugins]`, logging a failure.
+pub fn load(config: &WasmPluginConfig) -> Option<WasmClassifier> {
+    WasmClassifier::load(config).unwrap_or_else(|e| {
+        tracing::warn!("shabka-hooks: wasm classifier disabled: {e}");
+        None
+    })
+}
+
+/// The built-in intent as the classifier sees it.
+fn intent_json(intent: &CaptureIntent) -> serde_json::Value {
+    match intent {
+        CaptureIntent::Skip { reason } => serde_json::json!({
+            "intent": "skip",
+            "reason": reason,
+        }),
+        CaptureIntent::Save {
+            kind,
+            title,
+            importance,
+            confidence,
+            tags,
+            ..
+        } => serde_json::json!({
+            "intent": "save",
+            "kind": kind,
+            "title": title,
+            "importance": importance,
+            "confidence": confidence,
+            "tags": tags,
+        }),
+        CaptureIntent::Buffer {
+            kind,
+            title,
+            importance,
+            confidence,
+            tags,
+            event_type,
+            ..
+        } => serde_json::json!({
+            "intent": "buffer",
+            "kind": kind,
+            "title": title,
+            "importance": importance,
+            "confidence": confidence,
+            "tags": tags,
+            "event_type": event_type,
+        }),
+    }
+}
+
+fn apply(intent: CaptureIntent, verdict: ClassifierVerdict) -> CaptureIntent {
+    match verdict {
+        ClassifierVerdict::Keep => intent,
+        ClassifierVerdict::Skip { reason } => CaptureIntent::Skip {
+            reason: format!(
+                "wasm classifier: {}",
+                reason.as_deref().unwrap_or("skipped")
+            ),
+        },
+        ClassifierVerdict::Capture(o) => capture(intent, o),
+    }
+}
+
+fn capture(intent: CaptureIntent, o: CaptureOverride) -> CaptureIntent {
+    let importance = o.importance.map(|i| i.clamp(0.0, 1.0));
+    let confidence = o.confidence.map(|c| c.clamp(0.0, 1.0));
+    match intent {
+        CaptureIntent::Save {
+            kind,
+            title,
+            content,
+            importance: old_importance,
+            confidence: old_confidence,
+            tags,
+            code,
+        } => CaptureIntent::Save {
+            kind: o.kind.unwrap_or(kind),
+            title: o.title.unwrap_or(title),
+            content: o.content.unwrap_or(content),
+            importance: importance.unwrap_or(old_importance),
+            confidence: confidence.unwrap_or(old_confidence),
+            tags: merge_tags(tags, o.tags),
+            code,
+        },
+        CaptureIntent::Buffer {
+            kind,
+            title,
+            content,
+            importance: old_importance,
+            confidence: old_confidence,
+            tags,
+            file_path,
+            event_type,
+            code,
+        } => CaptureIntent::Buffer {
+            kind: o.kind.unwrap_or(kind),
+            title: o.title.unwrap_or(title),
+            content: o.content.unwrap_or(content),
+            importance: importance.unwrap_or(old_importance),
+            confidence: confidence.unwrap_or(old_confidence),
+            tags: merge_tags(tags, o.tags),
+            file_path,
+            event_type,
+            code,
+        },
+        CaptureIntent::Skip { reason } => match (o.title, o.content) {
+            (Some(title), Some(content)) => CaptureIntent::Save {
+                kind: o.kind.unwrap_or(MemoryKind::Observation),
+                title,
+                content,
+                importance: importance.unwrap_or(DEFAULT_IMPORTANCE),
+                confidence: confidence.unwrap_or(DEFAULT_CONFIDENCE),
+                tags: merge_tags(Vec::new(), o.tags),
+                code: None,
+            },
+            _ => {
+                tracing::warn!(
+                    "shabka-hooks: wasm classifier asked to capture a skipped event without a title and content"
+                );
+                CaptureIntent::Skip { reason }
+            }
+        },
+    }
+}
+
+fn merge_tags(mut tags: Vec<String>, extra: Vec<String>) -> Vec<String> {
+    for tag in extra {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save() -> CaptureIntent {
+        CaptureIntent::Save {
+            kind: MemoryKind::Observation,
+            title: "Edited src/main.rs".into(),
+            content: "diff".into(),
+            importance: 0.4,
+            confidence: 0.6,
+            tags: vec!["edit".into()],
+            code: None,
+        }
+    }
+
+    #[test]
+    fn test_capture_overrides_fields() {
+        let intent = apply(
+            save(),
+            ClassifierVerdict::Capture(CaptureOverride {
+                kind: Some(MemoryKind::Decision),
+                importance: Some(1.5),
+                tags: vec!["edit".into(), "adr".into()],
+                ..Default::default()
+            }),
+        );
+        let CaptureIntent::Save {
+            kind,
+            title,
+            importance,
+            confidence,
+            tags,
+            ..
+        } = intent
+        else {
+            panic!("expected a save");
+        };
+        assert_eq!(kind, MemoryKind::Decision);
+        assert_eq!(title, "Edited src/main.rs");
+        assert_eq!(importance, 1.0);
+        assert_eq!(confidence, 0.6);
+        assert_eq!(tags, ["edit", "adr"]);
+    }
+
+    #[test]
+    fn test_skip_and_keep() {
+        let skipped = apply(save(), ClassifierVerdict::Skip { reason: None });
+        assert!(
+            matches!(skipped, CaptureIntent::Skip { reason } if reason == "wasm classifier: skipped")
+        );
+        assert!(matches!(
+            apply(save(), ClassifierVerdict::Keep),
+            CaptureIntent::Save { .. }
+        ));
+    }
+
+    #[test]
+    fn test_capture_of_skipped_event_needs_title_and_content() {
+        let skip = || CaptureIntent::Skip {
+            reason: "untracked tool".into(),
+        };
+        let partial = CaptureOverride {
+            title: Some("Ran migrations".into()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            apply(skip(), ClassifierVerdict::Capture(partial.clone())),
+            CaptureIntent::Skip { .. }
+        ));
+        let full = CaptureOverride {
+            content: Some("sqlx migrate run".into()),
+            ..partial
+        };
+        let CaptureIntent::Save {
+            kind, importance, ..
+        } = apply(skip(), ClassifierVerdict::Capture(full))
+        else {
+            panic!("expected a save");
+        };
+        assert_eq!(kind, MemoryKind::Observation);
+        assert_eq!(importance, DEFAULT_IMPORTANCE);
+    }
+}
@@ -1,5 +1,5 @@
 use serde::Deserialize;
-use shabka_core::model::MemoryKind;
+use shabka_core::model::{CodeSnippet, MemoryKind};
 
 /// JSON payload received from Claude Code hooks on stdin.
 ///
@@ -35,7 +35,11 @@ pub enum CaptureIntent {
         title: String,
         content: String,
         importance: f32,
+        /// How sure the classification is that the event is what `kind`
+        /// says (0.0–1.0).
+        confidence: f32,
         tags: Vec<String>,
+        code: Option<CodeSnippet>,
     },
     /// Skip this event — not worth capturing.
     Skip { reason: String },
@@ -45,8 +49,12 @@ pub enum CaptureIntent {
         title: String,
         content: String,
         importance: f32,
+        /// How sure the classification is that the event is what `kind`
+        /// says (0.0–1.0).
+        confidence: f32,
         tags: Vec<String>,
         file_path: Option<String>,
         event_type: String,
+        code: Option<CodeSnippet>,
     },
 }
@@ -1,4 +1,4 @@
-use shabka_core::model::MemoryKind;
+use shabka_core::model::{CodeSnippet, MemoryKind};
 
 use crate::event::{CaptureIntent, HookEvent};
 
@@ -75,15 +75,31 @@ fn classify_file_change(event: &HookEvent, tool: &str, session_compression: bool
         }
     }
 
+    // The code as it reads after the change: the new text for an Edit, the
+    // whole file for a Write.
+    let code_key = if tool == "Edit" {
+        "new_string"
+    } else {
+        "content"
+    };
+    let code = event
+        .tool_input
+        .as_ref()
+        .and_then(|input| input.get(code_key))
+        .and_then(|v| v.as_str())
+        .and_then(|snippet| CodeSnippet::from_edit(file_path, snippet));
+
     if session_compression {
         CaptureIntent::Buffer {
             kind: MemoryKind::Decision,
             title,
             content,
             importance: 0.4,
+            confidence: 0.5,
             tags: vec!["auto-capture".into(), "file-change".into()],
             file_path: Some(file_path.to_string()),
             event_type: "tool_use".into(),
+            code,
         }
     } else {
         CaptureIntent::Save {
@@ -91,7 +107,9 @@ fn classify_file_change(event: &HookEvent, tool: &str, session_compression: bool
             title,
             content,
             importance: 0.4,
+            confidence: 0.5,
             tags: vec!["auto-capture".into(), "file-change".into()],
+            code,
         }
     }
 }
@@ -134,9 +152,11 @@ fn classify_bash_output(event: &HookEvent, session_compression: bool) -> Capture
             title,
             content,
             importance: 0.6,
+            confidence: 0.6,
             tags: vec!["auto-capture".into(), "bash-error".into()],
             file_path: None,
             event_type: "tool_use".into(),
+            code: None,
         }
     } else {
         CaptureIntent::Save {
@@ -144,7 +164,9 @@ fn classify_bash_output(event: &HookEvent, session_compression: bool) -> Capture
             title,
             content,
             importance: 0.6,
+            confidence: 0.6,
             tags: vec!["auto-capture".into(), "bash-error".into()],
+            code: None,
         }
     }
 }
@@ -163,9 +185,11 @@ fn classify_failure(event: &HookEvent, session_compression: bool) -> CaptureInte
             title,
             content,
             importance: 0.7,
+            confidence: 0.9,
             tags: vec!["auto-capture".into(), "tool-failure".into()],
             file_path: None,
             event_type: "tool_failure".into(),
+            code: None,
         }
     } else {
         CaptureIntent::Save {
@@ -173,7 +197,9 @@ fn classify_failure(event: &HookEvent, session_compression: bool) -> CaptureInte
             title,
             content,
             importance: 0.7,
+            confidence: 0.9,
             tags: vec!["auto-capture".into(), "tool-failure".into()],
+            code: None,
         }
     }
 }
@@ -193,9 +219,11 @@ fn classify_user_prompt(event: &HookEvent) -> CaptureIntent {
         title: "User intent".into(),
         content: truncate(prompt, 500),
         importance: 0.3,
+        confidence: 0.9,
         tags: Vec::new(),
         file_path: None,
         event_type: "intent".into(),
+        code: None,
     }
 }
 
@@ -262,17 +290,57 @@ mod tests {
                 kind,
                 title,
                 importance,
+                confidence,
                 ..
             } => {
                 assert_eq!(kind, MemoryKind::Decision);
                 assert!(title.contains("main.rs"));
                 assert!(title.starts_with("Edit "));
                 assert!((importance - 0.4).abs() < f32::EPSILON);
+                assert!((confidence - 0.5).abs() < f32::EPSILON);
             }
             _ => panic!("expected Save"),
         }
     }
 
+    #[test]
+    fn test_classify_edit_captures_code() {
+        let mut event = make_event("PostToolUse");
+        event.tool_name = Some("Edit".into());
+        event.tool_input = Some(serde_json::json!({
+            "file_path": "/src/pool.rs",
+            "old_string": "fn max_size() -> u32 { 8 }",
+            "new_string": "pub fn max_size() -> u32 { 16 }"
+        }));
+
+        match classify(&event, false) {
+            CaptureIntent::Save {
+                code: Some(code), ..
+            } => {
+                assert_eq!(code.language.as_deref(), Some("rust"));
+                assert_eq!(code.file.as_deref(), Some("/src/pool.rs"));
+                assert_eq!(code.symbol.as_deref(), Some("max_size"));
+                assert!(code.snippet.contains("16"));
+            }
+            _ => panic!("expected Save with code"),
+        }
+
+        event.tool_name = Some("Write".into());
+        event.tool_input = Some(serde_json::json!({
+            "file_path": "/app/views.py",
+            "content": "def index(request):\n    return render(request)\n"
+        }));
+        match classify(&event, true) {
+            CaptureIntent::Buffer {
+                code: Some(code), ..
+            } => {
+                assert_eq!(code.language.as_deref(), Some("python"));
+                assert_eq!(code.symbol.as_deref(), Some("index"));
+            }
+            _ => panic!("expected Buffer with code"),
+        }
+    }
+
     #[test]
     fn test_classify_bash_success_skipped() {
         let mut event = make_event("PostToolUse");
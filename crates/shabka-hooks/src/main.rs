@@ -1,6 +1,9 @@
+mod classifier;
 mod event;
 mod handlers;
+mod recurrence;
 mod relate;
+mod self_test;
 mod session;
 
 use std::io::Read;
@@ -10,18 +13,30 @@ use std::process::ExitCode;
 use chrono::Utc;
 use shabka_core::assess::{self, AssessConfig};
 use shabka_core::config::{self, ShabkaConfig};
-use shabka_core::embedding::EmbeddingService;
-use shabka_core::model::{Memory, MemorySource};
+use shabka_core::dedup::DedupDecision;
+use shabka_core::embedding::{self, EmbeddingService};
+use shabka_core::journal::{self, CaptureJournal, JournalOp, JournalOutcome};
+use shabka_core::model::{
+    Assertion, CodeSnippet, Memory, MemorySource, MemoryStatus, UpdateMemoryInput,
+};
+use shabka_core::quota::{self, CaptureBacklog, CaptureUsage};
+use shabka_core::screen;
 use shabka_core::sharing;
-use shabka_core::storage::{create_backend, StorageBackend};
+use shabka_core::storage::{create_backend, Storage, StorageBackend};
+use shabka_core::trust;
 use tracing::Level;
 
 use crate::event::{CaptureIntent, HookEvent};
+use crate::recurrence::FingerprintIndex;
 use crate::session::{BufferedEvent, CompressedMemory, SessionBuffer};
 
-/// Derive a project ID from the working directory.
-/// Uses the directory basename, e.g. "/home/user/projects/shabka" → "shabka".
-fn derive_project_id(cwd: &str) -> String {
+/// Derive a project ID for captured memories.
+/// A project pinned with `shabka project use` wins; otherwise the directory
+/// basename is used, e.g. "/home/user/projects/shabka" → "shabka".
+fn derive_project_id(config: &ShabkaConfig, cwd: &str) -> String {
+    if let Some(pinned) = config.resolve_project(None) {
+        return pinned;
+    }
     Path::new(cwd)
         .file_name()
         .and_then(|n| n.to_str())
@@ -34,23 +49,116 @@ fn derive_project_id(cwd: &str) -> String {
 /// Reads a Claude Code hook event from stdin, classifies it,
 /// and saves interesting events as memories in HelixDB.
 ///
-/// CRITICAL: Always exits 0. A non-zero exit could block Claude Code operations.
+/// With `--dry-run` (or `SHABKA_HOOKS_DRY_RUN=1`) the event is processed
+/// end-to-end but nothing is written; each decision is printed to stdout.
+///
+/// `--self-test` replays bundled sample events instead of reading stdin and
+/// exits 1 if any of them misbehave. It is never invoked by Claude Code.
+///
+/// CRITICAL: Hook runs always exit 0. A non-zero exit could block Claude Code operations.
 fn main() -> ExitCode {
+    if std::env::args().skip(1).any(|a| a == "--self-test") {
+        return run_self_test();
+    }
+
+    let dry_run = dry_run_requested(
+        std::env::args().skip(1),
+        std::env::var("SHABKA_HOOKS_DRY_RUN").ok(),
+    );
+
     // Set up stderr logging (hooks must not write to stdout)
     tracing_subscriber::fmt()
         .with_writer(std::io::stderr)
-        .with_max_level(Level::WARN)
+        .with_max_level(if dry_run { Level::DEBUG } else { Level::WARN })
         .compact()
         .init();
 
-    if let Err(e) = run() {
+    if let Err(e) = run(dry_run) {
         tracing::warn!("shabka-hooks: {e:#}");
     }
 
     ExitCode::SUCCESS
 }
 
-fn run() -> anyhow::Result<()> {
+/// Run `--self-test` against the config for the current directory.
+fn run_self_test() -> ExitCode {
+    let cwd = std::env::current_dir().unwrap_or_else(|_| ".".into());
+    let project_dir = config::find_project_root(&cwd).unwrap_or(cwd);
+    let config =
+        ShabkaConfig::load(Some(&project_dir)).unwrap_or_else(|_| ShabkaConfig::default_config());
+
+    match self_test::run(&config) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("self-test failed to run: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Whether `--dry-run` was passed or `SHABKA_HOOKS_DRY_RUN` is set to a truthy value.
+fn dry_run_requested(mut args: impl Iterator<Item = String>, env: Option<String>) -> bool {
+    args.any(|a| a == "--dry-run") || env.is_some_and(|v| !matches!(v.trim(), "" | "0" | "false"))
+}
+
+/// Print one dry-run decision line to stdout.
+fn report(step: &str, detail: impl std::fmt::Display) {
+    println!("{step:<10} {detail}");
+}
+
+/// One-line summary of a memory that would be saved.
+fn describe_memory(memory: &Memory) -> String {
+    format!(
+        "{} '{}' (importance {:.2}, project {}, tags [{}])",
+        memory.kind,
+        memory.title,
+        memory.importance,
+        memory.project_id.as_deref().unwrap_or("-"),
+        memory.tags.join(", "),
+    )
+}
+
+/// One-line summary of a dedup decision.
+fn describe_decision(decision: &DedupDecision) -> String {
+    match decision {
+        DedupDecision::Add => "add (no near duplicate)".to_string(),
+        DedupDecision::Skip {
+            existing_title,
+            similarity,
+            ..
+        } => format!("skip ({similarity:.2} match with '{existing_title}')"),
+        DedupDecision::Supersede {
+            existing_title,
+            similarity,
+            ..
+        } => format!("supersede '{existing_title}' ({similarity:.2})"),
+        DedupDecision::Update {
+            existing_title,
+            similarity,
+            ..
+        } => format!("merge into '{existing_title}' ({similarity:.2})"),
+        DedupDecision::Contradict {
+            existing_title,
+            similarity,
+            reason,
+            ..
+        } => format!("contradicts '{existing_title}' ({similarity:.2}): {reason}"),
+    }
+}
+
+/// Report what a dedup decision means for `memory` in dry-run mode.
+fn report_outcome(memory: &Memory, decision: &DedupDecision) {
+    report("dedup", describe_decision(decision));
+    match decision {
+        DedupDecision::Skip { .. } | DedupDecision::Update { .. } => {
+            report("result", format!("would not save '{}'", memory.title))
+        }
+        _ => report("result", format!("would save {}", describe_memory(memory))),
+    }
+}
+
+fn run(dry_run: bool) -> anyhow::Result<()> {
     // Read stdin
     let mut input = String::new();
     std::io::stdin().read_to_string(&mut input)?;
@@ -64,35 +172,71 @@ fn run() -> anyhow::Result<()> {
         }
     };
 
+    if dry_run {
+        let tool = event
+            .tool_name
+            .as_deref()
+            .map(|t| format!(" ({t})"))
+            .unwrap_or_default();
+        report("event", format!("{}{tool}", event.hook_event_name));
+    }
+
     // Load config
     let cwd = Path::new(&event.cwd);
-    let config = ShabkaConfig::load(Some(cwd)).unwrap_or_else(|_| ShabkaConfig::default_config());
+    let project_dir = config::find_project_root(cwd).unwrap_or_else(|| cwd.to_path_buf());
+    let config =
+        ShabkaConfig::load(Some(&project_dir)).unwrap_or_else(|_| ShabkaConfig::default_config());
 
     // Check if capture is enabled
     if !config.capture.enabled {
         tracing::debug!("capture disabled, skipping");
+        if dry_run {
+            report("result", "capture disabled, nothing would be saved");
+        }
         return Ok(());
     }
 
     // Dimension mismatch guard — prevent saving with incompatible embeddings
-    if let Err(msg) = config::check_dimensions(&config.embedding) {
-        tracing::warn!("shabka-hooks: {msg}");
+    if let Err(e) = config::check_dimensions(&config.embedding) {
+        tracing::warn!("shabka-hooks: {e}");
+        if dry_run {
+            report(
+                "result",
+                "embedding dimension mismatch, nothing would be saved",
+            );
+        }
         return Ok(());
     }
 
+    if !dry_run {
+        if let Err(e) = recover_journal(&config) {
+            tracing::warn!("capture journal recovery failed: {e}");
+        }
+    }
+
     let session_compression = config.capture.session_compression;
 
     // Handle Stop event separately — it triggers session compression
     if event.hook_event_name == "Stop" {
-        return handle_stop(&event, &config);
+        return handle_stop(&event, &config, dry_run);
     }
 
-    // Classify event
+    // Classify event, then let a [wasm_plugins] classifier refine it
     let intent = handlers::classify(&event, session_compression);
+    let intent = match classifier::load(&config.wasm_plugins) {
+        Some(wasm) => {
+            let raw = serde_json::from_str(&input).unwrap_or_default();
+            classifier::refine(&wasm, &raw, intent)
+        }
+        None => intent,
+    };
 
     match intent {
         CaptureIntent::Skip { reason } => {
             tracing::debug!("skipping: {reason}");
+            if dry_run {
+                report("intent", format!("skip ({reason})"));
+            }
             Ok(())
         }
         CaptureIntent::Buffer {
@@ -100,10 +244,24 @@ fn run() -> anyhow::Result<()> {
             title,
             content,
             importance,
+            confidence,
             tags,
             file_path,
             event_type,
+            code,
         } => {
+            if dry_run {
+                report(
+                    "intent",
+                    format!(
+                        "buffer {event_type} {kind} '{title}' (importance {importance:.2}, confidence {confidence:.2}, tags [{}])",
+                        tags.join(", ")
+                    ),
+                );
+                report("result", "would append to the session buffer");
+                return Ok(());
+            }
+
             // Write to session buffer for later compression
             let buffer = SessionBuffer::new(&event.session_id);
             let buffered = BufferedEvent {
@@ -112,43 +270,73 @@ fn run() -> anyhow::Result<()> {
                 title,
                 content,
                 importance,
+                confidence,
                 tags,
                 file_path,
                 event_type,
+                code,
             };
             buffer.append(&buffered)?;
             tracing::debug!("buffered event for session {}", event.session_id);
-            Ok(())
+            flush_if_due(&event, &config, &buffer)
         }
         CaptureIntent::Save {
             kind,
             title,
             content,
             importance,
+            confidence,
             tags,
+            code,
         } => {
+            if dry_run {
+                report(
+                    "intent",
+                    format!(
+                        "save {kind} '{title}' (importance {importance:.2}, confidence {confidence:.2})"
+                    ),
+                );
+            }
+
             // Check importance threshold
             if importance < config.capture.min_importance {
                 tracing::debug!(
                     "importance {importance} below threshold {}, skipping",
                     config.capture.min_importance
                 );
+                if dry_run {
+                    report(
+                        "result",
+                        format!(
+                            "below min_importance {}, would not save",
+                            config.capture.min_importance
+                        ),
+                    );
+                }
                 return Ok(());
             }
-            save_memory_immediate(&event, &config, kind, title, content, importance, tags)
+            save_memory_immediate(
+                &event, &config, kind, title, content, importance, confidence, tags, code, dry_run,
+            )
         }
     }
 }
 
 /// Handle the Stop event: compress buffered events and save compressed memories.
-fn handle_stop(event: &HookEvent, config: &ShabkaConfig) -> anyhow::Result<()> {
+fn handle_stop(event: &HookEvent, config: &ShabkaConfig, dry_run: bool) -> anyhow::Result<()> {
     let buffer = SessionBuffer::new(&event.session_id);
     let events = buffer.read_all()?;
 
+    if dry_run {
+        return dry_run_stop(&events, event, config);
+    }
+
     // Also compress any stale buffers from previous sessions
     let stale_buffers = session::find_stale_buffers(std::time::Duration::from_secs(2 * 60 * 60));
+    let backlog = CaptureBacklog::new();
+    let journal = CaptureJournal::new();
 
-    if events.is_empty() && stale_buffers.is_empty() {
+    if events.is_empty() && stale_buffers.is_empty() && backlog.is_empty() {
         tracing::debug!("no buffered events, skipping stop handler");
         return Ok(());
     }
@@ -158,10 +346,23 @@ fn handle_stop(event: &HookEvent, config: &ShabkaConfig) -> anyhow::Result<()> {
         .build()?;
 
     rt.block_on(async {
+        let embedding_service = EmbeddingService::from_config(&config.embedding)?;
+        let storage = create_backend(config)?;
+
         // Compress current session
         if !events.is_empty() {
             let memories = compress_events(&events, config).await;
-            save_compressed_memories(&memories, event, config).await?;
+            save_compressed_memories(
+                &storage,
+                &embedding_service,
+                &journal,
+                &memories,
+                event,
+                &event.session_id,
+                config,
+                false,
+            )
+            .await?;
             buffer.delete()?;
             tracing::info!(
                 "compressed {} events into {} memories for session {}",
@@ -179,7 +380,17 @@ fn handle_stop(event: &HookEvent, config: &ShabkaConfig) -> anyhow::Result<()> {
             match stale_buf.read_all() {
                 Ok(stale_events) if !stale_events.is_empty() => {
                     let memories = compress_events(&stale_events, config).await;
-                    save_compressed_memories(&memories, event, config).await?;
+                    save_compressed_memories(
+                        &storage,
+                        &embedding_service,
+                        &journal,
+                        &memories,
+                        event,
+                        stale_buf.session_id(),
+                        config,
+                        false,
+                    )
+                    .await?;
                     stale_buf.delete()?;
                     tracing::info!(
                         "compressed {} stale events from {:?}",
@@ -194,23 +405,236 @@ fn handle_stop(event: &HookEvent, config: &ShabkaConfig) -> anyhow::Result<()> {
             }
         }
 
+        if !backlog.is_empty() {
+            drain_backlog(&storage, &embedding_service, &backlog, config).await?;
+        }
+
         Ok::<(), anyhow::Error>(())
     })?;
 
     Ok(())
 }
 
+/// Compress and flush the session buffer mid-session once it reaches
+/// `capture.flush_after_events` events or `capture.flush_after_minutes` of
+/// age, so an abrupt session end loses at most one window of events.
+fn flush_if_due(
+    event: &HookEvent,
+    config: &ShabkaConfig,
+    buffer: &SessionBuffer,
+) -> anyhow::Result<()> {
+    let max_age = chrono::Duration::try_minutes(config.capture.flush_after_minutes as i64)
+        .unwrap_or(chrono::Duration::MAX);
+    let events = buffer.read_all()?;
+    if !session::should_flush(
+        &events,
+        config.capture.flush_after_events,
+        max_age,
+        Utc::now(),
+    ) {
+        return Ok(());
+    }
+
+    // Detach first so events arriving during compression go to a fresh buffer.
+    let Some(detached) = buffer.detach()? else {
+        return Ok(());
+    };
+    let events = detached.read_all()?;
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let embedding_service = EmbeddingService::from_config(&config.embedding)?;
+        let storage = create_backend(config)?;
+        let journal = CaptureJournal::new();
+        let memories = compress_events(&events, config).await;
+        save_compressed_memories(
+            &storage,
+            &embedding_service,
+            &journal,
+            &memories,
+            event,
+            &event.session_id,
+            config,
+            false,
+        )
+        .await?;
+        detached.delete()?;
+        tracing::info!(
+            "flushed {} events into {} memories mid-session {}",
+            events.len(),
+            memories.len(),
+            event.session_id,
+        );
+        Ok(())
+    })
+}
+
+/// Parse a hook session ID (a UUID for Claude Code) so captured memories can
+/// be linked to their session.
+fn session_uuid(session_id: &str) -> Option<uuid::Uuid> {
+    uuid::Uuid::parse_str(session_id).ok()
+}
+
+/// Dry-run the Stop handler for the current session: compress its buffer and
+/// report each resulting memory, leaving buffers and the backlog untouched.
+fn dry_run_stop(
+    events: &[BufferedEvent],
+    event: &HookEvent,
+    config: &ShabkaConfig,
+) -> anyhow::Result<()> {
+    report(
+        "intent",
+        format!("compress {} buffered events", events.len()),
+    );
+    if events.is_empty() {
+        report("result", "session buffer empty, nothing would be saved");
+        return Ok(());
+    }
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    rt.block_on(async {
+        let memories = compress_events(events, config).await;
+        report(
+            "compress",
+            format!("{} events -> {} memories", events.len(), memories.len()),
+        );
+        let embedding_service = EmbeddingService::from_config(&config.embedding)?;
+        let storage = create_backend(config)?;
+        // Dry runs never reach the journal; it is only here to satisfy the signature.
+        let journal = CaptureJournal::new();
+        save_compressed_memories(
+            &storage,
+            &embedding_service,
+            &journal,
+            &memories,
+            event,
+            &event.session_id,
+            config,
+            true,
+        )
+        .await
+    })
+}
+
+/// Load today's capture usage when quotas are configured.
+/// Quotas are best-effort: if usage can't be counted, captures go through.
+async fn load_usage(storage: &impl StorageBackend, config: &ShabkaConfig) -> Option<CaptureUsage> {
+    if !config.capture.has_quota() {
+        return None;
+    }
+    match quota::usage_today(storage, Utc::now()).await {
+        Ok(usage) => Some(usage),
+        Err(e) => {
+            tracing::warn!("failed to count today's captures, quotas not enforced: {e}");
+            None
+        }
+    }
+}
+
+/// Park `memory` in the capture backlog if it would exceed a quota.
+/// Returns true when the memory was held back and must not be saved.
+fn hold_over_quota(
+    usage: Option<&CaptureUsage>,
+    config: &ShabkaConfig,
+    memory: &Memory,
+    dry_run: bool,
+) -> bool {
+    let Some(limit) = usage.and_then(|u| u.exceeded(&config.capture, memory.project_id.as_deref()))
+    else {
+        return false;
+    };
+    if dry_run {
+        report(
+            "result",
+            format!("{limit}, would backlog '{}'", memory.title),
+        );
+        return true;
+    }
+    match CaptureBacklog::new().push(memory) {
+        Ok(()) => tracing::info!("{limit}, backlogged '{}'", memory.title),
+        Err(e) => tracing::warn!("{limit}, failed to backlog '{}': {e}", memory.title),
+    }
+    true
+}
+
+/// Save backlogged captures while today's quotas allow, keeping the rest.
+async fn drain_backlog(
+    storage: &Storage,
+    embedding_service: &EmbeddingService,
+    backlog: &CaptureBacklog,
+    config: &ShabkaConfig,
+) -> anyhow::Result<()> {
+    let mut usage = load_usage(storage, config).await;
+    if usage
+        .as_ref()
+        .is_some_and(|u| u.exceeded(&config.capture, None).is_some())
+    {
+        return Ok(());
+    }
+
+    let mut kept = Vec::new();
+    let mut saved = 0;
+    for memory in backlog.read_all() {
+        let project = memory.project_id.as_deref();
+        if usage
+            .as_ref()
+            .is_some_and(|u| u.exceeded(&config.capture, project).is_some())
+        {
+            kept.push(memory);
+            continue;
+        }
+        let embedding = match embedding_service.embed(&memory.embedding_text()).await {
+            Ok(e) => e,
+            Err(e) => {
+                tracing::warn!("embedding failed for backlogged '{}': {e}", memory.title);
+                kept.push(memory);
+                continue;
+            }
+        };
+        if let Err(e) = storage.save_memory(&memory, Some(&embedding)).await {
+            tracing::warn!("failed to save backlogged '{}': {e}", memory.title);
+            kept.push(memory);
+            continue;
+        }
+        if let Some(u) = usage.as_mut() {
+            u.record(project);
+        }
+        saved += 1;
+        if relatable(&memory, config) {
+            shabka_core::graph::semantic_auto_relate(storage, memory.id, &embedding, None, None)
+                .await;
+        }
+    }
+
+    backlog.replace(&kept)?;
+    if saved > 0 {
+        tracing::info!(
+            "saved {saved} backlogged memories, {} still held",
+            kept.len()
+        );
+    }
+    Ok(())
+}
+
 /// Compress events — try LLM first, fall back to heuristic.
 async fn compress_events(events: &[BufferedEvent], config: &ShabkaConfig) -> Vec<CompressedMemory> {
     // Try LLM compression if enabled
     if config.llm.enabled {
         match shabka_core::llm::LlmService::from_config(&config.llm) {
-            Ok(llm) => match session::compress_with_llm(events, &llm).await {
-                Ok(memories) => return memories,
-                Err(e) => {
-                    tracing::warn!("LLM compression failed, falling back to heuristic: {e}");
+            Ok(llm) => {
+                let llm = llm.with_language(config.output.language.as_deref());
+                match session::compress_with_llm(events, &llm, config.llm.max_tokens).await {
+                    Ok(memories) => return memories,
+                    Err(e) => {
+                        tracing::warn!("LLM compression failed, falling back to heuristic: {e}");
+                    }
                 }
-            },
+            }
             Err(e) => {
                 tracing::warn!("failed to create LLM service: {e}");
             }
@@ -234,21 +658,26 @@ fn log_quality_warnings(memory: &Memory) {
     }
 }
 
-/// Save a list of compressed memories to HelixDB.
+/// Save a list of compressed memories to storage.
+#[allow(clippy::too_many_arguments)]
 async fn save_compressed_memories(
+    storage: &Storage,
+    embedding_service: &EmbeddingService,
+    journal: &CaptureJournal,
     memories: &[CompressedMemory],
     event: &HookEvent,
+    session_id: &str,
     config: &ShabkaConfig,
+    dry_run: bool,
 ) -> anyhow::Result<()> {
     if memories.is_empty() {
         return Ok(());
     }
 
-    let embedding_service = EmbeddingService::from_config(&config.embedding)?;
-    let storage = create_backend(config)?;
-
     let llm_service = if config.llm.enabled && config.graph.dedup_llm {
-        shabka_core::llm::LlmService::from_config(&config.llm).ok()
+        shabka_core::llm::LlmService::from_config(&config.llm)
+            .ok()
+            .map(|llm| llm.with_language(config.output.language.as_deref()))
     } else {
         None
     };
@@ -263,6 +692,14 @@ async fn save_compressed_memories(
         None
     };
 
+    let screen_llm = screening_llm(config);
+
+    let mut usage = load_usage(storage, config).await;
+    let mut recurrences = config
+        .capture
+        .merge_recurrences
+        .then(FingerprintIndex::load);
+
     for compressed in memories {
         let mut memory = Memory::new(
             compressed.title.clone(),
@@ -275,13 +712,30 @@ async fn save_compressed_memories(
         })
         .with_tags(compressed.tags.clone())
         .with_importance(compressed.importance)
+        .with_confidence(compressed.confidence)
         .with_privacy(privacy)
-        .with_project(derive_project_id(&event.cwd));
+        .with_project(derive_project_id(config, &event.cwd));
+        config.labels.apply(&mut memory);
+        shabka_core::devices::stamp(&mut memory);
 
+        if let Some(session) = session_uuid(session_id) {
+            memory = memory.with_session(session);
+        }
+        memory.code = compressed.code.clone();
         if config.capture.review_mode {
             memory.status = shabka_core::model::MemoryStatus::Pending;
         }
 
+        // A lesson seen in an earlier session is merged, not saved again.
+        let fingerprint = recurrences
+            .as_ref()
+            .and_then(|_| recurrence::fingerprint(memory.kind, &memory.title));
+        if let (Some(index), Some(fp)) = (recurrences.as_mut(), fingerprint.as_deref()) {
+            if merge_recurrence(storage, index, fp, &memory, dry_run).await {
+                continue;
+            }
+        }
+
         // Auto-tag with LLM if enabled
         if let Some(ref llm) = auto_tag_llm {
             if let Some(result) = shabka_core::auto_tag::auto_tag(&memory, llm).await {
@@ -300,11 +754,27 @@ async fn save_compressed_memories(
                     memory.tags,
                     memory.importance
                 );
+                if dry_run {
+                    report(
+                        "auto-tag",
+                        format!(
+                            "'{}': tags [{}], importance {:.2}",
+                            memory.title,
+                            memory.tags.join(", "),
+                            memory.importance
+                        ),
+                    );
+                }
             }
         }
 
+        screen_capture(&mut memory, config, screen_llm.as_ref(), dry_run).await;
         log_quality_warnings(&memory);
 
+        if hold_over_quota(usage.as_ref(), config, &memory, dry_run) {
+            continue;
+        }
+
         let embedding_text = memory.embedding_text();
         let embedding = match embedding_service.embed(&embedding_text).await {
             Ok(e) => e,
@@ -316,7 +786,7 @@ async fn save_compressed_memories(
 
         // Dedup check
         let dedup_decision = shabka_core::dedup::check_duplicate(
-            &storage,
+            storage,
             &embedding,
             &config.graph,
             None,
@@ -326,19 +796,27 @@ async fn save_compressed_memories(
         )
         .await;
 
-        match dedup_decision {
-            shabka_core::dedup::DedupDecision::Skip {
+        if dry_run {
+            report_outcome(&memory, &dedup_decision);
+            continue;
+        }
+
+        let op = match dedup_decision {
+            DedupDecision::Skip {
+                existing_id,
                 existing_title,
                 similarity,
-                ..
             } => {
                 tracing::info!(
                     "dedup skip ({similarity:.2}): '{}' matches '{existing_title}'",
                     memory.title,
                 );
+                let assertion = Assertion::new(memory.created_by.clone(), "hooks");
+                let _ = trust::record_assertion(storage, existing_id, assertion).await;
+                remember(&mut recurrences, fingerprint.as_deref(), existing_id);
                 continue;
             }
-            shabka_core::dedup::DedupDecision::Supersede {
+            DedupDecision::Supersede {
                 existing_id,
                 existing_title,
                 similarity,
@@ -347,25 +825,14 @@ async fn save_compressed_memories(
                     "dedup supersede ({similarity:.2}): '{}' supersedes '{existing_title}'",
                     memory.title,
                 );
-                let _ = storage
-                    .update_memory(
-                        existing_id,
-                        &shabka_core::model::UpdateMemoryInput {
-                            status: Some(shabka_core::model::MemoryStatus::Superseded),
-                            ..Default::default()
-                        },
-                    )
-                    .await;
-                let _ = storage
-                    .add_relation(&shabka_core::model::MemoryRelation {
-                        source_id: memory.id,
-                        target_id: existing_id,
-                        relation_type: shabka_core::model::RelationType::Supersedes,
-                        strength: similarity,
-                    })
-                    .await;
+                Some(JournalOp::Supersede {
+                    memory_id: memory.id,
+                    existing_id,
+                    previous_status: current_status(storage, existing_id).await,
+                    strength: similarity,
+                })
             }
-            shabka_core::dedup::DedupDecision::Update {
+            DedupDecision::Update {
                 existing_id,
                 existing_title,
                 merged_content,
@@ -375,19 +842,18 @@ async fn save_compressed_memories(
                 tracing::info!(
                     "dedup merge ({similarity:.2}): new info merged into '{existing_title}' ({existing_id})",
                 );
-                let _ = storage
-                    .update_memory(
-                        existing_id,
-                        &shabka_core::model::UpdateMemoryInput {
-                            title: Some(merged_title),
-                            content: Some(merged_content),
-                            ..Default::default()
-                        },
-                    )
-                    .await;
+                let op = JournalOp::Merge {
+                    existing_id,
+                    title: merged_title,
+                    content: merged_content,
+                };
+                if apply_journaled(storage, journal, op, None).await.is_ok() {
+                    refresh_merged(storage, embedding_service, config, existing_id).await;
+                }
+                remember(&mut recurrences, fingerprint.as_deref(), existing_id);
                 continue;
             }
-            shabka_core::dedup::DedupDecision::Contradict {
+            DedupDecision::Contradict {
                 existing_id,
                 existing_title,
                 similarity,
@@ -397,36 +863,30 @@ async fn save_compressed_memories(
                     "dedup contradict ({similarity:.2}): '{}' contradicts '{existing_title}': {reason}",
                     memory.title,
                 );
-                // Save the new memory and link contradiction, then continue
-                // (memory is already saved — don't fall through to the second save)
-                if let Err(e) = storage.save_memory(&memory, Some(&embedding)).await {
-                    tracing::warn!(
-                        "failed to save contradicting memory '{}': {e}",
-                        memory.title
-                    );
-                    continue;
-                }
-                let _ = storage
-                    .add_relation(&shabka_core::model::MemoryRelation {
-                        source_id: memory.id,
-                        target_id: existing_id,
-                        relation_type: shabka_core::model::RelationType::Contradicts,
-                        strength: similarity,
-                    })
-                    .await;
-                shabka_core::graph::semantic_auto_relate(
-                    &storage, memory.id, &embedding, None, None,
-                )
-                .await;
-                continue;
+                Some(JournalOp::Contradict {
+                    memory_id: memory.id,
+                    existing_id,
+                    strength: similarity,
+                })
             }
-            shabka_core::dedup::DedupDecision::Add => {}
-        }
+            DedupDecision::Add => None,
+        };
 
-        if let Err(e) = storage.save_memory(&memory, Some(&embedding)).await {
+        let saved = match op {
+            Some(op) => apply_journaled(storage, journal, op, Some((&memory, &embedding))).await,
+            None => storage
+                .save_memory(&memory, Some(&embedding))
+                .await
+                .map_err(Into::into),
+        };
+        if let Err(e) = saved {
             tracing::warn!("failed to save compressed memory '{}': {e}", memory.title);
             continue;
         }
+        if let Some(u) = usage.as_mut() {
+            u.record(memory.project_id.as_deref());
+        }
+        remember(&mut recurrences, fingerprint.as_deref(), memory.id);
 
         tracing::info!(
             "saved compressed {} memory: {} (importance: {})",
@@ -436,13 +896,97 @@ async fn save_compressed_memories(
         );
 
         // Semantic auto-relate
-        shabka_core::graph::semantic_auto_relate(&storage, memory.id, &embedding, None, None).await;
+        if relatable(&memory, config) {
+            shabka_core::graph::semantic_auto_relate(storage, memory.id, &embedding, None, None)
+                .await;
+        }
+    }
+
+    if let Some(mut index) = recurrences.filter(|_| !dry_run) {
+        if let Err(e) = index.save(Utc::now()) {
+            tracing::warn!("failed to save recurrence index: {e}");
+        }
     }
 
     Ok(())
 }
 
+/// Merge `memory` into the earlier memory with the same fingerprint, bumping
+/// its importance and adding its tags. Returns true when merged, in which case
+/// the new memory must not be saved.
+async fn merge_recurrence(
+    storage: &Storage,
+    index: &mut FingerprintIndex,
+    fingerprint: &str,
+    memory: &Memory,
+    dry_run: bool,
+) -> bool {
+    let Some(entry) = index.get(fingerprint).cloned() else {
+        return false;
+    };
+    let existing = match storage.get_memory(entry.memory_id).await {
+        Ok(m) if matches!(m.status, MemoryStatus::Active | MemoryStatus::Pending) => m,
+        _ => {
+            // Deleted, archived or superseded since: start over.
+            index.forget(fingerprint);
+            return false;
+        }
+    };
+
+    let importance = (existing.importance + recurrence::RECURRENCE_BOOST).min(1.0);
+    if dry_run {
+        report(
+            "recurrence",
+            format!(
+                "'{}' recurs '{}' (seen {} times), importance {:.2} -> {importance:.2}",
+                memory.title,
+                existing.title,
+                entry.count + 1,
+                existing.importance
+            ),
+        );
+        report("result", format!("would not save '{}'", memory.title));
+        return true;
+    }
+
+    let mut tags = existing.tags.clone();
+    for tag in &memory.tags {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    let input = UpdateMemoryInput {
+        importance: Some(importance),
+        tags: Some(tags),
+        ..Default::default()
+    };
+    if let Err(e) = storage.update_memory(existing.id, &input).await {
+        tracing::warn!("failed to merge recurrence into '{}': {e}", existing.title);
+        return false;
+    }
+    index.record(fingerprint, existing.id, Utc::now());
+    tracing::info!(
+        "recurrence: '{}' merged into '{}' (seen {} times, importance {importance:.2})",
+        memory.title,
+        existing.title,
+        entry.count + 1,
+    );
+    true
+}
+
+/// Point a fingerprint at the memory that now represents it.
+fn remember(
+    index: &mut Option<FingerprintIndex>,
+    fingerprint: Option<&str>,
+    memory_id: uuid::Uuid,
+) {
+    if let (Some(index), Some(fp)) = (index.as_mut(), fingerprint) {
+        index.record(fp, memory_id, Utc::now());
+    }
+}
+
 /// Save a single memory immediately (legacy path when session_compression is off).
+#[allow(clippy::too_many_arguments)]
 fn save_memory_immediate(
     event: &HookEvent,
     config: &ShabkaConfig,
@@ -450,8 +994,50 @@ fn save_memory_immediate(
     title: String,
     content: String,
     importance: f32,
+    confidence: f32,
     tags: Vec<String>,
+    code: Option<CodeSnippet>,
+    dry_run: bool,
 ) -> anyhow::Result<()> {
+    let memory = immediate_memory(
+        event, config, kind, title, content, importance, confidence, tags, code,
+    );
+    log_quality_warnings(&memory);
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let embedding_service = EmbeddingService::from_config(&config.embedding)?;
+        let storage = create_backend(config)?;
+        let journal = CaptureJournal::new();
+        store_immediate(
+            &storage,
+            &embedding_service,
+            &journal,
+            memory,
+            event,
+            config,
+            dry_run,
+        )
+        .await
+    })
+}
+
+/// Build the memory for an event captured without session compression.
+#[allow(clippy::too_many_arguments)]
+fn immediate_memory(
+    event: &HookEvent,
+    config: &ShabkaConfig,
+    kind: shabka_core::model::MemoryKind,
+    title: String,
+    content: String,
+    importance: f32,
+    confidence: f32,
+    tags: Vec<String>,
+    code: Option<CodeSnippet>,
+) -> Memory {
     let user_id = config::resolve_user_id(&config.sharing);
     let privacy = sharing::parse_default_privacy(&config.privacy);
     let mut memory = Memory::new(title, content, kind, user_id)
@@ -460,160 +1046,367 @@ fn save_memory_immediate(
         })
         .with_tags(tags)
         .with_importance(importance)
+        .with_confidence(confidence)
         .with_privacy(privacy)
-        .with_project(derive_project_id(&event.cwd));
+        .with_project(derive_project_id(config, &event.cwd));
+    config.labels.apply(&mut memory);
+    shabka_core::devices::stamp(&mut memory);
 
+    if let Some(session) = session_uuid(&event.session_id) {
+        memory = memory.with_session(session);
+    }
     if config.capture.review_mode {
         memory.status = shabka_core::model::MemoryStatus::Pending;
     }
+    memory.code = code;
+    memory
+}
 
-    log_quality_warnings(&memory);
+/// LLM for capture screening, when both screening's LLM check and the LLM
+/// are enabled.
+fn screening_llm(config: &ShabkaConfig) -> Option<shabka_core::llm::LlmService> {
+    if config.screening.enabled && config.screening.llm && config.llm.enabled {
+        shabka_core::llm::LlmService::from_config(&config.llm).ok()
+    } else {
+        None
+    }
+}
 
-    let rt = tokio::runtime::Builder::new_current_thread()
-        .enable_all()
-        .build()?;
+/// Screen `memory` for prompt injection, flagging or quarantining it per
+/// `[screening]`.
+async fn screen_capture(
+    memory: &mut Memory,
+    config: &ShabkaConfig,
+    llm: Option<&shabka_core::llm::LlmService>,
+    dry_run: bool,
+) {
+    let findings = screen::screen_capture(memory, &config.screening, llm).await;
+    if findings.is_empty() {
+        return;
+    }
+    let summary = findings
+        .iter()
+        .map(|f| format!("{}: \"{}\"", f.kind.label(), f.excerpt))
+        .collect::<Vec<_>>()
+        .join("; ");
+    if dry_run {
+        report(
+            "screen",
+            format!("'{}' marked {}: {summary}", memory.title, memory.status),
+        );
+    } else {
+        tracing::warn!(
+            "screening: '{}' marked {}: {summary}",
+            memory.title,
+            memory.status
+        );
+    }
+}
 
-    rt.block_on(async {
-        // Auto-tag with LLM if enabled
-        if config.capture.auto_tag && config.llm.enabled {
-            if let Ok(llm) = shabka_core::llm::LlmService::from_config(&config.llm) {
-                if let Some(result) = shabka_core::auto_tag::auto_tag(&memory, &llm).await {
-                    let mut tags = memory.tags.clone();
-                    for tag in result.tags {
-                        if !tags.contains(&tag) {
-                            tags.push(tag);
-                        }
+/// Auto-tag, quota-check, dedup and save an immediately captured memory.
+async fn store_immediate(
+    storage: &Storage,
+    embedding_service: &EmbeddingService,
+    journal: &CaptureJournal,
+    mut memory: Memory,
+    event: &HookEvent,
+    config: &ShabkaConfig,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    // Auto-tag with LLM if enabled
+    if config.capture.auto_tag && config.llm.enabled {
+        if let Ok(llm) = shabka_core::llm::LlmService::from_config(&config.llm) {
+            if let Some(result) = shabka_core::auto_tag::auto_tag(&memory, &llm).await {
+                let mut tags = memory.tags.clone();
+                for tag in result.tags {
+                    if !tags.contains(&tag) {
+                        tags.push(tag);
                     }
-                    memory.tags = tags;
-                    memory.importance = result.importance;
+                }
+                memory.tags = tags;
+                memory.importance = result.importance;
+                if dry_run {
+                    report(
+                        "auto-tag",
+                        format!(
+                            "tags [{}], importance {:.2}",
+                            memory.tags.join(", "),
+                            memory.importance
+                        ),
+                    );
                 }
             }
         }
+    }
 
-        let embedding_service = EmbeddingService::from_config(&config.embedding)?;
-        let storage = create_backend(config)?;
+    let screen_llm = screening_llm(config);
+    screen_capture(&mut memory, config, screen_llm.as_ref(), dry_run).await;
 
-        let llm_service = if config.llm.enabled && config.graph.dedup_llm {
-            shabka_core::llm::LlmService::from_config(&config.llm).ok()
-        } else {
-            None
-        };
+    let usage = load_usage(storage, config).await;
+    if hold_over_quota(usage.as_ref(), config, &memory, dry_run) {
+        return Ok(());
+    }
 
-        let embedding_text = memory.embedding_text();
-        let embedding = embedding_service.embed(&embedding_text).await?;
+    let llm_service = if config.llm.enabled && config.graph.dedup_llm {
+        shabka_core::llm::LlmService::from_config(&config.llm)
+            .ok()
+            .map(|llm| llm.with_language(config.output.language.as_deref()))
+    } else {
+        None
+    };
 
-        // Dedup check
-        let dedup_decision = shabka_core::dedup::check_duplicate(
-            &storage,
-            &embedding,
-            &config.graph,
-            None,
-            llm_service.as_ref(),
-            &memory.title,
-            &memory.content,
-        )
-        .await;
+    let embedding_text = memory.embedding_text();
+    let embedding = embedding_service.embed(&embedding_text).await?;
 
-        match dedup_decision {
-            shabka_core::dedup::DedupDecision::Skip {
-                existing_id,
-                existing_title,
-                similarity,
-            } => {
-                tracing::info!(
-                    "dedup skip ({similarity:.2}): '{}' matches existing '{existing_title}' ({existing_id})",
-                    memory.title,
-                );
-                return Ok(());
-            }
-            shabka_core::dedup::DedupDecision::Supersede {
+    // Dedup check
+    let dedup_decision = shabka_core::dedup::check_duplicate(
+        storage,
+        &embedding,
+        &config.graph,
+        None,
+        llm_service.as_ref(),
+        &memory.title,
+        &memory.content,
+    )
+    .await;
+
+    if dry_run {
+        report_outcome(&memory, &dedup_decision);
+        return Ok(());
+    }
+
+    let op = match dedup_decision {
+        DedupDecision::Skip {
+            existing_id,
+            existing_title,
+            similarity,
+        } => {
+            tracing::info!(
+                "dedup skip ({similarity:.2}): '{}' matches existing '{existing_title}' ({existing_id})",
+                memory.title,
+            );
+            let assertion = Assertion::new(memory.created_by.clone(), "hooks");
+            let _ = trust::record_assertion(storage, existing_id, assertion).await;
+            return Ok(());
+        }
+        DedupDecision::Supersede {
+            existing_id,
+            existing_title,
+            similarity,
+        } => {
+            tracing::info!(
+                "dedup supersede ({similarity:.2}): '{}' supersedes '{existing_title}' ({existing_id})",
+                memory.title,
+            );
+            Some(JournalOp::Supersede {
+                memory_id: memory.id,
                 existing_id,
-                existing_title,
-                similarity,
-            } => {
-                tracing::info!(
-                    "dedup supersede ({similarity:.2}): '{}' supersedes '{existing_title}' ({existing_id})",
-                    memory.title,
-                );
-                let _ = storage
-                    .update_memory(
-                        existing_id,
-                        &shabka_core::model::UpdateMemoryInput {
-                            status: Some(shabka_core::model::MemoryStatus::Superseded),
-                            ..Default::default()
-                        },
-                    )
-                    .await;
-                let _ = storage
-                    .add_relation(&shabka_core::model::MemoryRelation {
-                        source_id: memory.id,
-                        target_id: existing_id,
-                        relation_type: shabka_core::model::RelationType::Supersedes,
-                        strength: similarity,
-                    })
-                    .await;
-            }
-            shabka_core::dedup::DedupDecision::Update {
+                previous_status: current_status(storage, existing_id).await,
+                strength: similarity,
+            })
+        }
+        DedupDecision::Update {
+            existing_id,
+            existing_title,
+            merged_content,
+            merged_title,
+            similarity,
+        } => {
+            tracing::info!(
+                "dedup merge ({similarity:.2}): new info merged into '{existing_title}' ({existing_id})",
+            );
+            let op = JournalOp::Merge {
                 existing_id,
-                existing_title,
-                merged_content,
-                merged_title,
-                similarity,
-            } => {
-                tracing::info!(
-                    "dedup merge ({similarity:.2}): new info merged into '{existing_title}' ({existing_id})",
-                );
-                let _ = storage
-                    .update_memory(
-                        existing_id,
-                        &shabka_core::model::UpdateMemoryInput {
-                            title: Some(merged_title),
-                            content: Some(merged_content),
-                            ..Default::default()
-                        },
-                    )
-                    .await;
-                return Ok(());
+                title: merged_title,
+                content: merged_content,
+            };
+            if apply_journaled(storage, journal, op, None).await.is_ok() {
+                refresh_merged(storage, embedding_service, config, existing_id).await;
             }
-            shabka_core::dedup::DedupDecision::Contradict {
+            return Ok(());
+        }
+        DedupDecision::Contradict {
+            existing_id,
+            existing_title,
+            similarity,
+            reason,
+        } => {
+            tracing::info!(
+                "dedup contradict ({similarity:.2}): '{}' contradicts '{existing_title}': {reason}",
+                memory.title,
+            );
+            Some(JournalOp::Contradict {
+                memory_id: memory.id,
                 existing_id,
-                existing_title,
-                similarity,
-                reason,
-            } => {
-                tracing::info!(
-                    "dedup contradict ({similarity:.2}): '{}' contradicts '{existing_title}': {reason}",
-                    memory.title,
-                );
-                storage.save_memory(&memory, Some(&embedding)).await?;
-                let _ = storage
-                    .add_relation(&shabka_core::model::MemoryRelation {
-                        source_id: memory.id,
-                        target_id: existing_id,
-                        relation_type: shabka_core::model::RelationType::Contradicts,
-                        strength: similarity,
-                    })
-                    .await;
-                shabka_core::graph::semantic_auto_relate(&storage, memory.id, &embedding, None, None).await;
-                return Ok(());
-            }
-            shabka_core::dedup::DedupDecision::Add => {}
+                strength: similarity,
+            })
         }
+        DedupDecision::Add => None,
+    };
 
-        storage.save_memory(&memory, Some(&embedding)).await?;
+    match op {
+        Some(op) => apply_journaled(storage, journal, op, Some((&memory, &embedding))).await?,
+        None => storage.save_memory(&memory, Some(&embedding)).await?,
+    }
 
-        tracing::info!(
-            "captured {} memory: {} (importance: {importance})",
-            memory.kind,
+    tracing::info!(
+        "captured {} memory: {} (importance: {})",
+        memory.kind,
+        memory.title,
+        memory.importance,
+    );
+
+    // Auto-create relations
+    if relatable(&memory, config) {
+        relate::auto_relate(storage, &memory, &event.session_id).await;
+        shabka_core::graph::semantic_auto_relate(storage, memory.id, &embedding, None, None).await;
+    }
+
+    Ok(())
+}
+
+/// Whether a new capture may be auto-related. Captures below
+/// `[capture] min_confidence` wait until someone verifies them.
+fn relatable(memory: &Memory, config: &ShabkaConfig) -> bool {
+    if memory.needs_confirmation(config.capture.min_confidence) {
+        tracing::debug!(
+            "'{}' has confidence {:.2}, below min_confidence {}; not relating",
             memory.title,
+            memory.confidence.unwrap_or_default(),
+            config.capture.min_confidence
         );
+        return false;
+    }
+    true
+}
 
-        // Auto-create relations
-        relate::auto_relate(&storage, &memory, &event.session_id).await;
-        shabka_core::graph::semantic_auto_relate(&storage, memory.id, &embedding, None, None).await;
+/// Status of an existing memory before it is superseded (active if unknown).
+async fn current_status(storage: &Storage, id: uuid::Uuid) -> MemoryStatus {
+    storage
+        .get_memory(id)
+        .await
+        .map(|m| m.status)
+        .unwrap_or_default()
+}
 
-        Ok::<(), anyhow::Error>(())
-    })?;
+/// Re-embed a memory a dedup merge rewrote. Failures only log; the next
+/// `shabka reembed` catches up.
+async fn refresh_merged(
+    storage: &Storage,
+    embedding_service: &EmbeddingService,
+    config: &ShabkaConfig,
+    id: uuid::Uuid,
+) {
+    let result = match storage.get_memory(id).await {
+        Ok(merged) => {
+            embedding::refresh_after_edit(
+                storage,
+                embedding_service,
+                &config.embedding,
+                None,
+                &merged,
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        tracing::warn!("failed to re-embed merged memory {id}: {e}");
+    }
+}
+
+/// Run a multi-step dedup operation under the capture journal: record the
+/// intent, save the new memory (if the op creates one), apply the remaining
+/// steps, then record the outcome. Errors only when the new memory could not
+/// be saved; a failure after that leaves the entry open for recovery.
+async fn apply_journaled(
+    storage: &Storage,
+    journal: &CaptureJournal,
+    op: JournalOp,
+    new: Option<(&Memory, &[f32])>,
+) -> anyhow::Result<()> {
+    let entry = match journal.begin(op.clone()) {
+        Ok(id) => Some(id),
+        Err(e) => {
+            tracing::warn!("failed to journal capture operation: {e}");
+            None
+        }
+    };
+    let close = |outcome| {
+        if let Some(id) = entry {
+            if let Err(e) = journal.finish(id, outcome) {
+                tracing::warn!("failed to record journal outcome: {e}");
+            }
+        }
+    };
 
+    if let Some((memory, embedding)) = new {
+        if let Err(e) = storage.save_memory(memory, Some(embedding)).await {
+            close(JournalOutcome::RolledBack);
+            return Err(e.into());
+        }
+    }
+    match op.complete(storage).await {
+        Ok(()) => close(JournalOutcome::Applied),
+        Err(e) => tracing::warn!("capture operation left for recovery: {e}"),
+    }
     Ok(())
 }
+
+/// Finish or roll back dedup operations a crashed hook run left half-applied.
+fn recover_journal(config: &ShabkaConfig) -> anyhow::Result<()> {
+    let journal = CaptureJournal::new();
+    let cutoff = Utc::now() - chrono::Duration::minutes(journal::RECOVERY_GRACE_MINUTES);
+    if !journal.unfinished().iter().any(|e| e.started_at < cutoff) {
+        return Ok(());
+    }
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    rt.block_on(async {
+        let storage = create_backend(config)?;
+        for (entry, outcome) in journal::recover(&storage, &journal, cutoff).await {
+            tracing::info!("capture journal entry {}: {outcome}", entry.id);
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> impl Iterator<Item = String> {
+        list.iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_dry_run_requested() {
+        assert!(!dry_run_requested(args(&[]), None));
+        assert!(dry_run_requested(args(&["--dry-run"]), None));
+        assert!(dry_run_requested(args(&[]), Some("1".into())));
+        assert!(!dry_run_requested(args(&[]), Some("0".into())));
+        assert!(!dry_run_requested(args(&[]), Some("false".into())));
+    }
+
+    #[test]
+    fn test_describe_decision() {
+        let skip = DedupDecision::Skip {
+            existing_id: uuid::Uuid::nil(),
+            existing_title: "Pool exhaustion".into(),
+            similarity: 0.97,
+        };
+        assert_eq!(
+            describe_decision(&skip),
+            "skip (0.97 match with 'Pool exhaustion')"
+        );
+        assert_eq!(
+            describe_decision(&DedupDecision::Add),
+            "add (no near duplicate)"
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! Rolling fingerprint index of recently compressed memories.
+//!
+//! The same lesson tends to be captured again in later sessions. Session
+//! compression looks each new memory's fingerprint up here and, on a hit,
+//! merges the recurrence into the earlier memory (bumping its importance)
+//! instead of saving another near-duplicate.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use shabka_core::model::MemoryKind;
+use uuid::Uuid;
+
+/// Importance added to a memory each time it recurs.
+pub const RECURRENCE_BOOST: f32 = 0.1;
+
+/// Entries not seen for this many days are dropped.
+const WINDOW_DAYS: i64 = 30;
+
+/// Maximum entries kept; the least recently seen are dropped first.
+const MAX_ENTRIES: usize = 1000;
+
+/// Words too common to tell lessons apart.
+const STOPWORDS: &[&str] = &[
+    "the", "and", "for", "with", "from", "into", "when", "that", "this", "use", "using", "via",
+    "after", "before", "not", "are", "was", "has", "have",
+];
+
+/// Where a fingerprint was last seen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FingerprintEntry {
+    pub memory_id: Uuid,
+    pub last_seen: DateTime<Utc>,
+    pub count: u32,
+}
+
+/// Fingerprint → memory index persisted at `~/.config/shabka/recurrence_index.json`.
+pub struct FingerprintIndex {
+    path: PathBuf,
+    entries: HashMap<String, FingerprintEntry>,
+}
+
+impl FingerprintIndex {
+    /// Load the index from its default location (empty if missing or unreadable).
+    pub fn load() -> Self {
+        let path = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("/tmp"))
+            .join("shabka")
+            .join("recurrence_index.json");
+        Self::load_from(path)
+    }
+
+    pub fn load_from(path: PathBuf) -> Self {
+        let entries = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { path, entries }
+    }
+
+    pub fn get(&self, fingerprint: &str) -> Option<&FingerprintEntry> {
+        self.entries.get(fingerprint)
+    }
+
+    /// Record that `fingerprint` was seen as `memory_id`, counting repeats.
+    pub fn record(&mut self, fingerprint: &str, memory_id: Uuid, now: DateTime<Utc>) {
+        let entry = self
+            .entries
+            .entry(fingerprint.to_string())
+            .or_insert(FingerprintEntry {
+                memory_id,
+                last_seen: now,
+                count: 0,
+            });
+        entry.memory_id = memory_id;
+        entry.last_seen = now;
+        entry.count += 1;
+    }
+
+    /// Drop an entry whose memory is gone or no longer active.
+    pub fn forget(&mut self, fingerprint: &str) {
+        self.entries.remove(fingerprint);
+    }
+
+    /// Prune to the rolling window and size cap, then write the index.
+    pub fn save(&mut self, now: DateTime<Utc>) -> anyhow::Result<()> {
+        let cutoff = now - chrono::Duration::days(WINDOW_DAYS);
+        self.entries.retain(|_, e| e.last_seen >= cutoff);
+        if self.entries.len() > MAX_ENTRIES {
+            let mut by_age: Vec<(String, DateTime<Utc>)> = self
+                .entries
+                .iter()
+                .map(|(k, e)| (k.clone(), e.last_seen))
+                .collect();
+            by_age.sort_by_key(|(_, seen)| *seen);
+            for (key, _) in by_age.iter().take(self.entries.len() - MAX_ENTRIES) {
+                self.entries.remove(key);
+            }
+        }
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string(&self.entries)?)?;
+        Ok(())
+    }
+}
+
+/// Fingerprint a memory by kind and the significant words of its title,
+/// ignoring case, order, digits and punctuation. Returns `None` for titles
+/// too generic to match on (fewer than two significant words).
+pub fn fingerprint(kind: MemoryKind, title: &str) -> Option<String> {
+    let mut words: Vec<String> = title
+        .split(|c: char| !c.is_alphanumeric())
+        .map(|w| w.to_lowercase())
+        .filter(|w| w.len() >= 3 && !w.chars().all(|c| c.is_ascii_digit()))
+        .filter(|w| !STOPWORDS.contains(&w.as_str()))
+        .collect();
+    words.sort();
+    words.dedup();
+    if words.len() < 2 {
+        return None;
+    }
+    Some(format!("{kind}:{}", words.join(" ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_ignores_case_order_and_numbers() {
+        let a = fingerprint(MemoryKind::Lesson, "Use deadpool for the Postgres pool");
+        let b = fingerprint(MemoryKind::Lesson, "postgres pool: deadpool (2x)");
+        assert!(a.is_some());
+        assert_eq!(a, b);
+        assert_ne!(a, fingerprint(MemoryKind::Error, "postgres pool deadpool"));
+        assert_eq!(
+            fingerprint(MemoryKind::Lesson, "Update main.rs"),
+            Some("lesson:main update".to_string())
+        );
+        assert_eq!(fingerprint(MemoryKind::Lesson, "Fix it"), None);
+    }
+
+    #[test]
+    fn test_index_roundtrip_and_rolling_window() {
+        let path = std::env::temp_dir().join(format!("shabka-recurrence-{}.json", Uuid::now_v7()));
+        let now = Utc::now();
+        let id = Uuid::now_v7();
+
+        let mut index = FingerprintIndex::load_from(path.clone());
+        index.record("lesson:deadpool pool", id, now);
+        index.record("lesson:deadpool pool", id, now);
+        index.record(
+            "error:old thing",
+            Uuid::now_v7(),
+            now - chrono::Duration::days(45),
+        );
+        index.save(now).unwrap();
+
+        let index = FingerprintIndex::load_from(path.clone());
+        let entry = index.get("lesson:deadpool pool").unwrap();
+        assert_eq!(entry.memory_id, id);
+        assert_eq!(entry.count, 2);
+        assert!(index.get("error:old thing").is_none());
+        std::fs::remove_file(path).ok();
+    }
+}
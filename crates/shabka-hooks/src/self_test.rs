@@ -0,0 +1,288 @@
+//! `shabka-hooks --self-test`: replay bundled sample events against an
+//! in-memory SQLite store and report which of them produced memories.
+//!
+//! Capture settings (`session_compression`, `min_importance`, `review_mode`)
+//! come from the user's config so configuration regressions show up. Embeddings
+//! use the offline hash provider and LLM features are disabled, so the run is
+//! deterministic and never touches the network, session buffers, the backlog or
+//! the capture journal.
+
+use serde::Deserialize;
+use shabka_core::config::{EmbeddingConfig, ShabkaConfig};
+use shabka_core::embedding::EmbeddingService;
+use shabka_core::journal::CaptureJournal;
+use shabka_core::model::{MemoryStatus, TimelineQuery};
+use shabka_core::storage::{SqliteStorage, Storage, StorageBackend};
+
+use crate::event::{CaptureIntent, HookEvent};
+use crate::handlers;
+use crate::session::{self, BufferedEvent};
+
+const FIXTURES: &str = include_str!("../fixtures/self_test.json");
+
+/// A bundled scenario: a sequence of hook events and whether it should
+/// leave at least one memory behind.
+#[derive(Debug, Deserialize)]
+struct Fixture {
+    name: String,
+    description: String,
+    expect_memories: bool,
+    events: Vec<serde_json::Value>,
+}
+
+/// Outcome of replaying one fixture.
+struct FixtureResult {
+    name: String,
+    description: String,
+    events: usize,
+    expected: bool,
+    titles: Vec<String>,
+}
+
+impl FixtureResult {
+    fn passed(&self) -> bool {
+        self.expected != self.titles.is_empty()
+    }
+}
+
+/// Run every bundled fixture and print a report. Returns true when all pass.
+pub fn run(config: &ShabkaConfig) -> anyhow::Result<bool> {
+    let config = self_test_config(config);
+    let fixtures: Vec<Fixture> = serde_json::from_str(FIXTURES)?;
+
+    println!(
+        "shabka-hooks self-test (session_compression: {}, min_importance: {})",
+        if config.capture.session_compression {
+            "on"
+        } else {
+            "off"
+        },
+        config.capture.min_importance
+    );
+    if !config.capture.enabled {
+        println!("  note: capture is disabled in config; the live hooks save nothing");
+    }
+    println!();
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    let mut failed = 0;
+    for fixture in &fixtures {
+        let result = rt.block_on(replay(fixture, &config))?;
+        let status = if result.passed() { "PASS" } else { "FAIL" };
+        println!(
+            "  {status}  {:<16} {} events -> {} memor{}",
+            result.name,
+            result.events,
+            result.titles.len(),
+            if result.titles.len() == 1 { "y" } else { "ies" }
+        );
+        if !result.passed() {
+            failed += 1;
+            println!(
+                "        expected {}: {}",
+                if result.expected {
+                    "a memory"
+                } else {
+                    "no memories"
+                },
+                result.description
+            );
+        }
+        for title in &result.titles {
+            println!("        + {title}");
+        }
+    }
+
+    println!();
+    println!("{} passed, {failed} failed", fixtures.len() - failed);
+    Ok(failed == 0)
+}
+
+/// The user's config with side effects stripped out: hash embeddings,
+/// no LLM, no quotas, no recurrence index, no plugins.
+fn self_test_config(config: &ShabkaConfig) -> ShabkaConfig {
+    let mut config = config.clone();
+    config.embedding = EmbeddingConfig::default();
+    config.llm.enabled = false;
+    config.capture.max_memories_per_day = None;
+    config.capture.project_quotas.clear();
+    config.capture.merge_recurrences = false;
+    config.plugins = Default::default();
+    config
+}
+
+/// Replay one fixture through classification, compression and saving.
+async fn replay(fixture: &Fixture, config: &ShabkaConfig) -> anyhow::Result<FixtureResult> {
+    let storage = Storage::Sqlite(SqliteStorage::open_in_memory()?);
+    let embedding_service = EmbeddingService::from_config(&config.embedding)?;
+    let journal = CaptureJournal::with_path(std::env::temp_dir().join(format!(
+        "shabka-self-test-journal-{}.jsonl",
+        uuid::Uuid::now_v7()
+    )));
+    let mut buffer: Vec<BufferedEvent> = Vec::new();
+
+    for raw in &fixture.events {
+        let event = fixture_event(raw, &fixture.name)?;
+
+        if event.hook_event_name == "Stop" {
+            let memories = session::compress_heuristic(&buffer);
+            crate::save_compressed_memories(
+                &storage,
+                &embedding_service,
+                &journal,
+                &memories,
+                &event,
+                &event.session_id,
+                config,
+                false,
+            )
+            .await?;
+            buffer.clear();
+            continue;
+        }
+
+        match handlers::classify(&event, config.capture.session_compression) {
+            CaptureIntent::Skip { .. } => {}
+            CaptureIntent::Buffer {
+                kind,
+                title,
+                content,
+                importance,
+                confidence,
+                tags,
+                file_path,
+                event_type,
+                code,
+            } => buffer.push(BufferedEvent {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                kind,
+                title,
+                content,
+                importance,
+                confidence,
+                tags,
+                file_path,
+                event_type,
+                code,
+            }),
+            CaptureIntent::Save {
+                kind,
+                title,
+                content,
+                importance,
+                confidence,
+                tags,
+                code,
+            } => {
+                if importance < config.capture.min_importance {
+                    continue;
+                }
+                let memory = crate::immediate_memory(
+                    &event, config, kind, title, content, importance, confidence, tags, code,
+                );
+                crate::store_immediate(
+                    &storage,
+                    &embedding_service,
+                    &journal,
+                    memory,
+                    &event,
+                    config,
+                    false,
+                )
+                .await?;
+            }
+        }
+    }
+
+    let mut entries = storage
+        .timeline(&TimelineQuery {
+            limit: 100,
+            ..Default::default()
+        })
+        .await?;
+    // Review mode saves captures as Pending, which the default timeline hides.
+    if config.capture.review_mode {
+        entries.extend(
+            storage
+                .timeline(&TimelineQuery {
+                    limit: 100,
+                    status: Some(MemoryStatus::Pending),
+                    ..Default::default()
+                })
+                .await?,
+        );
+    }
+
+    Ok(FixtureResult {
+        name: fixture.name.clone(),
+        description: fixture.description.clone(),
+        events: fixture.events.len(),
+        expected: fixture.expect_memories,
+        titles: entries.into_iter().map(|e| e.title).collect(),
+    })
+}
+
+/// Fill in the session and cwd fields the fixtures leave out.
+fn fixture_event(raw: &serde_json::Value, name: &str) -> anyhow::Result<HookEvent> {
+    let mut value = raw.clone();
+    if let Some(obj) = value.as_object_mut() {
+        obj.entry("session_id")
+            .or_insert_with(|| format!("self-test-{name}").into());
+        obj.entry("cwd")
+            .or_insert_with(|| "/tmp/shabka-self-test".into());
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_fixtures_pass_with_defaults() {
+        let fixtures: Vec<Fixture> = serde_json::from_str(FIXTURES).unwrap();
+        assert!(!fixtures.is_empty());
+
+        let config = self_test_config(&ShabkaConfig::default_config());
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        for fixture in &fixtures {
+            let result = rt.block_on(replay(fixture, &config)).unwrap();
+            assert!(result.passed(), "fixture {} failed", fixture.name);
+        }
+    }
+
+    #[test]
+    fn test_fixtures_pass_without_session_compression() {
+        let mut config = self_test_config(&ShabkaConfig::default_config());
+        config.capture.session_compression = false;
+        config.capture.min_importance = 0.0;
+        let fixtures: Vec<Fixture> = serde_json::from_str(FIXTURES).unwrap();
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        for fixture in &fixtures {
+            let result = rt.block_on(replay(fixture, &config)).unwrap();
+            assert!(result.passed(), "fixture {} failed", fixture.name);
+        }
+    }
+
+    #[test]
+    fn test_review_mode_counts_pending_captures() {
+        let mut config = self_test_config(&ShabkaConfig::default_config());
+        config.capture.review_mode = true;
+        let fixtures: Vec<Fixture> = serde_json::from_str(FIXTURES).unwrap();
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let result = rt.block_on(replay(&fixtures[0], &config)).unwrap();
+        assert!(result.passed());
+    }
+}
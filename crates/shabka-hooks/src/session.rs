@@ -3,7 +3,9 @@ use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 use shabka_core::llm::LlmService;
-use shabka_core::model::MemoryKind;
+use shabka_core::llm_budget::LlmFeature;
+use shabka_core::model::{CodeSnippet, MemoryKind};
+use shabka_core::tokens::estimate_tokens;
 
 /// A single event stored in the session buffer.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,10 +15,17 @@ pub struct BufferedEvent {
     pub title: String,
     pub content: String,
     pub importance: f32,
+    /// Classification confidence; buffers written before it existed read
+    /// as 0.5.
+    #[serde(default = "default_confidence")]
+    pub confidence: f32,
     pub tags: Vec<String>,
     pub file_path: Option<String>,
     /// "tool_use", "tool_failure", or "intent"
     pub event_type: String,
+    /// Snippet from an Edit/Write, when the event is a code change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<CodeSnippet>,
 }
 
 /// Manages the JSONL session buffer file for a single session.
@@ -97,6 +106,35 @@ impl SessionBuffer {
         Ok(())
     }
 
+    /// Move the buffer aside so new events start a fresh file while these are
+    /// compressed. The detached file keeps the `.jsonl` extension, so if the
+    /// flush dies halfway the stale-buffer sweep still picks it up.
+    pub fn detach(&self) -> anyhow::Result<Option<SessionBuffer>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let stem = self
+            .path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("session");
+        let millis = chrono::Utc::now().timestamp_millis();
+        let path = self
+            .path
+            .with_file_name(format!("{stem}.flush-{millis}.jsonl"));
+        std::fs::rename(&self.path, &path)?;
+        Ok(Some(SessionBuffer { path }))
+    }
+
+    /// The session ID a buffer file belongs to (its name up to the first '.').
+    pub fn session_id(&self) -> &str {
+        self.path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.split('.').next())
+            .unwrap_or("")
+    }
+
     /// Check if the buffer has any events.
     #[allow(dead_code)]
     pub fn is_empty(&self) -> bool {
@@ -107,6 +145,31 @@ impl SessionBuffer {
     }
 }
 
+/// Whether a session buffer is due for a mid-session flush: it holds at
+/// least `max_events` events, or its oldest event is `max_age` old.
+/// A zero limit disables that trigger.
+pub fn should_flush(
+    events: &[BufferedEvent],
+    max_events: usize,
+    max_age: chrono::Duration,
+    now: chrono::DateTime<chrono::Utc>,
+) -> bool {
+    if max_events > 0 && events.len() >= max_events {
+        return true;
+    }
+    if max_age <= chrono::Duration::zero() {
+        return false;
+    }
+    events
+        .first()
+        .and_then(|e| chrono::DateTime::parse_from_rfc3339(&e.timestamp).ok())
+        .is_some_and(|oldest| now.signed_duration_since(oldest) >= max_age)
+}
+
+fn default_confidence() -> f32 {
+    0.5
+}
+
 /// Directory where session buffers are stored.
 fn sessions_dir() -> PathBuf {
     dirs::config_dir()
@@ -121,159 +184,393 @@ pub struct CompressedMemory {
     pub title: String,
     pub content: String,
     pub importance: f32,
+    pub confidence: f32,
     pub tags: Vec<String>,
+    pub code: Option<CodeSnippet>,
 }
 
+/// Upper bound on memories produced by heuristic compression for one session.
+/// Groups beyond the cap are folded into a single summary memory.
+const MAX_HEURISTIC_MEMORIES: usize = 5;
+
 /// Compress buffered events into memories using heuristic grouping.
 /// Used when LLM is disabled or as fallback on LLM failure.
+///
+/// Edits are grouped per file and errors per signature, so each group becomes
+/// its own memory with before/after context. Output is capped at
+/// [`MAX_HEURISTIC_MEMORIES`].
 pub fn compress_heuristic(events: &[BufferedEvent]) -> Vec<CompressedMemory> {
     if events.is_empty() {
         return Vec::new();
     }
 
-    let mut memories = Vec::new();
-
-    // Separate intents, errors, and file edits
     let intents: Vec<&BufferedEvent> = events.iter().filter(|e| e.event_type == "intent").collect();
-    let errors: Vec<&BufferedEvent> = events
-        .iter()
-        .filter(|e| e.kind == MemoryKind::Error)
-        .collect();
-    let edits: Vec<&BufferedEvent> = events
+
+    // Group edits by file path, errors by signature; both keep buffer order
+    // (event index) so errors can point at the edits that followed them.
+    let mut file_groups: Vec<(String, Vec<(usize, &BufferedEvent)>)> = Vec::new();
+    let mut error_groups: Vec<(String, Vec<(usize, &BufferedEvent)>)> = Vec::new();
+    for (index, event) in events.iter().enumerate() {
+        if event.event_type == "intent" {
+            continue;
+        }
+        let (groups, key) = if event.kind == MemoryKind::Error {
+            (&mut error_groups, error_signature(event))
+        } else {
+            let path = event.file_path.as_deref().unwrap_or("unknown").to_string();
+            (&mut file_groups, path)
+        };
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, group)) => group.push((index, event)),
+            None => groups.push((key, vec![(index, event)])),
+        }
+    }
+    file_groups.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let single_file = file_groups.len() == 1;
+    let mut memories: Vec<CompressedMemory> = file_groups
         .iter()
-        .filter(|e| e.event_type != "intent" && e.kind != MemoryKind::Error)
+        .map(|(path, group)| compress_file_group(path, group, &intents, single_file))
         .collect();
+    memories.extend(
+        error_groups
+            .iter()
+            .map(|(_, group)| compress_error_group(group, &file_groups)),
+    );
 
-    // Group edits by file path
-    let mut file_groups: std::collections::HashMap<String, Vec<&BufferedEvent>> =
-        std::collections::HashMap::new();
-    for edit in &edits {
-        let key = edit.file_path.as_deref().unwrap_or("unknown").to_string();
-        file_groups.entry(key).or_default().push(edit);
-    }
-
-    // Build a session summary from all file groups
-    if !file_groups.is_empty() {
-        let mut files_summary: Vec<String> = Vec::new();
-        let mut max_importance: f32 = 0.4;
-        let mut all_content = String::new();
-        let mut file_tags: Vec<String> = Vec::new();
-
-        let mut sorted_groups: Vec<_> = file_groups.into_iter().collect();
-        sorted_groups.sort_by_key(|(path, _)| path.clone());
-
-        for (path, group) in &sorted_groups {
-            let filename = basename(path);
-            let count = group.len();
-            files_summary.push(format!(
-                "{filename} ({count} edit{})",
-                if count > 1 { "s" } else { "" }
-            ));
-
-            for event in group {
-                max_importance = max_importance.max(event.importance);
-            }
+    cap_memories(memories)
+}
 
-            // Extract filename-based tags (e.g. "auth.rs" -> "auth", "Cargo.toml" -> "dependencies")
-            let stem = filename
-                .rsplit_once('.')
-                .map(|(s, _)| s)
-                .unwrap_or(filename);
-            if !stem.is_empty() {
-                let tag = stem.to_lowercase();
-                if tag == "cargo" {
-                    file_tags.push("dependencies".into());
-                } else if !file_tags.contains(&tag) {
-                    file_tags.push(tag);
-                }
+/// One memory for all edits to a single file, with the first "before" and
+/// the last "after" snippet.
+fn compress_file_group(
+    path: &str,
+    group: &[(usize, &BufferedEvent)],
+    intents: &[&BufferedEvent],
+    single_file: bool,
+) -> CompressedMemory {
+    let filename = basename(path);
+    let count = group.len();
+    let edits = format!("{count} edit{}", if count > 1 { "s" } else { "" });
+
+    let title = match intents.first() {
+        Some(intent) if single_file => truncate(intent.content.trim(), 80),
+        Some(intent) => format!("{} ({filename})", truncate(intent.content.trim(), 60)),
+        None => format!("Update {filename} ({edits})"),
+    };
+
+    let mut content = format!("### {filename}\n{path}, {edits}\n");
+    let before = group
+        .first()
+        .and_then(|(_, e)| edit_block(&e.content, "Replaced:"));
+    let after = group
+        .last()
+        .and_then(|(_, e)| edit_block(&e.content, "With:"));
+    match (before, after) {
+        (None, None) => {
+            if let Some((_, first)) = group.first() {
+                content.push_str(&truncate(&first.content, 300));
+                content.push('\n');
             }
-
-            // Include first and last edit content (truncated)
-            if let Some(first) = group.first() {
-                all_content.push_str(&format!("### {filename}\n"));
-                all_content.push_str(&truncate(&first.content, 300));
-                all_content.push('\n');
+        }
+        (before, after) => {
+            if let Some(before) = before {
+                content.push_str(&format!("\nBefore:\n```\n{}\n```\n", truncate(before, 300)));
             }
-            if group.len() > 1 {
-                if let Some(last) = group.last() {
-                    all_content.push_str(&format!("...\n{}\n", truncate(&last.content, 300)));
-                }
+            if let Some(after) = after {
+                content.push_str(&format!("\nAfter:\n```\n{}\n```\n", truncate(after, 300)));
             }
         }
-
-        // Include intent context if available
-        if !intents.is_empty() {
-            all_content.push_str("\n### User Intent\n");
-            for intent in &intents {
-                all_content.push_str(&truncate(&intent.content, 200));
-                all_content.push('\n');
-            }
+    }
+    if count > 1 {
+        content.push_str("\nEdits:\n");
+        for (_, edit) in group.iter().take(5) {
+            content.push_str(&format!("- {}\n", truncate(&edit.title, 100)));
+        }
+        if count > 5 {
+            content.push_str(&format!("- ... and {} more\n", count - 5));
         }
+    }
+    push_intents(&mut content, intents);
 
-        // Build title
-        let title = if let Some(first_intent) = intents.first() {
-            // Use the intent text directly as title (truncated to 80 chars)
-            truncate(first_intent.content.trim(), 80)
-        } else {
-            // No intent — build from file list
-            if files_summary.len() == 1 {
-                format!("Update {}", files_summary[0])
-            } else if files_summary.len() <= 3 {
-                format!("Update {}", files_summary.join(", "))
-            } else {
-                format!(
-                    "Update {} files: {}, and {} more",
-                    sorted_groups.len(),
-                    files_summary[..2].join(", "),
-                    sorted_groups.len() - 2,
-                )
-            }
-        };
+    let mut tags = vec!["auto-capture".into(), "session-compressed".into()];
+    tags.extend(file_tag(filename));
+
+    let importance = group
+        .iter()
+        .map(|(_, e)| e.importance)
+        .fold(0.4_f32, f32::max);
+
+    // The latest snapshot of the file's code is the one worth keeping.
+    let code = group.iter().rev().find_map(|(_, e)| e.code.clone());
+
+    CompressedMemory {
+        kind: MemoryKind::Decision,
+        title,
+        content,
+        importance: importance.min(0.7),
+        confidence: group_confidence(group),
+        tags,
+        code,
+    }
+}
 
-        let mut tags = vec!["auto-capture".into(), "session-compressed".into()];
-        tags.extend(file_tags);
+/// One memory per error signature, listing each occurrence and the files
+/// edited afterwards (the likely fix).
+fn compress_error_group(
+    group: &[(usize, &BufferedEvent)],
+    file_groups: &[(String, Vec<(usize, &BufferedEvent)>)],
+) -> CompressedMemory {
+    let (first_index, first) = group[0];
+    let title = if group.len() == 1 {
+        first.title.clone()
+    } else {
+        format!("{} ({}x)", truncate(&first.title, 80), group.len())
+    };
 
-        memories.push(CompressedMemory {
-            kind: MemoryKind::Decision,
-            title,
-            content: all_content,
-            importance: max_importance.min(0.7),
-            tags,
-        });
+    let mut content = String::new();
+    for (_, err) in group.iter().take(5) {
+        content.push_str(&format!("- {}\n", truncate(&err.title, 100)));
+        content.push_str(&format!("  {}\n", truncate(&err.content, 200)));
+    }
+    if group.len() > 5 {
+        content.push_str(&format!("- ... and {} more\n", group.len() - 5));
     }
 
-    // Errors: keep as separate memories (merged if same type)
-    if !errors.is_empty() {
-        let mut error_content = String::new();
-        let mut max_importance: f32 = 0.6;
-        for err in &errors {
-            error_content.push_str(&format!("- {}\n", truncate(&err.title, 100)));
-            error_content.push_str(&format!("  {}\n", truncate(&err.content, 200)));
-            max_importance = max_importance.max(err.importance);
-        }
-        let title = if errors.len() == 1 {
-            errors[0].title.clone()
+    let followed_by: Vec<&str> = file_groups
+        .iter()
+        .filter(|(_, edits)| edits.iter().any(|(i, _)| *i > first_index))
+        .map(|(path, _)| basename(path))
+        .collect();
+    if !followed_by.is_empty() {
+        content.push_str(&format!(
+            "\nFollowed by edits to: {}\n",
+            followed_by.join(", ")
+        ));
+    }
+
+    let importance = group
+        .iter()
+        .map(|(_, e)| e.importance)
+        .fold(0.6_f32, f32::max);
+
+    CompressedMemory {
+        kind: MemoryKind::Error,
+        title,
+        content,
+        importance,
+        confidence: group_confidence(group),
+        tags: vec!["auto-capture".into(), "session-compressed".into()],
+        code: None,
+    }
+}
+
+/// A compressed memory is only as sure as the least certain event in it.
+fn group_confidence(group: &[(usize, &BufferedEvent)]) -> f32 {
+    group.iter().map(|(_, e)| e.confidence).fold(1.0, f32::min)
+}
+
+/// Keep the most important groups and fold the rest into one summary memory.
+fn cap_memories(memories: Vec<CompressedMemory>) -> Vec<CompressedMemory> {
+    if memories.len() <= MAX_HEURISTIC_MEMORIES {
+        return memories;
+    }
+
+    // Rank by importance (stable, so ties keep buffer/path order).
+    let mut ranked: Vec<usize> = (0..memories.len()).collect();
+    ranked.sort_by(|&a, &b| memories[b].importance.total_cmp(&memories[a].importance));
+    let keep: std::collections::HashSet<usize> = ranked[..MAX_HEURISTIC_MEMORIES - 1]
+        .iter()
+        .copied()
+        .collect();
+
+    let mut kept = Vec::new();
+    let mut folded = Vec::new();
+    for (i, memory) in memories.into_iter().enumerate() {
+        if keep.contains(&i) {
+            kept.push(memory);
         } else {
-            format!("{} errors encountered", errors.len())
-        };
-        memories.push(CompressedMemory {
-            kind: MemoryKind::Error,
-            title,
-            content: error_content,
-            importance: max_importance,
-            tags: vec!["auto-capture".into(), "session-compressed".into()],
-        });
+            folded.push(memory);
+        }
+    }
+
+    let mut content = String::new();
+    for memory in &folded {
+        content.push_str(&format!("- {}\n", truncate(&memory.title, 100)));
+    }
+    let mut tags: Vec<String> = vec!["auto-capture".into(), "session-compressed".into()];
+    for tag in folded.iter().flat_map(|m| m.tags.iter()) {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    kept.push(CompressedMemory {
+        kind: MemoryKind::Observation,
+        title: format!("{} more session changes", folded.len()),
+        content,
+        importance: 0.3,
+        confidence: folded.iter().map(|m| m.confidence).fold(1.0, f32::min),
+        tags,
+        code: None,
+    });
+    kept
+}
+
+/// Group key for an error: a compiler error code such as `error[E0382]` when
+/// present, otherwise the title with digits masked so line numbers and counts
+/// don't split otherwise identical errors.
+fn error_signature(event: &BufferedEvent) -> String {
+    if let Some(start) = event.content.find("error[") {
+        if let Some(len) = event.content[start..].find(']') {
+            return event.content[start..=start + len].to_string();
+        }
+    }
+    event
+        .title
+        .chars()
+        .map(|c| if c.is_ascii_digit() { '#' } else { c })
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Extract the fenced block following `label` in an Edit event's content
+/// (see `handlers::classify_file_change`).
+fn edit_block<'a>(content: &'a str, label: &str) -> Option<&'a str> {
+    let rest = &content[content.find(label)? + label.len()..];
+    let rest = rest
+        .trim_start()
+        .strip_prefix("```")?
+        .trim_start_matches('\n');
+    let end = rest.find("\n```")?;
+    Some(&rest[..end])
+}
+
+/// Tag derived from a file name, e.g. "auth.rs" -> "auth", "Cargo.toml" -> "dependencies".
+fn file_tag(filename: &str) -> Option<String> {
+    let stem = filename
+        .rsplit_once('.')
+        .map(|(s, _)| s)
+        .unwrap_or(filename);
+    if stem.is_empty() {
+        return None;
     }
+    let tag = stem.to_lowercase();
+    Some(if tag == "cargo" {
+        "dependencies".into()
+    } else {
+        tag
+    })
+}
 
-    memories
+/// Append the session's user intents as context.
+fn push_intents(content: &mut String, intents: &[&BufferedEvent]) {
+    if intents.is_empty() {
+        return;
+    }
+    content.push_str("\n### User Intent\n");
+    for intent in intents {
+        content.push_str(&truncate(&intent.content, 200));
+        content.push('\n');
+    }
 }
 
+/// System prompt shared by the single-pass and map-reduce LLM compression.
+const EXTRACT_SYSTEM_PROMPT: &str = "\
+You are a developer knowledge extractor. Given a coding session's events, \
+extract 1-3 high-value memories that would help a developer in FUTURE sessions.\n\
+\n\
+For each memory, identify:\n\
+- The KEY INSIGHT or LESSON learned (not just what files changed)\n\
+- ENTITIES: specific tools, libraries, APIs, patterns, or concepts involved\n\
+- WHY this matters for future reference\n\
+\n\
+Output a JSON array. Each object must have:\n\
+- \"title\": descriptive, searchable (include the core concept, e.g. 'Async trait pattern for Axum middleware')\n\
+- \"content\": 2-4 sentences explaining the insight, the approach taken, and any gotchas or key decisions. \
+Include specific technical details (function names, config keys, error messages) that aid future retrieval.\n\
+- \"kind\": one of: observation (noticed something), decision (chose an approach), pattern (reusable technique), \
+error (problem encountered), fix (solution to a problem), lesson (learned something new)\n\
+- \"importance\": 0.0-1.0 (0.8+ = would save significant time if recalled; 0.3 = minor/routine)\n\
+- \"confidence\": 0.0-1.0, how clearly the events support the memory (0.9+ = stated or shown outright; \
+below 0.5 = inferred or guessed)\n\
+- \"tags\": 3-8 lowercase tags for searchability — include: language, framework/library names, \
+specific module/file names, concepts (e.g. 'async', 'auth', 'config'), action type ('refactor', 'bugfix', 'feature')\n\
+\n\
+Focus on REUSABLE KNOWLEDGE, not session narration. Skip routine changes with no insight.";
+
+/// Confidence of an LLM-extracted memory whose reply didn't rate it.
+const DEFAULT_LLM_CONFIDENCE: f64 = 0.7;
+
+/// Smallest per-call context budget, so a tiny `llm.max_tokens` still makes progress.
+const MIN_CHUNK_TOKENS: usize = 256;
+
 /// Compress buffered events into memories using an LLM.
+///
+/// When the session context doesn't fit in `max_tokens` (estimated, minus the
+/// system prompt), events are compressed map-reduce style: each chunk is
+/// summarized on its own, then the chunk summaries are merged into the final
+/// memories.
 pub async fn compress_with_llm(
     events: &[BufferedEvent],
     llm: &LlmService,
+    max_tokens: usize,
 ) -> anyhow::Result<Vec<CompressedMemory>> {
-    // Build context for the LLM
+    let budget = context_budget(max_tokens);
+    let chunks = chunk_events(events, budget);
+    if chunks.len() <= 1 {
+        return extract_memories(&build_context(events), llm).await;
+    }
+
+    tracing::info!(
+        "session context ~{} tokens exceeds budget of {budget}, compressing {} events in {} chunks",
+        estimate_tokens(&build_context(events)),
+        events.len(),
+        chunks.len()
+    );
+    let mut partial = Vec::new();
+    for chunk in &chunks {
+        partial.extend(extract_memories(&build_context(chunk), llm).await?);
+    }
+    Ok(merge_memories(partial, llm, budget).await)
+}
+
+/// Tokens available for session context in one LLM call.
+fn context_budget(max_tokens: usize) -> usize {
+    max_tokens
+        .saturating_sub(estimate_tokens(EXTRACT_SYSTEM_PROMPT))
+        .max(MIN_CHUNK_TOKENS)
+}
+
+/// Split items into consecutive chunks whose estimated cost fits in `budget`
+/// tokens. An item larger than the budget still gets a chunk of its own.
+fn chunk_by_tokens<T>(items: &[T], budget: usize, cost: impl Fn(&T) -> usize) -> Vec<&[T]> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut used = 0;
+    for (i, item) in items.iter().enumerate() {
+        let item_cost = cost(item);
+        if i > start && used + item_cost > budget {
+            chunks.push(&items[start..i]);
+            start = i;
+            used = 0;
+        }
+        used += item_cost;
+    }
+    if start < items.len() {
+        chunks.push(&items[start..]);
+    }
+    chunks
+}
+
+/// Split events into chunks whose rendered context fits in `budget` tokens.
+fn chunk_events(events: &[BufferedEvent], budget: usize) -> Vec<&[BufferedEvent]> {
+    chunk_by_tokens(events, budget, |e| {
+        estimate_tokens(&build_context(std::slice::from_ref(e)))
+    })
+}
+
+/// Render events as the Markdown context sent to the LLM.
+fn build_context(events: &[BufferedEvent]) -> String {
     let mut context = String::new();
 
     // Collect intents
@@ -316,27 +613,14 @@ pub async fn compress_with_llm(
         context.push('\n');
     }
 
-    let system = "\
-You are a developer knowledge extractor. Given a coding session's events, \
-extract 1-3 high-value memories that would help a developer in FUTURE sessions.\n\
-\n\
-For each memory, identify:\n\
-- The KEY INSIGHT or LESSON learned (not just what files changed)\n\
-- ENTITIES: specific tools, libraries, APIs, patterns, or concepts involved\n\
-- WHY this matters for future reference\n\
-\n\
-Output a JSON array. Each object must have:\n\
-- \"title\": descriptive, searchable (include the core concept, e.g. 'Async trait pattern for Axum middleware')\n\
-- \"content\": 2-4 sentences explaining the insight, the approach taken, and any gotchas or key decisions. \
-Include specific technical details (function names, config keys, error messages) that aid future retrieval.\n\
-- \"kind\": one of: observation (noticed something), decision (chose an approach), pattern (reusable technique), \
-error (problem encountered), fix (solution to a problem), lesson (learned something new)\n\
-- \"importance\": 0.0-1.0 (0.8+ = would save significant time if recalled; 0.3 = minor/routine)\n\
-- \"tags\": 3-8 lowercase tags for searchability — include: language, framework/library names, \
-specific module/file names, concepts (e.g. 'async', 'auth', 'config'), action type ('refactor', 'bugfix', 'feature')\n\
-\n\
-Focus on REUSABLE KNOWLEDGE, not session narration. Skip routine changes with no insight.";
+    context
+}
 
+/// Map step: extract memories from one rendered session context.
+async fn extract_memories(
+    context: &str,
+    llm: &LlmService,
+) -> anyhow::Result<Vec<CompressedMemory>> {
     let prompt = format!(
         "Extract reusable developer knowledge from this coding session:\n\n\
         {context}\n\n\
@@ -348,12 +632,88 @@ Focus on REUSABLE KNOWLEDGE, not session narration. Skip routine changes with no
         Respond ONLY with a JSON array, no markdown fences."
     );
 
-    let response = llm.generate(&prompt, Some(system)).await?;
+    let response = llm
+        .generate(
+            LlmFeature::Compression,
+            &prompt,
+            Some(EXTRACT_SYSTEM_PROMPT),
+        )
+        .await?;
 
     // Parse JSON response
     parse_llm_memories(&response)
 }
 
+/// Render partial memories as context for the reduce step.
+fn render_partial(memories: &[CompressedMemory]) -> String {
+    let mut context = String::new();
+    for memory in memories {
+        context.push_str(&format!(
+            "### [{}] {} (importance {:.1})\n{}\n\n",
+            memory.kind,
+            memory.title,
+            memory.importance,
+            truncate(&memory.content, 600)
+        ));
+    }
+    context
+}
+
+/// Reduce step: merge chunk summaries into the final memories, batching
+/// further when they don't fit in one call. If merging fails, the chunk
+/// summaries are returned as they are rather than losing the session.
+async fn merge_memories(
+    mut memories: Vec<CompressedMemory>,
+    llm: &LlmService,
+    budget: usize,
+) -> Vec<CompressedMemory> {
+    loop {
+        let batches = chunk_memories(&memories, budget);
+        let mut merged = Vec::new();
+        for batch in &batches {
+            let prompt = format!(
+                "These memories were extracted from consecutive parts of ONE coding session:\n\n\
+                {}\n\
+                Merge them into 1-3 final memories: combine ones about the same topic, keep \
+                specific technical details, and drop anything routine.\n\
+                Respond ONLY with a JSON array, no markdown fences.",
+                render_partial(batch)
+            );
+            let result = match llm
+                .generate(
+                    LlmFeature::Compression,
+                    &prompt,
+                    Some(EXTRACT_SYSTEM_PROMPT),
+                )
+                .await
+            {
+                Ok(response) => parse_llm_memories(&response),
+                Err(e) => Err(e.into()),
+            };
+            match result {
+                Ok(batch_memories) => merged.extend(batch_memories),
+                Err(e) => {
+                    tracing::warn!("LLM merge of chunk summaries failed, keeping them as-is: {e}");
+                    return memories;
+                }
+            }
+        }
+        // Stop once everything went through a single call, or when another
+        // round would not shrink the set.
+        if batches.len() <= 1 || merged.len() >= memories.len() {
+            return merged;
+        }
+        memories = merged;
+    }
+}
+
+/// Split partial memories into batches whose rendering fits in `budget` tokens.
+fn chunk_memories(memories: &[CompressedMemory], budget: usize) -> Vec<&[CompressedMemory]> {
+    chunk_by_tokens(memories, budget, |m| {
+        estimate_tokens(&render_partial(std::slice::from_ref(m)))
+    })
+}
+
 /// Parse the LLM response into compressed memories.
 fn parse_llm_memories(response: &str) -> anyhow::Result<Vec<CompressedMemory>> {
     // Try to extract JSON array from response (handle markdown fences)
@@ -384,6 +744,10 @@ fn parse_llm_memories(response: &str) -> anyhow::Result<Vec<CompressedMemory>> {
             _ => MemoryKind::Observation,
         };
         let importance = item["importance"].as_f64().unwrap_or(0.5).clamp(0.0, 1.0) as f32;
+        let confidence = item["confidence"]
+            .as_f64()
+            .unwrap_or(DEFAULT_LLM_CONFIDENCE)
+            .clamp(0.0, 1.0) as f32;
 
         // Merge system tags with LLM-generated tags
         let mut tags = vec![
@@ -407,7 +771,9 @@ fn parse_llm_memories(response: &str) -> anyhow::Result<Vec<CompressedMemory>> {
             title,
             content,
             importance,
+            confidence,
             tags,
+            code: None,
         });
     }
 
@@ -487,9 +853,11 @@ mod tests {
             title: title.into(),
             content: format!("File modified via Edit: {file_path}"),
             importance: 0.4,
+            confidence: 0.5,
             tags: vec!["auto-capture".into()],
             file_path: Some(file_path.into()),
             event_type: "tool_use".into(),
+            code: None,
         }
     }
 
@@ -500,9 +868,11 @@ mod tests {
             title: title.into(),
             content: format!("Error: {title}"),
             importance: 0.6,
+            confidence: 0.6,
             tags: vec!["auto-capture".into()],
             file_path: None,
             event_type: "tool_use".into(),
+            code: None,
         }
     }
 
@@ -513,9 +883,11 @@ mod tests {
             title: "User intent".into(),
             content: prompt.into(),
             importance: 0.3,
+            confidence: 0.9,
             tags: Vec::new(),
             file_path: None,
             event_type: "intent".into(),
+            code: None,
         }
     }
 
@@ -570,6 +942,40 @@ mod tests {
         assert!(buf.is_empty());
     }
 
+    #[test]
+    fn test_should_flush_by_count_and_age() {
+        let now = Utc::now();
+        let thirty = chrono::Duration::minutes(30);
+        let events: Vec<BufferedEvent> = (0..3)
+            .map(|i| make_edit_event("/src/main.rs", &format!("Edit {i}")))
+            .collect();
+
+        assert!(!should_flush(&[], 3, thirty, now));
+        assert!(!should_flush(&events, 4, thirty, now));
+        assert!(should_flush(&events, 3, thirty, now));
+        assert!(!should_flush(&events, 0, chrono::Duration::zero(), now));
+
+        let mut old = events.clone();
+        old[0].timestamp = (now - chrono::Duration::minutes(31)).to_rfc3339();
+        assert!(should_flush(&old, 0, thirty, now));
+        assert!(!should_flush(&old, 0, chrono::Duration::zero(), now));
+    }
+
+    #[test]
+    fn test_buffer_detach_keeps_session_id() {
+        let buf = temp_buffer("detach");
+        buf.append(&make_edit_event("/src/main.rs", "Edit main.rs"))
+            .unwrap();
+        assert_eq!(buf.session_id(), "test-session");
+
+        let detached = buf.detach().unwrap().expect("buffer existed");
+        assert!(buf.is_empty());
+        assert_eq!(detached.session_id(), "test-session");
+        assert_eq!(detached.read_all().unwrap().len(), 1);
+        detached.delete().unwrap();
+        assert!(buf.detach().unwrap().is_none());
+    }
+
     #[test]
     fn test_compress_heuristic_single_file() {
         let events = vec![
@@ -597,13 +1003,10 @@ mod tests {
             make_edit_event("/src/utils.rs", "Edit utils.rs"),
         ];
         let memories = compress_heuristic(&events);
-        assert_eq!(memories.len(), 1);
-        assert!(
-            memories[0].title.starts_with("Update "),
-            "title should start with 'Update': {}",
-            memories[0].title
-        );
-        assert!(memories[0].title.contains("4 files"));
+        // One memory per file, ordered by path
+        assert_eq!(memories.len(), 4);
+        assert_eq!(memories[0].title, "Update config.rs (1 edit)");
+        assert!(memories.iter().all(|m| m.kind == MemoryKind::Decision));
     }
 
     #[test]
@@ -614,19 +1017,14 @@ mod tests {
             make_edit_event("/src/config.rs", "Edit config.rs: update settings"),
         ];
         let memories = compress_heuristic(&events);
-        assert_eq!(memories.len(), 1);
-        // Title should be the user intent text directly
-        assert!(
-            memories[0].title.contains("Fix the login bug"),
-            "title should contain intent: {}",
-            memories[0].title
-        );
-        // Should NOT contain "modified" — intent text is used directly
-        assert!(
-            !memories[0].title.contains("modified"),
-            "title should not contain 'modified': {}",
-            memories[0].title
+        assert_eq!(memories.len(), 2);
+        // Each file's title carries the intent plus the file name
+        assert_eq!(memories[0].title, "Fix the login bug in auth.rs (auth.rs)");
+        assert_eq!(
+            memories[1].title,
+            "Fix the login bug in auth.rs (config.rs)"
         );
+        assert!(memories.iter().all(|m| m.content.contains("User Intent")));
     }
 
     #[test]
@@ -636,17 +1034,64 @@ mod tests {
             make_edit_event("/Cargo.toml", "Edit Cargo.toml"),
         ];
         let memories = compress_heuristic(&events);
-        assert_eq!(memories.len(), 1);
-        assert!(
-            memories[0].tags.contains(&"auth".to_string()),
-            "should have 'auth' tag from auth.rs: {:?}",
-            memories[0].tags
-        );
+        assert_eq!(memories.len(), 2);
         assert!(
             memories[0].tags.contains(&"dependencies".to_string()),
             "should have 'dependencies' tag from Cargo.toml: {:?}",
             memories[0].tags
         );
+        assert!(
+            memories[1].tags.contains(&"auth".to_string()),
+            "should have 'auth' tag from auth.rs: {:?}",
+            memories[1].tags
+        );
+    }
+
+    #[test]
+    fn test_compress_heuristic_before_after_context() {
+        let mut first = make_edit_event("/src/pool.rs", "Edit pool.rs: max_size(16)");
+        first.content = "File modified via Edit: /src/pool.rs\n\nReplaced:\n```\nmax_size(8)\n```\n\nWith:\n```\nmax_size(16)\n```".into();
+        let mut second = make_edit_event("/src/pool.rs", "Edit pool.rs: max_size(32)");
+        second.content = "File modified via Edit: /src/pool.rs\n\nReplaced:\n```\nmax_size(16)\n```\n\nWith:\n```\nmax_size(32)\n```".into();
+
+        let memories = compress_heuristic(&[first, second]);
+        assert_eq!(memories.len(), 1);
+        let content = &memories[0].content;
+        assert!(
+            content.contains("Before:\n```\nmax_size(8)\n```"),
+            "{content}"
+        );
+        assert!(
+            content.contains("After:\n```\nmax_size(32)\n```"),
+            "{content}"
+        );
+        assert!(content.contains("- Edit pool.rs: max_size(16)"));
+    }
+
+    #[test]
+    fn test_compress_heuristic_keeps_latest_code() {
+        let mut first = make_edit_event("/src/pool.rs", "Edit pool.rs");
+        first.code = CodeSnippet::from_edit("/src/pool.rs", "fn max_size() { 8 }");
+        let mut second = make_edit_event("/src/pool.rs", "Edit pool.rs");
+        second.code = CodeSnippet::from_edit("/src/pool.rs", "fn max_size() { 16 }");
+        let error = make_error_event("build failed");
+
+        let memories = compress_heuristic(&[first, second, error]);
+        let code = memories[0].code.as_ref().expect("file group keeps code");
+        assert!(code.snippet.contains("16"));
+        assert!(memories[1].code.is_none());
+    }
+
+    #[test]
+    fn test_compress_heuristic_caps_output() {
+        let events: Vec<BufferedEvent> = (0..8)
+            .map(|i| make_edit_event(&format!("/src/mod{i}.rs"), &format!("Edit mod{i}.rs")))
+            .collect();
+        let memories = compress_heuristic(&events);
+        assert_eq!(memories.len(), MAX_HEURISTIC_MEMORIES);
+        let summary = memories.last().unwrap();
+        assert_eq!(summary.title, "4 more session changes");
+        assert!(summary.content.contains("Update mod7.rs (1 edit)"));
     }
 
     #[test]
@@ -661,6 +1106,24 @@ mod tests {
         assert!(memories.iter().any(|m| m.kind == MemoryKind::Decision));
     }
 
+    #[test]
+    fn test_compress_heuristic_keeps_lowest_confidence() {
+        let mut guessed = make_edit_event("/src/main.rs", "Edit main.rs");
+        guessed.confidence = 0.2;
+        let events = vec![make_edit_event("/src/main.rs", "Write main.rs"), guessed];
+        let memories = compress_heuristic(&events);
+        assert!((memories[0].confidence - 0.2).abs() < f32::EPSILON);
+
+        // Buffers written before confidence existed still load.
+        let old: BufferedEvent = serde_json::from_str(
+            r#"{"timestamp": "2026-01-01T00:00:00Z", "kind": "decision", "title": "t",
+                "content": "c", "importance": 0.4, "tags": [], "file_path": null,
+                "event_type": "tool_use"}"#,
+        )
+        .unwrap();
+        assert!((old.confidence - 0.5).abs() < f32::EPSILON);
+    }
+
     #[test]
     fn test_compress_heuristic_with_intents() {
         let events = vec![
@@ -688,9 +1151,69 @@ mod tests {
             make_error_event("test failed"),
         ];
         let memories = compress_heuristic(&events);
-        assert_eq!(memories.len(), 1);
-        assert_eq!(memories[0].kind, MemoryKind::Error);
-        assert!(memories[0].title.contains("2 errors"));
+        // Different signatures stay separate
+        assert_eq!(memories.len(), 2);
+        assert!(memories.iter().all(|m| m.kind == MemoryKind::Error));
+    }
+
+    #[test]
+    fn test_compress_heuristic_groups_errors_by_signature() {
+        let mut a = make_error_event("Bash error: cargo build");
+        a.content = "error[E0382]: borrow of moved value: `config` at src/main.rs:42".into();
+        let mut b = make_error_event("Bash error: cargo check");
+        b.content = "error[E0382]: borrow of moved value: `pool` at src/db.rs:7".into();
+        let events = vec![
+            make_error_event("Tool failure: Read (line 12)"),
+            a,
+            make_edit_event("/src/main.rs", "Edit main.rs: clone config"),
+            b,
+            make_error_event("Tool failure: Read (line 40)"),
+        ];
+        let memories = compress_heuristic(&events);
+        assert_eq!(memories.len(), 3);
+        let errors: Vec<_> = memories
+            .iter()
+            .filter(|m| m.kind == MemoryKind::Error)
+            .collect();
+        assert_eq!(errors[0].title, "Tool failure: Read (line 12) (2x)");
+        assert_eq!(errors[1].title, "Bash error: cargo build (2x)");
+        assert!(errors[1].content.contains("Followed by edits to: main.rs"));
+    }
+
+    #[test]
+    fn test_chunk_events_respects_budget() {
+        let events: Vec<BufferedEvent> = (0..40)
+            .map(|i| make_edit_event(&format!("/src/mod{i}.rs"), &format!("Edit mod{i}.rs")))
+            .collect();
+        let per_event = estimate_tokens(&build_context(&events[..1]));
+
+        // Everything fits: one chunk
+        assert_eq!(chunk_events(&events, per_event * 40).len(), 1);
+
+        let chunks = chunk_events(&events, per_event * 10);
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks.iter().all(|c| c.len() == 10));
+        assert_eq!(chunks.iter().map(|c| c.len()).sum::<usize>(), 40);
+    }
+
+    #[test]
+    fn test_chunk_events_oversized_event_gets_own_chunk() {
+        let mut big = make_edit_event("/src/big.rs", "Edit big.rs");
+        big.content = "x".repeat(4000);
+        let events = vec![
+            make_edit_event("/src/a.rs", "Edit a.rs"),
+            big,
+            make_edit_event("/src/b.rs", "Edit b.rs"),
+        ];
+        let chunks = chunk_events(&events, 50);
+        assert_eq!(chunks.len(), 3);
+    }
+
+    #[test]
+    fn test_context_budget_subtracts_system_prompt() {
+        let prompt = estimate_tokens(EXTRACT_SYSTEM_PROMPT);
+        assert_eq!(context_budget(prompt + 4000), 4000);
+        assert_eq!(context_budget(10), MIN_CHUNK_TOKENS);
     }
 
     #[test]
@@ -719,6 +1242,7 @@ mod tests {
         ]"#;
         let memories = parse_llm_memories(response).unwrap();
         assert_eq!(memories[0].tags.len(), 3); // only system tags
+        assert!((memories[0].confidence - DEFAULT_LLM_CONFIDENCE as f32).abs() < f32::EPSILON);
     }
 
     #[test]
@@ -778,9 +1302,11 @@ mod tests {
             title: "Edit auth.rs: fix login".into(),
             content: "File modified via Edit: /src/auth.rs".into(),
             importance: 0.4,
+            confidence: 0.5,
             tags: vec!["auto-capture".into()],
             file_path: Some("/src/auth.rs".into()),
             event_type: "tool_use".into(),
+            code: None,
         };
 
         buf.append(&event).unwrap();
@@ -795,4 +1321,27 @@ mod tests {
 
         buf.delete().unwrap();
     }
+
+    #[tokio::test]
+    async fn test_compress_with_llm_offline() {
+        use shabka_core::testing::MockLlm;
+
+        let mock = MockLlm::new().reply(
+            r#"[{"title": "JWT auth", "content": "Chose JWT for stateless auth", "kind": "decision", "importance": 0.7, "tags": ["Auth"]}]"#,
+        );
+        let llm = LlmService::mock(mock.clone());
+        let events = vec![
+            make_intent_event("add login"),
+            make_edit_event("/src/auth.rs", "Edit auth.rs"),
+        ];
+
+        let memories = compress_with_llm(&events, &llm, 4096).await.unwrap();
+        assert_eq!(memories.len(), 1);
+        assert_eq!(memories[0].kind, MemoryKind::Decision);
+        assert!(memories[0].tags.contains(&"auth".to_string()));
+
+        let calls = mock.calls();
+        assert_eq!(calls.len(), 1);
+        assert!(calls[0].prompt.contains("/src/auth.rs"));
+    }
 }
@@ -5,6 +5,8 @@ use clap::Parser;
 use rmcp::{transport::stdio, ServiceExt};
 use tracing_subscriber::EnvFilter;
 
+use shabka_core::config::ShabkaConfig;
+use shabka_core::services::Services;
 use shabka_mcp::ShabkaServer;
 
 #[derive(Parser)]
@@ -28,64 +30,114 @@ async fn main() -> Result<()> {
         .with_writer(std::io::stderr)
         .init();
 
-    // Spawn auto-consolidation if configured
-    maybe_auto_consolidate();
+    let config = ShabkaConfig::load(Some(&std::env::current_dir().unwrap_or_default()))
+        .unwrap_or_else(|_| ShabkaConfig::default_config());
+    let services = Services::new(config);
+
+    // Spawn auto-consolidation and the centrality refresh if configured
+    maybe_auto_consolidate(&services);
+    maybe_refresh_centrality(&services);
 
     match cli.http {
-        Some(port) => run_http(port, &cli.bind).await,
-        None => run_stdio().await,
+        Some(port) => run_http(services, port, &cli.bind).await,
+        None => run_stdio(&services).await,
     }
 }
 
 /// Check if auto-consolidation is due and spawn it in the background.
 /// Never blocks startup or propagates errors.
-fn maybe_auto_consolidate() {
-    use shabka_core::config::{ConsolidateState, ShabkaConfig};
-
-    let config = ShabkaConfig::load(Some(&std::env::current_dir().unwrap_or_default()))
-        .unwrap_or_else(|_| ShabkaConfig::default_config());
-
-    if !config.consolidate.auto {
-        return;
-    }
-
-    let state = ConsolidateState::load();
-    if !state.is_due(&config.consolidate.interval) {
-        tracing::debug!("auto-consolidation not due (last run: {})", state.last_run);
+fn maybe_auto_consolidate(services: &Arc<Services>) {
+    if !services.config().consolidate.auto {
         return;
     }
 
-    tracing::info!("auto-consolidation is due, spawning background task");
+    let services = services.clone();
     tokio::spawn(async move {
-        if let Err(e) = run_auto_consolidate(config).await {
+        if !auto_consolidate_due(&services).await {
+            return;
+        }
+        tracing::info!("auto-consolidation is due, running in the background");
+        if let Err(e) = run_auto_consolidate(&services).await {
             tracing::warn!("auto-consolidation failed: {e}");
         }
     });
 }
 
-async fn run_auto_consolidate(config: shabka_core::config::ShabkaConfig) -> Result<()> {
+/// Whether the configured interval has passed since the last successful
+/// consolidation. Uses the maintenance history when the backend keeps one,
+/// otherwise the state file.
+async fn auto_consolidate_due(services: &Services) -> bool {
+    use shabka_core::config::ConsolidateState;
+    use shabka_core::maintenance::{self, MaintenanceOp};
+    use shabka_core::storage::StorageBackend;
+
+    let interval = &services.config().consolidate.interval;
+    if let Ok(storage) = services.storage() {
+        if storage.capabilities().maintenance_log {
+            return match storage
+                .last_successful_run(MaintenanceOp::Consolidate)
+                .await
+            {
+                Ok(last) => {
+                    let due = maintenance::is_due(last.as_ref(), interval, chrono::Utc::now());
+                    if !due {
+                        tracing::debug!(
+                            "auto-consolidation not due (last run: {:?})",
+                            last.and_then(|run| run.finished_at)
+                        );
+                    }
+                    due
+                }
+                Err(e) => {
+                    tracing::warn!("failed to read maintenance history: {e}");
+                    false
+                }
+            };
+        }
+    }
+
+    let state = ConsolidateState::load();
+    let due = state.is_due(interval);
+    if !due {
+        tracing::debug!("auto-consolidation not due (last run: {})", state.last_run);
+    }
+    due
+}
+
+async fn run_auto_consolidate(services: &Services) -> Result<()> {
     use shabka_core::config::ConsolidateState;
     use shabka_core::consolidate;
-    use shabka_core::embedding::EmbeddingService;
-    use shabka_core::history::HistoryLogger;
-    use shabka_core::storage::create_backend;
+    use shabka_core::maintenance::{MaintenanceOp, MaintenanceRun};
 
-    let storage = create_backend(&config)?;
-    let embedder = EmbeddingService::from_config(&config.embedding)?;
-    let llm = shabka_core::llm::LlmService::from_config(&config.llm)?;
-    let history = HistoryLogger::new(config.history.enabled);
-    let user_id = shabka_core::config::resolve_user_id(&config.sharing);
+    let config = services.config();
+    let storage = services.storage()?;
+    let embedder = services.embedding()?;
+    let llm = shabka_core::llm::LlmService::from_config(&config.llm)?
+        .with_language(config.output.language.as_deref());
 
+    let mut run = MaintenanceRun::start(
+        MaintenanceOp::Consolidate,
+        serde_json::to_value(&config.consolidate)?,
+    );
     let result = consolidate::consolidate(
-        &storage,
+        storage.as_ref(),
         &embedder,
         &llm,
         &config.consolidate,
-        &user_id,
-        &history,
+        services.user_id(),
+        &services.history(),
         false,
     )
-    .await?;
+    .await;
+    match &result {
+        Ok(result) => result.record_counts(&mut run),
+        Err(e) => run.error(e.to_string()),
+    }
+    run.finish();
+    if let Err(e) = storage.record_maintenance_run(&run).await {
+        tracing::warn!("failed to record auto-consolidation run: {e}");
+    }
+    let result = result?;
 
     tracing::info!(
         "auto-consolidation complete: {} clusters consolidated, {} memories superseded, {} new memories",
@@ -104,15 +156,63 @@ async fn run_auto_consolidate(config: shabka_core::config::ShabkaConfig) -> Resu
     Ok(())
 }
 
-async fn run_stdio() -> Result<()> {
+/// Recompute memory centrality in the background when the configured
+/// interval has passed since the last refresh. Never blocks startup or
+/// propagates errors.
+fn maybe_refresh_centrality(services: &Arc<Services>) {
+    if !services.config().graph.auto_centrality {
+        return;
+    }
+
+    let services = services.clone();
+    tokio::spawn(async move {
+        if let Err(e) = refresh_centrality(&services).await {
+            tracing::warn!("centrality refresh failed: {e}");
+        }
+    });
+}
+
+async fn refresh_centrality(services: &Services) -> Result<()> {
+    use shabka_core::graph;
+    use shabka_core::maintenance::{self, MaintenanceOp, MaintenanceRun};
+    use shabka_core::storage::StorageBackend;
+
+    let storage = services.storage()?;
+    if !storage.capabilities().centrality {
+        return Ok(());
+    }
+    let last = storage
+        .last_successful_run(MaintenanceOp::Centrality)
+        .await?;
+    let interval = &services.config().graph.centrality_interval;
+    if !maintenance::is_due(last.as_ref(), interval, chrono::Utc::now()) {
+        tracing::debug!("centrality refresh not due");
+        return Ok(());
+    }
+
+    let mut run = MaintenanceRun::start(MaintenanceOp::Centrality, serde_json::Value::Null);
+    let result = graph::refresh_centrality(&storage).await;
+    match &result {
+        Ok(scores) => run.count("memories_scored", scores.len()),
+        Err(e) => run.error(e.to_string()),
+    }
+    run.finish();
+    if let Err(e) = storage.record_maintenance_run(&run).await {
+        tracing::warn!("failed to record centrality run: {e}");
+    }
+    tracing::info!("centrality refreshed for {} memories", result?.len());
+    Ok(())
+}
+
+async fn run_stdio(services: &Services) -> Result<()> {
     tracing::info!("Starting Shabka MCP server (stdio)");
-    let service = ShabkaServer::new()?;
+    let service = ShabkaServer::from_services(services)?;
     let running = service.serve(stdio()).await?;
     running.waiting().await?;
     Ok(())
 }
 
-async fn run_http(port: u16, bind: &str) -> Result<()> {
+async fn run_http(services: Arc<Services>, port: u16, bind: &str) -> Result<()> {
     use rmcp::transport::streamable_http_server::session::local::LocalSessionManager;
     use rmcp::transport::streamable_http_server::StreamableHttpServerConfig;
     use rmcp::transport::StreamableHttpService;
@@ -131,7 +231,7 @@ async fn run_http(port: u16, bind: &str) -> Result<()> {
     };
 
     let mcp_service = StreamableHttpService::new(
-        || ShabkaServer::new().map_err(std::io::Error::other),
+        move || ShabkaServer::from_services(&services).map_err(std::io::Error::other),
         session_manager,
         config,
     );
@@ -1,26 +1,42 @@
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use rmcp::handler::server::tool::ToolRouter;
+use rmcp::handler::server::tool::{ToolCallContext, ToolRouter};
 use rmcp::handler::server::wrapper::Parameters;
 use rmcp::model::*;
-use rmcp::{schemars, tool, tool_handler, tool_router, ServerHandler};
+use rmcp::service::RequestContext;
+use rmcp::{schemars, tool, tool_router, RoleServer, ServerHandler};
 use serde::Deserialize;
+use shabka_core::api_tokens::ApiToken;
 use shabka_core::assess::{self, AssessConfig, IssueCounts};
-use shabka_core::config::{self, EmbeddingState, ShabkaConfig};
-use shabka_core::context_pack::{build_context_pack, format_context_pack};
+use shabka_core::audit::{AuditEntry, AuditLog};
+use shabka_core::citations;
+use shabka_core::config::{EmbeddingState, ShabkaConfig};
+use shabka_core::context_pack::{build_context_pack, format_context_pack, PackFilter};
 use shabka_core::dedup::{self, DedupDecision};
 use shabka_core::embedding::EmbeddingService;
 use shabka_core::error::ShabkaError;
 use shabka_core::graph;
-use shabka_core::history::{EventAction, HistoryLogger, MemoryEvent};
+use shabka_core::history::{self, EventAction, HistoryLogger, MemoryEvent};
+use shabka_core::inbox;
 use shabka_core::llm::LlmService;
+use shabka_core::maintenance::{MaintenanceOp, MaintenanceRun};
 use shabka_core::model::*;
-use shabka_core::ranking::{self, RankCandidate, RankingWeights};
+use shabka_core::questions::{Question, QuestionLog};
+use shabka_core::retrieval::{self, SearchRequest};
+use shabka_core::retrieval_session::RetrievalSessions;
+use shabka_core::services::Services;
 use shabka_core::sharing;
-use shabka_core::storage::{create_backend, Storage, StorageBackend};
+use shabka_core::storage::{Storage, StorageBackend};
+use shabka_core::tenants::Tenants;
+use shabka_core::trust;
+use shabka_core::wasm_plugins::WasmRanker;
 use uuid::Uuid;
 
+/// Candidates `get_context` ranks before packing.
+const CONTEXT_CANDIDATES: usize = 50;
+
 #[derive(Clone)]
 pub struct ShabkaServer {
     storage: Arc<Storage>,
@@ -30,7 +46,15 @@ pub struct ShabkaServer {
     tool_router: ToolRouter<Self>,
     migration_checked: Arc<AtomicBool>,
     history: Arc<HistoryLogger>,
+    questions: Arc<QuestionLog>,
+    audit: Arc<AuditLog>,
     llm: Option<Arc<LlmService>>,
+    /// `[wasm_plugins]` ranker for search and context tools.
+    ranker: Option<Arc<WasmRanker>>,
+    retrieval_sessions: Arc<RetrievalSessions>,
+    /// Per-tenant stores, in multi-tenant mode.
+    tenants: Option<Arc<Tenants>>,
+    tenant_servers: Arc<Mutex<HashMap<String, Arc<ShabkaServer>>>>,
 }
 
 // -- Tool parameter types --
@@ -68,6 +92,12 @@ pub struct SaveMemoryParams {
     #[serde(default)]
     pub privacy: Option<String>,
 
+    #[schemars(
+        description = "Classification label, e.g. internal, confidential, public-ok (default from config)"
+    )]
+    #[serde(default)]
+    pub label: Option<String>,
+
     #[schemars(description = "Project ID to associate this memory with (optional)")]
     #[serde(default)]
     pub project_id: Option<String>,
@@ -85,7 +115,9 @@ pub struct RememberParams {
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct SearchParams {
-    #[schemars(description = "Search query text for semantic + keyword matching")]
+    #[schemars(
+        description = "Search query text for semantic + keyword matching. Supports field operators: kind:, tag:, project:, status:, author:, after:, before:, importance:, symbol:, title: (e.g. 'kind:error after:2025-01-01 pool'); quoted phrases must match exactly"
+    )]
     pub query: String,
 
     #[schemars(description = "Filter by memory kind (optional)")]
@@ -109,12 +141,36 @@ pub struct SearchParams {
     )]
     #[serde(default)]
     pub token_budget: Option<usize>,
+
+    #[schemars(
+        description = "Memory IDs (or ID prefixes) already in your context; these are left out of the results"
+    )]
+    #[serde(default)]
+    pub exclude_ids: Vec<String>,
+
+    #[schemars(
+        description = "SHA-256 hex digests (or prefixes) of memory content already in your context; matching memories are left out of the results"
+    )]
+    #[serde(default)]
+    pub exclude_hashes: Vec<String>,
+
+    #[schemars(
+        description = "Any ID you choose for this conversation (optional). Searches sharing it skip memories already returned, and favour memories like the ones you fetched with get_memories."
+    )]
+    #[serde(default)]
+    pub retrieval_session: Option<String>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct GetMemoriesParams {
     #[schemars(description = "List of memory IDs to retrieve full details for")]
     pub ids: Vec<String>,
+
+    #[schemars(
+        description = "The retrieval_session passed to search (optional). Fetched memories shape what later searches in the session favour."
+    )]
+    #[serde(default)]
+    pub retrieval_session: Option<String>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -138,13 +194,19 @@ pub struct UpdateMemoryParams {
     #[serde(default)]
     pub importance: Option<f32>,
 
-    #[schemars(description = "New status: active, archived, superseded, pending (optional)")]
+    #[schemars(
+        description = "New status: active, archived, superseded, pending, quarantined (optional)"
+    )]
     #[serde(default)]
     pub status: Option<String>,
 
     #[schemars(description = "New privacy level: public, team, private (optional)")]
     #[serde(default)]
     pub privacy: Option<String>,
+
+    #[schemars(description = "New classification label; empty string removes it (optional)")]
+    #[serde(default)]
+    pub label: Option<String>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -242,6 +304,20 @@ pub struct FollowChainParams {
     #[schemars(description = "Maximum traversal depth (default 5)")]
     #[serde(default)]
     pub max_depth: Option<usize>,
+
+    #[schemars(description = "Most memories to visit (default 50)")]
+    #[serde(default)]
+    pub max_nodes: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct SimilarMemoriesParams {
+    #[schemars(description = "Memory ID to find neighbours for")]
+    pub memory_id: String,
+
+    #[schemars(description = "Max results (default 10)")]
+    #[serde(default = "default_limit")]
+    pub limit: usize,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -275,6 +351,39 @@ pub struct VerifyMemoryParams {
 
     #[schemars(description = "Verification status: verified, disputed, outdated, or unverified")]
     pub status: String,
+
+    #[schemars(description = "Why the memory is disputed (status disputed only)")]
+    #[serde(default)]
+    pub reason: Option<String>,
+
+    #[schemars(
+        description = "ID of a memory holding counter-evidence; linked with a contradicts relation (status disputed only)"
+    )]
+    #[serde(default)]
+    pub evidence_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct VerifyCitationsParams {
+    #[schemars(
+        description = "Answer text citing memories by full UUID or bracketed short ID, e.g. 'Use a pool of 20 [0192a4b7].'"
+    )]
+    pub answer: String,
+
+    #[schemars(
+        description = "Only compare words, skipping the embedding similarity check (default false)"
+    )]
+    #[serde(default)]
+    pub lexical_only: bool,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct InboxParams {
+    #[schemars(
+        description = "Only these kinds: review, quarantine, duplicate, contradiction, verification_expired, verification_expiring, assigned, reclassify, backlog, sync_conflict (default: all)"
+    )]
+    #[serde(default)]
+    pub kinds: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -300,6 +409,28 @@ pub struct GetContextParams {
     #[schemars(description = "Max tokens in the context pack (default 2000)")]
     #[serde(default = "default_token_budget")]
     pub token_budget: usize,
+
+    #[schemars(description = "Only include verified memories (default false)")]
+    #[serde(default)]
+    pub verified_only: bool,
+
+    #[schemars(
+        description = "Leave out memories flagged by capture screening as possible prompt injection (default false)"
+    )]
+    #[serde(default)]
+    pub exclude_suspicious: bool,
+
+    #[schemars(
+        description = "Memory IDs (or ID prefixes) already in your context; these are left out of the context pack"
+    )]
+    #[serde(default)]
+    pub exclude_ids: Vec<String>,
+
+    #[schemars(
+        description = "SHA-256 hex digests (or prefixes) of memory content already in your context; matching memories are left out of the context pack"
+    )]
+    #[serde(default)]
+    pub exclude_hashes: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -364,33 +495,21 @@ fn default_order_by() -> String {
 }
 
 fn to_mcp_error(e: ShabkaError) -> ErrorData {
+    let mut data = serde_json::json!({"error_type": e.error_type()});
+    // The hint goes in the message too: clients often show only that.
+    let message = match e.hint() {
+        Some(hint) => {
+            data["hint"] = hint.clone().into();
+            format!("{e} (hint: {hint})")
+        }
+        None => e.to_string(),
+    };
     match &e {
-        ShabkaError::NotFound(_) => ErrorData::resource_not_found(
-            e.to_string(),
-            Some(serde_json::json!({"error_type": "not_found"})),
-        ),
-        ShabkaError::InvalidInput(_) => ErrorData::invalid_params(
-            e.to_string(),
-            Some(serde_json::json!({"error_type": "invalid_input"})),
-        ),
-        ShabkaError::Config(_) => ErrorData::invalid_params(
-            e.to_string(),
-            Some(serde_json::json!({"error_type": "config_error"})),
-        ),
-        _ => {
-            let variant = match &e {
-                ShabkaError::Storage(_) => "storage_error",
-                ShabkaError::Helix(_) => "helix_error",
-                ShabkaError::Http(_) => "http_error",
-                ShabkaError::Embedding(_) => "embedding_error",
-                ShabkaError::Serialization(_) => "serialization_error",
-                _ => "internal_error",
-            };
-            ErrorData::internal_error(
-                e.to_string(),
-                Some(serde_json::json!({"error_type": variant})),
-            )
+        ShabkaError::NotFound(_) => ErrorData::resource_not_found(message, Some(data)),
+        ShabkaError::InvalidInput(_) | ShabkaError::Config(_) => {
+            ErrorData::invalid_params(message, Some(data))
         }
+        _ => ErrorData::internal_error(message, Some(data)),
     }
 }
 
@@ -399,148 +518,174 @@ impl ShabkaServer {
     pub fn new() -> anyhow::Result<Self> {
         let config = ShabkaConfig::load(Some(&std::env::current_dir()?))
             .unwrap_or_else(|_| ShabkaConfig::default_config());
+        Self::from_services(&Services::new(config))
+    }
 
-        let storage = create_backend(&config)?;
-
-        let embedder = EmbeddingService::from_config(&config.embedding)?;
-        let user_id = config::resolve_user_id(&config.sharing);
-        let history = HistoryLogger::new(config.history.enabled);
-
-        let llm = if config.llm.enabled {
-            LlmService::from_config(&config.llm).ok().map(Arc::new)
-        } else {
-            None
-        };
-
+    /// A server drawing storage and services from a shared registry, so
+    /// every session in a process reuses the same handles.
+    pub fn from_services(services: &Services) -> anyhow::Result<Self> {
         Ok(Self {
-            storage: Arc::new(storage),
-            embedder: Arc::new(embedder),
-            user_id,
-            history: Arc::new(history),
-            llm,
-            config: Arc::new(config),
+            storage: services.storage()?,
+            embedder: services.embedding()?,
+            user_id: services.user_id().to_string(),
+            history: services.history(),
+            questions: services.questions(),
+            audit: services.audit(),
+            llm: services.llm(),
+            ranker: services.wasm_ranker(),
+            config: services.config().clone(),
             tool_router: Self::tool_router(),
             migration_checked: Arc::new(AtomicBool::new(false)),
+            retrieval_sessions: Arc::new(RetrievalSessions::default()),
+            tenants: None,
+            tenant_servers: Arc::default(),
         })
     }
 
+    /// Serve tool calls made with a tenant's API token from that tenant's
+    /// store.
+    pub fn with_tenants(mut self, tenants: Arc<Tenants>) -> Self {
+        self.tenants = Some(tenants);
+        self
+    }
+
+    /// The server for `tenant`'s store, built on first use.
+    fn tenant_server(&self, tenant: &str) -> Result<Arc<ShabkaServer>, ErrorData> {
+        let Some(tenants) = &self.tenants else {
+            return Err(ErrorData::invalid_request(
+                "this token belongs to a tenant, but multi-tenant mode is off",
+                None,
+            ));
+        };
+        let mut servers = self
+            .tenant_servers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some(server) = servers.get(tenant) {
+            return Ok(server.clone());
+        }
+        let services = tenants.services(tenant).map_err(to_mcp_error)?;
+        let server = Self::from_services(&services)
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+        let server = Arc::new(server);
+        servers.insert(tenant.to_string(), server.clone());
+        Ok(server)
+    }
+
+    /// Overwrite `existing_id` with a dedup merge and re-embed it.
+    async fn merge_into(&self, existing_id: Uuid, title: &str, content: String) {
+        let input = UpdateMemoryInput {
+            title: Some(title.to_string()),
+            content: Some(content),
+            ..Default::default()
+        };
+        if let Ok(merged) = self.storage.update_memory(existing_id, &input).await {
+            if let Err(e) = shabka_core::embedding::refresh_after_edit(
+                self.storage.as_ref(),
+                &self.embedder,
+                &self.config.embedding,
+                None,
+                &merged,
+            )
+            .await
+            {
+                tracing::warn!("failed to re-embed merged memory '{title}': {e}");
+            }
+        }
+        self.history.log(
+            &MemoryEvent::new(existing_id, EventAction::Updated, self.user_id.clone())
+                .with_title(title),
+        );
+    }
+
     #[cfg(test)]
     pub fn new_test(storage: Storage, config: ShabkaConfig) -> anyhow::Result<Self> {
         let embedder = EmbeddingService::from_config(&config.embedding)?;
         let user_id = "test-user".to_string();
         let history = HistoryLogger::new(true);
+        let questions = QuestionLog::with_path(
+            std::env::temp_dir().join(format!("shabka-mcp-questions-{}.jsonl", Uuid::now_v7())),
+            &config.questions,
+        );
+
+        let audit = AuditLog::with_path(
+            std::env::temp_dir().join(format!("shabka-mcp-audit-{}.jsonl", Uuid::now_v7())),
+            &config.audit,
+        );
 
         Ok(Self {
             storage: Arc::new(storage),
             embedder: Arc::new(embedder),
             user_id,
             history: Arc::new(history),
+            questions: Arc::new(questions),
+            audit: Arc::new(audit),
             llm: None,
+            ranker: None,
             config: Arc::new(config),
             tool_router: Self::tool_router(),
             migration_checked: Arc::new(AtomicBool::new(false)),
+            retrieval_sessions: Arc::new(RetrievalSessions::default()),
+            tenants: None,
+            tenant_servers: Arc::default(),
         })
     }
 
     // -- Layer 1: Index (compact search results, ~50-100 tokens each) --
 
     #[tool(
-        description = "Search memories by semantic similarity and keywords. Returns compact index entries (id, title, kind, date, score). Use get_memories to retrieve full details for specific IDs. Filters: kind (observation/decision/pattern/error/fix/preference/fact/lesson/todo), project_id, tags, limit. Always start here before using get_memories."
+        description = "Search memories by semantic similarity and keywords. Returns compact index entries (id, title, kind, date, score). Use get_memories to retrieve full details for specific IDs. The query accepts field operators mixed with free text, e.g. 'kind:error tag:auth after:2025-01-01 \"connection pool\"' (operators: kind, tag, project, status, author, after, before, importance, symbol, title; a quoted \"phrase\" must appear word for word, which suits exact error strings; prefix kind:, tag: or project: with - to exclude, and use -\"phrase\" to drop memories mentioning a phrase). Filters: kind (observation/decision/pattern/error/fix/preference/fact/lesson/todo), project_id, tags, limit. Always start here before using get_memories."
     )]
     async fn search(
         &self,
         Parameters(params): Parameters<SearchParams>,
     ) -> Result<CallToolResult, ErrorData> {
-        // Embed the query text
-        let embedding = self
-            .embedder
-            .embed(&params.query)
-            .await
-            .map_err(to_mcp_error)?;
-
-        // Over-fetch 3x to have enough candidates after filtering
-        let fetch_limit = params.limit * 3;
-
-        let results = self
-            .storage
-            .vector_search(&embedding, fetch_limit)
-            .await
-            .map_err(to_mcp_error)?;
-
-        // Filter by privacy, kind, project, tags
-        let mut filtered: Vec<(Memory, f32)> = results;
-        sharing::filter_search_results(&mut filtered, &self.user_id);
-        let filtered: Vec<(Memory, f32)> = filtered
-            .into_iter()
-            .filter(|(memory, _)| {
-                if let Some(ref kind) = params.kind {
-                    if memory.kind.to_string() != *kind {
-                        return false;
-                    }
-                }
-                if let Some(ref pid) = params.project_id {
-                    if memory.project_id.as_ref() != Some(pid) {
-                        return false;
-                    }
-                }
-                if !params.tags.is_empty() && !params.tags.iter().any(|t| memory.tags.contains(t)) {
-                    return false;
-                }
-                true
+        // Parse field operators (kind:, tag:, after:, ...) and merge explicit params
+        let kind = params
+            .kind
+            .as_deref()
+            .map(|k| k.parse::<MemoryKind>())
+            .transpose()
+            .map_err(|e: String| ErrorData::invalid_params(e, None))?;
+        let request = SearchRequest::parse(&params.query)
+            .map_err(to_mcp_error)?
+            .map_query(|q| {
+                q.with_kind(kind)
+                    .with_tags(params.tags.clone())
+                    .with_project(params.project_id.clone())
+                    .without_ids(params.exclude_ids.clone())
+                    .without_hashes(params.exclude_hashes.clone())
             })
-            .collect();
-
-        // Get relation counts for ranking
-        let memory_ids: Vec<Uuid> = filtered.iter().map(|(m, _)| m.id).collect();
-        let relation_counts = self
-            .storage
-            .count_relations(&memory_ids)
-            .await
-            .map_err(to_mcp_error)?;
-
-        let count_map: std::collections::HashMap<Uuid, usize> =
-            relation_counts.into_iter().collect();
+            .limit(params.limit)
+            .token_budget(params.token_budget)
+            .visible_to(self.user_id.as_str())
+            .demote_contradicted(self.config.retrieval.demote_contradicted)
+            .ranker(self.ranker.clone());
+
+        // Memories this retrieval session already showed are left out.
+        let session = params.retrieval_session.as_deref();
+        let shown = session
+            .map(|id| self.retrieval_sessions.with(id, |s| s.shown().clone()))
+            .unwrap_or_default();
+        let request = request.excluding(shown);
 
-        let contradiction_counts = self
-            .storage
-            .count_contradictions(&memory_ids)
+        let mut results = retrieval::search(self.storage.as_ref(), &self.embedder, &request)
             .await
             .map_err(to_mcp_error)?;
-
-        let contradiction_map: std::collections::HashMap<Uuid, usize> =
-            contradiction_counts.into_iter().collect();
-
-        // Build rank candidates with keyword scoring
-        let candidates: Vec<RankCandidate> = filtered
-            .into_iter()
-            .map(|(memory, vector_score)| {
-                let id = memory.id;
-                let relation_count = count_map.get(&id).copied().unwrap_or(0);
-                let contradiction_count = contradiction_map.get(&id).copied().unwrap_or(0);
-                let kw_score = ranking::keyword_score(&params.query, &memory);
-                RankCandidate {
-                    memory,
-                    vector_score,
-                    keyword_score: kw_score,
-                    relation_count,
-                    contradiction_count,
-                }
-            })
-            .collect();
-
-        // Rank and take top N
-        let ranked = ranking::rank(candidates, &RankingWeights::default());
-        let top: Vec<MemoryIndex> = ranked
-            .into_iter()
-            .take(params.limit)
-            .map(|r| MemoryIndex::from((&r.memory, r.score)))
-            .collect();
-
-        // Apply token budget if set
-        let top = match params.token_budget {
-            Some(budget) => ranking::budget_truncate(top, budget),
-            None => top,
-        };
+        if let Some(id) = session {
+            self.retrieval_sessions
+                .with(id, |s| s.boost(&mut results.ranked));
+        }
+        let top = results.index();
+        self.questions.record(
+            &Question::new(&request.query.text, "mcp", &self.user_id)
+                .with_project(request.query.project.clone())
+                .with_scores(top.iter().map(|r| r.score)),
+        );
+        if let Some(id) = session {
+            self.retrieval_sessions
+                .with(id, |s| s.record_shown(top.iter().map(|r| r.id)));
+        }
 
         let json = serde_json::to_string_pretty(&top)
             .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
@@ -557,21 +702,19 @@ impl ShabkaServer {
         &self,
         Parameters(params): Parameters<TimelineParams>,
     ) -> Result<CallToolResult, ErrorData> {
-        let query = TimelineQuery {
-            memory_id: params.memory_id.and_then(|s| Uuid::parse_str(&s).ok()),
-            start: params
-                .start
-                .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
-                .map(|dt| dt.with_timezone(&chrono::Utc)),
-            end: params
-                .end
-                .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
-                .map(|dt| dt.with_timezone(&chrono::Utc)),
-            session_id: params.session_id.and_then(|s| Uuid::parse_str(&s).ok()),
-            limit: params.limit,
-            project_id: params.project_id,
-            ..Default::default()
+        let parse_time = |s: String| {
+            chrono::DateTime::parse_from_rfc3339(&s)
+                .ok()
+                .map(|dt| dt.with_timezone(&chrono::Utc))
         };
+        let query = TimelineQuery::new(params.limit)
+            .with_memory(params.memory_id.and_then(|s| Uuid::parse_str(&s).ok()))
+            .with_range(
+                params.start.and_then(parse_time),
+                params.end.and_then(parse_time),
+            )
+            .with_session(params.session_id.and_then(|s| Uuid::parse_str(&s).ok()))
+            .with_project(params.project_id);
 
         let mut entries = self.storage.timeline(&query).await.map_err(to_mcp_error)?;
 
@@ -618,6 +761,14 @@ impl ShabkaServer {
         // Filter by privacy
         sharing::filter_memories(&mut memories, &self.user_id);
 
+        if let Some(id) = params.retrieval_session.as_deref() {
+            self.retrieval_sessions.with(id, |s| {
+                for memory in &memories {
+                    s.record_used(memory);
+                }
+            });
+        }
+
         let mut results = Vec::new();
         for memory in &memories {
             let relations = self
@@ -674,7 +825,10 @@ impl ShabkaServer {
         let mut memory = Memory::new(params.title, params.content, kind, self.user_id.clone())
             .with_tags(params.tags)
             .with_importance(params.importance)
-            .with_privacy(privacy);
+            .with_privacy(privacy)
+            .with_label(params.label);
+        self.config.labels.apply(&mut memory);
+        shabka_core::devices::stamp(&mut memory);
 
         if let Some(scope) = params.scope {
             if scope != "global" {
@@ -739,6 +893,13 @@ impl ShabkaServer {
                 existing_title,
                 similarity,
             } => {
+                // Saving the same fact again corroborates it.
+                let _ = trust::record_assertion(
+                    self.storage.as_ref(),
+                    existing_id,
+                    Assertion::new(self.user_id.clone(), "mcp"),
+                )
+                .await;
                 let response = serde_json::json!({
                     "action": "skipped",
                     "existing_id": existing_id.to_string(),
@@ -812,23 +973,9 @@ impl ShabkaServer {
                 similarity,
             } => {
                 // Update existing memory with LLM-merged content
-                let _ = self
-                    .storage
-                    .update_memory(
-                        existing_id,
-                        &UpdateMemoryInput {
-                            title: Some(merged_title.clone()),
-                            content: Some(merged_content.clone()),
-                            ..Default::default()
-                        },
-                    )
+                self.merge_into(existing_id, &merged_title, merged_content)
                     .await;
 
-                self.history.log(
-                    &MemoryEvent::new(existing_id, EventAction::Updated, self.user_id.clone())
-                        .with_title(&merged_title),
-                );
-
                 let response = serde_json::json!({
                     "action": "merged",
                     "existing_id": existing_id.to_string(),
@@ -963,6 +1110,7 @@ impl ShabkaServer {
             scope: None,
             related_to: Vec::new(),
             privacy: None,
+            label: None,
             project_id: params.project_id,
         };
 
@@ -979,8 +1127,6 @@ impl ShabkaServer {
         let id = Uuid::parse_str(&params.id)
             .map_err(|e| ErrorData::invalid_params(format!("invalid UUID: {e}"), None))?;
 
-        let needs_reembed = params.title.is_some() || params.content.is_some();
-
         // Fetch old memory for diff
         let old_memory = self.storage.get_memory(id).await.map_err(to_mcp_error)?;
 
@@ -994,7 +1140,7 @@ impl ShabkaServer {
 
         let privacy = params.privacy.and_then(|s| s.parse().ok());
 
-        let input = UpdateMemoryInput {
+        let mut input = UpdateMemoryInput {
             title: params.title,
             content: params.content,
             tags: params.tags,
@@ -1002,8 +1148,18 @@ impl ShabkaServer {
             status,
             kind: None,
             privacy,
+            label: params.label,
+            owner: None,
             verification: None,
+            verification_expires_at: None,
+            dispute: None,
+            accessed_at: None,
+            attachments: None,
+            code: None,
+            assertions: None,
+            endorsements: None,
         };
+        self.config.labels.constrain_update(&old_memory, &mut input);
 
         shabka_core::model::validate_update_input(&input).map_err(to_mcp_error)?;
 
@@ -1021,19 +1177,15 @@ impl ShabkaServer {
                 .with_changes(changes),
         );
 
-        // Re-embed if title or content changed
-        if needs_reembed {
-            let embedding = self
-                .embedder
-                .embed(&memory.embedding_text())
-                .await
-                .map_err(to_mcp_error)?;
-            // Re-save with the new embedding
-            self.storage
-                .save_memory(&memory, Some(&embedding))
-                .await
-                .map_err(to_mcp_error)?;
-        }
+        shabka_core::embedding::refresh_after_edit(
+            self.storage.as_ref(),
+            &self.embedder,
+            &self.config.embedding,
+            Some(&old_memory),
+            &memory,
+        )
+        .await
+        .map_err(to_mcp_error)?;
 
         let response = serde_json::json!({
             "id": memory.id.to_string(),
@@ -1054,19 +1206,14 @@ impl ShabkaServer {
         let id = Uuid::parse_str(&params.id)
             .map_err(|e| ErrorData::invalid_params(format!("invalid UUID: {e}"), None))?;
 
-        // Fetch title before deleting for audit trail
-        let title = self
-            .storage
-            .get_memory(id)
-            .await
-            .ok()
-            .map(|m| m.title.clone());
+        // Fetch the memory before deleting for the audit trail
+        let memory = self.storage.get_memory(id).await.ok();
 
         self.storage.delete_memory(id).await.map_err(to_mcp_error)?;
 
         let mut event = MemoryEvent::new(id, EventAction::Deleted, self.user_id.clone());
-        if let Some(t) = title {
-            event = event.with_title(t);
+        if let Some(memory) = &memory {
+            event = event.with_snapshot(memory);
         }
         self.history.log(&event);
 
@@ -1258,7 +1405,7 @@ impl ShabkaServer {
     }
 
     #[tool(
-        description = "Follow a chain of relations from a starting memory. BFS traversal for debugging narratives (fixes/caused_by), knowledge exploration (related), or version history (supersedes). Returns linked memories with relation types and depth."
+        description = "Follow a chain of relations from a starting memory. BFS traversal for debugging narratives (fixes/caused_by), knowledge exploration (related), or version history (supersedes). Returns linked memories with relation types and depth; links marked cycle lead back to a memory already listed."
     )]
     async fn follow_chain(
         &self,
@@ -1290,11 +1437,15 @@ impl ShabkaServer {
         let max_depth = params
             .max_depth
             .unwrap_or(self.config.graph.max_chain_depth);
+        let max_nodes = params
+            .max_nodes
+            .unwrap_or(self.config.graph.max_chain_nodes);
         let chain = graph::follow_chain(
             self.storage.as_ref(),
             start_id,
             &relation_types,
             Some(max_depth),
+            Some(max_nodes),
         )
         .await;
 
@@ -1305,7 +1456,7 @@ impl ShabkaServer {
         }
 
         // Fetch full memory details for each chain link
-        let chain_ids: Vec<Uuid> = chain.iter().map(|l| l.memory_id).collect();
+        let chain_ids: Vec<Uuid> = chain.links.iter().map(|l| l.memory_id).collect();
         let memories = self
             .storage
             .get_memories(&chain_ids)
@@ -1316,6 +1467,7 @@ impl ShabkaServer {
             memories.iter().map(|m| (m.id, m)).collect();
 
         let results: Vec<serde_json::Value> = chain
+            .links
             .iter()
             .filter_map(|link| {
                 memory_map.get(&link.memory_id).map(|memory| {
@@ -1328,6 +1480,7 @@ impl ShabkaServer {
                         "from_id": link.from_id.to_string(),
                         "strength": link.strength,
                         "depth": link.depth,
+                        "cycle": link.cycle,
                     })
                 })
             })
@@ -1336,6 +1489,59 @@ impl ShabkaServer {
         let json = serde_json::to_string_pretty(&results)
             .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
 
+        let mut content = vec![Content::text(json)];
+        if chain.truncated {
+            content.push(Content::text(format!(
+                "Stopped after {max_nodes} memories; pass a higher max_nodes to see more."
+            )));
+        }
+        Ok(CallToolResult::success(content))
+    }
+
+    #[tool(
+        description = "List the memories most similar to an existing memory, with similarity scores and any relation already linking them. Use this to spot near-duplicates before saving or consolidating, or to find related prior work."
+    )]
+    async fn similar_memories(
+        &self,
+        Parameters(params): Parameters<SimilarMemoriesParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let memory_id = Uuid::parse_str(&params.memory_id)
+            .map_err(|e| ErrorData::invalid_params(format!("invalid UUID: {e}"), None))?;
+
+        let memory = self
+            .storage
+            .get_memory(memory_id)
+            .await
+            .map_err(to_mcp_error)?;
+        let embedding = self
+            .embedder
+            .embed(&memory.embedding_text())
+            .await
+            .map_err(to_mcp_error)?;
+
+        let similar =
+            graph::find_similar(self.storage.as_ref(), memory_id, &embedding, params.limit)
+                .await
+                .map_err(to_mcp_error)?;
+
+        let results: Vec<serde_json::Value> = similar
+            .iter()
+            .filter(|s| sharing::is_visible(s.memory.privacy, &s.memory.created_by, &self.user_id))
+            .map(|s| {
+                serde_json::json!({
+                    "id": s.memory.id.to_string(),
+                    "title": s.memory.title,
+                    "kind": s.memory.kind.to_string(),
+                    "summary": s.memory.summary,
+                    "score": s.score,
+                    "relation": s.relation.map(|r| r.to_string()),
+                })
+            })
+            .collect();
+
+        let json = serde_json::to_string_pretty(&results)
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+
         Ok(CallToolResult::success(vec![Content::text(json)]))
     }
 
@@ -1361,6 +1567,10 @@ impl ShabkaServer {
             config.min_age_days = age;
         }
 
+        let mut run = MaintenanceRun::start(
+            MaintenanceOp::Consolidate,
+            serde_json::to_value(&config).unwrap_or_default(),
+        );
         let result = shabka_core::consolidate::consolidate(
             self.storage.as_ref(),
             self.embedder.as_ref(),
@@ -1370,8 +1580,18 @@ impl ShabkaServer {
             &self.history,
             params.dry_run,
         )
-        .await
-        .map_err(to_mcp_error)?;
+        .await;
+        if !params.dry_run {
+            match &result {
+                Ok(result) => result.record_counts(&mut run),
+                Err(e) => run.error(e.to_string()),
+            }
+            run.finish();
+            if let Err(e) = self.storage.record_maintenance_run(&run).await {
+                tracing::warn!("failed to record consolidation run: {e}");
+            }
+        }
+        let result = result.map_err(to_mcp_error)?;
 
         let response = serde_json::json!({
             "clusters_found": result.clusters_found,
@@ -1441,11 +1661,12 @@ impl ShabkaServer {
 
         let assess_config = AssessConfig {
             stale_days: self.config.graph.stale_days,
+            min_confidence: self.config.capture.min_confidence,
             ..AssessConfig::default()
         };
 
         // Analyze each memory
-        let results: Vec<assess::AssessmentResult> = memories
+        let mut results: Vec<assess::AssessmentResult> = memories
             .iter()
             .filter_map(|m| {
                 let rel_count = count_map.get(&m.id).copied().unwrap_or(0);
@@ -1462,12 +1683,14 @@ impl ShabkaServer {
             })
             .collect();
 
+        assess::flag_retention(&mut results, &memories, &self.config.retention);
+
         let score = assess::quality_score(&results, total);
         let counts = IssueCounts::from_results(&results);
 
         // Top 10 issues
         let mut sorted = results.clone();
-        sorted.sort_by(|a, b| b.issues.len().cmp(&a.issues.len()));
+        sorted.sort_by_key(|r| std::cmp::Reverse(r.issues.len()));
         let top_issues: Vec<serde_json::Value> = sorted
             .iter()
             .take(10)
@@ -1497,7 +1720,7 @@ impl ShabkaServer {
 
     #[tool(
         name = "verify_memory",
-        description = "Set verification status on a memory (verified, disputed, outdated, unverified). Verified memories rank higher in search results."
+        description = "Set verification status on a memory (verified, disputed, outdated, unverified). Verified memories rank higher in search results. When disputing, give a reason and optionally the ID of a counter-evidence memory."
     )]
     async fn verify_memory(
         &self,
@@ -1511,11 +1734,49 @@ impl ShabkaServer {
             .parse()
             .map_err(|e: String| ErrorData::invalid_params(e, None))?;
 
+        if verification != VerificationStatus::Disputed
+            && (params.reason.is_some() || params.evidence_id.is_some())
+        {
+            return Err(ErrorData::invalid_params(
+                "reason and evidence_id only apply to status disputed",
+                None,
+            ));
+        }
+        let evidence_id = params
+            .evidence_id
+            .as_deref()
+            .map(Uuid::parse_str)
+            .transpose()
+            .map_err(|e| ErrorData::invalid_params(format!("invalid evidence ID: {e}"), None))?;
+        if evidence_id == Some(id) {
+            return Err(ErrorData::invalid_params(
+                "a memory can't be its own counter-evidence",
+                None,
+            ));
+        }
+        if let Some(evidence_id) = evidence_id {
+            self.storage
+                .get_memory(evidence_id)
+                .await
+                .map_err(to_mcp_error)?;
+        }
+
         // Fetch old state for audit trail before updating
         let old_memory = self.storage.get_memory(id).await.map_err(to_mcp_error)?;
 
         let input = UpdateMemoryInput {
             verification: Some(verification),
+            verification_expires_at: trust::verification_expiry(
+                verification,
+                old_memory.kind,
+                &self.config.retention,
+                chrono::Utc::now(),
+            ),
+            dispute: (verification == VerificationStatus::Disputed).then(|| {
+                Dispute::new(self.user_id.clone())
+                    .with_reason(params.reason)
+                    .with_evidence(evidence_id)
+            }),
             ..Default::default()
         };
 
@@ -1525,14 +1786,37 @@ impl ShabkaServer {
             .await
             .map_err(to_mcp_error)?;
 
+        if let Some(evidence_id) = evidence_id {
+            self.storage
+                .add_relation(&MemoryRelation {
+                    source_id: evidence_id,
+                    target_id: id,
+                    relation_type: RelationType::Contradicts,
+                    strength: 1.0,
+                })
+                .await
+                .map_err(to_mcp_error)?;
+        }
+
+        let mut changes = vec![shabka_core::history::FieldChange {
+            field: "verification".to_string(),
+            old_value: old_memory.verification.to_string(),
+            new_value: verification.to_string(),
+        }];
+        if let Some(reason) = memory.dispute.as_ref().and_then(|d| d.reason.clone()) {
+            changes.push(shabka_core::history::FieldChange {
+                field: "dispute_reason".to_string(),
+                old_value: old_memory
+                    .dispute
+                    .and_then(|d| d.reason)
+                    .unwrap_or_default(),
+                new_value: reason,
+            });
+        }
         self.history.log(
             &MemoryEvent::new(id, EventAction::Updated, self.user_id.clone())
                 .with_title(&memory.title)
-                .with_changes(vec![shabka_core::history::FieldChange {
-                    field: "verification".to_string(),
-                    old_value: old_memory.verification.to_string(),
-                    new_value: verification.to_string(),
-                }]),
+                .with_changes(changes),
         );
 
         Ok(CallToolResult::success(vec![Content::text(format!(
@@ -1542,29 +1826,69 @@ impl ShabkaServer {
     }
 
     #[tool(
-        name = "get_context",
-        description = "Get a token-budgeted context pack of relevant memories, formatted as markdown ready for injection into prompts. Supports filtering by query, project, kind, and tags. Use this when you need rich context rather than individual search results."
+        name = "verify_citations",
+        description = "Check the memory citations in an answer before relying on it. Each cited ID must exist, be active, and support the sentence citing it (shared wording or semantic similarity). Returns one entry per citation with a verdict: supported, unsupported, inactive, missing, or ambiguous."
     )]
-    async fn get_context(
+    async fn verify_citations(
         &self,
-        Parameters(params): Parameters<GetContextParams>,
+        Parameters(params): Parameters<VerifyCitationsParams>,
     ) -> Result<CallToolResult, ErrorData> {
-        let query = if params.query.is_empty() {
-            "*"
-        } else {
-            &params.query
-        };
+        let embedder = (!params.lexical_only).then_some(self.embedder.as_ref());
+        let checks = citations::verify_citations(
+            self.storage.as_ref(),
+            embedder,
+            &self.user_id,
+            &params.answer,
+        )
+        .await
+        .map_err(to_mcp_error)?;
 
-        let embedding = self.embedder.embed(query).await.map_err(to_mcp_error)?;
+        let json = serde_json::to_string_pretty(&checks)
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
 
-        let mut results = self
-            .storage
-            .vector_search(&embedding, 50)
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
+    #[tool(
+        name = "inbox",
+        description = "List everything waiting on the user: auto-captured memories awaiting review, quarantined memories, likely duplicates, contradictions, expired or soon-expiring verifications, captures parked by a quota, and unreadable sync records. Returns JSON with a total, counts per kind, and items (kind, memory_ids, title, detail, since), newest first."
+    )]
+    async fn inbox(
+        &self,
+        Parameters(params): Parameters<InboxParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let kinds = params
+            .kinds
+            .iter()
+            .map(|k| k.parse::<inbox::InboxKind>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ErrorData::invalid_params(e, None))?;
+        let options = inbox::InboxOptions::from_config(&self.config);
+        let mut items = inbox::collect(self.storage.as_ref(), &self.user_id, &options)
             .await
             .map_err(to_mcp_error)?;
+        items.retain_kinds(&kinds);
+
+        let json = serde_json::to_string_pretty(&items)
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
 
-        sharing::filter_search_results(&mut results, &self.user_id);
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
 
+    #[tool(
+        name = "get_context",
+        description = "Get a token-budgeted context pack of relevant memories, formatted as markdown ready for injection into prompts. Supports filtering by query, project, kind, and tags. Use this when you need rich context rather than individual search results."
+    )]
+    async fn get_context(
+        &self,
+        Parameters(params): Parameters<GetContextParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let kind = params
+            .kind
+            .as_deref()
+            .map(|k| k.parse::<MemoryKind>())
+            .transpose()
+            .map_err(|e: String| ErrorData::invalid_params(e, None))?;
         let tag_filter: Vec<String> = params
             .tags
             .map(|t| {
@@ -1574,61 +1898,32 @@ impl ShabkaServer {
                     .collect()
             })
             .unwrap_or_default();
-
-        let filtered: Vec<(Memory, f32)> = results
-            .into_iter()
-            .filter(|(memory, _)| {
-                if let Some(ref kind) = params.kind {
-                    if memory.kind.to_string() != *kind {
-                        return false;
-                    }
-                }
-                if let Some(ref pid) = params.project_id {
-                    if memory.project_id.as_ref() != Some(pid) {
-                        return false;
-                    }
-                }
-                if !tag_filter.is_empty() && !tag_filter.iter().any(|t| memory.tags.contains(t)) {
-                    return false;
-                }
-                true
+        let request = SearchRequest::parse(&params.query)
+            .map_err(to_mcp_error)?
+            .map_query(|q| {
+                q.with_kind(kind)
+                    .with_tags(tag_filter)
+                    .with_project(params.project_id.clone())
+                    .without_ids(params.exclude_ids)
+                    .without_hashes(params.exclude_hashes)
             })
-            .collect();
-
-        let memory_ids: Vec<Uuid> = filtered.iter().map(|(m, _)| m.id).collect();
-        let relation_counts = self
-            .storage
-            .count_relations(&memory_ids)
-            .await
-            .map_err(to_mcp_error)?;
-        let count_map: std::collections::HashMap<Uuid, usize> =
-            relation_counts.into_iter().collect();
-        let contradiction_counts = self
-            .storage
-            .count_contradictions(&memory_ids)
+            .fetch_limit(CONTEXT_CANDIDATES)
+            .filter(PackFilter {
+                verified_only: params.verified_only,
+                exclude_suspicious: params.exclude_suspicious,
+                min_confidence: self.config.capture.min_confidence,
+            })
+            .visible_to(self.user_id.as_str())
+            .demote_contradicted(self.config.retrieval.demote_contradicted)
+            .ranker(self.ranker.clone());
+        let results = retrieval::search(self.storage.as_ref(), &self.embedder, &request)
             .await
             .map_err(to_mcp_error)?;
-        let contradiction_map: std::collections::HashMap<Uuid, usize> =
-            contradiction_counts.into_iter().collect();
-
-        let candidates: Vec<RankCandidate> = filtered
-            .into_iter()
-            .map(|(memory, vector_score)| {
-                let kw_score = ranking::keyword_score(query, &memory);
-                RankCandidate {
-                    relation_count: count_map.get(&memory.id).copied().unwrap_or(0),
-                    contradiction_count: contradiction_map.get(&memory.id).copied().unwrap_or(0),
-                    keyword_score: kw_score,
-                    memory,
-                    vector_score,
-                }
-            })
-            .collect();
 
-        let ranked = ranking::rank(candidates, &RankingWeights::default());
-        let memories: Vec<Memory> = ranked.into_iter().map(|r| r.memory).collect();
+        let memories: Vec<Memory> = results.ranked.into_iter().map(|r| r.memory).collect();
 
-        let pack = build_context_pack(memories, params.token_budget, params.project_id);
+        let pack = build_context_pack(memories, params.token_budget, request.query.project)
+            .with_trust(&results.contradiction_counts);
 
         if pack.memories.is_empty() {
             return Ok(CallToolResult::success(vec![Content::text(
@@ -1684,6 +1979,8 @@ impl ShabkaServer {
             .with_importance(input.importance)
             .with_privacy(privacy)
             .with_session(session_id);
+            self.config.labels.apply(&mut memory);
+            shabka_core::devices::stamp(&mut memory);
 
             if let Some(ref pid) = params.project_id {
                 memory = memory.with_project(pid.clone());
@@ -1787,21 +2084,8 @@ impl ShabkaServer {
                     merged_title,
                     ..
                 } => {
-                    let _ = self
-                        .storage
-                        .update_memory(
-                            existing_id,
-                            &UpdateMemoryInput {
-                                title: Some(merged_title.clone()),
-                                content: Some(merged_content),
-                                ..Default::default()
-                            },
-                        )
+                    self.merge_into(existing_id, &merged_title, merged_content)
                         .await;
-                    self.history.log(
-                        &MemoryEvent::new(existing_id, EventAction::Updated, self.user_id.clone())
-                            .with_title(&merged_title),
-                    );
                     saved += 1;
                 }
                 DedupDecision::Contradict {
@@ -1868,8 +2152,86 @@ impl ShabkaServer {
     }
 }
 
-#[tool_handler]
+/// `ok`, or `error: ...` with the error's first line, and whether the
+/// call succeeded.
+fn tool_outcome(result: &Result<CallToolResult, ErrorData>) -> (String, bool) {
+    let message = match result {
+        Ok(result) if result.is_error != Some(true) => return ("ok".to_string(), true),
+        Ok(result) => result
+            .content
+            .iter()
+            .find_map(|c| c.as_text().map(|t| t.text.clone()))
+            .unwrap_or_default(),
+        Err(e) => e.message.to_string(),
+    };
+    let first_line = message.lines().next().unwrap_or_default();
+    (format!("error: {first_line}"), false)
+}
+
 impl ServerHandler for ShabkaServer {
+    /// Dispatch to the tool router, recording the call in the audit log.
+    /// Over HTTP, the API token `shabka-web` authenticated is the call's
+    /// token in the audit and history logs, and a tenant's token is served
+    /// from that tenant's store.
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let api_token = context
+            .extensions
+            .get::<axum::http::request::Parts>()
+            .and_then(|parts| parts.extensions.get::<ApiToken>());
+        let token = api_token.map(|token| token.name.clone());
+        let tenant = api_token.and_then(|token| token.tenant.clone());
+        let params = request
+            .arguments
+            .as_ref()
+            .map(|args| serde_json::to_string(args).unwrap_or_default())
+            .unwrap_or_default();
+        let entry = AuditEntry::new("mcp", request.name.to_string())
+            .with_token(token.clone())
+            .with_tenant(tenant.clone())
+            .with_params(&params);
+
+        let tenant_server;
+        let server = match &tenant {
+            Some(tenant) => {
+                tenant_server = self.tenant_server(tenant)?;
+                &*tenant_server
+            }
+            None => self,
+        };
+        let started = std::time::Instant::now();
+        let call = server
+            .tool_router
+            .call(ToolCallContext::new(server, request, context));
+        let result = match token {
+            Some(name) => history::with_token(name, call).await,
+            None => call.await,
+        };
+        let (outcome, success) = tool_outcome(&result);
+        self.audit
+            .log(&entry.finish(outcome, success, started.elapsed()));
+        result
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, ErrorData> {
+        Ok(ListToolsResult {
+            tools: self.tool_router.list_all(),
+            meta: None,
+            next_cursor: None,
+        })
+    }
+
+    fn get_tool(&self, name: &str) -> Option<Tool> {
+        self.tool_router.get(name).cloned()
+    }
+
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::V_2024_11_05,
@@ -1887,6 +2249,8 @@ impl ServerHandler for ShabkaServer {
                  Quality: assess (scorecard with issue counts and overall score).\n\n\
                  Trust: verify_memory (set verified/disputed/outdated status — verified memories rank higher).\n\n\
                  Context: get_context (token-budgeted context pack of relevant memories for prompt injection).\n\n\
+                 Citations: verify_citations (check that memory IDs cited in an answer exist, are active and back their claims).\n\n\
+                 Inbox: inbox (memories to review, duplicates, contradictions and lapsing verifications waiting on the user).\n\n\
                  Session capture: save_session_summary (batch-save multiple memories at end of conversation).\n\n\
                  Always start with search, then drill down as needed."
                     .to_string(),
@@ -1912,6 +2276,21 @@ mod tests {
         assert_eq!(error_type, "not_found");
     }
 
+    #[test]
+    fn test_tool_outcome() {
+        let ok = Ok(CallToolResult::success(vec![Content::text("done")]));
+        assert_eq!(tool_outcome(&ok), ("ok".to_string(), true));
+        let failed = Ok(CallToolResult::error(vec![Content::text(
+            "no such memory\nmore",
+        )]));
+        assert_eq!(
+            tool_outcome(&failed),
+            ("error: no such memory".to_string(), false)
+        );
+        let err = Err(to_mcp_error(ShabkaError::InvalidInput("bad kind".into())));
+        assert!(!tool_outcome(&err).1);
+    }
+
     #[test]
     fn test_invalid_input_maps_to_invalid_params() {
         let err = ShabkaError::InvalidInput("title cannot be empty".into());
@@ -1949,6 +2328,17 @@ mod tests {
         assert_eq!(error_type, "storage_error");
     }
 
+    #[test]
+    fn test_hint_in_message_and_data() {
+        let err = ShabkaError::StorageUnavailable("cannot reach HelixDB".into());
+        let data = to_mcp_error(err);
+        assert_eq!(data.code.0, -32603); // INTERNAL_ERROR
+        assert!(data.message.contains("hint:"));
+        let data = data.data.unwrap();
+        assert_eq!(data["error_type"], "storage_unavailable");
+        assert!(data["hint"].as_str().unwrap().contains("shabka doctor"));
+    }
+
     #[test]
     fn test_embedding_maps_to_internal_error() {
         let err = ShabkaError::Embedding("embed failed".into());
@@ -2060,6 +2450,7 @@ mod tests {
             scope: None,
             related_to: vec![],
             privacy: None,
+            label: None,
             project_id: None,
         };
         let result = server.save_memory(Parameters(params)).await.unwrap();
@@ -2081,6 +2472,7 @@ mod tests {
             scope: None,
             related_to: vec![],
             privacy: None,
+            label: None,
             project_id: None,
         };
         let result = server.save_memory(Parameters(params)).await;
@@ -2105,6 +2497,7 @@ mod tests {
             scope: None,
             related_to: vec![],
             privacy: None,
+            label: None,
             project_id: None,
         };
         let result = server.save_memory(Parameters(params)).await;
@@ -2118,6 +2511,7 @@ mod tests {
 
         let params = GetMemoriesParams {
             ids: vec![id.clone()],
+            retrieval_session: None,
         };
         let result = server.get_memories(Parameters(params)).await;
         assert!(result.is_ok(), "get_memories failed: {result:?}");
@@ -2136,7 +2530,10 @@ mod tests {
     async fn test_get_memories_not_found() {
         let server = test_server();
         let fake_id = Uuid::new_v4().to_string();
-        let params = GetMemoriesParams { ids: vec![fake_id] };
+        let params = GetMemoriesParams {
+            ids: vec![fake_id],
+            retrieval_session: None,
+        };
         let result = server.get_memories(Parameters(params)).await;
         // Handler returns ok with empty results for non-existent IDs
         assert!(
@@ -2165,6 +2562,7 @@ mod tests {
             importance: None,
             status: None,
             privacy: None,
+            label: None,
         };
         let result = server.update_memory(Parameters(params)).await;
         assert!(result.is_ok(), "update_memory failed: {result:?}");
@@ -2172,6 +2570,7 @@ mod tests {
         // Verify the update took effect
         let get_params = GetMemoriesParams {
             ids: vec![id.clone()],
+            retrieval_session: None,
         };
         let get_result = server.get_memories(Parameters(get_params)).await.unwrap();
         let text = extract_text(&get_result);
@@ -2194,6 +2593,7 @@ mod tests {
         // Verify it's gone
         let get_params = GetMemoriesParams {
             ids: vec![id.clone()],
+            retrieval_session: None,
         };
         let get_result = server.get_memories(Parameters(get_params)).await.unwrap();
         let text = extract_text(&get_result);
@@ -2211,6 +2611,9 @@ mod tests {
             tags: vec![],
             limit: 10,
             token_budget: None,
+            exclude_ids: vec![],
+            exclude_hashes: vec![],
+            retrieval_session: None,
         };
         let result = server.search(Parameters(params)).await;
         assert!(
@@ -2231,6 +2634,9 @@ mod tests {
             tags: vec![],
             limit: 10,
             token_budget: None,
+            exclude_ids: vec![],
+            exclude_hashes: vec![],
+            retrieval_session: None,
         };
         let result = server.search(Parameters(params)).await;
         assert!(result.is_ok(), "search failed: {result:?}");
@@ -2240,6 +2646,97 @@ mod tests {
         assert!(!json.is_empty(), "search should return at least one result");
     }
 
+    #[tokio::test]
+    async fn test_search_retrieval_session_skips_shown() {
+        let server = test_server();
+        save_test_memory(&server, "session-gamma one").await;
+        save_test_memory(&server, "session-gamma two").await;
+
+        let search = || SearchParams {
+            query: "session-gamma".to_string(),
+            kind: None,
+            project_id: None,
+            tags: vec![],
+            limit: 1,
+            token_budget: None,
+            exclude_ids: vec![],
+            exclude_hashes: vec![],
+            retrieval_session: Some("conversation-1".to_string()),
+        };
+        let ids = |result: CallToolResult| -> Vec<String> {
+            let json: Vec<serde_json::Value> = serde_json::from_str(extract_text(&result)).unwrap();
+            json.iter()
+                .map(|r| r["id"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let first = ids(server.search(Parameters(search())).await.unwrap());
+        let second = ids(server.search(Parameters(search())).await.unwrap());
+        assert_eq!(first.len(), 1);
+        assert_eq!(second.len(), 1);
+        assert_ne!(first, second, "follow-up search should not repeat results");
+        let third = ids(server.search(Parameters(search())).await.unwrap());
+        assert!(third.is_empty(), "both memories were already shown");
+    }
+
+    #[tokio::test]
+    async fn test_search_field_operators() {
+        let server = test_server();
+        let _id = save_test_memory(&server, "operator-beta").await;
+
+        let search = |query: &str| SearchParams {
+            query: query.to_string(),
+            kind: None,
+            project_id: None,
+            tags: vec![],
+            limit: 10,
+            token_budget: None,
+            exclude_ids: vec![],
+            exclude_hashes: vec![],
+            retrieval_session: None,
+        };
+
+        let result = server
+            .search(Parameters(search("kind:observation tag:test")))
+            .await
+            .unwrap();
+        let json: Vec<serde_json::Value> = serde_json::from_str(extract_text(&result)).unwrap();
+        assert_eq!(json.len(), 1, "filter-only query should list matches");
+
+        let result = server
+            .search(Parameters(search("kind:error operator-beta")))
+            .await
+            .unwrap();
+        let json: Vec<serde_json::Value> = serde_json::from_str(extract_text(&result)).unwrap();
+        assert!(json.is_empty(), "kind operator should exclude observations");
+
+        let err = server
+            .search(Parameters(search("after:someday")))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_similar_memories() {
+        let server = test_server();
+        let id = save_test_memory(&server, "similar-source").await;
+        let _other = save_test_memory(&server, "similar-neighbour").await;
+
+        let params = SimilarMemoriesParams {
+            memory_id: id.clone(),
+            limit: 10,
+        };
+        let result = server.similar_memories(Parameters(params)).await.unwrap();
+        let json: Vec<serde_json::Value> = serde_json::from_str(extract_text(&result)).unwrap();
+        assert!(!json.is_empty());
+        assert!(
+            json.iter().all(|r| r["id"] != id.as_str()),
+            "source excluded"
+        );
+        assert!(json[0]["score"].is_number());
+    }
+
     #[tokio::test]
     async fn test_timeline() {
         let server = test_server();
@@ -2294,6 +2791,8 @@ mod tests {
         let params = VerifyMemoryParams {
             id: id.clone(),
             status: "verified".to_string(),
+            reason: None,
+            evidence_id: None,
         };
         let result = server.verify_memory(Parameters(params)).await;
         assert!(result.is_ok(), "verify_memory failed: {result:?}");
@@ -2305,6 +2804,44 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_verify_memory_dispute_with_evidence() {
+        let server = test_server();
+        let id = save_test_memory(&server, "dispute-target").await;
+        let evidence = save_test_memory(&server, "dispute-evidence").await;
+
+        let params = VerifyMemoryParams {
+            id: id.clone(),
+            status: "disputed".to_string(),
+            reason: Some("Benchmarks show the opposite".to_string()),
+            evidence_id: Some(evidence.clone()),
+        };
+        let result = server.verify_memory(Parameters(params)).await;
+        assert!(result.is_ok(), "verify_memory failed: {result:?}");
+
+        let id = Uuid::parse_str(&id).unwrap();
+        let memory = server.storage.get_memory(id).await.unwrap();
+        let dispute = memory.dispute.expect("dispute should be recorded");
+        assert_eq!(
+            dispute.reason.as_deref(),
+            Some("Benchmarks show the opposite")
+        );
+        assert_eq!(dispute.evidence_id.map(|e| e.to_string()), Some(evidence));
+        let relations = server.storage.get_relations(id).await.unwrap();
+        assert!(relations
+            .iter()
+            .any(|r| r.relation_type == RelationType::Contradicts && r.target_id == id));
+
+        // A reason without disputing is rejected.
+        let params = VerifyMemoryParams {
+            id: id.to_string(),
+            status: "verified".to_string(),
+            reason: Some("looks fine".to_string()),
+            evidence_id: None,
+        };
+        assert!(server.verify_memory(Parameters(params)).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_assess() {
         let server = test_server();
@@ -2323,6 +2860,56 @@ mod tests {
         assert!(json["score"].is_number(), "should have a score");
     }
 
+    #[tokio::test]
+    async fn test_verify_citations() {
+        let server = test_server();
+        let id = save_test_memory(&server, "citation-delta").await;
+
+        let params = VerifyCitationsParams {
+            answer: format!(
+                "Test memory citation-delta [{id}]. Made up [{}].",
+                Uuid::now_v7()
+            ),
+            lexical_only: true,
+        };
+        let result = server.verify_citations(Parameters(params)).await.unwrap();
+        let json: Vec<serde_json::Value> = serde_json::from_str(extract_text(&result)).unwrap();
+        assert_eq!(json.len(), 2);
+        assert_eq!(json[0]["verdict"], "supported");
+        assert_eq!(json[1]["verdict"], "missing");
+    }
+
+    #[tokio::test]
+    async fn test_inbox_lists_pending_review() {
+        let server = test_server();
+        let id = save_test_memory(&server, "inbox-review").await;
+        server
+            .storage
+            .update_memory(
+                Uuid::parse_str(&id).unwrap(),
+                &UpdateMemoryInput {
+                    status: Some(MemoryStatus::Pending),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let params = InboxParams {
+            kinds: vec!["review".to_string()],
+        };
+        let result = server.inbox(Parameters(params)).await.unwrap();
+        let json: serde_json::Value = serde_json::from_str(extract_text(&result)).unwrap();
+        assert_eq!(json["total"], 1);
+        assert_eq!(json["items"][0]["kind"], "review");
+        assert_eq!(json["items"][0]["memory_ids"][0], id);
+
+        let bad = InboxParams {
+            kinds: vec!["nope".to_string()],
+        };
+        assert!(server.inbox(Parameters(bad)).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_get_context() {
         let server = test_server();
@@ -2334,6 +2921,10 @@ mod tests {
             kind: None,
             tags: None,
             token_budget: 2000,
+            verified_only: false,
+            exclude_suspicious: false,
+            exclude_ids: vec![],
+            exclude_hashes: vec![],
         };
         let result = server.get_context(Parameters(params)).await;
         assert!(result.is_ok(), "get_context failed: {result:?}");
@@ -2408,6 +2999,7 @@ mod tests {
             memory_id: id1.clone(),
             relation_types: vec![],
             max_depth: Some(3),
+            max_nodes: None,
         };
         let result = server.follow_chain(Parameters(params)).await;
         assert!(result.is_ok(), "follow_chain failed: {result:?}");
@@ -2479,6 +3071,53 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_consolidate_with_mock_llm() {
+        use shabka_core::testing::{MockEmbedding, MockLlm};
+
+        let mut config = ShabkaConfig::default_config();
+        config.history.enabled = false;
+        let llm = MockLlm::new().fallback(
+            r#"{"title":"Pool exhaustion","content":"Merged","kind":"error","tags":["db"]}"#,
+        );
+        let services = Services::with_providers(
+            config,
+            Storage::Sqlite(SqliteStorage::open_in_memory().unwrap()),
+            EmbeddingService::mock(MockEmbedding::new(128)),
+            Some(LlmService::mock(llm.clone())),
+        );
+        let server = ShabkaServer::from_services(&services).unwrap();
+        for _ in 0..3 {
+            let memory = Memory::new(
+                "Pool exhaustion".into(),
+                "The pool runs dry under load".into(),
+                MemoryKind::Error,
+                "test-user".into(),
+            );
+            let embedding = server
+                .embedder
+                .embed(&memory.embedding_text())
+                .await
+                .unwrap();
+            server
+                .storage
+                .save_memory(&memory, Some(&embedding))
+                .await
+                .unwrap();
+        }
+
+        let params = ConsolidateParams {
+            dry_run: false,
+            min_cluster_size: Some(2),
+            min_age_days: Some(0),
+        };
+        let result = server.consolidate(Parameters(params)).await.unwrap();
+        let json: serde_json::Value = serde_json::from_str(extract_text(&result)).unwrap();
+        assert_eq!(json["clusters_consolidated"], 1);
+        assert_eq!(json["memories_superseded"], 3);
+        assert_eq!(llm.calls().len(), 1);
+    }
+
     #[tokio::test]
     async fn test_remember_tool_creates_procedure() {
         let server = test_server();
@@ -2498,6 +3137,7 @@ mod tests {
         let id = json["id"].as_str().unwrap().to_string();
         let get_params = GetMemoriesParams {
             ids: vec![id.clone()],
+            retrieval_session: None,
         };
         let get_result = server.get_memories(Parameters(get_params)).await.unwrap();
         let memories: Vec<serde_json::Value> =
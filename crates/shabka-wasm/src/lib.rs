@@ -0,0 +1,205 @@
+//! Shabka's pure logic for JavaScript, built with `wasm-pack`.
+//!
+//! Exposes the same ranking, trust, scrubbing and context-pack code the
+//! server runs, so the dashboard can re-rank or preview on the client and
+//! other JS tools score memories exactly as Shabka does. Memories cross the
+//! boundary as JSON in the shape `/api/v1/memories/{id}` returns; results
+//! come back as JSON strings.
+//!
+//! ```js
+//! import init, { rank, contextPack } from "./pkg/shabka_wasm.js";
+//! await init();
+//! const ranked = JSON.parse(rank("pool exhaustion", JSON.stringify(candidates)));
+//! ```
+
+use serde::{Deserialize, Serialize};
+use shabka_core::context_pack::{build_context_pack, format_context_pack};
+use shabka_core::model::Memory;
+use shabka_core::ranking::{self, RankCandidate, RankingWeights, ScoreBreakdown};
+use shabka_core::scrub::{self, ScrubConfig};
+use shabka_core::{tokens, trust};
+use uuid::Uuid;
+use wasm_bindgen::prelude::*;
+
+/// A memory to rank, with the scores only the server knows.
+#[derive(Debug, Deserialize)]
+struct Candidate {
+    memory: Memory,
+    /// Similarity to the query embedding, 0 to 1.
+    #[serde(default)]
+    vector_score: f32,
+    #[serde(default)]
+    relation_count: usize,
+    #[serde(default)]
+    contradiction_count: usize,
+    /// Stored PageRank, 0 to 1.
+    #[serde(default)]
+    centrality: f32,
+}
+
+#[derive(Debug, Serialize)]
+struct Ranked {
+    id: Uuid,
+    score: f32,
+    breakdown: ScoreBreakdown,
+}
+
+fn parse<'a, T: Deserialize<'a>>(json: &'a str, what: &str) -> Result<T, String> {
+    serde_json::from_str(json).map_err(|e| format!("invalid {what}: {e}"))
+}
+
+fn rank_json(query: &str, candidates: &str, weights: Option<&str>) -> Result<String, String> {
+    let candidates: Vec<Candidate> = parse(candidates, "candidates")?;
+    let weights: RankingWeights = match weights {
+        Some(json) => parse(json, "weights")?,
+        None => RankingWeights::default(),
+    };
+    let candidates = candidates
+        .into_iter()
+        .map(|c| RankCandidate {
+            keyword_score: ranking::keyword_score(query, &c.memory),
+            memory: c.memory,
+            vector_score: c.vector_score,
+            relation_count: c.relation_count,
+            contradiction_count: c.contradiction_count,
+            centrality: c.centrality,
+        })
+        .collect();
+    let ranked: Vec<Ranked> = ranking::rank(candidates, &weights)
+        .into_iter()
+        .map(|r| Ranked {
+            id: r.memory.id,
+            score: r.score,
+            breakdown: r.breakdown,
+        })
+        .collect();
+    serde_json::to_string(&ranked).map_err(|e| e.to_string())
+}
+
+fn trust_json(memory: &str, contradiction_count: usize) -> Result<f32, String> {
+    let memory: Memory = parse(memory, "memory")?;
+    Ok(trust::trust_score(&memory, contradiction_count))
+}
+
+fn scrub_config(config: Option<&str>) -> Result<ScrubConfig, String> {
+    match config {
+        Some(json) => parse(json, "scrub config"),
+        None => Ok(ScrubConfig::default()),
+    }
+}
+
+fn context_pack_json(
+    memories: &str,
+    token_budget: usize,
+    project_id: Option<String>,
+) -> Result<String, String> {
+    let memories: Vec<Memory> = parse(memories, "memories")?;
+    Ok(format_context_pack(&build_context_pack(
+        memories,
+        token_budget,
+        project_id,
+    )))
+}
+
+/// Rank `candidates` (a JSON array of `{memory, vector_score,
+/// relation_count, contradiction_count, centrality}`) for `query`. Returns a JSON array
+/// of `{id, score, breakdown}`, best first. `weights` overrides the default
+/// ranking weights, field by field.
+#[wasm_bindgen]
+pub fn rank(query: &str, candidates: &str, weights: Option<String>) -> Result<String, JsError> {
+    rank_json(query, candidates, weights.as_deref()).map_err(|e| JsError::new(&e))
+}
+
+/// Keyword relevance of a memory (JSON) to `query`, from 0 to 1.
+#[wasm_bindgen(js_name = keywordScore)]
+pub fn keyword_score(query: &str, memory: &str) -> Result<f32, JsError> {
+    let memory: Memory = parse(memory, "memory").map_err(|e| JsError::new(&e))?;
+    Ok(ranking::keyword_score(query, &memory))
+}
+
+/// Trust score of a memory (JSON), from 0 to 1.
+#[wasm_bindgen(js_name = trustScore)]
+pub fn trust_score(memory: &str, contradiction_count: usize) -> Result<f32, JsError> {
+    trust_json(memory, contradiction_count).map_err(|e| JsError::new(&e))
+}
+
+/// `text` with PII redacted. `config` is a JSON `[scrub]` section.
+#[wasm_bindgen]
+pub fn scrub(text: &str, config: Option<String>) -> Result<String, JsError> {
+    let config = scrub_config(config.as_deref()).map_err(|e| JsError::new(&e))?;
+    Ok(scrub::scrub(text, &config))
+}
+
+/// Counts of the PII `scrub` would redact from `text`, as JSON.
+#[wasm_bindgen(js_name = scrubReport)]
+pub fn scrub_report(text: &str, config: Option<String>) -> Result<String, JsError> {
+    let config = scrub_config(config.as_deref()).map_err(|e| JsError::new(&e))?;
+    serde_json::to_string(&scrub::analyze(text, &config)).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Markdown context pack of `memories` (a JSON array, most relevant first)
+/// that fit in `token_budget`.
+#[wasm_bindgen(js_name = contextPack)]
+pub fn context_pack(
+    memories: &str,
+    token_budget: usize,
+    project_id: Option<String>,
+) -> Result<String, JsError> {
+    context_pack_json(memories, token_budget, project_id).map_err(|e| JsError::new(&e))
+}
+
+/// Estimated token count of `text`, as used for budgets.
+#[wasm_bindgen(js_name = estimateTokens)]
+pub fn estimate_tokens(text: &str) -> usize {
+    tokens::estimate_tokens(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shabka_core::model::MemoryKind;
+
+    fn memory_json(title: &str, content: &str) -> (Uuid, serde_json::Value) {
+        let memory = Memory::new(title.into(), content.into(), MemoryKind::Fact, "u".into());
+        (memory.id, serde_json::to_value(&memory).unwrap())
+    }
+
+    #[test]
+    fn test_rank_orders_by_keyword_match() {
+        let (pool, pool_json) = memory_json("Connection pool", "The pool runs dry under load");
+        let (_, other_json) = memory_json("Logging", "Logs are JSON lines");
+        let candidates = serde_json::json!([
+            {"memory": other_json, "vector_score": 0.5},
+            {"memory": pool_json, "vector_score": 0.5},
+        ]);
+
+        let ranked: serde_json::Value =
+            serde_json::from_str(&rank_json("pool", &candidates.to_string(), None).unwrap())
+                .unwrap();
+        assert_eq!(ranked[0]["id"], pool.to_string());
+        assert!(ranked[0]["breakdown"]["keyword"].as_f64().unwrap() > 0.0);
+
+        let keyword_only = r#"{"similarity": 0, "recency": 0, "importance": 0, "access_freq": 0, "graph_proximity": 0, "trust": 0}"#;
+        let ranked: serde_json::Value = serde_json::from_str(
+            &rank_json("logs", &candidates.to_string(), Some(keyword_only)).unwrap(),
+        )
+        .unwrap();
+        assert_ne!(ranked[0]["id"], pool.to_string());
+        assert!(rank_json("pool", "not json", None)
+            .unwrap_err()
+            .contains("invalid candidates"));
+    }
+
+    #[test]
+    fn test_trust_scrub_and_context_pack() {
+        let (_, memory) = memory_json("Retry budget", "Mail ops@example.com before raising it");
+        let score = trust_json(&memory.to_string(), 0).unwrap();
+        assert!((0.0..=1.0).contains(&score));
+
+        let config = scrub_config(None).unwrap();
+        assert!(!scrub::scrub("mail ops@example.com", &config).contains("ops@example.com"));
+
+        let pack = context_pack_json(&format!("[{memory}]"), 2000, Some("api".into())).unwrap();
+        assert!(pack.contains("Retry budget"));
+    }
+}
@@ -0,0 +1,64 @@
+//! Check the third-party assets in `vendor.txt` against the files vendored
+//! under `assets/vendor/`. A vendored file whose SHA-256 differs from its pin
+//! fails the build; a file that isn't vendored (or has no pin yet) only warns,
+//! and the dashboard loads it from its pinned CDN URL instead.
+
+use sha2::{Digest, Sha256};
+
+fn main() {
+    println!("cargo:rerun-if-changed=vendor.txt");
+    println!("cargo:rerun-if-changed=assets/vendor");
+
+    let list = std::fs::read_to_string("vendor.txt").expect("failed to read vendor.txt");
+    let mut problems = Vec::new();
+    let mut warnings = Vec::new();
+    for line in list
+        .lines()
+        .filter(|l| !l.starts_with('#') && !l.trim().is_empty())
+    {
+        let mut fields = line.split_whitespace();
+        let (Some(name), Some(pinned), Some(_url)) = (fields.next(), fields.next(), fields.next())
+        else {
+            problems.push(format!("malformed line: {line}"));
+            continue;
+        };
+        let path = format!("assets/vendor/{name}");
+        println!("cargo:rerun-if-changed={path}");
+        let Ok(data) = std::fs::read(&path) else {
+            warnings.push(format!(
+                "{path} is not vendored; the dashboard will load it from its CDN URL"
+            ));
+            continue;
+        };
+        let actual = Sha256::digest(&data)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>();
+        if pinned == "-" {
+            warnings.push(format!(
+                "{name} has no pinned hash (file hashes to {actual})"
+            ));
+        } else if actual != pinned {
+            problems.push(format!(
+                "{path} hashes to {actual}, vendor.txt pins {pinned}"
+            ));
+        }
+    }
+    for warning in &warnings {
+        println!("cargo:warning={warning}");
+    }
+    if !warnings.is_empty() {
+        println!(
+            "cargo:warning=run `just web-assets` (or `just web-assets-pin` after changing a \
+             version) and commit assets/vendor/ and vendor.txt for an offline dashboard"
+        );
+    }
+    if !problems.is_empty() {
+        panic!(
+            "vendored dashboard assets don't match vendor.txt:\n  {}\n\
+             Run `just web-assets` (or `just web-assets-pin` after changing a version) \
+             and commit assets/vendor/ and vendor.txt.",
+            problems.join("\n  ")
+        );
+    }
+}
@@ -0,0 +1,53 @@
+//! Request audit logging (`[audit] enabled`).
+//!
+//! Every request except health checks, static assets and `/mcp` is recorded
+//! in the audit log with its status and latency. MCP tool calls are
+//! recorded one by one by the MCP server instead of as opaque `POST /mcp`s.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use shabka_core::api_tokens::ApiToken;
+use shabka_core::audit::AuditEntry;
+
+use crate::AppState;
+
+pub async fn record(State(state): State<Arc<AppState>>, req: Request, next: Next) -> Response {
+    let path = req.uri().path();
+    if !state.audit.enabled()
+        || path == "/health"
+        || path.starts_with("/assets/")
+        || path == "/mcp"
+        || path.starts_with("/mcp/")
+    {
+        return next.run(req).await;
+    }
+
+    let entry = AuditEntry::new("web", format!("{} {path}", req.method()))
+        .with_params(&redact_token(req.uri().query().unwrap_or_default()));
+    let started = Instant::now();
+    let resp = next.run(req).await;
+    let status = resp.status();
+    let token = resp.extensions().get::<ApiToken>();
+    let entry = entry
+        .with_token(token.map(|t| t.name.clone()))
+        .with_tenant(token.and_then(|t| t.tenant.clone()));
+    state.audit.log(&entry.finish(
+        status.as_u16().to_string(),
+        !(status.is_client_error() || status.is_server_error()),
+        started.elapsed(),
+    ));
+    resp
+}
+
+/// The query string without a `token=` secret.
+fn redact_token(query: &str) -> String {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty() && !pair.starts_with("token="))
+        .collect::<Vec<_>>()
+        .join("&")
+}
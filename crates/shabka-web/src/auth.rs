@@ -0,0 +1,123 @@
+//! API token authentication (`[web] require_token`).
+//!
+//! A token is read from an `Authorization: Bearer` header, the
+//! `shabka_token` cookie, or a `?token=` query parameter. The query form is
+//! for opening the dashboard in a browser: it also sets the cookie, so later
+//! requests from the page carry the token on their own.
+//!
+//! The authenticated [`ApiToken`](api_tokens::ApiToken) is added to the
+//! request's extensions, where the MCP server finds it, and to the
+//! response's, for the audit log. In multi-tenant mode, so is its tenant's
+//! state (see [`crate::tenant`]).
+
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use shabka_core::api_tokens;
+
+use crate::error::ApiError;
+use crate::tenant::TenantState;
+use crate::AppState;
+
+const COOKIE: &str = "shabka_token";
+
+/// POST endpoints that only read, so a read-only token may call them:
+/// retriever and embedding clients send their queries as POST bodies.
+const READ_ONLY_POSTS: &[&str] = &["/api/retriever", "/v1/embeddings"];
+
+/// Reject requests without an active token, and writes (or MCP) with a
+/// read-only one. Runs the request with the token's name as history
+/// context. Does nothing unless `web.require_token` is set.
+pub async fn require_token(
+    State(state): State<Arc<AppState>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let path = req.uri().path();
+    if !state.config.web.require_token || path == "/health" || path.starts_with("/assets/") {
+        return next.run(req).await;
+    }
+
+    let from_query = query_token(req.uri().query());
+    let Some(secret) = bearer_token(req.headers())
+        .or_else(|| cookie_token(req.headers()))
+        .or_else(|| from_query.clone())
+    else {
+        return ApiError::unauthorized("API token required").into_response();
+    };
+
+    let token = match api_tokens::authenticate(&state.storage, &secret, chrono::Utc::now()).await {
+        Ok(Some(token)) => token,
+        Ok(None) => {
+            return ApiError::unauthorized("invalid, expired or revoked API token").into_response()
+        }
+        Err(e) => return ApiError::from(e).into_response(),
+    };
+
+    let writes = (!matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS)
+        && !READ_ONLY_POSTS.contains(&path))
+        || path == "/mcp"
+        || path.starts_with("/mcp/");
+    if writes && !token.can_write() {
+        return ApiError::forbidden(format!("token '{}' is read-only", token.name)).into_response();
+    }
+
+    // A tenant's token is served from that tenant's store, and never from
+    // the default one.
+    if let Some(tenant) = &token.tenant {
+        let Some(tenants) = &state.tenants else {
+            return ApiError::forbidden(format!(
+                "token '{}' belongs to tenant '{tenant}', but multi-tenant mode is off",
+                token.name
+            ))
+            .into_response();
+        };
+        match tenants.state(tenant) {
+            Ok(tenant_state) => {
+                req.extensions_mut().insert(TenantState(tenant_state));
+            }
+            Err(e) => return ApiError::from(e).into_response(),
+        }
+    }
+
+    // Downstream: MCP tool calls read it from the request, the audit log
+    // from the response.
+    req.extensions_mut().insert(token.clone());
+    let mut resp = shabka_core::history::with_token(token.name.clone(), next.run(req)).await;
+    resp.extensions_mut().insert(token);
+    if from_query.as_deref() == Some(secret.as_str()) {
+        let cookie = format!("{COOKIE}={secret}; Path=/; HttpOnly; SameSite=Strict");
+        if let Ok(value) = HeaderValue::from_str(&cookie) {
+            resp.headers_mut().append(header::SET_COOKIE, value);
+        }
+    }
+    resp
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let token = value.strip_prefix("Bearer ")?.trim();
+    (!token.is_empty()).then(|| token.to_string())
+}
+
+fn cookie_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|pair| {
+            let (name, value) = pair.trim().split_once('=')?;
+            (name == COOKIE && !value.is_empty()).then(|| value.to_string())
+        })
+}
+
+fn query_token(query: Option<&str>) -> Option<String> {
+    serde_urlencoded::from_str::<Vec<(String, String)>>(query?)
+        .ok()?
+        .into_iter()
+        .find_map(|(name, value)| (name == "token" && !value.is_empty()).then_some(value))
+}
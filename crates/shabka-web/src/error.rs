@@ -1,5 +1,6 @@
 use axum::http::StatusCode;
 use axum::response::{Html, IntoResponse, Json, Response};
+use shabka_core::error::ShabkaError;
 
 /// Application error type that renders as an HTML error page.
 pub struct AppError(pub anyhow::Error);
@@ -7,6 +8,15 @@ pub struct AppError(pub anyhow::Error);
 impl AppError {
     /// Check if the error is likely a HelixDB connectivity issue.
     fn is_db_unavailable(&self) -> bool {
+        let typed = self.0.chain().any(|e| {
+            matches!(
+                e.downcast_ref::<ShabkaError>(),
+                Some(ShabkaError::StorageUnavailable(_))
+            )
+        });
+        if typed {
+            return true;
+        }
         let msg = format!("{:#}", self.0).to_lowercase();
         msg.contains("connection refused")
             || msg.contains("timed out")
@@ -63,34 +73,52 @@ impl<E: Into<anyhow::Error>> From<E> for AppError {
 pub struct ApiError {
     pub status: StatusCode,
     pub message: String,
+    /// Machine-readable error type, as `ShabkaError::error_type`.
+    pub error_type: Option<&'static str>,
+    /// What the caller can do about it.
+    pub hint: Option<String>,
 }
 
 impl ApiError {
-    pub fn not_found(msg: impl Into<String>) -> Self {
+    fn new(status: StatusCode, msg: impl Into<String>) -> Self {
         Self {
-            status: StatusCode::NOT_FOUND,
+            status,
             message: msg.into(),
+            error_type: None,
+            hint: None,
         }
     }
 
+    pub fn not_found(msg: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, msg)
+    }
+
     pub fn bad_request(msg: impl Into<String>) -> Self {
-        Self {
-            status: StatusCode::BAD_REQUEST,
-            message: msg.into(),
-        }
+        Self::new(StatusCode::BAD_REQUEST, msg)
+    }
+
+    pub fn unauthorized(msg: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, msg)
+    }
+
+    pub fn forbidden(msg: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, msg)
     }
 
     pub fn internal(msg: impl Into<String>) -> Self {
-        Self {
-            status: StatusCode::INTERNAL_SERVER_ERROR,
-            message: msg.into(),
-        }
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, msg)
     }
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        let body = serde_json::json!({ "error": self.message });
+        let mut body = serde_json::json!({ "error": self.message });
+        if let Some(error_type) = self.error_type {
+            body["error_type"] = error_type.into();
+        }
+        if let Some(hint) = self.hint {
+            body["hint"] = hint.into();
+        }
         (self.status, Json(body)).into_response()
     }
 }
@@ -98,19 +126,32 @@ impl IntoResponse for ApiError {
 impl From<anyhow::Error> for ApiError {
     fn from(err: anyhow::Error) -> Self {
         tracing::error!("api error: {:#}", err);
-        Self::internal(format!("{:#}", err))
+        match err.downcast::<ShabkaError>() {
+            Ok(err) => err.into(),
+            Err(err) => Self::internal(format!("{:#}", err)),
+        }
     }
 }
 
-impl From<shabka_core::error::ShabkaError> for ApiError {
-    fn from(err: shabka_core::error::ShabkaError) -> Self {
-        match &err {
-            shabka_core::error::ShabkaError::NotFound(_) => Self::not_found(err.to_string()),
-            shabka_core::error::ShabkaError::InvalidInput(_) => Self::bad_request(err.to_string()),
+impl From<ShabkaError> for ApiError {
+    fn from(err: ShabkaError) -> Self {
+        let status = match &err {
+            ShabkaError::NotFound(_) => StatusCode::NOT_FOUND,
+            ShabkaError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            ShabkaError::StorageUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ShabkaError::ProviderAuth { .. } => StatusCode::BAD_GATEWAY,
+            ShabkaError::DimensionMismatch { .. } => StatusCode::CONFLICT,
+            ShabkaError::QuotaExceeded { .. } => StatusCode::TOO_MANY_REQUESTS,
             _ => {
                 tracing::error!("api error: {}", err);
-                Self::internal(err.to_string())
+                StatusCode::INTERNAL_SERVER_ERROR
             }
+        };
+        Self {
+            status,
+            message: err.to_string(),
+            error_type: Some(err.error_type()),
+            hint: err.hint(),
         }
     }
 }
@@ -1,5 +1,8 @@
+mod audit;
+mod auth;
 mod error;
 mod routes;
+mod tenant;
 
 use std::sync::Arc;
 
@@ -7,22 +10,70 @@ use anyhow::Result;
 use rmcp::transport::streamable_http_server::session::local::LocalSessionManager;
 use rmcp::transport::streamable_http_server::StreamableHttpServerConfig;
 use rmcp::transport::StreamableHttpService;
-use shabka_core::config::{self, ShabkaConfig};
-use shabka_core::embedding::EmbeddingService;
+use shabka_core::attachments::BlobStore;
+use shabka_core::audit::AuditLog;
+use shabka_core::config::ShabkaConfig;
+use shabka_core::embedding::{self, EmbeddingService};
 use shabka_core::history::HistoryLogger;
 use shabka_core::llm::LlmService;
-use shabka_core::storage::{create_backend, Storage};
+use shabka_core::model::Memory;
+use shabka_core::questions::QuestionLog;
+use shabka_core::services::Services;
+use shabka_core::storage::Storage;
+use shabka_core::tenants::Tenants;
 use shabka_mcp::ShabkaServer;
 use tokio_util::sync::CancellationToken;
 use tower_http::trace::TraceLayer;
 
 pub struct AppState {
-    pub storage: Storage,
-    pub embedding: EmbeddingService,
+    pub storage: Arc<Storage>,
+    pub embedding: Arc<EmbeddingService>,
     pub config: ShabkaConfig,
     pub user_id: String,
-    pub history: HistoryLogger,
-    pub llm: Option<LlmService>,
+    pub history: Arc<HistoryLogger>,
+    pub questions: Arc<QuestionLog>,
+    pub llm: Option<Arc<LlmService>>,
+    pub blobs: Arc<BlobStore>,
+    pub audit: Arc<AuditLog>,
+    /// Per-tenant states, in multi-tenant mode.
+    pub tenants: Option<Arc<tenant::TenantStates>>,
+}
+
+impl AppState {
+    pub fn from_services(
+        services: &Services,
+        tenants: Option<Arc<tenant::TenantStates>>,
+    ) -> shabka_core::error::Result<Self> {
+        Ok(Self {
+            storage: services.storage()?,
+            embedding: services.embedding()?,
+            config: (**services.config()).clone(),
+            user_id: services.user_id().to_string(),
+            history: services.history(),
+            questions: services.questions(),
+            llm: services.llm(),
+            blobs: services.blobs(),
+            audit: services.audit(),
+            tenants,
+        })
+    }
+
+    /// Re-embed `memory` after an edit (see
+    /// [`shabka_core::embedding::refresh_after_edit`]). A failure only logs:
+    /// the edit is saved, and the next `shabka reembed` catches up.
+    pub async fn refresh_embedding(&self, previous: Option<&Memory>, memory: &Memory) {
+        if let Err(e) = embedding::refresh_after_edit(
+            self.storage.as_ref(),
+            &self.embedding,
+            &self.config.embedding,
+            previous,
+            memory,
+        )
+        .await
+        {
+            tracing::warn!("failed to re-embed '{}': {e}", memory.title);
+        }
+    }
 }
 
 #[tokio::main]
@@ -36,31 +87,43 @@ async fn main() -> Result<()> {
 
     let config = ShabkaConfig::load(None).unwrap_or_else(|_| ShabkaConfig::default_config());
 
-    let storage = create_backend(&config)?;
+    // One registry for the UI routes and every MCP session.
+    let services = Services::new(config.clone());
 
-    let embedding = EmbeddingService::from_config(&config.embedding)?;
-
-    let user_id = config::resolve_user_id(&config.sharing);
-    let history = HistoryLogger::new(config.history.enabled);
-
-    let llm = if config.llm.enabled {
-        LlmService::from_config(&config.llm).ok()
+    let tenants = if config.tenants.enabled {
+        if !config.web.require_token {
+            anyhow::bail!("[tenants] enabled needs [web] require_token = true");
+        }
+        let tenants = Tenants::new(services.clone());
+        tracing::info!(
+            "multi-tenant mode, stores under {}",
+            tenants.root().display()
+        );
+        Some(Arc::new(tenants))
     } else {
         None
     };
 
-    let state = Arc::new(AppState {
-        storage,
-        embedding,
-        config: config.clone(),
-        user_id,
-        history,
-        llm,
+    let state = Arc::new(AppState::from_services(
+        &services,
+        tenants
+            .clone()
+            .map(|tenants| Arc::new(tenant::TenantStates::new(tenants))),
+    )?);
+
+    let digest = config.digest.enabled.then(|| {
+        tracing::info!("daily digest at {:02}:00 UTC", config.digest.hour);
+        tokio::spawn(shabka_core::digest::run_scheduled(
+            state.storage.clone(),
+            state.history.clone(),
+            config.digest.clone(),
+        ))
     });
 
     // Build MCP HTTP service
     let ct = CancellationToken::new();
     let ct_shutdown = ct.clone();
+    tokio::spawn(cancel_on_signal(ct.clone()));
     let session_manager = Arc::new(LocalSessionManager::default());
     let mcp_config = StreamableHttpServerConfig {
         sse_keep_alive: Some(std::time::Duration::from_secs(30)),
@@ -69,14 +132,29 @@ async fn main() -> Result<()> {
         cancellation_token: ct,
     };
     let mcp_service = StreamableHttpService::new(
-        || ShabkaServer::new().map_err(std::io::Error::other),
+        move || {
+            let server = ShabkaServer::from_services(&services).map_err(std::io::Error::other)?;
+            Ok(match &tenants {
+                Some(tenants) => server.with_tenants(tenants.clone()),
+                None => server,
+            })
+        },
         session_manager,
         mcp_config,
     );
 
+    if config.web.require_token {
+        tracing::info!("API token required (shabka token create)");
+    }
+
     let app = routes::router()
-        .with_state(state)
+        .with_state(state.clone())
         .nest_service("/mcp", mcp_service)
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_token,
+        ))
+        .layer(axum::middleware::from_fn_with_state(state, audit::record))
         .layer(tower_http::cors::CorsLayer::permissive())
         .layer(TraceLayer::new_for_http());
 
@@ -89,5 +167,41 @@ async fn main() -> Result<()> {
         .with_graceful_shutdown(async move { ct_shutdown.cancelled().await })
         .await?;
 
+    // The router (and with it `AppState`) is gone; stop the digest task too,
+    // so the last handle on storage drops here and the memory backend writes
+    // its snapshot before the process exits.
+    if let Some(digest) = digest {
+        digest.abort();
+        let _ = digest.await;
+    }
+    tracing::info!("shabka-web stopped");
     Ok(())
 }
+
+/// Cancel `ct` on Ctrl-C or SIGTERM, for a graceful shutdown.
+async fn cancel_on_signal(ct: CancellationToken) {
+    let ctrl_c = async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    tracing::info!("shutting down");
+    ct.cancel();
+}
@@ -2,20 +2,21 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use askama::Template;
-use axum::extract::State;
 use axum::http::{HeaderMap, StatusCode};
 use axum::response::{Html, IntoResponse, Response};
 use axum::routing::{get, post};
 use axum::Router;
 use chrono::Utc;
-use shabka_core::assess::{self, AssessConfig, AssessmentResult, IssueCounts};
+use shabka_core::assess::{self, AssessConfig, AssessmentResult, HealthPoint, IssueCounts};
 use shabka_core::config::EmbeddingState;
 use shabka_core::history::{EventAction, MemoryEvent};
+use shabka_core::maintenance::MaintenanceOp;
 use shabka_core::model::*;
 use shabka_core::storage::StorageBackend;
 use uuid::Uuid;
 
 use crate::error::AppError;
+use crate::tenant::Tenant;
 use crate::AppState;
 
 pub fn routes() -> Router<Arc<AppState>> {
@@ -45,9 +46,15 @@ struct AnalyticsTemplate {
     quality_score: u32,
     quality_counts: IssueCounts,
     quality_top_issues: Vec<QualityTopIssue>,
+    /// SVG polyline points of the monthly score, empty below two months.
+    quality_sparkline: String,
+    quality_trend_label: String,
     contradiction_count: usize,
 }
 
+/// Months of recorded assess runs shown in the score sparkline.
+const QUALITY_TREND_MONTHS: usize = 12;
+
 struct QualityTopIssue {
     id: Uuid,
     short_id: String,
@@ -63,7 +70,7 @@ struct AccessedEntry {
     days_inactive: i64,
 }
 
-async fn analytics_page(State(state): State<Arc<AppState>>) -> Result<Html<String>, AppError> {
+async fn analytics_page(Tenant(state): Tenant) -> Result<Html<String>, AppError> {
     let entries = state
         .storage
         .timeline(&TimelineQuery {
@@ -91,12 +98,13 @@ async fn analytics_page(State(state): State<Arc<AppState>>) -> Result<Html<Strin
             MemoryStatus::Active => active_count += 1,
             MemoryStatus::Archived => archived_count += 1,
             MemoryStatus::Superseded => superseded_count += 1,
-            MemoryStatus::Pending => {} // Pending memories counted but not charted
+            // Pending and quarantined memories counted but not charted
+            MemoryStatus::Pending | MemoryStatus::Quarantined => {}
         }
     }
 
     let mut kind_items: Vec<(String, usize)> = kind_counts.into_iter().collect();
-    kind_items.sort_by(|a, b| b.1.cmp(&a.1));
+    kind_items.sort_by_key(|k| std::cmp::Reverse(k.1));
     let kind_labels: Vec<String> = kind_items
         .iter()
         .map(|(k, _)| format!("\"{}\"", k))
@@ -146,7 +154,7 @@ async fn analytics_page(State(state): State<Arc<AppState>>) -> Result<Html<Strin
 
     // Most recently accessed (top 10)
     let mut sorted = memories.clone();
-    sorted.sort_by(|a, b| b.accessed_at.cmp(&a.accessed_at));
+    sorted.sort_by_key(|m| std::cmp::Reverse(m.accessed_at));
     let most_accessed: Vec<AccessedEntry> = sorted
         .into_iter()
         .take(10)
@@ -165,6 +173,7 @@ async fn analytics_page(State(state): State<Arc<AppState>>) -> Result<Html<Strin
     // Quality assessment
     let assess_config = AssessConfig {
         stale_days: state.config.graph.stale_days,
+        min_confidence: state.config.capture.min_confidence,
         ..AssessConfig::default()
     };
     let mut quality_results: Vec<AssessmentResult> = memories
@@ -183,7 +192,8 @@ async fn analytics_page(State(state): State<Arc<AppState>>) -> Result<Html<Strin
             }
         })
         .collect();
-    quality_results.sort_by(|a, b| b.issues.len().cmp(&a.issues.len()));
+    assess::flag_retention(&mut quality_results, &memories, &state.config.retention);
+    quality_results.sort_by_key(|r| std::cmp::Reverse(r.issues.len()));
 
     let quality_score = assess::quality_score(&quality_results, memories.len());
     let quality_counts = IssueCounts::from_results(&quality_results);
@@ -201,6 +211,24 @@ async fn analytics_page(State(state): State<Arc<AppState>>) -> Result<Html<Strin
         })
         .collect();
 
+    // Month-end scores of `shabka assess` runs
+    let runs = state
+        .storage
+        .maintenance_runs(Some(MaintenanceOp::Assess), 2000)
+        .await
+        .unwrap_or_default();
+    let mut quality_trend = assess::health_trend(&runs);
+    let skip = quality_trend.len().saturating_sub(QUALITY_TREND_MONTHS);
+    quality_trend.drain(..skip);
+    let (quality_sparkline, quality_trend_label) =
+        match (quality_trend.first(), quality_trend.last()) {
+            (Some(first), Some(last)) if quality_trend.len() > 1 => (
+                sparkline_points(&quality_trend),
+                format!("{} → {} since {}", first.score, last.score, first.month),
+            ),
+            _ => (String::new(), String::new()),
+        };
+
     let migration_warning = EmbeddingState::migration_warning(
         state.embedding.provider_name(),
         state.embedding.model_id(),
@@ -226,13 +254,29 @@ async fn analytics_page(State(state): State<Arc<AppState>>) -> Result<Html<Strin
         quality_score,
         quality_counts,
         quality_top_issues,
+        quality_sparkline,
+        quality_trend_label,
         contradiction_count,
     };
 
     Ok(Html(tmpl.render()?))
 }
 
-async fn archive_stale(State(state): State<Arc<AppState>>) -> Result<Response, AppError> {
+/// Points of a 100×24 polyline through the scores, oldest on the left.
+fn sparkline_points(trend: &[HealthPoint]) -> String {
+    let step = 100.0 / (trend.len().max(2) - 1) as f32;
+    trend
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let y = 22.0 - p.score.min(100) as f32 * 0.2;
+            format!("{:.1},{:.1}", i as f32 * step, y)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+async fn archive_stale(Tenant(state): Tenant) -> Result<Response, AppError> {
     let entries = state
         .storage
         .timeline(&TimelineQuery {
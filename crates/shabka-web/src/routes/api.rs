@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use axum::extract::{Path, Query, State};
+use axum::extract::{Path, Query};
 use axum::http::{HeaderMap, StatusCode};
 use axum::response::{Html, IntoResponse, Json, Response};
 use axum::routing::{get, post};
@@ -8,14 +8,19 @@ use axum::Router;
 use serde::{Deserialize, Serialize};
 use shabka_core::dedup::{self, DedupDecision};
 use shabka_core::graph;
-use shabka_core::history::{EventAction, MemoryEvent};
+use shabka_core::history::{self, EventAction, HistoryFilter, MemoryEvent};
+use shabka_core::inbox::{self, Inbox, InboxOptions};
 use shabka_core::model::*;
-use shabka_core::ranking::{self, RankCandidate, RankingWeights};
+use shabka_core::questions::Question;
+use shabka_core::ranking;
+use shabka_core::retrieval::{self, SearchRequest};
 use shabka_core::sharing;
 use shabka_core::storage::StorageBackend;
+use shabka_core::trust;
 use uuid::Uuid;
 
 use crate::error::ApiError;
+use crate::tenant::Tenant;
 use crate::AppState;
 
 pub fn routes() -> Router<Arc<AppState>> {
@@ -33,11 +38,26 @@ pub fn routes() -> Router<Arc<AppState>> {
         .route("/api/v1/memories/{id}/relate", post(add_relation))
         .route("/api/v1/memories/{id}/relations", get(get_relations))
         .route("/api/v1/memories/{id}/history", get(get_history))
+        .route(
+            "/api/v1/memories/{id}/endorse",
+            post(endorse_memory).delete(withdraw_endorsement),
+        )
+        .route(
+            "/api/v1/memories/{id}/attachments/{sha256}",
+            get(get_attachment),
+        )
         .route("/api/v1/search", get(search))
+        .route("/api/retriever", post(retriever))
         .route("/api/v1/timeline", get(timeline))
         .route("/api/v1/stats", get(stats))
+        .route("/api/v1/activity", get(activity))
+        .route("/api/v1/tags/map", get(tag_map))
+        .route("/api/v1/notifications", get(notifications))
+        .route("/api/v1/history", get(list_history))
         .route("/api/v1/memories/bulk/archive", post(bulk_archive))
         .route("/api/v1/memories/bulk/delete", post(bulk_delete))
+        .route("/api/v1/memories/bulk/tag", post(bulk_tag))
+        .route("/api/v1/memories/bulk/verify", post(bulk_verify))
 }
 
 // -- Request/Response types --
@@ -57,6 +77,8 @@ pub struct CreateMemoryRequest {
     pub related_to: Vec<String>,
     #[serde(default)]
     pub privacy: Option<String>,
+    #[serde(default)]
+    pub label: Option<String>,
 }
 
 fn default_importance() -> f32 {
@@ -72,7 +94,13 @@ pub struct UpdateMemoryRequest {
     pub importance: Option<f32>,
     pub status: Option<String>,
     pub privacy: Option<String>,
+    /// New classification label; an empty string removes it.
+    pub label: Option<String>,
+    /// User responsible for verifying the memory; an empty string unassigns it.
+    pub owner: Option<String>,
     pub verification: Option<String>,
+    /// Why the memory is disputed; used with `verification: "disputed"`.
+    pub dispute_reason: Option<String>,
 }
 
 /// Flat form version where tags is a comma-separated string (from HTMX form inputs).
@@ -85,7 +113,10 @@ struct UpdateMemoryForm {
     importance: Option<f32>,
     status: Option<String>,
     privacy: Option<String>,
+    label: Option<String>,
+    owner: Option<String>,
     verification: Option<String>,
+    dispute_reason: Option<String>,
 }
 
 impl From<UpdateMemoryForm> for UpdateMemoryRequest {
@@ -104,7 +135,10 @@ impl From<UpdateMemoryForm> for UpdateMemoryRequest {
             importance: form.importance,
             status: form.status,
             privacy: form.privacy,
+            label: form.label,
+            owner: form.owner,
             verification: form.verification,
+            dispute_reason: form.dispute_reason,
         }
     }
 }
@@ -142,6 +176,77 @@ fn default_search_limit() -> usize {
     10
 }
 
+/// Most documents one `/api/retriever` call returns.
+const MAX_RETRIEVER_K: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct RetrieverRequest {
+    /// Search text; accepts the same field operators as `/api/v1/search`.
+    pub query: String,
+    #[serde(default = "default_retriever_k")]
+    pub k: usize,
+}
+
+fn default_retriever_k() -> usize {
+    4
+}
+
+#[derive(Debug, Serialize)]
+pub struct RetrieverResponse {
+    pub documents: Vec<RetrieverDocument>,
+}
+
+/// A memory shaped as a framework document: `page_content` plus flat
+/// `metadata`, with `id` and `score` also at the top level.
+#[derive(Debug, Serialize)]
+pub struct RetrieverDocument {
+    pub id: Uuid,
+    pub page_content: String,
+    pub score: f32,
+    pub metadata: RetrieverMetadata,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RetrieverMetadata {
+    pub id: Uuid,
+    pub title: String,
+    pub kind: String,
+    pub tags: Vec<String>,
+    pub project_id: Option<String>,
+    pub importance: f32,
+    pub verification: String,
+    pub created_by: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub score: f32,
+    pub source: String,
+}
+
+impl From<ranking::RankedResult> for RetrieverDocument {
+    fn from(r: ranking::RankedResult) -> Self {
+        let m = r.memory;
+        Self {
+            id: m.id,
+            score: r.score,
+            metadata: RetrieverMetadata {
+                id: m.id,
+                title: m.title,
+                kind: m.kind.to_string(),
+                tags: m.tags,
+                project_id: m.project_id,
+                importance: m.importance,
+                verification: m.verification.to_string(),
+                created_by: m.created_by,
+                created_at: m.created_at,
+                updated_at: m.updated_at,
+                score: r.score,
+                source: "shabka".to_string(),
+            },
+            page_content: m.content,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct TimelineParams {
     #[serde(default = "default_list_limit")]
@@ -149,11 +254,84 @@ pub struct TimelineParams {
     pub session_id: Option<String>,
 }
 
+/// Longest window `/api/v1/activity` covers, in days.
+const MAX_ACTIVITY_DAYS: i64 = 3660;
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityParams {
+    #[serde(default = "default_activity_days")]
+    pub days: i64,
+    pub project: Option<String>,
+}
+
+fn default_activity_days() -> i64 {
+    365
+}
+
+/// Most tags `/api/v1/tags/map` will return.
+const MAX_MAP_TAGS: usize = 300;
+
+#[derive(Debug, Deserialize)]
+pub struct TagMapParams {
+    #[serde(default = "default_map_tags")]
+    pub max_tags: usize,
+}
+
+fn default_map_tags() -> usize {
+    60
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryParams {
+    pub memory_id: Option<Uuid>,
+    pub actor: Option<String>,
+    /// Comma-separated actions, e.g. `updated,deleted`.
+    pub action: Option<String>,
+    pub project: Option<String>,
+    /// `YYYY-MM-DD`, RFC 3339, or a span back from now like `7d`.
+    pub since: Option<String>,
+    /// Exclusive; a bare date includes that day.
+    pub until: Option<String>,
+    #[serde(default = "default_history_limit")]
+    pub limit: usize,
+}
+
+fn default_history_limit() -> usize {
+    100
+}
+
+/// Most events one history request returns.
+const MAX_HISTORY_EVENTS: usize = 10_000;
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationParams {
+    /// Also look for consolidation candidates, which embeds every memory.
+    #[serde(default)]
+    pub consolidation: bool,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct BulkIdsRequest {
     pub ids: Vec<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct BulkTagRequest {
+    pub ids: Vec<String>,
+    /// Tags to add to each memory.
+    #[serde(default)]
+    pub add: Vec<String>,
+    /// Tags to remove from each memory (case-insensitive).
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkVerifyRequest {
+    pub ids: Vec<String>,
+    pub verification: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct CreateMemoryResponse {
     pub action: String,
@@ -182,6 +360,25 @@ pub struct StatsResponse {
     pub embedding_dimensions: usize,
 }
 
+#[derive(Debug, Serialize)]
+pub struct ActivityResponse {
+    pub since: chrono::NaiveDate,
+    pub until: chrono::NaiveDate,
+    pub total: usize,
+    pub projects: Vec<ProjectActivity>,
+}
+
+/// Daily capture counts for one project (`None` for memories without one).
+#[derive(Debug, Serialize)]
+pub struct ProjectActivity {
+    pub project: Option<String>,
+    pub total: usize,
+    pub last_day: Option<chrono::NaiveDate>,
+    /// Longest run of days in the window with nothing captured.
+    pub longest_gap_days: i64,
+    pub days: std::collections::BTreeMap<chrono::NaiveDate, usize>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct KindCount {
     pub kind: String,
@@ -383,12 +580,47 @@ fn render_verification_fragment(id: &Uuid, memory: &Memory) -> String {
         r#"{badge}
 <div class="verify-actions" style="margin-left:auto;display:flex;gap:0.35rem">
     <button hx-put="/api/v1/memories/{id}" hx-headers='{{"Content-Type":"application/json"}}' hx-vals='{{"verification":"verified"}}' hx-target="closest .verify-group" hx-swap="innerHTML" hx-confirm="Mark this memory as verified?" class="btn btn-outline" style="font-size:0.72rem;padding:0.2rem 0.5rem;border-color:#22c55e;color:#22c55e">Verify</button>
-    <button hx-put="/api/v1/memories/{id}" hx-headers='{{"Content-Type":"application/json"}}' hx-vals='{{"verification":"disputed"}}' hx-target="closest .verify-group" hx-swap="innerHTML" hx-confirm="Mark this memory as disputed?" class="btn btn-outline" style="font-size:0.72rem;padding:0.2rem 0.5rem;border-color:#f59e0b;color:#f59e0b">Dispute</button>
+    <button hx-put="/api/v1/memories/{id}" hx-headers='{{"Content-Type":"application/json"}}' hx-vals='{{"verification":"disputed"}}' hx-target="closest .verify-group" hx-swap="innerHTML" hx-prompt="Why is this memory disputed? (optional)" class="btn btn-outline" style="font-size:0.72rem;padding:0.2rem 0.5rem;border-color:#f59e0b;color:#f59e0b">Dispute</button>
     <button hx-put="/api/v1/memories/{id}" hx-headers='{{"Content-Type":"application/json"}}' hx-vals='{{"verification":"outdated"}}' hx-target="closest .verify-group" hx-swap="innerHTML" hx-confirm="Mark this memory as outdated?" class="btn btn-outline" style="font-size:0.72rem;padding:0.2rem 0.5rem;border-color:#ef4444;color:#ef4444">Outdated</button>
 </div>"#,
     )
 }
 
+/// The detail page's endorsement row: who endorsed the memory, and a
+/// button to endorse or withdraw unless it is `user_id`'s own.
+fn render_endorsement_fragment(id: &Uuid, memory: &Memory, user_id: &str) -> String {
+    let count = memory.endorsements.len();
+    let names = memory
+        .endorsements
+        .iter()
+        .map(|e| html_escape(&e.endorsed_by))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let by = if names.is_empty() {
+        String::new()
+    } else {
+        format!(r#" <span style="color:var(--text-dim)">by {names}</span>"#)
+    };
+    let button = if memory.created_by == user_id {
+        String::new()
+    } else if memory.endorsements.iter().any(|e| e.endorsed_by == user_id) {
+        format!(
+            r#"<button hx-delete="/api/v1/memories/{id}/endorse" hx-target="closest .endorse-group" hx-swap="outerHTML" class="btn btn-outline" style="font-size:0.72rem;padding:0.2rem 0.5rem">Withdraw endorsement</button>"#
+        )
+    } else {
+        format!(
+            r#"<button hx-post="/api/v1/memories/{id}/endorse" hx-target="closest .endorse-group" hx-swap="outerHTML" class="btn btn-outline" style="font-size:0.72rem;padding:0.2rem 0.5rem;border-color:#22c55e;color:#22c55e">Endorse</button>"#
+        )
+    };
+    format!(
+        r#"<div class="endorse-group" style="display:flex;gap:0.5rem;align-items:center;margin-bottom:1rem;font-size:0.85rem">
+  <span>{count} endorsement{}{by}</span>
+  {button}
+</div>"#,
+        if count == 1 { "" } else { "s" },
+    )
+}
+
 fn html_escape(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('<', "&lt;")
@@ -404,7 +636,7 @@ pub struct EditFieldParams {
 }
 
 async fn edit_field(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Path(id): Path<Uuid>,
     Query(params): Query<EditFieldParams>,
 ) -> Result<Html<String>, ApiError> {
@@ -419,7 +651,7 @@ async fn edit_field(
 }
 
 async fn create_memory(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Json(input): Json<CreateMemoryRequest>,
 ) -> Result<Json<CreateMemoryResponse>, ApiError> {
     let kind: MemoryKind = input
@@ -438,7 +670,10 @@ async fn create_memory(
     let mut memory = Memory::new(input.title, input.content, kind, state.user_id.clone())
         .with_tags(input.tags)
         .with_importance(input.importance)
-        .with_privacy(privacy);
+        .with_privacy(privacy)
+        .with_label(input.label);
+    state.config.labels.apply(&mut memory);
+    shabka_core::devices::stamp(&mut memory);
 
     if let Some(scope) = input.scope {
         if scope != "global" {
@@ -453,9 +688,9 @@ async fn create_memory(
         .map_err(|e| ApiError::internal(e.to_string()))?;
 
     // Smart dedup
-    let llm_ref = state.llm.as_ref();
+    let llm_ref = state.llm.as_deref();
     let decision = dedup::check_duplicate(
-        &state.storage,
+        state.storage.as_ref(),
         &embedding,
         &state.config.graph,
         None,
@@ -470,13 +705,22 @@ async fn create_memory(
             existing_id,
             existing_title,
             similarity,
-        } => Ok(Json(CreateMemoryResponse {
-            action: "skipped".to_string(),
-            id: existing_id.to_string(),
-            title: existing_title,
-            superseded_id: None,
-            similarity: Some(similarity),
-        })),
+        } => {
+            // Saving the same fact again corroborates it.
+            let _ = trust::record_assertion(
+                state.storage.as_ref(),
+                existing_id,
+                Assertion::new(state.user_id.clone(), "web"),
+            )
+            .await;
+            Ok(Json(CreateMemoryResponse {
+                action: "skipped".to_string(),
+                id: existing_id.to_string(),
+                title: existing_title,
+                superseded_id: None,
+                similarity: Some(similarity),
+            }))
+        }
         DedupDecision::Supersede {
             existing_id,
             existing_title,
@@ -531,7 +775,7 @@ async fn create_memory(
             merged_title,
             similarity,
         } => {
-            let _ = state
+            if let Ok(merged) = state
                 .storage
                 .update_memory(
                     existing_id,
@@ -541,7 +785,10 @@ async fn create_memory(
                         ..Default::default()
                     },
                 )
-                .await;
+                .await
+            {
+                state.refresh_embedding(None, &merged).await;
+            }
 
             state.history.log(
                 &MemoryEvent::new(existing_id, EventAction::Updated, state.user_id.clone())
@@ -611,7 +858,7 @@ async fn create_memory(
 
             // Auto-relate
             let _ = graph::semantic_auto_relate(
-                &state.storage,
+                state.storage.as_ref(),
                 memory.id,
                 &embedding,
                 Some(state.config.graph.similarity_threshold),
@@ -636,7 +883,7 @@ async fn create_memory(
 }
 
 async fn list_memories(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Query(params): Query<ListParams>,
 ) -> Result<Json<Vec<TimelineEntry>>, ApiError> {
     let query = TimelineQuery {
@@ -677,7 +924,7 @@ async fn list_memories(
 }
 
 async fn get_memory(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Path(id): Path<Uuid>,
 ) -> Result<Json<MemoryResponse>, ApiError> {
     let memory = state
@@ -692,7 +939,7 @@ async fn get_memory(
 }
 
 async fn update_memory(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Path(id): Path<Uuid>,
     headers: HeaderMap,
     body: axum::body::Bytes,
@@ -748,7 +995,7 @@ async fn update_memory(
         })
         .transpose()?;
 
-    let update = UpdateMemoryInput {
+    let mut update = UpdateMemoryInput {
         title: input.title,
         content: input.content,
         tags: input.tags,
@@ -756,8 +1003,35 @@ async fn update_memory(
         status,
         kind,
         privacy,
+        label: input.label,
+        owner: input.owner.map(|o| o.trim().to_string()),
         verification,
+        verification_expires_at: verification.and_then(|v| {
+            trust::verification_expiry(
+                v,
+                kind.unwrap_or(old_memory.kind),
+                &state.config.retention,
+                chrono::Utc::now(),
+            )
+        }),
+        dispute: (verification == Some(VerificationStatus::Disputed)).then(|| {
+            // The detail page's Dispute button asks for a reason via hx-prompt.
+            let prompted = headers
+                .get("hx-prompt")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            Dispute::new(state.user_id.clone()).with_reason(input.dispute_reason.or(prompted))
+        }),
+        accessed_at: None,
+        attachments: None,
+        code: None,
+        assertions: None,
+        endorsements: None,
     };
+    state
+        .config
+        .labels
+        .constrain_update(&old_memory, &mut update);
 
     shabka_core::model::validate_update_input(&update)?;
 
@@ -766,6 +1040,7 @@ async fn update_memory(
         .update_memory(id, &update)
         .await
         .map_err(|e| ApiError::internal(e.to_string()))?;
+    state.refresh_embedding(Some(&old_memory), &memory).await;
 
     let changes = shabka_core::history::diff_update(&old_memory, &update);
     state.history.log(
@@ -804,12 +1079,43 @@ async fn update_memory(
     Ok(Json(memory).into_response())
 }
 
+async fn endorse_memory(
+    Tenant(state): Tenant,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let memory = trust::endorse(&*state.storage, id, &state.user_id).await?;
+    endorsement_response(&state, &id, memory, &headers)
+}
+
+async fn withdraw_endorsement(
+    Tenant(state): Tenant,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let memory = trust::withdraw_endorsement(&*state.storage, id, &state.user_id).await?;
+    endorsement_response(&state, &id, memory, &headers)
+}
+
+fn endorsement_response(
+    state: &AppState,
+    id: &Uuid,
+    memory: Memory,
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
+    if is_htmx(headers) {
+        let html = render_endorsement_fragment(id, &memory, &state.user_id);
+        return Ok(Html(html).into_response());
+    }
+    Ok(Json(memory).into_response())
+}
+
 async fn delete_memory(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Path(id): Path<Uuid>,
     headers: HeaderMap,
 ) -> Result<Response, ApiError> {
-    let title = state.storage.get_memory(id).await.ok().map(|m| m.title);
+    let memory = state.storage.get_memory(id).await.ok();
 
     state
         .storage
@@ -818,8 +1124,8 @@ async fn delete_memory(
         .map_err(|e| ApiError::internal(e.to_string()))?;
 
     let mut event = MemoryEvent::new(id, EventAction::Deleted, state.user_id.clone());
-    if let Some(t) = title {
-        event = event.with_title(t);
+    if let Some(memory) = &memory {
+        event = event.with_snapshot(memory);
     }
     state.history.log(&event);
 
@@ -833,7 +1139,7 @@ async fn delete_memory(
 }
 
 async fn add_relation(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Path(id): Path<Uuid>,
     Json(input): Json<AddRelationRequest>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
@@ -866,7 +1172,7 @@ async fn add_relation(
 }
 
 async fn get_relations(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Path(id): Path<Uuid>,
 ) -> Result<Json<Vec<MemoryRelation>>, ApiError> {
     let relations = state
@@ -878,103 +1184,164 @@ async fn get_relations(
 }
 
 async fn get_history(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Path(id): Path<Uuid>,
 ) -> Result<Json<Vec<shabka_core::history::MemoryEvent>>, ApiError> {
     let events = state.history.history_for(id);
     Ok(Json(events))
 }
 
-async fn search(
-    State(state): State<Arc<AppState>>,
-    Query(params): Query<SearchParams>,
-) -> Result<Json<Vec<MemoryIndex>>, ApiError> {
-    let embedding = state
-        .embedding
-        .embed(&params.q)
-        .await
-        .map_err(|e| ApiError::internal(e.to_string()))?;
-
-    let fetch_limit = params.limit * 3;
-    let mut results = state
-        .storage
-        .vector_search(&embedding, fetch_limit)
+async fn list_history(
+    Tenant(state): Tenant,
+    Query(params): Query<HistoryParams>,
+) -> Result<Json<Vec<MemoryEvent>>, ApiError> {
+    let now = chrono::Utc::now();
+    let time = |value: Option<String>, end_of_day: bool| {
+        value
+            .map(|v| history::parse_time(&v, end_of_day, now))
+            .transpose()
+            .map_err(|e| ApiError::bad_request(e.to_string()))
+    };
+    let actions = params
+        .action
+        .iter()
+        .flat_map(|a| a.split(','))
+        .map(|a| a.trim())
+        .filter(|a| !a.is_empty())
+        .map(|a| a.parse::<EventAction>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ApiError::bad_request)?;
+    let mut filter = HistoryFilter {
+        memory_id: params.memory_id,
+        actor: params.actor.filter(|a| !a.is_empty()),
+        actions,
+        since: time(params.since, false)?,
+        until: time(params.until, true)?,
+        project: params.project.filter(|p| !p.is_empty()),
+        ..Default::default()
+    };
+    filter
+        .resolve_project(state.storage.as_ref())
         .await
         .map_err(|e| ApiError::internal(e.to_string()))?;
+    Ok(Json(
+        state
+            .history
+            .query(&filter, params.limit.clamp(1, MAX_HISTORY_EVENTS)),
+    ))
+}
 
-    sharing::filter_search_results(&mut results, &state.user_id);
+/// Download an attachment. Only blobs referenced by the memory are served.
+async fn get_attachment(
+    Tenant(state): Tenant,
+    Path((id, sha256)): Path<(Uuid, String)>,
+) -> Result<Response, ApiError> {
+    let memory = state.storage.get_memory(id).await?;
+    let attachment = memory
+        .attachments
+        .iter()
+        .find(|a| a.sha256 == sha256)
+        .ok_or_else(|| ApiError::not_found(format!("attachment {sha256} not found")))?;
+    let bytes = state.blobs.get(&attachment.sha256)?;
+    let content_type = if attachment.content_type.is_empty() {
+        "application/octet-stream"
+    } else {
+        attachment.content_type.as_str()
+    };
+    let filename = attachment.name.replace(['"', '\\', '\r', '\n'], "_");
+    Ok((
+        [
+            (axum::http::header::CONTENT_TYPE, content_type.to_string()),
+            (
+                axum::http::header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        bytes,
+    )
+        .into_response())
+}
 
+async fn search(
+    Tenant(state): Tenant,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<Vec<MemoryIndex>>, ApiError> {
     let tag_filter: Vec<String> = params
         .tag
         .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
         .unwrap_or_default();
 
-    let filtered: Vec<(Memory, f32)> = results
-        .into_iter()
-        .filter(|(m, _)| {
-            if let Some(ref kind_str) = params.kind {
-                if let Ok(k) = kind_str.parse::<MemoryKind>() {
-                    if m.kind != k {
-                        return false;
-                    }
-                }
-            }
-            if !tag_filter.is_empty() && !tag_filter.iter().any(|t| m.tags.contains(t)) {
-                return false;
-            }
-            true
-        })
-        .collect();
-
-    let memory_ids: Vec<Uuid> = filtered.iter().map(|(m, _)| m.id).collect();
-    let counts = state
-        .storage
-        .count_relations(&memory_ids)
-        .await
-        .unwrap_or_default();
-    let count_map: std::collections::HashMap<Uuid, usize> = counts.into_iter().collect();
-
-    let contradiction_counts = state
-        .storage
-        .count_contradictions(&memory_ids)
-        .await
-        .unwrap_or_default();
-    let contradiction_map: std::collections::HashMap<Uuid, usize> =
-        contradiction_counts.into_iter().collect();
-
-    let candidates: Vec<RankCandidate> = filtered
-        .into_iter()
-        .map(|(memory, vector_score)| {
-            let kw_score = ranking::keyword_score(&params.q, &memory);
-            RankCandidate {
-                relation_count: count_map.get(&memory.id).copied().unwrap_or(0),
-                keyword_score: kw_score,
-                contradiction_count: contradiction_map.get(&memory.id).copied().unwrap_or(0),
-                memory,
-                vector_score,
-            }
+    let request = SearchRequest::parse(&params.q)
+        .map_err(|e| ApiError::bad_request(e.to_string()))?
+        .map_query(|q| {
+            q.with_kind(params.kind.as_deref().and_then(|k| k.parse().ok()))
+                .with_tags(tag_filter)
         })
-        .collect();
+        .limit(params.limit);
+    let query = request.query.clone();
 
-    let ranked = ranking::rank(candidates, &RankingWeights::default());
-    let top: Vec<MemoryIndex> = ranked
+    let results = rank_search(&state, request).await?;
+    let conflicts = results.conflicts.clone();
+    let mut top: Vec<MemoryIndex> = results
+        .top()
         .into_iter()
-        .take(params.limit)
         .map(|r| MemoryIndex::from((&r.memory, r.score)))
         .collect();
+    retrieval::mark_conflicts(&mut top, &conflicts);
+    state.questions.record(
+        &Question::new(&query.text, "web", &state.user_id)
+            .with_project(query.project.clone())
+            .with_scores(top.iter().map(|r| r.score)),
+    );
 
     Ok(Json(top))
 }
 
+/// Search as this user; `top()` gives the `request.limit` best results.
+async fn rank_search(
+    state: &AppState,
+    request: SearchRequest,
+) -> Result<retrieval::SearchResults, ApiError> {
+    let request = request
+        .visible_to(state.user_id.as_str())
+        .demote_contradicted(state.config.retrieval.demote_contradicted);
+    retrieval::search(state.storage.as_ref(), &state.embedding, &request)
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))
+}
+
+/// `POST /api/retriever` — the `{"query", "k"} -> documents` contract of
+/// LangChain and LlamaIndex remote retrievers.
+async fn retriever(
+    Tenant(state): Tenant,
+    Json(req): Json<RetrieverRequest>,
+) -> Result<Json<RetrieverResponse>, ApiError> {
+    if req.k == 0 || req.k > MAX_RETRIEVER_K {
+        return Err(ApiError::bad_request(format!(
+            "k must be between 1 and {MAX_RETRIEVER_K}"
+        )));
+    }
+    let request = SearchRequest::parse(&req.query)
+        .map_err(|e| ApiError::bad_request(e.to_string()))?
+        .limit(req.k);
+    let query = request.query.clone();
+    let ranked = rank_search(&state, request).await?.top();
+    let documents: Vec<RetrieverDocument> =
+        ranked.into_iter().map(RetrieverDocument::from).collect();
+    state.questions.record(
+        &Question::new(&query.text, "web", &state.user_id)
+            .with_project(query.project.clone())
+            .with_scores(documents.iter().map(|d| d.score)),
+    );
+    Ok(Json(RetrieverResponse { documents }))
+}
+
 async fn timeline(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Query(params): Query<TimelineParams>,
 ) -> Result<Json<Vec<TimelineEntry>>, ApiError> {
-    let query = TimelineQuery {
-        limit: params.limit,
-        session_id: params.session_id.and_then(|s| Uuid::parse_str(&s).ok()),
-        ..Default::default()
-    };
+    let query = TimelineQuery::new(params.limit)
+        .with_session(params.session_id.and_then(|s| Uuid::parse_str(&s).ok()));
 
     let mut entries = state
         .storage
@@ -987,7 +1354,112 @@ async fn timeline(
     Ok(Json(entries))
 }
 
-async fn stats(State(state): State<Arc<AppState>>) -> Result<Json<StatsResponse>, ApiError> {
+async fn activity(
+    Tenant(state): Tenant,
+    Query(params): Query<ActivityParams>,
+) -> Result<Json<ActivityResponse>, ApiError> {
+    if !(1..=MAX_ACTIVITY_DAYS).contains(&params.days) {
+        return Err(ApiError::bad_request(format!(
+            "days must be between 1 and {MAX_ACTIVITY_DAYS}"
+        )));
+    }
+    let now = chrono::Utc::now();
+    let until = now.date_naive();
+    let since = until - chrono::Duration::days(params.days - 1);
+    let rows = state
+        .storage
+        .daily_activity(since.and_time(chrono::NaiveTime::MIN).and_utc())
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))?;
+
+    let mut projects: Vec<ProjectActivity> = Vec::new();
+    for row in rows {
+        if params.project.is_some() && row.project_id != params.project {
+            continue;
+        }
+        let index = match projects.iter().position(|p| p.project == row.project_id) {
+            Some(i) => i,
+            None => {
+                projects.push(ProjectActivity {
+                    project: row.project_id.clone(),
+                    total: 0,
+                    last_day: None,
+                    longest_gap_days: 0,
+                    days: Default::default(),
+                });
+                projects.len() - 1
+            }
+        };
+        let project = &mut projects[index];
+        project.total += row.count;
+        *project.days.entry(row.day).or_insert(0) += row.count;
+    }
+    for project in &mut projects {
+        project.last_day = project.days.keys().next_back().copied();
+        project.longest_gap_days = longest_gap(since, until, project.days.keys().copied());
+    }
+    projects.sort_by_key(|p| std::cmp::Reverse(p.total));
+
+    Ok(Json(ActivityResponse {
+        since,
+        until,
+        total: projects.iter().map(|p| p.total).sum(),
+        projects,
+    }))
+}
+
+/// Longest run of days in `since..=until` missing from `active` (sorted).
+fn longest_gap(
+    since: chrono::NaiveDate,
+    until: chrono::NaiveDate,
+    active: impl Iterator<Item = chrono::NaiveDate>,
+) -> i64 {
+    let mut longest = 0;
+    let mut previous = since - chrono::Duration::days(1);
+    for day in active.chain(std::iter::once(until + chrono::Duration::days(1))) {
+        longest = longest.max((day - previous).num_days() - 1);
+        previous = day;
+    }
+    longest
+}
+
+async fn tag_map(
+    Tenant(state): Tenant,
+    Query(params): Query<TagMapParams>,
+) -> Result<Json<shabka_core::tag_map::TagMap>, ApiError> {
+    let (usage, pairs) = state
+        .storage
+        .tag_aggregates()
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))?;
+    Ok(Json(shabka_core::tag_map::build(
+        usage,
+        pairs,
+        params.max_tags.clamp(1, MAX_MAP_TAGS),
+    )))
+}
+
+async fn notifications(
+    Tenant(state): Tenant,
+    Query(params): Query<NotificationParams>,
+) -> Result<Json<Inbox>, ApiError> {
+    let options = InboxOptions::from_config(&state.config);
+    let mut inbox = inbox::collect(state.storage.as_ref(), &state.user_id, &options)
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))?;
+    if params.consolidation {
+        let previews = shabka_core::consolidate::preview(
+            state.storage.as_ref(),
+            &state.embedding,
+            &state.config.consolidate,
+        )
+        .await;
+        inbox.add_consolidation(&previews);
+    }
+    Ok(Json(inbox))
+}
+
+async fn stats(Tenant(state): Tenant) -> Result<Json<StatsResponse>, ApiError> {
     let entries = state
         .storage
         .timeline(&TimelineQuery {
@@ -1017,7 +1489,7 @@ async fn stats(State(state): State<Arc<AppState>>) -> Result<Json<StatsResponse>
             MemoryStatus::Active => active += 1,
             MemoryStatus::Archived => archived += 1,
             MemoryStatus::Superseded => superseded += 1,
-            MemoryStatus::Pending => pending += 1,
+            MemoryStatus::Pending | MemoryStatus::Quarantined => pending += 1,
         }
     }
 
@@ -1025,7 +1497,7 @@ async fn stats(State(state): State<Arc<AppState>>) -> Result<Json<StatsResponse>
         .into_iter()
         .map(|(kind, count)| KindCount { kind, count })
         .collect();
-    by_kind.sort_by(|a, b| b.count.cmp(&a.count));
+    by_kind.sort_by_key(|k| std::cmp::Reverse(k.count));
 
     // Count total relations
     let mut total_relations = 0usize;
@@ -1053,7 +1525,7 @@ async fn stats(State(state): State<Arc<AppState>>) -> Result<Json<StatsResponse>
 }
 
 async fn bulk_archive(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Json(input): Json<BulkIdsRequest>,
 ) -> Result<Json<BulkResult>, ApiError> {
     let mut processed = 0usize;
@@ -1088,8 +1560,115 @@ async fn bulk_archive(
     Ok(Json(BulkResult { processed, errors }))
 }
 
+async fn bulk_tag(
+    Tenant(state): Tenant,
+    Json(input): Json<BulkTagRequest>,
+) -> Result<Json<BulkResult>, ApiError> {
+    let clean = |tags: &[String]| -> Vec<String> {
+        tags.iter()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect()
+    };
+    let add = clean(&input.add);
+    let remove = clean(&input.remove);
+    if add.is_empty() && remove.is_empty() {
+        return Err(ApiError::bad_request("no tags to add or remove"));
+    }
+
+    Ok(Json(
+        bulk_update(&state, &input.ids, |memory| {
+            let mut tags: Vec<String> = memory
+                .tags
+                .iter()
+                .filter(|t| !remove.iter().any(|r| r.eq_ignore_ascii_case(t)))
+                .cloned()
+                .collect();
+            for tag in &add {
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
+                }
+            }
+            UpdateMemoryInput {
+                tags: Some(tags),
+                ..Default::default()
+            }
+        })
+        .await,
+    ))
+}
+
+async fn bulk_verify(
+    Tenant(state): Tenant,
+    Json(input): Json<BulkVerifyRequest>,
+) -> Result<Json<BulkResult>, ApiError> {
+    let verification = input
+        .verification
+        .parse::<VerificationStatus>()
+        .map_err(ApiError::bad_request)?;
+    let now = chrono::Utc::now();
+
+    Ok(Json(
+        bulk_update(&state, &input.ids, |memory| UpdateMemoryInput {
+            verification: Some(verification),
+            verification_expires_at: trust::verification_expiry(
+                verification,
+                memory.kind,
+                &state.config.retention,
+                now,
+            ),
+            dispute: (verification == VerificationStatus::Disputed)
+                .then(|| Dispute::new(state.user_id.clone())),
+            ..Default::default()
+        })
+        .await,
+    ))
+}
+
+/// Apply the update `update_for` builds from each memory in `ids`, logging
+/// each change. Unknown or invalid IDs count as errors.
+async fn bulk_update(
+    state: &AppState,
+    ids: &[String],
+    update_for: impl Fn(&Memory) -> UpdateMemoryInput,
+) -> BulkResult {
+    let mut processed = 0usize;
+    let mut errors = 0usize;
+
+    for id_str in ids {
+        let Ok(id) = Uuid::parse_str(id_str) else {
+            errors += 1;
+            continue;
+        };
+        let Ok(old_memory) = state.storage.get_memory(id).await else {
+            errors += 1;
+            continue;
+        };
+
+        let mut update = update_for(&old_memory);
+        state
+            .config
+            .labels
+            .constrain_update(&old_memory, &mut update);
+        match state.storage.update_memory(id, &update).await {
+            Ok(m) => {
+                processed += 1;
+                let changes = shabka_core::history::diff_update(&old_memory, &update);
+                state.history.log(
+                    &MemoryEvent::new(id, EventAction::Updated, state.user_id.clone())
+                        .with_title(&m.title)
+                        .with_changes(changes),
+                );
+            }
+            Err(_) => errors += 1,
+        }
+    }
+
+    BulkResult { processed, errors }
+}
+
 async fn bulk_delete(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Json(input): Json<BulkIdsRequest>,
 ) -> Result<Json<BulkResult>, ApiError> {
     let mut processed = 0usize;
@@ -1104,14 +1683,14 @@ async fn bulk_delete(
             }
         };
 
-        let title = state.storage.get_memory(id).await.ok().map(|m| m.title);
+        let memory = state.storage.get_memory(id).await.ok();
 
         match state.storage.delete_memory(id).await {
             Ok(()) => {
                 processed += 1;
                 let mut event = MemoryEvent::new(id, EventAction::Deleted, state.user_id.clone());
-                if let Some(t) = title {
-                    event = event.with_title(t);
+                if let Some(memory) = &memory {
+                    event = event.with_snapshot(memory);
                 }
                 state.history.log(&event);
             }
@@ -1128,23 +1707,46 @@ mod tests {
     use axum::body::Body;
     use axum::http::{Request, StatusCode};
     use http_body_util::BodyExt;
+    use shabka_core::attachments::BlobStore;
+    use shabka_core::audit::{AuditConfig, AuditFilter, AuditLog};
     use shabka_core::config::ShabkaConfig;
     use shabka_core::embedding::EmbeddingService;
     use shabka_core::history::HistoryLogger;
+    use shabka_core::questions::QuestionLog;
     use shabka_core::storage::{SqliteStorage, Storage};
     use tower::ServiceExt;
 
     fn test_app_state() -> Arc<AppState> {
+        test_app_state_with_history(HistoryLogger::new(false))
+    }
+
+    fn test_app_state_with_history(history: HistoryLogger) -> Arc<AppState> {
         let storage = Storage::Sqlite(SqliteStorage::open_in_memory().unwrap());
         let config = ShabkaConfig::default_config();
         let embedding = EmbeddingService::from_config(&config.embedding).unwrap();
+        let blobs = BlobStore::with_path(
+            std::env::temp_dir().join(format!("shabka-web-blobs-{}", Uuid::now_v7())),
+            &config.attachments,
+        );
+        let questions = QuestionLog::with_path(
+            std::env::temp_dir().join(format!("shabka-web-questions-{}.jsonl", Uuid::now_v7())),
+            &config.questions,
+        );
+        let audit = AuditLog::with_path(
+            std::env::temp_dir().join(format!("shabka-web-audit-{}.jsonl", Uuid::now_v7())),
+            &config.audit,
+        );
         Arc::new(AppState {
-            storage,
-            embedding,
+            storage: Arc::new(storage),
+            embedding: Arc::new(embedding),
             config,
             user_id: "test-user".to_string(),
-            history: HistoryLogger::new(false),
+            history: Arc::new(history),
+            questions: Arc::new(questions),
             llm: None,
+            blobs: Arc::new(blobs),
+            audit: Arc::new(audit),
+            tenants: None,
         })
     }
 
@@ -1347,16 +1949,169 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_timeline() {
+    async fn test_retriever() {
         let app = test_router();
-        let req = Request::builder()
-            .uri("/api/v1/timeline")
-            .body(Body::empty())
+        for (title, content) in [
+            (
+                "Retry budget",
+                "Cap retries at three; upstream rate-limits after that",
+            ),
+            ("Logging format", "Logs are JSON lines with a request ID"),
+        ] {
+            let req = Request::builder()
+                .method("POST")
+                .uri("/api/v1/memories")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({"title": title, "content": content, "kind": "decision", "tags": ["ops"]})
+                        .to_string(),
+                ))
+                .unwrap();
+            app.clone().oneshot(req).await.unwrap();
+        }
+
+        let retrieve = |body: serde_json::Value| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/retriever")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let resp = app
+            .clone()
+            .oneshot(retrieve(serde_json::json!({"query": "retries", "k": 1})))
+            .await
             .unwrap();
-        let resp = app.oneshot(req).await.unwrap();
         assert_eq!(resp.status(), StatusCode::OK);
         let json = body_json(resp.into_body()).await;
-        assert!(json.is_array());
+        let docs = json["documents"].as_array().unwrap();
+        assert_eq!(docs.len(), 1);
+        assert!(docs[0]["page_content"]
+            .as_str()
+            .unwrap()
+            .starts_with("Cap retries"));
+        assert_eq!(docs[0]["metadata"]["title"], "Retry budget");
+        assert_eq!(docs[0]["metadata"]["kind"], "decision");
+        assert_eq!(docs[0]["metadata"]["tags"][0], "ops");
+        assert_eq!(docs[0]["metadata"]["id"], docs[0]["id"]);
+
+        let resp = app
+            .oneshot(retrieve(serde_json::json!({"query": "retries", "k": 0})))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_openai_embeddings() {
+        let state = test_app_state();
+        let app = crate::routes::router().with_state(state.clone());
+        let embed = |body: serde_json::Value| {
+            Request::builder()
+                .method("POST")
+                .uri("/v1/embeddings")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let resp = app
+            .clone()
+            .oneshot(embed(serde_json::json!({
+                "model": "text-embedding-3-small",
+                "input": ["first text", "second text"]
+            })))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp.into_body()).await;
+        assert_eq!(json["object"], "list");
+        assert_eq!(json["model"], state.embedding.model_id());
+        assert_eq!(json["data"][1]["index"], 1);
+        let vector: Vec<f32> =
+            serde_json::from_value(json["data"][0]["embedding"].clone()).unwrap();
+        assert_eq!(vector, state.embedding.embed("first text").await.unwrap());
+        assert!(json["usage"]["prompt_tokens"].as_u64().unwrap() > 0);
+
+        let resp = app
+            .clone()
+            .oneshot(embed(serde_json::json!({
+                "input": "first text",
+                "encoding_format": "base64"
+            })))
+            .await
+            .unwrap();
+        let json = body_json(resp.into_body()).await;
+        let encoded = json["data"][0]["embedding"].as_str().unwrap();
+        assert_eq!(
+            base64::Engine::decode(&base64::engine::general_purpose::STANDARD, encoded)
+                .unwrap()
+                .len(),
+            state.embedding.dimensions() * 4
+        );
+
+        let resp = app
+            .oneshot(embed(serde_json::json!({"input": "x", "dimensions": 3})))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_search_field_operators() {
+        let state = test_app_state();
+        let app = crate::routes::router().with_state(state);
+
+        let create_body = serde_json::json!({
+            "title": "Pool exhaustion",
+            "content": "Connection pool ran dry under load",
+            "kind": "error"
+        });
+        let req = Request::builder()
+            .method("POST")
+            .uri("/api/v1/memories")
+            .header("content-type", "application/json")
+            .body(Body::from(create_body.to_string()))
+            .unwrap();
+        app.clone().oneshot(req).await.unwrap();
+
+        let req = Request::builder()
+            .uri("/api/v1/search?q=kind%3Aerror")
+            .body(Body::empty())
+            .unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp.into_body()).await;
+        assert_eq!(json.as_array().unwrap().len(), 1);
+
+        let req = Request::builder()
+            .uri("/api/v1/search?q=kind%3Afix%20pool")
+            .body(Body::empty())
+            .unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        let json = body_json(resp.into_body()).await;
+        assert!(json.as_array().unwrap().is_empty());
+
+        let req = Request::builder()
+            .uri("/api/v1/search?q=after%3Asomeday")
+            .body(Body::empty())
+            .unwrap();
+        let resp = app.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_timeline() {
+        let app = test_router();
+        let req = Request::builder()
+            .uri("/api/v1/timeline")
+            .body(Body::empty())
+            .unwrap();
+        let resp = app.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp.into_body()).await;
+        assert!(json.is_array());
     }
 
     #[tokio::test]
@@ -1556,6 +2311,59 @@ mod tests {
             .unwrap();
         let resp = app.oneshot(req).await.unwrap();
         assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp.into_body()).await;
+        assert_eq!(json["storage"]["backend"], "memory");
+        assert_eq!(json["storage"]["capabilities"]["integrity_check"], true);
+        assert_eq!(json["storage"]["unavailable"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_download_attachment() {
+        let state = test_app_state();
+        let memory = Memory::new(
+            "Crash".into(),
+            "trace attached".into(),
+            MemoryKind::Error,
+            "test-user".into(),
+        );
+        state.storage.save_memory(&memory, None).await.unwrap();
+        let memory = shabka_core::attachments::attach(
+            state.storage.as_ref(),
+            &state.blobs,
+            &state.config.attachments,
+            memory.id,
+            "trace.log",
+            b"panicked",
+        )
+        .await
+        .unwrap();
+        let sha = &memory.attachments[0].sha256;
+        let app = crate::routes::router().with_state(state.clone());
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/memories/{}/attachments/{sha}", memory.id))
+            .body(Body::empty())
+            .unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()["content-disposition"],
+            "attachment; filename=\"trace.log\""
+        );
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"panicked");
+
+        let req = Request::builder()
+            .uri(format!(
+                "/api/v1/memories/{}/attachments/{}",
+                memory.id,
+                "0".repeat(64)
+            ))
+            .body(Body::empty())
+            .unwrap();
+        let resp = app.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let _ = std::fs::remove_dir_all(state.blobs.path());
     }
 
     #[tokio::test]
@@ -1655,6 +2463,38 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_show_memory_page_with_code() {
+        let state = test_app_state();
+        let code = shabka_core::model::CodeSnippet::from_edit(
+            "src/pool.rs",
+            "fn max_size() -> u32 { 16 }",
+        )
+        .unwrap();
+        let mem = shabka_core::model::Memory::new(
+            "Raise pool size".to_string(),
+            "Bumped the pool".to_string(),
+            shabka_core::model::MemoryKind::Decision,
+            "test-user".to_string(),
+        )
+        .with_code(code);
+        let id = mem.id;
+        state.storage.save_memory(&mem, None).await.unwrap();
+
+        let app = crate::routes::router().with_state(state);
+        let req = Request::builder()
+            .uri(format!("/memories/{id}"))
+            .body(Body::empty())
+            .unwrap();
+        let resp = app.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let html = String::from_utf8_lossy(&bytes);
+        assert!(html.contains(r#"class="language-rust""#));
+        assert!(html.contains("max_size"));
+        assert!(html.contains("src/pool.rs"));
+    }
+
     #[tokio::test]
     async fn test_graph_data_json() {
         let app = test_router();
@@ -1736,6 +2576,110 @@ mod tests {
         assert!(json.is_array());
     }
 
+    #[tokio::test]
+    async fn test_api_history_filters() {
+        let path =
+            std::env::temp_dir().join(format!("shabka-web-history-{}.jsonl", Uuid::now_v7()));
+        let history = HistoryLogger::with_path(path.clone(), true);
+        let mut mem = shabka_core::model::Memory::new(
+            "Filtered".to_string(),
+            "Content".to_string(),
+            shabka_core::model::MemoryKind::Fact,
+            "test-user".to_string(),
+        );
+        mem.project_id = Some("api".to_string());
+        let mut old = MemoryEvent::new(mem.id, EventAction::Created, "bot".to_string());
+        old.timestamp = chrono::Utc::now() - chrono::Duration::days(30);
+        history.log(&old);
+        history.log(&MemoryEvent::new(
+            mem.id,
+            EventAction::Updated,
+            "bot".to_string(),
+        ));
+        history.log(&MemoryEvent::new(
+            Uuid::now_v7(),
+            EventAction::Updated,
+            "alice".to_string(),
+        ));
+        let state = test_app_state_with_history(history);
+        state.storage.save_memory(&mem, None).await.unwrap();
+        let app = crate::routes::router().with_state(state);
+
+        let get = |uri: &'static str| {
+            let app = app.clone();
+            async move {
+                let resp = app
+                    .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                (resp.status(), body_json(resp.into_body()).await)
+            }
+        };
+        let (status, json) = get("/api/v1/history?actor=BOT&since=7d").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json.as_array().unwrap().len(), 1);
+        assert_eq!(json[0]["action"], "updated");
+
+        let (_, json) = get("/api/v1/history?project=api&action=created,updated").await;
+        assert_eq!(json.as_array().unwrap().len(), 2);
+
+        let (_, json) = get("/api/v1/history?limit=1").await;
+        assert_eq!(json[0]["actor"], "alice");
+
+        let (status, _) = get("/api/v1/history?action=exploded").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = get("/api/v1/history?since=someday").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_api_endorse() {
+        let state = test_app_state();
+        let mine = shabka_core::model::Memory::new(
+            "Mine".to_string(),
+            "Content".to_string(),
+            shabka_core::model::MemoryKind::Pattern,
+            "test-user".to_string(),
+        );
+        let theirs = shabka_core::model::Memory::new(
+            "Theirs".to_string(),
+            "Content".to_string(),
+            shabka_core::model::MemoryKind::Pattern,
+            "alice".to_string(),
+        );
+        state.storage.save_memory(&mine, None).await.unwrap();
+        state.storage.save_memory(&theirs, None).await.unwrap();
+        let app = crate::routes::router().with_state(state);
+
+        let send = |method: &'static str, id: Uuid| {
+            let app = app.clone();
+            async move {
+                let resp = app
+                    .oneshot(
+                        Request::builder()
+                            .method(method)
+                            .uri(format!("/api/v1/memories/{id}/endorse"))
+                            .body(Body::empty())
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                (resp.status(), body_json(resp.into_body()).await)
+            }
+        };
+        let (status, json) = send("POST", theirs.id).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["endorsements"][0]["endorsed_by"], "test-user");
+
+        let (status, _) = send("POST", mine.id).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, json) = send("DELETE", theirs.id).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(json.get("endorsements").is_none());
+    }
+
     #[tokio::test]
     async fn test_bulk_archive_empty() {
         let app = test_router();
@@ -1837,6 +2781,80 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_bulk_tag_and_verify() {
+        let state = test_app_state();
+        let app = crate::routes::router().with_state(state.clone());
+
+        let body = serde_json::json!({
+            "title": "Bulk tag target",
+            "content": "Unique content for the bulk tag and verify test",
+            "kind": "fact",
+            "tags": ["old", "keep"]
+        });
+        let req = Request::builder()
+            .method("POST")
+            .uri("/api/v1/memories")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        let id = body_json(resp.into_body()).await["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let post = |uri: &str, body: serde_json::Value| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let resp = app
+            .clone()
+            .oneshot(post(
+                "/api/v1/memories/bulk/tag",
+                serde_json::json!({ "ids": [id, "not-a-uuid"], "add": ["new", " "], "remove": ["OLD"] }),
+            ))
+            .await
+            .unwrap();
+        let json = body_json(resp.into_body()).await;
+        assert_eq!(json["processed"], 1);
+        assert_eq!(json["errors"], 1);
+
+        let resp = app
+            .clone()
+            .oneshot(post(
+                "/api/v1/memories/bulk/verify",
+                serde_json::json!({ "ids": [id], "verification": "verified" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(body_json(resp.into_body()).await["processed"], 1);
+
+        let resp = app
+            .clone()
+            .oneshot(post(
+                "/api/v1/memories/bulk/verify",
+                serde_json::json!({ "ids": [id], "verification": "bogus" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let memory = state
+            .storage
+            .get_memory(Uuid::parse_str(&id).unwrap())
+            .await
+            .unwrap();
+        assert!(memory.tags.contains(&"new".to_string()));
+        assert!(memory.tags.contains(&"keep".to_string()));
+        assert!(!memory.tags.contains(&"old".to_string()));
+        assert_eq!(memory.verification, VerificationStatus::Verified);
+    }
+
     #[tokio::test]
     async fn test_bulk_archive_invalid_ids() {
         let app = test_router();
@@ -2045,4 +3063,365 @@ mod tests {
             .expect("Expected hx-redirect header");
         assert!(redirect.to_str().unwrap().contains("toast=Memory"));
     }
+
+    #[tokio::test]
+    async fn test_permalink_page() {
+        let state = test_app_state();
+        let app = crate::routes::router().with_state(state.clone());
+
+        let mut shared = Memory::new(
+            "Pool sizing rule".to_string(),
+            "Keep the **connection pool** at twice the core count.".to_string(),
+            MemoryKind::Decision,
+            "test-user".to_string(),
+        );
+        let private = Memory::new(
+            "Someone else's note".to_string(),
+            "Not for sharing".to_string(),
+            MemoryKind::Observation,
+            "other-user".to_string(),
+        )
+        .with_privacy(MemoryPrivacy::Private);
+        shared.id = Uuid::parse_str("0aaaaaaa-0000-7000-8000-000000000001").unwrap();
+        for memory in [&shared, &private] {
+            state.storage.save_memory(memory, None).await.unwrap();
+        }
+
+        let get = |uri: String| {
+            Request::builder()
+                .uri(uri)
+                .header("host", "shabka.example")
+                .body(Body::empty())
+                .unwrap()
+        };
+        let short = shared.id.to_string()[..8].to_string();
+        let resp = app
+            .clone()
+            .oneshot(get(format!("/m/{short}")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let html = String::from_utf8(bytes.to_vec()).unwrap();
+        assert!(html.contains(r#"<meta property="og:title" content="Pool sizing rule">"#));
+        assert!(html.contains("decision · Keep the **connection pool**"));
+        assert!(html.contains(&format!("http://shabka.example/m/{short}")));
+
+        let resp = app
+            .clone()
+            .oneshot(get(format!("/m/{}", private.id)))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_longest_gap() {
+        let d = |day| chrono::NaiveDate::from_ymd_opt(2026, 3, day).unwrap();
+        assert_eq!(longest_gap(d(1), d(10), std::iter::empty()), 10);
+        assert_eq!(longest_gap(d(1), d(10), [d(1), d(10)].into_iter()), 8);
+        assert_eq!(longest_gap(d(1), d(10), [d(4), d(6)].into_iter()), 4);
+    }
+
+    #[tokio::test]
+    async fn test_activity_endpoint() {
+        let state = test_app_state();
+        let app = crate::routes::router().with_state(state.clone());
+        let mut memory = Memory::new(
+            "Captured today".to_string(),
+            "Content".to_string(),
+            MemoryKind::Fact,
+            "test-user".to_string(),
+        );
+        memory.project_id = Some("shabka".to_string());
+        state.storage.save_memory(&memory, None).await.unwrap();
+
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let resp = app.clone().oneshot(get("/api/v1/activity")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp.into_body()).await;
+        assert_eq!(json["total"], 1);
+        assert_eq!(json["projects"][0]["project"], "shabka");
+        assert_eq!(json["projects"][0]["last_day"], json["until"]);
+        assert_eq!(json["projects"][0]["longest_gap_days"], 364);
+
+        let resp = app
+            .clone()
+            .oneshot(get("/api/v1/activity?project=other"))
+            .await
+            .unwrap();
+        assert_eq!(body_json(resp.into_body()).await["total"], 0);
+
+        let resp = app.oneshot(get("/api/v1/activity?days=0")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_tag_map_endpoint() {
+        let state = test_app_state();
+        let app = crate::routes::router().with_state(state.clone());
+        for tags in [vec!["auth", "jwt"], vec!["Auth", "jwt"], vec!["css"]] {
+            let memory = Memory::new(
+                "Tagged".to_string(),
+                "Content".to_string(),
+                MemoryKind::Fact,
+                "test-user".to_string(),
+            )
+            .with_tags(tags.into_iter().map(String::from).collect());
+            state.storage.save_memory(&memory, None).await.unwrap();
+        }
+
+        let req = Request::builder()
+            .uri("/api/v1/tags/map")
+            .body(Body::empty())
+            .unwrap();
+        let json = body_json(app.oneshot(req).await.unwrap().into_body()).await;
+        assert_eq!(json["tags"][0]["tag"], "auth");
+        assert_eq!(json["tags"][0]["count"], 2);
+        assert_eq!(json["pairs"][0]["count"], 2);
+        assert_eq!(
+            json["clusters"][0]["tags"],
+            serde_json::json!(["auth", "jwt"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_notifications_endpoint() {
+        let state = test_app_state();
+        let app = crate::routes::router().with_state(state.clone());
+        let mut memory = Memory::new(
+            "Auto-captured".to_string(),
+            "Content".to_string(),
+            MemoryKind::Observation,
+            "test-user".to_string(),
+        );
+        memory.status = MemoryStatus::Pending;
+        state.storage.save_memory(&memory, None).await.unwrap();
+
+        let req = Request::builder()
+            .uri("/api/v1/notifications")
+            .body(Body::empty())
+            .unwrap();
+        let json = body_json(app.clone().oneshot(req).await.unwrap().into_body()).await;
+        assert_eq!(json["total"], 1);
+        assert_eq!(json["counts"]["review"], 1);
+        assert_eq!(json["items"][0]["memory_ids"][0], memory.id.to_string());
+
+        let req = Request::builder()
+            .uri("/notifications")
+            .body(Body::empty())
+            .unwrap();
+        let resp = app.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        assert!(String::from_utf8_lossy(&bytes).contains("Auto-captured"));
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_audited() {
+        let mut state = Arc::into_inner(test_app_state()).unwrap();
+        let path = std::env::temp_dir().join(format!("shabka-web-audit-{}.jsonl", Uuid::now_v7()));
+        state.audit = Arc::new(AuditLog::with_path(
+            path.clone(),
+            &AuditConfig {
+                enabled: true,
+                ..Default::default()
+            },
+        ));
+        let state = Arc::new(state);
+        let app = crate::routes::router().with_state(state.clone()).layer(
+            axum::middleware::from_fn_with_state(state.clone(), crate::audit::record),
+        );
+        for uri in [
+            "/api/v1/memories?limit=5&token=shabka_secret",
+            "/api/v1/memories/not-a-uuid",
+            "/health",
+        ] {
+            let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            app.clone().oneshot(req).await.unwrap();
+        }
+
+        let entries = state.audit.tail(&AuditFilter::default(), 10);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(entries.len(), 2, "health checks aren't audited");
+        assert_eq!(entries[0].endpoint, "GET /api/v1/memories");
+        assert_eq!(entries[0].params, "limit=5");
+        assert_eq!(entries[0].outcome, "200");
+        assert!(entries[0].success);
+        assert!(!entries[1].success);
+    }
+
+    #[tokio::test]
+    async fn test_require_token() {
+        use shabka_core::api_tokens::{self, TokenScope};
+
+        let path =
+            std::env::temp_dir().join(format!("shabka-web-history-{}.jsonl", Uuid::now_v7()));
+        let mut state = Arc::into_inner(test_app_state_with_history(HistoryLogger::with_path(
+            path.clone(),
+            true,
+        )))
+        .unwrap();
+        state.config.web.require_token = true;
+        let state = Arc::new(state);
+        let app = crate::routes::router().with_state(state.clone()).layer(
+            axum::middleware::from_fn_with_state(state.clone(), crate::auth::require_token),
+        );
+        let reader = api_tokens::create(&state.storage, "reader", TokenScope::Read, None, None)
+            .await
+            .unwrap();
+        let writer = api_tokens::create(&state.storage, "writer", TokenScope::Write, None, None)
+            .await
+            .unwrap();
+        let create = |auth: Option<&str>| {
+            let mut req = Request::builder()
+                .method("POST")
+                .uri("/api/v1/memories")
+                .header("content-type", "application/json");
+            if let Some(secret) = auth {
+                req = req.header("authorization", format!("Bearer {secret}"));
+            }
+            req.body(Body::from(
+                serde_json::json!({"title": "T", "content": "C", "kind": "fact"}).to_string(),
+            ))
+            .unwrap()
+        };
+
+        let req = Request::builder()
+            .uri("/health")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(
+            app.clone().oneshot(req).await.unwrap().status(),
+            StatusCode::OK
+        );
+        let req = Request::builder()
+            .uri("/api/v1/memories")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(
+            app.clone().oneshot(req).await.unwrap().status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let resp = app
+            .clone()
+            .oneshot(create(Some("shabka_bogus")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        // A read token can browse but not write; `?token=` sets the cookie.
+        let req = Request::builder()
+            .uri(format!("/api/v1/memories?token={}", reader.secret))
+            .body(Body::empty())
+            .unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let cookie = resp.headers()["set-cookie"].to_str().unwrap().to_string();
+        assert!(cookie.starts_with(&format!("shabka_token={}", reader.secret)));
+        let req = Request::builder()
+            .uri("/api/v1/memories")
+            .header("cookie", format!("shabka_token={}", reader.secret))
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(
+            app.clone().oneshot(req).await.unwrap().status(),
+            StatusCode::OK
+        );
+        let resp = app
+            .clone()
+            .oneshot(create(Some(&reader.secret)))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let req = Request::builder()
+            .method("POST")
+            .uri("/api/retriever")
+            .header("content-type", "application/json")
+            .header("authorization", format!("Bearer {}", reader.secret))
+            .body(Body::from(r#"{"query": "anything"}"#))
+            .unwrap();
+        assert_eq!(
+            app.clone().oneshot(req).await.unwrap().status(),
+            StatusCode::OK
+        );
+
+        // A write token can; history names the token.
+        let resp = app
+            .clone()
+            .oneshot(create(Some(&writer.secret)))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let events = state.history.recent(1);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(events[0].token.as_deref(), Some("writer"));
+    }
+
+    #[tokio::test]
+    async fn test_tenant_tokens_are_isolated() {
+        use crate::tenant::TenantStates;
+        use shabka_core::api_tokens::{self, TokenScope};
+        use shabka_core::services::Services;
+        use shabka_core::tenants::Tenants;
+
+        let root = std::env::temp_dir().join(format!("shabka-web-tenants-{}", Uuid::now_v7()));
+        let mut config = ShabkaConfig::default_config();
+        config.history.enabled = false;
+        config.web.require_token = true;
+        config.tenants.enabled = true;
+        let services = Services::with_storage(
+            config,
+            Storage::Sqlite(SqliteStorage::open_in_memory().unwrap()),
+        );
+        let tenants = Arc::new(Tenants::at(services.clone(), &root));
+        let state = Arc::new(
+            AppState::from_services(&services, Some(Arc::new(TenantStates::new(tenants)))).unwrap(),
+        );
+        let app = crate::routes::router().with_state(state.clone()).layer(
+            axum::middleware::from_fn_with_state(state.clone(), crate::auth::require_token),
+        );
+        let mut secrets = Vec::new();
+        for (name, tenant) in [
+            ("acme", Some("acme")),
+            ("globex", Some("globex")),
+            ("ops", None),
+        ] {
+            let token = api_tokens::create(&state.storage, name, TokenScope::Write, None, tenant)
+                .await
+                .unwrap();
+            secrets.push(token.secret);
+        }
+        let (acme, globex, ops) = (&secrets[0], &secrets[1], &secrets[2]);
+        let request = |method: &str, secret: &str, body: Body| {
+            Request::builder()
+                .method(method)
+                .uri("/api/v1/memories")
+                .header("content-type", "application/json")
+                .header("authorization", format!("Bearer {secret}"))
+                .body(body)
+                .unwrap()
+        };
+        let list = |secret: &str| {
+            let app = app.clone();
+            let req = request("GET", secret, Body::empty());
+            async move {
+                let resp = app.oneshot(req).await.unwrap();
+                body_json(resp.into_body()).await.as_array().unwrap().len()
+            }
+        };
+
+        let body = serde_json::json!({"title": "Acme plan", "content": "C", "kind": "fact"});
+        let resp = app
+            .clone()
+            .oneshot(request("POST", acme, Body::from(body.to_string())))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(list(acme).await, 1);
+        assert_eq!(list(globex).await, 0);
+        assert_eq!(list(ops).await, 0);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
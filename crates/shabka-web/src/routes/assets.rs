@@ -0,0 +1,176 @@
+//! Static assets compiled into the binary.
+//!
+//! Everything under `assets/` is embedded at build time, so the dashboard
+//! runs on machines without internet access. Third-party libraries are
+//! committed to `assets/vendor/` under versioned file names and cached as
+//! immutable; `build.rs` checks them against the hashes in `vendor.txt`. A
+//! library that wasn't vendored when the binary was built redirects to its
+//! pinned CDN URL.
+
+use axum::extract::Path;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::get;
+use axum::Router;
+use rust_embed::RustEmbed;
+
+#[derive(RustEmbed)]
+#[folder = "assets/"]
+struct Assets;
+
+const VENDOR_LIST: &str = include_str!("../../vendor.txt");
+
+/// Versioned vendor files never change, so browsers may keep them forever.
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// Anything else is revalidated against its ETag.
+const REVALIDATE: &str = "no-cache";
+
+pub fn routes<S: Clone + Send + Sync + 'static>() -> Router<S> {
+    Router::new().route("/assets/{*path}", get(asset))
+}
+
+/// Upstream URL for a vendored file, from `vendor.txt`.
+fn cdn_url(path: &str) -> Option<&'static str> {
+    let name = path.strip_prefix("vendor/")?;
+    VENDOR_LIST
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .find(|fields| fields.first() == Some(&name))
+        .and_then(|fields| fields.get(2).copied())
+}
+
+async fn asset(Path(path): Path<String>, headers: HeaderMap) -> Response {
+    let Some(file) = Assets::get(&path) else {
+        return match cdn_url(&path) {
+            Some(url) => Redirect::temporary(url).into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        };
+    };
+
+    let etag = format!(
+        "\"{}\"",
+        file.metadata
+            .sha256_hash()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>()
+    );
+    let cache = if path.starts_with("vendor/") {
+        IMMUTABLE
+    } else {
+        REVALIDATE
+    };
+    if headers
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|v| v.as_bytes() == etag.as_bytes())
+    {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [
+                (header::ETAG, etag),
+                (header::CACHE_CONTROL, cache.to_string()),
+            ],
+        )
+            .into_response();
+    }
+
+    (
+        [
+            (header::CONTENT_TYPE, file.metadata.mimetype().to_string()),
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, cache.to_string()),
+        ],
+        file.data,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use sha2::{Digest, Sha256};
+    use tower::ServiceExt;
+
+    async fn get(uri: &str, etag: Option<&str>) -> Response {
+        let mut request = Request::builder().uri(uri);
+        if let Some(etag) = etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        routes::<()>()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    /// `(file name, pinned SHA-256)` for each line of `vendor.txt`.
+    fn vendored() -> Vec<(&'static str, &'static str)> {
+        VENDOR_LIST
+            .lines()
+            .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                Some((fields.next()?, fields.next()?))
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_vendor_assets_are_served_or_redirected() {
+        for (name, pinned) in vendored() {
+            let uri = format!("/assets/vendor/{name}");
+            let resp = get(&uri, None).await;
+            if resp.status() == StatusCode::TEMPORARY_REDIRECT {
+                let url = cdn_url(&format!("vendor/{name}")).unwrap();
+                assert_eq!(resp.headers()[header::LOCATION], url, "{name}");
+                continue;
+            }
+            assert_eq!(resp.status(), StatusCode::OK, "{name}");
+            assert_eq!(resp.headers()[header::CACHE_CONTROL], IMMUTABLE);
+            let content_type = resp.headers()[header::CONTENT_TYPE].to_str().unwrap();
+            let expected = if name.ends_with(".css") {
+                "text/css"
+            } else {
+                "javascript"
+            };
+            assert!(content_type.contains(expected), "{name}: {content_type}");
+            let etag = resp.headers()[header::ETAG].to_str().unwrap().to_string();
+
+            let body = resp.into_body().collect().await.unwrap().to_bytes();
+            let hash: String = Sha256::digest(&body)
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect();
+            if pinned != "-" {
+                assert_eq!(hash, pinned, "{name} body differs from vendor.txt");
+            }
+
+            let again = get(&uri, Some(&etag)).await;
+            assert_eq!(again.status(), StatusCode::NOT_MODIFIED);
+        }
+
+        let missing = get("/assets/vendor/nope.js", None).await;
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_every_template_asset_is_listed() {
+        let names: Vec<&str> = vendored().into_iter().map(|(name, _)| name).collect();
+        for name in [
+            "htmx-2.0.4.min.js",
+            "chart-4.4.7.umd.min.js",
+            "cytoscape-3.30.4.min.js",
+            "marked-15.0.4.min.js",
+            "highlight-11.10.0.min.js",
+            "highlight-github-dark-11.10.0.min.css",
+        ] {
+            assert!(names.contains(&name), "{name}");
+            assert!(cdn_url(&format!("vendor/{name}")).is_some(), "{name}");
+        }
+        assert!(cdn_url("app.css").is_none());
+    }
+}
@@ -0,0 +1,148 @@
+//! OpenAI-compatible `POST /v1/embeddings`.
+//!
+//! Serves the configured [`EmbeddingService`](shabka_core::embedding::EmbeddingService)
+//! so other local tools can point an OpenAI client at Shabka and get the
+//! same vectors, from the same provider and credentials, that Shabka stores.
+//! The request's `model` is accepted but not used to pick a provider; the
+//! response names the model that actually produced the vectors.
+
+use std::sync::Arc;
+
+use axum::response::Json;
+use axum::routing::post;
+use axum::Router;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use shabka_core::tokens::estimate_tokens;
+
+use crate::error::ApiError;
+use crate::tenant::Tenant;
+use crate::AppState;
+
+/// Most inputs one request may embed, as with OpenAI.
+const MAX_INPUTS: usize = 2048;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/v1/embeddings", post(embeddings))
+}
+
+/// A single string or a batch of strings. Token-ID inputs are not supported.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmbeddingRequest {
+    pub input: EmbeddingInput,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// `float` (default) or `base64` (little-endian f32).
+    #[serde(default)]
+    pub encoding_format: Option<String>,
+    /// Must match the provider's dimensions when given.
+    #[serde(default)]
+    pub dimensions: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmbeddingResponse {
+    pub object: &'static str,
+    pub data: Vec<EmbeddingData>,
+    pub model: String,
+    pub usage: EmbeddingUsage,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmbeddingData {
+    pub object: &'static str,
+    pub index: usize,
+    pub embedding: EmbeddingVector,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum EmbeddingVector {
+    Float(Vec<f32>),
+    Base64(String),
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmbeddingUsage {
+    pub prompt_tokens: usize,
+    pub total_tokens: usize,
+}
+
+async fn embeddings(
+    Tenant(state): Tenant,
+    Json(req): Json<EmbeddingRequest>,
+) -> Result<Json<EmbeddingResponse>, ApiError> {
+    let inputs = match req.input {
+        EmbeddingInput::One(text) => vec![text],
+        EmbeddingInput::Many(texts) => texts,
+    };
+    if inputs.is_empty() || inputs.len() > MAX_INPUTS {
+        return Err(ApiError::bad_request(format!(
+            "input must hold between 1 and {MAX_INPUTS} strings"
+        )));
+    }
+    let base64 = match req.encoding_format.as_deref() {
+        None | Some("float") => false,
+        Some("base64") => true,
+        Some(other) => {
+            return Err(ApiError::bad_request(format!(
+                "unsupported encoding_format '{other}' (expected 'float' or 'base64')"
+            )))
+        }
+    };
+    let dimensions = state.embedding.dimensions();
+    if req.dimensions.is_some_and(|d| d != dimensions) {
+        return Err(ApiError::bad_request(format!(
+            "model '{}' produces {dimensions}-dimensional embeddings",
+            state.embedding.model_id()
+        )));
+    }
+    if let Some(model) = req.model.as_deref() {
+        if model != state.embedding.model_id() {
+            tracing::debug!(
+                "embeddings: requested model '{model}', serving '{}'",
+                state.embedding.model_id()
+            );
+        }
+    }
+
+    let texts: Vec<&str> = inputs.iter().map(String::as_str).collect();
+    let vectors = state
+        .embedding
+        .embed_batch(&texts)
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))?;
+
+    let prompt_tokens = texts.iter().map(|t| estimate_tokens(t)).sum();
+    let data = vectors
+        .into_iter()
+        .enumerate()
+        .map(|(index, vector)| EmbeddingData {
+            object: "embedding",
+            index,
+            embedding: if base64 {
+                let bytes: Vec<u8> = vector.iter().flat_map(|x| x.to_le_bytes()).collect();
+                EmbeddingVector::Base64(base64::engine::general_purpose::STANDARD.encode(bytes))
+            } else {
+                EmbeddingVector::Float(vector)
+            },
+        })
+        .collect();
+
+    Ok(Json(EmbeddingResponse {
+        object: "list",
+        data,
+        model: state.embedding.model_id().to_string(),
+        usage: EmbeddingUsage {
+            prompt_tokens,
+            total_tokens: prompt_tokens,
+        },
+    }))
+}
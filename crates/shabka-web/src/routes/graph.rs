@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use askama::Template;
-use axum::extract::{Path, Query, State};
+use axum::extract::{Path, Query};
 use axum::response::{Html, Json};
 use axum::routing::get;
 use axum::Router;
@@ -11,12 +11,14 @@ use shabka_core::storage::StorageBackend;
 use uuid::Uuid;
 
 use crate::error::AppError;
+use crate::tenant::Tenant;
 use crate::AppState;
 
 pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/graph", get(graph_page))
         .route("/graph/data", get(graph_data))
+        .route("/graph/tags", get(tag_map_page))
         .route("/api/memories/{id}", get(memory_json))
         .route("/api/memories/{id}/chain", get(memory_chain))
 }
@@ -30,6 +32,16 @@ async fn graph_page() -> Result<Html<String>, AppError> {
     Ok(Html(tmpl.render()?))
 }
 
+#[derive(Template)]
+#[template(path = "tag_map.html")]
+struct TagMapTemplate;
+
+/// Tag co-occurrence network and cluster/project table; the data comes
+/// from `/api/v1/tags/map`.
+async fn tag_map_page() -> Result<Html<String>, AppError> {
+    Ok(Html(TagMapTemplate.render()?))
+}
+
 // -- JSON API for graph data --
 
 #[derive(Serialize)]
@@ -68,7 +80,7 @@ struct MemoryDetail {
     updated_at: String,
 }
 
-async fn graph_data(State(state): State<Arc<AppState>>) -> Result<Json<GraphData>, AppError> {
+async fn graph_data(Tenant(state): Tenant) -> Result<Json<GraphData>, AppError> {
     let query = TimelineQuery {
         limit: 2000,
         ..Default::default()
@@ -116,7 +128,7 @@ async fn graph_data(State(state): State<Arc<AppState>>) -> Result<Json<GraphData
 }
 
 async fn memory_json(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Path(id): Path<Uuid>,
 ) -> Result<Json<MemoryDetail>, AppError> {
     let memory = state.storage.get_memory(id).await?;
@@ -139,6 +151,7 @@ async fn memory_json(
 struct ChainQueryParams {
     depth: Option<usize>,
     relation: Option<String>,
+    max_nodes: Option<usize>,
 }
 
 #[derive(Serialize)]
@@ -149,7 +162,7 @@ struct ChainData {
 }
 
 async fn memory_chain(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Path(id): Path<Uuid>,
     Query(params): Query<ChainQueryParams>,
 ) -> Result<Json<ChainData>, AppError> {
@@ -171,8 +184,18 @@ async fn memory_chain(
         ]
     };
 
-    let chain_links =
-        shabka_core::graph::follow_chain(&state.storage, id, &relation_types, Some(depth)).await;
+    let max_nodes = params
+        .max_nodes
+        .unwrap_or(state.config.graph.max_chain_nodes);
+    let chain_links = shabka_core::graph::follow_chain(
+        state.storage.as_ref(),
+        id,
+        &relation_types,
+        Some(depth),
+        Some(max_nodes),
+    )
+    .await
+    .links;
 
     // Collect all memory IDs we need to fetch (center + chain neighbors)
     let mut all_ids: Vec<Uuid> = chain_links.iter().map(|l| l.memory_id).collect();
@@ -196,6 +219,7 @@ async fn memory_chain(
 
     let nodes: Vec<GraphNode> = chain_links
         .iter()
+        .filter(|link| !link.cycle)
         .filter_map(|link| {
             memories
                 .iter()
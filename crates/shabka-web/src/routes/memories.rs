@@ -2,8 +2,9 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use askama::Template;
-use axum::extract::{Path, State};
-use axum::response::{Html, Redirect};
+use axum::extract::Path;
+use axum::http::{header, HeaderMap};
+use axum::response::{Html, IntoResponse, Redirect, Response};
 use axum::routing::{get, post};
 use axum::{Form, Router};
 use chrono::Utc;
@@ -19,6 +20,7 @@ use shabka_core::model::RelationType;
 use shabka_core::trust::trust_score;
 
 use crate::error::AppError;
+use crate::tenant::Tenant;
 use crate::AppState;
 
 /// Returns the stale threshold from config (or default 90).
@@ -35,8 +37,17 @@ pub fn routes() -> Router<Arc<AppState>> {
         .route("/memories/{id}/edit", get(edit_memory_form))
         .route("/memories/{id}/update", post(update_memory))
         .route("/memories/{id}/delete", post(delete_memory))
+        .route("/m/{short_id}", get(permalink))
 }
 
+/// ID prefix lengths tried for `/m/<short-id>` permalinks, shortest first.
+/// UUIDv7 prefixes are timestamps, so memories created close together need
+/// more characters to tell apart.
+const SHORT_ID_LENS: [usize; 3] = [8, 13, 18];
+
+/// Longest OpenGraph description, in characters.
+const OG_DESCRIPTION_CHARS: usize = 200;
+
 // -- Templates --
 
 #[derive(Template)]
@@ -71,6 +82,19 @@ struct MemoryDetailTemplate {
     similar_memories: Vec<SimilarMemoryEntry>,
     trust_pct: u8,
     verification_class: String,
+    short_id: String,
+    /// Someone other than the creator is viewing, so they may endorse it.
+    can_endorse: bool,
+    endorsed: bool,
+}
+
+#[derive(Template)]
+#[template(path = "memories/permalink.html")]
+struct PermalinkTemplate {
+    memory: Memory,
+    url: String,
+    description: String,
+    markdown: String,
 }
 
 struct SimilarMemoryEntry {
@@ -121,7 +145,7 @@ pub struct MemoryFormInput {
 // -- Handlers --
 
 async fn list_memories(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     axum::extract::Query(params): axum::extract::Query<ListParams>,
 ) -> Result<Html<String>, AppError> {
     // Build a DB-level query with all filters pushed down
@@ -218,7 +242,7 @@ async fn list_memories(
 }
 
 async fn show_memory(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Path(id): Path<Uuid>,
 ) -> Result<Html<String>, AppError> {
     let memory = state.storage.get_memory(id).await?;
@@ -307,6 +331,11 @@ async fn show_memory(
     }
     .to_string();
 
+    let can_endorse = memory.created_by != state.user_id;
+    let endorsed = memory
+        .endorsements
+        .iter()
+        .any(|e| e.endorsed_by == state.user_id);
     let tmpl = MemoryDetailTemplate {
         memory,
         relations,
@@ -316,10 +345,91 @@ async fn show_memory(
         similar_memories,
         trust_pct,
         verification_class,
+        short_id: short_id(&state, id).await,
+        can_endorse,
+        endorsed,
     };
     Ok(Html(tmpl.render()?))
 }
 
+/// Read-only page for one memory, addressed by an ID prefix, with
+/// OpenGraph tags so shared links unfurl. Private memories of other users
+/// and ambiguous prefixes are a 404.
+async fn permalink(
+    Tenant(state): Tenant,
+    Path(short_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let matches = state.storage.find_by_id_prefix(&short_id, 2).await?;
+    let [entry] = matches.as_slice() else {
+        return Ok(super::not_found().await.into_response());
+    };
+    if !shabka_core::sharing::is_visible(entry.privacy, &entry.created_by, &state.user_id) {
+        return Ok(super::not_found().await.into_response());
+    }
+    let memory = state.storage.get_memory(entry.id).await?;
+
+    let tmpl = PermalinkTemplate {
+        url: absolute_url(&headers, &format!("/m/{}", &short_id)),
+        description: og_description(&memory.content),
+        markdown: memory_markdown(&memory),
+        memory,
+    };
+    Ok(Html(tmpl.render()?).into_response())
+}
+
+/// Shortest ID prefix from [`SHORT_ID_LENS`] that names only `id`, or the
+/// full ID.
+async fn short_id(state: &AppState, id: Uuid) -> String {
+    let full = id.to_string();
+    for len in SHORT_ID_LENS {
+        let prefix = &full[..len];
+        match state.storage.find_by_id_prefix(prefix, 2).await {
+            Ok(matches) if matches.len() == 1 => return prefix.to_string(),
+            Ok(_) => continue,
+            Err(_) => break,
+        }
+    }
+    full
+}
+
+/// Absolute URL for `path` as the client reached us (OpenGraph needs one).
+fn absolute_url(headers: &HeaderMap, path: &str) -> String {
+    let scheme = headers
+        .get("x-forwarded-proto")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("http");
+    let host = headers
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("localhost");
+    format!("{scheme}://{host}{path}")
+}
+
+/// First [`OG_DESCRIPTION_CHARS`] of `content` on one line.
+fn og_description(content: &str) -> String {
+    let flat = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() <= OG_DESCRIPTION_CHARS {
+        return flat;
+    }
+    let cut: String = flat.chars().take(OG_DESCRIPTION_CHARS - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// The memory as markdown, for pasting into chat or docs.
+fn memory_markdown(memory: &Memory) -> String {
+    let mut meta = format!("**Kind:** {}", memory.kind);
+    if !memory.tags.is_empty() {
+        meta.push_str(&format!(" · **Tags:** {}", memory.tags.join(", ")));
+    }
+    format!(
+        "# {}\n\n{}\n\n{}\n",
+        memory.title,
+        meta,
+        memory.content.trim()
+    )
+}
+
 async fn new_memory_form() -> Result<Html<String>, AppError> {
     let tmpl = MemoryFormTemplate {
         memory: None,
@@ -329,7 +439,7 @@ async fn new_memory_form() -> Result<Html<String>, AppError> {
 }
 
 async fn edit_memory_form(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Path(id): Path<Uuid>,
 ) -> Result<Html<String>, AppError> {
     let memory = state.storage.get_memory(id).await?;
@@ -342,7 +452,7 @@ async fn edit_memory_form(
 }
 
 async fn create_memory(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Form(input): Form<MemoryFormInput>,
 ) -> Result<Redirect, AppError> {
     let kind: MemoryKind = input.kind.parse().map_err(|e: String| anyhow::anyhow!(e))?;
@@ -360,6 +470,8 @@ async fn create_memory(
         .with_tags(tags)
         .with_importance(input.importance)
         .with_privacy(privacy);
+    state.config.labels.apply(&mut memory);
+    shabka_core::devices::stamp(&mut memory);
 
     if let Some(ref project) = input.project {
         let p = project.trim();
@@ -372,9 +484,9 @@ async fn create_memory(
     let embedding = state.embedding.embed(&embedding_text).await?;
 
     // Smart dedup check
-    let llm_ref = state.llm.as_ref();
+    let llm_ref = state.llm.as_deref();
     let decision = dedup::check_duplicate(
-        &state.storage,
+        state.storage.as_ref(),
         &embedding,
         &state.config.graph,
         None,
@@ -386,6 +498,13 @@ async fn create_memory(
 
     match decision {
         DedupDecision::Skip { existing_id, .. } => {
+            // Saving the same fact again corroborates it.
+            let _ = shabka_core::trust::record_assertion(
+                state.storage.as_ref(),
+                existing_id,
+                Assertion::new(state.user_id.clone(), "web"),
+            )
+            .await;
             return Ok(Redirect::to(&format!(
                 "/memories/{existing_id}?toast=Near-duplicate%20found%20%E2%80%94%20memory%20not%20saved&toast_type=warning"
             )));
@@ -432,7 +551,7 @@ async fn create_memory(
             merged_title,
             ..
         } => {
-            let _ = state
+            if let Ok(merged) = state
                 .storage
                 .update_memory(
                     existing_id,
@@ -442,7 +561,10 @@ async fn create_memory(
                         ..Default::default()
                     },
                 )
-                .await;
+                .await
+            {
+                state.refresh_embedding(None, &merged).await;
+            }
             state.history.log(
                 &MemoryEvent::new(existing_id, EventAction::Updated, state.user_id.clone())
                     .with_title(&merged_title),
@@ -489,7 +611,7 @@ async fn create_memory(
 }
 
 async fn update_memory(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     Path(id): Path<Uuid>,
     Form(input): Form<MemoryFormInput>,
 ) -> Result<Redirect, AppError> {
@@ -513,10 +635,20 @@ async fn update_memory(
         importance: Some(input.importance),
         status: None,
         privacy: None,
+        label: None,
+        owner: None,
         verification: None,
+        verification_expires_at: None,
+        dispute: None,
+        accessed_at: None,
+        attachments: None,
+        code: None,
+        assertions: None,
+        endorsements: None,
     };
 
     let memory = state.storage.update_memory(id, &update).await?;
+    state.refresh_embedding(Some(&old_memory), &memory).await;
 
     let changes = shabka_core::history::diff_update(&old_memory, &update);
     state.history.log(
@@ -531,16 +663,13 @@ async fn update_memory(
     )))
 }
 
-async fn delete_memory(
-    State(state): State<Arc<AppState>>,
-    Path(id): Path<Uuid>,
-) -> Result<Redirect, AppError> {
-    let title = state.storage.get_memory(id).await.ok().map(|m| m.title);
+async fn delete_memory(Tenant(state): Tenant, Path(id): Path<Uuid>) -> Result<Redirect, AppError> {
+    let memory = state.storage.get_memory(id).await.ok();
     state.storage.delete_memory(id).await?;
 
     let mut event = MemoryEvent::new(id, EventAction::Deleted, state.user_id.clone());
-    if let Some(t) = title {
-        event = event.with_title(t);
+    if let Some(memory) = &memory {
+        event = event.with_snapshot(memory);
     }
     state.history.log(&event);
 
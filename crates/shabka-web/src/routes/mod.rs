@@ -1,18 +1,21 @@
 pub mod analytics;
 pub mod api;
+pub mod assets;
+pub mod embeddings;
 pub mod graph;
 pub mod memories;
+pub mod notifications;
 pub mod search;
 pub mod timeline;
 
 use std::sync::Arc;
 
-use axum::extract::State;
 use axum::response::{Html, Json};
 use axum::routing::get;
 use axum::Router;
 use shabka_core::storage::StorageBackend;
 
+use crate::tenant::Tenant;
 use crate::AppState;
 
 pub fn router() -> Router<Arc<AppState>> {
@@ -24,12 +27,13 @@ pub fn router() -> Router<Arc<AppState>> {
         .merge(graph::routes())
         .merge(api::routes())
         .merge(analytics::routes())
+        .merge(notifications::routes())
+        .merge(embeddings::routes())
+        .merge(assets::routes())
         .fallback(not_found)
 }
 
-async fn health(
-    State(state): State<Arc<AppState>>,
-) -> (axum::http::StatusCode, Json<serde_json::Value>) {
+async fn health(Tenant(state): Tenant) -> (axum::http::StatusCode, Json<serde_json::Value>) {
     use shabka_core::model::TimelineQuery;
     let db_ok = state
         .storage
@@ -51,11 +55,16 @@ async fn health(
             "status": if db_ok { "ok" } else { "degraded" },
             "helix_db": if db_ok { "connected" } else { "unavailable" },
             "embedding_provider": state.embedding.provider_name(),
+            "storage": {
+                "backend": state.storage.backend_name(),
+                "capabilities": state.storage.capabilities(),
+                "unavailable": state.storage.capabilities().unavailable(),
+            },
         })),
     )
 }
 
-async fn not_found() -> (axum::http::StatusCode, Html<String>) {
+pub(crate) async fn not_found() -> (axum::http::StatusCode, Html<String>) {
     let body = r#"<!doctype html>
 <html><head><title>404 — Shabka</title>
 <style>body{font-family:system-ui;background:#0f0f1a;color:#e0e0e0;display:flex;justify-content:center;align-items:center;height:100vh;margin:0}
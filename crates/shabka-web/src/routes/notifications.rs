@@ -0,0 +1,71 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use askama::Template;
+use axum::response::Html;
+use axum::routing::get;
+use axum::Router;
+use shabka_core::inbox::{self, InboxItem, InboxKind, InboxOptions};
+
+use crate::error::AppError;
+use crate::tenant::Tenant;
+use crate::AppState;
+
+/// How long the page waits for consolidation candidates before showing the
+/// rest without them.
+const CONSOLIDATION_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/notifications", get(notifications_page))
+}
+
+#[derive(Template)]
+#[template(path = "notifications.html")]
+struct NotificationsTemplate {
+    groups: Vec<NotificationGroup>,
+    total: usize,
+    consolidation_skipped: bool,
+}
+
+struct NotificationGroup {
+    kind: String,
+    label: &'static str,
+    hint: &'static str,
+    items: Vec<InboxItem>,
+}
+
+async fn notifications_page(Tenant(state): Tenant) -> Result<Html<String>, AppError> {
+    let options = InboxOptions::from_config(&state.config);
+    let mut inbox = inbox::collect(state.storage.as_ref(), &state.user_id, &options).await?;
+    let previews = tokio::time::timeout(
+        CONSOLIDATION_TIMEOUT,
+        shabka_core::consolidate::preview(
+            state.storage.as_ref(),
+            &state.embedding,
+            &state.config.consolidate,
+        ),
+    )
+    .await;
+    let consolidation_skipped = previews.is_err();
+    if let Ok(previews) = previews {
+        inbox.add_consolidation(&previews);
+    }
+
+    let groups = InboxKind::ALL
+        .into_iter()
+        .map(|kind| NotificationGroup {
+            kind: kind.as_str().to_string(),
+            label: kind.label(),
+            hint: kind.hint(),
+            items: inbox.of_kind(kind).cloned().collect(),
+        })
+        .filter(|g| !g.items.is_empty())
+        .collect();
+
+    let tmpl = NotificationsTemplate {
+        groups,
+        total: inbox.total,
+        consolidation_skipped,
+    };
+    Ok(Html(tmpl.render()?))
+}
@@ -1,8 +1,6 @@
-use std::collections::HashMap;
 use std::sync::Arc;
 
 use askama::Template;
-use axum::extract::State;
 use axum::http::HeaderMap;
 use axum::response::Html;
 use axum::routing::get;
@@ -10,11 +8,12 @@ use axum::Router;
 use chrono::Utc;
 use serde::Deserialize;
 use shabka_core::model::Memory;
-use shabka_core::ranking::{self, RankCandidate, RankingWeights};
-use shabka_core::storage::StorageBackend;
+use shabka_core::questions::Question;
+use shabka_core::retrieval::{self, SearchRequest};
 use uuid::Uuid;
 
 use crate::error::AppError;
+use crate::tenant::Tenant;
 use crate::AppState;
 
 pub fn routes() -> Router<Arc<AppState>> {
@@ -42,6 +41,8 @@ struct SearchResult {
     days_inactive: i64,
     is_stale: bool,
     relation_count: usize,
+    /// Other results on this page it contradicts.
+    contradicts: usize,
 }
 
 #[derive(Deserialize)]
@@ -52,7 +53,7 @@ pub struct SearchParams {
 }
 
 async fn search(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     headers: HeaderMap,
     axum::extract::Query(params): axum::extract::Query<SearchParams>,
 ) -> Result<Html<String>, AppError> {
@@ -62,67 +63,44 @@ async fn search(
     let results = if query.is_empty() {
         vec![]
     } else {
-        let embedding = state.embedding.embed(&query).await?;
-        let mut raw = state.storage.vector_search(&embedding, limit * 3).await?;
-        shabka_core::sharing::filter_search_results(&mut raw, &state.user_id);
-
-        // Filter by project
-        if let Some(ref p) = params.project {
-            raw.retain(|(m, _)| m.project_id.as_deref() == Some(p.as_str()));
-        }
-
-        // Get relation counts for ranking
-        let memory_ids: Vec<Uuid> = raw.iter().map(|(m, _)| m.id).collect();
-        let counts = state
-            .storage
-            .count_relations(&memory_ids)
-            .await
-            .unwrap_or_default();
-        let count_map: HashMap<Uuid, usize> = counts.into_iter().collect();
-
-        // Get contradiction counts for trust scoring
-        let contradiction_counts = state
-            .storage
-            .count_contradictions(&memory_ids)
-            .await
-            .unwrap_or_default();
-        let contradiction_map: HashMap<Uuid, usize> = contradiction_counts.into_iter().collect();
-
-        // Build rank candidates with keyword scoring
-        let candidates: Vec<RankCandidate> = raw
-            .into_iter()
-            .map(|(memory, vector_score)| {
-                let kw_score = ranking::keyword_score(&query, &memory);
-                RankCandidate {
-                    relation_count: count_map.get(&memory.id).copied().unwrap_or(0),
-                    keyword_score: kw_score,
-                    contradiction_count: contradiction_map.get(&memory.id).copied().unwrap_or(0),
-                    memory,
-                    vector_score,
-                }
-            })
-            .collect();
-
-        let ranked = ranking::rank(candidates, &RankingWeights::default());
+        let request = SearchRequest::parse(&query)?
+            .map_query(|q| q.with_project(params.project.clone()))
+            .limit(limit)
+            .visible_to(state.user_id.as_str())
+            .demote_contradicted(state.config.retrieval.demote_contradicted);
+        let search = retrieval::search(state.storage.as_ref(), &state.embedding, &request).await?;
+        let relation_counts = search.relation_counts.clone();
+        let conflicts = search.conflicts.clone();
         let now = Utc::now();
         let stale_threshold = state.config.graph.stale_days as i64;
 
-        ranked
+        let top = search.top();
+        let shown: Vec<Uuid> = top.iter().map(|r| r.memory.id).collect();
+        let results: Vec<SearchResult> = top
             .into_iter()
-            .take(limit)
             .map(|r| {
                 let days_inactive = (now - r.memory.accessed_at).num_days();
                 let is_stale = days_inactive >= stale_threshold;
-                let relation_count = count_map.get(&r.memory.id).copied().unwrap_or(0);
+                let relation_count = relation_counts.get(&r.memory.id).copied().unwrap_or(0);
+                let contradicts = conflicts.get(&r.memory.id).map_or(0, |others| {
+                    others.iter().filter(|id| shown.contains(id)).count()
+                });
                 SearchResult {
                     memory: r.memory,
                     score: r.score,
                     days_inactive,
                     is_stale,
                     relation_count,
+                    contradicts,
                 }
             })
-            .collect()
+            .collect();
+        state.questions.record(
+            &Question::new(&request.query.text, "web", &state.user_id)
+                .with_project(request.query.project.clone())
+                .with_scores(results.iter().map(|r| r.score)),
+        );
+        results
     };
 
     if headers.get("hx-request").is_some() {
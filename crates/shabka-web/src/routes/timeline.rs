@@ -1,7 +1,6 @@
 use std::sync::Arc;
 
 use askama::Template;
-use axum::extract::State;
 use axum::response::Html;
 use axum::routing::get;
 use axum::Router;
@@ -11,10 +10,13 @@ use shabka_core::storage::StorageBackend;
 use uuid::Uuid;
 
 use crate::error::AppError;
+use crate::tenant::Tenant;
 use crate::AppState;
 
 pub fn routes() -> Router<Arc<AppState>> {
-    Router::new().route("/timeline", get(timeline))
+    Router::new()
+        .route("/timeline", get(timeline))
+        .route("/timeline/activity", get(activity))
 }
 
 #[derive(Template)]
@@ -25,6 +27,10 @@ struct TimelineTemplate {
     session_filter: String,
 }
 
+#[derive(Template)]
+#[template(path = "activity.html")]
+struct ActivityTemplate;
+
 #[derive(Deserialize)]
 pub struct TimelineParams {
     limit: Option<usize>,
@@ -32,7 +38,7 @@ pub struct TimelineParams {
 }
 
 async fn timeline(
-    State(state): State<Arc<AppState>>,
+    Tenant(state): Tenant,
     axum::extract::Query(params): axum::extract::Query<TimelineParams>,
 ) -> Result<Html<String>, AppError> {
     let query = TimelineQuery {
@@ -51,3 +57,9 @@ async fn timeline(
     };
     Ok(Html(tmpl.render()?))
 }
+
+/// Calendar heatmap of captures per project; the data comes from
+/// `/api/v1/activity`.
+async fn activity() -> Result<Html<String>, AppError> {
+    Ok(Html(ActivityTemplate.render()?))
+}
@@ -0,0 +1,66 @@
+//! Multi-tenant request routing (`[tenants] enabled`).
+//!
+//! [`crate::auth::require_token`] looks up the tenant of the request's API
+//! token and attaches that tenant's [`AppState`] to the request. Handlers
+//! take their state through the [`Tenant`] extractor rather than `State`,
+//! so every route reads and writes the store the token belongs to.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use shabka_core::error::Result;
+use shabka_core::tenants::Tenants;
+
+use crate::AppState;
+
+/// The per-tenant states of the server, built on first use.
+pub struct TenantStates {
+    tenants: Arc<Tenants>,
+    states: Mutex<HashMap<String, Arc<AppState>>>,
+}
+
+impl TenantStates {
+    pub fn new(tenants: Arc<Tenants>) -> Self {
+        Self {
+            tenants,
+            states: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The state serving `tenant`.
+    pub fn state(&self, tenant: &str) -> Result<Arc<AppState>> {
+        let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(state) = states.get(tenant) {
+            return Ok(state.clone());
+        }
+        let services = self.tenants.services(tenant)?;
+        let state = Arc::new(AppState::from_services(&services, None)?);
+        states.insert(tenant.to_string(), state.clone());
+        Ok(state)
+    }
+}
+
+/// A tenant's state, attached to a request by the auth middleware.
+#[derive(Clone)]
+pub struct TenantState(pub Arc<AppState>);
+
+/// The state a request is served from: its token's tenant, or the server's
+/// own store.
+pub struct Tenant(pub Arc<AppState>);
+
+impl FromRequestParts<Arc<AppState>> for Tenant {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        Ok(Self(match parts.extensions.get::<TenantState>() {
+            Some(TenantState(tenant)) => tenant.clone(),
+            None => state.clone(),
+        }))
+    }
+}